@@ -0,0 +1,138 @@
+mod stats;
+mod workload;
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use clap::Parser;
+use geth_client::{EndPoint, GrpcClient};
+use geth_engine::Options;
+use serde::Serialize;
+use tokio::time::Instant;
+use uuid::Uuid;
+
+use crate::stats::Report;
+use crate::workload::Workload;
+
+/// Runs workloads against a server and reports their throughput and latencies as JSON, e.g.
+/// `geth-bench -w append:concurrency=8 -w read:concurrency=4 -w subscribe:subscribers=16`.
+#[derive(Parser, Debug)]
+#[command(name = "geth-bench")]
+struct Args {
+    /// Host of the server to benchmark. An embedded server is started when not set.
+    #[arg(long)]
+    host: Option<String>,
+
+    /// Port of the server to benchmark.
+    #[arg(long, default_value = "2113")]
+    port: u16,
+
+    /// Data directory of the embedded server, the in-memory storage is used when not set.
+    #[arg(long)]
+    db: Option<String>,
+
+    /// How long the workloads run, in seconds.
+    #[arg(long, default_value = "10")]
+    duration_secs: u64,
+
+    /// Workload to run: `append`, `read` or `subscribe`, optionally followed by parameters like
+    /// `append:concurrency=8,batch=10,payload=256,rate=1000`. Repeat it to run workloads at the
+    /// same time.
+    #[arg(long = "workload", short = 'w', required = true)]
+    workloads: Vec<Workload>,
+
+    /// File the report is written to, stdout when not set.
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Serialize)]
+struct BenchReport {
+    target: String,
+    duration_secs: f64,
+    workloads: Vec<Report>,
+}
+
+#[tokio::main]
+async fn main() -> eyre::Result<()> {
+    let args = Args::parse();
+
+    let (embedded, endpoint, target) = match args.host.clone() {
+        Some(host) => {
+            let target = format!("{host}:{}", args.port);
+            (None, EndPoint::new(host, args.port), target)
+        }
+
+        None => {
+            let port = std::net::TcpListener::bind("127.0.0.1:0")?
+                .local_addr()?
+                .port();
+
+            let mut options = Options::new(
+                "127.0.0.1".to_string(),
+                port,
+                args.db.clone().unwrap_or_else(|| "in_mem".to_string()),
+            );
+
+            // Server logs would end up in the middle of the report.
+            options.telemetry.event_filters =
+                vec!["off".to_string(), "geth_engine=off".to_string()];
+
+            let embedded = geth_engine::run_embedded(&options).await?;
+            let endpoint = EndPoint::new(options.host.clone(), port);
+
+            (Some(embedded), endpoint, "embedded".to_string())
+        }
+    };
+
+    let client = Arc::new(GrpcClient::connect(endpoint).await?);
+    let run_id = Uuid::new_v4();
+    let streams = (0..args.workloads.len())
+        .map(|i| format!("bench-{run_id}-{i}"))
+        .collect::<Vec<_>>();
+
+    for (workload, stream) in args.workloads.iter().zip(streams.iter()) {
+        workload.prepare(client.as_ref(), stream).await?;
+    }
+
+    let started = Instant::now();
+    let deadline = started + Duration::from_secs(args.duration_secs);
+
+    let runs = args
+        .workloads
+        .iter()
+        .cloned()
+        .zip(streams)
+        .map(|(workload, stream)| tokio::spawn(workload.run(client.clone(), stream, deadline)))
+        .collect::<Vec<_>>();
+
+    let mut recorders = Vec::with_capacity(runs.len());
+    for run in runs {
+        recorders.push(run.await?);
+    }
+
+    let elapsed = started.elapsed();
+    let report = BenchReport {
+        target,
+        duration_secs: elapsed.as_secs_f64(),
+        workloads: args
+            .workloads
+            .iter()
+            .zip(recorders)
+            .map(|(workload, recorder)| recorder.report(workload.to_string(), elapsed))
+            .collect(),
+    };
+
+    let report = serde_json::to_string_pretty(&report)?;
+    match args.output.as_ref() {
+        Some(path) => std::fs::write(path, report)?,
+        None => println!("{report}"),
+    }
+
+    if let Some(embedded) = embedded {
+        embedded.shutdown().await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,116 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+/// What the workers of a workload measured. Every latency is kept so percentiles are exact, which
+/// is fine for the few millions of operations a run does.
+#[derive(Default)]
+pub struct Recorder {
+    latencies: Vec<u64>,
+    events: u64,
+    bytes: u64,
+    errors: u64,
+}
+
+impl Recorder {
+    /// Accounts for an operation that took `latency` and moved `events` events of `bytes` bytes
+    /// overall.
+    pub fn record(&mut self, latency: Duration, events: u64, bytes: u64) {
+        self.latencies.push(latency.as_micros() as u64);
+        self.events += events;
+        self.bytes += bytes;
+    }
+
+    pub fn error(&mut self) {
+        self.errors += 1;
+    }
+
+    pub fn merge(&mut self, other: Recorder) {
+        self.latencies.extend(other.latencies);
+        self.events += other.events;
+        self.bytes += other.bytes;
+        self.errors += other.errors;
+    }
+
+    pub fn report(mut self, workload: String, elapsed: Duration) -> Report {
+        self.latencies.sort_unstable();
+
+        let secs = elapsed.as_secs_f64();
+        let ops = self.latencies.len() as u64;
+
+        Report {
+            workload,
+            ops,
+            events: self.events,
+            errors: self.errors,
+            ops_per_sec: ops as f64 / secs,
+            events_per_sec: self.events as f64 / secs,
+            bytes_per_sec: self.bytes as f64 / secs,
+            latency_us: Latencies::from_sorted(&self.latencies),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct Report {
+    pub workload: String,
+    pub ops: u64,
+    pub events: u64,
+    pub errors: u64,
+    pub ops_per_sec: f64,
+    pub events_per_sec: f64,
+    pub bytes_per_sec: f64,
+    pub latency_us: Latencies,
+}
+
+#[derive(Serialize, Default)]
+pub struct Latencies {
+    pub min: u64,
+    pub mean: f64,
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub p999: u64,
+    pub max: u64,
+}
+
+impl Latencies {
+    fn from_sorted(latencies: &[u64]) -> Self {
+        let Some((&min, &max)) = latencies.first().zip(latencies.last()) else {
+            return Self::default();
+        };
+
+        let percentile = |q: f64| {
+            let rank = (q * latencies.len() as f64).ceil() as usize;
+            latencies[rank.clamp(1, latencies.len()) - 1]
+        };
+
+        Self {
+            min,
+            mean: latencies.iter().sum::<u64>() as f64 / latencies.len() as f64,
+            p50: percentile(0.5),
+            p90: percentile(0.9),
+            p99: percentile(0.99),
+            p999: percentile(0.999),
+            max,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles_use_nearest_rank() {
+        let latencies = (1..=1_000).collect::<Vec<u64>>();
+        let latencies = Latencies::from_sorted(&latencies);
+
+        assert_eq!(1, latencies.min);
+        assert_eq!(500, latencies.p50);
+        assert_eq!(900, latencies.p90);
+        assert_eq!(990, latencies.p99);
+        assert_eq!(999, latencies.p999);
+        assert_eq!(1_000, latencies.max);
+    }
+}
@@ -0,0 +1,474 @@
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use geth_client::{
+    Client, ContentType, Direction, ExpectedRevision, Propose, Revision, SubscriptionEvent,
+};
+use tokio::sync::Barrier;
+use tokio::task::JoinSet;
+use tokio::time::{Instant, Interval, MissedTickBehavior};
+use uuid::Uuid;
+
+use crate::stats::Recorder;
+
+const PRELOAD_BATCH_SIZE: u64 = 500;
+
+/// A load put on the server, written `<kind>[:<param>=<value>,...]` on the command line, e.g.
+/// `append:concurrency=8,batch=10`. Workloads of a run share the server at the same time but each
+/// gets its own streams.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Workload {
+    /// Workers appending batches of events, each worker to its own stream. `rate` caps the appends
+    /// per second of all the workers together, 0 means as fast as possible.
+    Append {
+        concurrency: usize,
+        batch: usize,
+        payload: usize,
+        rate: u64,
+    },
+
+    /// Workers reading `count` events at a time out of a stream of `events` events written
+    /// beforehand.
+    Read {
+        concurrency: usize,
+        events: u64,
+        count: u64,
+        payload: usize,
+    },
+
+    /// Subscribers to a stream `rate` events per second are appended to. Latency is the time an
+    /// event took to be delivered after it was sent.
+    Subscribe {
+        subscribers: usize,
+        rate: u64,
+        payload: usize,
+    },
+}
+
+impl Workload {
+    /// Sets up what the workload needs before the clock starts, e.g. the stream a read workload
+    /// reads.
+    pub async fn prepare<C>(&self, client: &C, stream: &str) -> eyre::Result<()>
+    where
+        C: Client,
+    {
+        let Workload::Read {
+            events, payload, ..
+        } = self
+        else {
+            return Ok(());
+        };
+
+        let mut written = 0;
+        while written < *events {
+            let count = PRELOAD_BATCH_SIZE.min(*events - written);
+
+            client
+                .append_stream(
+                    stream,
+                    ExpectedRevision::Any,
+                    proposes(count as usize, *payload),
+                )
+                .await?
+                .success()?;
+
+            written += count;
+        }
+
+        Ok(())
+    }
+
+    pub async fn run<C>(self, client: Arc<C>, stream: String, deadline: Instant) -> Recorder
+    where
+        C: Client + Send + Sync + 'static,
+    {
+        let mut workers = JoinSet::new();
+
+        match self {
+            Workload::Append {
+                concurrency,
+                batch,
+                payload,
+                rate,
+            } => {
+                for worker in 0..concurrency {
+                    let client = client.clone();
+                    let stream = format!("{stream}-{worker}");
+                    let mut pacer = pacer(rate, concurrency);
+
+                    workers.spawn(async move {
+                        let mut recorder = Recorder::default();
+
+                        while Instant::now() < deadline {
+                            if let Some(pacer) = pacer.as_mut() {
+                                pacer.tick().await;
+                            }
+
+                            let started = Instant::now();
+                            let outcome = client
+                                .append_stream(
+                                    &stream,
+                                    ExpectedRevision::Any,
+                                    proposes(batch, payload),
+                                )
+                                .await;
+
+                            match outcome.and_then(|c| c.success()) {
+                                Ok(_) => recorder.record(
+                                    started.elapsed(),
+                                    batch as u64,
+                                    (batch * payload) as u64,
+                                ),
+                                Err(_) => recorder.error(),
+                            }
+                        }
+
+                        recorder
+                    });
+                }
+            }
+
+            Workload::Read {
+                concurrency,
+                events,
+                count,
+                ..
+            } => {
+                for worker in 0..concurrency {
+                    let client = client.clone();
+                    let stream = stream.clone();
+                    // Spreads the workers over the stream.
+                    let mut position = (worker as u64 * count) % events.max(1);
+
+                    workers.spawn(async move {
+                        let mut recorder = Recorder::default();
+
+                        while Instant::now() < deadline {
+                            let started = Instant::now();
+
+                            match read(client.as_ref(), &stream, position, count).await {
+                                Ok((read, bytes)) => {
+                                    recorder.record(started.elapsed(), read, bytes)
+                                }
+                                Err(_) => recorder.error(),
+                            }
+
+                            position = (position + count) % events.max(1);
+                        }
+
+                        recorder
+                    });
+                }
+            }
+
+            Workload::Subscribe {
+                subscribers,
+                rate,
+                payload,
+            } => {
+                // The send time is written at the start of each event.
+                let payload = payload.max(8);
+                let origin = std::time::Instant::now();
+                let ready = Arc::new(Barrier::new(subscribers + 1));
+
+                for _ in 0..subscribers {
+                    let client = client.clone();
+                    let stream = stream.clone();
+                    let ready = ready.clone();
+
+                    workers.spawn(async move {
+                        let mut recorder = Recorder::default();
+                        let subscription = client.subscribe_to_stream(&stream, Revision::End).await;
+
+                        let mut subscription = match subscription {
+                            Ok(mut sub) => match sub.wait_until_confirmed().await {
+                                Ok(_) => Some(sub),
+                                Err(_) => None,
+                            },
+                            Err(_) => None,
+                        };
+
+                        ready.wait().await;
+
+                        let Some(sub) = subscription.as_mut() else {
+                            recorder.error();
+                            return recorder;
+                        };
+
+                        loop {
+                            match tokio::time::timeout_at(deadline, sub.next()).await {
+                                Err(_) | Ok(Ok(None)) => break,
+
+                                Ok(Ok(Some(SubscriptionEvent::EventAppeared(record)))) => {
+                                    let Some(sent) = record.data.get(..8) else {
+                                        recorder.error();
+                                        continue;
+                                    };
+
+                                    let sent = Duration::from_nanos(u64::from_le_bytes(
+                                        sent.try_into().expect("8 bytes"),
+                                    ));
+
+                                    recorder.record(
+                                        origin.elapsed().saturating_sub(sent),
+                                        1,
+                                        record.data.len() as u64,
+                                    );
+                                }
+
+                                Ok(Ok(Some(_))) => continue,
+
+                                Ok(Err(_)) => {
+                                    recorder.error();
+                                    break;
+                                }
+                            }
+                        }
+
+                        recorder
+                    });
+                }
+
+                let mut pacer = pacer(rate, 1);
+                workers.spawn(async move {
+                    // Deliveries are what the workload measures, the appends aren't recorded.
+                    let recorder = Recorder::default();
+
+                    ready.wait().await;
+
+                    while Instant::now() < deadline {
+                        if let Some(pacer) = pacer.as_mut() {
+                            pacer.tick().await;
+                        }
+
+                        let mut data = vec![0u8; payload];
+                        data[..8]
+                            .copy_from_slice(&(origin.elapsed().as_nanos() as u64).to_le_bytes());
+
+                        let propose = Propose {
+                            id: Uuid::new_v4(),
+                            content_type: ContentType::Binary,
+                            class: "bench".to_string(),
+                            data: data.into(),
+                            metadata: Default::default(),
+                        };
+
+                        let _ = client
+                            .append_stream(&stream, ExpectedRevision::Any, vec![propose])
+                            .await;
+                    }
+
+                    recorder
+                });
+            }
+        }
+
+        let mut recorder = Recorder::default();
+        while let Some(worker) = workers.join_next().await {
+            match worker {
+                Ok(worker) => recorder.merge(worker),
+                Err(_) => recorder.error(),
+            }
+        }
+
+        recorder
+    }
+}
+
+async fn read<C>(client: &C, stream: &str, position: u64, count: u64) -> eyre::Result<(u64, u64)>
+where
+    C: Client,
+{
+    let mut records = client
+        .read_stream(
+            stream,
+            Direction::Forward,
+            Revision::Revision(position),
+            Some(count),
+            false,
+        )
+        .await?
+        .success()?;
+
+    let mut read = 0;
+    let mut bytes = 0;
+    while let Some(record) = records.next().await? {
+        read += 1;
+        bytes += record.data.len() as u64;
+    }
+
+    Ok((read, bytes))
+}
+
+fn proposes(count: usize, payload: usize) -> Vec<Propose> {
+    (0..count)
+        .map(|_| Propose {
+            id: Uuid::new_v4(),
+            content_type: ContentType::Binary,
+            class: "bench".to_string(),
+            data: vec![0u8; payload].into(),
+            metadata: Default::default(),
+        })
+        .collect()
+}
+
+/// Paces each of `workers` workers so that together they don't go past `rate` operations per
+/// second. A worker falling behind doesn't try to catch up.
+fn pacer(rate: u64, workers: usize) -> Option<Interval> {
+    if rate == 0 {
+        return None;
+    }
+
+    let period = Duration::from_secs_f64(workers as f64 / rate as f64);
+    let mut interval = tokio::time::interval(period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    Some(interval)
+}
+
+impl Display for Workload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Workload::Append {
+                concurrency,
+                batch,
+                payload,
+                rate,
+            } => write!(
+                f,
+                "append:concurrency={concurrency},batch={batch},payload={payload},rate={rate}"
+            ),
+
+            Workload::Read {
+                concurrency,
+                events,
+                count,
+                payload,
+            } => write!(
+                f,
+                "read:concurrency={concurrency},events={events},count={count},payload={payload}"
+            ),
+
+            Workload::Subscribe {
+                subscribers,
+                rate,
+                payload,
+            } => write!(
+                f,
+                "subscribe:subscribers={subscribers},rate={rate},payload={payload}"
+            ),
+        }
+    }
+}
+
+impl FromStr for Workload {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, params) = s.split_once(':').unwrap_or((s, ""));
+        let mut params = Params::parse(params)?;
+
+        let workload = match kind {
+            "append" => Workload::Append {
+                concurrency: params.take("concurrency", 1)?,
+                batch: params.take("batch", 1)?,
+                payload: params.take("payload", 256)?,
+                rate: params.take("rate", 0)?,
+            },
+
+            "read" => Workload::Read {
+                concurrency: params.take("concurrency", 1)?,
+                events: params.take("events", 1_000)?,
+                count: params.take("count", 100)?,
+                payload: params.take("payload", 256)?,
+            },
+
+            "subscribe" => Workload::Subscribe {
+                subscribers: params.take("subscribers", 1)?,
+                rate: params.take("rate", 100)?,
+                payload: params.take("payload", 256)?,
+            },
+
+            other => return Err(format!("unknown workload '{other}'")),
+        };
+
+        if let Some(key) = params.0.keys().next() {
+            return Err(format!("unknown parameter '{key}' for workload '{kind}'"));
+        }
+
+        Ok(workload)
+    }
+}
+
+struct Params<'a>(HashMap<&'a str, &'a str>);
+
+impl<'a> Params<'a> {
+    fn parse(params: &'a str) -> Result<Self, String> {
+        let mut map = HashMap::new();
+
+        for param in params.split(',').filter(|p| !p.is_empty()) {
+            let Some((key, value)) = param.split_once('=') else {
+                return Err(format!("parameter '{param}' is not of the form key=value"));
+            };
+
+            map.insert(key.trim(), value.trim());
+        }
+
+        Ok(Self(map))
+    }
+
+    fn take<A: FromStr>(&mut self, key: &str, default: A) -> Result<A, String> {
+        match self.0.remove(key) {
+            None => Ok(default),
+            Some(value) => value
+                .parse()
+                .map_err(|_| format!("invalid value '{value}' for parameter '{key}'")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_workloads() {
+        assert_eq!(
+            Ok(Workload::Append {
+                concurrency: 8,
+                batch: 1,
+                payload: 256,
+                rate: 1_000,
+            }),
+            "append:concurrency=8,rate=1000".parse()
+        );
+
+        assert_eq!(
+            Ok(Workload::Subscribe {
+                subscribers: 1,
+                rate: 100,
+                payload: 256,
+            }),
+            "subscribe".parse()
+        );
+
+        assert!("read:count=ten".parse::<Workload>().is_err());
+        assert!("read:size=10".parse::<Workload>().is_err());
+        assert!("delete".parse::<Workload>().is_err());
+    }
+
+    #[test]
+    fn test_display_roundtrips() {
+        let workload = Workload::Read {
+            concurrency: 4,
+            events: 10_000,
+            count: 50,
+            payload: 1_024,
+        };
+
+        assert_eq!(Ok(workload.clone()), workload.to_string().parse());
+    }
+}
@@ -0,0 +1,360 @@
+use std::path::PathBuf;
+
+use geth_client::{Client, GrpcClient, TlsConfig};
+use geth_common::{
+    Direction, ExpectedRevision, LinkTo, Propose, Revision, StreamAcl, StreamMetadata,
+};
+use geth_engine::{Options, PrefixAclResolver, Tls};
+use temp_dir::TempDir;
+
+use crate::tests::{client_endpoint, random_valid_options, Toto};
+
+// Generated with openssl: a CA valid for a century, signing a server certificate for localhost
+// and 127.0.0.1 and one client certificate per principal, named by its DNS name.
+fn resource(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("src/resources/tls/acl")
+        .join(name)
+}
+
+fn read_resource(name: &str) -> eyre::Result<Vec<u8>> {
+    Ok(std::fs::read(resource(name))?)
+}
+
+fn orders_acl() -> PrefixAclResolver {
+    PrefixAclResolver::new([
+        (
+            "orders-".to_string(),
+            StreamAcl {
+                read: vec!["billing".to_string(), "auditor".to_string()],
+                write: vec!["billing".to_string()],
+                delete: vec![],
+            },
+        ),
+        (
+            "ledger-".to_string(),
+            StreamAcl {
+                read: vec!["billing".to_string()],
+                write: vec!["billing".to_string()],
+                delete: vec![],
+            },
+        ),
+        (
+            "$all".to_string(),
+            StreamAcl {
+                read: vec!["billing".to_string()],
+                write: vec![],
+                delete: vec![],
+            },
+        ),
+    ])
+    .with_admins(["admin".to_string()])
+}
+
+fn mutual_tls_options(db_dir: &TempDir) -> Options {
+    random_valid_options(db_dir)
+        .with_tls(Tls {
+            cert: Some(resource("server.pem")),
+            key: Some(resource("server.key")),
+            client_ca: Some(resource("ca.pem")),
+        })
+        .with_acl(orders_acl())
+}
+
+async fn connect_as(options: &Options, principal: &str) -> eyre::Result<GrpcClient> {
+    let tls = TlsConfig::new(read_resource("ca.pem")?).with_identity(
+        read_resource(&format!("{principal}.pem"))?,
+        read_resource(&format!("{principal}.key"))?,
+    );
+
+    GrpcClient::connect_tls(client_endpoint(options), tls).await
+}
+
+fn toto(value: i64) -> eyre::Result<Propose> {
+    Propose::from_value(&Toto {
+        key: "acl".to_string(),
+        value,
+    })
+}
+
+#[tokio::test]
+async fn principal_denied_write_but_allowed_read() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = mutual_tls_options(&db_dir);
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let billing = connect_as(&options, "billing").await?;
+    let auditor = connect_as(&options, "auditor").await?;
+
+    billing
+        .append_stream("orders-1", ExpectedRevision::Any, vec![toto(1)?])
+        .await?
+        .success()?;
+
+    let error = auditor
+        .append_stream("orders-1", ExpectedRevision::Any, vec![toto(2)?])
+        .await
+        .unwrap_err();
+
+    assert!(error.to_string().contains("denied"), "{error}");
+
+    let mut stream = auditor
+        .read_stream("orders-1", Direction::Forward, Revision::Start, None, false)
+        .await?
+        .success()?;
+
+    let record = stream.next().await?.unwrap();
+
+    assert_eq!(1, record.as_value::<Toto>()?.value);
+    assert!(stream.next().await?.is_none());
+
+    // Nobody is allowed to delete.
+    assert!(billing
+        .delete_stream("orders-1", ExpectedRevision::Any, false)
+        .await
+        .is_err());
+
+    // Streams no prefix matches are open to everyone.
+    auditor
+        .append_stream("invoices-1", ExpectedRevision::Any, vec![toto(3)?])
+        .await?
+        .success()?;
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn metadata_stream_is_guarded_by_its_stream_acl() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = mutual_tls_options(&db_dir);
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let billing = connect_as(&options, "billing").await?;
+    let auditor = connect_as(&options, "auditor").await?;
+    let admin = connect_as(&options, "admin").await?;
+
+    billing
+        .append_stream("orders-1", ExpectedRevision::Any, vec![toto(1)?])
+        .await?
+        .success()?;
+
+    let hiding = StreamMetadata {
+        max_count: Some(0),
+        ..Default::default()
+    };
+
+    // The auditor can't write to the stream, nor to its metadata stream.
+    let error = auditor
+        .append_stream("$$orders-1", ExpectedRevision::Any, vec![toto(2)?])
+        .await
+        .unwrap_err();
+
+    assert!(error.to_string().contains("denied"), "{error}");
+
+    let error = auditor
+        .set_stream_metadata("orders-1", hiding.clone())
+        .await
+        .unwrap_err();
+
+    assert!(error.to_string().contains("denied"), "{error}");
+
+    // Settings can hide the events of the stream, they also take deleting it.
+    let error = billing
+        .set_stream_metadata("orders-1", hiding)
+        .await
+        .unwrap_err();
+
+    assert!(error.to_string().contains("denied"), "{error}");
+
+    auditor
+        .read_stream(
+            "$$orders-1",
+            Direction::Forward,
+            Revision::Start,
+            None,
+            false,
+        )
+        .await?
+        .success()?;
+
+    let error = admin
+        .read_stream(
+            "$$orders-1",
+            Direction::Forward,
+            Revision::Start,
+            None,
+            false,
+        )
+        .await
+        .err()
+        .expect("a denied read");
+
+    assert!(error.to_string().contains("denied"), "{error}");
+
+    let mut stream = billing
+        .read_stream("orders-1", Direction::Forward, Revision::Start, None, false)
+        .await?
+        .success()?;
+
+    assert_eq!(1, stream.next().await?.unwrap().as_value::<Toto>()?.value);
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn acl_set_in_stream_metadata_is_enforced() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = mutual_tls_options(&db_dir);
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let billing = connect_as(&options, "billing").await?;
+    let auditor = connect_as(&options, "auditor").await?;
+
+    billing
+        .append_stream("invoices-1", ExpectedRevision::Any, vec![toto(1)?])
+        .await?
+        .success()?;
+
+    billing
+        .set_stream_metadata(
+            "invoices-1",
+            StreamMetadata {
+                acl: Some(StreamAcl {
+                    read: vec!["billing".to_string()],
+                    write: vec!["billing".to_string()],
+                    delete: vec!["billing".to_string()],
+                }),
+                ..Default::default()
+            },
+        )
+        .await?
+        .success()?;
+
+    let error = auditor
+        .append_stream("invoices-1", ExpectedRevision::Any, vec![toto(2)?])
+        .await
+        .unwrap_err();
+
+    assert!(error.to_string().contains("denied"), "{error}");
+
+    let error = auditor
+        .read_stream(
+            "invoices-1",
+            Direction::Forward,
+            Revision::Start,
+            None,
+            false,
+        )
+        .await
+        .err()
+        .expect("a denied read");
+
+    assert!(error.to_string().contains("denied"), "{error}");
+
+    billing
+        .append_stream("invoices-1", ExpectedRevision::Any, vec![toto(3)?])
+        .await?
+        .success()?;
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn listing_streams_takes_reading_all() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = mutual_tls_options(&db_dir);
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let billing = connect_as(&options, "billing").await?;
+    let auditor = connect_as(&options, "auditor").await?;
+
+    billing
+        .append_stream("ledger-1", ExpectedRevision::Any, vec![toto(1)?])
+        .await?
+        .success()?;
+
+    let error = auditor.list_streams(None, 0, 10).await.unwrap_err();
+
+    assert!(error.to_string().contains("denied"), "{error}");
+
+    let page = billing.list_streams(None, 0, 10).await?;
+
+    assert!(page.streams.contains(&"ledger-1".to_string()));
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn links_to_unreadable_streams_are_not_resolved() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = mutual_tls_options(&db_dir);
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let billing = connect_as(&options, "billing").await?;
+    let auditor = connect_as(&options, "auditor").await?;
+
+    billing
+        .append_stream("ledger-1", ExpectedRevision::Any, vec![toto(1)?])
+        .await?
+        .success()?;
+
+    let link = LinkTo {
+        target_stream: "ledger-1".to_string(),
+        target_revision: 0,
+    };
+
+    billing
+        .append_stream("orders-1", ExpectedRevision::Any, vec![link.propose()])
+        .await?
+        .success()?;
+
+    let mut stream = billing
+        .read_stream("orders-1", Direction::Forward, Revision::Start, None, true)
+        .await?
+        .success()?;
+
+    assert_eq!(1, stream.next().await?.unwrap().as_value::<Toto>()?.value);
+
+    // The auditor can't read the ledger, the link is left as is.
+    let mut stream = auditor
+        .read_stream("orders-1", Direction::Forward, Revision::Start, None, true)
+        .await?
+        .success()?;
+
+    let record = stream.next().await?.unwrap();
+
+    assert_eq!(LinkTo::CLASS, record.class);
+    assert!(record.link.is_none());
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn only_admins_maintain_the_index() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = mutual_tls_options(&db_dir);
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let billing = connect_as(&options, "billing").await?;
+    let admin = connect_as(&options, "admin").await?;
+
+    let error = billing.maintain_index(false).await.unwrap_err();
+
+    assert!(error.to_string().contains("denied"), "{error}");
+
+    let error = billing.stop_program(1).await.unwrap_err();
+
+    assert!(error.to_string().contains("denied"), "{error}");
+
+    admin.maintain_index(false).await?;
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn acl_without_mutual_tls_is_refused() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = random_valid_options(&db_dir).with_acl(orders_acl());
+    let error = geth_engine::run_embedded(&options)
+        .await
+        .err()
+        .expect("a node enforcing ACLs without mutual TLS");
+
+    assert!(error.to_string().contains("mutual TLS"), "{error}");
+
+    Ok(())
+}
@@ -0,0 +1,113 @@
+use geth_client::{Client, GrpcClient};
+use geth_common::{Direction, ExpectedRevision, LogFiltersSet, Propose, Revision};
+use temp_dir::TempDir;
+
+use crate::tests::{client_endpoint, random_valid_options, Toto};
+
+#[tokio::test]
+async fn set_log_filters() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = random_valid_options(&db_dir);
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let client = GrpcClient::connect(client_endpoint(&options)).await?;
+
+    let result = client
+        .set_log_filters(vec!["geth_engine=trace".to_string()])
+        .await?;
+
+    assert!(matches!(result, LogFiltersSet::Success));
+
+    let result = client
+        .set_log_filters(vec![
+            "geth_engine=info".to_string(),
+            "geth_engine=loud".to_string(),
+        ])
+        .await?;
+
+    assert!(matches!(result, LogFiltersSet::InvalidDirective(_)));
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn list_streams() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = random_valid_options(&db_dir);
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let client = GrpcClient::connect(client_endpoint(&options)).await?;
+
+    for stream in ["accounts-1", "accounts-2", "carts-1"] {
+        client
+            .append_stream(
+                stream,
+                ExpectedRevision::Any,
+                vec![Propose::from_value(&Toto {
+                    key: stream.to_string(),
+                    value: 1,
+                })?],
+            )
+            .await?
+            .success()?;
+    }
+
+    let page = client
+        .list_streams(Some("accounts-".to_string()), 0, 1)
+        .await?;
+
+    assert_eq!(page.streams, vec!["accounts-1".to_string()]);
+    assert_eq!(page.total, 2);
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn maintain_index() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = random_valid_options(&db_dir);
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let client = GrpcClient::connect(client_endpoint(&options)).await?;
+
+    for value in 0..3 {
+        client
+            .append_stream(
+                "maintained",
+                ExpectedRevision::Any,
+                vec![Propose::from_value(&Toto {
+                    key: "maintained".to_string(),
+                    value,
+                })?],
+            )
+            .await?
+            .success()?;
+
+        client.maintain_index(false).await?;
+    }
+
+    let result = client.maintain_index(true).await?;
+    assert!(result.ss_tables_after <= 1);
+
+    // Compacting again has nothing left to do.
+    let result = client.maintain_index(true).await?;
+    assert_eq!(0, result.flushed);
+    assert_eq!(result.ss_tables_before, result.ss_tables_after);
+
+    let mut stream = client
+        .read_stream(
+            "maintained",
+            Direction::Forward,
+            Revision::Start,
+            None,
+            false,
+        )
+        .await?
+        .success()?;
+
+    let mut values = vec![];
+    while let Some(record) = stream.next().await? {
+        values.push(record.as_value::<Toto>()?.value);
+    }
+
+    assert_eq!(vec![0, 1, 2], values);
+
+    embedded.shutdown().await
+}
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use bytes::Bytes;
 use eyre::bail;
 use fake::faker::name::en::Name;
@@ -5,9 +7,10 @@ use fake::{Fake, Faker};
 use temp_dir::TempDir;
 use uuid::Uuid;
 
-use geth_client::{Client, GrpcClient};
+use geth_client::{read_conflicting_events, Client, DecodePolicy, GrpcClient};
 use geth_common::{
-    AppendError, AppendStreamCompleted, ContentType, Direction, ExpectedRevision, Propose, Revision,
+    AppendError, AppendStreamCompleted, ContentType, Direction, ExpectedRevision, Propose,
+    Revision, RevisionConflict, SubscriptionBatching, SubscriptionEvent,
 };
 
 use crate::tests::{client_endpoint, random_valid_options, Toto};
@@ -34,6 +37,7 @@ async fn simple_append() -> eyre::Result<()> {
                 content_type,
                 class: class.clone(),
                 data: serde_json::to_vec(&expected)?.into(),
+                metadata: Default::default(),
             }],
         )
         .await?;
@@ -49,7 +53,13 @@ async fn simple_append() -> eyre::Result<()> {
     );
 
     let mut stream = client
-        .read_stream(&stream_name, Direction::Forward, Revision::Start, 1)
+        .read_stream(
+            &stream_name,
+            Direction::Forward,
+            Revision::Start,
+            Some(1),
+            true,
+        )
         .await?
         .success()?;
 
@@ -69,6 +79,122 @@ async fn simple_append() -> eyre::Result<()> {
     embedded.shutdown().await
 }
 
+#[tokio::test]
+async fn unbounded_read_returns_the_whole_stream() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = random_valid_options(&db_dir);
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let client = GrpcClient::connect(client_endpoint(&options)).await?;
+    let stream_name: String = Name().fake();
+
+    let events = (0..25)
+        .map(|_| Propose::from_value(&Faker.fake::<Toto>()))
+        .collect::<eyre::Result<Vec<_>>>()?;
+
+    client
+        .append_stream(&stream_name, ExpectedRevision::Any, events)
+        .await?
+        .success()?;
+
+    let mut stream = client
+        .read_stream(
+            &stream_name,
+            Direction::Forward,
+            Revision::Start,
+            None,
+            false,
+        )
+        .await?
+        .success()?;
+
+    let mut count = 0;
+    while stream.next().await?.is_some() {
+        count += 1;
+    }
+
+    assert_eq!(25, count);
+
+    // Same as asking for everything the way it used to be done.
+    let mut stream = client
+        .read_stream_up_to(
+            &stream_name,
+            Direction::Forward,
+            Revision::Start,
+            u64::MAX,
+            false,
+        )
+        .await?
+        .success()?;
+
+    let mut count = 0;
+    while stream.next().await?.is_some() {
+        count += 1;
+    }
+
+    assert_eq!(25, count);
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn batched_append_chains_expected_revisions() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = random_valid_options(&db_dir);
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let client = GrpcClient::connect(client_endpoint(&options)).await?;
+    let stream_name: String = Name().fake();
+
+    let events = (0..5_000)
+        .map(|_| Propose::from_value(&Faker.fake::<Toto>()))
+        .collect::<eyre::Result<Vec<_>>>()?;
+
+    let result = client
+        .append_stream_batched(&stream_name, ExpectedRevision::NoStream, events, 500)
+        .await?
+        .success()?;
+
+    assert_eq!(
+        ExpectedRevision::Revision(5_000),
+        result.next_expected_version
+    );
+    assert_eq!(5_000, result.event_ids.len());
+
+    let mut stream = client
+        .read_stream(
+            &stream_name,
+            Direction::Forward,
+            Revision::Start,
+            None,
+            false,
+        )
+        .await?
+        .success()?;
+
+    let mut revision = 0;
+    while let Some(record) = stream.next().await? {
+        assert_eq!(revision, record.revision);
+        assert_eq!(result.event_ids[revision as usize], record.id);
+        revision += 1;
+    }
+
+    assert_eq!(5_000, revision);
+
+    // Only the first batch checks the expected revision the caller gave.
+    let err = client
+        .append_stream_batched(
+            &stream_name,
+            ExpectedRevision::NoStream,
+            vec![Propose::from_value(&Faker.fake::<Toto>())?],
+            500,
+        )
+        .await?
+        .err()?;
+
+    assert!(matches!(err, AppendError::WrongExpectedRevision(_)));
+
+    embedded.shutdown().await
+}
+
 #[tokio::test]
 async fn simple_append_expecting_no_stream_on_non_existing_stream() -> eyre::Result<()> {
     let db_dir = TempDir::new()?;
@@ -91,6 +217,7 @@ async fn simple_append_expecting_no_stream_on_non_existing_stream() -> eyre::Res
                 content_type,
                 class,
                 data: serde_json::to_vec(&expected)?.into(),
+                metadata: Default::default(),
             }],
         )
         .await?;
@@ -130,6 +257,7 @@ async fn simple_append_expecting_existence_on_non_existing_stream() -> eyre::Res
                 content_type,
                 class,
                 data: serde_json::to_vec(&expected)?.into(),
+                metadata: Default::default(),
             }],
         )
         .await?
@@ -168,6 +296,7 @@ async fn simple_append_expecting_revision_on_non_existing_stream() -> eyre::Resu
                 content_type,
                 class,
                 data: serde_json::to_vec(&expected)?.into(),
+                metadata: Default::default(),
             }],
         )
         .await?;
@@ -188,6 +317,66 @@ async fn simple_append_expecting_revision_on_non_existing_stream() -> eyre::Resu
     embedded.shutdown().await
 }
 
+#[tokio::test]
+async fn concurrent_append_conflict_can_be_rebased() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = random_valid_options(&db_dir);
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let client = GrpcClient::connect(client_endpoint(&options)).await?;
+
+    let stream_name: String = Name().fake();
+    let seen: Vec<Toto> = (0..2).map(|_| Faker.fake()).collect();
+    let missed: Vec<Toto> = (0..3).map(|_| Faker.fake()).collect();
+
+    for values in [&seen, &missed] {
+        client
+            .append_stream(
+                &stream_name,
+                ExpectedRevision::Any,
+                values
+                    .iter()
+                    .map(Propose::from_value)
+                    .collect::<eyre::Result<Vec<_>>>()?,
+            )
+            .await?
+            .success()?;
+    }
+
+    // The caller only saw the first two events, the last of them being at revision 1.
+    let err = match client
+        .append_stream(
+            &stream_name,
+            ExpectedRevision::Revision(1),
+            vec![Propose::from_value(&Faker.fake::<Toto>())?],
+        )
+        .await?
+        .err()?
+    {
+        AppendError::WrongExpectedRevision(e) => e,
+        other => bail!("unexpected error: {other}"),
+    };
+
+    assert_eq!(
+        RevisionConflict::ConcurrentAppend {
+            expected: 1,
+            behind_by: 3,
+        },
+        err.classify()
+    );
+
+    let records = read_conflicting_events(&client, &stream_name, &err).await?;
+
+    assert_eq!(
+        missed,
+        records
+            .iter()
+            .map(|r| r.as_value::<Toto>())
+            .collect::<eyre::Result<Vec<_>>>()?
+    );
+
+    embedded.shutdown().await
+}
+
 #[tokio::test]
 async fn simple_append_expecting_revision_on_existing_stream() -> eyre::Result<()> {
     let db_dir = TempDir::new()?;
@@ -210,6 +399,7 @@ async fn simple_append_expecting_revision_on_existing_stream() -> eyre::Result<(
                 content_type,
                 class,
                 data: serde_json::to_vec(&expected)?.into(),
+                metadata: Default::default(),
             }],
         )
         .await?;
@@ -250,6 +440,7 @@ async fn read_whole_stream_forward() -> eyre::Result<()> {
             content_type,
             class: class.clone(),
             data: data.clone(),
+            metadata: Default::default(),
         });
     }
 
@@ -258,7 +449,13 @@ async fn read_whole_stream_forward() -> eyre::Result<()> {
         .await?;
 
     let mut stream = client
-        .read_stream(&stream_name, Direction::Forward, Revision::Start, u64::MAX)
+        .read_stream(
+            &stream_name,
+            Direction::Forward,
+            Revision::Start,
+            None,
+            true,
+        )
         .await?
         .success()?;
 
@@ -282,3 +479,496 @@ async fn read_whole_stream_forward() -> eyre::Result<()> {
 
     embedded.shutdown().await
 }
+
+#[tokio::test]
+async fn append_and_subscribe_misses_nothing() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = random_valid_options(&db_dir);
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let client = GrpcClient::connect(client_endpoint(&options)).await?;
+
+    let stream_name: String = Name().fake();
+    let command: Toto = Faker.fake();
+    let reply: Toto = Faker.fake();
+
+    let (result, mut subscription) = client
+        .append_and_subscribe(
+            &stream_name,
+            ExpectedRevision::NoStream,
+            vec![Propose::from_value(&command)?],
+        )
+        .await?
+        .success()?;
+
+    assert_eq!(ExpectedRevision::Revision(1), result.next_expected_version);
+
+    // Written right after the append, before the subscription is even polled.
+    client
+        .append_stream(
+            &stream_name,
+            ExpectedRevision::Any,
+            vec![Propose::from_value(&reply)?],
+        )
+        .await?
+        .success()?;
+
+    subscription.wait_until_confirmed().await?;
+
+    let record = loop {
+        match subscription.next().await? {
+            Some(SubscriptionEvent::EventAppeared(record)) => break record,
+            Some(_) => continue,
+            None => bail!("subscription ended early"),
+        }
+    };
+
+    assert_eq!(1, record.revision);
+    assert_eq!(reply, record.as_value::<Toto>()?);
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn batched_appends_get_their_own_results() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = random_valid_options(&db_dir);
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let client = GrpcClient::connect(client_endpoint(&options))
+        .await?
+        .with_append_batching(Duration::from_millis(50));
+
+    let stream_name: String = Name().fake();
+    let mut handles = vec![];
+
+    for _ in 0..10 {
+        let client = client.clone();
+        let stream_name = stream_name.clone();
+        let expected: Toto = Faker.fake();
+
+        handles.push(tokio::spawn(async move {
+            let result = client
+                .append_stream(
+                    &stream_name,
+                    ExpectedRevision::Any,
+                    vec![Propose::from_value(&expected)?],
+                )
+                .await?
+                .success()?;
+
+            eyre::Ok((result, expected))
+        }));
+    }
+
+    let mut appended = vec![];
+    for handle in handles {
+        appended.push(handle.await??);
+    }
+
+    let mut stream = client
+        .read_stream(
+            &stream_name,
+            Direction::Forward,
+            Revision::Start,
+            Some(10),
+            true,
+        )
+        .await?
+        .success()?;
+
+    let mut count = 0;
+    while let Some(record) = stream.next().await? {
+        let (result, expected) = appended
+            .iter()
+            .find(|(result, _)| result.event_ids == vec![record.id])
+            .expect("every record to belong to a caller");
+
+        assert_eq!(
+            ExpectedRevision::Revision(record.revision + 1),
+            result.next_expected_version
+        );
+        assert_eq!(*expected, record.as_value::<Toto>()?);
+
+        count += 1;
+    }
+
+    assert_eq!(10, count);
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn typed_read_decodes_records() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = random_valid_options(&db_dir);
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let client = GrpcClient::connect(client_endpoint(&options)).await?;
+
+    let stream_name: String = Name().fake();
+    let first: Toto = Faker.fake();
+    let second: Toto = Faker.fake();
+
+    client
+        .append_stream(
+            &stream_name,
+            ExpectedRevision::Any,
+            vec![
+                Propose::from_value(&first)?,
+                Propose {
+                    id: Uuid::new_v4(),
+                    content_type: ContentType::Binary,
+                    class: "not-a-toto".to_string(),
+                    data: Bytes::from_static(b"\x00\x01"),
+                    metadata: Default::default(),
+                },
+                Propose::from_value(&second)?,
+            ],
+        )
+        .await?
+        .success()?;
+
+    let mut stream = client
+        .read_stream(
+            &stream_name,
+            Direction::Forward,
+            Revision::Start,
+            Some(3),
+            true,
+        )
+        .await?
+        .success()?
+        .typed::<Toto>()
+        .with_policy(DecodePolicy::Skip);
+
+    let decoded = stream.next().await?.unwrap();
+    assert_eq!(first, decoded.value);
+    assert_eq!(0, decoded.revision);
+
+    let decoded = stream.next().await?.unwrap();
+    assert_eq!(second, decoded.value);
+    assert_eq!(2, decoded.revision);
+
+    assert!(stream.next().await?.is_none());
+
+    let mut stream = client
+        .read_stream(
+            &stream_name,
+            Direction::Forward,
+            Revision::Start,
+            Some(3),
+            true,
+        )
+        .await?
+        .success()?
+        .typed::<Toto>();
+
+    assert_eq!(first, stream.next().await?.unwrap().value);
+    assert!(stream.next().await.is_err());
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn appends_without_notification_are_not_pushed() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = random_valid_options(&db_dir);
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let client = GrpcClient::connect(client_endpoint(&options)).await?;
+    let importer = client.clone().without_subscriber_notifications();
+
+    let stream_name: String = Name().fake();
+    let imported: Toto = Faker.fake();
+    let live: Toto = Faker.fake();
+
+    let mut subscription = client
+        .subscribe_to_stream(&stream_name, Revision::End)
+        .await?;
+    subscription.wait_until_confirmed().await?;
+
+    importer
+        .append_stream(
+            &stream_name,
+            ExpectedRevision::Any,
+            vec![Propose::from_value(&imported)?],
+        )
+        .await?
+        .success()?;
+
+    client
+        .append_stream(
+            &stream_name,
+            ExpectedRevision::Any,
+            vec![Propose::from_value(&live)?],
+        )
+        .await?
+        .success()?;
+
+    let record = loop {
+        match subscription.next().await? {
+            Some(SubscriptionEvent::EventAppeared(record)) => break record,
+            Some(_) => continue,
+            None => bail!("subscription ended early"),
+        }
+    };
+
+    // The imported event is the first of the stream, but only reading it shows it.
+    assert_eq!(1, record.revision);
+    assert_eq!(live, record.as_value::<Toto>()?);
+
+    let mut stream = client
+        .read_stream(
+            &stream_name,
+            Direction::Forward,
+            Revision::Start,
+            Some(1),
+            false,
+        )
+        .await?
+        .success()?;
+
+    assert_eq!(imported, stream.next().await?.unwrap().as_value::<Toto>()?);
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn subscription_delivers_records_in_batches() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = random_valid_options(&db_dir);
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let client = GrpcClient::connect(client_endpoint(&options)).await?;
+
+    let stream_name: String = Name().fake();
+    let expected: Vec<Toto> = (0..10).map(|_| Faker.fake()).collect();
+
+    client
+        .append_stream(
+            &stream_name,
+            ExpectedRevision::Any,
+            expected
+                .iter()
+                .map(Propose::from_value)
+                .collect::<eyre::Result<Vec<_>>>()?,
+        )
+        .await?
+        .success()?;
+
+    let mut subscription = client
+        .subscribe_to_stream_in_batches(
+            &stream_name,
+            Revision::Start,
+            SubscriptionBatching {
+                max_size: 4,
+                max_delay: Duration::from_millis(100),
+            },
+        )
+        .await?;
+    subscription.wait_until_confirmed().await?;
+
+    let mut caught_up = Vec::new();
+    loop {
+        match tokio::time::timeout(Duration::from_secs(5), subscription.next()).await?? {
+            Some(SubscriptionEvent::EventsAppeared(records)) => {
+                assert!(!records.is_empty() && records.len() <= 4);
+                caught_up.extend(records);
+            }
+
+            Some(SubscriptionEvent::CaughtUp) => break,
+            other => bail!("unexpected event: {:?}", other),
+        }
+    }
+
+    // Records read before catching up are all delivered ahead of `CaughtUp`.
+    assert_eq!(
+        expected,
+        caught_up
+            .iter()
+            .map(|r| r.as_value::<Toto>())
+            .collect::<eyre::Result<Vec<_>>>()?
+    );
+
+    let live: Toto = Faker.fake();
+    client
+        .append_stream(
+            &stream_name,
+            ExpectedRevision::Any,
+            vec![Propose::from_value(&live)?],
+        )
+        .await?
+        .success()?;
+
+    // A lone record is delivered once the delay is over.
+    match tokio::time::timeout(Duration::from_secs(5), subscription.next()).await?? {
+        Some(SubscriptionEvent::EventsAppeared(records)) => {
+            assert_eq!(1, records.len());
+            assert_eq!(10, records[0].revision);
+            assert_eq!(live, records[0].as_value::<Toto>()?);
+        }
+
+        other => bail!("unexpected event: {:?}", other),
+    }
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn subscription_only_delivers_filtered_classes() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = random_valid_options(&db_dir);
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let client = GrpcClient::connect(client_endpoint(&options)).await?;
+    let stream_name: String = Name().fake();
+
+    let propose = |class: &str| -> eyre::Result<Propose> {
+        let mut propose = Propose::from_value(&Faker.fake::<Toto>())?;
+        propose.class = class.to_string();
+        Ok(propose)
+    };
+
+    client
+        .append_stream(
+            &stream_name,
+            ExpectedRevision::Any,
+            vec![
+                propose("created")?,
+                propose("renamed")?,
+                propose("deleted")?,
+            ],
+        )
+        .await?
+        .success()?;
+
+    let mut subscription = client
+        .subscribe_to_stream_with_classes(
+            &stream_name,
+            Revision::Start,
+            vec!["created".to_string(), "deleted".to_string()],
+        )
+        .await?;
+    subscription.wait_until_confirmed().await?;
+
+    let mut received = Vec::new();
+    loop {
+        match tokio::time::timeout(Duration::from_secs(5), subscription.next()).await?? {
+            Some(SubscriptionEvent::EventAppeared(record)) => {
+                received.push((record.revision, record.class))
+            }
+
+            Some(SubscriptionEvent::CaughtUp) => break,
+            other => bail!("unexpected event: {:?}", other),
+        }
+    }
+
+    assert_eq!(
+        vec![(0, "created".to_string()), (2, "deleted".to_string())],
+        received
+    );
+
+    client
+        .append_stream(
+            &stream_name,
+            ExpectedRevision::Any,
+            vec![propose("renamed")?, propose("created")?],
+        )
+        .await?
+        .success()?;
+
+    // Live records go through the same filter.
+    match tokio::time::timeout(Duration::from_secs(5), subscription.next()).await?? {
+        Some(SubscriptionEvent::EventAppeared(record)) => {
+            assert_eq!(4, record.revision);
+            assert_eq!("created", record.class);
+        }
+
+        other => bail!("unexpected event: {:?}", other),
+    }
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn read_all_returns_events_across_streams_in_commit_order() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = random_valid_options(&db_dir);
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let client = GrpcClient::connect(client_endpoint(&options)).await?;
+
+    let streams: Vec<String> = (0..3).map(|_| Uuid::new_v4().to_string()).collect();
+    let mut expected = Vec::new();
+
+    for round in 0..3 {
+        for stream_name in &streams {
+            let id = Uuid::new_v4();
+
+            client
+                .append_stream(
+                    stream_name,
+                    ExpectedRevision::Any,
+                    vec![Propose {
+                        id,
+                        content_type: ContentType::Binary,
+                        class: format!("round-{round}"),
+                        data: Bytes::default(),
+                        metadata: Default::default(),
+                    }],
+                )
+                .await?
+                .success()?;
+
+            expected.push(id);
+        }
+    }
+
+    // $all also carries the events of system streams.
+    let read = |from: Revision<u64>, direction: Direction| {
+        let client = client.clone();
+        let streams = streams.clone();
+
+        async move {
+            let mut stream = client.read_all(from, direction, None).await?;
+            let mut records = Vec::new();
+
+            while let Some(record) = stream.next().await? {
+                if streams.contains(&record.stream_name) {
+                    records.push(record);
+                }
+            }
+
+            Ok::<_, eyre::Report>(records)
+        }
+    };
+
+    let records = read(Revision::Start, Direction::Forward).await?;
+
+    assert_eq!(expected, records.iter().map(|r| r.id).collect::<Vec<_>>());
+
+    // Resuming from a position that isn't the one of an event starts at the next one.
+    let resumed = read(
+        Revision::Revision(records[3].position + 1),
+        Direction::Forward,
+    )
+    .await?;
+
+    assert_eq!(
+        &expected[4..],
+        resumed.iter().map(|r| r.id).collect::<Vec<_>>()
+    );
+
+    let backward = read(Revision::Revision(records[3].position), Direction::Backward).await?;
+
+    assert_eq!(
+        expected[..4].iter().rev().copied().collect::<Vec<_>>(),
+        backward.iter().map(|r| r.id).collect::<Vec<_>>()
+    );
+
+    client
+        .delete_stream(&streams[1], ExpectedRevision::Any, false)
+        .await?
+        .success()?;
+
+    let records = read(Revision::End, Direction::Backward).await?;
+
+    assert_eq!(6, records.len());
+    assert!(records.iter().all(|r| r.stream_name != streams[1]));
+
+    embedded.shutdown().await
+}
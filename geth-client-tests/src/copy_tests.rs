@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+
+use bytes::Bytes;
+use fake::faker::name::en::Name;
+use fake::Fake;
+use geth_client::{Client, GrpcClient};
+use geth_common::{
+    AppendError, ContentType, CopyError, Direction, ExpectedRevision, MapperErrorPolicy, Propose,
+    Record, Revision,
+};
+use geth_engine::MappingError;
+use temp_dir::TempDir;
+use uuid::Uuid;
+
+use crate::tests::{client_endpoint, random_valid_options};
+
+fn binary(class: &str) -> Propose {
+    Propose {
+        id: Uuid::new_v4(),
+        content_type: ContentType::Binary,
+        class: class.to_string(),
+        data: Bytes::default(),
+        metadata: Default::default(),
+    }
+}
+
+/// Splits `order` events in two, drops `noise` events and fails on `broken` ones.
+fn split_orders(record: &Record) -> Result<Vec<Propose>, MappingError> {
+    match record.class.as_str() {
+        "order" => Ok(vec![binary("order-placed"), binary("order-paid")]),
+        "noise" => Ok(vec![]),
+        "broken" => Err(MappingError::new("can't upcast")),
+        _ => Ok(vec![Propose {
+            id: record.id,
+            content_type: record.content_type,
+            class: record.class.clone(),
+            data: record.data.clone(),
+            metadata: Default::default(),
+        }]),
+    }
+}
+
+#[tokio::test]
+async fn copy_keeps_events_and_renames_classes() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = random_valid_options(&db_dir);
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let client = GrpcClient::connect(client_endpoint(&options)).await?;
+
+    let name: String = Name().fake();
+    let source = format!("{name}-source");
+    let target = format!("{name}-target");
+    let mut events = vec![];
+
+    // Enough events to span several batches.
+    for i in 0..1_200u32 {
+        events.push(Propose {
+            id: Uuid::new_v4(),
+            content_type: ContentType::Binary,
+            class: if i % 2 == 0 { "even" } else { "odd" }.to_string(),
+            data: Bytes::from(i.to_le_bytes().to_vec()),
+            metadata: Default::default(),
+        });
+    }
+
+    client
+        .append_stream(&source, ExpectedRevision::Any, events.clone())
+        .await?
+        .success()?;
+
+    let copied = client
+        .copy_stream(
+            &source,
+            &target,
+            ExpectedRevision::NoStream,
+            HashMap::from([("even".to_string(), "pair".to_string())]),
+        )
+        .await?
+        .success()?;
+
+    assert_eq!(events.len() as u64, copied.copied);
+    assert_eq!(
+        ExpectedRevision::Revision(events.len() as u64),
+        copied.next_expected_version
+    );
+
+    let mut stream = client
+        .read_stream(&target, Direction::Forward, Revision::Start, None, false)
+        .await?
+        .success()?;
+
+    let mut actuals = vec![];
+    while let Some(record) = stream.next().await? {
+        actuals.push(record);
+    }
+
+    assert_eq!(events.len(), actuals.len());
+
+    for (expected, actual) in events.iter().zip(actuals.iter()) {
+        assert_eq!(expected.id, actual.id);
+        assert_eq!(expected.data, actual.data);
+        assert_eq!(target, actual.stream_name);
+
+        if expected.class == "even" {
+            assert_eq!("pair", actual.class);
+        } else {
+            assert_eq!(expected.class, actual.class);
+        }
+    }
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn copy_fails_on_deleted_source_or_wrong_target_revision() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = random_valid_options(&db_dir);
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let client = GrpcClient::connect(client_endpoint(&options)).await?;
+
+    let name: String = Name().fake();
+    let source = format!("{name}-source");
+    let target = format!("{name}-target");
+    client
+        .append_stream(&source, ExpectedRevision::Any, vec![binary("event")])
+        .await?
+        .success()?;
+
+    client
+        .append_stream(&target, ExpectedRevision::Any, vec![binary("event")])
+        .await?
+        .success()?;
+
+    let outcome = client
+        .copy_stream(&source, &target, ExpectedRevision::NoStream, HashMap::new())
+        .await?
+        .into_result();
+
+    assert!(matches!(
+        outcome,
+        Err(CopyError::Append(AppendError::WrongExpectedRevision(_)))
+    ));
+
+    client
+        .delete_stream(&source, ExpectedRevision::Any, false)
+        .await?
+        .success()?;
+
+    let outcome = client
+        .copy_stream(&source, &target, ExpectedRevision::Any, HashMap::new())
+        .await?
+        .into_result();
+
+    assert!(matches!(outcome, Err(CopyError::SourceDeleted)));
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn copy_goes_through_registered_mapper() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = random_valid_options(&db_dir).with_mapper("split-orders", split_orders);
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let client = GrpcClient::connect(client_endpoint(&options)).await?;
+
+    let name: String = Name().fake();
+    let source = format!("{name}-source");
+
+    client
+        .append_stream(
+            &source,
+            ExpectedRevision::Any,
+            vec![
+                binary("order"),
+                binary("noise"),
+                binary("broken"),
+                binary("other"),
+            ],
+        )
+        .await?
+        .success()?;
+
+    let outcome = client
+        .copy_stream_with_mapper(
+            &source,
+            &format!("{name}-unknown"),
+            ExpectedRevision::Any,
+            "unknown",
+            MapperErrorPolicy::Stop,
+        )
+        .await?
+        .into_result();
+
+    assert!(matches!(outcome, Err(CopyError::UnknownMapper(n)) if n == "unknown"));
+
+    let outcome = client
+        .copy_stream_with_mapper(
+            &source,
+            &format!("{name}-stopped"),
+            ExpectedRevision::Any,
+            "split-orders",
+            MapperErrorPolicy::Stop,
+        )
+        .await?
+        .into_result();
+
+    assert!(matches!(
+        outcome,
+        Err(CopyError::MapperFailed { revision: 2, .. })
+    ));
+
+    let target = format!("{name}-skipped");
+    let copied = client
+        .copy_stream_with_mapper(
+            &source,
+            &target,
+            ExpectedRevision::NoStream,
+            "split-orders",
+            MapperErrorPolicy::Skip,
+        )
+        .await?
+        .success()?;
+
+    assert_eq!(3, copied.copied);
+    assert_eq!(1, copied.skipped);
+
+    let mut stream = client
+        .read_stream(&target, Direction::Forward, Revision::Start, None, false)
+        .await?
+        .success()?;
+
+    let mut classes = vec![];
+    while let Some(record) = stream.next().await? {
+        classes.push(record.class);
+    }
+
+    assert_eq!(vec!["order-placed", "order-paid", "other"], classes);
+
+    embedded.shutdown().await
+}
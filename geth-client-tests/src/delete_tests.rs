@@ -1,12 +1,38 @@
 use bytes::Bytes;
 use fake::{faker::name::en::Name, Fake};
 use geth_client::{Client, GrpcClient};
-use geth_common::{ContentType, Direction, ExpectedRevision, Propose, Revision};
+use geth_common::{ContentType, Direction, Epochs, ExpectedRevision, Propose, Revision};
 use temp_dir::TempDir;
 use uuid::Uuid;
 
 use crate::tests::{client_endpoint, random_valid_options};
 
+/// Returns the epoch and revision of every event of the stream.
+async fn read_epochs(
+    client: &GrpcClient,
+    stream_name: &str,
+    epochs: Epochs,
+) -> eyre::Result<Vec<(u64, u64)>> {
+    let mut stream = client
+        .read_stream_with_epochs(
+            stream_name,
+            Direction::Forward,
+            Revision::Start,
+            None,
+            true,
+            epochs,
+        )
+        .await?
+        .success()?;
+
+    let mut records = vec![];
+    while let Some(record) = stream.next().await? {
+        records.push((record.epoch, record.revision));
+    }
+
+    Ok(records)
+}
+
 #[tokio::test]
 async fn simple_delete() -> eyre::Result<()> {
     let db_dir = TempDir::new()?;
@@ -28,21 +54,128 @@ async fn simple_delete() -> eyre::Result<()> {
                 content_type,
                 class: class.clone(),
                 data: Bytes::default(),
+                metadata: Default::default(),
             }],
         )
         .await?
         .success()?;
 
     client
-        .delete_stream(&stream_name, ExpectedRevision::Any)
+        .delete_stream(&stream_name, ExpectedRevision::Any, false)
         .await?
         .success()?;
 
     let stream = client
-        .read_stream(&stream_name, Direction::Forward, Revision::Start, u64::MAX)
+        .read_stream(
+            &stream_name,
+            Direction::Forward,
+            Revision::Start,
+            None,
+            true,
+        )
         .await?;
 
     assert!(stream.is_stream_deleted());
 
     embedded.shutdown().await
 }
+
+#[tokio::test]
+async fn delete_dry_run() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = random_valid_options(&db_dir);
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let client = GrpcClient::connect(client_endpoint(&options)).await?;
+
+    let stream_name: String = Name().fake();
+
+    client
+        .append_stream(
+            &stream_name,
+            ExpectedRevision::Any,
+            vec![Propose {
+                id: Uuid::new_v4(),
+                content_type: ContentType::Binary,
+                class: Name().fake(),
+                data: Bytes::default(),
+                metadata: Default::default(),
+            }],
+        )
+        .await?
+        .success()?;
+
+    let current = client
+        .delete_stream(&stream_name, ExpectedRevision::StreamExists, true)
+        .await?
+        .dry_run()?;
+
+    assert_eq!(ExpectedRevision::Revision(0), current);
+
+    let stream = client
+        .read_stream(
+            &stream_name,
+            Direction::Forward,
+            Revision::Start,
+            None,
+            true,
+        )
+        .await?;
+
+    assert!(!stream.is_stream_deleted());
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn recreate_after_delete() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = random_valid_options(&db_dir);
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let client = GrpcClient::connect(client_endpoint(&options)).await?;
+
+    let stream_name: String = Name().fake();
+    let event = || Propose {
+        id: Uuid::new_v4(),
+        content_type: ContentType::Binary,
+        class: "event".to_string(),
+        data: Bytes::default(),
+        metadata: Default::default(),
+    };
+
+    client
+        .append_stream(&stream_name, ExpectedRevision::Any, vec![event()])
+        .await?
+        .success()?;
+
+    client
+        .delete_stream(&stream_name, ExpectedRevision::Any, false)
+        .await?
+        .success()?;
+
+    client
+        .append_stream(&stream_name, ExpectedRevision::NoStream, vec![event()])
+        .await?
+        .success()?;
+
+    assert_eq!(
+        vec![(1, 2)],
+        read_epochs(&client, &stream_name, Epochs::Current).await?
+    );
+    assert_eq!(
+        vec![(0, 0), (1, 2)],
+        read_epochs(&client, &stream_name, Epochs::All).await?
+    );
+
+    embedded.shutdown().await?;
+
+    // The epochs are worked out the same way once the index is rebuilt.
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let client = GrpcClient::connect(client_endpoint(&options)).await?;
+
+    assert_eq!(
+        vec![(0, 0), (1, 2)],
+        read_epochs(&client, &stream_name, Epochs::All).await?
+    );
+
+    embedded.shutdown().await
+}
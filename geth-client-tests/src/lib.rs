@@ -1,12 +1,39 @@
+#[cfg(test)]
+mod acl_tests;
+
+#[cfg(test)]
+mod admin_tests;
+
 #[cfg(test)]
 mod append_read_tests;
 
+#[cfg(test)]
+mod copy_tests;
+
 #[cfg(test)]
 mod delete_tests;
 
+#[cfg(test)]
+mod namespace_tests;
+
+#[cfg(test)]
+mod persistent_tests;
+
+#[cfg(test)]
+mod pool_tests;
+
 #[cfg(test)]
 mod program_tests;
 
+#[cfg(test)]
+mod reconnect_tests;
+
+#[cfg(test)]
+mod revision_tests;
+
+#[cfg(test)]
+mod tls_tests;
+
 #[cfg(test)]
 pub mod tests {
     use fake::{Dummy, Fake};
@@ -0,0 +1,175 @@
+use std::time::Duration;
+
+use eyre::bail;
+use geth_client::{Client, GrpcClient};
+use geth_common::{
+    Direction, ExpectedRevision, Propose, Record, Revision, SubscriptionConfirmation,
+    SubscriptionEvent,
+};
+use temp_dir::TempDir;
+
+use crate::tests::{client_endpoint, random_valid_options, Toto};
+
+async fn append(client: &GrpcClient, stream_name: &str, value: i64) -> eyre::Result<()> {
+    client
+        .append_stream(
+            stream_name,
+            ExpectedRevision::Any,
+            vec![Propose::from_value(&Toto {
+                key: stream_name.to_string(),
+                value,
+            })?],
+        )
+        .await?
+        .success()?;
+
+    Ok(())
+}
+
+async fn read_all(client: &GrpcClient, stream_name: &str) -> eyre::Result<Vec<Record>> {
+    let mut stream = client
+        .read_stream(
+            stream_name,
+            Direction::Forward,
+            Revision::Start,
+            None,
+            false,
+        )
+        .await?
+        .success()?;
+
+    let mut records = vec![];
+    while let Some(record) = stream.next().await? {
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+#[tokio::test]
+async fn namespaces_isolate_reads_and_writes() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = random_valid_options(&db_dir);
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let default = GrpcClient::connect(client_endpoint(&options)).await?;
+    let tenant_a = default.clone().with_namespace("tenant-a")?;
+    let tenant_b = default.clone().with_namespace("tenant-b")?;
+
+    append(&tenant_a, "orders", 1).await?;
+    append(&tenant_b, "orders", 2).await?;
+
+    let records = read_all(&tenant_a, "orders").await?;
+    assert_eq!(1, records.len());
+    assert_eq!("orders", records[0].stream_name);
+    assert_eq!(1, records[0].as_value::<Toto>()?.value);
+
+    let records = read_all(&tenant_b, "orders").await?;
+    assert_eq!(1, records.len());
+    assert_eq!(2, records[0].as_value::<Toto>()?.value);
+
+    // Neither tenant wrote to the default namespace.
+    assert!(read_all(&default, "orders").await?.is_empty());
+
+    // The default namespace can't reach into a tenant by spelling out its stored names.
+    assert!(read_all(&default, "$ns:tenant-a:orders").await.is_err());
+    assert!(default
+        .append_stream(
+            "$ns:tenant-a:orders",
+            ExpectedRevision::Any,
+            vec![Propose::from_value(&Toto {
+                key: "intruder".to_string(),
+                value: 3,
+            })?],
+        )
+        .await
+        .is_err());
+
+    // Node-wide operations are refused to tenants.
+    assert!(tenant_a.list_programs().await.is_err());
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn namespaces_isolate_subscriptions() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = random_valid_options(&db_dir);
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let default = GrpcClient::connect(client_endpoint(&options)).await?;
+    let tenant_a = default.clone().with_namespace("tenant-a")?;
+    let tenant_b = default.clone().with_namespace("tenant-b")?;
+
+    let mut stream_sub = tenant_a
+        .subscribe_to_stream("orders", Revision::Start)
+        .await?;
+    let mut all_sub = tenant_a
+        .subscribe_to_stream("$all", Revision::Start)
+        .await?;
+
+    match stream_sub.wait_until_confirmed().await? {
+        SubscriptionConfirmation::StreamName { stream_name, .. } => {
+            assert_eq!("orders", stream_name)
+        }
+
+        other => bail!("unexpected confirmation: {:?}", other),
+    }
+
+    all_sub.wait_until_confirmed().await?;
+
+    append(&tenant_b, "orders", 1).await?;
+    append(&default, "orders", 2).await?;
+    append(&tenant_a, "orders", 3).await?;
+
+    for sub in [&mut stream_sub, &mut all_sub] {
+        let record = loop {
+            match tokio::time::timeout(Duration::from_secs(5), sub.next()).await?? {
+                Some(SubscriptionEvent::EventAppeared(record)) => break record,
+                Some(_) => continue,
+                None => bail!("subscription ended early"),
+            }
+        };
+
+        // The writes of the other namespaces came first but were never delivered.
+        assert_eq!("orders", record.stream_name);
+        assert_eq!(3, record.as_value::<Toto>()?.value);
+    }
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn namespaces_isolate_stream_listing() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = random_valid_options(&db_dir);
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let default = GrpcClient::connect(client_endpoint(&options)).await?;
+    let tenant_a = default.clone().with_namespace("tenant-a")?;
+    let tenant_b = default.clone().with_namespace("tenant-b")?;
+
+    append(&tenant_a, "orders-1", 1).await?;
+    append(&tenant_a, "orders-2", 1).await?;
+    append(&tenant_b, "orders-3", 1).await?;
+    append(&default, "orders-4", 1).await?;
+
+    let page = tenant_a
+        .list_streams(Some("orders-".to_string()), 0, 10)
+        .await?;
+
+    assert_eq!(
+        page.streams,
+        vec!["orders-1".to_string(), "orders-2".to_string()]
+    );
+    assert_eq!(page.total, 2);
+
+    let page = tenant_b
+        .list_streams(Some("orders-".to_string()), 0, 10)
+        .await?;
+    assert_eq!(page.streams, vec!["orders-3".to_string()]);
+
+    let page = default
+        .list_streams(Some("orders-".to_string()), 0, 10)
+        .await?;
+    assert_eq!(page.streams, vec!["orders-4".to_string()]);
+
+    embedded.shutdown().await
+}
@@ -0,0 +1,110 @@
+use fake::faker::name::en::Name;
+use fake::Fake;
+use geth_client::{Client, GrpcClient, SubscriptionStreaming};
+use geth_common::{ExpectedRevision, Propose, Record, SubscriptionEvent};
+use temp_dir::TempDir;
+
+use crate::tests::{client_endpoint, random_valid_options, Toto};
+
+async fn next_record(sub: &mut SubscriptionStreaming) -> eyre::Result<Record> {
+    while let Some(event) = sub.next().await? {
+        match event {
+            SubscriptionEvent::EventAppeared(record) => return Ok(record),
+            SubscriptionEvent::Unsubscribed(reason) => eyre::bail!("unsubscribed: {reason:?}"),
+            _ => continue,
+        }
+    }
+
+    eyre::bail!("subscription ended")
+}
+
+#[tokio::test]
+async fn persistent_subscription_resumes_from_last_ack_after_restart() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = random_valid_options(&db_dir);
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let client = GrpcClient::connect(client_endpoint(&options)).await?;
+    let stream_name: String = Name().fake();
+
+    let events = (0..5)
+        .map(|value| {
+            Propose::from_value(&Toto {
+                key: "persistent".to_string(),
+                value,
+            })
+        })
+        .collect::<eyre::Result<Vec<_>>>()?;
+
+    client
+        .append_stream(&stream_name, ExpectedRevision::Any, events)
+        .await?
+        .success()?;
+
+    let mut sub = client.subscribe_persistent("group", &stream_name).await?;
+
+    for expected in 0..5 {
+        let record = next_record(&mut sub).await?;
+
+        assert_eq!(expected, record.as_value::<Toto>()?.value);
+
+        // The last two records are left unacknowledged.
+        if expected < 3 {
+            client.ack("group", &stream_name, record.revision).await?;
+        }
+    }
+
+    drop(sub);
+    embedded.shutdown().await?;
+
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let client = GrpcClient::connect(client_endpoint(&options)).await?;
+    let mut sub = client.subscribe_persistent("group", &stream_name).await?;
+
+    assert_eq!(3, next_record(&mut sub).await?.revision);
+    assert_eq!(4, next_record(&mut sub).await?.revision);
+
+    // Another group has its own checkpoint.
+    let mut other = client.subscribe_persistent("other", &stream_name).await?;
+
+    assert_eq!(0, next_record(&mut other).await?.revision);
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn unacknowledged_records_go_to_another_member() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = random_valid_options(&db_dir);
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let client = GrpcClient::connect(client_endpoint(&options)).await?;
+    let stream_name: String = Name().fake();
+
+    let mut first = client.subscribe_persistent("group", &stream_name).await?;
+    first.wait_until_confirmed().await?;
+
+    client
+        .append_stream(
+            &stream_name,
+            ExpectedRevision::Any,
+            vec![Propose::from_value(&Toto {
+                key: "persistent".to_string(),
+                value: 0,
+            })?],
+        )
+        .await?
+        .success()?;
+
+    assert_eq!(0, next_record(&mut first).await?.revision);
+
+    let mut second = client.subscribe_persistent("group", &stream_name).await?;
+    second.wait_until_confirmed().await?;
+
+    // Leaving without acknowledging hands the record to the remaining member.
+    drop(first);
+
+    assert_eq!(0, next_record(&mut second).await?.revision);
+
+    client.ack("group", &stream_name, 0).await?;
+
+    embedded.shutdown().await
+}
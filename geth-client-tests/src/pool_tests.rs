@@ -0,0 +1,97 @@
+use std::time::Duration;
+
+use eyre::bail;
+use fake::faker::name::en::Name;
+use fake::Fake;
+use geth_client::{Client, GrpcClientPool, LoadBalancing};
+use geth_common::{Direction, ExpectedRevision, Propose, Revision, SubscriptionEvent};
+use temp_dir::TempDir;
+
+use crate::tests::{client_endpoint, random_valid_options, Toto};
+
+fn toto(value: i64) -> eyre::Result<Propose> {
+    Propose::from_value(&Toto {
+        key: "pool".to_string(),
+        value,
+    })
+}
+
+#[tokio::test]
+async fn pool_spreads_operations_over_connections() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = random_valid_options(&db_dir);
+    let embedded = geth_engine::run_embedded(&options).await?;
+
+    for load_balancing in [LoadBalancing::RoundRobin, LoadBalancing::LeastLoaded] {
+        let pool = GrpcClientPool::connect(client_endpoint(&options), 4, load_balancing).await?;
+        let stream_name: String = Name().fake();
+
+        for value in 0..8 {
+            pool.append_stream(&stream_name, ExpectedRevision::Any, vec![toto(value)?])
+                .await?
+                .success()?;
+        }
+
+        // Nothing else is in flight, tied connections are picked in turn.
+        assert_eq!(vec![2, 2, 2, 2], pool.operations_per_connection());
+
+        let mut stream = pool
+            .read_stream(
+                &stream_name,
+                Direction::Forward,
+                Revision::Start,
+                None,
+                false,
+            )
+            .await?
+            .success()?;
+
+        let mut values = vec![];
+        while let Some(record) = stream.next().await? {
+            values.push(record.as_value::<Toto>()?.value);
+        }
+
+        assert_eq!((0..8).collect::<Vec<_>>(), values);
+    }
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn pool_subscriptions_stay_on_their_connection() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = random_valid_options(&db_dir);
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let pool =
+        GrpcClientPool::connect(client_endpoint(&options), 2, LoadBalancing::RoundRobin).await?;
+    let stream_name: String = Name().fake();
+
+    let mut sub = pool
+        .subscribe_to_stream(&stream_name, Revision::End)
+        .await?;
+    sub.wait_until_confirmed().await?;
+
+    // The appends go through both connections while the subscription keeps receiving from its
+    // own.
+    for value in 0..4 {
+        pool.append_stream(&stream_name, ExpectedRevision::Any, vec![toto(value)?])
+            .await?
+            .success()?;
+    }
+
+    for expected in 0..4 {
+        let record = loop {
+            match tokio::time::timeout(Duration::from_secs(5), sub.next()).await?? {
+                Some(SubscriptionEvent::EventAppeared(record)) => break record,
+                Some(_) => continue,
+                None => bail!("subscription ended early"),
+            }
+        };
+
+        assert_eq!(expected, record.as_value::<Toto>()?.value);
+    }
+
+    assert_eq!(vec![3, 2], pool.operations_per_connection());
+
+    embedded.shutdown().await
+}
@@ -1,6 +1,9 @@
 use fake::{faker::name::en::Name, Fake};
 use geth_client::{Client, GrpcClient};
-use geth_common::{ContentType, ExpectedRevision, Propose, SubscriptionConfirmation};
+use geth_common::{
+    ContentType, ExpectedRevision, Propose, RestartPolicy, SubscriptionConfirmation,
+    UnsubscribeReason,
+};
 use temp_dir::TempDir;
 use uuid::Uuid;
 
@@ -19,7 +22,12 @@ async fn start_program_subscriptions() -> eyre::Result<()> {
     let expecteds = fake::vec![Toto; 10];
 
     let mut stream = client
-        .subscribe_to_process("echo", include_str!("./resources/programs/echo.pyro"))
+        .subscribe_to_process(
+            "echo",
+            include_str!("./resources/programs/echo.pyro"),
+            false,
+            RestartPolicy::Never,
+        )
         .await?;
 
     let proposes = expecteds
@@ -29,6 +37,7 @@ async fn start_program_subscriptions() -> eyre::Result<()> {
             content_type,
             class: class.clone(),
             data: serde_json::to_vec(x).unwrap().into(),
+            metadata: Default::default(),
         })
         .collect();
 
@@ -88,7 +97,12 @@ async fn get_program_stats() -> eyre::Result<()> {
     let expecteds = fake::vec![Toto; 10];
 
     let mut stream = client
-        .subscribe_to_process("echo", include_str!("./resources/programs/echo.pyro"))
+        .subscribe_to_process(
+            "echo",
+            include_str!("./resources/programs/echo.pyro"),
+            false,
+            RestartPolicy::Never,
+        )
         .await?;
 
     let id = stream.wait_until_confirmed().await?.try_into_process_id()?;
@@ -100,6 +114,7 @@ async fn get_program_stats() -> eyre::Result<()> {
             content_type,
             class: class.clone(),
             data: serde_json::to_vec(x).unwrap().into(),
+            metadata: Default::default(),
         })
         .collect();
 
@@ -156,21 +171,28 @@ async fn stop_program_subscription() -> eyre::Result<()> {
     let client = GrpcClient::connect(client_endpoint(&options)).await?;
 
     let mut stream = client
-        .subscribe_to_process("echo", include_str!("./resources/programs/echo.pyro"))
+        .subscribe_to_process(
+            "echo",
+            include_str!("./resources/programs/echo.pyro"),
+            false,
+            RestartPolicy::Never,
+        )
         .await?;
 
     let proc_id = stream.wait_until_confirmed().await?.try_into_process_id()?;
     client.stop_program(proc_id).await?;
 
-    let mut unsubscribed = false;
+    let mut unsubscribed = None;
     while let Some(event) = stream.next().await? {
-        if let geth_common::SubscriptionEvent::Unsubscribed(_) = event {
-            unsubscribed = true;
+        if let geth_common::SubscriptionEvent::Unsubscribed(reason) = event {
+            unsubscribed = Some(reason);
             break;
         }
     }
 
-    assert!(unsubscribed);
+    // The server said why the subscription ended before closing it.
+    assert!(unsubscribed.is_some());
+    assert_ne!(Some(UnsubscribeReason::Disconnected), unsubscribed);
     assert!(stream.next().await?.is_none());
 
     embedded.shutdown().await
@@ -192,6 +214,8 @@ async fn list_program_subscription() -> eyre::Result<()> {
             .subscribe_to_process(
                 name.as_str(),
                 include_str!("./resources/programs/echo.pyro"),
+                false,
+                RestartPolicy::Never,
             )
             .await?;
 
@@ -0,0 +1,145 @@
+use std::time::Duration;
+
+use fake::faker::name::en::Name;
+use fake::Fake;
+use geth_client::{Client, GrpcClient, KeepAlive, ReconnectPolicy, SubscriptionStreaming};
+use geth_common::{ExpectedRevision, Propose, Revision, SubscriptionEvent};
+use temp_dir::TempDir;
+
+use crate::tests::{client_endpoint, random_valid_options, Toto};
+
+fn toto(value: i64) -> eyre::Result<Propose> {
+    Propose::from_value(&Toto {
+        key: "reconnect".to_string(),
+        value,
+    })
+}
+
+async fn next_value(sub: &mut SubscriptionStreaming) -> eyre::Result<i64> {
+    while let Some(event) = sub.next().await? {
+        match event {
+            SubscriptionEvent::EventAppeared(record) => {
+                return Ok(record.as_value::<Toto>()?.value)
+            }
+            SubscriptionEvent::Unsubscribed(reason) => eyre::bail!("unsubscribed: {reason:?}"),
+            _ => continue,
+        }
+    }
+
+    eyre::bail!("subscription ended")
+}
+
+#[test]
+fn reconnect_backoff_grows_up_to_max_delay() {
+    let policy = ReconnectPolicy {
+        initial_delay: Duration::from_millis(100),
+        max_delay: Duration::from_millis(500),
+        multiplier: 2.0,
+        max_attempts: 10,
+    };
+
+    let delays = (1..=5).map(|n| policy.backoff(n)).collect::<Vec<_>>();
+
+    assert_eq!(
+        vec![
+            Duration::from_millis(100),
+            Duration::from_millis(200),
+            Duration::from_millis(400),
+            Duration::from_millis(500),
+            Duration::from_millis(500),
+        ],
+        delays
+    );
+}
+
+#[tokio::test]
+async fn subscription_resumes_after_node_restart() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = random_valid_options(&db_dir);
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let client = GrpcClient::connect(client_endpoint(&options)).await?;
+    let stream_name: String = Name().fake();
+
+    let mut sub = client
+        .subscribe_to_stream(&stream_name, Revision::Start)
+        .await?;
+    sub.wait_until_confirmed().await?;
+
+    client
+        .append_stream(&stream_name, ExpectedRevision::Any, vec![toto(0)?])
+        .await?
+        .success()?;
+
+    assert_eq!(0, next_value(&mut sub).await?);
+
+    embedded.shutdown().await?;
+    let embedded = geth_engine::run_embedded(&options).await?;
+
+    // Operations aren't retried, the first ones can fail while the connection comes back.
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        match client
+            .append_stream(&stream_name, ExpectedRevision::Any, vec![toto(1)?])
+            .await
+        {
+            Ok(result) => {
+                result.success()?;
+                break;
+            }
+
+            Err(e) if attempts >= 10 => return Err(e),
+            Err(_) => tokio::time::sleep(Duration::from_millis(100)).await,
+        }
+    }
+
+    // The first event isn't delivered twice.
+    assert_eq!(1, next_value(&mut sub).await?);
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn heartbeats_keep_idle_subscription_alive() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = random_valid_options(&db_dir);
+    let embedded = geth_engine::run_embedded(&options).await?;
+    // Without resubscriptions, missing heartbeats would end the subscription with an error.
+    let client =
+        GrpcClient::connect_with_policy(client_endpoint(&options), ReconnectPolicy::never())
+            .await?
+            .with_keep_alive(KeepAlive {
+                interval: Duration::from_millis(50),
+                timeout: Duration::from_millis(300),
+            });
+    let stream_name: String = Name().fake();
+
+    let mut sub = client
+        .subscribe_to_stream(&stream_name, Revision::Start)
+        .await?;
+    sub.wait_until_confirmed().await?;
+
+    // Nothing is written for longer than the keep-alive timeout.
+    let idle = tokio::time::timeout(Duration::from_secs(1), async {
+        while let Some(event) = sub.next().await? {
+            if let SubscriptionEvent::Unsubscribed(reason) = event {
+                eyre::bail!("unsubscribed: {reason:?}");
+            }
+        }
+
+        Err::<(), _>(eyre::eyre!("subscription ended"))
+    })
+    .await;
+
+    assert!(idle.is_err(), "subscription ended while idle: {idle:?}");
+    assert!(sub.heartbeats() >= 5);
+
+    client
+        .append_stream(&stream_name, ExpectedRevision::Any, vec![toto(0)?])
+        .await?
+        .success()?;
+
+    assert_eq!(0, next_value(&mut sub).await?);
+
+    embedded.shutdown().await
+}
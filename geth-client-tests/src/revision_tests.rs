@@ -0,0 +1,81 @@
+use bytes::Bytes;
+use fake::{faker::name::en::Name, Fake};
+use geth_client::{Client, GrpcClient};
+use geth_common::{ExpectedRevision, Propose};
+use temp_dir::TempDir;
+
+use crate::tests::{client_endpoint, random_valid_options};
+
+#[tokio::test]
+async fn latest_revision_of_existing_stream() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = random_valid_options(&db_dir);
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let client = GrpcClient::connect(client_endpoint(&options)).await?;
+
+    let stream_name: String = Name().fake();
+    let class: String = Name().fake();
+
+    client
+        .append_stream(
+            &stream_name,
+            ExpectedRevision::Any,
+            vec![
+                Propose::binary(class.clone(), Bytes::default()),
+                Propose::binary(class.clone(), Bytes::default()),
+                Propose::binary(class, Bytes::default()),
+            ],
+        )
+        .await?
+        .success()?;
+
+    assert_eq!(Some(2), client.latest_revision(&stream_name).await?);
+    assert!(client.stream_exists(&stream_name).await?);
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn latest_revision_of_absent_stream() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = random_valid_options(&db_dir);
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let client = GrpcClient::connect(client_endpoint(&options)).await?;
+
+    let stream_name: String = Name().fake();
+
+    assert_eq!(None, client.latest_revision(&stream_name).await?);
+    assert!(!client.stream_exists(&stream_name).await?);
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn latest_revision_of_deleted_stream() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = random_valid_options(&db_dir);
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let client = GrpcClient::connect(client_endpoint(&options)).await?;
+
+    let stream_name: String = Name().fake();
+    let class: String = Name().fake();
+
+    client
+        .append_stream(
+            &stream_name,
+            ExpectedRevision::Any,
+            vec![Propose::binary(class, Bytes::default())],
+        )
+        .await?
+        .success()?;
+
+    client
+        .delete_stream(&stream_name, ExpectedRevision::Any, false)
+        .await?
+        .success()?;
+
+    assert_eq!(None, client.latest_revision(&stream_name).await?);
+    assert!(!client.stream_exists(&stream_name).await?);
+
+    embedded.shutdown().await
+}
@@ -0,0 +1,102 @@
+use std::path::PathBuf;
+
+use geth_client::{Client, GrpcClient, TlsConfig};
+use geth_common::{Direction, ExpectedRevision, Propose, Revision};
+use geth_engine::Tls;
+use temp_dir::TempDir;
+
+use crate::tests::{client_endpoint, random_valid_options, Toto};
+
+// Generated with openssl: a CA valid for a century, signing a server certificate for localhost
+// and 127.0.0.1 and a client certificate.
+fn resource(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("src/resources/tls")
+        .join(name)
+}
+
+fn read_resource(name: &str) -> eyre::Result<Vec<u8>> {
+    Ok(std::fs::read(resource(name))?)
+}
+
+async fn append_then_read(client: &GrpcClient) -> eyre::Result<()> {
+    let expected = Toto {
+        key: "tls".to_string(),
+        value: 42,
+    };
+
+    client
+        .append_stream(
+            "tls-stream",
+            ExpectedRevision::Any,
+            vec![Propose::from_value(&expected)?],
+        )
+        .await?
+        .success()?;
+
+    let mut stream = client
+        .read_stream(
+            "tls-stream",
+            Direction::Forward,
+            Revision::Start,
+            None,
+            false,
+        )
+        .await?
+        .success()?;
+
+    let record = stream.next().await?.unwrap();
+
+    assert_eq!(expected, record.as_value::<Toto>()?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn tls_append_read_round_trip() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = random_valid_options(&db_dir).with_tls(Tls {
+        cert: Some(resource("server.pem")),
+        key: Some(resource("server.key")),
+        client_ca: None,
+    });
+
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let client = GrpcClient::connect_tls(
+        client_endpoint(&options),
+        TlsConfig::new(read_resource("ca.pem")?),
+    )
+    .await?;
+
+    append_then_read(&client).await?;
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn mutual_tls_requires_a_client_certificate() -> eyre::Result<()> {
+    let db_dir = TempDir::new()?;
+    let options = random_valid_options(&db_dir).with_tls(Tls {
+        cert: Some(resource("server.pem")),
+        key: Some(resource("server.key")),
+        client_ca: Some(resource("ca.pem")),
+    });
+
+    let embedded = geth_engine::run_embedded(&options).await?;
+    let tls = TlsConfig::new(read_resource("ca.pem")?);
+
+    // The handshake fails, either when connecting or on the first request.
+    if let Ok(anonymous) = GrpcClient::connect_tls(client_endpoint(&options), tls.clone()).await {
+        assert!(append_then_read(&anonymous).await.is_err());
+    }
+
+    let client = GrpcClient::connect_tls(
+        client_endpoint(&options),
+        tls.with_identity(read_resource("client.pem")?, read_resource("client.key")?),
+    )
+    .await?;
+
+    append_then_read(&client).await?;
+
+    embedded.shutdown().await
+}
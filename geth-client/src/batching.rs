@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use futures_util::future::join_all;
+use geth_common::{AppendStreamCompleted, ExpectedRevision, Propose, WriteResult};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::sync::oneshot;
+
+use crate::{Client, GrpcClient};
+
+type AppendResp = oneshot::Sender<eyre::Result<AppendStreamCompleted>>;
+
+struct PendingAppend {
+    stream_name: String,
+    expected_revision: ExpectedRevision,
+    proposes: Vec<Propose>,
+    resp: AppendResp,
+}
+
+/// Appends to the same stream sent to the server as a single call.
+struct Batch {
+    expected_revision: ExpectedRevision,
+    proposes: Vec<Propose>,
+    /// Number of events each caller proposed, in the order they joined the batch.
+    callers: Vec<(usize, AppendResp)>,
+}
+
+impl Batch {
+    fn new(pending: PendingAppend) -> Self {
+        Self {
+            expected_revision: pending.expected_revision,
+            callers: vec![(pending.proposes.len(), pending.resp)],
+            proposes: pending.proposes,
+        }
+    }
+
+    fn join(&mut self, pending: PendingAppend) {
+        self.callers.push((pending.proposes.len(), pending.resp));
+        self.proposes.extend(pending.proposes);
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct AppendBatcher {
+    inner: UnboundedSender<PendingAppend>,
+    window: Duration,
+}
+
+impl AppendBatcher {
+    pub(crate) fn start(client: GrpcClient, window: Duration) -> Self {
+        let (inner, queue) = unbounded_channel();
+
+        tokio::spawn(run(client, window, queue));
+
+        Self { inner, window }
+    }
+
+    pub(crate) fn window(&self) -> Duration {
+        self.window
+    }
+
+    pub(crate) async fn append(
+        &self,
+        stream_name: &str,
+        expected_revision: ExpectedRevision,
+        proposes: Vec<Propose>,
+    ) -> eyre::Result<AppendStreamCompleted> {
+        let (resp, receiver) = oneshot::channel();
+
+        if self
+            .inner
+            .send(PendingAppend {
+                stream_name: stream_name.to_string(),
+                expected_revision,
+                proposes,
+                resp,
+            })
+            .is_err()
+        {
+            eyre::bail!("append batcher has shutdown");
+        }
+
+        match receiver.await {
+            Ok(outcome) => outcome,
+            Err(_) => eyre::bail!("append batcher has shutdown"),
+        }
+    }
+}
+
+async fn run(client: GrpcClient, window: Duration, mut queue: UnboundedReceiver<PendingAppend>) {
+    while let Some(pending) = queue.recv().await {
+        let mut streams = HashMap::<String, Vec<Batch>>::new();
+        let deadline = tokio::time::Instant::now() + window;
+
+        push(&mut streams, pending);
+        while let Ok(Some(pending)) = tokio::time::timeout_at(deadline, queue.recv()).await {
+            push(&mut streams, pending);
+        }
+
+        // Batches of a stream are flushed one after the other, and before the next window
+        // starts, so appends to a stream reach the server in the order they were issued.
+        join_all(streams.into_iter().map(|(stream_name, batches)| {
+            let client = &client;
+
+            async move {
+                for batch in batches {
+                    flush(client, &stream_name, batch).await;
+                }
+            }
+        }))
+        .await;
+    }
+}
+
+fn push(streams: &mut HashMap<String, Vec<Batch>>, pending: PendingAppend) {
+    let batches = streams.entry(pending.stream_name.clone()).or_default();
+
+    match batches.last_mut() {
+        // An append expecting a specific revision can't be checked once merged with the ones
+        // before it, so it starts a new batch instead.
+        Some(batch) if matches!(pending.expected_revision, ExpectedRevision::Any) => {
+            batch.join(pending)
+        }
+
+        _ => batches.push(Batch::new(pending)),
+    }
+}
+
+async fn flush(client: &GrpcClient, stream_name: &str, batch: Batch) {
+    let total = batch.proposes.len();
+    let outcome = client
+        .append_stream(stream_name, batch.expected_revision, batch.proposes)
+        .await;
+
+    match outcome {
+        Err(e) => {
+            let error = e.to_string();
+
+            for (_, resp) in batch.callers {
+                let _ = resp.send(Err(eyre::eyre!("{}", error)));
+            }
+        }
+
+        Ok(AppendStreamCompleted::Error(e)) => {
+            for (_, resp) in batch.callers {
+                let _ = resp.send(Ok(AppendStreamCompleted::Error(e.clone())));
+            }
+        }
+
+        Ok(AppendStreamCompleted::Success(result)) => {
+            let mut remaining = total as u64;
+            let mut event_ids = result.event_ids.into_iter();
+
+            for (count, resp) in batch.callers {
+                remaining -= count as u64;
+
+                let next_expected_version = match result.next_expected_version {
+                    ExpectedRevision::Revision(r) => {
+                        ExpectedRevision::Revision(r.saturating_sub(remaining))
+                    }
+                    other => other,
+                };
+
+                let _ = resp.send(Ok(AppendStreamCompleted::Success(WriteResult {
+                    next_expected_version,
+                    position: result.position,
+                    next_logical_position: result.next_logical_position,
+                    event_ids: event_ids.by_ref().take(count).collect(),
+                    created: result.created,
+                })));
+            }
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use geth_common::{Direction, Record, Revision, RevisionConflict, WrongExpectedRevisionError};
+
+use crate::Client;
+
+/// Reads the events written to `stream_id` since the revision a failed write expected, so the
+/// caller can rebase on them before trying again. Empty when rebasing doesn't apply to the
+/// conflict, see [`RevisionConflict`].
+pub async fn read_conflicting_events<C>(
+    client: &C,
+    stream_id: &str,
+    error: &WrongExpectedRevisionError,
+) -> eyre::Result<Vec<Record>>
+where
+    C: Client + ?Sized,
+{
+    let (start, count) = match error.classify() {
+        RevisionConflict::ConcurrentAppend {
+            expected,
+            behind_by,
+        } => (Revision::Revision(expected + 1), behind_by),
+        RevisionConflict::StreamAlreadyExists { current } => (Revision::Start, current + 1),
+        _ => return Ok(vec![]),
+    };
+
+    let mut stream = client
+        .read_stream(stream_id, Direction::Forward, start, Some(count), false)
+        .await?
+        .success()?;
+
+    let mut records = Vec::new();
+    while let Some(record) = stream.next().await? {
+        records.push(record);
+    }
+
+    Ok(records)
+}
@@ -1,25 +1,40 @@
+use std::collections::HashMap;
 use std::time::Duration;
 
+use futures_util::TryStreamExt;
 use geth_grpc::generated::protocol::protocol_client::ProtocolClient;
-use geth_grpc::protocol::ProgramStatsRequest;
+use geth_grpc::protocol::{
+    append_and_subscribe_response, GetStreamMetadataRequest, GetStreamRevisionRequest,
+    ProgramStatsRequest,
+};
+use tonic::metadata::AsciiMetadataValue;
 use tonic::service::interceptor::InterceptedService;
 use tonic::service::Interceptor;
-use tonic::transport::{Channel, Uri};
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Identity, Uri};
 use tonic::{Code, Request};
 
 use geth_common::{
-    AppendStream, AppendStreamCompleted, DeleteStream, DeleteStreamCompleted, Direction, EndPoint,
-    ExpectedRevision, GetProgramError, KillProgram, ListPrograms, ProgramObtained, ProgramStats,
-    ProgramSummary, Propose, ReadError, ReadStream, ReadStreamCompleted, Revision, Subscribe,
-    SubscribeToProgram, SubscribeToStream,
+    Ack, AppendAndSubscribeCompleted, AppendStream, AppendStreamCompleted, CopyStream,
+    CopyStreamCompleted, DeleteStream, DeleteStreamCompleted, Direction, EndPoint, Epochs,
+    ExpectedRevision, GetProgramError, IndexMaintained, KillProgram, ListPrograms, ListStreams,
+    LogFiltersSet, MaintainIndex, MapperErrorPolicy, ProgramObtained, ProgramStats, ProgramSummary,
+    Propose, ReadAll, ReadError, ReadProjection, ReadStream, ReadStreamCompleted, RestartPolicy,
+    Revision, SetLogFilters, SetStreamMetadata, SetStreamMetadataCompleted, StreamMetadata,
+    StreamPage, Subscribe, SubscribeToGroup, SubscribeToProgram, SubscribeToStream,
+    SubscriptionBatching, TruncateStream, TruncateStreamCompleted,
 };
 
-use crate::{Client, ReadStreaming, SubscriptionStreaming};
+use crate::batching::AppendBatcher;
+use crate::{
+    Client, KeepAlive, ProjectionStreaming, ReadStreaming, ReconnectPolicy, SubscriptionStreaming,
+};
 
-#[derive(Debug, Clone, Copy)]
-struct CorrelationInjectionInterceptor;
+#[derive(Debug, Clone, Default)]
+struct MetadataInjectionInterceptor {
+    namespace: Option<AsciiMetadataValue>,
+}
 
-impl Interceptor for CorrelationInjectionInterceptor {
+impl Interceptor for MetadataInjectionInterceptor {
     fn call(
         &mut self,
         mut request: tonic::Request<()>,
@@ -29,18 +44,78 @@ impl Interceptor for CorrelationInjectionInterceptor {
             uuid::Uuid::new_v4().to_string().parse().unwrap(),
         );
 
+        if let Some(namespace) = &self.namespace {
+            request
+                .metadata_mut()
+                .insert("namespace", namespace.clone());
+        }
+
         Ok(request)
     }
 }
 
+/// How [`GrpcClient::connect_tls`] authenticates the node, and itself to nodes requiring mutual
+/// TLS.
+#[derive(Clone, Debug)]
+pub struct TlsConfig {
+    inner: ClientTlsConfig,
+}
+
+impl TlsConfig {
+    /// Trusts the nodes presenting a certificate signed by `ca_cert`, PEM encoded.
+    pub fn new(ca_cert: impl AsRef<[u8]>) -> Self {
+        Self {
+            inner: ClientTlsConfig::new().ca_certificate(Certificate::from_pem(ca_cert)),
+        }
+    }
+
+    /// Presents `cert` with its private `key`, both PEM encoded, to nodes requiring a client
+    /// certificate. Nodes enforcing stream ACLs issue the requests on behalf of the first DNS
+    /// name of `cert`.
+    pub fn with_identity(self, cert: impl AsRef<[u8]>, key: impl AsRef<[u8]>) -> Self {
+        Self {
+            inner: self.inner.identity(Identity::from_pem(cert, key)),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct GrpcClient {
-    inner: ProtocolClient<InterceptedService<Channel, CorrelationInjectionInterceptor>>,
+    channel: Channel,
+    inner: ProtocolClient<InterceptedService<Channel, MetadataInjectionInterceptor>>,
+    batcher: Option<AppendBatcher>,
+    notify_subscribers: bool,
+    reconnect: ReconnectPolicy,
+    keep_alive: Option<KeepAlive>,
 }
 
 impl GrpcClient {
     pub async fn connect(endpoint: EndPoint) -> eyre::Result<Self> {
-        let max_attempts = 10;
+        Self::connect_with_policy(endpoint, ReconnectPolicy::default()).await
+    }
+
+    /// Connects following `policy`, which stream subscriptions also follow to resume where they
+    /// left off when their connection is lost. Other operations aren't retried, they fail once
+    /// the connection is lost and the next ones go through once the node is reachable again.
+    pub async fn connect_with_policy(
+        endpoint: EndPoint,
+        policy: ReconnectPolicy,
+    ) -> eyre::Result<Self> {
+        Self::connect_inner(endpoint, policy, None).await
+    }
+
+    /// Same as [`GrpcClient::connect`] but over TLS, for nodes started with a certificate.
+    pub async fn connect_tls(endpoint: EndPoint, tls: TlsConfig) -> eyre::Result<Self> {
+        Self::connect_inner(endpoint, ReconnectPolicy::default(), Some(tls)).await
+    }
+
+    async fn connect_inner(
+        endpoint: EndPoint,
+        policy: ReconnectPolicy,
+        tls: Option<TlsConfig>,
+    ) -> eyre::Result<Self> {
+        let scheme = if tls.is_some() { "https" } else { "http" };
+        let max_attempts = policy.max_attempts.max(1);
         let mut attempt = 1;
 
         while attempt <= max_attempts {
@@ -51,26 +126,172 @@ impl GrpcClient {
                 "connecting to node"
             );
 
-            let uri = format!("http://{}:{}", endpoint.host, endpoint.port).parse::<Uri>()?;
-            match Channel::builder(uri.clone()).connect().await {
+            let uri = format!("{}://{}:{}", scheme, endpoint.host, endpoint.port).parse::<Uri>()?;
+            let mut builder = Channel::builder(uri);
+
+            if let Some(tls) = &tls {
+                builder = builder.tls_config(tls.inner.clone())?;
+            }
+
+            match builder.connect().await {
                 Err(e) => {
                     tracing::warn!(attempt = attempt, max_attempts = max_attempts, error = %e, "failed to connect to node");
+                    tokio::time::sleep(policy.backoff(attempt)).await;
                     attempt += 1;
-
-                    tokio::time::sleep(Duration::from_millis(500)).await;
                 }
 
                 Ok(channel) => {
                     tracing::debug!(attempt = attempt, max_attempts = max_attempts, endpoint = %endpoint, "connected to node");
-                    let inner =
-                        ProtocolClient::with_interceptor(channel, CorrelationInjectionInterceptor);
-                    return Ok(Self { inner });
+                    let inner = ProtocolClient::with_interceptor(
+                        channel.clone(),
+                        MetadataInjectionInterceptor::default(),
+                    );
+                    return Ok(Self {
+                        channel,
+                        inner,
+                        batcher: None,
+                        notify_subscribers: true,
+                        reconnect: policy,
+                        keep_alive: Some(KeepAlive::default()),
+                    });
                 }
             }
         }
 
         eyre::bail!("cannot connect to {}", endpoint)
     }
+
+    /// Coalesces the appends to a same stream issued within `window` of each other into a single
+    /// server call, each caller still getting its own result. Disabled by default as it delays
+    /// every append by up to `window`.
+    ///
+    /// Only appends expecting [`ExpectedRevision::Any`] join a pending batch, the others start a
+    /// new one, so appends to a stream reach the server in the order they were issued. Callers
+    /// sharing a batch are given the position of the whole batch.
+    pub fn with_append_batching(self, window: Duration) -> Self {
+        let unbatched = Self {
+            batcher: None,
+            ..self.clone()
+        };
+
+        Self {
+            batcher: Some(AppendBatcher::start(unbatched, window)),
+            ..self
+        }
+    }
+
+    /// Scopes every request to `namespace`, streams of different namespaces being distinct even
+    /// when they share a name. The server refuses program and log filter requests issued from a
+    /// namespace. An empty `namespace` is the default one.
+    pub fn with_namespace(self, namespace: &str) -> eyre::Result<Self> {
+        let interceptor = MetadataInjectionInterceptor {
+            namespace: Some(namespace.parse()?),
+        };
+
+        Ok(Self {
+            inner: ProtocolClient::with_interceptor(self.channel.clone(), interceptor),
+            ..self
+        }
+        .restart_batcher())
+    }
+
+    /// Appends issued through the returned client are committed without being pushed to
+    /// subscriptions, which only see those events by reading the stream again. Meant for bulk
+    /// imports, where notifying every event is wasted work. Doesn't apply to
+    /// [`Client::append_and_subscribe`].
+    pub fn without_subscriber_notifications(self) -> Self {
+        Self {
+            notify_subscribers: false,
+            ..self
+        }
+        .restart_batcher()
+    }
+
+    /// Stream subscriptions started from the returned client follow `keep_alive`, see
+    /// [`KeepAlive`]. Subscriptions use [`KeepAlive::default`] unless told otherwise.
+    pub fn with_keep_alive(self, keep_alive: KeepAlive) -> Self {
+        Self {
+            keep_alive: Some(keep_alive),
+            ..self
+        }
+    }
+
+    /// Stream subscriptions started from the returned client get no heartbeat and wait on the
+    /// server for as long as it takes.
+    pub fn without_keep_alive(self) -> Self {
+        Self {
+            keep_alive: None,
+            ..self
+        }
+    }
+
+    /// [`Client::read_stream`] taking the count the way it used to, `u64::MAX` reading until the
+    /// end of the stream.
+    pub async fn read_stream_up_to(
+        &self,
+        stream_id: &str,
+        direction: Direction,
+        revision: Revision<u64>,
+        max_count: u64,
+        resolve_links: bool,
+    ) -> eyre::Result<ReadStreamCompleted<ReadStreaming>> {
+        let max_count = (max_count != u64::MAX).then_some(max_count);
+
+        Client::read_stream(
+            self,
+            stream_id,
+            direction,
+            revision,
+            max_count,
+            resolve_links,
+        )
+        .await
+    }
+
+    /// The batcher appends through the client it was started with, so it's started again from
+    /// the updated one.
+    fn restart_batcher(self) -> Self {
+        match self.batcher.clone() {
+            None => self,
+            Some(batcher) => Self {
+                batcher: None,
+                ..self
+            }
+            .with_append_batching(batcher.window()),
+        }
+    }
+
+    async fn copy(&self, params: CopyStream) -> eyre::Result<CopyStreamCompleted> {
+        let result = self
+            .inner
+            .clone()
+            .copy_stream(Request::new(params.into()))
+            .await?;
+
+        Ok(result.into_inner().try_into()?)
+    }
+
+    pub(crate) async fn subscribe_stream(
+        &self,
+        mut params: SubscribeToStream,
+    ) -> eyre::Result<SubscriptionStreaming> {
+        params.heartbeat_interval = self.keep_alive.map(|k| k.interval);
+
+        let result = self
+            .inner
+            .clone()
+            .subscribe(Request::new(Subscribe::ToStream(params.clone()).into()))
+            .await?;
+
+        let streaming = SubscriptionStreaming::from_grpc(result.into_inner())
+            .with_idle_timeout(self.keep_alive.map(|k| k.timeout));
+
+        if self.reconnect.max_attempts == 0 {
+            return Ok(streaming);
+        }
+
+        Ok(streaming.resuming(self.clone(), params, self.reconnect))
+    }
 }
 
 #[async_trait::async_trait]
@@ -81,6 +302,10 @@ impl Client for GrpcClient {
         expected_revision: ExpectedRevision,
         proposes: Vec<Propose>,
     ) -> eyre::Result<AppendStreamCompleted> {
+        if let Some(batcher) = &self.batcher {
+            return batcher.append(stream_id, expected_revision, proposes).await;
+        }
+
         let result = self
             .inner
             .clone()
@@ -89,6 +314,7 @@ impl Client for GrpcClient {
                     stream_name: stream_id.to_string(),
                     expected_revision,
                     events: proposes,
+                    notify_subscribers: self.notify_subscribers,
                 }
                 .into(),
             ))
@@ -97,12 +323,69 @@ impl Client for GrpcClient {
         Ok(result.into_inner().try_into()?)
     }
 
+    async fn append_and_subscribe(
+        &self,
+        stream_id: &str,
+        expected_revision: ExpectedRevision,
+        proposes: Vec<Propose>,
+    ) -> eyre::Result<AppendAndSubscribeCompleted<SubscriptionStreaming>> {
+        let mut streaming = self
+            .inner
+            .clone()
+            .append_and_subscribe(Request::new(
+                AppendStream {
+                    stream_name: stream_id.to_string(),
+                    expected_revision,
+                    events: proposes,
+                    notify_subscribers: true,
+                }
+                .into(),
+            ))
+            .await?
+            .into_inner();
+
+        let Some(append_and_subscribe_response::Response::Appended(appended)) =
+            streaming.try_next().await?.and_then(|resp| resp.response)
+        else {
+            eyre::bail!("append outcome is missing");
+        };
+
+        match appended.try_into()? {
+            AppendStreamCompleted::Error(e) => Ok(AppendAndSubscribeCompleted::Error(e)),
+            AppendStreamCompleted::Success(result) => Ok(AppendAndSubscribeCompleted::Success(
+                result,
+                SubscriptionStreaming::from_grpc_appended(streaming),
+            )),
+        }
+    }
+
     async fn read_stream(
         &self,
         stream_id: &str,
         direction: Direction,
         revision: Revision<u64>,
-        max_count: u64,
+        max_count: Option<u64>,
+        resolve_links: bool,
+    ) -> eyre::Result<ReadStreamCompleted<ReadStreaming>> {
+        self.read_stream_with_epochs(
+            stream_id,
+            direction,
+            revision,
+            max_count,
+            resolve_links,
+            Epochs::Current,
+        )
+        .await
+    }
+
+    async fn read_stream_with_epochs(
+        &self,
+        stream_id: &str,
+        direction: Direction,
+        revision: Revision<u64>,
+        max_count: Option<u64>,
+        resolve_links: bool,
+        epochs: Epochs,
     ) -> eyre::Result<ReadStreamCompleted<ReadStreaming>> {
         let result = self
             .inner
@@ -113,6 +396,8 @@ impl Client for GrpcClient {
                     direction,
                     revision,
                     max_count,
+                    resolve_links,
+                    epochs,
                 }
                 .into(),
             ))
@@ -123,36 +408,129 @@ impl Client for GrpcClient {
                 ReadError::StreamDeleted => Ok(ReadStreamCompleted::StreamDeleted),
             },
 
-            Ok(resp) => Ok(ReadStreamCompleted::Success(ReadStreaming::Grpc(
-                resp.into_inner(),
-            ))),
+            Ok(resp) => Ok(ReadStreamCompleted::Success(ReadStreaming::Grpc {
+                inner: resp.into_inner(),
+                tail_revision: None,
+            })),
         }
     }
 
-    async fn subscribe_to_stream(
+    async fn read_all(
+        &self,
+        from: Revision<u64>,
+        direction: Direction,
+        max_count: Option<u64>,
+    ) -> eyre::Result<ReadStreaming> {
+        let resp = self
+            .inner
+            .clone()
+            .read_all(Request::new(
+                ReadAll {
+                    position: from,
+                    direction,
+                    max_count,
+                }
+                .into(),
+            ))
+            .await?;
+
+        Ok(ReadStreaming::Grpc {
+            inner: resp.into_inner(),
+            tail_revision: None,
+        })
+    }
+
+    async fn read_projection(
         &self,
         stream_id: &str,
-        start: Revision<u64>,
-    ) -> eyre::Result<SubscriptionStreaming> {
+        direction: Direction,
+        revision: Revision<u64>,
+        max_count: u64,
+        fields: Vec<String>,
+    ) -> eyre::Result<ReadStreamCompleted<ProjectionStreaming>> {
         let result = self
             .inner
             .clone()
-            .subscribe(Request::new(
-                Subscribe::ToStream(SubscribeToStream {
+            .read_projection(Request::new(
+                ReadProjection {
                     stream_name: stream_id.to_string(),
-                    start,
-                })
+                    direction,
+                    revision,
+                    max_count,
+                    fields,
+                }
                 .into(),
             ))
-            .await?;
+            .await;
 
-        Ok(SubscriptionStreaming::from_grpc(result.into_inner()))
+        match result {
+            Err(s) => match parse_read_error(s)? {
+                ReadError::StreamDeleted => Ok(ReadStreamCompleted::StreamDeleted),
+            },
+
+            Ok(resp) => Ok(ReadStreamCompleted::Success(ProjectionStreaming::Grpc(
+                resp.into_inner(),
+            ))),
+        }
+    }
+
+    async fn subscribe_to_stream(
+        &self,
+        stream_id: &str,
+        start: Revision<u64>,
+    ) -> eyre::Result<SubscriptionStreaming> {
+        self.subscribe_stream(SubscribeToStream {
+            stream_name: stream_id.to_string(),
+            start,
+            batching: None,
+            class_filter: Vec::new(),
+            heartbeat_interval: None,
+        })
+        .await
+    }
+
+    async fn subscribe_to_stream_in_batches(
+        &self,
+        stream_id: &str,
+        start: Revision<u64>,
+        batching: SubscriptionBatching,
+    ) -> eyre::Result<SubscriptionStreaming> {
+        if batching.max_size == 0 {
+            eyre::bail!("batches must hold at least one record");
+        }
+
+        self.subscribe_stream(SubscribeToStream {
+            stream_name: stream_id.to_string(),
+            start,
+            batching: Some(batching),
+            class_filter: Vec::new(),
+            heartbeat_interval: None,
+        })
+        .await
+    }
+
+    async fn subscribe_to_stream_with_classes(
+        &self,
+        stream_id: &str,
+        start: Revision<u64>,
+        classes: Vec<String>,
+    ) -> eyre::Result<SubscriptionStreaming> {
+        self.subscribe_stream(SubscribeToStream {
+            stream_name: stream_id.to_string(),
+            start,
+            batching: None,
+            class_filter: classes,
+            heartbeat_interval: None,
+        })
+        .await
     }
 
     async fn subscribe_to_process(
         &self,
         name: &str,
         source_code: &str,
+        logs: bool,
+        restart: RestartPolicy,
     ) -> eyre::Result<SubscriptionStreaming> {
         let result = self
             .inner
@@ -161,6 +539,8 @@ impl Client for GrpcClient {
                 Subscribe::ToProgram(SubscribeToProgram {
                     name: name.to_string(),
                     source: source_code.to_string(),
+                    logs,
+                    restart,
                 })
                 .into(),
             ))
@@ -176,10 +556,47 @@ impl Client for GrpcClient {
         Ok(SubscriptionStreaming::from_grpc(stream))
     }
 
+    async fn subscribe_persistent(
+        &self,
+        group: &str,
+        stream_id: &str,
+    ) -> eyre::Result<SubscriptionStreaming> {
+        let result = self
+            .inner
+            .clone()
+            .subscribe(Request::new(
+                Subscribe::ToGroup(SubscribeToGroup {
+                    group: group.to_string(),
+                    stream_name: stream_id.to_string(),
+                })
+                .into(),
+            ))
+            .await?;
+
+        Ok(SubscriptionStreaming::from_grpc(result.into_inner()))
+    }
+
+    async fn ack(&self, group: &str, stream_id: &str, position: u64) -> eyre::Result<()> {
+        self.inner
+            .clone()
+            .ack(Request::new(
+                Ack {
+                    group: group.to_string(),
+                    stream_name: stream_id.to_string(),
+                    position,
+                }
+                .into(),
+            ))
+            .await?;
+
+        Ok(())
+    }
+
     async fn delete_stream(
         &self,
         stream_id: &str,
         expected_revision: ExpectedRevision,
+        dry_run: bool,
     ) -> eyre::Result<DeleteStreamCompleted> {
         let result = self
             .inner
@@ -188,6 +605,27 @@ impl Client for GrpcClient {
                 DeleteStream {
                     stream_name: stream_id.to_string(),
                     expected_revision,
+                    dry_run,
+                }
+                .into(),
+            ))
+            .await?;
+
+        Ok(result.into_inner().try_into()?)
+    }
+
+    async fn truncate_stream(
+        &self,
+        stream_id: &str,
+        before: u64,
+    ) -> eyre::Result<TruncateStreamCompleted> {
+        let result = self
+            .inner
+            .clone()
+            .truncate_stream(Request::new(
+                TruncateStream {
+                    stream_name: stream_id.to_string(),
+                    before,
                 }
                 .into(),
             ))
@@ -196,6 +634,53 @@ impl Client for GrpcClient {
         Ok(result.into_inner().try_into()?)
     }
 
+    async fn set_stream_metadata(
+        &self,
+        stream_id: &str,
+        metadata: StreamMetadata,
+    ) -> eyre::Result<SetStreamMetadataCompleted> {
+        let result = self
+            .inner
+            .clone()
+            .set_stream_metadata(Request::new(
+                SetStreamMetadata {
+                    stream_name: stream_id.to_string(),
+                    metadata,
+                }
+                .into(),
+            ))
+            .await?;
+
+        Ok(result.into_inner().try_into()?)
+    }
+
+    async fn get_stream_metadata(
+        &self,
+        stream_id: &str,
+    ) -> eyre::Result<ReadStreamCompleted<StreamMetadata>> {
+        let result = self
+            .inner
+            .clone()
+            .get_stream_metadata(Request::new(GetStreamMetadataRequest {
+                stream_name: stream_id.to_string(),
+            }))
+            .await?;
+
+        Ok(result.into_inner().try_into()?)
+    }
+
+    async fn latest_revision(&self, stream_id: &str) -> eyre::Result<Option<u64>> {
+        let result = self
+            .inner
+            .clone()
+            .get_stream_revision(Request::new(GetStreamRevisionRequest {
+                stream_name: stream_id.to_string(),
+            }))
+            .await?;
+
+        Ok(result.into_inner().revision)
+    }
+
     async fn list_programs(&self) -> eyre::Result<Vec<ProgramSummary>> {
         let result = self
             .inner
@@ -248,6 +733,85 @@ impl Client for GrpcClient {
 
         Ok(())
     }
+
+    async fn set_log_filters(&self, directives: Vec<String>) -> eyre::Result<LogFiltersSet> {
+        let result = self
+            .inner
+            .clone()
+            .set_log_filters(Request::new(SetLogFilters { directives }.into()))
+            .await?;
+
+        Ok(result.into_inner().try_into()?)
+    }
+
+    async fn maintain_index(&self, compact: bool) -> eyre::Result<IndexMaintained> {
+        let result = self
+            .inner
+            .clone()
+            .maintain_index(Request::new(MaintainIndex { compact }.into()))
+            .await?;
+
+        Ok(result.into_inner().into())
+    }
+
+    async fn list_streams(
+        &self,
+        prefix: Option<String>,
+        offset: usize,
+        limit: usize,
+    ) -> eyre::Result<StreamPage> {
+        let result = self
+            .inner
+            .clone()
+            .list_streams(Request::new(
+                ListStreams {
+                    prefix,
+                    offset,
+                    limit,
+                }
+                .into(),
+            ))
+            .await?;
+
+        Ok(result.into_inner().into())
+    }
+
+    async fn copy_stream(
+        &self,
+        source: &str,
+        target: &str,
+        expected_revision: ExpectedRevision,
+        class_renames: HashMap<String, String>,
+    ) -> eyre::Result<CopyStreamCompleted> {
+        self.copy(CopyStream {
+            source_stream: source.to_string(),
+            target_stream: target.to_string(),
+            expected_revision,
+            class_renames,
+            mapper: None,
+            on_mapper_error: MapperErrorPolicy::default(),
+        })
+        .await
+    }
+
+    async fn copy_stream_with_mapper(
+        &self,
+        source: &str,
+        target: &str,
+        expected_revision: ExpectedRevision,
+        mapper: &str,
+        on_mapper_error: MapperErrorPolicy,
+    ) -> eyre::Result<CopyStreamCompleted> {
+        self.copy(CopyStream {
+            source_stream: source.to_string(),
+            target_stream: target.to_string(),
+            expected_revision,
+            class_renames: HashMap::new(),
+            mapper: Some(mapper.to_string()),
+            on_mapper_error,
+        })
+        .await
+    }
 }
 
 fn parse_read_error(status: tonic::Status) -> eyre::Result<ReadError> {
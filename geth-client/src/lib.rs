@@ -1,19 +1,39 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
 use std::sync::Arc;
+use std::time::Duration;
 
+pub use conflict::read_conflicting_events;
 use futures_util::TryStreamExt;
+use geth_common::SubscribeToStream;
 pub use geth_common::{
-    AppendStreamCompleted, ContentType, DeleteStreamCompleted, Direction, EndPoint,
-    ExpectedRevision, ProgramStats, ProgramSummary, Propose, ReadStreamCompleted,
-    ReadStreamResponse, Record, Revision, SubscriptionConfirmation, SubscriptionEvent,
+    AppendAndSubscribeCompleted, AppendStreamCompleted, ContentType, CopyStreamCompleted,
+    DeleteStreamCompleted, Direction, EndPoint, Epochs, ExpectedRevision, IndexMaintained,
+    LogFiltersSet, MapperErrorPolicy, ProgramStats, ProgramSummary, ProjectedRecord, Propose,
+    ReadProjectionResponse, ReadStreamCompleted, ReadStreamResponse, Record, RestartPolicy,
+    Revision, RevisionConflict, SetStreamMetadataCompleted, StreamAcl, StreamMetadata, StreamPage,
+    SubscriptionBatching, SubscriptionConfirmation, SubscriptionEvent, TruncateStreamCompleted,
+    UnsubscribeReason, WrongExpectedRevisionError,
 };
-pub use grpc::GrpcClient;
+pub use grpc::{GrpcClient, TlsConfig};
+pub use pool::{GrpcClientPool, LoadBalancing};
+pub use reconnect::{KeepAlive, ReconnectPolicy};
+use serde::de::DeserializeOwned;
 use tonic::Streaming;
 
+mod batching;
+mod conflict;
 mod grpc;
+mod pool;
+mod reconnect;
 mod types;
 
+#[allow(clippy::large_enum_variant)]
 pub enum ReadStreaming {
-    Grpc(Streaming<geth_grpc::protocol::ReadStreamResponse>),
+    Grpc {
+        inner: Streaming<geth_grpc::protocol::ReadStreamResponse>,
+        tail_revision: Option<u64>,
+    },
     Local(geth_engine::reading::Streaming),
     Subscription(SubscriptionStreaming),
 }
@@ -21,11 +41,19 @@ pub enum ReadStreaming {
 impl ReadStreaming {
     pub async fn next(&mut self) -> eyre::Result<Option<Record>> {
         match self {
-            ReadStreaming::Grpc(streaming) => {
-                if let Some(resp) = streaming.try_next().await? {
+            ReadStreaming::Grpc {
+                inner,
+                tail_revision,
+            } => {
+                if let Some(resp) = inner.try_next().await? {
                     match resp.try_into()? {
                         ReadStreamResponse::EventAppeared(record) => return Ok(Some(record)),
-                        ReadStreamResponse::EndOfStream => return Ok(None),
+                        ReadStreamResponse::EndOfStream {
+                            tail_revision: tail,
+                        } => {
+                            *tail_revision = tail;
+                            return Ok(None);
+                        }
                         ReadStreamResponse::StreamDeleted => unreachable!(),
                     }
                 }
@@ -40,9 +68,14 @@ impl ReadStreaming {
                     match event {
                         SubscriptionEvent::EventAppeared(record) => return Ok(Some(record)),
 
+                        SubscriptionEvent::EventsAppeared(_) => {
+                            eyre::bail!("unexpected batch of events while reading")
+                        }
+
                         SubscriptionEvent::Confirmed(_)
                         | SubscriptionEvent::CaughtUp
-                        | SubscriptionEvent::Notification(_) => continue,
+                        | SubscriptionEvent::Notification(_)
+                        | SubscriptionEvent::ProgramLog { .. } => continue,
 
                         SubscriptionEvent::Unsubscribed(_) => break,
                     }
@@ -52,25 +85,293 @@ impl ReadStreaming {
             }
         }
     }
+
+    /// Last revision of the stream when the read started, `None` if the stream was empty. Only
+    /// known once [`ReadStreaming::next`] returned `None`, and always `None` for subscriptions.
+    pub fn tail_revision(&self) -> Option<u64> {
+        match self {
+            ReadStreaming::Grpc { tail_revision, .. } => *tail_revision,
+            ReadStreaming::Local(streaming) => streaming.tail_revision(),
+            ReadStreaming::Subscription(_) => None,
+        }
+    }
+
+    /// Decodes the records into `A` as they are read, see [`TypedReadStreaming`].
+    pub fn typed<A>(self) -> TypedReadStreaming<A>
+    where
+        A: DeserializeOwned,
+    {
+        TypedReadStreaming {
+            inner: self,
+            policy: DecodePolicy::default(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// What a typed stream does with a record that can't be decoded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DecodePolicy {
+    /// Moves on to the next record.
+    Skip,
+    /// Returns the decoding error.
+    #[default]
+    Fail,
+}
+
+/// A decoded record, along with where it sits in the stream and in the log.
+#[derive(Clone, Debug)]
+pub struct Decoded<A> {
+    pub value: A,
+    pub revision: u64,
+    pub position: u64,
+}
+
+/// Read stream yielding decoded records. Fails on the first record that can't be decoded unless
+/// told otherwise with [`TypedReadStreaming::with_policy`].
+pub struct TypedReadStreaming<A> {
+    inner: ReadStreaming,
+    policy: DecodePolicy,
+    _marker: PhantomData<A>,
+}
+
+impl<A> TypedReadStreaming<A>
+where
+    A: DeserializeOwned,
+{
+    pub fn with_policy(self, policy: DecodePolicy) -> Self {
+        Self { policy, ..self }
+    }
+
+    pub async fn next(&mut self) -> eyre::Result<Option<Decoded<A>>> {
+        while let Some(record) = self.inner.next().await? {
+            match record.as_value::<A>() {
+                Ok(value) => {
+                    return Ok(Some(Decoded {
+                        value,
+                        revision: record.revision,
+                        position: record.position,
+                    }))
+                }
+
+                Err(e) => match self.policy {
+                    DecodePolicy::Fail => {
+                        return Err(e.wrap_err(format!(
+                            "error when decoding record {} of stream '{}'",
+                            record.revision, record.stream_name
+                        )))
+                    }
+
+                    DecodePolicy::Skip => {
+                        tracing::debug!(
+                            stream_name = record.stream_name,
+                            revision = record.revision,
+                            error = %e,
+                            "skipping record that can't be decoded"
+                        );
+                    }
+                },
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// See [`ReadStreaming::tail_revision`].
+    pub fn tail_revision(&self) -> Option<u64> {
+        self.inner.tail_revision()
+    }
+}
+
+#[allow(clippy::large_enum_variant)]
+pub enum ProjectionStreaming {
+    Grpc(Streaming<geth_grpc::protocol::ReadProjectionResponse>),
+    Local(geth_engine::reading::ProjectedStreaming),
 }
 
+impl ProjectionStreaming {
+    pub async fn next(&mut self) -> eyre::Result<Option<ProjectedRecord>> {
+        match self {
+            ProjectionStreaming::Grpc(streaming) => {
+                if let Some(resp) = streaming.try_next().await? {
+                    match resp.try_into()? {
+                        ReadProjectionResponse::EventAppeared(record) => return Ok(Some(record)),
+                        ReadProjectionResponse::EndOfStream => return Ok(None),
+                    }
+                }
+
+                Ok(None)
+            }
+
+            ProjectionStreaming::Local(streaming) => streaming.next().await,
+        }
+    }
+}
+
+const ALL_STREAM: &str = "$all";
+
 enum SubscriptionType {
     Grpc(Streaming<geth_grpc::protocol::SubscribeResponse>),
+    GrpcAppended(Streaming<geth_grpc::protocol::AppendAndSubscribeResponse>),
 }
 
+/// Events of a subscription. A subscription always ends with either an error or a
+/// [`SubscriptionEvent::Unsubscribed`] telling why, [`UnsubscribeReason::Disconnected`] if the
+/// server closed the stream without saying. `next` returns `None` afterward.
+///
+/// Stream subscriptions of a [`GrpcClient`] subscribe again when their connection is lost,
+/// following its [`ReconnectPolicy`], and carry on after the last event they delivered. With a
+/// [`KeepAlive`], hearing nothing from the server for too long counts as losing the connection.
 pub struct SubscriptionStreaming {
     confirmation: Option<SubscriptionConfirmation>,
+    ended: bool,
     r#type: SubscriptionType,
+    resume: Option<Resume>,
+    idle_timeout: Option<Duration>,
+    heartbeats: u64,
+}
+
+/// What a stream subscription needs to subscribe again.
+struct Resume {
+    client: GrpcClient,
+    params: SubscribeToStream,
+    policy: ReconnectPolicy,
+    /// Revision of the last event delivered, its position when subscribed to `$all`.
+    cursor: Option<u64>,
+}
+
+impl Resume {
+    fn track(&mut self, event: &SubscriptionEvent) {
+        let last = match event {
+            SubscriptionEvent::EventAppeared(record) => record,
+            SubscriptionEvent::EventsAppeared(records) => match records.last() {
+                Some(record) => record,
+                None => return,
+            },
+            _ => return,
+        };
+
+        self.cursor = Some(if self.params.stream_name == ALL_STREAM {
+            last.position
+        } else {
+            last.revision
+        });
+    }
+
+    /// Subscribes again after the last event delivered, skipping the confirmation the caller
+    /// already got.
+    async fn resubscribe(&mut self) -> eyre::Result<SubscriptionType> {
+        let mut params = self.params.clone();
+        if let Some(cursor) = self.cursor {
+            params.start = Revision::Revision(cursor + 1);
+        }
+
+        let mut last_error = None;
+        for attempt in 1..=self.policy.max_attempts {
+            tokio::time::sleep(self.policy.backoff(attempt)).await;
+
+            tracing::debug!(
+                stream_name = self.params.stream_name,
+                attempt = attempt,
+                max_attempts = self.policy.max_attempts,
+                "resubscribing to stream"
+            );
+
+            let mut streaming = match self.client.subscribe_stream(params.clone()).await {
+                Ok(streaming) => streaming,
+                Err(e) => {
+                    last_error = Some(e);
+                    continue;
+                }
+            };
+
+            match streaming.next_from_server().await {
+                Ok(Some(SubscriptionEvent::Confirmed(_))) => return Ok(streaming.r#type),
+                Ok(other) => {
+                    last_error = Some(eyre::eyre!(
+                        "expected a confirmation when resubscribing, got {other:?}"
+                    ))
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+
+        match last_error {
+            Some(e) => Err(e.wrap_err(format!(
+                "lost the subscription to '{}', resubscribing failed after {} attempts",
+                self.params.stream_name, self.policy.max_attempts
+            ))),
+
+            None => eyre::bail!("lost the subscription to '{}'", self.params.stream_name),
+        }
+    }
+}
+
+/// Errors meaning the connection was lost rather than the server refusing the subscription.
+fn is_disconnection(error: &eyre::Report) -> bool {
+    error.downcast_ref::<tonic::Status>().is_some_and(|status| {
+        matches!(
+            status.code(),
+            tonic::Code::Unavailable | tonic::Code::Unknown | tonic::Code::Cancelled
+        )
+    })
 }
 
 impl SubscriptionStreaming {
     pub fn from_grpc(streaming: Streaming<geth_grpc::protocol::SubscribeResponse>) -> Self {
         Self {
             confirmation: None,
+            ended: false,
             r#type: SubscriptionType::Grpc(streaming),
+            resume: None,
+            idle_timeout: None,
+            heartbeats: 0,
+        }
+    }
+
+    pub(crate) fn with_idle_timeout(self, idle_timeout: Option<Duration>) -> Self {
+        Self {
+            idle_timeout,
+            ..self
         }
     }
 
+    pub(crate) fn resuming(
+        self,
+        client: GrpcClient,
+        params: SubscribeToStream,
+        policy: ReconnectPolicy,
+    ) -> Self {
+        Self {
+            resume: Some(Resume {
+                client,
+                params,
+                policy,
+                cursor: None,
+            }),
+            ..self
+        }
+    }
+
+    /// Wraps what follows the append outcome of an append-and-subscribe call.
+    pub fn from_grpc_appended(
+        streaming: Streaming<geth_grpc::protocol::AppendAndSubscribeResponse>,
+    ) -> Self {
+        Self {
+            confirmation: None,
+            ended: false,
+            r#type: SubscriptionType::GrpcAppended(streaming),
+            resume: None,
+            idle_timeout: None,
+            heartbeats: 0,
+        }
+    }
+
+    /// Heartbeats received from the server so far, see [`KeepAlive`].
+    pub fn heartbeats(&self) -> u64 {
+        self.heartbeats
+    }
+
     pub async fn wait_until_confirmed(&mut self) -> eyre::Result<SubscriptionConfirmation> {
         if let Some(conf) = self.confirmation.as_ref() {
             return Ok(conf.clone());
@@ -85,10 +386,96 @@ impl SubscriptionStreaming {
     }
 
     pub async fn next(&mut self) -> eyre::Result<Option<SubscriptionEvent>> {
+        if self.ended {
+            return Ok(None);
+        }
+
+        let mut outcome = self.next_from_server().await;
+
+        // Taken out for the time being, as reading from the server needs the whole subscription.
+        if let Some(mut resume) = self.resume.take() {
+            // The new subscription can be lost too before delivering anything.
+            loop {
+                let lost = match &outcome {
+                    Ok(None) => true,
+                    Err(e) => is_disconnection(e),
+                    // Only a shutdown, other reasons end the new subscription the same way.
+                    Ok(Some(SubscriptionEvent::Unsubscribed(UnsubscribeReason::Shutdown))) => true,
+                    Ok(Some(_)) => false,
+                };
+
+                if !lost {
+                    break;
+                }
+
+                match resume.resubscribe().await {
+                    Ok(r#type) => {
+                        self.r#type = r#type;
+                        outcome = self.next_from_server().await;
+                    }
+
+                    Err(e) => {
+                        outcome = Err(e);
+                        break;
+                    }
+                }
+            }
+
+            if let Ok(Some(event)) = &outcome {
+                resume.track(event);
+            }
+
+            self.resume = Some(resume);
+        }
+
+        match &outcome {
+            Ok(Some(SubscriptionEvent::Unsubscribed(_))) | Err(_) => self.ended = true,
+            Ok(Some(_)) => {}
+            Ok(None) => {
+                self.ended = true;
+                return Ok(Some(SubscriptionEvent::Unsubscribed(
+                    UnsubscribeReason::Disconnected,
+                )));
+            }
+        }
+
+        outcome
+    }
+
+    async fn next_from_server(&mut self) -> eyre::Result<Option<SubscriptionEvent>> {
         match &mut self.r#type {
-            SubscriptionType::Grpc(streaming) => {
-                if let Some(resp) = streaming.try_next().await? {
-                    return Ok(Some(resp.try_into()?));
+            SubscriptionType::Grpc(streaming) => loop {
+                let next = match self.idle_timeout {
+                    None => streaming.try_next().await?,
+                    Some(timeout) => {
+                        match tokio::time::timeout(timeout, streaming.try_next()).await {
+                            Ok(next) => next?,
+                            Err(_) => {
+                                let status = tonic::Status::unavailable(format!(
+                                    "no heartbeat from the server for {timeout:?}"
+                                ));
+
+                                return Err(status.into());
+                            }
+                        }
+                    }
+                };
+
+                match next {
+                    Some(resp) if resp.is_heartbeat() => self.heartbeats += 1,
+                    Some(resp) => return Ok(Some(resp.try_into()?)),
+                    None => return Ok(None),
+                }
+            },
+
+            SubscriptionType::GrpcAppended(streaming) => {
+                while let Some(resp) = streaming.try_next().await? {
+                    if let Some(
+                        geth_grpc::protocol::append_and_subscribe_response::Response::Event(event),
+                    ) = resp.response
+                    {
+                        return Ok(Some(event.try_into()?));
+                    }
                 }
 
                 Ok(None)
@@ -106,37 +493,254 @@ pub trait Client {
         proposes: Vec<Propose>,
     ) -> eyre::Result<AppendStreamCompleted>;
 
+    /// Appends `proposes` in successive appends of at most `batch_size` events, for lists too
+    /// large to fit in a single request. Only the first append checks `expected_revision`, the
+    /// following ones expect the stream to be where the previous one left it.
+    ///
+    /// Batches aren't atomic as a whole: when one fails, the ones before it stay committed and
+    /// its error is returned. On success, the result is the one of the last batch with the ids
+    /// of every appended event.
+    async fn append_stream_batched(
+        &self,
+        stream_id: &str,
+        expected_revision: ExpectedRevision,
+        proposes: Vec<Propose>,
+        batch_size: usize,
+    ) -> eyre::Result<AppendStreamCompleted> {
+        if batch_size == 0 {
+            eyre::bail!("batch size must be greater than 0");
+        }
+
+        if proposes.len() <= batch_size {
+            return self
+                .append_stream(stream_id, expected_revision, proposes)
+                .await;
+        }
+
+        let mut expected = expected_revision;
+        let mut event_ids = Vec::with_capacity(proposes.len());
+        let mut proposes = proposes.into_iter().peekable();
+        let mut last = None;
+
+        while proposes.peek().is_some() {
+            let batch = proposes.by_ref().take(batch_size).collect::<Vec<_>>();
+            let mut result = match self.append_stream(stream_id, expected, batch).await? {
+                AppendStreamCompleted::Success(result) => result,
+                error => return Ok(error),
+            };
+
+            // The next expected version is one past the last event appended, where the next
+            // batch expects the revision of that event.
+            expected = match result.next_expected_version {
+                ExpectedRevision::Revision(next) => {
+                    ExpectedRevision::Revision(next.saturating_sub(1))
+                }
+                other => other,
+            };
+
+            event_ids.append(&mut result.event_ids);
+            last = Some(result);
+        }
+
+        let mut result = last.expect("at least one batch to be appended");
+        result.event_ids = event_ids;
+
+        Ok(AppendStreamCompleted::Success(result))
+    }
+
+    /// Appends to a stream and subscribes to the events written to it after the append, in one
+    /// operation. The server registers the subscription before committing the append, so nothing
+    /// written right after it can be missed.
+    async fn append_and_subscribe(
+        &self,
+        stream_id: &str,
+        expected_revision: ExpectedRevision,
+        proposes: Vec<Propose>,
+    ) -> eyre::Result<AppendAndSubscribeCompleted<SubscriptionStreaming>>;
+
+    /// Reads a stream. Once all the records are consumed, [`ReadStreaming::tail_revision`] tells
+    /// if the end of the stream was reached or if more records can be read. A `max_count` of
+    /// `None` reads until the end of the stream. With `resolve_links`, link events are replaced
+    /// by the events they point to, see [`geth_common::LinkTo`].
     async fn read_stream(
         &self,
         stream_id: &str,
         direction: Direction,
         revision: Revision<u64>,
-        max_count: u64,
+        max_count: Option<u64>,
+        resolve_links: bool,
+    ) -> eyre::Result<ReadStreamCompleted<ReadStreaming>>;
+
+    /// Same as [`Client::read_stream`] but `epochs` decides whether the events appended before
+    /// the stream was last deleted and recreated are returned too, see [`Record::epoch`].
+    async fn read_stream_with_epochs(
+        &self,
+        stream_id: &str,
+        direction: Direction,
+        revision: Revision<u64>,
+        max_count: Option<u64>,
+        resolve_links: bool,
+        epochs: Epochs,
     ) -> eyre::Result<ReadStreamCompleted<ReadStreaming>>;
 
+    /// Reads the events of every stream in commit order, starting at a log position taken from
+    /// [`Record::position`]. Events of deleted streams are skipped.
+    async fn read_all(
+        &self,
+        from: Revision<u64>,
+        direction: Direction,
+        max_count: Option<u64>,
+    ) -> eyre::Result<ReadStreaming>;
+
+    /// Reads a stream but only returns the selected JSON fields of each record. Fields use a
+    /// JSONPath-like syntax (`$.foo.bar[0]`). Missing fields are returned as `null` and records
+    /// that are not JSON encoded are skipped.
+    async fn read_projection(
+        &self,
+        stream_id: &str,
+        direction: Direction,
+        revision: Revision<u64>,
+        max_count: u64,
+        fields: Vec<String>,
+    ) -> eyre::Result<ReadStreamCompleted<ProjectionStreaming>>;
+
     async fn subscribe_to_stream(
         &self,
         stream_id: &str,
         start: Revision<u64>,
     ) -> eyre::Result<SubscriptionStreaming>;
 
+    /// Like [`Client::subscribe_to_stream`] but records are delivered in batches, as
+    /// [`SubscriptionEvent::EventsAppeared`], see [`SubscriptionBatching`].
+    async fn subscribe_to_stream_in_batches(
+        &self,
+        stream_id: &str,
+        start: Revision<u64>,
+        batching: SubscriptionBatching,
+    ) -> eyre::Result<SubscriptionStreaming>;
+
+    /// Like [`Client::subscribe_to_stream`] but only records of `classes` are delivered. The
+    /// server does the filtering, the other records never go over the wire.
+    async fn subscribe_to_stream_with_classes(
+        &self,
+        stream_id: &str,
+        start: Revision<u64>,
+        classes: Vec<String>,
+    ) -> eyre::Result<SubscriptionStreaming>;
+
+    /// Runs a program and subscribes to what it emits. With `logs`, what the program prints is
+    /// also streamed, as [`SubscriptionEvent::ProgramLog`] events. `restart` decides whether the
+    /// program subscriptions come back after an error.
     async fn subscribe_to_process(
         &self,
         name: &str,
         source_code: &str,
+        logs: bool,
+        restart: RestartPolicy,
+    ) -> eyre::Result<SubscriptionStreaming>;
+
+    /// Joins the persistent subscription of `group` to a stream. It resumes from the first record
+    /// the group didn't acknowledge with [`Client::ack`], across reconnections and server
+    /// restarts. Members of a group compete for records, each record going to a single member
+    /// and to another one if that member leaves without acknowledging it.
+    async fn subscribe_persistent(
+        &self,
+        group: &str,
+        stream_id: &str,
     ) -> eyre::Result<SubscriptionStreaming>;
 
+    /// Acknowledges a record of a persistent subscription, `position` being its revision, or its
+    /// log position when subscribed to `$all`.
+    async fn ack(&self, group: &str, stream_id: &str, position: u64) -> eyre::Result<()>;
+
+    /// Deletes a stream. With `dry_run`, only checks the deletion would go through and reports
+    /// the current revision of the stream without deleting it.
     async fn delete_stream(
         &self,
         stream_id: &str,
         expected_revision: ExpectedRevision,
+        dry_run: bool,
     ) -> eyre::Result<DeleteStreamCompleted>;
 
+    /// Drops every event prior to the `before` revision while keeping the stream alive. Reads
+    /// then start at `before` and appends continue from the current revision. Truncating past
+    /// the end of the stream empties it, truncating a stream that doesn't exist fails.
+    ///
+    /// Truncated events are only hidden from reads, their space on disk is reclaimed once the
+    /// log is scavenged.
+    async fn truncate_stream(
+        &self,
+        stream_id: &str,
+        before: u64,
+    ) -> eyre::Result<TruncateStreamCompleted>;
+
+    /// Replaces the settings of a stream, see [`StreamMetadata`].
+    async fn set_stream_metadata(
+        &self,
+        stream_id: &str,
+        metadata: StreamMetadata,
+    ) -> eyre::Result<SetStreamMetadataCompleted>;
+
+    /// Returns the settings of a stream, the default ones if none were ever set.
+    async fn get_stream_metadata(
+        &self,
+        stream_id: &str,
+    ) -> eyre::Result<ReadStreamCompleted<StreamMetadata>>;
+
+    /// Returns the revision of the last event of a stream, `None` if the stream was never written
+    /// to or is deleted. Cheaper than reading the stream backward.
+    async fn latest_revision(&self, stream_id: &str) -> eyre::Result<Option<u64>>;
+
+    /// Tells if a stream holds events, a deleted stream doesn't.
+    async fn stream_exists(&self, stream_id: &str) -> eyre::Result<bool> {
+        Ok(self.latest_revision(stream_id).await?.is_some())
+    }
+
     async fn list_programs(&self) -> eyre::Result<Vec<ProgramSummary>>;
 
     async fn get_program(&self, id: u64) -> eyre::Result<Option<ProgramStats>>;
 
     async fn stop_program(&self, id: u64) -> eyre::Result<()>;
+
+    /// Replaces the server log filters at runtime, e.g. `geth_engine=trace`.
+    async fn set_log_filters(&self, directives: Vec<String>) -> eyre::Result<LogFiltersSet>;
+
+    /// Flushes the index entries held in memory to disk and, with `compact`, merges the index
+    /// files into one. Returns once done, which can take a while on a large index. Meant to be
+    /// run before a backup, or to bring read amplification down without waiting on the server.
+    async fn maintain_index(&self, compact: bool) -> eyre::Result<IndexMaintained>;
+
+    /// Lists the names of the streams in the database, in lexicographic order, optionally only
+    /// those starting with `prefix`. Deleted streams are not listed.
+    async fn list_streams(
+        &self,
+        prefix: Option<String>,
+        offset: usize,
+        limit: usize,
+    ) -> eyre::Result<StreamPage>;
+
+    /// Copies the events of `source` to the end of `target` on the server, keeping their ids and
+    /// payloads. Classes found in `class_renames` are renamed on the way. `expected_revision` is
+    /// checked against `target` before anything is written to it.
+    async fn copy_stream(
+        &self,
+        source: &str,
+        target: &str,
+        expected_revision: ExpectedRevision,
+        class_renames: HashMap<String, String>,
+    ) -> eyre::Result<CopyStreamCompleted>;
+
+    /// Same as [`Client::copy_stream`] but each event goes through `mapper`, registered on the
+    /// server under that name, which returns the events to copy in its place. `on_mapper_error`
+    /// decides whether an event the mapper fails on stops the copy or is left out.
+    async fn copy_stream_with_mapper(
+        &self,
+        source: &str,
+        target: &str,
+        expected_revision: ExpectedRevision,
+        mapper: &str,
+        on_mapper_error: MapperErrorPolicy,
+    ) -> eyre::Result<CopyStreamCompleted>;
 }
 
 #[async_trait::async_trait]
@@ -155,15 +759,70 @@ where
             .await
     }
 
+    async fn append_and_subscribe(
+        &self,
+        stream_id: &str,
+        expected_revision: ExpectedRevision,
+        proposes: Vec<Propose>,
+    ) -> eyre::Result<AppendAndSubscribeCompleted<SubscriptionStreaming>> {
+        self.as_ref()
+            .append_and_subscribe(stream_id, expected_revision, proposes)
+            .await
+    }
+
     async fn read_stream(
         &self,
         stream_id: &str,
         direction: Direction,
         revision: Revision<u64>,
-        max_count: u64,
+        max_count: Option<u64>,
+        resolve_links: bool,
+    ) -> eyre::Result<ReadStreamCompleted<ReadStreaming>> {
+        self.as_ref()
+            .read_stream(stream_id, direction, revision, max_count, resolve_links)
+            .await
+    }
+
+    async fn read_all(
+        &self,
+        from: Revision<u64>,
+        direction: Direction,
+        max_count: Option<u64>,
+    ) -> eyre::Result<ReadStreaming> {
+        self.as_ref().read_all(from, direction, max_count).await
+    }
+
+    async fn read_stream_with_epochs(
+        &self,
+        stream_id: &str,
+        direction: Direction,
+        revision: Revision<u64>,
+        max_count: Option<u64>,
+        resolve_links: bool,
+        epochs: Epochs,
     ) -> eyre::Result<ReadStreamCompleted<ReadStreaming>> {
         self.as_ref()
-            .read_stream(stream_id, direction, revision, max_count)
+            .read_stream_with_epochs(
+                stream_id,
+                direction,
+                revision,
+                max_count,
+                resolve_links,
+                epochs,
+            )
+            .await
+    }
+
+    async fn read_projection(
+        &self,
+        stream_id: &str,
+        direction: Direction,
+        revision: Revision<u64>,
+        max_count: u64,
+        fields: Vec<String>,
+    ) -> eyre::Result<ReadStreamCompleted<ProjectionStreaming>> {
+        self.as_ref()
+            .read_projection(stream_id, direction, revision, max_count, fields)
             .await
     }
 
@@ -175,24 +834,90 @@ where
         self.as_ref().subscribe_to_stream(stream_id, start).await
     }
 
+    async fn subscribe_to_stream_in_batches(
+        &self,
+        stream_id: &str,
+        start: Revision<u64>,
+        batching: SubscriptionBatching,
+    ) -> eyre::Result<SubscriptionStreaming> {
+        self.as_ref()
+            .subscribe_to_stream_in_batches(stream_id, start, batching)
+            .await
+    }
+
+    async fn subscribe_to_stream_with_classes(
+        &self,
+        stream_id: &str,
+        start: Revision<u64>,
+        classes: Vec<String>,
+    ) -> eyre::Result<SubscriptionStreaming> {
+        self.as_ref()
+            .subscribe_to_stream_with_classes(stream_id, start, classes)
+            .await
+    }
+
     async fn subscribe_to_process(
         &self,
         name: &str,
         source_code: &str,
+        logs: bool,
+        restart: RestartPolicy,
+    ) -> eyre::Result<SubscriptionStreaming> {
+        self.as_ref()
+            .subscribe_to_process(name, source_code, logs, restart)
+            .await
+    }
+
+    async fn subscribe_persistent(
+        &self,
+        group: &str,
+        stream_id: &str,
     ) -> eyre::Result<SubscriptionStreaming> {
-        self.as_ref().subscribe_to_process(name, source_code).await
+        self.as_ref().subscribe_persistent(group, stream_id).await
+    }
+
+    async fn ack(&self, group: &str, stream_id: &str, position: u64) -> eyre::Result<()> {
+        self.as_ref().ack(group, stream_id, position).await
     }
 
     async fn delete_stream(
         &self,
         stream_id: &str,
         expected_revision: ExpectedRevision,
+        dry_run: bool,
     ) -> eyre::Result<DeleteStreamCompleted> {
         self.as_ref()
-            .delete_stream(stream_id, expected_revision)
+            .delete_stream(stream_id, expected_revision, dry_run)
             .await
     }
 
+    async fn truncate_stream(
+        &self,
+        stream_id: &str,
+        before: u64,
+    ) -> eyre::Result<TruncateStreamCompleted> {
+        self.as_ref().truncate_stream(stream_id, before).await
+    }
+
+    async fn set_stream_metadata(
+        &self,
+        stream_id: &str,
+        metadata: StreamMetadata,
+    ) -> eyre::Result<SetStreamMetadataCompleted> {
+        self.as_ref().set_stream_metadata(stream_id, metadata).await
+    }
+
+    async fn get_stream_metadata(
+        &self,
+        stream_id: &str,
+    ) -> eyre::Result<ReadStreamCompleted<StreamMetadata>> {
+        self.as_ref().get_stream_metadata(stream_id).await
+    }
+
+    async fn latest_revision(&self, stream_id: &str) -> eyre::Result<Option<u64>> {
+        self.as_ref().latest_revision(stream_id).await
+    }
+
     async fn list_programs(&self) -> eyre::Result<Vec<ProgramSummary>> {
         self.as_ref().list_programs().await
     }
@@ -204,4 +929,46 @@ where
     async fn stop_program(&self, id: u64) -> eyre::Result<()> {
         self.as_ref().stop_program(id).await
     }
+
+    async fn set_log_filters(&self, directives: Vec<String>) -> eyre::Result<LogFiltersSet> {
+        self.as_ref().set_log_filters(directives).await
+    }
+
+    async fn maintain_index(&self, compact: bool) -> eyre::Result<IndexMaintained> {
+        self.as_ref().maintain_index(compact).await
+    }
+
+    async fn list_streams(
+        &self,
+        prefix: Option<String>,
+        offset: usize,
+        limit: usize,
+    ) -> eyre::Result<StreamPage> {
+        self.as_ref().list_streams(prefix, offset, limit).await
+    }
+
+    async fn copy_stream(
+        &self,
+        source: &str,
+        target: &str,
+        expected_revision: ExpectedRevision,
+        class_renames: HashMap<String, String>,
+    ) -> eyre::Result<CopyStreamCompleted> {
+        self.as_ref()
+            .copy_stream(source, target, expected_revision, class_renames)
+            .await
+    }
+
+    async fn copy_stream_with_mapper(
+        &self,
+        source: &str,
+        target: &str,
+        expected_revision: ExpectedRevision,
+        mapper: &str,
+        on_mapper_error: MapperErrorPolicy,
+    ) -> eyre::Result<CopyStreamCompleted> {
+        self.as_ref()
+            .copy_stream_with_mapper(source, target, expected_revision, mapper, on_mapper_error)
+            .await
+    }
 }
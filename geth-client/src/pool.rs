@@ -0,0 +1,378 @@
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use geth_common::{
+    AppendAndSubscribeCompleted, AppendStreamCompleted, CopyStreamCompleted, DeleteStreamCompleted,
+    Direction, EndPoint, Epochs, ExpectedRevision, IndexMaintained, LogFiltersSet,
+    MapperErrorPolicy, ProgramStats, ProgramSummary, Propose, ReadStreamCompleted, RestartPolicy,
+    Revision, SetStreamMetadataCompleted, StreamMetadata, StreamPage, SubscriptionBatching,
+    TruncateStreamCompleted,
+};
+
+use crate::{Client, GrpcClient, ProjectionStreaming, ReadStreaming, SubscriptionStreaming};
+
+/// How a [`GrpcClientPool`] picks the connection an operation goes through.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LoadBalancing {
+    /// Each connection in turn.
+    #[default]
+    RoundRobin,
+    /// The connection with the fewest operations in flight, connections being tried in turn
+    /// when several are tied.
+    LeastLoaded,
+}
+
+struct Connection {
+    client: GrpcClient,
+    in_flight: AtomicUsize,
+    operations: AtomicU64,
+}
+
+struct Inner {
+    connections: Vec<Connection>,
+    load_balancing: LoadBalancing,
+    next: AtomicUsize,
+}
+
+/// Spreads operations over several connections to a node, so throughput isn't capped by what a
+/// single connection can carry. It's a drop-in replacement for [`GrpcClient`].
+///
+/// An operation stays on the connection it was given: a subscription, or the records of a read,
+/// come through that connection until they end. Only the time it takes to issue the operation
+/// counts towards the load of a connection though.
+#[derive(Clone)]
+pub struct GrpcClientPool {
+    inner: Arc<Inner>,
+}
+
+impl GrpcClientPool {
+    /// Opens `size` connections to `endpoint`.
+    pub async fn connect(
+        endpoint: EndPoint,
+        size: usize,
+        load_balancing: LoadBalancing,
+    ) -> eyre::Result<Self> {
+        if size == 0 {
+            eyre::bail!("a connection pool needs at least one connection");
+        }
+
+        let mut clients = Vec::with_capacity(size);
+        for _ in 0..size {
+            clients.push(GrpcClient::connect(endpoint.clone()).await?);
+        }
+
+        Self::from_clients(clients, load_balancing)
+    }
+
+    /// Pools clients that were already connected, e.g. to scope all of them to a namespace. A
+    /// pool of clones of a same client shares a single connection.
+    pub fn from_clients(
+        clients: Vec<GrpcClient>,
+        load_balancing: LoadBalancing,
+    ) -> eyre::Result<Self> {
+        if clients.is_empty() {
+            eyre::bail!("a connection pool needs at least one connection");
+        }
+
+        let connections = clients
+            .into_iter()
+            .map(|client| Connection {
+                client,
+                in_flight: AtomicUsize::new(0),
+                operations: AtomicU64::new(0),
+            })
+            .collect();
+
+        Ok(Self {
+            inner: Arc::new(Inner {
+                connections,
+                load_balancing,
+                next: AtomicUsize::new(0),
+            }),
+        })
+    }
+
+    pub fn size(&self) -> usize {
+        self.inner.connections.len()
+    }
+
+    pub fn load_balancing(&self) -> LoadBalancing {
+        self.inner.load_balancing
+    }
+
+    /// Operations issued through each connection so far, in the order the connections were
+    /// opened.
+    pub fn operations_per_connection(&self) -> Vec<u64> {
+        self.inner
+            .connections
+            .iter()
+            .map(|c| c.operations.load(Ordering::Relaxed))
+            .collect()
+    }
+
+    fn pick(&self) -> Lease<'_> {
+        let connections = &self.inner.connections;
+        let start = self.inner.next.fetch_add(1, Ordering::Relaxed) % connections.len();
+
+        let connection = match self.inner.load_balancing {
+            LoadBalancing::RoundRobin => &connections[start],
+            LoadBalancing::LeastLoaded => (0..connections.len())
+                .map(|i| &connections[(start + i) % connections.len()])
+                .min_by_key(|c| c.in_flight.load(Ordering::Relaxed))
+                .expect("a pool is never empty"),
+        };
+
+        connection.operations.fetch_add(1, Ordering::Relaxed);
+        connection.in_flight.fetch_add(1, Ordering::Relaxed);
+
+        Lease { connection }
+    }
+}
+
+/// A connection an operation is in flight on.
+struct Lease<'a> {
+    connection: &'a Connection,
+}
+
+impl Deref for Lease<'_> {
+    type Target = GrpcClient;
+
+    fn deref(&self) -> &Self::Target {
+        &self.connection.client
+    }
+}
+
+impl Drop for Lease<'_> {
+    fn drop(&mut self) {
+        self.connection.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[async_trait::async_trait]
+impl Client for GrpcClientPool {
+    async fn append_stream(
+        &self,
+        stream_id: &str,
+        expected_revision: ExpectedRevision,
+        proposes: Vec<Propose>,
+    ) -> eyre::Result<AppendStreamCompleted> {
+        self.pick()
+            .append_stream(stream_id, expected_revision, proposes)
+            .await
+    }
+
+    async fn append_and_subscribe(
+        &self,
+        stream_id: &str,
+        expected_revision: ExpectedRevision,
+        proposes: Vec<Propose>,
+    ) -> eyre::Result<AppendAndSubscribeCompleted<SubscriptionStreaming>> {
+        self.pick()
+            .append_and_subscribe(stream_id, expected_revision, proposes)
+            .await
+    }
+
+    async fn read_stream(
+        &self,
+        stream_id: &str,
+        direction: Direction,
+        revision: Revision<u64>,
+        max_count: Option<u64>,
+        resolve_links: bool,
+    ) -> eyre::Result<ReadStreamCompleted<ReadStreaming>> {
+        self.pick()
+            .read_stream(stream_id, direction, revision, max_count, resolve_links)
+            .await
+    }
+
+    async fn read_stream_with_epochs(
+        &self,
+        stream_id: &str,
+        direction: Direction,
+        revision: Revision<u64>,
+        max_count: Option<u64>,
+        resolve_links: bool,
+        epochs: Epochs,
+    ) -> eyre::Result<ReadStreamCompleted<ReadStreaming>> {
+        self.pick()
+            .read_stream_with_epochs(
+                stream_id,
+                direction,
+                revision,
+                max_count,
+                resolve_links,
+                epochs,
+            )
+            .await
+    }
+
+    async fn read_all(
+        &self,
+        from: Revision<u64>,
+        direction: Direction,
+        max_count: Option<u64>,
+    ) -> eyre::Result<ReadStreaming> {
+        self.pick().read_all(from, direction, max_count).await
+    }
+
+    async fn read_projection(
+        &self,
+        stream_id: &str,
+        direction: Direction,
+        revision: Revision<u64>,
+        max_count: u64,
+        fields: Vec<String>,
+    ) -> eyre::Result<ReadStreamCompleted<ProjectionStreaming>> {
+        self.pick()
+            .read_projection(stream_id, direction, revision, max_count, fields)
+            .await
+    }
+
+    async fn subscribe_to_stream(
+        &self,
+        stream_id: &str,
+        start: Revision<u64>,
+    ) -> eyre::Result<SubscriptionStreaming> {
+        self.pick().subscribe_to_stream(stream_id, start).await
+    }
+
+    async fn subscribe_to_stream_in_batches(
+        &self,
+        stream_id: &str,
+        start: Revision<u64>,
+        batching: SubscriptionBatching,
+    ) -> eyre::Result<SubscriptionStreaming> {
+        self.pick()
+            .subscribe_to_stream_in_batches(stream_id, start, batching)
+            .await
+    }
+
+    async fn subscribe_to_stream_with_classes(
+        &self,
+        stream_id: &str,
+        start: Revision<u64>,
+        classes: Vec<String>,
+    ) -> eyre::Result<SubscriptionStreaming> {
+        self.pick()
+            .subscribe_to_stream_with_classes(stream_id, start, classes)
+            .await
+    }
+
+    async fn subscribe_to_process(
+        &self,
+        name: &str,
+        source_code: &str,
+        logs: bool,
+        restart: RestartPolicy,
+    ) -> eyre::Result<SubscriptionStreaming> {
+        self.pick()
+            .subscribe_to_process(name, source_code, logs, restart)
+            .await
+    }
+
+    async fn subscribe_persistent(
+        &self,
+        group: &str,
+        stream_id: &str,
+    ) -> eyre::Result<SubscriptionStreaming> {
+        self.pick().subscribe_persistent(group, stream_id).await
+    }
+
+    async fn ack(&self, group: &str, stream_id: &str, position: u64) -> eyre::Result<()> {
+        self.pick().ack(group, stream_id, position).await
+    }
+
+    async fn delete_stream(
+        &self,
+        stream_id: &str,
+        expected_revision: ExpectedRevision,
+        dry_run: bool,
+    ) -> eyre::Result<DeleteStreamCompleted> {
+        self.pick()
+            .delete_stream(stream_id, expected_revision, dry_run)
+            .await
+    }
+
+    async fn truncate_stream(
+        &self,
+        stream_id: &str,
+        before: u64,
+    ) -> eyre::Result<TruncateStreamCompleted> {
+        self.pick().truncate_stream(stream_id, before).await
+    }
+
+    async fn set_stream_metadata(
+        &self,
+        stream_id: &str,
+        metadata: StreamMetadata,
+    ) -> eyre::Result<SetStreamMetadataCompleted> {
+        self.pick().set_stream_metadata(stream_id, metadata).await
+    }
+
+    async fn get_stream_metadata(
+        &self,
+        stream_id: &str,
+    ) -> eyre::Result<ReadStreamCompleted<StreamMetadata>> {
+        self.pick().get_stream_metadata(stream_id).await
+    }
+
+    async fn latest_revision(&self, stream_id: &str) -> eyre::Result<Option<u64>> {
+        self.pick().latest_revision(stream_id).await
+    }
+
+    async fn list_programs(&self) -> eyre::Result<Vec<ProgramSummary>> {
+        self.pick().list_programs().await
+    }
+
+    async fn get_program(&self, id: u64) -> eyre::Result<Option<ProgramStats>> {
+        self.pick().get_program(id).await
+    }
+
+    async fn stop_program(&self, id: u64) -> eyre::Result<()> {
+        self.pick().stop_program(id).await
+    }
+
+    async fn set_log_filters(&self, directives: Vec<String>) -> eyre::Result<LogFiltersSet> {
+        self.pick().set_log_filters(directives).await
+    }
+
+    async fn maintain_index(&self, compact: bool) -> eyre::Result<IndexMaintained> {
+        self.pick().maintain_index(compact).await
+    }
+
+    async fn list_streams(
+        &self,
+        prefix: Option<String>,
+        offset: usize,
+        limit: usize,
+    ) -> eyre::Result<StreamPage> {
+        self.pick().list_streams(prefix, offset, limit).await
+    }
+
+    async fn copy_stream(
+        &self,
+        source: &str,
+        target: &str,
+        expected_revision: ExpectedRevision,
+        class_renames: HashMap<String, String>,
+    ) -> eyre::Result<CopyStreamCompleted> {
+        self.pick()
+            .copy_stream(source, target, expected_revision, class_renames)
+            .await
+    }
+
+    async fn copy_stream_with_mapper(
+        &self,
+        source: &str,
+        target: &str,
+        expected_revision: ExpectedRevision,
+        mapper: &str,
+        on_mapper_error: MapperErrorPolicy,
+    ) -> eyre::Result<CopyStreamCompleted> {
+        self.pick()
+            .copy_stream_with_mapper(source, target, expected_revision, mapper, on_mapper_error)
+            .await
+    }
+}
@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+/// How a [`crate::GrpcClient`] retries connecting to a node, both when it first connects and
+/// when a stream subscription loses its connection. The wait between attempts starts at
+/// `initial_delay` and grows by `multiplier` after each failed attempt, up to `max_delay`.
+/// Connecting always makes at least one attempt.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReconnectPolicy {
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    pub multiplier: f64,
+    pub max_attempts: u32,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(2),
+            multiplier: 2.0,
+            max_attempts: 10,
+        }
+    }
+}
+
+/// Keeps the stream subscriptions of a [`crate::GrpcClient`] from dying silently when nothing
+/// happens on them. The server sends a heartbeat every `interval`, and a subscription that hears
+/// nothing for `timeout` considers its connection lost, subscribing again following the
+/// [`ReconnectPolicy`]. `timeout` should leave room for a few missed heartbeats.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeepAlive {
+    pub interval: Duration,
+    pub timeout: Duration,
+}
+
+impl Default for KeepAlive {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(10),
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Connects once and lets subscriptions end when their connection is lost.
+    pub fn never() -> Self {
+        Self {
+            max_attempts: 0,
+            ..Self::default()
+        }
+    }
+
+    /// How long to wait after `failed` attempts failed in a row.
+    pub fn backoff(&self, failed: u32) -> Duration {
+        let exponent = failed.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let delay = self.initial_delay.as_secs_f64() * self.multiplier.max(1.0).powi(exponent);
+
+        Duration::from_secs_f64(delay.min(self.max_delay.as_secs_f64()))
+    }
+}
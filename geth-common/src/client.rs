@@ -1,20 +1,27 @@
 #![allow(async_fn_in_trait)]
 
-use crate::{Record, SubscriptionConfirmation};
+use crate::{ProgramLimit, Record, SubscriptionConfirmation};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SubscriptionNotification {
     Subscribed(String),
     Unsubscribed(String),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SubscriptionEvent {
     EventAppeared(Record),
+    /// Records of a batched subscription, in order. Never empty, see
+    /// [`crate::SubscriptionBatching`].
+    EventsAppeared(Vec<Record>),
     Confirmed(SubscriptionConfirmation),
     CaughtUp,
     Unsubscribed(UnsubscribeReason),
     Notification(SubscriptionNotification),
+    /// A line printed by a program, only sent to program subscriptions asking for logs.
+    ProgramLog {
+        line: String,
+    },
 }
 
 impl SubscriptionEvent {
@@ -27,8 +34,16 @@ impl SubscriptionEvent {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum UnsubscribeReason {
     User,
+    /// The server ended the subscription, e.g. the stream got deleted.
     Server,
+    /// The server is shutting down, subscribing again once it's back carries on.
+    Shutdown,
+    /// The program was stopped because it went over one of its limits.
+    LimitExceeded(ProgramLimit),
+    /// The subscription ended without the server saying why, e.g. the connection was lost.
+    /// Only produced on the client side, the server never sends it.
+    Disconnected,
 }
@@ -29,6 +29,18 @@ pub trait IteratorIO {
         Filter { func, inner: self }
     }
 
+    fn take_while<F>(self, func: F) -> TakeWhile<F, Self>
+    where
+        Self: Sized,
+        F: FnMut(&Self::Item) -> bool,
+    {
+        TakeWhile {
+            func,
+            inner: self,
+            done: false,
+        }
+    }
+
     fn last(mut self) -> io::Result<Option<Self::Item>>
     where
         Self: Sized,
@@ -110,6 +122,35 @@ where
     }
 }
 
+pub struct TakeWhile<F, I> {
+    func: F,
+    inner: I,
+    done: bool,
+}
+
+impl<F, I> IteratorIO for TakeWhile<F, I>
+where
+    I: IteratorIO,
+    F: FnMut(&I::Item) -> bool,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> io::Result<Option<Self::Item>> {
+        if self.done {
+            return Ok(None);
+        }
+
+        if let Some(item) = self.inner.next()? {
+            if (self.func)(&item) {
+                return Ok(Some(item));
+            }
+        }
+
+        self.done = true;
+        Ok(None)
+    }
+}
+
 pub struct Lift<I> {
     inner: I,
 }
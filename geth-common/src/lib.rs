@@ -2,17 +2,35 @@ use bytes::Bytes;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::any::type_name;
+use std::collections::HashMap;
 use std::fmt::Display;
+use std::time::Duration;
 use thiserror::Error;
 use uuid::Uuid;
 
 pub use client::{SubscriptionEvent, SubscriptionNotification, UnsubscribeReason};
 pub use io::{IteratorIO, IteratorIOExt};
+pub use redact::{VerboseOperation, VerboseReply};
 
 mod client;
 mod io;
-
-#[derive(Clone, Debug)]
+mod redact;
+
+#[cfg(test)]
+mod paging_tests;
+#[cfg(test)]
+mod propose_tests;
+#[cfg(test)]
+mod redact_tests;
+#[cfg(test)]
+mod retry_tests;
+#[cfg(test)]
+mod revision_tests;
+#[cfg(all(test, feature = "serde"))]
+mod serde_tests;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct EndPoint {
     pub host: String,
     pub port: u16,
@@ -36,19 +54,28 @@ pub struct OperationIn {
     pub operation: Operation,
 }
 
-#[derive(Clone)]
+/// `Debug` redacts event payloads, use [`Operation::verbose`] to log them in full.
+#[derive(Clone, Debug)]
 pub enum Operation {
     AppendStream(AppendStream),
     DeleteStream(DeleteStream),
     ReadStream(ReadStream),
+    ReadAll(ReadAll),
     Subscribe(Subscribe),
+    Ack(Ack),
     ListPrograms(ListPrograms),
     GetProgramStats(GetProgramStats),
     KillProgram(KillProgram),
     Unsubscribe,
 }
 
-#[derive(Debug)]
+impl Operation {
+    pub fn verbose(&self) -> VerboseOperation<'_> {
+        VerboseOperation(self)
+    }
+}
+
+/// `Debug` and `Display` redact event payloads, use [`Reply::verbose`] to log them in full.
 pub enum Reply {
     AppendStreamCompleted(AppendStreamCompleted),
     StreamRead(ReadStreamResponse),
@@ -61,6 +88,12 @@ pub enum Reply {
     Error(String),
 }
 
+impl Reply {
+    pub fn verbose(&self) -> VerboseReply<'_> {
+        VerboseReply(self)
+    }
+}
+
 pub struct OperationOut {
     pub correlation: Uuid,
     pub reply: Reply,
@@ -74,6 +107,55 @@ impl OperationOut {
             _ => false,
         }
     }
+
+    /// Tells whether the operation this reply answers is worth sending again. Replies that are
+    /// not failures are [`Retryability::NonRetriable`], there is nothing to retry.
+    pub fn retryability(&self) -> Retryability {
+        match &self.reply {
+            Reply::ServerDisconnected => Retryability::Retriable,
+
+            Reply::AppendStreamCompleted(AppendStreamCompleted::Error(e)) => match e {
+                AppendError::StreamDeleted => Retryability::Fatal,
+                AppendError::WrongExpectedRevision(_)
+                | AppendError::InvalidEvent(_)
+                | AppendError::DuplicateEvents => Retryability::NonRetriable,
+            },
+
+            Reply::DeleteStreamCompleted(DeleteStreamCompleted::Error(e)) => match e {
+                DeleteError::StreamDeleted => Retryability::Fatal,
+                // The same request goes through once sent to the leader.
+                DeleteError::NotLeaderException(_) => Retryability::Retriable,
+                DeleteError::WrongExpectedRevision(_) => Retryability::NonRetriable,
+            },
+
+            Reply::StreamRead(ReadStreamResponse::StreamDeleted) => Retryability::Fatal,
+
+            Reply::SubscriptionEvent(SubscriptionEvent::Unsubscribed(reason)) => match reason {
+                UnsubscribeReason::Shutdown | UnsubscribeReason::Disconnected => {
+                    Retryability::Retriable
+                }
+                UnsubscribeReason::User
+                | UnsubscribeReason::Server
+                | UnsubscribeReason::LimitExceeded(_) => Retryability::NonRetriable,
+            },
+
+            // The server rejected the request itself, sending it again yields the same error.
+            Reply::Error(_) => Retryability::NonRetriable,
+
+            _ => Retryability::NonRetriable,
+        }
+    }
+}
+
+/// How a client should react to a reply, see [`OperationOut::retryability`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Retryability {
+    /// Sending the operation again, possibly after reconnecting, can succeed.
+    Retriable,
+    /// The operation failed and would fail the same way if sent again as-is.
+    NonRetriable,
+    /// The target of the operation is gone for good, e.g. the stream was deleted.
+    Fatal,
 }
 
 #[derive(Clone)]
@@ -81,41 +163,218 @@ pub struct AppendStream {
     pub stream_name: String,
     pub events: Vec<Propose>,
     pub expected_revision: ExpectedRevision,
+    /// When false, the events are committed without being pushed to subscriptions, which have
+    /// to read the stream again to see them. Meant for bulk imports no one is tailing.
+    pub notify_subscribers: bool,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct DeleteStream {
     pub stream_name: String,
     pub expected_revision: ExpectedRevision,
+    /// Only checks whether the deletion would go through, the stream is left untouched.
+    pub dry_run: bool,
 }
 
-#[derive(Clone)]
+/// Drops every event of a stream prior to the `before` revision. The stream remains alive and
+/// appends continue from its current revision.
+#[derive(Clone, Debug)]
+pub struct TruncateStream {
+    pub stream_name: String,
+    pub before: u64,
+}
+
+/// Copies every event of `source_stream` to `target_stream`, server-side. Ids, content types and
+/// payloads are kept, revisions and positions are the ones of the target.
+#[derive(Clone, Debug)]
+pub struct CopyStream {
+    pub source_stream: String,
+    pub target_stream: String,
+    /// Checked against the target stream before copying anything.
+    pub expected_revision: ExpectedRevision,
+    /// Classes renamed on the way, events of other classes keep theirs.
+    pub class_renames: HashMap<String, String>,
+    /// Name of the server-side mapper each event goes through before being copied. The mapper
+    /// can turn an event into any number of events, none filtering it out. Class renames apply
+    /// to what the mapper returns.
+    pub mapper: Option<String>,
+    pub on_mapper_error: MapperErrorPolicy,
+}
+
+/// What a stream copy does when its mapper fails on an event.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MapperErrorPolicy {
+    /// Fails the copy, the batches before the event remain copied.
+    #[default]
+    Stop,
+    /// Logs the error and leaves the event out of the copy.
+    Skip,
+}
+
+#[derive(Clone, Debug)]
+pub struct SetStreamMetadata {
+    pub stream_name: String,
+    pub metadata: StreamMetadata,
+}
+
+/// Per-stream settings, persisted as events of the stream's metadata stream. The latest
+/// metadata set replaces the previous one entirely.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StreamMetadata {
+    /// Maximum number of events the stream keeps, older events are no longer readable.
+    pub max_count: Option<u64>,
+    /// Maximum age of the events the stream keeps, going by their commit time. Events committed
+    /// before commit times were stored never expire.
+    pub max_age_secs: Option<u64>,
+    /// Who may read, write and delete the stream, enforced on top of the ACLs the server is
+    /// configured with.
+    pub acl: Option<StreamAcl>,
+    /// User defined settings, the server doesn't interpret those.
+    pub custom: HashMap<String, String>,
+}
+
+impl StreamMetadata {
+    /// Commit time before which events are no longer readable as of `now`, if they expire.
+    pub fn expires_before(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let secs = i64::try_from(self.max_age_secs?).ok()?;
+
+        now.checked_sub_signed(chrono::TimeDelta::try_seconds(secs)?)
+    }
+}
+
+/// Principals allowed to perform each operation on a stream.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StreamAcl {
+    pub read: Vec<String>,
+    pub write: Vec<String>,
+    pub delete: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
 pub struct ReadStream {
     pub stream_name: String,
     pub direction: Direction,
     pub revision: Revision<u64>,
-    pub max_count: u64,
+    /// How many events are read at most, `None` reads until the end of the stream.
+    pub max_count: Option<u64>,
+    /// Replaces link events with the events they point to, see [`LinkTo`].
+    pub resolve_links: bool,
+    pub epochs: Epochs,
+}
+
+/// Reads the events of every stream in commit order, by log position.
+#[derive(Clone, Debug)]
+pub struct ReadAll {
+    /// Log position to start from, as found in [`Record::position`]. Any position works: a
+    /// forward read starts at the first event at or after it, a backward read at the last event
+    /// at or before it.
+    pub position: Revision<u64>,
+    pub direction: Direction,
+    /// How many events are read at most, `None` reads until the end of the log.
+    pub max_count: Option<u64>,
+}
+
+/// Which epochs of a stream a read returns. A stream deleted then appended to with
+/// [`ExpectedRevision::NoStream`] starts a new epoch, see [`Record::epoch`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Epochs {
+    /// Only the events appended since the stream was last recreated. Reading a deleted stream
+    /// fails with [`ReadStreamCompleted::StreamDeleted`].
+    #[default]
+    Current,
+    /// The events of the deleted epochs as well, oldest first when reading forward. A deleted
+    /// stream remains readable.
+    All,
 }
 
 #[derive(Clone)]
+pub struct ReadProjection {
+    pub stream_name: String,
+    pub direction: Direction,
+    pub revision: Revision<u64>,
+    pub max_count: u64,
+    pub fields: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
 pub enum Subscribe {
     ToProgram(SubscribeToProgram),
     ToStream(SubscribeToStream),
+    ToGroup(SubscribeToGroup),
+}
+
+/// Joins a persistent subscription. Its members share a checkpoint, stored in the database, so
+/// the subscription resumes from the first record they didn't [`Ack`] after a reconnection or a
+/// restart. Members compete for records: each record goes to a single member, and is handed to
+/// another one if that member leaves before acknowledging it. Records are therefore delivered at
+/// least once.
+#[derive(Clone, Debug)]
+pub struct SubscribeToGroup {
+    pub group: String,
+    pub stream_name: String,
+}
+
+/// Acknowledges a record of a persistent subscription, by its revision or by its log position
+/// when subscribed to `$all`.
+#[derive(Clone, Debug)]
+pub struct Ack {
+    pub group: String,
+    pub stream_name: String,
+    pub position: u64,
 }
 
 #[derive(Clone)]
 pub struct SubscribeToProgram {
     pub name: String,
     pub source: String,
+    /// Also streams what the program prints, as [`SubscriptionEvent::ProgramLog`] events.
+    pub logs: bool,
+    pub restart: RestartPolicy,
 }
 
-#[derive(Clone)]
+/// What happens when one of a program subscriptions fails. Subscriptions ending normally are
+/// never restarted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RestartPolicy {
+    #[default]
+    Never,
+    /// Re-establishes the subscription from the last event handed to the program, up to `max`
+    /// times, waiting `backoff` before each attempt.
+    OnError { max: u32, backoff: Duration },
+}
+
+#[derive(Clone, Debug)]
 pub struct SubscribeToStream {
     pub stream_name: String,
     pub start: Revision<u64>,
+    /// Delivers the records in batches instead of one at a time.
+    pub batching: Option<SubscriptionBatching>,
+    /// Only records of those classes are delivered, every record when empty.
+    pub class_filter: Vec<String>,
+    /// Has the server send a heartbeat that often, whether records are delivered or not.
+    pub heartbeat_interval: Option<Duration>,
 }
 
+/// Records of a batched subscription are delivered together, as
+/// [`SubscriptionEvent::EventsAppeared`], once `max_size` of them are waiting or `max_delay` after
+/// the first of them arrived, whichever comes first. Any other event is delivered after the
+/// records received before it, so `CaughtUp` still follows every record of the catch-up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SubscriptionBatching {
+    pub max_size: usize,
+    pub max_delay: Duration,
+}
+
+/// With the `serde` feature, serialized as an externally tagged enum: `"start"`, `"end"` or
+/// `{"revision":42}`.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum Revision<A> {
     Start,
     End,
@@ -150,12 +409,27 @@ impl<D: Display> Display for Revision<D> {
     }
 }
 
+/// With the `serde` feature, serialized as `"forward"` or `"backward"`.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum Direction {
     Forward,
     Backward,
 }
 
+impl Direction {
+    pub fn reverse(self) -> Self {
+        match self {
+            Direction::Forward => Direction::Backward,
+            Direction::Backward => Direction::Forward,
+        }
+    }
+}
+
 impl TryFrom<i32> for Direction {
     type Error = WrongDirectionError;
 
@@ -185,6 +459,7 @@ pub enum ContentType {
     Unknown = 0,
     Json = 1,
     Binary = 2,
+    Protobuf = 3,
 }
 
 impl TryFrom<i32> for ContentType {
@@ -195,17 +470,23 @@ impl TryFrom<i32> for ContentType {
             0 => Ok(ContentType::Unknown),
             1 => Ok(ContentType::Json),
             2 => Ok(ContentType::Binary),
+            3 => Ok(ContentType::Protobuf),
             x => eyre::bail!("unknown content type: {}", x),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+/// An event to append to a stream. Setting `id` to [`Uuid::nil`] lets the server assign a
+/// time-ordered (v7) id to the event. Explicitly provided ids are used as-is and take part in
+/// idempotency checks, nil ids are always treated as new writes.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Propose {
     pub id: Uuid,
     pub content_type: ContentType,
     pub class: String,
     pub data: Bytes,
+    /// Opaque user metadata stored along with the event, empty when there is none.
+    pub metadata: Bytes,
 }
 
 impl Propose {
@@ -220,11 +501,170 @@ impl Propose {
             content_type: ContentType::Json,
             class: type_name::<A>().to_string(),
             data,
+            metadata: Bytes::new(),
         })
     }
+
+    /// JSON event of the given class, with `metadata` serialized as JSON too.
+    pub fn with_metadata<A, M>(
+        value: &A,
+        class: impl Into<String>,
+        metadata: &M,
+    ) -> eyre::Result<Self>
+    where
+        A: Serialize,
+        M: Serialize,
+    {
+        Ok(Self {
+            id: Uuid::new_v4(),
+            content_type: ContentType::Json,
+            class: class.into(),
+            data: Bytes::from(serde_json::to_vec(value)?),
+            metadata: Bytes::from(serde_json::to_vec(metadata)?),
+        })
+    }
+
+    pub fn binary(class: impl Into<String>, data: Bytes) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            content_type: ContentType::Binary,
+            class: class.into(),
+            data,
+            metadata: Bytes::new(),
+        }
+    }
+
+    /// Builds a proposal of the given class whose content type matches its payload, see
+    /// [`ProposeBuilder`].
+    pub fn builder(class: impl Into<String>) -> ProposeBuilder {
+        ProposeBuilder {
+            id: Uuid::new_v4(),
+            class: class.into(),
+            payload: None,
+            metadata: Bytes::new(),
+        }
+    }
 }
 
+/// Builds a [`Propose`] after checking its payload is what its content type says: JSON payloads
+/// must parse. Building a [`Propose`] directly skips those checks.
 #[derive(Debug, Clone)]
+pub struct ProposeBuilder {
+    id: Uuid,
+    class: String,
+    payload: Option<Result<(ContentType, Bytes), ProposeError>>,
+    metadata: Bytes,
+}
+
+impl ProposeBuilder {
+    pub fn id(self, id: Uuid) -> Self {
+        Self { id, ..self }
+    }
+
+    /// Serializes `value` as the JSON payload.
+    pub fn json<A>(self, value: &A) -> Self
+    where
+        A: Serialize,
+    {
+        let payload = serde_json::to_vec(value)
+            .map(|data| (ContentType::Json, Bytes::from(data)))
+            .map_err(|e| ProposeError::InvalidJson(e.to_string()));
+
+        Self {
+            payload: Some(payload),
+            ..self
+        }
+    }
+
+    /// Payload already encoded as JSON, checked to parse when building.
+    pub fn json_bytes(self, data: impl Into<Bytes>) -> Self {
+        let data = data.into();
+        let payload = match serde_json::from_slice::<serde::de::IgnoredAny>(&data) {
+            Ok(_) => Ok((ContentType::Json, data)),
+            Err(e) => Err(ProposeError::InvalidJson(e.to_string())),
+        };
+
+        Self {
+            payload: Some(payload),
+            ..self
+        }
+    }
+
+    pub fn binary(self, data: impl Into<Bytes>) -> Self {
+        Self {
+            payload: Some(Ok((ContentType::Binary, data.into()))),
+            ..self
+        }
+    }
+
+    pub fn metadata(self, metadata: impl Into<Bytes>) -> Self {
+        Self {
+            metadata: metadata.into(),
+            ..self
+        }
+    }
+
+    pub fn build(self) -> Result<Propose, ProposeError> {
+        let (content_type, data) = self.payload.unwrap_or(Err(ProposeError::MissingPayload))?;
+
+        Ok(Propose {
+            id: self.id,
+            content_type,
+            class: self.class,
+            data,
+            metadata: self.metadata,
+        })
+    }
+}
+
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
+pub enum ProposeError {
+    MissingPayload,
+    InvalidJson(String),
+}
+
+impl Display for ProposeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProposeError::MissingPayload => write!(f, "proposed event has no payload"),
+            ProposeError::InvalidJson(e) => write!(f, "payload is not valid JSON: {e}"),
+        }
+    }
+}
+
+/// Payload of a link event, which points at an event of another stream instead of copying it.
+/// When reading a stream with link resolution on, links are replaced by their target. Links to
+/// events that are no longer readable (deleted or truncated streams) are returned as-is. Links
+/// are only followed once: a link to a link yields the second link.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkTo {
+    pub target_stream: String,
+    pub target_revision: u64,
+}
+
+impl LinkTo {
+    pub const CLASS: &'static str = "$>";
+
+    pub fn propose(&self) -> Propose {
+        Propose {
+            id: Uuid::new_v4(),
+            content_type: ContentType::Json,
+            class: Self::CLASS.to_string(),
+            data: serde_json::to_vec(self).unwrap().into(),
+            metadata: Default::default(),
+        }
+    }
+}
+
+/// Link event a record was resolved through.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordLink {
+    pub stream_name: String,
+    pub revision: u64,
+    pub position: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Record {
     pub id: Uuid,
     pub content_type: ContentType,
@@ -233,6 +673,16 @@ pub struct Record {
     pub position: u64,
     pub revision: u64,
     pub data: Bytes,
+    /// User metadata the event was appended with.
+    pub metadata: Bytes,
+    /// Commit time assigned by the server, with millisecond precision. Records written before
+    /// the server recorded it are dated from Unix epoch.
+    pub created: DateTime<Utc>,
+    /// Set when the record was read through a link event of the stream being read.
+    pub link: Option<RecordLink>,
+    /// Number of times the stream was deleted before the record was appended. Revisions keep
+    /// increasing across epochs. Reads of `$all` don't look it up and report 0.
+    pub epoch: u64,
 }
 
 impl Record {
@@ -252,17 +702,54 @@ impl Record {
     }
 }
 
+/// Computes where to resume reading after `last` was seen when paging in the given direction.
+/// Reads are inclusive of their start revision, so the returned revision skips `last`. `None`
+/// means there is nothing left to read in that direction.
+pub fn paging_next(last: &Record, dir: Direction) -> Option<Revision<u64>> {
+    // A resolved link stands for the link event, which is what the read stream holds.
+    let revision = last.link.as_ref().map_or(last.revision, |l| l.revision);
+
+    match dir {
+        Direction::Forward => revision.checked_add(1).map(Revision::Revision),
+        Direction::Backward => revision.checked_sub(1).map(Revision::Revision),
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct PyroRecord<A> {
     pub class: String,
     pub event_revision: u64,
+    #[serde(with = "uuid_as_string")]
     pub id: Uuid,
     pub position: u64,
     pub stream_name: String,
     pub payload: A,
 }
 
+/// Hyphenated like the `uuid` crate does, without requiring its `serde` feature.
+mod uuid_as_string {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use uuid::Uuid;
+
+    pub fn serialize<S: Serializer>(id: &Uuid, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(id)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Uuid, D::Error> {
+        let id = String::deserialize(deserializer)?;
+
+        Uuid::parse_str(&id).map_err(D::Error::custom)
+    }
+}
+
+/// With the `serde` feature, serialized as an externally tagged enum: `"no_stream"`, `"any"`,
+/// `"stream_exists"` or `{"revision":42}`.
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum ExpectedRevision {
     Revision(u64),
     NoStream,
@@ -292,7 +779,7 @@ impl Display for ExpectedRevision {
     }
 }
 
-#[derive(Error, Debug, Copy, Clone)]
+#[derive(Error, Debug, Copy, Clone, PartialEq, Eq)]
 pub struct WrongExpectedRevisionError {
     pub expected: ExpectedRevision,
     pub current: ExpectedRevision,
@@ -308,20 +795,72 @@ impl Display for WrongExpectedRevisionError {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+impl WrongExpectedRevisionError {
+    /// Tells what the conflict means for the caller, e.g. whether rebasing on the events written
+    /// in the meantime makes sense.
+    pub fn classify(&self) -> RevisionConflict {
+        match (self.expected, self.current) {
+            (_, ExpectedRevision::NoStream) => RevisionConflict::StreamNotFound,
+
+            (ExpectedRevision::NoStream, ExpectedRevision::Revision(current)) => {
+                RevisionConflict::StreamAlreadyExists { current }
+            }
+
+            (ExpectedRevision::Revision(expected), ExpectedRevision::Revision(current))
+                if current > expected =>
+            {
+                RevisionConflict::ConcurrentAppend {
+                    expected,
+                    behind_by: current - expected,
+                }
+            }
+
+            (ExpectedRevision::Revision(expected), ExpectedRevision::Revision(current)) => {
+                RevisionConflict::ExpectedAhead { expected, current }
+            }
+
+            _ => RevisionConflict::Other,
+        }
+    }
+}
+
+/// What a [`WrongExpectedRevisionError`] means, see [`WrongExpectedRevisionError::classify`].
+/// Revisions are the ones of the last event of the stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RevisionConflict {
+    /// The write expected the stream to exist but it doesn't, or no longer does.
+    StreamNotFound,
+    /// The write expected no stream but the stream has events up to `current`.
+    StreamAlreadyExists { current: u64 },
+    /// `behind_by` events were written after `expected`, the caller can read them and retry.
+    ConcurrentAppend { expected: u64, behind_by: u64 },
+    /// The write expected a revision the stream hasn't reached, retrying won't help.
+    ExpectedAhead { expected: u64, current: u64 },
+    /// A pair the server never reports, e.g. one built by hand.
+    Other,
+}
+
+#[derive(Clone, Debug)]
 pub enum AppendCompleted {
     Success(WriteResult),
     Error(WrongExpectedRevisionError),
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
 pub struct WriteResult {
+    /// Either the revision to expect on the next write or `NoStream`, e.g. when the stream
+    /// metadata was set before anything was appended. Never `Any` or `StreamExists`.
     pub next_expected_version: ExpectedRevision,
     pub position: u64,
     pub next_logical_position: u64,
+    /// Ids of the written events, in the order they were proposed. It includes ids assigned by
+    /// the server.
+    pub event_ids: Vec<Uuid>,
+    /// Commit time assigned by the server to the written events, with millisecond precision.
+    pub created: DateTime<Utc>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum AppendStreamCompleted {
     Success(WriteResult),
     Error(AppendError),
@@ -343,12 +882,49 @@ impl AppendStreamCompleted {
 
         eyre::bail!("append failed")
     }
+
+    pub fn is_success(&self) -> bool {
+        matches!(self, Self::Success(_))
+    }
+
+    pub fn is_error(&self) -> bool {
+        matches!(self, Self::Error(_))
+    }
+
+    pub fn into_result(self) -> Result<WriteResult, AppendError> {
+        match self {
+            Self::Success(r) => Ok(r),
+            Self::Error(e) => Err(e),
+        }
+    }
 }
 
-#[derive(Clone, Copy, Debug)]
+/// Outcome of an append that also subscribes to the events written after it, see
+/// `Client::append_and_subscribe`.
+#[derive(Debug)]
+pub enum AppendAndSubscribeCompleted<A> {
+    Success(WriteResult, A),
+    Error(AppendError),
+}
+
+impl<A> AppendAndSubscribeCompleted<A> {
+    pub fn success(self) -> eyre::Result<(WriteResult, A)> {
+        if let Self::Success(r, s) = self {
+            return Ok((r, s));
+        }
+
+        eyre::bail!("append failed")
+    }
+}
+
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
 pub enum AppendError {
     WrongExpectedRevision(WrongExpectedRevisionError),
     StreamDeleted,
+    InvalidEvent(InvalidEventError),
+    /// Some of the events were already appended, but not as the whole last append of the stream.
+    /// Retrying the exact same append is fine, sending part of it again is not.
+    DuplicateEvents,
 }
 
 impl Display for AppendError {
@@ -363,10 +939,34 @@ impl Display for AppendError {
             }
 
             AppendError::StreamDeleted => write!(f, "stream deleted"),
+            AppendError::InvalidEvent(e) => write!(f, "{e}"),
+            AppendError::DuplicateEvents => {
+                write!(f, "some of the events were already appended")
+            }
         }
     }
 }
 
+/// An event of an append was rejected by the server's schema validation, or because its class
+/// is reserved to the server. The whole append was rejected.
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
+pub struct InvalidEventError {
+    /// Position of the offending event in the append.
+    pub index: usize,
+    pub class: String,
+    pub reason: String,
+}
+
+impl Display for InvalidEventError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "event {} of class '{}' is invalid: {}",
+            self.index, self.class, self.reason
+        )
+    }
+}
+
 pub enum ReadStreamCompleted<A> {
     StreamDeleted,
     Success(A),
@@ -387,26 +987,54 @@ impl<A> ReadStreamCompleted<A> {
 
 #[derive(Debug)]
 pub enum ReadStreamResponse {
-    EndOfStream,
+    /// Last message of a read. `tail_revision` is the last revision of the stream when the read
+    /// started, `None` if the stream was empty. Comparing it with the last record received tells
+    /// if the read stopped because of its `max_count` or because it reached the end of the stream.
+    EndOfStream {
+        tail_revision: Option<u64>,
+    },
     EventAppeared(Record),
     StreamDeleted,
 }
 
+/// A record reduced to the fields that were selected by a [`ReadProjection`]. `values` follows
+/// the same order as the requested fields, a missing field is represented by `null`.
+#[derive(Debug, Clone)]
+pub struct ProjectedRecord {
+    pub id: Uuid,
+    pub stream_name: String,
+    pub position: u64,
+    pub revision: u64,
+    pub values: Vec<serde_json::Value>,
+}
+
+#[derive(Debug)]
+pub enum ReadProjectionResponse {
+    EndOfStream,
+    EventAppeared(ProjectedRecord),
+}
+
 #[derive(Debug)]
 pub enum ReadError {
     StreamDeleted,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SubscriptionConfirmation {
-    StreamName(String),
+    StreamName {
+        stream_name: String,
+        /// Revision of the first event the subscription can deliver, which differs from the
+        /// requested one when subscribing from the end or to a truncated stream. `None` when
+        /// not known, like for `$all` or live only subscriptions.
+        start_revision: Option<u64>,
+    },
     ProcessId(u64),
 }
 
 impl SubscriptionConfirmation {
     pub fn try_into_process_id(self) -> eyre::Result<u64> {
         match self {
-            SubscriptionConfirmation::StreamName(_) => {
+            SubscriptionConfirmation::StreamName { .. } => {
                 eyre::bail!("unexpected a program confirmation but got a stream instead")
             }
             SubscriptionConfirmation::ProcessId(id) => Ok(id),
@@ -422,9 +1050,12 @@ impl Display for SubscriptionError {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum DeleteStreamCompleted {
     Success(WriteResult),
+    /// A dry run found the deletion would succeed. Holds the current revision of the stream,
+    /// the one that would be tombstoned.
+    DryRun(ExpectedRevision),
     Error(DeleteError),
 }
 
@@ -436,9 +1067,40 @@ impl DeleteStreamCompleted {
 
         eyre::bail!("stream deletion failed")
     }
+
+    pub fn is_success(&self) -> bool {
+        matches!(self, Self::Success(_))
+    }
+
+    pub fn is_error(&self) -> bool {
+        matches!(self, Self::Error(_))
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        matches!(self, Self::DryRun(_))
+    }
+
+    /// Returns the current revision of the stream when a dry run found the deletion would
+    /// succeed.
+    pub fn dry_run(self) -> eyre::Result<ExpectedRevision> {
+        if let Self::DryRun(r) = self {
+            return Ok(r);
+        }
+
+        eyre::bail!("stream deletion dry run failed")
+    }
+
+    /// Dry runs have no write result and come back as `Ok(None)`.
+    pub fn into_result(self) -> Result<Option<WriteResult>, DeleteError> {
+        match self {
+            Self::Success(r) => Ok(Some(r)),
+            Self::DryRun(_) => Ok(None),
+            Self::Error(e) => Err(e),
+        }
+    }
 }
 
-#[derive(Debug)]
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
 pub enum DeleteError {
     StreamDeleted,
     WrongExpectedRevision(WrongExpectedRevisionError),
@@ -473,6 +1135,162 @@ impl Display for DeleteError {
     }
 }
 
+#[derive(Debug)]
+pub enum TruncateStreamCompleted {
+    Success(WriteResult),
+    Error(TruncateError),
+}
+
+impl TruncateStreamCompleted {
+    pub fn success(self) -> eyre::Result<WriteResult> {
+        if let Self::Success(r) = self {
+            return Ok(r);
+        }
+
+        eyre::bail!("stream truncation failed")
+    }
+
+    pub fn is_success(&self) -> bool {
+        matches!(self, Self::Success(_))
+    }
+
+    pub fn is_error(&self) -> bool {
+        matches!(self, Self::Error(_))
+    }
+
+    pub fn into_result(self) -> Result<WriteResult, TruncateError> {
+        match self {
+            Self::Success(r) => Ok(r),
+            Self::Error(e) => Err(e),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SetStreamMetadataCompleted {
+    Success(WriteResult),
+    Error(SetStreamMetadataError),
+}
+
+impl SetStreamMetadataCompleted {
+    pub fn success(self) -> eyre::Result<WriteResult> {
+        if let Self::Success(r) = self {
+            return Ok(r);
+        }
+
+        eyre::bail!("setting stream metadata failed")
+    }
+
+    pub fn is_success(&self) -> bool {
+        matches!(self, Self::Success(_))
+    }
+
+    pub fn is_error(&self) -> bool {
+        matches!(self, Self::Error(_))
+    }
+
+    pub fn into_result(self) -> Result<WriteResult, SetStreamMetadataError> {
+        match self {
+            Self::Success(r) => Ok(r),
+            Self::Error(e) => Err(e),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum SetStreamMetadataError {
+    StreamDeleted,
+}
+
+impl Display for SetStreamMetadataError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SetStreamMetadataError::StreamDeleted => write!(f, "stream deleted"),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum TruncateError {
+    StreamDeleted,
+    StreamNotFound,
+}
+
+impl Display for TruncateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TruncateError::StreamDeleted => write!(f, "stream deleted"),
+            TruncateError::StreamNotFound => write!(f, "stream not found"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum CopyStreamCompleted {
+    Success(StreamCopied),
+    Error(CopyError),
+}
+
+impl CopyStreamCompleted {
+    pub fn success(self) -> eyre::Result<StreamCopied> {
+        if let Self::Success(r) = self {
+            return Ok(r);
+        }
+
+        eyre::bail!("stream copy failed")
+    }
+
+    pub fn is_success(&self) -> bool {
+        matches!(self, Self::Success(_))
+    }
+
+    pub fn is_error(&self) -> bool {
+        matches!(self, Self::Error(_))
+    }
+
+    pub fn into_result(self) -> Result<StreamCopied, CopyError> {
+        match self {
+            Self::Success(r) => Ok(r),
+            Self::Error(e) => Err(e),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct StreamCopied {
+    /// Number of events written to the target stream.
+    pub copied: u64,
+    /// Number of source events left out because the mapper failed on them, see
+    /// [`MapperErrorPolicy::Skip`].
+    pub skipped: u64,
+    /// Same as [`WriteResult::next_expected_version`], for the target stream once copied to.
+    /// [`ExpectedRevision::NoStream`] when nothing was copied to a stream that doesn't exist.
+    pub next_expected_version: ExpectedRevision,
+}
+
+/// Events are copied in batches, each one being a separate append to the target stream. When
+/// an append fails, the batches before it remain copied.
+#[derive(Error, Clone, Debug)]
+pub enum CopyError {
+    SourceDeleted,
+    Append(AppendError),
+    UnknownMapper(String),
+    MapperFailed { revision: u64, reason: String },
+}
+
+impl Display for CopyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CopyError::SourceDeleted => write!(f, "source stream deleted"),
+            CopyError::Append(e) => write!(f, "append to the target stream failed: {e}"),
+            CopyError::UnknownMapper(name) => write!(f, "unknown mapper '{name}'"),
+            CopyError::MapperFailed { revision, reason } => {
+                write!(f, "mapper failed on revision {revision}: {reason}")
+            }
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct ListPrograms {}
 
@@ -492,6 +1310,7 @@ pub struct ProgramListed {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ProgramSummary {
     pub id: u64,
     pub name: String,
@@ -509,6 +1328,103 @@ pub enum ProgramKillError {
     NotExists,
 }
 
+/// Replaces the server log filters, using the `tracing` directives syntax, e.g.
+/// `geth_engine=trace`.
+#[derive(Clone, Debug)]
+pub struct SetLogFilters {
+    pub directives: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+pub enum LogFiltersSet {
+    Success,
+    /// A directive couldn't be parsed, the filters in use are left unchanged.
+    InvalidDirective(String),
+}
+
+/// Forces index maintenance instead of waiting for it to happen on its own: the entries held in
+/// memory are flushed to disk and, with `compact`, every sstable is merged into a single one.
+#[derive(Clone, Copy, Debug)]
+pub struct MaintainIndex {
+    pub compact: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct IndexMaintained {
+    /// Number of entries moved from memory to disk.
+    pub flushed: u64,
+    pub ss_tables_before: u64,
+    pub ss_tables_after: u64,
+}
+
+/// Lists the names of the streams in the database, in lexicographic order. Deleted streams are
+/// not listed.
+#[derive(Clone, Debug)]
+pub struct ListStreams {
+    pub prefix: Option<String>,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamPage {
+    pub streams: Vec<String>,
+    /// Number of streams matching the prefix, across all pages.
+    pub total: usize,
+}
+
+/// Synthetic stream announcing stream creations and deletions as [`StreamFeedEvent`]s. Nothing
+/// is persisted: the feed is live only and its revisions start over when the server restarts.
+pub const STREAMS_FEED: &str = "$streams";
+
+/// Event of the [`STREAMS_FEED`]. A deleted stream can't be written to again, so no
+/// `StreamCreated` ever follows the `StreamDeleted` of a stream.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StreamFeedEvent {
+    StreamCreated { name: String },
+    StreamDeleted { name: String },
+}
+
+#[derive(Serialize, Deserialize)]
+struct StreamFeedPayload {
+    name: String,
+}
+
+impl StreamFeedEvent {
+    pub fn class(&self) -> &'static str {
+        match self {
+            StreamFeedEvent::StreamCreated { .. } => "StreamCreated",
+            StreamFeedEvent::StreamDeleted { .. } => "StreamDeleted",
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            StreamFeedEvent::StreamCreated { name } | StreamFeedEvent::StreamDeleted { name } => {
+                name
+            }
+        }
+    }
+
+    pub fn data(&self) -> Bytes {
+        let payload = StreamFeedPayload {
+            name: self.name().to_string(),
+        };
+
+        serde_json::to_vec(&payload).unwrap().into()
+    }
+
+    pub fn from_record(record: &Record) -> eyre::Result<Self> {
+        let StreamFeedPayload { name } = record.as_value()?;
+
+        match record.class.as_str() {
+            "StreamCreated" => Ok(StreamFeedEvent::StreamCreated { name }),
+            "StreamDeleted" => Ok(StreamFeedEvent::StreamDeleted { name }),
+            class => eyre::bail!("unknown '{}' event class '{}'", STREAMS_FEED, class),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct ProgramStats {
     pub id: u64,
@@ -517,6 +1433,48 @@ pub struct ProgramStats {
     pub subscriptions: Vec<String>,
     pub pushed_events: usize,
     pub started: DateTime<Utc>,
+    pub limits: ProgramLimits,
+    /// How many times the program subscriptions were restarted after an error.
+    pub restarts: usize,
+}
+
+/// Resources a program can use before the server stops it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProgramLimits {
+    /// Events a program can emit within a second.
+    pub max_events_per_sec: u64,
+    /// Streams a program can be subscribed to at the same time.
+    pub max_subscriptions: usize,
+    /// Emitted events waiting to be pushed to the program subscriber.
+    pub max_output_buffer: usize,
+}
+
+impl Default for ProgramLimits {
+    fn default() -> Self {
+        Self {
+            max_events_per_sec: 10_000,
+            max_subscriptions: 32,
+            max_output_buffer: 10_000,
+        }
+    }
+}
+
+/// The limit a program exceeded, along with its configured value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProgramLimit {
+    EventsPerSecond(u64),
+    Subscriptions(usize),
+    OutputBuffer(usize),
+}
+
+impl Display for ProgramLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProgramLimit::EventsPerSecond(n) => write!(f, "more than {n} events per second"),
+            ProgramLimit::Subscriptions(n) => write!(f, "more than {n} subscriptions"),
+            ProgramLimit::OutputBuffer(n) => write!(f, "more than {n} buffered events"),
+        }
+    }
 }
 
 #[derive(Debug)]
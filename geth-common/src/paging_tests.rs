@@ -0,0 +1,78 @@
+use bytes::Bytes;
+use uuid::Uuid;
+
+use crate::{paging_next, ContentType, Direction, Record, RecordLink, Revision};
+
+fn record(revision: u64) -> Record {
+    Record {
+        id: Uuid::new_v4(),
+        content_type: ContentType::Binary,
+        class: "foo".to_string(),
+        stream_name: "bar".to_string(),
+        position: 0,
+        revision,
+        data: Bytes::new(),
+        metadata: Default::default(),
+        created: Default::default(),
+        link: None,
+        epoch: 0,
+    }
+}
+
+#[test]
+fn test_direction_reverse() {
+    assert_eq!(Direction::Backward, Direction::Forward.reverse());
+    assert_eq!(Direction::Forward, Direction::Backward.reverse());
+}
+
+#[test]
+fn test_paging_forward_skips_last_seen() {
+    assert_eq!(
+        Some(Revision::Revision(1)),
+        paging_next(&record(0), Direction::Forward)
+    );
+
+    assert_eq!(
+        Some(Revision::Revision(43)),
+        paging_next(&record(42), Direction::Forward)
+    );
+}
+
+#[test]
+fn test_paging_backward_skips_last_seen() {
+    assert_eq!(
+        Some(Revision::Revision(41)),
+        paging_next(&record(42), Direction::Backward)
+    );
+}
+
+#[test]
+fn test_paging_stops_at_stream_boundaries() {
+    assert_eq!(None, paging_next(&record(0), Direction::Backward));
+    assert_eq!(None, paging_next(&record(u64::MAX), Direction::Forward));
+}
+
+#[test]
+fn test_paging_after_reversing_direction() {
+    let last = record(10);
+
+    assert_eq!(
+        Some(Revision::Revision(9)),
+        paging_next(&last, Direction::Forward.reverse())
+    );
+}
+
+#[test]
+fn test_paging_resolved_link_uses_link_revision() {
+    let mut last = record(3);
+    last.link = Some(RecordLink {
+        stream_name: "index".to_string(),
+        revision: 7,
+        position: 0,
+    });
+
+    assert_eq!(
+        Some(Revision::Revision(8)),
+        paging_next(&last, Direction::Forward)
+    );
+}
@@ -0,0 +1,101 @@
+use bytes::Bytes;
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{ContentType, Propose, ProposeError};
+
+#[test]
+fn test_json_payload_sets_json_content_type() {
+    let id = Uuid::new_v4();
+    let propose = Propose::builder("user-created")
+        .id(id)
+        .json(&json!({ "name": "foo" }))
+        .build()
+        .unwrap();
+
+    assert_eq!(id, propose.id);
+    assert_eq!(ContentType::Json, propose.content_type);
+    assert_eq!("user-created", propose.class);
+    assert_eq!(br#"{"name":"foo"}"#.as_slice(), propose.data.as_ref());
+}
+
+#[test]
+fn test_json_bytes_must_parse() {
+    let propose = Propose::builder("user-created")
+        .json_bytes(&b"[1, 2, 3]"[..])
+        .build()
+        .unwrap();
+
+    assert_eq!(ContentType::Json, propose.content_type);
+
+    let error = Propose::builder("user-created")
+        .json_bytes(&b"\x00\x01not json"[..])
+        .build()
+        .unwrap_err();
+
+    assert!(matches!(error, ProposeError::InvalidJson(_)));
+}
+
+#[test]
+fn test_binary_payload_is_not_checked() {
+    let propose = Propose::builder("snapshot")
+        .binary(&b"\x00\x01not json"[..])
+        .build()
+        .unwrap();
+
+    assert_eq!(ContentType::Binary, propose.content_type);
+}
+
+#[test]
+fn test_payload_is_required() {
+    assert_eq!(
+        ProposeError::MissingPayload,
+        Propose::builder("user-created").build().unwrap_err()
+    );
+}
+
+#[test]
+fn test_with_metadata_serializes_both_as_json() {
+    let propose = Propose::with_metadata(
+        &json!({ "order": 42 }),
+        "OrderCreated",
+        &json!({ "correlation": "abc" }),
+    )
+    .unwrap();
+
+    assert_eq!(ContentType::Json, propose.content_type);
+    assert_eq!("OrderCreated", propose.class);
+    assert_eq!(br#"{"order":42}"#.as_slice(), propose.data.as_ref());
+    assert_eq!(
+        br#"{"correlation":"abc"}"#.as_slice(),
+        propose.metadata.as_ref()
+    );
+}
+
+#[test]
+fn test_binary_sets_binary_content_type() {
+    let propose = Propose::binary("thumbnail", Bytes::from_static(&[0xFF, 0xD8]));
+
+    assert_eq!(ContentType::Binary, propose.content_type);
+    assert_eq!("thumbnail", propose.class);
+    assert_eq!(&[0xFF, 0xD8], propose.data.as_ref());
+    assert!(propose.metadata.is_empty());
+}
+
+#[test]
+fn test_content_type_roundtrips_through_i32() {
+    for content_type in [
+        ContentType::Unknown,
+        ContentType::Json,
+        ContentType::Binary,
+        ContentType::Protobuf,
+    ] {
+        assert_eq!(
+            content_type,
+            ContentType::try_from(content_type as i32).unwrap()
+        );
+    }
+
+    assert_eq!(3, ContentType::Protobuf as i32);
+    assert!(ContentType::try_from(4).is_err());
+}
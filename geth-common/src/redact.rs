@@ -0,0 +1,277 @@
+use std::fmt::{self, Debug, Display, Formatter};
+
+use crate::{
+    AppendStream, AppendStreamCompleted, DeleteStreamCompleted, Operation, OperationIn,
+    OperationOut, ProgramKilled, ProgramObtained, Propose, ReadStreamResponse, Record, Reply,
+    SubscribeToProgram, SubscriptionEvent,
+};
+
+struct Size(usize);
+
+impl Debug for Size {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "<{} bytes>", self.0)
+    }
+}
+
+struct RedactedPropose<'a>(&'a Propose);
+
+impl Debug for RedactedPropose<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Propose")
+            .field("id", &self.0.id)
+            .field("content_type", &self.0.content_type)
+            .field("class", &self.0.class)
+            .field("data", &Size(self.0.data.len()))
+            .field("metadata", &Size(self.0.metadata.len()))
+            .finish()
+    }
+}
+
+struct RedactedRecord<'a>(&'a Record);
+
+impl Debug for RedactedRecord<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Record")
+            .field("id", &self.0.id)
+            .field("content_type", &self.0.content_type)
+            .field("class", &self.0.class)
+            .field("stream_name", &self.0.stream_name)
+            .field("position", &self.0.position)
+            .field("revision", &self.0.revision)
+            .field("created", &self.0.created)
+            .field("data", &Size(self.0.data.len()))
+            .field("metadata", &Size(self.0.metadata.len()))
+            .finish()
+    }
+}
+
+impl Debug for AppendStream {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let events = self.events.iter().map(RedactedPropose).collect::<Vec<_>>();
+
+        f.debug_struct("AppendStream")
+            .field("stream_name", &self.stream_name)
+            .field("expected_revision", &self.expected_revision)
+            .field("events", &events)
+            .field("notify_subscribers", &self.notify_subscribers)
+            .finish()
+    }
+}
+
+impl Debug for SubscribeToProgram {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SubscribeToProgram")
+            .field("name", &self.name)
+            .field("source", &Size(self.source.len()))
+            .field("logs", &self.logs)
+            .field("restart", &self.restart)
+            .finish()
+    }
+}
+
+impl Debug for Reply {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Reply::StreamRead(ReadStreamResponse::EventAppeared(record)) => f
+                .debug_tuple("StreamRead")
+                .field(&RedactedEventAppeared(record))
+                .finish(),
+
+            Reply::SubscriptionEvent(SubscriptionEvent::EventAppeared(record)) => f
+                .debug_tuple("SubscriptionEvent")
+                .field(&RedactedEventAppeared(record))
+                .finish(),
+
+            Reply::SubscriptionEvent(SubscriptionEvent::EventsAppeared(records)) => {
+                let records = records.iter().map(RedactedRecord).collect::<Vec<_>>();
+
+                f.debug_tuple("SubscriptionEvent")
+                    .field(&format_args!("EventsAppeared({records:?})"))
+                    .finish()
+            }
+
+            Reply::ProgramObtained(ProgramObtained::Success(stats)) => f
+                .debug_tuple("ProgramObtained")
+                .field(&format_args!(
+                    "Success(ProgramStats {{ id: {}, name: {:?}, source_code: {:?}, subscriptions: {:?}, pushed_events: {}, started: {:?}, limits: {:?}, restarts: {} }})",
+                    stats.id,
+                    stats.name,
+                    Size(stats.source_code.len()),
+                    stats.subscriptions,
+                    stats.pushed_events,
+                    stats.started,
+                    stats.limits,
+                    stats.restarts,
+                ))
+                .finish(),
+
+            other => VerboseReply(other).fmt(f),
+        }
+    }
+}
+
+struct RedactedEventAppeared<'a>(&'a Record);
+
+impl Debug for RedactedEventAppeared<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("EventAppeared")
+            .field(&RedactedRecord(self.0))
+            .finish()
+    }
+}
+
+impl Display for Reply {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Reply::AppendStreamCompleted(AppendStreamCompleted::Success(r)) => write!(
+                f,
+                "append completed at position {}, next expected revision {}",
+                r.position, r.next_expected_version
+            ),
+
+            Reply::AppendStreamCompleted(AppendStreamCompleted::Error(e)) => {
+                write!(f, "append failed: {e}")
+            }
+
+            Reply::StreamRead(resp) => match resp {
+                ReadStreamResponse::EndOfStream {
+                    tail_revision: Some(r),
+                } => write!(f, "end of stream, tail revision {r}"),
+                ReadStreamResponse::EndOfStream {
+                    tail_revision: None,
+                } => {
+                    write!(f, "end of stream")
+                }
+                ReadStreamResponse::StreamDeleted => write!(f, "stream deleted"),
+                ReadStreamResponse::EventAppeared(r) => display_record(f, r),
+            },
+
+            Reply::SubscriptionEvent(event) => match event {
+                SubscriptionEvent::EventAppeared(r) => display_record(f, r),
+                SubscriptionEvent::EventsAppeared(rs) => write!(f, "{} events appeared", rs.len()),
+                SubscriptionEvent::Confirmed(c) => write!(f, "subscription confirmed: {c:?}"),
+                SubscriptionEvent::CaughtUp => write!(f, "subscription caught up"),
+                SubscriptionEvent::Unsubscribed(r) => write!(f, "unsubscribed: {r:?}"),
+                SubscriptionEvent::Notification(n) => write!(f, "notification: {n:?}"),
+                SubscriptionEvent::ProgramLog { line } => write!(f, "program log: {line}"),
+            },
+
+            Reply::DeleteStreamCompleted(DeleteStreamCompleted::Success(r)) => {
+                write!(f, "stream deletion completed at position {}", r.position)
+            }
+
+            Reply::DeleteStreamCompleted(DeleteStreamCompleted::DryRun(r)) => {
+                write!(f, "stream deletion would succeed, current revision {r}")
+            }
+
+            Reply::DeleteStreamCompleted(DeleteStreamCompleted::Error(e)) => {
+                write!(f, "stream deletion failed: {e}")
+            }
+
+            Reply::ProgramsListed(p) => write!(f, "{} program(s) listed", p.programs.len()),
+
+            Reply::ProgramKilled(ProgramKilled::Success) => write!(f, "program killed"),
+            Reply::ProgramKilled(ProgramKilled::Error(e)) => {
+                write!(f, "program kill failed: {e:?}")
+            }
+
+            Reply::ProgramObtained(ProgramObtained::Success(s)) => {
+                write!(f, "program {} '{}' obtained", s.id, s.name)
+            }
+
+            Reply::ProgramObtained(ProgramObtained::Error(e)) => {
+                write!(f, "program retrieval failed: {e:?}")
+            }
+
+            Reply::ServerDisconnected => write!(f, "server disconnected"),
+            Reply::Error(e) => write!(f, "error: {e}"),
+        }
+    }
+}
+
+fn display_record(f: &mut Formatter<'_>, record: &Record) -> fmt::Result {
+    write!(
+        f,
+        "event appeared: stream '{}', revision {}, class '{}', {:?}, metadata {:?}",
+        record.stream_name,
+        record.revision,
+        record.class,
+        Size(record.data.len()),
+        Size(record.metadata.len())
+    )
+}
+
+/// Formats a [`Reply`] including full event payloads. Only meant for verbose logging.
+pub struct VerboseReply<'a>(pub &'a Reply);
+
+impl Debug for VerboseReply<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Reply::AppendStreamCompleted(x) => {
+                f.debug_tuple("AppendStreamCompleted").field(x).finish()
+            }
+            Reply::StreamRead(x) => f.debug_tuple("StreamRead").field(x).finish(),
+            Reply::SubscriptionEvent(x) => f.debug_tuple("SubscriptionEvent").field(x).finish(),
+            Reply::DeleteStreamCompleted(x) => {
+                f.debug_tuple("DeleteStreamCompleted").field(x).finish()
+            }
+            Reply::ProgramsListed(x) => f.debug_tuple("ProgramsListed").field(x).finish(),
+            Reply::ProgramKilled(x) => f.debug_tuple("ProgramKilled").field(x).finish(),
+            Reply::ProgramObtained(x) => f.debug_tuple("ProgramObtained").field(x).finish(),
+            Reply::ServerDisconnected => write!(f, "ServerDisconnected"),
+            Reply::Error(x) => f.debug_tuple("Error").field(x).finish(),
+        }
+    }
+}
+
+/// Formats an [`Operation`] including full event payloads. Only meant for verbose logging.
+pub struct VerboseOperation<'a>(pub &'a Operation);
+
+impl Debug for VerboseOperation<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Operation::AppendStream(x) => f
+                .debug_tuple("AppendStream")
+                .field(&format_args!(
+                    "AppendStream {{ stream_name: {:?}, expected_revision: {:?}, events: {:?} }}",
+                    x.stream_name, x.expected_revision, x.events
+                ))
+                .finish(),
+
+            Operation::Subscribe(crate::Subscribe::ToProgram(x)) => f
+                .debug_tuple("Subscribe")
+                .field(&format_args!(
+                    "ToProgram(SubscribeToProgram {{ name: {:?}, source: {:?}, logs: {:?}, restart: {:?} }})",
+                    x.name, x.source, x.logs, x.restart
+                ))
+                .finish(),
+
+            other => other.fmt(f),
+        }
+    }
+}
+
+impl Debug for OperationIn {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OperationIn")
+            .field("correlation", &self.correlation)
+            .field("operation", &self.operation)
+            .finish()
+    }
+}
+
+impl Debug for OperationOut {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OperationOut")
+            .field("correlation", &self.correlation)
+            .field("reply", &self.reply)
+            .finish()
+    }
+}
+
+impl Display for OperationOut {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}", self.correlation, self.reply)
+    }
+}
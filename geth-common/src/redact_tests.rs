@@ -0,0 +1,113 @@
+use bytes::Bytes;
+use uuid::Uuid;
+
+use crate::{
+    AppendStream, ContentType, ExpectedRevision, Operation, OperationOut, Propose,
+    ReadStreamResponse, Record, Reply, SubscriptionEvent,
+};
+
+const SECRET: &[u8] = b"{\"ssn\":\"123-45-6789\"}";
+
+fn record() -> Record {
+    Record {
+        id: Uuid::nil(),
+        content_type: ContentType::Json,
+        class: "user-created".to_string(),
+        stream_name: "users".to_string(),
+        position: 12,
+        revision: 3,
+        data: Bytes::from_static(SECRET),
+        metadata: Bytes::from_static(SECRET),
+        created: Default::default(),
+        link: None,
+        epoch: 0,
+    }
+}
+
+#[test]
+fn test_operation_debug_redacts_payloads() {
+    let op = Operation::AppendStream(AppendStream {
+        stream_name: "users".to_string(),
+        events: vec![Propose {
+            id: Uuid::nil(),
+            content_type: ContentType::Json,
+            class: "user-created".to_string(),
+            data: Bytes::from_static(SECRET),
+            metadata: Bytes::from_static(SECRET),
+        }],
+        expected_revision: ExpectedRevision::Any,
+        notify_subscribers: true,
+    });
+
+    let redacted = format!("{op:?}");
+    assert!(redacted.contains("users"));
+    assert!(redacted.contains("user-created"));
+    assert!(redacted.contains(&format!("<{} bytes>", SECRET.len())));
+    assert!(!redacted.contains("123-45-6789"));
+
+    assert!(format!("{:?}", op.verbose()).contains("123-45-6789"));
+}
+
+#[test]
+fn test_reply_debug_and_display_redact_payloads() {
+    let replies = vec![
+        Reply::StreamRead(ReadStreamResponse::EventAppeared(record())),
+        Reply::SubscriptionEvent(SubscriptionEvent::EventAppeared(record())),
+    ];
+
+    for reply in replies {
+        let debug = format!("{reply:?}");
+        let display = reply.to_string();
+
+        assert!(debug.contains("revision: 3"));
+        assert!(!debug.contains("123-45-6789"));
+        assert!(display.contains("users"));
+        assert!(display.contains(&format!("<{} bytes>", SECRET.len())));
+        assert!(display.contains(&format!("metadata <{} bytes>", SECRET.len())));
+        assert!(!display.contains("123-45-6789"));
+
+        assert!(format!("{:?}", reply.verbose()).contains("123-45-6789"));
+    }
+}
+
+#[test]
+fn test_batched_events_debug_redacts_payloads() {
+    let reply = Reply::SubscriptionEvent(SubscriptionEvent::EventsAppeared(vec![
+        record(),
+        Record {
+            revision: 4,
+            metadata: Bytes::new(),
+            ..record()
+        },
+    ]));
+
+    let debug = format!("{reply:?}");
+
+    assert!(debug.contains("EventsAppeared"));
+    assert!(debug.contains("revision: 3"));
+    assert!(debug.contains("revision: 4"));
+    assert!(debug.contains(&format!("metadata: <{} bytes>", SECRET.len())));
+    assert!(debug.contains("metadata: <0 bytes>"));
+    assert!(!debug.contains("123-45-6789"));
+    assert_eq!("2 events appeared", reply.to_string());
+
+    assert!(format!("{:?}", reply.verbose()).contains("123-45-6789"));
+}
+
+#[test]
+fn test_operation_out_display() {
+    let out = OperationOut {
+        correlation: Uuid::nil(),
+        reply: Reply::StreamRead(ReadStreamResponse::EndOfStream {
+            tail_revision: None,
+        }),
+    };
+
+    assert_eq!(format!("[{}] end of stream", Uuid::nil()), out.to_string());
+
+    let reply = Reply::StreamRead(ReadStreamResponse::EndOfStream {
+        tail_revision: Some(41),
+    });
+
+    assert_eq!("end of stream, tail revision 41", reply.to_string());
+}
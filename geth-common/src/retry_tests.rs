@@ -0,0 +1,95 @@
+use uuid::Uuid;
+
+use crate::{
+    AppendError, AppendStreamCompleted, DeleteError, DeleteStreamCompleted, OperationOut,
+    ReadStreamResponse, Reply, Retryability, SubscriptionEvent, UnsubscribeReason,
+};
+
+fn out(reply: Reply) -> OperationOut {
+    OperationOut {
+        correlation: Uuid::nil(),
+        reply,
+    }
+}
+
+#[test]
+fn test_disconnection_is_retriable() {
+    assert_eq!(
+        Retryability::Retriable,
+        out(Reply::ServerDisconnected).retryability()
+    );
+
+    assert_eq!(
+        Retryability::Retriable,
+        out(Reply::SubscriptionEvent(SubscriptionEvent::Unsubscribed(
+            UnsubscribeReason::Shutdown
+        )))
+        .retryability()
+    );
+
+    assert_eq!(
+        Retryability::Retriable,
+        out(Reply::SubscriptionEvent(SubscriptionEvent::Unsubscribed(
+            UnsubscribeReason::Disconnected
+        )))
+        .retryability()
+    );
+}
+
+#[test]
+fn test_ended_by_server_is_not_retriable() {
+    // The server ends subscriptions to streams that get deleted, subscribing again ends the same.
+    assert_eq!(
+        Retryability::NonRetriable,
+        out(Reply::SubscriptionEvent(SubscriptionEvent::Unsubscribed(
+            UnsubscribeReason::Server
+        )))
+        .retryability()
+    );
+}
+
+#[test]
+fn test_invalid_argument_is_not_retriable() {
+    assert_eq!(
+        Retryability::NonRetriable,
+        out(Reply::Error(
+            "invalid argument: direction is missing".to_string()
+        ))
+        .retryability()
+    );
+}
+
+#[test]
+fn test_stream_deleted_is_fatal() {
+    assert_eq!(
+        Retryability::Fatal,
+        out(Reply::AppendStreamCompleted(AppendStreamCompleted::Error(
+            AppendError::StreamDeleted
+        )))
+        .retryability()
+    );
+
+    assert_eq!(
+        Retryability::Fatal,
+        out(Reply::DeleteStreamCompleted(DeleteStreamCompleted::Error(
+            DeleteError::StreamDeleted
+        )))
+        .retryability()
+    );
+
+    assert_eq!(
+        Retryability::Fatal,
+        out(Reply::StreamRead(ReadStreamResponse::StreamDeleted)).retryability()
+    );
+}
+
+#[test]
+fn test_success_is_not_retriable() {
+    assert_eq!(
+        Retryability::NonRetriable,
+        out(Reply::StreamRead(ReadStreamResponse::EndOfStream {
+            tail_revision: None
+        }))
+        .retryability()
+    );
+}
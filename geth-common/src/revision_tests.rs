@@ -0,0 +1,59 @@
+use crate::{ExpectedRevision, RevisionConflict, WrongExpectedRevisionError};
+
+fn classify(expected: ExpectedRevision, current: ExpectedRevision) -> RevisionConflict {
+    WrongExpectedRevisionError { expected, current }.classify()
+}
+
+#[test]
+fn test_missing_stream() {
+    assert_eq!(
+        RevisionConflict::StreamNotFound,
+        classify(ExpectedRevision::Revision(3), ExpectedRevision::NoStream)
+    );
+
+    assert_eq!(
+        RevisionConflict::StreamNotFound,
+        classify(ExpectedRevision::StreamExists, ExpectedRevision::NoStream)
+    );
+}
+
+#[test]
+fn test_stream_already_exists() {
+    assert_eq!(
+        RevisionConflict::StreamAlreadyExists { current: 4 },
+        classify(ExpectedRevision::NoStream, ExpectedRevision::Revision(4))
+    );
+}
+
+#[test]
+fn test_concurrent_append() {
+    assert_eq!(
+        RevisionConflict::ConcurrentAppend {
+            expected: 2,
+            behind_by: 3,
+        },
+        classify(ExpectedRevision::Revision(2), ExpectedRevision::Revision(5))
+    );
+}
+
+#[test]
+fn test_expected_ahead() {
+    assert_eq!(
+        RevisionConflict::ExpectedAhead {
+            expected: 42,
+            current: 5,
+        },
+        classify(
+            ExpectedRevision::Revision(42),
+            ExpectedRevision::Revision(5)
+        )
+    );
+}
+
+#[test]
+fn test_pairs_the_server_never_reports() {
+    assert_eq!(
+        RevisionConflict::Other,
+        classify(ExpectedRevision::Any, ExpectedRevision::Revision(5))
+    );
+}
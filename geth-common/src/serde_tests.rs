@@ -0,0 +1,66 @@
+use chrono::{TimeZone, Utc};
+use serde::{de::DeserializeOwned, Serialize};
+use std::fmt::Debug;
+
+use crate::{Direction, EndPoint, ExpectedRevision, ProgramSummary, Revision};
+
+fn round_trip<A>(value: &A) -> A
+where
+    A: Serialize + DeserializeOwned,
+{
+    let json = serde_json::to_string(value).unwrap();
+    serde_json::from_str(&json).unwrap()
+}
+
+fn assert_json<A>(value: A, expected: &str)
+where
+    A: Serialize + DeserializeOwned + PartialEq + Debug,
+{
+    assert_eq!(expected, serde_json::to_string(&value).unwrap());
+    assert_eq!(value, round_trip(&value));
+}
+
+#[test]
+fn test_revision_representation() {
+    assert_json(Revision::<u64>::Start, r#""start""#);
+    assert_json(Revision::<u64>::End, r#""end""#);
+    assert_json(Revision::Revision(42u64), r#"{"revision":42}"#);
+}
+
+#[test]
+fn test_direction_representation() {
+    assert_json(Direction::Forward, r#""forward""#);
+    assert_json(Direction::Backward, r#""backward""#);
+}
+
+#[test]
+fn test_expected_revision_representation() {
+    assert_json(ExpectedRevision::Any, r#""any""#);
+    assert_json(ExpectedRevision::NoStream, r#""no_stream""#);
+    assert_json(ExpectedRevision::StreamExists, r#""stream_exists""#);
+    assert_json(ExpectedRevision::Revision(7), r#"{"revision":7}"#);
+}
+
+#[test]
+fn test_endpoint_round_trip() {
+    let endpoint = EndPoint::new("localhost".to_string(), 2_113);
+    let actual = round_trip(&endpoint);
+
+    assert_eq!(endpoint.host, actual.host);
+    assert_eq!(endpoint.port, actual.port);
+}
+
+#[test]
+fn test_program_summary_round_trip() {
+    let summary = ProgramSummary {
+        id: 1,
+        name: "echo".to_string(),
+        started_at: Utc.timestamp_opt(1_700_000_000, 0).unwrap(),
+    };
+
+    let actual = round_trip(&summary);
+
+    assert_eq!(summary.id, actual.id);
+    assert_eq!(summary.name, actual.name);
+    assert_eq!(summary.started_at, actual.started_at);
+}
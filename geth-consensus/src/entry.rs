@@ -12,10 +12,21 @@ impl EntryId {
     }
 }
 
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum EntryKind {
+    #[default]
+    Command,
+    /// The payload is a [`crate::membership::Configuration`] the cluster switches to.
+    Configuration,
+    /// Empty entry a leader appends when elected, see [`crate::state_machine::RaftSM`].
+    Noop,
+}
+
 #[derive(Debug, Clone)]
 pub struct Entry {
     pub index: u64,
     pub term: u64,
+    pub kind: EntryKind,
     pub payload: Bytes,
 }
 
@@ -1,18 +1,24 @@
+use std::collections::VecDeque;
 use std::hash::Hash;
 use std::io;
 use std::time::{Duration, Instant};
 
-use bytes::{Bytes, BytesMut};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 use rand::{thread_rng, Rng};
 
-use crate::entry::{Entry, EntryId};
+use crate::entry::{Entry, EntryId, EntryKind};
+use crate::membership::MembershipChange;
 use crate::msg::{
-    AppendEntries, EntriesAppended, EntriesReplicated, RequestVote, VoteCasted, VoteReceived,
+    AppendEntries, EntriesAppended, EntriesReplicated, InstallSnapshot, RequestVote, VoteCasted,
+    VoteReceived,
 };
+use crate::snapshot::Snapshot;
 use crate::state_machine::RaftSM;
 
 mod entry;
+mod membership;
 mod msg;
+mod snapshot;
 
 mod state_machine;
 #[cfg(test)]
@@ -21,10 +27,16 @@ mod tests;
 #[derive(Debug)]
 pub enum Msg<Id, Command> {
     RequestVote(RequestVote<Id>),
+    PreVote(RequestVote<Id>),
     AppendEntries(AppendEntries<Id>),
+    InstallSnapshot(InstallSnapshot<Id>),
     VoteReceived(VoteReceived<Id>),
+    PreVoteReceived(VoteReceived<Id>),
     EntriesAppended(EntriesAppended<Id>),
     Command(Command),
+    /// Only the leader changes the membership of the cluster, one change at a time. Otherwise
+    /// the change is dropped and must be submitted again.
+    ChangeMembership(MembershipChange<Id>),
     Tick,
     Shutdown,
 }
@@ -32,8 +44,13 @@ pub enum Msg<Id, Command> {
 #[derive(Debug)]
 pub enum Request<Id> {
     RequestVote(RequestVote<Id>),
+    /// Asks a node whether it would vote for the candidate in an election at the request's term,
+    /// which is one past the candidate's current term. Nothing changes on the node's side.
+    PreVote(RequestVote<Id>),
     AppendEntries(AppendEntries<Id>),
+    InstallSnapshot(InstallSnapshot<Id>),
     VoteCasted(VoteCasted<Id>),
+    PreVoteCasted(VoteCasted<Id>),
     EntriesReplicated(EntriesReplicated<Id>),
 }
 
@@ -41,6 +58,36 @@ pub trait RaftCommand {
     fn write(&self, buffer: &mut BytesMut);
 }
 
+/// Node ids are written in the log as part of configuration entries.
+pub trait RaftNodeId: Ord + Hash + Clone {
+    fn write(&self, buffer: &mut BytesMut);
+    fn read(bytes: &mut Bytes) -> Option<Self>;
+}
+
+impl RaftNodeId for u64 {
+    fn write(&self, buffer: &mut BytesMut) {
+        buffer.put_u64_le(*self);
+    }
+
+    fn read(bytes: &mut Bytes) -> Option<Self> {
+        if bytes.remaining() < 8 {
+            return None;
+        }
+
+        Some(bytes.get_u64_le())
+    }
+}
+
+impl RaftNodeId for usize {
+    fn write(&self, buffer: &mut BytesMut) {
+        buffer.put_u64_le(*self as u64);
+    }
+
+    fn read(bytes: &mut Bytes) -> Option<Self> {
+        u64::read(bytes).map(|id| id as usize)
+    }
+}
+
 pub trait CommandDispatch {
     type Command: UserCommand;
 
@@ -73,6 +120,14 @@ pub trait RaftSender {
         self.send(target, Request::VoteCasted(resp));
     }
 
+    fn pre_vote(&self, target: Self::Id, req: RequestVote<Self::Id>) {
+        self.send(target, Request::PreVote(req));
+    }
+
+    fn pre_vote_casted(&self, target: Self::Id, resp: VoteCasted<Self::Id>) {
+        self.send(target, Request::PreVoteCasted(resp));
+    }
+
     fn entries_replicated(&self, target: Self::Id, resp: EntriesReplicated<Self::Id>) {
         self.send(target, Request::EntriesReplicated(resp));
     }
@@ -80,6 +135,10 @@ pub trait RaftSender {
     fn replicate_entries(&self, target: Self::Id, req: AppendEntries<Self::Id>) {
         self.send(target, Request::AppendEntries(req));
     }
+
+    fn install_snapshot(&self, target: Self::Id, req: InstallSnapshot<Self::Id>) {
+        self.send(target, Request::InstallSnapshot(req));
+    }
 }
 
 pub trait PersistentStorage {
@@ -91,12 +150,22 @@ pub trait PersistentStorage {
     fn previous_entry(&self, index: u64) -> Option<EntryId>;
     fn contains_entry(&self, entry_id: &EntryId) -> bool;
 
+    /// Keeps `snapshot` as the latest one. Entries it covers are removed, so is the whole log if
+    /// it doesn't contain the snapshot's last included entry. Once the log is compacted,
+    /// `last_entry`, `previous_entry` and `contains_entry` fall back on the snapshot's last
+    /// included entry.
+    fn install_snapshot(&mut self, snapshot: Snapshot);
+    fn latest_snapshot(&self) -> Option<Snapshot>;
+    /// Removes entries up to `index` included. The latest snapshot must cover them.
+    fn compact_to(&mut self, index: u64);
+
     fn append_entry(&mut self, term: u64, payload: Bytes) -> u64 {
         let index = self.next_index();
 
         self.append_entries(vec![Entry {
             index,
             term,
+            kind: EntryKind::Command,
             payload,
         }]);
 
@@ -153,6 +222,8 @@ pub trait IterateEntries {
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum State {
+    /// Checking it could win an election before starting one, see [`Request::PreVote`].
+    PreCandidate,
     Candidate,
     Follower,
     Leader,
@@ -161,11 +232,31 @@ pub enum State {
 pub struct TimeRange {
     low: u64,
     high: u64,
+    lease: Duration,
 }
 
 impl TimeRange {
+    /// The leader lease defaults to half the shortest election timeout.
     pub fn new(low: u64, high: u64) -> Self {
-        Self { low, high }
+        Self {
+            low,
+            high,
+            lease: Duration::from_millis(low / 2),
+        }
+    }
+
+    /// How long after a majority acknowledged it the leader serves reads on its own. It must
+    /// stay under the shortest election timeout, so no other leader can be elected meanwhile,
+    /// and is capped to it.
+    pub fn with_lease(self, lease: Duration) -> Self {
+        Self {
+            lease: lease.min(Duration::from_millis(self.low)),
+            ..self
+        }
+    }
+
+    pub fn lease(&self) -> Duration {
+        self.lease
     }
 
     pub fn new_timeout(&self) -> Duration {
@@ -179,19 +270,73 @@ pub struct Replica<Id> {
     id: Id,
     next_index: u64,
     match_index: u64,
-    // When sending entries to replica represents the last index of the batch. If the replication
-    // was successful, that value will be used to update the next_index value.
-    batch_end_index: u64,
+    // Sequence number of the next batch sent to the replica.
+    next_seq: u64,
+    // Batches sent to the replica and not answered yet, oldest first.
+    batches: VecDeque<Batch>,
+    // When the latest acknowledged batch was sent, the replica followed us at that point.
+    acked_at: Option<Instant>,
+}
+
+/// Entries or heartbeat sent to a replica, identified by the `seq` its reply echoes.
+struct Batch {
+    seq: u64,
+    sent_at: Instant,
+    // Last index of the batch. If the replication was successful, that value will be used to
+    // update the next_index value.
+    end_index: u64,
 }
 
+/// Batches a replica can leave unanswered before the oldest are forgotten. Their replies, if
+/// they ever come, are then ignored.
+const MAX_UNANSWERED_BATCHES: usize = 64;
+
 impl<Id> Replica<Id> {
     pub fn new(id: Id) -> Self {
         Self {
             id,
             next_index: 0,
             match_index: 0,
-            batch_end_index: 0,
+            next_seq: 0,
+            batches: VecDeque::new(),
+            acked_at: None,
+        }
+    }
+
+    /// Records a batch about to be sent and returns its sequence number.
+    fn send_batch(&mut self, sent_at: Instant, end_index: u64) -> u64 {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+
+        if self.batches.len() == MAX_UNANSWERED_BATCHES {
+            self.batches.pop_front();
         }
+
+        self.batches.push_back(Batch {
+            seq,
+            sent_at,
+            end_index,
+        });
+
+        seq
+    }
+
+    /// The batch a reply answers, `None` when the reply is stale: its batch is older than one
+    /// already answered, or was forgotten. Batches older than the answered one are forgotten,
+    /// their replies would be stale.
+    fn answered(&mut self, seq: u64) -> Option<Batch> {
+        while let Some(batch) = self.batches.pop_front() {
+            if batch.seq == seq {
+                return Some(batch);
+            }
+
+            if batch.seq > seq {
+                self.batches.push_front(batch);
+                break;
+            }
+        }
+
+        None
     }
 }
 
@@ -204,7 +349,7 @@ pub fn run_raft_app<NodeId, Storage, Command, R, S, D>(
     sender: S,
     dispatcher: D,
 ) where
-    NodeId: Ord + Hash + Clone,
+    NodeId: RaftNodeId,
     Storage: PersistentStorage,
     Command: UserCommand,
     S: RaftSender<Id = NodeId>,
@@ -213,6 +358,7 @@ pub fn run_raft_app<NodeId, Storage, Command, R, S, D>(
 {
     let term = storage.last_entry().map(|e| e.term);
     let mut sm = RaftSM::new(node_id, &time_range, seeds, term);
+    sm.restore_configuration(&storage);
 
     while let Some(msg) = mailbox.recv() {
         match msg {
@@ -220,20 +366,36 @@ pub fn run_raft_app<NodeId, Storage, Command, R, S, D>(
                 sm.handle_request_vote(&sender, &storage, args);
             }
 
+            Msg::PreVote(args) => {
+                sm.handle_pre_vote(&sender, &storage, Instant::now(), args);
+            }
+
             Msg::AppendEntries(args) => {
                 sm.handle_append_entries(&sender, &mut storage, Instant::now(), args);
             }
 
+            Msg::InstallSnapshot(args) => {
+                sm.handle_install_snapshot(&sender, &mut storage, Instant::now(), args);
+            }
+
             Msg::VoteReceived(args) => {
-                sm.handle_vote_received(&time_range, &storage, &sender, Instant::now(), args)
+                sm.handle_vote_received(&time_range, &mut storage, &sender, Instant::now(), args)
+            }
+
+            Msg::PreVoteReceived(args) => {
+                sm.handle_pre_vote_received(&time_range, &storage, &sender, Instant::now(), args)
             }
 
             Msg::EntriesAppended(args) => {
-                sm.handle_entries_appended(&dispatcher, args);
+                sm.handle_entries_appended(&mut storage, &dispatcher, args);
             }
 
             Msg::Command(cmd) => {
-                sm.handle_command(&mut storage, &dispatcher, cmd);
+                sm.handle_command(&mut storage, &dispatcher, Instant::now(), cmd);
+            }
+
+            Msg::ChangeMembership(change) => {
+                sm.handle_membership_change(&mut storage, change);
             }
 
             Msg::Tick => {
@@ -0,0 +1,157 @@
+use std::collections::BTreeSet;
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::RaftNodeId;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MembershipChange<Id> {
+    AddNode(Id),
+    RemoveNode(Id),
+}
+
+/// Nodes of the cluster that vote and whose log counts when committing entries, the node itself
+/// included.
+///
+/// A membership change goes through a joint configuration where `next` is set: until the entry
+/// introducing it commits, elections and commits must reach a majority of both `voters` and
+/// `next`. Only then the cluster switches to `next` alone, so there is no point where the old and
+/// new configurations can make decisions on their own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Configuration<Id> {
+    pub voters: BTreeSet<Id>,
+    pub next: Option<BTreeSet<Id>>,
+}
+
+impl<Id> Configuration<Id>
+where
+    Id: RaftNodeId,
+{
+    pub fn new(voters: impl IntoIterator<Item = Id>) -> Self {
+        Self {
+            voters: voters.into_iter().collect(),
+            next: None,
+        }
+    }
+
+    pub fn is_joint(&self) -> bool {
+        self.next.is_some()
+    }
+
+    /// Every node of the configuration, of both sides during a joint configuration.
+    pub fn members(&self) -> BTreeSet<Id> {
+        let mut members = self.voters.clone();
+
+        if let Some(next) = &self.next {
+            members.extend(next.iter().cloned());
+        }
+
+        members
+    }
+
+    /// Joint configuration moving the cluster to `change`.
+    pub fn joint(&self, change: &MembershipChange<Id>) -> Self {
+        let mut next = self.voters.clone();
+
+        match change {
+            MembershipChange::AddNode(id) => next.insert(id.clone()),
+            MembershipChange::RemoveNode(id) => next.remove(id),
+        };
+
+        Self {
+            voters: self.voters.clone(),
+            next: Some(next),
+        }
+    }
+
+    /// Configuration the cluster ends up in once the joint one commits.
+    pub fn finalized(&self) -> Self {
+        Self {
+            voters: self.next.clone().unwrap_or_else(|| self.voters.clone()),
+            next: None,
+        }
+    }
+
+    /// Whether the nodes `agreed` holds for form a majority, on both sides of a joint
+    /// configuration.
+    pub fn has_quorum(&self, agreed: impl Fn(&Id) -> bool) -> bool {
+        let majority =
+            |nodes: &BTreeSet<Id>| nodes.iter().filter(|id| agreed(id)).count() * 2 > nodes.len();
+
+        majority(&self.voters) && self.next.as_ref().is_none_or(majority)
+    }
+
+    /// Highest index replicated on a majority of nodes, on both sides of a joint configuration.
+    pub fn committed_index(&self, match_index: impl Fn(&Id) -> u64) -> u64 {
+        self.agreed(|id| Some(match_index(id))).unwrap_or_default()
+    }
+
+    /// Highest value a majority of nodes reached, on both sides of a joint configuration. Nodes
+    /// without a value count as being behind everyone else.
+    pub fn agreed<T: Ord + Copy>(&self, value: impl Fn(&Id) -> Option<T>) -> Option<T> {
+        let agreed = |nodes: &BTreeSet<Id>| {
+            let mut values = nodes.iter().map(&value).collect::<Vec<_>>();
+            values.sort_unstable_by(|a, b| b.cmp(a));
+
+            values.get(nodes.len() / 2).copied().flatten()
+        };
+
+        let mut result = agreed(&self.voters);
+
+        if let Some(next) = &self.next {
+            result = result.min(agreed(next));
+        }
+
+        result
+    }
+
+    pub fn write(&self, buffer: &mut BytesMut) {
+        write_nodes(&self.voters, buffer);
+
+        if let Some(next) = &self.next {
+            buffer.put_u8(1);
+            write_nodes(next, buffer);
+        } else {
+            buffer.put_u8(0);
+        }
+    }
+
+    pub fn read(mut bytes: Bytes) -> Option<Self> {
+        let voters = read_nodes(&mut bytes)?;
+
+        if bytes.remaining() < 1 {
+            return None;
+        }
+
+        let next = if bytes.get_u8() == 1 {
+            Some(read_nodes(&mut bytes)?)
+        } else {
+            None
+        };
+
+        Some(Self { voters, next })
+    }
+}
+
+fn write_nodes<Id: RaftNodeId>(nodes: &BTreeSet<Id>, buffer: &mut BytesMut) {
+    buffer.put_u32_le(nodes.len() as u32);
+
+    for node in nodes {
+        node.write(buffer);
+    }
+}
+
+fn read_nodes<Id: RaftNodeId>(bytes: &mut Bytes) -> Option<BTreeSet<Id>> {
+    if bytes.remaining() < 4 {
+        return None;
+    }
+
+    let len = bytes.get_u32_le();
+    let mut nodes = BTreeSet::new();
+
+    for _ in 0..len {
+        nodes.insert(Id::read(bytes)?);
+    }
+
+    Some(nodes)
+}
@@ -1,4 +1,5 @@
 use crate::entry::Entry;
+use crate::snapshot::Snapshot;
 
 #[derive(Debug)]
 pub struct RequestVote<Id> {
@@ -12,16 +13,32 @@ pub struct RequestVote<Id> {
 pub struct AppendEntries<Id> {
     pub term: u64,
     pub leader_id: Id,
+    /// Echoed in the [`EntriesReplicated`] answering it, so the leader knows which request a
+    /// reply is about.
+    pub seq: u64,
     pub prev_log_index: u64,
     pub prev_log_term: u64,
     pub leader_commit: u64,
     pub entries: Vec<Entry>,
 }
 
+/// Sent instead of [`AppendEntries`] when the entries a replica is missing got compacted. The
+/// replica answers with [`EntriesReplicated`].
+#[derive(Debug)]
+pub struct InstallSnapshot<Id> {
+    pub term: u64,
+    pub leader_id: Id,
+    /// See [`AppendEntries::seq`].
+    pub seq: u64,
+    pub snapshot: Snapshot,
+}
+
 #[derive(Debug)]
 pub struct EntriesReplicated<Id> {
     pub node_id: Id,
     pub term: u64,
+    /// The `seq` of the request it answers.
+    pub seq: u64,
     pub success: bool,
 }
 
@@ -43,5 +60,6 @@ pub struct VoteReceived<Id> {
 pub struct EntriesAppended<Id> {
     pub node_id: Id,
     pub term: u64,
+    pub seq: u64,
     pub success: bool,
 }
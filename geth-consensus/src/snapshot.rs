@@ -0,0 +1,18 @@
+use bytes::Bytes;
+
+use crate::entry::EntryId;
+
+/// State of the application once every entry up to `last_included_index` got applied. Entries it
+/// covers can be removed from the log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub last_included_index: u64,
+    pub last_included_term: u64,
+    pub state: Bytes,
+}
+
+impl Snapshot {
+    pub fn last_included(&self) -> EntryId {
+        EntryId::new(self.last_included_index, self.last_included_term)
+    }
+}
@@ -1,17 +1,18 @@
-use std::cmp::min;
+use std::cmp::{max, min};
 use std::collections::{HashMap, HashSet, VecDeque};
-use std::hash::Hash;
 use std::time::{Duration, Instant};
 
 use bytes::BytesMut;
 
-use crate::entry::EntryId;
+use crate::entry::{Entry, EntryId, EntryKind};
+use crate::membership::{Configuration, MembershipChange};
 use crate::msg::{
-    AppendEntries, EntriesAppended, EntriesReplicated, RequestVote, VoteCasted, VoteReceived,
+    AppendEntries, EntriesAppended, EntriesReplicated, InstallSnapshot, RequestVote, VoteCasted,
+    VoteReceived,
 };
 use crate::{
-    CommandDispatch, IterateEntries, PersistentStorage, RaftSender, Replica, State, TimeRange,
-    UserCommand,
+    CommandDispatch, IterateEntries, PersistentStorage, RaftNodeId, RaftSender, Replica, State,
+    TimeRange, UserCommand,
 };
 
 pub struct RaftSM<NodeId, Command> {
@@ -26,11 +27,26 @@ pub struct RaftSM<NodeId, Command> {
     pub inflights: VecDeque<(u64, Command)>,
     pub buffer: BytesMut,
     pub replicas: HashMap<NodeId, Replica<NodeId>>,
+    pub config: Configuration<NodeId>,
+    pub lease_duration: Duration,
+    /// Until when the leader serves reads without checking with the cluster.
+    pub lease_expiry: Option<Instant>,
+    /// When the leader last sent entries or heartbeats to the replicas.
+    pub replicated_at: Option<Instant>,
+    /// Reads waiting for the commit index to reach theirs and for a majority to acknowledge the
+    /// leader after they were received.
+    pub pending_reads: VecDeque<(u64, Instant, Command)>,
+    /// Index of the latest configuration entry, `None` when the configuration comes from the
+    /// seeds.
+    pub config_index: Option<u64>,
+    /// Index of the no-op entry the node appended when elected leader. Until it commits, the
+    /// leader doesn't know which entries of the previous terms committed and holds reads back.
+    pub noop_index: Option<u64>,
 }
 
 impl<NodeId, Command> RaftSM<NodeId, Command>
 where
-    NodeId: Clone + Ord + Hash,
+    NodeId: RaftNodeId,
     Command: UserCommand,
 {
     pub fn new(id: NodeId, time_range: &TimeRange, seeds: Vec<NodeId>, term: Option<u64>) -> Self {
@@ -41,6 +57,8 @@ where
             State::Follower
         };
 
+        let config = Configuration::new(seeds.iter().cloned().chain([id.clone()]));
+
         for seed in seeds {
             replicas.insert(seed.clone(), Replica::new(seed));
         }
@@ -57,9 +75,107 @@ where
             inflights: VecDeque::new(),
             buffer: Default::default(),
             replicas,
+            config,
+            config_index: None,
+            noop_index: None,
+            lease_duration: time_range.lease(),
+            lease_expiry: None,
+            replicated_at: None,
+            pending_reads: VecDeque::new(),
+        }
+    }
+
+    /// Picks up the latest configuration entry of the log, if any.
+    pub fn restore_configuration<P>(&mut self, storage: &P)
+    where
+        P: PersistentStorage,
+    {
+        let mut entries = storage.read_all();
+
+        while let Ok(Some(entry)) = entries.next() {
+            self.apply_configuration_entry(&entry);
         }
     }
 
+    /// A configuration is used as soon as its entry is in the log, whether committed or not.
+    fn apply_configuration_entry(&mut self, entry: &Entry) {
+        if entry.kind != EntryKind::Configuration {
+            return;
+        }
+
+        if let Some(config) = Configuration::read(entry.payload.clone()) {
+            self.apply_configuration(entry.index, config);
+        }
+    }
+
+    fn apply_configuration(&mut self, index: u64, config: Configuration<NodeId>) {
+        let members = config.members();
+
+        self.replicas.retain(|id, _| members.contains(id));
+
+        for member in members {
+            if member != self.id && !self.replicas.contains_key(&member) {
+                self.replicas.insert(member.clone(), Replica::new(member));
+            }
+        }
+
+        self.config = config;
+        self.config_index = Some(index);
+    }
+
+    fn append_configuration<P>(&mut self, storage: &mut P, config: Configuration<NodeId>)
+    where
+        P: PersistentStorage,
+    {
+        config.write(&mut self.buffer);
+
+        let entry = Entry {
+            index: storage.next_index(),
+            term: self.term,
+            kind: EntryKind::Configuration,
+            payload: self.buffer.split().freeze(),
+        };
+
+        self.apply_configuration(entry.index, config);
+        storage.append_entries(vec![entry]);
+    }
+
+    /// Another leader showed up.
+    fn step_down(&mut self) {
+        self.state = State::Follower;
+        self.noop_index = None;
+        self.reject_pending_reads();
+    }
+
+    fn append_noop<P>(&mut self, storage: &mut P)
+    where
+        P: PersistentStorage,
+    {
+        let entry = Entry {
+            index: storage.next_index(),
+            term: self.term,
+            kind: EntryKind::Noop,
+            payload: Default::default(),
+        };
+
+        self.noop_index = Some(entry.index);
+        storage.append_entries(vec![entry]);
+    }
+
+    /// Reads we couldn't confirm we were still the leader for are refused.
+    fn reject_pending_reads(&mut self) {
+        self.lease_expiry = None;
+
+        while let Some((_, _, cmd)) = self.pending_reads.pop_front() {
+            cmd.reject();
+        }
+    }
+
+    fn has_quorum(&self) -> bool {
+        self.config
+            .has_quorum(|id| *id == self.id || self.tally.contains(id))
+    }
+
     pub fn handle_request_vote<S, P>(&mut self, sender: &S, storage: &P, args: RequestVote<NodeId>)
     where
         S: RaftSender<Id = NodeId>,
@@ -111,6 +227,38 @@ where
         )
     }
 
+    pub fn handle_pre_vote<S, P>(
+        &self,
+        sender: &S,
+        storage: &P,
+        now: Instant,
+        args: RequestVote<NodeId>,
+    ) where
+        S: RaftSender<Id = NodeId>,
+        P: PersistentStorage,
+    {
+        // A node that still hears from its leader doesn't help starting an election, that's
+        // what keeps a node that got partitioned from disrupting the cluster when it comes back.
+        let heard_from_leader = self.state == State::Leader
+            || (self.state == State::Follower
+                && now.duration_since(self.time) < self.election_timeout);
+
+        let last_entry_id = storage.last_entry_or_default();
+        let granted = args.term > self.term
+            && !heard_from_leader
+            && last_entry_id.index <= args.last_log_index
+            && last_entry_id.term <= args.last_log_term;
+
+        sender.pre_vote_casted(
+            args.candidate_id,
+            VoteCasted {
+                node_id: self.id.clone(),
+                term: if granted { args.term } else { self.term },
+                granted,
+            },
+        );
+    }
+
     pub fn handle_append_entries<S, P>(
         &mut self,
         sender: &S,
@@ -127,6 +275,7 @@ where
                 EntriesReplicated {
                     node_id: self.id.clone(),
                     term: self.term,
+                    seq: args.seq,
                     success: false,
                 },
             );
@@ -140,7 +289,7 @@ where
         }
 
         self.time = now;
-        self.state = State::Follower;
+        self.step_down();
 
         // Checks if we have a point of reference with the leader.
         if !storage.contains_entry(&EntryId::new(args.prev_log_index, args.prev_log_term)) {
@@ -149,6 +298,7 @@ where
                 EntriesReplicated {
                     node_id: self.id.clone(),
                     term: self.term,
+                    seq: args.seq,
                     success: false,
                 },
             );
@@ -169,6 +319,7 @@ where
                 EntriesReplicated {
                     node_id: self.id.clone(),
                     term: self.term,
+                    seq: args.seq,
                     success: true,
                 },
             );
@@ -183,8 +334,18 @@ where
             }
         }
 
+        let config_entry = args
+            .entries
+            .iter()
+            .rfind(|e| e.kind == EntryKind::Configuration)
+            .cloned();
+
         storage.append_entries(args.entries);
 
+        if let Some(entry) = config_entry {
+            self.apply_configuration_entry(&entry);
+        }
+
         if args.leader_commit > self.commit_index {
             self.commit_index = min(args.leader_commit, last_entry_index);
         }
@@ -194,6 +355,61 @@ where
             EntriesReplicated {
                 node_id: self.id.clone(),
                 term: self.term,
+                seq: args.seq,
+                success: true,
+            },
+        );
+    }
+
+    pub fn handle_install_snapshot<S, P>(
+        &mut self,
+        sender: &S,
+        storage: &mut P,
+        now: Instant,
+        args: InstallSnapshot<NodeId>,
+    ) where
+        S: RaftSender<Id = NodeId>,
+        P: PersistentStorage,
+    {
+        if self.term > args.term {
+            sender.entries_replicated(
+                args.leader_id,
+                EntriesReplicated {
+                    node_id: self.id.clone(),
+                    term: self.term,
+                    seq: args.seq,
+                    success: false,
+                },
+            );
+
+            return;
+        }
+
+        if self.term < args.term {
+            self.voted_for = None;
+            self.term = args.term;
+        }
+
+        self.time = now;
+        self.step_down();
+
+        // We might already have that snapshot or a more recent one if that message got delayed.
+        let last_included_index = args.snapshot.last_included_index;
+        let stale = storage
+            .latest_snapshot()
+            .is_some_and(|s| s.last_included_index >= last_included_index);
+
+        if !stale {
+            storage.install_snapshot(args.snapshot);
+            self.commit_index = max(self.commit_index, last_included_index);
+        }
+
+        sender.entries_replicated(
+            args.leader_id,
+            EntriesReplicated {
+                node_id: self.id.clone(),
+                term: self.term,
+                seq: args.seq,
                 success: true,
             },
         );
@@ -202,7 +418,7 @@ where
     pub fn handle_vote_received<P, S>(
         &mut self,
         time_range: &TimeRange,
-        storage: &P,
+        storage: &mut P,
         sender: &S,
         now: Instant,
         args: VoteReceived<NodeId>,
@@ -224,11 +440,11 @@ where
             return;
         }
 
-        if args.granted {
+        if args.granted && self.state == State::Candidate {
             self.tally.insert(args.node_id);
 
             // If the cluster reached quorum
-            if self.tally.len() + 1 >= self.replicas.len().div_ceil(2) {
+            if self.has_quorum() {
                 self.state = State::Leader;
 
                 let last_index = storage.last_entry().map(|e| e.index).unwrap_or_default();
@@ -237,56 +453,215 @@ where
                     replica.match_index = 0;
                 }
 
-                self.replicate_entries(storage, sender);
+                self.append_noop(storage);
+                self.replicate_entries(storage, sender, now);
             }
         }
     }
 
-    pub fn handle_entries_appended<D>(&mut self, dispatcher: &D, args: EntriesAppended<NodeId>)
-    where
+    pub fn handle_pre_vote_received<P, S>(
+        &mut self,
+        time_range: &TimeRange,
+        storage: &P,
+        sender: &S,
+        now: Instant,
+        args: VoteReceived<NodeId>,
+    ) where
+        P: PersistentStorage,
+        S: RaftSender<Id = NodeId>,
+    {
+        if self.state != State::PreCandidate {
+            return;
+        }
+
+        if !args.granted {
+            // The cluster moved on without us.
+            if self.term < args.term {
+                self.term = args.term;
+                self.voted_for = None;
+                self.state = State::Follower;
+                self.time = now;
+                self.election_timeout = time_range.new_timeout();
+            }
+
+            return;
+        }
+
+        if args.term != self.term + 1 {
+            return;
+        }
+
+        self.tally.insert(args.node_id);
+
+        if self.has_quorum() {
+            self.start_election(time_range, storage, sender, now);
+        }
+    }
+
+    pub fn handle_entries_appended<P, D>(
+        &mut self,
+        storage: &mut P,
+        dispatcher: &D,
+        args: EntriesAppended<NodeId>,
+    ) where
+        P: PersistentStorage,
         D: CommandDispatch<Command = Command>,
     {
         if self.state != State::Leader {
             return;
         }
 
-        if let Some(replica) = self.replicas.get_mut(&args.node_id) {
-            if args.success {
-                replica.match_index = replica.batch_end_index;
-                replica.next_index = replica.batch_end_index + 1;
+        let Some(replica) = self.replicas.get_mut(&args.node_id) else {
+            return;
+        };
 
-                let mut lowest_replicated_index = u64::MAX;
-                for replica in self.replicas.values() {
-                    lowest_replicated_index = min(lowest_replicated_index, replica.match_index);
-                }
+        let Some(batch) = replica.answered(args.seq) else {
+            return;
+        };
 
-                // Report all commands that got successfully replicated.
-                while let Some((index, cmd)) = self.inflights.pop_front() {
-                    if index <= lowest_replicated_index {
-                        dispatcher.dispatch(cmd);
-                    } else {
-                        // We reached a point where we didn't receive acknowledgement that
-                        // pass that point, the commands got replicated.
-                        self.inflights.push_front((index, cmd));
-                        break;
-                    }
-                }
+        if !args.success {
+            // FIXME - This is the simplest way of handling this. On large dataset, it
+            // could be beneficial for the replica to actually send an hint of where
+            // its log actually is.
+            replica.next_index = replica.next_index.saturating_sub(1);
+            return;
+        }
+
+        replica.match_index = batch.end_index;
+        replica.next_index = batch.end_index + 1;
+        replica.acked_at = Some(batch.sent_at);
+
+        // An entry is committed once a majority of the configuration has it, both the old and
+        // new one during a membership change.
+        let last_index = storage.last_entry_or_default().index;
+        let replicated_index = self.config.committed_index(|id| {
+            if *id == self.id {
+                return last_index;
+            }
+
+            self.replicas.get(id).map_or(0, |r| r.match_index)
+        });
 
-                self.commit_index = lowest_replicated_index;
+        // Report all commands that got successfully replicated.
+        while let Some((index, cmd)) = self.inflights.pop_front() {
+            if index <= replicated_index {
+                dispatcher.dispatch(cmd);
             } else {
-                // FIXME - This is the simplest way of handling this. On large dataset, it
-                // could be beneficial for the replica to actually send an hint of where
-                // its log actually is.
-                replica.next_index = replica.next_index.saturating_sub(1);
+                // We reached a point where we didn't receive acknowledgement that
+                // pass that point, the commands got replicated.
+                self.inflights.push_front((index, cmd));
+                break;
             }
         }
+
+        self.commit_index = max(self.commit_index, replicated_index);
+
+        // A majority followed us at that point, no other leader can be elected until the lease
+        // expires.
+        let contact = self.config.agreed(|id| {
+            if *id == self.id {
+                return self.replicated_at;
+            }
+
+            self.replicas.get(id).and_then(|r| r.acked_at)
+        });
+
+        if let Some(contact) = contact {
+            self.lease_expiry = Some(contact + self.lease_duration);
+            self.serve_pending_reads(dispatcher, contact);
+        }
+
+        let Some(config_index) = self.config_index else {
+            return;
+        };
+
+        if config_index > self.commit_index {
+            return;
+        }
+
+        if self.config.is_joint() {
+            // Both configurations agreed on the joint one, the new one can take over.
+            let config = self.config.finalized();
+            self.append_configuration(storage, config);
+        } else if !self.config.voters.contains(&self.id) {
+            // We got removed from the cluster.
+            self.state = State::Follower;
+        }
+    }
+
+    /// Starts moving the cluster to a new membership, by way of a joint configuration. Returns
+    /// `false` if the node isn't the leader, if another change is in progress or if the change
+    /// wouldn't change anything.
+    pub fn handle_membership_change<P>(
+        &mut self,
+        storage: &mut P,
+        change: MembershipChange<NodeId>,
+    ) -> bool
+    where
+        P: PersistentStorage,
+    {
+        if self.state != State::Leader || self.config.is_joint() {
+            return false;
+        }
+
+        if self
+            .config_index
+            .is_some_and(|index| index > self.commit_index)
+        {
+            return false;
+        }
+
+        let config = self.config.joint(&change);
+
+        if config.next.as_ref() == Some(&self.config.voters) {
+            return false;
+        }
+
+        self.append_configuration(storage, config);
+        true
     }
 
-    pub fn handle_command<D, P>(&mut self, storage: &mut P, dispatcher: &D, cmd: Command)
+    fn serve_pending_reads<D>(&mut self, dispatcher: &D, contact: Instant)
     where
+        D: CommandDispatch<Command = Command>,
+    {
+        while let Some((index, received, cmd)) = self.pending_reads.pop_front() {
+            if index > self.commit_index || received > contact {
+                self.pending_reads.push_front((index, received, cmd));
+                break;
+            }
+
+            dispatcher.dispatch(cmd);
+        }
+    }
+
+    pub fn handle_command<D, P>(
+        &mut self,
+        storage: &mut P,
+        dispatcher: &D,
+        now: Instant,
+        cmd: Command,
+    ) where
         P: PersistentStorage,
         D: CommandDispatch<Command = Command>,
     {
+        // The leader serves reads without going through the log. Within its lease no other
+        // leader can exist, so it's up-to-date. Otherwise, it first checks with a majority of the
+        // cluster it's still the leader, with the next round of heartbeats. Either way, a new
+        // leader waits for its no-op entry to commit, committing the entries before it.
+        if cmd.is_read() && self.state == State::Leader {
+            let lease_valid = self.lease_expiry.is_some_and(|expiry| now < expiry);
+            let read_index = max(self.commit_index, self.noop_index.unwrap_or_default());
+
+            if self.replicas.is_empty() || (lease_valid && read_index == self.commit_index) {
+                dispatcher.dispatch(cmd);
+            } else {
+                self.pending_reads.push_back((read_index, now, cmd));
+            }
+
+            return;
+        }
+
         // If we are dealing with a write command but are not the leader of the cluster,
         // we must refuse to serve the command.
         //
@@ -331,22 +706,27 @@ where
             return;
         }
 
+        // We lost leadership without hearing of a new leader, like when an election started.
+        if self.state != State::Leader {
+            self.reject_pending_reads();
+        }
+
         if self.state == State::Leader {
-            self.replicate_entries(storage, sender);
+            self.replicate_entries(storage, sender, now);
         } else if now.duration_since(self.time) >= self.election_timeout {
-            // We didn't hear form the leader a long time ago, time to start a new election.
-            self.state = State::Candidate;
-            self.term += 1;
-            self.voted_for = Some(self.id.clone());
+            // We didn't hear form the leader a long time ago. Before starting a new election,
+            // we check we could win it, so the term doesn't move if we can't.
+            self.state = State::PreCandidate;
+            self.tally.clear();
             self.election_timeout = time_range.new_timeout();
             self.time = now;
 
             let last_entry = storage.last_entry_or_default();
             for replica in self.replicas.values() {
-                sender.request_vote(
+                sender.pre_vote(
                     replica.id.clone(),
                     RequestVote {
-                        term: self.term,
+                        term: self.term + 1,
                         candidate_id: self.id.clone(),
                         last_log_index: last_entry.index,
                         last_log_term: last_entry.term,
@@ -356,12 +736,66 @@ where
         }
     }
 
-    pub fn replicate_entries<P, S>(&self, storage: &P, sender: &S)
-    where
+    fn start_election<P, S>(
+        &mut self,
+        time_range: &TimeRange,
+        storage: &P,
+        sender: &S,
+        now: Instant,
+    ) where
         P: PersistentStorage,
         S: RaftSender<Id = NodeId>,
     {
+        self.state = State::Candidate;
+        self.term += 1;
+        self.voted_for = Some(self.id.clone());
+        self.tally.clear();
+        self.election_timeout = time_range.new_timeout();
+        self.time = now;
+
+        let last_entry = storage.last_entry_or_default();
         for replica in self.replicas.values() {
+            sender.request_vote(
+                replica.id.clone(),
+                RequestVote {
+                    term: self.term,
+                    candidate_id: self.id.clone(),
+                    last_log_index: last_entry.index,
+                    last_log_term: last_entry.term,
+                },
+            );
+        }
+    }
+
+    pub fn replicate_entries<P, S>(&mut self, storage: &P, sender: &S, now: Instant)
+    where
+        P: PersistentStorage,
+        S: RaftSender<Id = NodeId>,
+    {
+        let snapshot = storage.latest_snapshot();
+        self.replicated_at = Some(now);
+
+        for replica in self.replicas.values_mut() {
+            // The entries the replica is missing got compacted, it gets the snapshot instead.
+            if let Some(snapshot) = snapshot
+                .as_ref()
+                .filter(|s| replica.next_index <= s.last_included_index)
+            {
+                let seq = replica.send_batch(now, snapshot.last_included_index);
+
+                sender.install_snapshot(
+                    replica.id.clone(),
+                    InstallSnapshot {
+                        term: self.term,
+                        leader_id: self.id.clone(),
+                        seq,
+                        snapshot: snapshot.clone(),
+                    },
+                );
+
+                continue;
+            }
+
             let prev_entry = storage.previous_entry_or_default(replica.next_index);
 
             let entries = storage.read_entries(prev_entry.index, 500);
@@ -376,11 +810,15 @@ where
                 }
 
                 Ok(entries) => {
+                    let end_index = entries.last().map_or(prev_entry.index, |e| e.index);
+                    let seq = replica.send_batch(now, end_index);
+
                     sender.replicate_entries(
                         replica.id.clone(),
                         AppendEntries {
                             term: self.term,
                             leader_id: self.id.clone(),
+                            seq,
                             prev_log_index: prev_entry.index,
                             prev_log_term: prev_entry.term,
                             leader_commit: self.commit_index,
@@ -6,9 +6,10 @@ use proptest::collection::vec;
 use proptest::prelude::{any, Strategy};
 use proptest::prop_compose;
 
-use crate::entry::Entry;
+use crate::entry::{Entry, EntryKind};
 use crate::{CommandDispatch, RaftCommand, RaftSender, Request, UserCommand};
 
+mod membership;
 mod sm;
 mod storage;
 
@@ -21,6 +22,7 @@ prop_compose! {
         Entry {
             index,
             term,
+            kind: EntryKind::Command,
             payload: Bytes::from(payload),
         }
     }
@@ -56,6 +58,13 @@ impl TestCommand {
         }
     }
 
+    pub fn read_command() -> Self {
+        Self {
+            reject: Arc::new(Default::default()),
+            kind: TestCommandKind::Read,
+        }
+    }
+
     pub fn is_rejected(&self) -> bool {
         self.reject.load(Ordering::SeqCst)
     }
@@ -132,6 +141,10 @@ impl<A> TestDispatch<A> {
             inner: Arc::new(Mutex::new(Vec::new())),
         }
     }
+
+    pub fn dispatched(&self) -> usize {
+        self.inner.lock().unwrap().len()
+    }
 }
 
 impl<A> CommandDispatch for TestDispatch<A>
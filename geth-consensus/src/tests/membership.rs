@@ -0,0 +1,30 @@
+use std::collections::BTreeSet;
+
+use bytes::BytesMut;
+
+use crate::membership::{Configuration, MembershipChange};
+
+#[test]
+fn test_joint_configuration_needs_both_majorities() {
+    let config = Configuration::new([0usize, 1]).joint(&MembershipChange::AddNode(2));
+
+    assert_eq!(BTreeSet::from([0, 1, 2]), config.members());
+
+    // A majority of the new configuration but not of the old one.
+    assert!(!config.has_quorum(|id| *id != 1));
+    assert!(config.has_quorum(|id| *id != 2));
+
+    let matches = [10, 4, 7];
+    assert_eq!(4, config.committed_index(|id| matches[*id]));
+    assert_eq!(7, config.finalized().committed_index(|id| matches[*id]));
+}
+
+#[test]
+fn test_configuration_roundtrip() {
+    let config = Configuration::new([0usize, 1]).joint(&MembershipChange::RemoveNode(1));
+    let mut buffer = BytesMut::new();
+
+    config.write(&mut buffer);
+
+    assert_eq!(Some(config), Configuration::read(buffer.freeze()));
+}
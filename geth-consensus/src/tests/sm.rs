@@ -1,13 +1,16 @@
 use std::time::{Duration, Instant};
 
+use bytes::Bytes;
 use proptest::proptest;
 
-use crate::entry::Entry;
-use crate::msg::{AppendEntries, VoteReceived};
+use crate::entry::{Entry, EntryKind};
+use crate::membership::{Configuration, MembershipChange};
+use crate::msg::{AppendEntries, EntriesAppended, VoteReceived};
+use crate::snapshot::Snapshot;
 use crate::state_machine::RaftSM;
 use crate::tests::storage::in_mem::InMemStorage;
 use crate::tests::{arb_entries, TestCommand, TestDispatch, TestSender};
-use crate::{PersistentStorage, Request, State, TimeRange};
+use crate::{IterateEntries, PersistentStorage, RaftSender, Request, State, TimeRange};
 
 proptest! {
     #[test]
@@ -45,6 +48,28 @@ proptest! {
     }
 }
 
+/// Times the node out and grants it the pre-vote of node 1, so it starts an election.
+fn start_election(
+    sm: &mut RaftSM<usize, TestCommand>,
+    time_range: &TimeRange,
+    storage: &InMemStorage,
+    sender: &TestSender<usize>,
+    now: Instant,
+) {
+    sm.handle_tick(time_range, storage, sender, now);
+    sm.handle_pre_vote_received(
+        time_range,
+        storage,
+        sender,
+        now,
+        VoteReceived {
+            node_id: 1,
+            term: sm.term + 1,
+            granted: true,
+        },
+    );
+}
+
 fn prop_follower_is_moving_to_candidate_on_timeout(entries: Vec<Entry>) {
     let node_id = 0;
     let seeds = (1usize..=2).collect::<Vec<_>>();
@@ -63,6 +88,44 @@ fn prop_follower_is_moving_to_candidate_on_timeout(entries: Vec<Entry>) {
 
     sm.handle_tick(&time_range, &storage, &sender, new_time);
 
+    // The term only moves once the node knows it could win the election.
+    assert_eq!(State::PreCandidate, sm.state);
+    assert_eq!(term, sm.term);
+    assert_eq!(None, sm.voted_for);
+
+    let mut reqs = sender.take();
+
+    reqs.sort_by(|a, b| a.target.cmp(&b.target));
+
+    assert_eq!(reqs.len(), seeds.len());
+
+    for (seed, req) in seeds.iter().zip(reqs.into_iter()) {
+        assert_eq!(*seed, req.target);
+
+        if let Request::PreVote(args) = req.request {
+            assert_eq!(node_id, args.candidate_id);
+            assert_eq!(term + 1, args.term);
+            assert_eq!(last_entry.index, args.last_log_index);
+            assert_eq!(last_entry.term, args.last_log_term);
+
+            continue;
+        }
+
+        panic!("We expected to only deal with pre-vote requests");
+    }
+
+    sm.handle_pre_vote_received(
+        &time_range,
+        &storage,
+        &sender,
+        new_time,
+        VoteReceived {
+            node_id: 1,
+            term: term + 1,
+            granted: true,
+        },
+    );
+
     assert_eq!(State::Candidate, sm.state);
     // While I would like to keep that check in-place but the fact is election_timeout depens on
     // RNG, so it's possible sometimes that `election_timeout` is updated with the same value it
@@ -106,14 +169,15 @@ fn prop_move_to_leader_when_garnered_enough_votes(entries: Vec<Entry>) {
     let election_timeout = sm.election_timeout;
     let new_time = Instant::now() + election_timeout;
 
-    sm.handle_tick(&time_range, &storage, &sender, new_time);
+    start_election(&mut sm, &time_range, &storage, &sender, new_time);
 
     // We clear the vote requests
     sender.take();
 
+    let last_entry = storage.last_entry();
     sm.handle_vote_received(
         &time_range,
-        &storage,
+        &mut storage,
         &sender,
         new_time + Duration::from_millis(10),
         VoteReceived {
@@ -133,7 +197,12 @@ fn prop_move_to_leader_when_garnered_enough_votes(entries: Vec<Entry>) {
     assert!(!reqs.is_empty());
     assert_eq!(seeds.len(), reqs.len());
 
-    let last_entry = storage.last_entry_or_default();
+    // The new leader appended a no-op entry of its term, replicated along the entries the
+    // followers might be missing.
+    let noop = storage.last_entry_or_default();
+    assert_eq!(Some(noop.index), sm.noop_index);
+    assert_eq!(sm.term, noop.term);
+
     for (seed, req) in seeds.into_iter().zip(reqs.into_iter()) {
         assert_eq!(seed, req.target);
 
@@ -141,8 +210,12 @@ fn prop_move_to_leader_when_garnered_enough_votes(entries: Vec<Entry>) {
             assert_eq!(args.leader_id, node_id);
             assert_eq!(args.leader_commit, sm.commit_index);
             assert_eq!(args.term, sm.term);
-            assert_eq!(args.prev_log_index, last_entry.index);
-            assert_eq!(args.prev_log_term, last_entry.term);
+            assert_eq!(Some(noop), args.entries.last().map(Entry::id));
+
+            if let Some(last_entry) = last_entry {
+                assert_eq!(args.prev_log_index, last_entry.index);
+                assert_eq!(args.prev_log_term, last_entry.term);
+            }
 
             continue;
         }
@@ -166,12 +239,8 @@ fn prop_move_from_candidate_to_follower_when_leader_show_up(entries: Vec<Entry>)
         seeds.clone(),
         Some(last_entry.term),
     );
-    sm.handle_tick(
-        &time_range,
-        &storage,
-        &sender,
-        Instant::now() + sm.election_timeout,
-    );
+    let new_time = Instant::now() + sm.election_timeout;
+    start_election(&mut sm, &time_range, &storage, &sender, new_time);
 
     assert_eq!(State::Candidate, sm.state);
     sender.take();
@@ -185,6 +254,7 @@ fn prop_move_from_candidate_to_follower_when_leader_show_up(entries: Vec<Entry>)
         AppendEntries {
             term: sm.term,
             leader_id: 1,
+            seq: 0,
             prev_log_index: last_entry.index,
             prev_log_term: last_entry.term,
             leader_commit: 0,
@@ -232,14 +302,14 @@ fn prop_move_from_leader_to_follower_if_better_leader_is_showing_up(entries: Vec
     let election_timeout = sm.election_timeout;
     let new_time = Instant::now() + election_timeout;
 
-    sm.handle_tick(&time_range, &storage, &sender, new_time);
+    start_election(&mut sm, &time_range, &storage, &sender, new_time);
 
     // We clear the vote requests
     sender.take();
 
     sm.handle_vote_received(
         &time_range,
-        &storage,
+        &mut storage,
         &sender,
         new_time + Duration::from_millis(10),
         VoteReceived {
@@ -260,6 +330,7 @@ fn prop_move_from_leader_to_follower_if_better_leader_is_showing_up(entries: Vec
         AppendEntries {
             term: new_term,
             leader_id: 2,
+            seq: 0,
             prev_log_index: last_entry.index,
             prev_log_term: last_entry.term,
             leader_commit: 0,
@@ -292,7 +363,622 @@ fn test_reject_command_if_not_leader() {
     assert_eq!(State::Follower, sm.state);
 
     let command = TestCommand::write_command();
-    sm.handle_command(&mut storage, &dispatch, command.clone());
+    sm.handle_command(&mut storage, &dispatch, Instant::now(), command.clone());
 
     assert!(command.is_rejected());
 }
+
+#[test]
+fn test_lagging_replica_catches_up_via_snapshot() {
+    let time_range = TimeRange::new(150, 300);
+    let leader_sender = TestSender::new();
+    let follower_sender = TestSender::new();
+    let mut leader_storage = InMemStorage::empty();
+    let mut follower_storage = InMemStorage::empty();
+
+    leader_storage.append_entries(
+        (1u64..=10)
+            .map(|index| Entry {
+                index,
+                term: 1,
+                kind: EntryKind::Command,
+                payload: Bytes::from(index.to_le_bytes().to_vec()),
+            })
+            .collect(),
+    );
+
+    let snapshot = Snapshot {
+        last_included_index: 6,
+        last_included_term: 1,
+        state: Bytes::from_static(b"state up to 6"),
+    };
+
+    leader_storage.install_snapshot(snapshot.clone());
+    leader_storage.compact_to(6);
+
+    let mut leader = RaftSM::<usize, TestCommand>::new(0, &time_range, vec![1, 2], Some(1));
+    let mut follower = RaftSM::<usize, TestCommand>::new(1, &time_range, vec![0, 2], None);
+
+    let now = Instant::now() + leader.election_timeout;
+    start_election(
+        &mut leader,
+        &time_range,
+        &leader_storage,
+        &leader_sender,
+        now,
+    );
+    leader_sender.take();
+    leader.handle_vote_received(
+        &time_range,
+        &mut leader_storage,
+        &leader_sender,
+        now,
+        VoteReceived {
+            node_id: 1,
+            term: leader.term,
+            granted: true,
+        },
+    );
+
+    assert_eq!(State::Leader, leader.state);
+
+    let mut snapshot_sent = false;
+    for _ in 0..20 {
+        for req in leader_sender.take() {
+            if req.target != 1 {
+                continue;
+            }
+
+            match req.request {
+                Request::AppendEntries(args) => follower.handle_append_entries(
+                    &follower_sender,
+                    &mut follower_storage,
+                    now,
+                    args,
+                ),
+
+                Request::InstallSnapshot(args) => {
+                    snapshot_sent = true;
+                    follower.handle_install_snapshot(
+                        &follower_sender,
+                        &mut follower_storage,
+                        now,
+                        args,
+                    )
+                }
+
+                _ => panic!("We expected to only deal with replication requests"),
+            }
+        }
+
+        for resp in follower_sender.take() {
+            if let Request::EntriesReplicated(args) = resp.request {
+                leader.handle_entries_appended(
+                    &mut leader_storage,
+                    &TestDispatch::new(),
+                    EntriesAppended {
+                        node_id: args.node_id,
+                        term: args.term,
+                        seq: args.seq,
+                        success: args.success,
+                    },
+                );
+            }
+        }
+
+        if follower_storage.last_entry() == leader_storage.last_entry() {
+            break;
+        }
+
+        leader.handle_tick(&time_range, &leader_storage, &leader_sender, now);
+    }
+
+    assert!(snapshot_sent);
+    assert_eq!(Some(snapshot), follower_storage.latest_snapshot());
+    assert!(follower.commit_index >= 6);
+
+    let expected = leader_storage.read_all().collect().unwrap();
+    let actual = follower_storage.read_all().collect().unwrap();
+
+    assert_eq!(
+        expected.iter().map(Entry::id).collect::<Vec<_>>(),
+        actual.iter().map(Entry::id).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_flapping_node_does_not_disrupt_stable_leader() {
+    let time_range = TimeRange::new(150, 300);
+    let leader_sender = TestSender::new();
+    let follower_sender = TestSender::new();
+    let flapping_sender = TestSender::new();
+    let mut storage = InMemStorage::empty();
+    let mut follower_storage = InMemStorage::empty();
+    let flapping_storage = InMemStorage::empty();
+
+    let mut leader = RaftSM::<usize, TestCommand>::new(0, &time_range, vec![1, 2], None);
+    let mut follower = RaftSM::<usize, TestCommand>::new(1, &time_range, vec![0, 2], None);
+    let mut flapping = RaftSM::<usize, TestCommand>::new(2, &time_range, vec![0, 1], None);
+
+    let now = Instant::now() + leader.election_timeout;
+    start_election(&mut leader, &time_range, &storage, &leader_sender, now);
+    leader.handle_vote_received(
+        &time_range,
+        &mut storage,
+        &leader_sender,
+        now,
+        VoteReceived {
+            node_id: 1,
+            term: leader.term,
+            granted: true,
+        },
+    );
+
+    assert_eq!(State::Leader, leader.state);
+    let term = leader.term;
+
+    // The follower keeps hearing from the leader.
+    for req in leader_sender.take() {
+        if let (1, Request::AppendEntries(args)) = (req.target, req.request) {
+            follower.handle_append_entries(&follower_sender, &mut follower_storage, now, args);
+        }
+    }
+
+    follower_sender.take();
+
+    // The partitioned node times out over and over, asking for pre-votes each time.
+    let mut flapping_time = now;
+    for _ in 0..5 {
+        flapping_time += flapping.election_timeout;
+        flapping.handle_tick(
+            &time_range,
+            &flapping_storage,
+            &flapping_sender,
+            flapping_time,
+        );
+
+        assert_eq!(State::PreCandidate, flapping.state);
+
+        for req in flapping_sender.take() {
+            let Request::PreVote(args) = req.request else {
+                panic!("We expected to only deal with pre-vote requests");
+            };
+
+            let (voter_sender, at) = match req.target {
+                0 => {
+                    leader.handle_pre_vote(&leader_sender, &storage, now, args);
+                    (&leader_sender, now)
+                }
+
+                1 => {
+                    // Within the election timeout of the last time it heard from the leader.
+                    follower.handle_pre_vote(&follower_sender, &follower_storage, now, args);
+                    (&follower_sender, now)
+                }
+
+                _ => unreachable!(),
+            };
+
+            for resp in voter_sender.take() {
+                let Request::PreVoteCasted(resp) = resp.request else {
+                    panic!("We expected to only deal with pre-vote responses");
+                };
+
+                assert!(!resp.granted);
+
+                flapping.handle_pre_vote_received(
+                    &time_range,
+                    &flapping_storage,
+                    &flapping_sender,
+                    at,
+                    VoteReceived {
+                        node_id: resp.node_id,
+                        term: resp.term,
+                        granted: resp.granted,
+                    },
+                );
+            }
+        }
+    }
+
+    // Nobody started an election, the leader and its term stay as they were.
+    assert_eq!(State::Leader, leader.state);
+    assert_eq!(term, leader.term);
+    assert_eq!(term, follower.term);
+    assert_eq!(State::Follower, follower.state);
+    assert_ne!(State::Candidate, flapping.state);
+    assert!(flapping.term <= term);
+}
+
+struct TestNode {
+    sm: RaftSM<usize, TestCommand>,
+    storage: InMemStorage,
+    sender: TestSender<usize>,
+    dispatch: TestDispatch<TestCommand>,
+}
+
+impl TestNode {
+    fn new(id: usize, seeds: Vec<usize>, time_range: &TimeRange) -> Self {
+        Self {
+            sm: RaftSM::new(id, time_range, seeds, None),
+            storage: InMemStorage::empty(),
+            sender: TestSender::new(),
+            dispatch: TestDispatch::new(),
+        }
+    }
+}
+
+/// Delivers what the nodes send each other until nothing is left, dropping what is sent to or
+/// from `unreachable` nodes. Node ids are their position in `nodes`.
+fn deliver(nodes: &mut [TestNode], time_range: &TimeRange, now: Instant, unreachable: &[usize]) {
+    loop {
+        let mut reqs = Vec::new();
+        for (from, node) in nodes.iter().enumerate() {
+            for req in node.sender.take() {
+                if !unreachable.contains(&from) && !unreachable.contains(&req.target) {
+                    reqs.push(req);
+                }
+            }
+        }
+
+        if reqs.is_empty() {
+            break;
+        }
+
+        for req in reqs {
+            let node = &mut nodes[req.target];
+
+            match req.request {
+                Request::RequestVote(args) => {
+                    node.sm
+                        .handle_request_vote(&node.sender, &node.storage, args)
+                }
+
+                Request::PreVote(args) => {
+                    node.sm
+                        .handle_pre_vote(&node.sender, &node.storage, now, args)
+                }
+
+                Request::AppendEntries(args) => {
+                    node.sm
+                        .handle_append_entries(&node.sender, &mut node.storage, now, args)
+                }
+
+                Request::InstallSnapshot(args) => {
+                    node.sm
+                        .handle_install_snapshot(&node.sender, &mut node.storage, now, args)
+                }
+
+                Request::VoteCasted(resp) => node.sm.handle_vote_received(
+                    time_range,
+                    &mut node.storage,
+                    &node.sender,
+                    now,
+                    VoteReceived {
+                        node_id: resp.node_id,
+                        term: resp.term,
+                        granted: resp.granted,
+                    },
+                ),
+
+                Request::PreVoteCasted(resp) => node.sm.handle_pre_vote_received(
+                    time_range,
+                    &node.storage,
+                    &node.sender,
+                    now,
+                    VoteReceived {
+                        node_id: resp.node_id,
+                        term: resp.term,
+                        granted: resp.granted,
+                    },
+                ),
+
+                Request::EntriesReplicated(resp) => node.sm.handle_entries_appended(
+                    &mut node.storage,
+                    &node.dispatch,
+                    EntriesAppended {
+                        node_id: resp.node_id,
+                        term: resp.term,
+                        seq: resp.seq,
+                        success: resp.success,
+                    },
+                ),
+            }
+        }
+    }
+}
+
+/// Submits a command to the leader, node 0, and replicates it.
+fn propose(nodes: &mut [TestNode], time_range: &TimeRange, now: Instant, unreachable: &[usize]) {
+    let leader = &mut nodes[0];
+    leader.sm.handle_command(
+        &mut leader.storage,
+        &leader.dispatch,
+        now,
+        TestCommand::write_command(),
+    );
+
+    // The first round replicates the command, the second lets the followers know it committed.
+    for _ in 0..2 {
+        let leader = &mut nodes[0];
+        leader
+            .sm
+            .handle_tick(time_range, &leader.storage, &leader.sender, now);
+
+        deliver(nodes, time_range, now, unreachable);
+    }
+}
+
+#[test]
+fn test_add_node_to_two_node_cluster_through_joint_consensus() {
+    let time_range = TimeRange::new(150, 300);
+    let mut nodes = vec![
+        TestNode::new(0, vec![1], &time_range),
+        TestNode::new(1, vec![0], &time_range),
+        // Not part of the cluster yet, nobody talks to it until it's added.
+        TestNode::new(2, vec![0, 1], &time_range),
+    ];
+
+    let now = Instant::now() + nodes[0].sm.election_timeout;
+    let leader = &mut nodes[0];
+    start_election(
+        &mut leader.sm,
+        &time_range,
+        &leader.storage,
+        &leader.sender,
+        now,
+    );
+    deliver(&mut nodes, &time_range, now, &[]);
+
+    assert_eq!(State::Leader, nodes[0].sm.state);
+    let term = nodes[0].sm.term;
+
+    propose(&mut nodes, &time_range, now, &[]);
+    assert_eq!(1, nodes[0].dispatch.dispatched());
+
+    let leader = &mut nodes[0];
+    assert!(leader
+        .sm
+        .handle_membership_change(&mut leader.storage, MembershipChange::AddNode(2)));
+
+    // Another change has to wait for this one to complete.
+    assert!(!leader
+        .sm
+        .handle_membership_change(&mut leader.storage, MembershipChange::RemoveNode(1)));
+
+    assert!(leader.sm.config.is_joint());
+    assert!(leader.sm.replicas.contains_key(&2));
+
+    // The new node lags behind, the old configuration and a majority of the new one still
+    // commit commands.
+    propose(&mut nodes, &time_range, now, &[2]);
+    assert_eq!(2, nodes[0].dispatch.dispatched());
+
+    // The joint configuration committed, the leader moved on to the new one and keeps
+    // committing.
+    let expected = Configuration::new([0, 1, 2]);
+    assert_eq!(expected, nodes[0].sm.config);
+    assert_eq!(expected, nodes[1].sm.config);
+
+    propose(&mut nodes, &time_range, now, &[2]);
+    assert_eq!(3, nodes[0].dispatch.dispatched());
+
+    // Once reachable, the new node catches up and follows the new configuration.
+    propose(&mut nodes, &time_range, now, &[]);
+    assert_eq!(4, nodes[0].dispatch.dispatched());
+
+    assert_eq!(expected, nodes[2].sm.config);
+    assert_eq!(State::Follower, nodes[2].sm.state);
+    assert_eq!(nodes[0].storage.last_entry(), nodes[2].storage.last_entry());
+
+    let mut replicas = nodes[2].sm.replicas.keys().copied().collect::<Vec<_>>();
+    replicas.sort();
+    assert_eq!(vec![0, 1], replicas);
+
+    // The leader stayed in charge the whole time.
+    assert_eq!(State::Leader, nodes[0].sm.state);
+    assert_eq!(term, nodes[0].sm.term);
+}
+
+/// Three node cluster where node 0 got elected at the returned time.
+fn three_node_cluster(time_range: &TimeRange) -> (Vec<TestNode>, Instant) {
+    let mut nodes = vec![
+        TestNode::new(0, vec![1, 2], time_range),
+        TestNode::new(1, vec![0, 2], time_range),
+        TestNode::new(2, vec![0, 1], time_range),
+    ];
+
+    let now = Instant::now() + nodes[0].sm.election_timeout;
+    let leader = &mut nodes[0];
+    start_election(
+        &mut leader.sm,
+        time_range,
+        &leader.storage,
+        &leader.sender,
+        now,
+    );
+    deliver(&mut nodes, time_range, now, &[]);
+
+    assert_eq!(State::Leader, nodes[0].sm.state);
+
+    (nodes, now)
+}
+
+fn read(node: &mut TestNode, now: Instant) -> TestCommand {
+    let cmd = TestCommand::read_command();
+    node.sm
+        .handle_command(&mut node.storage, &node.dispatch, now, cmd.clone());
+
+    cmd
+}
+
+#[test]
+fn test_leader_serves_reads_locally_within_lease() {
+    let time_range = TimeRange::new(150, 300).with_lease(Duration::from_millis(100));
+    let (mut nodes, now) = three_node_cluster(&time_range);
+    let last_entry = nodes[0].storage.last_entry();
+
+    read(&mut nodes[0], now + Duration::from_millis(50));
+
+    assert_eq!(1, nodes[0].dispatch.dispatched());
+    // Reads don't go through the log.
+    assert_eq!(last_entry, nodes[0].storage.last_entry());
+}
+
+#[test]
+fn test_leader_checks_with_majority_once_lease_expired() {
+    let time_range = TimeRange::new(150, 300).with_lease(Duration::from_millis(100));
+    let (mut nodes, now) = three_node_cluster(&time_range);
+    let later = now + Duration::from_millis(200);
+
+    let cmd = read(&mut nodes[0], later);
+
+    assert_eq!(0, nodes[0].dispatch.dispatched());
+
+    // The next round of heartbeats confirms the node is still the leader.
+    let leader = &mut nodes[0];
+    leader
+        .sm
+        .handle_tick(&time_range, &leader.storage, &leader.sender, later);
+    deliver(&mut nodes, &time_range, later, &[]);
+
+    assert_eq!(1, nodes[0].dispatch.dispatched());
+    assert!(!cmd.is_rejected());
+}
+
+#[test]
+fn test_stale_leader_refuses_local_reads() {
+    let time_range = TimeRange::new(150, 300).with_lease(Duration::from_millis(100));
+    let (mut nodes, now) = three_node_cluster(&time_range);
+    let later = now + Duration::from_millis(200);
+
+    // The leader got partitioned and its lease ran out.
+    let cmd = read(&mut nodes[0], later);
+    let leader = &mut nodes[0];
+    leader
+        .sm
+        .handle_tick(&time_range, &leader.storage, &leader.sender, later);
+    deliver(&mut nodes, &time_range, later, &[0]);
+
+    assert_eq!(State::Leader, nodes[0].sm.state);
+    assert_eq!(0, nodes[0].dispatch.dispatched());
+    assert!(!cmd.is_rejected());
+
+    // Meanwhile, the rest of the cluster elected another leader.
+    let last_entry = nodes[0].storage.last_entry_or_default();
+    let stale = &mut nodes[0];
+    stale.sm.handle_append_entries(
+        &stale.sender,
+        &mut stale.storage,
+        later,
+        AppendEntries {
+            term: stale.sm.term + 1,
+            leader_id: 1,
+            seq: 0,
+            prev_log_index: last_entry.index,
+            prev_log_term: last_entry.term,
+            leader_commit: 0,
+            entries: vec![],
+        },
+    );
+
+    assert_eq!(State::Follower, nodes[0].sm.state);
+    assert_eq!(0, nodes[0].dispatch.dispatched());
+    assert!(cmd.is_rejected());
+}
+
+#[test]
+fn test_lease_starts_when_the_acknowledged_batch_was_sent() {
+    let time_range = TimeRange::new(150, 300).with_lease(Duration::from_millis(100));
+    let (mut nodes, now) = three_node_cluster(&time_range);
+    let first = now + Duration::from_millis(200);
+    let second = now + Duration::from_millis(400);
+
+    // The heartbeats sent at `first` are only answered after the ones sent at `second` got lost.
+    let leader = &mut nodes[0];
+    leader
+        .sm
+        .handle_tick(&time_range, &leader.storage, &leader.sender, first);
+    let delayed = leader.sender.take();
+    leader
+        .sm
+        .handle_tick(&time_range, &leader.storage, &leader.sender, second);
+    leader.sender.take();
+
+    for req in delayed {
+        nodes[0].sender.send(req.target, req.request);
+    }
+
+    deliver(&mut nodes, &time_range, second, &[]);
+
+    // A majority followed the leader at `first`, that lease expired before `second`.
+    let cmd = read(&mut nodes[0], second + Duration::from_millis(10));
+
+    assert_eq!(0, nodes[0].dispatch.dispatched());
+    assert!(!cmd.is_rejected());
+}
+
+#[test]
+fn test_new_leader_waits_for_its_noop_to_commit_before_serving_reads() {
+    let time_range = TimeRange::new(150, 300).with_lease(Duration::from_millis(100));
+    let mut nodes = vec![
+        TestNode::new(0, vec![1, 2], &time_range),
+        TestNode::new(1, vec![0, 2], &time_range),
+        TestNode::new(2, vec![0, 1], &time_range),
+    ];
+
+    // An entry of a previous term, the next leader can't tell whether it committed.
+    for node in nodes.iter_mut() {
+        node.sm.term = 1;
+        node.storage.append_entries(vec![Entry {
+            index: 0,
+            term: 1,
+            kind: EntryKind::Command,
+            payload: Bytes::new(),
+        }]);
+    }
+
+    let now = Instant::now() + nodes[0].sm.election_timeout;
+    let leader = &mut nodes[0];
+    start_election(
+        &mut leader.sm,
+        &time_range,
+        &leader.storage,
+        &leader.sender,
+        now,
+    );
+    leader.sender.take();
+    leader.sm.handle_vote_received(
+        &time_range,
+        &mut leader.storage,
+        &leader.sender,
+        now,
+        VoteReceived {
+            node_id: 1,
+            term: leader.sm.term,
+            granted: true,
+        },
+    );
+
+    assert_eq!(State::Leader, leader.sm.state);
+    let noop = leader.storage.last_entry_or_default();
+    assert_eq!(Some(noop.index), leader.sm.noop_index);
+    assert_eq!(leader.sm.term, noop.term);
+
+    // The first round of replication got lost. Even within a lease, the read waits.
+    leader.sender.take();
+    leader.sm.lease_expiry = Some(now + Duration::from_millis(100));
+    let cmd = read(&mut nodes[0], now);
+
+    assert_eq!(0, nodes[0].dispatch.dispatched());
+
+    let leader = &mut nodes[0];
+    leader
+        .sm
+        .handle_tick(&time_range, &leader.storage, &leader.sender, now);
+    deliver(&mut nodes, &time_range, now, &[]);
+
+    assert_eq!(noop.index, nodes[0].sm.commit_index);
+    assert_eq!(1, nodes[0].dispatch.dispatched());
+    assert!(!cmd.is_rejected());
+}
@@ -1,6 +1,9 @@
 use rand::Rng;
 
+use bytes::Bytes;
+
 use crate::entry::{Entry, EntryId};
+use crate::snapshot::Snapshot;
 use crate::IterateEntries;
 use crate::PersistentStorage;
 
@@ -89,3 +92,94 @@ fn prop_contains_entry_when_empty<S: PersistentStorage>() {
 
     assert!(storage.contains_entry(&EntryId::new(0, 0)));
 }
+
+fn random_point(entries: &[Entry]) -> Option<&Entry> {
+    if entries.is_empty() {
+        return None;
+    }
+
+    entries.get(rand::thread_rng().gen_range(0usize..entries.len()))
+}
+
+fn prop_install_snapshot_keeps_following_entries<S: PersistentStorage>(entries: Vec<Entry>) {
+    let mut storage = S::empty();
+    storage.append_entries(entries.clone());
+
+    let Some(point) = random_point(&entries) else {
+        return;
+    };
+
+    let snapshot = Snapshot {
+        last_included_index: point.index,
+        last_included_term: point.term,
+        state: Bytes::from_static(b"state"),
+    };
+
+    storage.install_snapshot(snapshot.clone());
+
+    let mut expected = entries.clone();
+    expected.retain(|e| e.index > point.index);
+
+    let actual = storage.read_all().collect().unwrap();
+
+    assert_eq!(expected.len(), actual.len());
+
+    for (a, b) in expected.iter().zip(actual.iter()) {
+        assert_eq!(a.id(), b.id());
+    }
+
+    assert_eq!(Some(snapshot), storage.latest_snapshot());
+    assert!(storage.contains_entry(&point.id()));
+    assert_eq!(
+        entries.last().map(Entry::id),
+        storage.last_entry(),
+        "the last entry is either retained or the snapshot's last included one"
+    );
+}
+
+/// We assume that `term` is not in the submitted entry set.
+fn prop_install_snapshot_discards_diverging_log<S: PersistentStorage>(
+    entries: Vec<Entry>,
+    index: u64,
+    term: u64,
+) {
+    let mut storage = S::empty();
+    storage.append_entries(entries);
+
+    let snapshot = Snapshot {
+        last_included_index: index,
+        last_included_term: term,
+        state: Bytes::from_static(b"state"),
+    };
+
+    storage.install_snapshot(snapshot);
+
+    assert!(storage.read_all().collect().unwrap().is_empty());
+    assert_eq!(Some(EntryId::new(index, term)), storage.last_entry());
+    assert_eq!(
+        Some(EntryId::new(index, term)),
+        storage.previous_entry(index + 1)
+    );
+}
+
+fn prop_compact_to<S: PersistentStorage>(entries: Vec<Entry>) {
+    let mut storage = S::empty();
+    storage.append_entries(entries.clone());
+
+    let Some(point) = random_point(&entries) else {
+        return;
+    };
+
+    storage.compact_to(point.index);
+
+    let mut expected = entries.clone();
+    expected.retain(|e| e.index > point.index);
+
+    let actual = storage.read_all().collect().unwrap();
+
+    assert_eq!(expected.len(), actual.len());
+
+    for (a, b) in expected.iter().zip(actual.iter()) {
+        assert_eq!(a.id(), b.id());
+    }
+}
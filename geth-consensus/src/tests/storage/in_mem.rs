@@ -1,20 +1,26 @@
 use proptest::proptest;
 
 use crate::entry::{Entry, EntryId};
+use crate::snapshot::Snapshot;
 use crate::tests::arb_entries;
 use crate::tests::storage::{
-    prop_append_entries_and_read_all, prop_contains_entry_when_empty, prop_contains_non_existing,
-    prop_previous_entry, prop_remove_entries,
+    prop_append_entries_and_read_all, prop_compact_to, prop_contains_entry_when_empty,
+    prop_contains_non_existing, prop_install_snapshot_discards_diverging_log,
+    prop_install_snapshot_keeps_following_entries, prop_previous_entry, prop_remove_entries,
 };
 use crate::{IterateEntries, PersistentStorage};
 
 pub struct InMemStorage {
     inner: Vec<Entry>,
+    snapshot: Option<Snapshot>,
 }
 
 impl PersistentStorage for InMemStorage {
     fn empty() -> Self {
-        Self { inner: Vec::new() }
+        Self {
+            inner: Vec::new(),
+            snapshot: None,
+        }
     }
 
     fn append_entries(&mut self, entries: Vec<Entry>) {
@@ -37,10 +43,13 @@ impl PersistentStorage for InMemStorage {
     }
 
     fn last_entry(&self) -> Option<EntryId> {
-        self.inner.last().map(|e| EntryId {
-            index: e.index,
-            term: e.term,
-        })
+        self.inner
+            .last()
+            .map(|e| EntryId {
+                index: e.index,
+                term: e.term,
+            })
+            .or_else(|| self.snapshot.as_ref().map(Snapshot::last_included))
     }
 
     fn previous_entry(&self, index: u64) -> Option<EntryId> {
@@ -57,18 +66,49 @@ impl PersistentStorage for InMemStorage {
             });
         }
 
-        prev
+        prev.or_else(|| {
+            self.snapshot
+                .as_ref()
+                .filter(|s| s.last_included_index < index)
+                .map(Snapshot::last_included)
+        })
     }
 
     fn contains_entry(&self, entry_id: &EntryId) -> bool {
-        if self.inner.is_empty() && entry_id.index == 0 {
+        if self.inner.is_empty() && self.snapshot.is_none() && entry_id.index == 0 {
             return true;
         }
 
+        if let Some(snapshot) = &self.snapshot {
+            if snapshot.last_included() == *entry_id {
+                return true;
+            }
+        }
+
         self.inner
             .iter()
             .any(|e| e.index == entry_id.index && e.term == entry_id.term)
     }
+
+    fn install_snapshot(&mut self, snapshot: Snapshot) {
+        let last_included = snapshot.last_included();
+
+        if self.inner.iter().any(|e| e.id() == last_included) {
+            self.inner.retain(|e| e.index > last_included.index);
+        } else {
+            self.inner.clear();
+        }
+
+        self.snapshot = Some(snapshot);
+    }
+
+    fn latest_snapshot(&self) -> Option<Snapshot> {
+        self.snapshot.clone()
+    }
+
+    fn compact_to(&mut self, index: u64) {
+        self.inner.retain(|e| e.index > index);
+    }
 }
 
 struct InMemIter<'a> {
@@ -145,6 +185,35 @@ proptest! {
     }
 }
 
+proptest! {
+    #[test]
+    fn test_in_mem_install_snapshot_keeps_following_entries(
+        entries in arb_entries(0u64 ..= 100),
+    ) {
+        prop_install_snapshot_keeps_following_entries::<InMemStorage>(entries);
+    }
+}
+
+proptest! {
+    #[test]
+    fn test_in_mem_install_snapshot_discards_diverging_log(
+        entries in arb_entries(0u64 ..= 100),
+        index in 0u64 ..= 200,
+        term in 200u64 ..= 300,
+    ) {
+        prop_install_snapshot_discards_diverging_log::<InMemStorage>(entries, index, term);
+    }
+}
+
+proptest! {
+    #[test]
+    fn test_in_mem_compact_to(
+        entries in arb_entries(0u64 ..= 100),
+    ) {
+        prop_compact_to::<InMemStorage>(entries);
+    }
+}
+
 #[test]
 fn test_in_mem_contains_entry_when_empty() {
     prop_contains_entry_when_empty::<InMemStorage>();
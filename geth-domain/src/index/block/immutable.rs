@@ -107,13 +107,13 @@ impl Block {
         let mut low = 0usize;
         let mut high = self.len();
 
-        while low <= high {
-            let mid = (low + high) / 2;
+        while low < high {
+            let mid = low + (high - low) / 2;
             let entry = self.try_read(mid)?;
 
             match entry.cmp_key_rev(key, revision) {
                 Ordering::Less => low = mid + 1,
-                Ordering::Greater => high = mid - 1,
+                Ordering::Greater => high = mid,
                 Ordering::Equal => {
                     return Some(entry);
                 }
@@ -0,0 +1,75 @@
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+pub const DEFAULT_BLOOM_BITS_PER_KEY: usize = 10;
+
+/// Bloom filter over the keys of an sstable. Answers whether a key is definitely absent from the
+/// table, or might be in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BloomFilter {
+    hash_count: u8,
+    bits: Bytes,
+}
+
+impl BloomFilter {
+    pub fn build(keys: &[u64], bits_per_key: usize) -> Self {
+        // ln(2) * bits_per_key minimizes the false positive rate.
+        let hash_count = ((bits_per_key as f64 * 0.69) as usize).clamp(1, 30) as u8;
+        let bit_count = (keys.len() * bits_per_key).max(64);
+        let mut bits = vec![0u8; bit_count.div_ceil(8)];
+        let bit_count = bits.len() * 8;
+
+        for key in keys {
+            for bit in probes(*key, hash_count, bit_count) {
+                bits[bit / 8] |= 1 << (bit % 8);
+            }
+        }
+
+        Self {
+            hash_count,
+            bits: Bytes::from(bits),
+        }
+    }
+
+    pub fn may_contain(&self, key: u64) -> bool {
+        if self.bits.is_empty() {
+            return true;
+        }
+
+        probes(key, self.hash_count, self.bits.len() * 8)
+            .all(|bit| self.bits[bit / 8] & (1 << (bit % 8)) != 0)
+    }
+
+    pub fn size(&self) -> usize {
+        1 + self.bits.len()
+    }
+
+    pub fn put(&self, buffer: &mut BytesMut) {
+        buffer.put_u8(self.hash_count);
+        buffer.put_slice(&self.bits);
+    }
+
+    pub fn get(mut buffer: Bytes) -> Self {
+        let hash_count = buffer.get_u8();
+
+        Self {
+            hash_count,
+            bits: buffer,
+        }
+    }
+}
+
+/// Double hashing, the probes are derived from a single 64 bits hash of the key.
+fn probes(key: u64, hash_count: u8, bit_count: usize) -> impl Iterator<Item = usize> {
+    let hash = mix(key);
+    let delta = hash.rotate_right(17) | 1;
+
+    (0..hash_count as u64)
+        .map(move |i| (hash.wrapping_add(i.wrapping_mul(delta)) % bit_count as u64) as usize)
+}
+
+// Finalizer of splitmix64, keys are already hashes but spreading them again is cheap.
+fn mix(mut value: u64) -> u64 {
+    value = (value ^ (value >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94d049bb133111eb);
+    value ^ (value >> 31)
+}
@@ -6,6 +6,7 @@ use bytes::{Buf, BufMut, BytesMut};
 use uuid::Uuid;
 
 use crate::index::block::BlockEntry;
+use crate::index::bloom::DEFAULT_BLOOM_BITS_PER_KEY;
 use crate::index::mem_table::MemTable;
 use crate::index::merge::Merge;
 use crate::index::ss_table::SsTable;
@@ -14,16 +15,47 @@ use geth_mikoshi::storage::{FileId, Storage};
 
 pub const LSM_DEFAULT_MEM_TABLE_SIZE: usize = 4_096;
 pub const LSM_BASE_SSTABLE_BLOCK_COUNT: usize = 4;
-
-pub fn sst_table_block_count_limit(level: u8) -> usize {
-    (2 ^ (level as usize)) * LSM_BASE_SSTABLE_BLOCK_COUNT
-}
+pub const LSM_DEFAULT_LEVEL_SIZE_MULTIPLIER: usize = 10;
 
 #[derive(Debug, Clone, Copy)]
 pub struct LsmSettings {
     pub mem_table_max_size: usize,
+    /// Number of sstables a level reaches before they are merged into a single table of the next
+    /// level.
     pub ss_table_max_count: usize,
     pub base_block_size: usize,
+    /// Number of blocks the sstables of level 1 reach before they are merged into level 2.
+    pub level_base_block_count: usize,
+    /// How many times more blocks a level holds compared to the previous one before being merged
+    /// into the next level. Doesn't apply to level 0, only bounded by its number of sstables.
+    pub level_size_multiplier: usize,
+    /// Leaves compaction to the caller, see [`Lsm::next_compaction`]. Otherwise, compaction runs
+    /// right after a memtable is flushed.
+    pub background_compaction: bool,
+    /// Bits of the bloom filter of each sstable per distinct key. Lookups skip the tables whose
+    /// filter rules the key out. Zero disables the filters.
+    pub bloom_bits_per_key: usize,
+}
+
+impl LsmSettings {
+    /// Number of blocks a level reaches before being merged into the next one.
+    pub fn level_block_limit(&self, level: u8) -> usize {
+        let exp = u32::from(level.saturating_sub(1));
+
+        self.level_size_multiplier
+            .saturating_pow(exp)
+            .saturating_mul(self.level_base_block_count)
+    }
+
+    fn needs_compaction(&self, level: u8, tables: &VecDeque<SsTable>) -> bool {
+        if tables.len() >= self.ss_table_max_count {
+            return true;
+        }
+
+        level > 0
+            && tables.len() > 1
+            && tables.iter().map(|t| t.len()).sum::<usize>() >= self.level_block_limit(level)
+    }
 }
 
 impl Default for LsmSettings {
@@ -32,10 +64,52 @@ impl Default for LsmSettings {
             mem_table_max_size: LSM_DEFAULT_MEM_TABLE_SIZE,
             ss_table_max_count: LSM_BASE_SSTABLE_BLOCK_COUNT,
             base_block_size: 4_096,
+            level_base_block_count: LSM_BASE_SSTABLE_BLOCK_COUNT
+                * LSM_DEFAULT_LEVEL_SIZE_MULTIPLIER,
+            level_size_multiplier: LSM_DEFAULT_LEVEL_SIZE_MULTIPLIER,
+            background_compaction: false,
+            bloom_bits_per_key: DEFAULT_BLOOM_BITS_PER_KEY,
         }
     }
 }
 
+/// Snapshot of the sstables of a level, merged into a single sstable of the next level.
+///
+/// Merging doesn't need access to the [`Lsm`], so it can run while the index keeps serving reads
+/// and writes. The result is then handed back to [`Lsm::apply_compaction`].
+#[derive(Debug, Clone)]
+pub struct Compaction {
+    pub level: u8,
+    tables: Vec<SsTable>,
+    storage: Storage,
+    block_size: usize,
+    bloom_bits_per_key: usize,
+}
+
+impl Compaction {
+    pub fn tables(&self) -> &[SsTable] {
+        &self.tables
+    }
+
+    /// Writes the merged sstable, which isn't referred to by the index until the compaction is
+    /// applied.
+    pub fn run(&self) -> io::Result<SsTable> {
+        let mut builder = Merge::builder_for_ss_tables_only();
+
+        // Tables are ordered from the most recent to the oldest, so the merge keeps the newest
+        // entry when a key/revision pair is found in several tables.
+        for table in &self.tables {
+            builder.push_ss_table_scan(table.iter());
+        }
+
+        let mut new_table = SsTable::new(self.storage.clone(), self.block_size)
+            .with_bloom_bits_per_key(self.bloom_bits_per_key);
+        new_table.put(builder.build().map(|e| (e.key, e.revision, e.position)))?;
+
+        Ok(new_table)
+    }
+}
+
 #[derive(Clone)]
 pub struct Lsm {
     pub storage: Storage,
@@ -89,6 +163,34 @@ impl Lsm {
         Ok(lsm)
     }
 
+    /// Removes every file of the index, along with the log position it was built up to. The index
+    /// is empty the next time it's loaded.
+    pub fn clear(storage: &Storage) -> io::Result<()> {
+        if storage.exists(FileId::index_chk())? {
+            storage.remove(FileId::index_chk())?;
+        }
+
+        if !storage.exists(FileId::IndexMap)? {
+            return Ok(());
+        }
+
+        let mut bytes = storage.read_all(FileId::IndexMap)?;
+        bytes.advance(8);
+
+        while bytes.remaining() >= 17 {
+            bytes.advance(1);
+            storage.remove(FileId::SSTable(Uuid::from_u128(bytes.get_u128_le())))?;
+        }
+
+        storage.remove(FileId::IndexMap)
+    }
+
+    /// Whether some entries are only held in memory, and lost if the process stops before they
+    /// are flushed to disk.
+    pub fn has_unflushed_entries(&self) -> bool {
+        self.active_table.size() > 0
+    }
+
     pub fn ss_table_count(&self) -> usize {
         self.levels.values().map(|ts| ts.len()).sum()
     }
@@ -123,60 +225,161 @@ impl Lsm {
             return Ok(());
         }
 
+        self.flush_active_table()
+    }
+
+    /// Moves the entries held in memory to disk, without waiting for the memtable to be full.
+    /// Returns the number of entries flushed.
+    pub fn flush(&mut self) -> io::Result<usize> {
+        let count = self.active_table.len();
+
+        if count > 0 {
+            self.flush_active_table()?;
+        }
+
+        Ok(count)
+    }
+
+    /// Flushes the memtable and merges every sstable into a single one, at the highest level in
+    /// use. Reads then go through a single table. Compacting an already compacted index does
+    /// nothing.
+    pub fn compact(&mut self) -> io::Result<()> {
+        self.flush()?;
+
+        if self.ss_table_count() <= 1 {
+            return Ok(());
+        }
+
+        let mut builder = Merge::builder_for_ss_tables_only();
+        let mut cleanups = Vec::new();
+        let mut top_level = 0u8;
+
+        // Lower levels and the front of a level hold the most recent entries, they are pushed
+        // first so the merge keeps them over older ones.
+        for (level, tables) in &self.levels {
+            top_level = *level;
+
+            for table in tables {
+                builder.push_ss_table_scan(table.iter());
+                cleanups.push(table.id);
+            }
+        }
+
+        let mut new_table = SsTable::new(self.storage.clone(), self.settings.base_block_size)
+            .with_bloom_bits_per_key(self.settings.bloom_bits_per_key);
+        new_table.put(builder.build().map(|e| (e.key, e.revision, e.position)))?;
+
+        self.levels.clear();
+        self.levels.insert(top_level, VecDeque::from([new_table]));
+
+        // The previous tables are only removed once the index map stops referring to them.
+        self.persist()?;
+
+        for id in cleanups {
+            self.storage.remove(FileId::SSTable(id))?;
+        }
+
+        Ok(())
+    }
+
+    fn flush_active_table(&mut self) -> io::Result<()> {
         let mem_table = std::mem::take(&mut self.active_table);
         let mut new_table = SsTable::with_buffer(
             self.storage.clone(),
             self.settings.base_block_size,
             self.buffer.split(),
-        );
+        )
+        .with_bloom_bits_per_key(self.settings.bloom_bits_per_key);
 
         new_table.put(mem_table.entries().lift())?;
+        self.levels.entry(0).or_default().push_front(new_table);
 
-        let mut level = 0u8;
-        let mut cleanups = Vec::new();
+        // We only update the logical position this late because if we went that far, it means we
+        // actually flushed some data to disk. Anything prior is stored in mem-table.
+        self.persist()?;
+
+        if !self.settings.background_compaction {
+            self.compact_levels()?;
+        }
 
-        loop {
-            if let Some(tables) = self.levels.get_mut(&level) {
-                if tables.len() + 1 >= self.settings.ss_table_max_count {
-                    let mut builder = Merge::builder_for_ss_tables_only();
-                    cleanups.push(new_table.id);
+        Ok(())
+    }
 
-                    for table in tables.drain(..) {
-                        builder.push_ss_table_scan(table.iter());
-                        cleanups.push(table.id);
-                    }
+    /// Whether a level holds enough sstables to be merged into the next one.
+    pub fn needs_compaction(&self) -> bool {
+        self.levels
+            .iter()
+            .any(|(level, tables)| self.settings.needs_compaction(*level, tables))
+    }
 
-                    let values = builder.build().map(|e| (e.key, e.revision, e.position));
+    /// Picks the lowest level due for compaction and takes a snapshot of its sstables.
+    pub fn next_compaction(&self) -> Option<Compaction> {
+        let (level, tables) = self
+            .levels
+            .iter()
+            .find(|(level, tables)| self.settings.needs_compaction(**level, tables))?;
+
+        Some(Compaction {
+            level: *level,
+            tables: tables.iter().cloned().collect(),
+            storage: self.storage.clone(),
+            block_size: self.settings.base_block_size,
+            bloom_bits_per_key: self.settings.bloom_bits_per_key,
+        })
+    }
 
-                    new_table = SsTable::new(self.storage.clone(), self.settings.base_block_size);
-                    new_table.put(values)?;
+    /// Replaces the sstables of a compaction with the table it produced, which lands at the front
+    /// of the next level. Returns `false` and discards the new table if the sstables were changed
+    /// in the meantime, by [`Lsm::compact`] for instance.
+    pub fn apply_compaction(&mut self, compaction: Compaction, table: SsTable) -> io::Result<bool> {
+        let still_there = self.levels.get(&compaction.level).is_some_and(|tables| {
+            compaction
+                .tables
+                .iter()
+                .all(|c| tables.iter().any(|t| t.id == c.id))
+        });
+
+        if !still_there {
+            self.storage.remove(table.file_id())?;
+            return Ok(false);
+        }
 
-                    if new_table.len() >= sst_table_block_count_limit(level) {
-                        level += 1;
-                        continue;
-                    }
-                }
+        if let Some(tables) = self.levels.get_mut(&compaction.level) {
+            tables.retain(|t| compaction.tables.iter().all(|c| c.id != t.id));
 
-                tables.push_front(new_table);
-                break;
+            if tables.is_empty() {
+                self.levels.remove(&compaction.level);
             }
+        }
+
+        self.levels
+            .entry(compaction.level + 1)
+            .or_default()
+            .push_front(table);
 
-            let mut tables = VecDeque::new();
-            tables.push_front(new_table);
+        // The previous tables are only removed once the index map stops referring to them.
+        self.persist()?;
 
-            self.levels.insert(level, tables);
-            break;
+        for table in compaction.tables {
+            self.storage.remove(table.file_id())?;
         }
 
-        // We only update the logical position this late because if we went beyond the main loop,
-        // it means we actually flushed some data to disk. Anything prior is stored in mem-table.
-        self.persist()?;
+        Ok(true)
+    }
 
-        for id in cleanups {
-            self.storage.remove(FileId::SSTable(id))?;
+    /// Runs compactions until no level is due for one. Returns the number of compactions.
+    pub fn compact_levels(&mut self) -> io::Result<usize> {
+        let mut count = 0;
+
+        while let Some(compaction) = self.next_compaction() {
+            let table = compaction.run()?;
+
+            if self.apply_compaction(compaction, table)? {
+                count += 1;
+            }
         }
 
-        Ok(())
+        Ok(count)
     }
 
     pub fn get(&mut self, key: u64, revision: u64) -> io::Result<Option<u64>> {
@@ -253,6 +456,31 @@ impl Lsm {
         builder.build()
     }
 
+    /// Returns the entries of `key` with a revision between `from` and `to`, both inclusive,
+    /// across memtables and sstables.
+    pub fn range(
+        &self,
+        key: u64,
+        from: u64,
+        to: u64,
+    ) -> impl IteratorIO<Item = BlockEntry> + use<'_> {
+        let mut builder = Merge::builder();
+
+        builder.push_mem_table_scan(self.active_table.range(key, from, to));
+
+        for mem_table in self.immutable_tables.iter() {
+            builder.push_mem_table_scan(mem_table.range(key, from, to));
+        }
+
+        for tables in self.levels.values() {
+            for table in tables {
+                builder.push_ss_table_scan(table.scan_forward(key, from, usize::MAX));
+            }
+        }
+
+        builder.build().take_while(move |e| e.revision <= to)
+    }
+
     pub fn highest_revision(&self, key: u64) -> io::Result<Option<u64>> {
         Ok(self
             .scan_backward(key, u64::MAX, 1)
@@ -270,8 +498,9 @@ impl Lsm {
             }
         }
 
+        // The map shrinks when tables are merged, so it's rewritten as a whole.
         self.storage
-            .write_to(FileId::IndexMap, 0, self.buffer.split().freeze())?;
+            .write_atomic(FileId::IndexMap, self.buffer.split().freeze())?;
 
         Ok(())
     }
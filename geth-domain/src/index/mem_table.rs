@@ -54,10 +54,29 @@ impl MemTable {
         }
     }
 
+    /// Returns the entries of `key` with a revision between `from` and `to`, both inclusive.
+    pub fn range(&self, key: u64, from: u64, to: u64) -> Range<'_> {
+        let inner = if from <= to {
+            self.inner.get(&key).map(|x| x.range(from..=to))
+        } else {
+            None
+        };
+
+        Range { key, inner }
+    }
+
     pub fn size(&self) -> usize {
         self.entries_count * MEM_TABLE_ENTRY_SIZE
     }
 
+    pub fn len(&self) -> usize {
+        self.entries_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries_count == 0
+    }
+
     pub fn entries(self) -> impl Iterator<Item = (u64, u64, u64)> {
         self.into_iter()
             .map(|entry| (entry.key, entry.revision, entry.position))
@@ -113,6 +132,25 @@ impl Iterator for ScanForward<'_> {
     }
 }
 
+pub struct Range<'a> {
+    key: u64,
+    inner: Option<std::collections::btree_map::Range<'a, u64, u64>>,
+}
+
+impl Iterator for Range<'_> {
+    type Item = BlockEntry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (rev, pos) = self.inner.as_mut()?.next()?;
+
+        Some(BlockEntry {
+            key: self.key,
+            revision: *rev,
+            position: *pos,
+        })
+    }
+}
+
 pub struct ScanBackward<'a> {
     key: u64,
     start: u64,
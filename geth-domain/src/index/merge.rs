@@ -38,6 +38,11 @@ impl<TMemTable, TSSTable> MergeBuilder<TMemTable, TSSTable> {
     }
 }
 
+/// Merges sorted [`BlockEntry`] sources into a single sorted one.
+///
+/// Sources must be pushed from the most recent to the oldest, memtables before sstables. When the
+/// same key/revision pair shows up in several sources, only the entry coming from the most recent
+/// one is kept.
 pub struct Merge<TMemTable, TSSTable> {
     mem_tables: Vec<TMemTable>,
     ss_tables: Vec<TSSTable>,
@@ -82,6 +87,8 @@ where
                 if let Some((entry_idx, entry)) = lower.as_mut() {
                     match entry.cmp_key_id(&cell_value) {
                         Ordering::Less => continue,
+                        // `lower` always comes from a more recent source than `cell` because
+                        // caches are ordered like the sources, so the stale duplicate is dropped.
                         Ordering::Equal => *cell = None,
                         Ordering::Greater => {
                             *entry_idx = idx;
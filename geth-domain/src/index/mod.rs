@@ -1,8 +1,9 @@
 pub use block::BlockEntry;
-pub use lsm::{Lsm, LsmSettings};
+pub use lsm::{Compaction, Lsm, LsmSettings};
 pub use merge::MergeBuilder;
 
 pub(crate) mod block;
+mod bloom;
 pub(crate) mod lsm;
 mod mem_table;
 mod merge;
@@ -8,6 +8,7 @@ use geth_common::{IteratorIO, IteratorIOExt};
 use geth_mikoshi::storage::{FileId, Storage};
 
 use crate::index::block::{Block, BlockEntry};
+use crate::index::bloom::{BloomFilter, DEFAULT_BLOOM_BITS_PER_KEY};
 
 use super::block::get_block_size;
 use super::block::mutable::BlockMut;
@@ -17,6 +18,10 @@ const SSTABLE_META_ENTRY_SIZE: usize =
 
 const SSTABLE_HEADER_SIZE: usize = std::mem::size_of::<u32>();
 
+/// Set on the block size stored in the header when a bloom filter follows the block metas. The
+/// table then ends with the offset of the filter, followed by the offset of the metas.
+const SSTABLE_BLOOM_FLAG: u32 = 0x8000_0000;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct BlockMeta {
     pub offset: u32,
@@ -92,6 +97,9 @@ pub struct SsTable {
     pub metas: BlockMetas,
     pub meta_offset: u64,
     pub block_size: usize,
+    /// Bits of the bloom filter per distinct key, no filter is built when zero.
+    pub bloom_bits_per_key: usize,
+    pub bloom: Option<BloomFilter>,
     pub buffer: BytesMut,
 }
 
@@ -117,10 +125,19 @@ impl SsTable {
             metas: BlockMetas(Default::default()),
             meta_offset: 0,
             block_size,
+            bloom_bits_per_key: DEFAULT_BLOOM_BITS_PER_KEY,
+            bloom: None,
             buffer,
         }
     }
 
+    pub fn with_bloom_bits_per_key(self, bloom_bits_per_key: usize) -> Self {
+        Self {
+            bloom_bits_per_key,
+            ..self
+        }
+    }
+
     pub fn with_default(storage: Storage) -> Self {
         SsTable::new(storage, 4_096)
     }
@@ -128,9 +145,21 @@ impl SsTable {
     pub fn load_with_buffer(storage: Storage, raw_id: Uuid, buffer: BytesMut) -> io::Result<Self> {
         let id = FileId::SSTable(raw_id);
         let len = storage.len(id)?;
-        let block_size = storage.read_from(id, 0, SSTABLE_HEADER_SIZE)?.get_u32_le() as usize;
+        let header = storage.read_from(id, 0, SSTABLE_HEADER_SIZE)?.get_u32_le();
+        let block_size = (header & !SSTABLE_BLOOM_FLAG) as usize;
         let meta_offset = storage.read_from(id, len as u64 - 4, 4)?.get_u32_le() as u64;
-        let metas = storage.read_from(id, meta_offset, len - 4usize - meta_offset as usize)?;
+        let mut meta_end = len - 4;
+        let mut bloom = None;
+
+        if header & SSTABLE_BLOOM_FLAG != 0 {
+            let bloom_offset = storage.read_from(id, len as u64 - 8, 4)?.get_u32_le() as usize;
+            let bytes = storage.read_from(id, bloom_offset as u64, len - 8 - bloom_offset)?;
+
+            meta_end = bloom_offset;
+            bloom = Some(BloomFilter::get(bytes));
+        }
+
+        let metas = storage.read_from(id, meta_offset, meta_end - meta_offset as usize)?;
 
         Ok(SsTable {
             id: raw_id,
@@ -138,6 +167,8 @@ impl SsTable {
             metas: BlockMetas::from(metas),
             meta_offset,
             block_size,
+            bloom_bits_per_key: DEFAULT_BLOOM_BITS_PER_KEY,
+            bloom,
             buffer,
         })
     }
@@ -195,7 +226,18 @@ impl SsTable {
         Ok(Block::from(self.block_size, block_bytes))
     }
 
+    /// Whether the table might hold entries of `key`. Always true if the table has no bloom filter.
+    pub fn may_contain(&self, key: u64) -> bool {
+        self.bloom
+            .as_ref()
+            .is_none_or(|bloom| bloom.may_contain(key))
+    }
+
     pub fn find_key(&self, key: u64, revision: u64) -> io::Result<Option<BlockEntry>> {
+        if !self.may_contain(key) {
+            return Ok(None);
+        }
+
         for block_idx in self.find_best_candidates(key, revision) {
             let block = self.read_block(block_idx)?;
 
@@ -220,8 +262,14 @@ impl SsTable {
     {
         let mut builder = BlockMut::new(self.buffer.split(), self.block_size);
         let mut block_start_offset = std::mem::size_of::<u32>();
+        let mut keys = Vec::new();
 
-        self.buffer.put_u32_le(self.block_size as u32);
+        if self.bloom_bits_per_key > 0 {
+            self.buffer
+                .put_u32_le(self.block_size as u32 | SSTABLE_BLOOM_FLAG);
+        } else {
+            self.buffer.put_u32_le(self.block_size as u32);
+        }
 
         self.storage
             .write_to(self.file_id(), 0, self.buffer.split().freeze())?;
@@ -229,6 +277,11 @@ impl SsTable {
         while let Some((key, rev, pos)) = values.next()? {
             let mut retried = false;
 
+            // Values are sorted, so duplicate keys are next to each other.
+            if keys.last() != Some(&key) {
+                keys.push(key);
+            }
+
             loop {
                 if builder.try_add(key, rev, pos) {
                     if builder.len() == 1 {
@@ -267,6 +320,15 @@ impl SsTable {
             .append(self.file_id(), self.metas.serialize(self.buffer.split()))?;
         self.meta_offset = meta_offset;
 
+        if self.bloom_bits_per_key > 0 {
+            let bloom = BloomFilter::build(&keys, self.bloom_bits_per_key);
+            let bloom_offset = self.storage.offset(self.file_id())?;
+
+            bloom.put(&mut self.buffer);
+            self.buffer.put_u32_le(bloom_offset as u32);
+            self.bloom = Some(bloom);
+        }
+
         self.buffer.put_u32_le(meta_offset as u32);
 
         self.storage
@@ -289,6 +351,18 @@ impl SsTable {
     }
 
     pub fn scan_forward(&self, key: u64, start: u64, count: usize) -> ScanForward<'_> {
+        if !self.may_contain(key) {
+            return ScanForward {
+                key,
+                revision: start,
+                count: 0,
+                block_idx: 0,
+                block_scan: None,
+                table: self,
+                candidates: VecDeque::new(),
+            };
+        }
+
         ScanForward {
             key,
             revision: start,
@@ -301,6 +375,18 @@ impl SsTable {
     }
 
     pub fn scan_backward(&self, key: u64, start: u64, count: usize) -> ScanBackward<'_> {
+        if !self.may_contain(key) {
+            return ScanBackward {
+                key,
+                revision: start,
+                count: 0,
+                block_idx: None,
+                block_scan: None,
+                table: self,
+                candidates: VecDeque::new(),
+            };
+        }
+
         let mut candidates = self.find_best_candidates(key, start);
 
         candidates.rotate_left(candidates.len() - 1);
@@ -328,24 +414,22 @@ impl IteratorIO for SsTableIter {
     type Item = BlockEntry;
 
     fn next(&mut self) -> io::Result<Option<Self::Item>> {
-        if self.block_idx >= self.table.len() {
-            return Ok(None);
-        }
+        while self.block_idx < self.table.len() {
+            if self.block.is_none() {
+                self.block = Some(self.table.read_block(self.block_idx)?);
+            }
 
-        if self.block.is_none() {
-            self.block = Some(self.table.read_block(self.block_idx)?);
-        }
+            if let Some(block) = self.block.as_ref() {
+                if let Some(entry) = block.try_read(self.entry_idx) {
+                    self.entry_idx += 1;
 
-        if let Some(block) = self.block.as_ref() {
-            if let Some(entry) = block.try_read(self.entry_idx) {
-                self.entry_idx += 1;
+                    return Ok(Some(entry));
+                }
 
-                return Ok(Some(entry));
+                self.block = None;
+                self.entry_idx = 0;
+                self.block_idx += 1;
             }
-
-            self.block = None;
-            self.entry_idx = 0;
-            self.block_idx += 1;
         }
 
         Ok(None)
@@ -6,6 +6,7 @@ use temp_testdir::TempDir;
 use geth_common::IteratorIO;
 use geth_mikoshi::FileSystemStorage;
 
+use crate::index::block::get_block_size;
 use crate::index::lsm::{Lsm, LsmSettings};
 use crate::index::mem_table::MEM_TABLE_ENTRY_SIZE;
 use crate::index::ss_table::SsTable;
@@ -109,6 +110,38 @@ fn test_fs_lsm_sync() -> io::Result<()> {
     Ok(())
 }
 
+/// Overwrites the same key/revision pairs across several flushes until compaction kicks in.
+#[test]
+fn test_fs_lsm_compaction_keeps_most_recent() -> io::Result<()> {
+    let setts = LsmSettings {
+        mem_table_max_size: MEM_TABLE_ENTRY_SIZE,
+        ..Default::default()
+    };
+
+    let temp = TempDir::default();
+    let root = PathBuf::from(temp.as_ref());
+    let storage = FileSystemStorage::new_storage(root)?;
+    let mut lsm = Lsm::new(setts, storage);
+
+    lsm.put_single(1, 0, 1)?;
+    lsm.put_single(2, 0, 2)?;
+    lsm.put_single(1, 0, 10)?;
+    lsm.put_single(3, 0, 3)?;
+
+    assert_eq!(1, lsm.ss_table_count());
+    assert_eq!(10, lsm.get(1, 0)?.unwrap());
+    assert_eq!(2, lsm.get(2, 0)?.unwrap());
+    assert_eq!(3, lsm.get(3, 0)?.unwrap());
+
+    let mut iter = lsm.scan_forward(1, 0, usize::MAX);
+    let entry = iter.next()?.unwrap();
+    assert_eq!(0, entry.revision);
+    assert_eq!(10, entry.position);
+    assert!(iter.next()?.is_none());
+
+    Ok(())
+}
+
 #[test]
 fn test_fs_lsm_serialization() -> io::Result<()> {
     let temp = TempDir::default();
@@ -147,3 +180,45 @@ fn test_fs_lsm_serialization() -> io::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_fs_lsm_leveled_compaction_survives_reload() -> io::Result<()> {
+    let setts = LsmSettings {
+        mem_table_max_size: 8 * MEM_TABLE_ENTRY_SIZE,
+        ss_table_max_count: 4,
+        base_block_size: get_block_size(4),
+        level_base_block_count: 8,
+        level_size_multiplier: 2,
+        ..Default::default()
+    };
+
+    let temp = TempDir::default();
+    let root = PathBuf::from(temp.as_ref());
+    let storage = FileSystemStorage::new_storage(root.clone())?;
+    let mut lsm = Lsm::new(setts, storage.clone());
+    let count = 1_000u64;
+
+    for key in 0..count {
+        lsm.put_single(key, 0, key * 10)?;
+    }
+
+    lsm.flush()?;
+
+    let mut actual = Lsm::load(setts, storage)?;
+    assert_eq!(lsm.ss_table_count(), actual.ss_table_count());
+    assert!(!actual.needs_compaction());
+
+    for key in 0..count {
+        assert_eq!(Some(key * 10), actual.get(key, 0)?);
+    }
+
+    // Merged tables are removed, only the ones the index refers to are left.
+    let files = std::fs::read_dir(root)?
+        .filter_map(|e| e.ok())
+        .filter(|e| uuid::Uuid::parse_str(&e.file_name().to_string_lossy()).is_ok())
+        .count();
+
+    assert_eq!(actual.ss_table_count(), files);
+
+    Ok(())
+}
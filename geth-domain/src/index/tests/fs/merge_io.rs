@@ -1,6 +1,11 @@
 use std::io;
+use std::path::PathBuf;
+
+use geth_mikoshi::FileSystemStorage;
+use temp_testdir::TempDir;
 
 use crate::index::merge::Merge;
+use crate::index::ss_table::SsTable;
 use crate::index::tests::{build_mem_table, check_merge_io_result};
 
 #[test]
@@ -78,3 +83,58 @@ fn test_merge_io_mem_table_2() -> io::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_merge_io_duplicate_keeps_most_recent() -> io::Result<()> {
+    let mem_1 = build_mem_table([(1, 0, 100), (2, 0, 200), (2, 1, 210)]);
+    let mem_2 = build_mem_table([(1, 0, 1), (2, 1, 21), (3, 0, 3)]);
+    let mem_3 = build_mem_table([(1, 0, 0), (2, 0, 2), (3, 0, 30)]);
+
+    let mut builder = Merge::builder_for_mem_tables_only();
+    builder.push_mem_table_scan(mem_1.clone().into_iter());
+    builder.push_mem_table_scan(mem_2.clone().into_iter());
+    builder.push_mem_table_scan(mem_3.clone().into_iter());
+
+    check_merge_io_result(
+        builder.build(),
+        [(1, 0, 100), (2, 0, 200), (2, 1, 210), (3, 0, 3)],
+    )?;
+
+    let mut builder = Merge::builder_for_mem_tables_only();
+    builder.push_mem_table_scan(mem_3.into_iter());
+    builder.push_mem_table_scan(mem_2.into_iter());
+    builder.push_mem_table_scan(mem_1.into_iter());
+
+    check_merge_io_result(
+        builder.build(),
+        [(1, 0, 0), (2, 0, 2), (2, 1, 21), (3, 0, 30)],
+    )?;
+
+    Ok(())
+}
+
+#[test]
+fn test_merge_io_duplicate_mem_table_over_ss_tables() -> io::Result<()> {
+    let temp = TempDir::default();
+    let root = PathBuf::from(temp.as_ref());
+    let storage = FileSystemStorage::new_storage(root)?;
+
+    let mem = build_mem_table([(2, 0, 1_000)]);
+    let mut recent = SsTable::with_capacity(storage.clone(), 1);
+    let mut old = SsTable::with_capacity(storage, 1);
+
+    recent.put_iter([(1, 0, 10), (2, 0, 20), (3, 0, 30)])?;
+    old.put_iter([(1, 0, 1), (2, 0, 2), (3, 0, 3), (4, 0, 4)])?;
+
+    let mut builder = Merge::builder();
+    builder.push_mem_table_scan(mem.into_iter());
+    builder.push_ss_table_scan(recent.iter());
+    builder.push_ss_table_scan(old.iter());
+
+    check_merge_io_result(
+        builder.build(),
+        [(1, 0, 10), (2, 0, 1_000), (3, 0, 30), (4, 0, 4)],
+    )?;
+
+    Ok(())
+}
@@ -316,6 +316,28 @@ fn test_fs_ss_table_serialization() -> io::Result<()> {
     assert_eq!(table.block_size, actual.block_size);
     assert_eq!(table.meta_offset, actual.meta_offset);
     assert_eq!(table.metas, actual.metas);
+    assert!(actual.bloom.is_some());
+    assert_eq!(table.bloom, actual.bloom);
+    assert!(actual.may_contain(1));
+
+    Ok(())
+}
+
+#[test]
+fn test_fs_ss_table_without_bloom_filter() -> io::Result<()> {
+    let temp = TempDir::default();
+    let storage = FileSystemStorage::new_storage(PathBuf::from(temp.as_ref()))?;
+    let mut table = SsTable::with_capacity(storage.clone(), 10).with_bloom_bits_per_key(0);
+
+    table.put_iter([(1, 2, 3), (4, 5, 6)])?;
+
+    let actual = SsTable::load(storage, table.id)?;
+
+    assert!(actual.bloom.is_none());
+    assert_eq!(table.block_size, actual.block_size);
+    assert_eq!(table.metas, actual.metas);
+    assert_eq!(6, actual.find_key(4, 5)?.unwrap().position);
+    assert!(actual.find_key(7, 0)?.is_none());
 
     Ok(())
 }
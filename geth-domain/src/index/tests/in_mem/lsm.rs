@@ -3,6 +3,7 @@ use std::io;
 use geth_common::IteratorIO;
 use geth_mikoshi::InMemoryStorage;
 
+use crate::index::block::get_block_size;
 use crate::index::lsm::{Lsm, LsmSettings};
 use crate::index::mem_table::MEM_TABLE_ENTRY_SIZE;
 
@@ -137,3 +138,195 @@ fn test_in_mem_lsm_sync() -> io::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_in_mem_lsm_range() -> io::Result<()> {
+    let setts = LsmSettings {
+        mem_table_max_size: 3 * MEM_TABLE_ENTRY_SIZE,
+        ..Default::default()
+    };
+
+    let mut lsm = Lsm::new(setts, InMemoryStorage::new_storage());
+
+    lsm.put_values([(1, 0, 10), (1, 1, 11), (2, 0, 20)])?;
+    lsm.put_values([(1, 2, 12), (1, 3, 13)])?;
+
+    assert_eq!(1, lsm.ss_table_count());
+
+    let mut iter = lsm.range(1, 1, 2);
+
+    let entry = iter.next()?.unwrap();
+    assert_eq!(1, entry.key);
+    assert_eq!(1, entry.revision);
+    assert_eq!(11, entry.position);
+
+    let entry = iter.next()?.unwrap();
+    assert_eq!(1, entry.key);
+    assert_eq!(2, entry.revision);
+    assert_eq!(12, entry.position);
+
+    assert!(iter.next()?.is_none());
+
+    let mut iter = lsm.range(1, 0, u64::MAX);
+    let mut revisions = Vec::new();
+
+    while let Some(entry) = iter.next()? {
+        revisions.push(entry.revision);
+    }
+
+    assert_eq!(vec![0, 1, 2, 3], revisions);
+    assert!(lsm.range(1, 3, 1).next()?.is_none());
+    assert!(lsm.range(2, 1, 5).next()?.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_in_mem_lsm_flush_and_compact() -> io::Result<()> {
+    let setts = LsmSettings {
+        mem_table_max_size: 2 * MEM_TABLE_ENTRY_SIZE,
+        ss_table_max_count: 8,
+        ..Default::default()
+    };
+
+    let mut lsm = Lsm::new(setts, InMemoryStorage::new_storage());
+
+    lsm.put_values([(1, 0, 10), (2, 0, 20)])?;
+    lsm.put_values([(1, 1, 11), (2, 1, 21)])?;
+    lsm.put_values([(1, 2, 12)])?;
+
+    assert_eq!(2, lsm.ss_table_count());
+    assert!(lsm.has_unflushed_entries());
+
+    assert_eq!(1, lsm.flush()?);
+    assert_eq!(0, lsm.flush()?);
+    assert!(!lsm.has_unflushed_entries());
+    assert_eq!(3, lsm.ss_table_count());
+
+    lsm.compact()?;
+    assert_eq!(1, lsm.ss_table_count());
+
+    // Compacting twice leaves the index as is.
+    let table = lsm.ss_table_first().map(|t| t.id);
+    lsm.compact()?;
+    assert_eq!(table, lsm.ss_table_first().map(|t| t.id));
+
+    let mut revisions = Vec::new();
+    let mut iter = lsm.scan_forward(1, 0, usize::MAX);
+
+    while let Some(entry) = iter.next()? {
+        revisions.push((entry.revision, entry.position));
+    }
+
+    assert_eq!(vec![(0, 10), (1, 11), (2, 12)], revisions);
+    drop(iter);
+
+    assert_eq!(Some(21), lsm.get(2, 1)?);
+
+    Ok(())
+}
+
+fn leveled_settings() -> LsmSettings {
+    LsmSettings {
+        mem_table_max_size: 8 * MEM_TABLE_ENTRY_SIZE,
+        ss_table_max_count: 4,
+        base_block_size: get_block_size(4),
+        level_base_block_count: 8,
+        level_size_multiplier: 2,
+        ..Default::default()
+    }
+}
+
+fn check_all_keys(lsm: &mut Lsm, count: u64) -> io::Result<()> {
+    for key in 0..count {
+        assert_eq!(Some(key * 10), lsm.get(key, 0)?, "key {key}");
+    }
+
+    let mut iter = lsm.scan_forward(count / 2, 0, usize::MAX);
+    let entry = iter.next()?.unwrap();
+    assert_eq!(count / 2, entry.key);
+    assert_eq!(count / 2 * 10, entry.position);
+    assert!(iter.next()?.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_in_mem_lsm_leveled_compaction_bounds_table_count() -> io::Result<()> {
+    let setts = leveled_settings();
+    let mut lsm = Lsm::new(setts, InMemoryStorage::new_storage());
+    let count = 5_000u64;
+
+    for key in 0..count {
+        lsm.put_single(key, 0, key * 10)?;
+
+        assert!(!lsm.needs_compaction());
+        for tables in lsm.levels.values() {
+            assert!(tables.len() < setts.ss_table_max_count);
+        }
+    }
+
+    assert!(lsm.levels.len() > 2);
+    assert!(lsm.ss_table_count() < lsm.levels.len() * setts.ss_table_max_count);
+
+    check_all_keys(&mut lsm, count)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_in_mem_lsm_background_compaction() -> io::Result<()> {
+    let setts = LsmSettings {
+        background_compaction: true,
+        ..leveled_settings()
+    };
+
+    let mut lsm = Lsm::new(setts, InMemoryStorage::new_storage());
+    let count = 2_000u64;
+
+    for key in 0..count / 2 {
+        lsm.put_single(key, 0, key * 10)?;
+    }
+
+    let tables_before = lsm.ss_table_count();
+    assert!(lsm.needs_compaction());
+    assert!(tables_before > setts.ss_table_max_count);
+
+    let compaction = lsm.next_compaction().unwrap();
+    assert_eq!(0, compaction.level);
+    assert_eq!(tables_before, compaction.tables().len());
+
+    // The index keeps serving reads and writes while the snapshot is merged.
+    for key in count / 2..count {
+        lsm.put_single(key, 0, key * 10)?;
+    }
+
+    let table = compaction.run()?;
+    check_all_keys(&mut lsm, count)?;
+
+    assert!(lsm.apply_compaction(compaction, table)?);
+    assert!(lsm.compact_levels()? > 0);
+    assert!(!lsm.needs_compaction());
+
+    check_all_keys(&mut lsm, count)?;
+
+    // A compaction whose tables were compacted in the meantime is discarded.
+    for key in count..count + 100 {
+        lsm.put_single(key, 0, key * 10)?;
+    }
+
+    let compaction = lsm.next_compaction().unwrap();
+    let table = compaction.run()?;
+    let table_id = table.id;
+
+    lsm.compact()?;
+    assert!(!lsm.apply_compaction(compaction, table)?);
+    assert_eq!(1, lsm.ss_table_count());
+    assert!(!lsm
+        .storage()
+        .exists(geth_mikoshi::storage::FileId::SSTable(table_id))?);
+
+    check_all_keys(&mut lsm, count + 100)?;
+
+    Ok(())
+}
@@ -238,3 +238,35 @@ fn test_mem_table_flush() -> io::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_mem_table_range() {
+    let mut mem_table = MemTable::default();
+
+    mem_table.put(1, 0, 0);
+    mem_table.put(1, 1, 5);
+    mem_table.put(1, 2, 10);
+    mem_table.put(1, 3, 15);
+    mem_table.put(2, 1, 20);
+
+    let entries = mem_table.range(1, 1, 2).collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            BlockEntry {
+                key: 1,
+                revision: 1,
+                position: 5,
+            },
+            BlockEntry {
+                key: 1,
+                revision: 2,
+                position: 10,
+            },
+        ],
+        entries
+    );
+
+    assert_eq!(4, mem_table.range(1, 0, u64::MAX).count());
+    assert_eq!(0, mem_table.range(1, 2, 1).count());
+    assert_eq!(0, mem_table.range(3, 0, u64::MAX).count());
+}
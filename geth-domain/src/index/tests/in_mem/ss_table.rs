@@ -1,6 +1,9 @@
 use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
 use geth_common::IteratorIO;
+use geth_mikoshi::storage::{FaultInjector, IoOp, Storage};
 use geth_mikoshi::InMemoryStorage;
 
 use crate::index::block::BLOCK_ENTRY_SIZE;
@@ -285,3 +288,56 @@ fn test_in_mem_ss_table_serialization() -> io::Result<()> {
 
     Ok(())
 }
+
+/// Absent keys the bloom filter rules out must not read a single block.
+#[test]
+fn test_in_mem_sst_bloom_skips_absent_keys() -> io::Result<()> {
+    let storage = InMemoryStorage::default();
+    let mut table = SsTable::new(Storage::InMemory(storage.clone()), 4_096);
+    let count = 10_000u64;
+
+    table.put_iter((0..count).map(|i| (i * 2, 0, i)))?;
+
+    let reads = Arc::new(AtomicUsize::new(0));
+    let table_id = table.file_id();
+    let counter = reads.clone();
+    storage.inject_faults(FaultInjector::default().fail_when(move |op, id| {
+        if op == IoOp::Read && id == table_id {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+
+        false
+    }));
+
+    let mut false_positives = 0;
+    for i in 0..count {
+        let key = i * 2 + 1;
+
+        if table.may_contain(key) {
+            false_positives += 1;
+            continue;
+        }
+
+        assert!(table.find_key(key, 0)?.is_none());
+        assert!(table.scan_forward(key, 0, usize::MAX).next()?.is_none());
+        assert!(table
+            .scan_backward(key, u64::MAX, usize::MAX)
+            .next()?
+            .is_none());
+    }
+
+    assert_eq!(0, reads.load(Ordering::Relaxed));
+    // 10 bits per key gives a false positive rate of about 1%.
+    assert!(
+        false_positives < count / 50,
+        "{false_positives} false positives"
+    );
+
+    for i in (0..count).step_by(97) {
+        assert_eq!(i, table.find_key(i * 2, 0)?.unwrap().position);
+    }
+
+    assert!(reads.load(Ordering::Relaxed) > 0);
+
+    Ok(())
+}
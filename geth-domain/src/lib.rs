@@ -3,7 +3,7 @@ use chrono::{DateTime, TimeZone, Utc};
 use prost::Message;
 use uuid::Uuid;
 
-pub use index::{Lsm, LsmSettings};
+pub use index::{Compaction, Lsm, LsmSettings};
 
 use crate::binary::models::Events;
 
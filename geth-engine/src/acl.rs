@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+use geth_common::StreamAcl;
+
+use crate::names::streams;
+
+/// Principal matching every principal in a [`StreamAcl`], anonymous requests included.
+pub const ANY_PRINCIPAL: &str = "*";
+
+/// Operation a [`StreamAcl`] grants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamAccess {
+    Read,
+    Write,
+    Delete,
+}
+
+impl StreamAccess {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StreamAccess::Read => "read",
+            StreamAccess::Write => "write",
+            StreamAccess::Delete => "delete",
+        }
+    }
+}
+
+/// Stream whose ACL guards `access` to `stream_name`, and the accesses that ACL must grant. A
+/// metadata stream is guarded by the ACL of the stream it holds the metadata of: writing to it
+/// can truncate or hide that stream, so it takes deleting it as well.
+pub(crate) fn guarding(stream_name: &str, access: StreamAccess) -> (&str, &'static [StreamAccess]) {
+    let Some(target) = streams::metadata_target(stream_name) else {
+        return match access {
+            StreamAccess::Read => (stream_name, &[StreamAccess::Read]),
+            StreamAccess::Write => (stream_name, &[StreamAccess::Write]),
+            StreamAccess::Delete => (stream_name, &[StreamAccess::Delete]),
+        };
+    };
+
+    match access {
+        StreamAccess::Read => (target, &[StreamAccess::Read]),
+        StreamAccess::Write | StreamAccess::Delete => {
+            (target, &[StreamAccess::Write, StreamAccess::Delete])
+        }
+    }
+}
+
+/// Whether `acl` grants every one of `accesses` to `principal`, `None` for anonymous requests.
+pub(crate) fn grants(acl: &StreamAcl, principal: Option<&str>, accesses: &[StreamAccess]) -> bool {
+    accesses.iter().all(|access| {
+        let principals = match access {
+            StreamAccess::Read => &acl.read,
+            StreamAccess::Write => &acl.write,
+            StreamAccess::Delete => &acl.delete,
+        };
+
+        granted_to(principals, principal)
+    })
+}
+
+/// Source of truth of the stream ACLs the gRPC server enforces.
+pub trait AclResolver: Send + Sync {
+    /// ACL of `stream_name`, as stored. `None` when the stream is open to everyone.
+    fn resolve(&self, stream_name: &str) -> Option<StreamAcl>;
+
+    /// Principals allowed to administer the node: run and stop programs, change the log filters
+    /// and maintain the index. Nobody is by default.
+    fn admins(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// ACLs by stream name prefix, the longest prefix a stream name starts with applies. Streams
+/// matching no prefix are open to everyone.
+#[derive(Debug, Clone, Default)]
+pub struct PrefixAclResolver {
+    acls: Vec<(String, StreamAcl)>,
+    admins: Vec<String>,
+}
+
+impl PrefixAclResolver {
+    pub fn new(acls: impl IntoIterator<Item = (String, StreamAcl)>) -> Self {
+        let mut acls = acls.into_iter().collect::<Vec<_>>();
+
+        acls.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()));
+
+        Self {
+            acls,
+            admins: Vec::new(),
+        }
+    }
+
+    /// Grants the administration of the node to `admins`, see [`AclResolver::admins`].
+    pub fn with_admins(self, admins: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            admins: admins.into_iter().collect(),
+            ..self
+        }
+    }
+
+    /// Loads a JSON file mapping stream name prefixes to ACLs, for instance
+    /// `{ "orders-": { "read": ["*"], "write": ["billing"] } }`.
+    pub fn load(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)
+            .map_err(|e| eyre::eyre!("cannot read '{}': {}", path.display(), e))?;
+
+        let acls = serde_json::from_slice::<HashMap<String, StreamAcl>>(&bytes)
+            .map_err(|e| eyre::eyre!("malformed ACL file '{}': {}", path.display(), e))?;
+
+        tracing::info!(count = acls.len(), path = %path.display(), "loaded stream ACLs");
+
+        Ok(Self::new(acls))
+    }
+}
+
+impl AclResolver for PrefixAclResolver {
+    fn resolve(&self, stream_name: &str) -> Option<StreamAcl> {
+        self.acls
+            .iter()
+            .find(|(prefix, _)| stream_name.starts_with(prefix.as_str()))
+            .map(|(_, acl)| acl.clone())
+    }
+
+    fn admins(&self) -> Vec<String> {
+        self.admins.clone()
+    }
+}
+
+/// The ACL resolver the gRPC server uses, configured through [`crate::Options`].
+#[derive(Clone)]
+pub struct SharedAclResolver(Arc<dyn AclResolver>);
+
+impl SharedAclResolver {
+    pub fn new(resolver: impl AclResolver + 'static) -> Self {
+        Self(Arc::new(resolver))
+    }
+
+    /// Whether `principal`, `None` for anonymous requests, may perform `access` on
+    /// `stream_name`, see [`guarding`].
+    pub(crate) fn allows(
+        &self,
+        principal: Option<&str>,
+        stream_name: &str,
+        access: StreamAccess,
+    ) -> bool {
+        let (stream_name, accesses) = guarding(stream_name, access);
+
+        self.0
+            .resolve(stream_name)
+            .is_none_or(|acl| grants(&acl, principal, accesses))
+    }
+
+    /// Whether `principal`, `None` for anonymous requests, may administer the node.
+    pub(crate) fn allows_admin(&self, principal: Option<&str>) -> bool {
+        granted_to(&self.0.admins(), principal)
+    }
+}
+
+fn granted_to(principals: &[String], principal: Option<&str>) -> bool {
+    principals
+        .iter()
+        .any(|allowed| allowed == ANY_PRINCIPAL || Some(allowed.as_str()) == principal)
+}
+
+impl fmt::Debug for SharedAclResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SharedAclResolver")
+    }
+}
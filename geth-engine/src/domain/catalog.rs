@@ -0,0 +1,97 @@
+use std::collections::BTreeSet;
+use std::ops::Bound;
+
+use chrono::{DateTime, Utc};
+use geth_common::{ContentType, Record, STREAMS_FEED, StreamFeedEvent, StreamPage};
+use uuid::Uuid;
+
+use crate::names::namespaces;
+
+/// How a write changes the set of streams in the database.
+#[derive(Clone, Debug)]
+pub enum CatalogChange {
+    Created(String),
+    Deleted(String),
+}
+
+impl CatalogChange {
+    /// Announces the change on the `$streams` feed.
+    pub fn feed_record(&self, revision: u64, position: u64, created: DateTime<Utc>) -> Record {
+        let event = match self {
+            CatalogChange::Created(name) => StreamFeedEvent::StreamCreated { name: name.clone() },
+            CatalogChange::Deleted(name) => StreamFeedEvent::StreamDeleted { name: name.clone() },
+        };
+
+        Record {
+            id: Uuid::now_v7(),
+            content_type: ContentType::Json,
+            class: event.class().to_string(),
+            stream_name: STREAMS_FEED.to_string(),
+            position,
+            revision,
+            data: event.data(),
+            metadata: Default::default(),
+            created,
+            link: None,
+            epoch: 0,
+        }
+    }
+}
+
+/// Names of the live streams in the database, kept in lexicographic order so pages are stable.
+#[derive(Default)]
+pub struct StreamCatalog {
+    streams: BTreeSet<String>,
+}
+
+impl StreamCatalog {
+    pub fn apply(&mut self, change: CatalogChange) {
+        match change {
+            CatalogChange::Created(stream) => {
+                self.streams.insert(stream);
+            }
+
+            CatalogChange::Deleted(stream) => {
+                self.streams.remove(&stream);
+            }
+        }
+    }
+
+    /// Streams of a namespace are only listed when `prefix` is itself scoped to a namespace.
+    /// Otherwise the range of namespaced streams is stepped over, so they neither take room in
+    /// the page nor count in its total.
+    pub fn page(&self, prefix: Option<&str>, offset: usize, limit: usize) -> StreamPage {
+        let prefix = prefix.unwrap_or_default();
+
+        StreamPage {
+            streams: self
+                .matching(prefix)
+                .skip(offset)
+                .take(limit)
+                .cloned()
+                .collect(),
+            total: self.matching(prefix).count(),
+        }
+    }
+
+    fn matching<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = &'a String> {
+        let from = |start: &'a str| {
+            self.streams
+                .range::<str, _>((Bound::Included(start), Bound::Unbounded))
+        };
+
+        let skipped = namespaces::namespace_of(prefix)
+            .is_none()
+            .then_some(namespaces::PREFIX);
+
+        from(prefix)
+            .take_while(move |s| skipped.is_none_or(|start| s.as_str() < start))
+            .chain(
+                skipped
+                    .map(|_| from(prefix.max(namespaces::PREFIX_END)))
+                    .into_iter()
+                    .flatten(),
+            )
+            .take_while(move |s| s.starts_with(prefix))
+    }
+}
@@ -1,5 +1,23 @@
 use geth_common::ExpectedRevision;
 
+/// Tombstones are indexed at `u64::MAX - epoch`, out of reach of event revisions, so each epoch
+/// of a stream gets its own.
+pub const TOMBSTONE_FLOOR: u64 = u64::MAX - u32::MAX as u64;
+
+pub fn tombstone_revision(epoch: u64) -> u64 {
+    u64::MAX - epoch
+}
+
+pub fn is_tombstone(revision: u64) -> bool {
+    revision >= TOMBSTONE_FLOOR
+}
+
+/// Tombstones carry the epoch they close. The ones written before streams could be recreated
+/// carry nothing and closed the first epoch.
+pub fn tombstone_epoch(data: &[u8]) -> u64 {
+    data.try_into().map_or(0, u64::from_le_bytes)
+}
+
 #[derive(Copy, Clone, Debug)]
 pub enum CurrentRevision {
     NoStream,
@@ -37,3 +55,40 @@ impl CurrentRevision {
         None
     }
 }
+
+/// Where a stream stands, considering the times it was deleted and recreated.
+#[derive(Clone, Debug)]
+pub struct StreamState {
+    /// Revision of the last event of the current epoch, deleted if that epoch ended with a
+    /// tombstone.
+    pub current: CurrentRevision,
+    /// First revision of each epoch, oldest first. When the stream is deleted, the last one is
+    /// where a recreation starts.
+    pub epoch_starts: Vec<u64>,
+}
+
+impl Default for StreamState {
+    fn default() -> Self {
+        Self {
+            current: CurrentRevision::NoStream,
+            epoch_starts: vec![0],
+        }
+    }
+}
+
+impl StreamState {
+    pub fn epoch(&self) -> u64 {
+        self.epoch_starts.len() as u64 - 1
+    }
+
+    pub fn epoch_start(&self) -> u64 {
+        self.epoch_starts.last().copied().unwrap_or_default()
+    }
+}
+
+/// Returns the epoch an event belongs to, `epoch_starts` being ordered as in [`StreamState`].
+pub fn epoch_of(epoch_starts: &[u64], revision: u64) -> u64 {
+    epoch_starts
+        .partition_point(|start| *start <= revision)
+        .saturating_sub(1) as u64
+}
@@ -1,14 +1,33 @@
+pub use crate::acl::{
+    ANY_PRINCIPAL, AclResolver, PrefixAclResolver, SharedAclResolver, StreamAccess,
+};
+pub use crate::clock::{Clock, MockClock, SharedClock, SystemClock};
+pub use crate::mapping::{EventMapper, EventMappers, MappingError};
 use crate::metrics::configure_metrics;
-pub use crate::options::Options;
+pub use crate::options::{Options, Tls};
+pub use crate::validation::{
+    JsonSchemaValidator, SchemaValidator, SharedSchemaValidator, ValidationError,
+};
 
+mod acl;
+mod clock;
 mod domain;
+mod mapping;
 mod metrics;
 mod names;
 mod options;
 mod process;
+#[cfg(test)]
+mod tests;
+mod validation;
 
+use geth_common::LogFiltersSet;
+use geth_domain::Lsm;
 use geth_mikoshi::{
-    FileSystemStorage, InMemoryStorage, storage::Storage, wal::chunks::ChunkContainer,
+    FileSystemStorage, InMemoryStorage,
+    manifest::Manifest,
+    storage::Storage,
+    wal::chunks::{ChunkContainer, ChunkOptions},
 };
 use opentelemetry::{KeyValue, trace::TracerProvider};
 use opentelemetry_appender_tracing::layer::OpenTelemetryTracingBridge;
@@ -18,15 +37,17 @@ use opentelemetry_sdk::{
 };
 pub use process::{
     Proc, RequestContext,
+    copying::copy_stream,
     indexing::IndexClient,
     manager::{Catalog, CatalogBuilder, ManagerClient, start_process_manager_with_catalog},
     reading::{self, ReaderClient},
     start_process_manager,
     writing::WriterClient,
 };
-use tokio::sync::OnceCell;
+use std::sync::{OnceLock, RwLock};
 use tracing_opentelemetry::OpenTelemetryLayer;
-use tracing_subscriber::{EnvFilter, prelude::*};
+use tracing_subscriber::filter::Directive;
+use tracing_subscriber::{EnvFilter, Registry, prelude::*, reload};
 use tracing_subscriber::{filter::filter_fn, layer::SubscriberExt};
 
 pub mod built_info {
@@ -34,15 +55,26 @@ pub mod built_info {
     include!(concat!(env!("OUT_DIR"), "/built.rs"));
 }
 
-static STORAGE: OnceCell<Storage> = OnceCell::const_new();
-static CHUNK_CONTAINER: OnceCell<ChunkContainer> = OnceCell::const_new();
+// Replaced each time the engine starts, so it can be started again in the same process once
+// shut down.
+static STORAGE: RwLock<Option<Storage>> = RwLock::new(None);
+static CHUNK_CONTAINER: RwLock<Option<ChunkContainer>> = RwLock::new(None);
+static EVENT_FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
 
 pub(crate) fn get_storage() -> Storage {
-    STORAGE.get().unwrap().clone()
+    STORAGE
+        .read()
+        .unwrap()
+        .clone()
+        .expect("engine is not started")
 }
 
 pub(crate) fn get_chunk_container() -> ChunkContainer {
-    CHUNK_CONTAINER.get().unwrap().clone()
+    CHUNK_CONTAINER
+        .read()
+        .unwrap()
+        .clone()
+        .expect("engine is not started")
 }
 
 fn configure_storage(options: &Options) -> eyre::Result<Storage> {
@@ -57,14 +89,107 @@ fn configure_storage(options: &Options) -> eyre::Result<Storage> {
     Ok(storage)
 }
 
+/// Loads the manifest of the database, creating it if the database predates it or is new.
+fn check_manifest(container: &ChunkContainer) -> eyre::Result<Manifest> {
+    let storage = container.storage();
+    let chunk_size = container.chunk_size()?;
+    let Some(manifest) = Manifest::load(storage)? else {
+        let manifest = Manifest::new(container.hash_algorithm()?, chunk_size);
+        manifest.save(storage)?;
+        return Ok(manifest);
+    };
+
+    manifest.validate()?;
+
+    if manifest.chunk_size != chunk_size as u64 {
+        eyre::bail!(
+            "manifest records {} bytes chunks but the chunks are {} bytes",
+            manifest.chunk_size,
+            chunk_size
+        );
+    }
+
+    Ok(manifest)
+}
+
+/// Reconciles the hash algorithm of the database, as recorded in its manifest, with the
+/// configured one.
+fn check_hash_algorithm(
+    options: &Options,
+    container: &ChunkContainer,
+    manifest: &mut Manifest,
+) -> eyre::Result<()> {
+    let storage = container.storage();
+
+    // The manifest is authoritative. The chunk headers can lag behind when a reindex stopped
+    // before updating all of them.
+    if container.hash_algorithm()? != manifest.hash_algorithm {
+        container.record_hash_algorithm(manifest.hash_algorithm)?;
+    }
+
+    if manifest.hash_algorithm == options.hash_algorithm {
+        return Ok(());
+    }
+
+    if !options.reindex {
+        eyre::bail!(
+            "database uses the '{}' hash algorithm but '{}' is configured, use --reindex to rebuild the index with the configured one",
+            manifest.hash_algorithm,
+            options.hash_algorithm
+        );
+    }
+
+    tracing::warn!(
+        from = %manifest.hash_algorithm,
+        to = %options.hash_algorithm,
+        "hash algorithm changed, the index will be rebuilt"
+    );
+
+    Lsm::clear(storage)?;
+    manifest.hash_algorithm = options.hash_algorithm;
+    manifest.save(storage)?;
+    container.record_hash_algorithm(options.hash_algorithm)?;
+
+    Ok(())
+}
+
+/// Stream ACLs grant operations to the principals of client certificates, so without mutual TLS
+/// every request would be anonymous.
+fn check_acl(options: &Options) -> eyre::Result<()> {
+    let acl = options.acl.is_some() || options.acl_file.is_some();
+    let mutual_tls = options.tls.cert.is_some() && options.tls.client_ca.is_some();
+
+    if acl && !mutual_tls {
+        eyre::bail!(
+            "stream ACLs need mutual TLS to authenticate principals, configure a server certificate and a client CA"
+        );
+    }
+
+    Ok(())
+}
+
 pub async fn run(options: Options) -> eyre::Result<()> {
     let client = run_embedded(&options).await?;
 
-    // TODO - handle CTRL-C signal to properly flush telemetry data before exiting
-    client.manager.manager_exited().await;
-    client.handles.shutdown()?;
+    client.run_until(ctrl_c()).await
+}
 
-    Ok(())
+/// Resolves on the first CTRL-C. A second one exits the process right away, without waiting for
+/// the node to shut down.
+async fn ctrl_c() {
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        tracing::error!(error = %e, "unable to listen for CTRL-C");
+        std::future::pending::<()>().await;
+    }
+
+    tracing::info!("shutting down, press CTRL-C again to exit immediately");
+
+    tokio::spawn(async {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            tracing::warn!("exiting without waiting for the shutdown to complete");
+            std::process::exit(130);
+        }
+    });
 }
 
 #[derive(Clone)]
@@ -85,6 +210,15 @@ impl EmbeddedClient {
     pub fn manager(&self) -> &ManagerClient {
         &self.manager
     }
+
+    /// Runs until the process manager exits or `signal` resolves, whichever comes first. Either
+    /// way, telemetry is flushed before returning.
+    async fn run_until(self, signal: impl Future<Output = ()>) -> eyre::Result<()> {
+        tokio::select! {
+            _ = self.manager.clone().manager_exited() => self.handles.shutdown(),
+            _ = signal => self.shutdown().await,
+        }
+    }
 }
 
 #[derive(Default, Clone)]
@@ -113,18 +247,25 @@ impl TelemetryHandles {
 }
 
 pub async fn run_embedded(options: &Options) -> eyre::Result<EmbeddedClient> {
+    check_acl(options)?;
     let handles = init_telemetry(options)?;
-    configure_metrics();
+    configure_metrics(options.metrics_top_streams);
 
     let storage = configure_storage(options)?;
-    let container = ChunkContainer::load(storage)?;
+    let container = ChunkContainer::load_with(
+        storage,
+        ChunkOptions {
+            chunk_size: options.chunk_size,
+            verify: options.verify_chunks,
+            hash_algorithm: options.hash_algorithm,
+        },
+    )?;
 
-    STORAGE
-        .set(container.storage().clone())
-        .expect("to always work");
-    CHUNK_CONTAINER
-        .set(container)
-        .expect("expect to always work");
+    let mut manifest = check_manifest(&container)?;
+    check_hash_algorithm(options, &container, &mut manifest)?;
+
+    *STORAGE.write().unwrap() = Some(container.storage().clone());
+    *CHUNK_CONTAINER.write().unwrap() = Some(container);
 
     let manager = start_process_manager(options.clone()).await?;
 
@@ -238,28 +379,87 @@ fn init_telemetry(options: &Options) -> eyre::Result<TelemetryHandles> {
         }
     }
 
-    let fmt_layer = if options.telemetry.disabled || options.telemetry.endpoint.is_none() {
-        let layer = tracing_subscriber::fmt::layer()
-            .with_file(true)
-            .with_line_number(true)
-            .with_target(true);
+    let mut fmt_layer = None;
+    let mut json_layer = None;
+
+    if options.telemetry.json_logs {
+        json_layer = Some(
+            tracing_subscriber::fmt::layer()
+                .json()
+                .with_file(true)
+                .with_line_number(true)
+                .with_target(true),
+        );
+    } else if options.telemetry.disabled || options.telemetry.endpoint.is_none() {
+        fmt_layer = Some(
+            tracing_subscriber::fmt::layer()
+                .with_file(true)
+                .with_line_number(true)
+                .with_target(true),
+        );
+    }
 
-        Some(layer)
-    } else {
-        None
+    let (event_filter, event_filter_handle) =
+        reload::Layer::new(create_event_filter(&options.telemetry.event_filters)?);
+
+    let _ = EVENT_FILTER.set(event_filter_handle);
+
+    // The event filter only applies to our own layers, the console layer needs the runtime's
+    // trace level events whatever the filters in use are.
+    #[cfg(feature = "tokio-console")]
+    let console_layer = options.telemetry.tokio_console.then(|| {
+        console_subscriber::ConsoleLayer::builder()
+            .with_default_env()
+            .spawn()
+    });
+
+    #[cfg(not(feature = "tokio-console"))]
+    let console_layer: Option<tracing_subscriber::layer::Identity> = None;
+
+    // `Option` has its own `and_then`.
+    let layers = tracing_subscriber::Layer::and_then(tracer_layer, log_layer)
+        .and_then(fmt_layer)
+        .and_then(json_layer)
+        .with_filter(event_filter);
+
+    // Only the first start of the engine in the process installs its layers.
+    let _ = tracing_subscriber::registry()
+        .with(layers)
+        .with(console_layer)
+        .try_init();
+
+    if cfg!(not(feature = "tokio-console")) && options.telemetry.tokio_console {
+        tracing::warn!(
+            "tokio-console support is not compiled in, rebuild with the tokio-console feature"
+        );
+    }
+
+    Ok(handles)
+}
+
+/// Replaces the log filters passed through `--telemetry-event-filters` while the server is
+/// running. Directives are all checked first, the filters in use are left unchanged if one of
+/// them is invalid.
+pub fn set_log_filters(directives: &[String]) -> eyre::Result<LogFiltersSet> {
+    for directive in directives {
+        if let Err(e) = directive.parse::<Directive>() {
+            return Ok(LogFiltersSet::InvalidDirective(format!(
+                "invalid directive '{directive}': {e}"
+            )));
+        }
+    }
+
+    let Some(handle) = EVENT_FILTER.get() else {
+        eyre::bail!("logging is not initialized");
     };
 
-    tracing_subscriber::registry()
-        .with(create_event_filter(options)?)
-        .with(tracer_layer)
-        .with(log_layer)
-        .with(fmt_layer)
-        .init();
+    handle.reload(create_event_filter(directives)?)?;
+    tracing::info!(?directives, "log filters updated");
 
-    Ok(handles)
+    Ok(LogFiltersSet::Success)
 }
 
-fn create_event_filter(options: &Options) -> eyre::Result<EnvFilter> {
+fn create_event_filter(directives: &[String]) -> eyre::Result<EnvFilter> {
     let ignore = vec!["hyper=off", "tonic=off", "h2=off", "reqwest=off"];
     let default_scopes = vec!["geth_engine=debug"];
 
@@ -273,7 +473,7 @@ fn create_event_filter(options: &Options) -> eyre::Result<EnvFilter> {
         filter = filter.add_directive(directive.parse()?);
     }
 
-    for directive in &options.telemetry.event_filters {
+    for directive in directives {
         filter = filter.add_directive(directive.parse()?);
     }
 
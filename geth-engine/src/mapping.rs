@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::sync::Arc;
+
+use geth_common::{Propose, Record};
+
+/// Reason an [`EventMapper`] failed on an event.
+#[derive(Debug, Clone)]
+pub struct MappingError {
+    pub reason: String,
+}
+
+impl MappingError {
+    pub fn new(reason: impl Into<String>) -> Self {
+        Self {
+            reason: reason.into(),
+        }
+    }
+}
+
+impl Display for MappingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.reason)
+    }
+}
+
+impl std::error::Error for MappingError {}
+
+/// Transforms events while a stream is copied, e.g. to migrate them to a new schema. An event
+/// can be turned into any number of events: none to filter it out, several to split it.
+pub trait EventMapper: Send + Sync {
+    fn map(&self, record: &Record) -> Result<Vec<Propose>, MappingError>;
+}
+
+impl<F> EventMapper for F
+where
+    F: Fn(&Record) -> Result<Vec<Propose>, MappingError> + Send + Sync,
+{
+    fn map(&self, record: &Record) -> Result<Vec<Propose>, MappingError> {
+        self(record)
+    }
+}
+
+/// The mappers a stream copy can refer to by name, registered through [`crate::Options`].
+#[derive(Clone, Default)]
+pub struct EventMappers(Arc<HashMap<String, Arc<dyn EventMapper>>>);
+
+impl EventMappers {
+    pub fn register(&mut self, name: impl Into<String>, mapper: impl EventMapper + 'static) {
+        Arc::make_mut(&mut self.0).insert(name.into(), Arc::new(mapper));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn EventMapper> {
+        self.0.get(name).map(Arc::as_ref)
+    }
+}
+
+impl fmt::Debug for EventMappers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.0.keys()).finish()
+    }
+}
@@ -1,14 +1,19 @@
 use std::{
-    sync::{Arc, RwLock, mpsc},
+    sync::{Arc, Mutex, RwLock, mpsc},
     thread,
     time::Duration,
 };
 
 use geth_mikoshi::wal::{LogEntries, LogEntry};
+use opentelemetry::KeyValue;
 use opentelemetry::metrics::{Counter, Histogram, ObservableGauge, UpDownCounter};
 use sysinfo::{CpuRefreshKind, MemoryRefreshKind, RefreshKind, System};
 use tokio::sync::OnceCell;
 
+use hot_streams::HotStreams;
+
+mod hot_streams;
+
 #[derive(Debug, Clone)]
 pub struct Metrics {
     programs_total: Counter<u64>,
@@ -27,6 +32,14 @@ pub struct Metrics {
     write_size_bytes: Histogram<f64>,
     write_propose_event_total: Counter<u64>,
     write_error_total: Counter<u64>,
+    stream_append_total: Counter<u64>,
+    stream_append_size_bytes: Counter<u64>,
+    stream_read_total: Counter<u64>,
+    stream_read_size_bytes: Counter<u64>,
+    operation_total: Counter<u64>,
+    operation_duration_seconds: Histogram<f64>,
+    operation_first_message_seconds: Histogram<f64>,
+    hot_streams: Arc<Mutex<HotStreams>>,
 
     _total_memory: ObservableGauge<f64>,
     _used_memory: ObservableGauge<f64>,
@@ -92,6 +105,43 @@ impl Metrics {
         self.write_error_total.add(1, &[]);
     }
 
+    pub fn observe_stream_append(&self, stream: &str, size: usize) {
+        let attrs = [self.stream_attribute(stream)];
+
+        self.stream_append_total.add(1, &attrs);
+        self.stream_append_size_bytes.add(size as u64, &attrs);
+    }
+
+    pub fn observe_stream_read(&self, stream: &str, size: usize) {
+        let attrs = [self.stream_attribute(stream)];
+
+        self.stream_read_total.add(1, &attrs);
+        self.stream_read_size_bytes.add(size as u64, &attrs);
+    }
+
+    /// Only the busiest streams get their own series, see [`HotStreams`].
+    fn stream_attribute(&self, stream: &str) -> KeyValue {
+        let series = self.hot_streams.lock().unwrap().observe(stream, 1);
+
+        KeyValue::new("stream", series.to_string())
+    }
+
+    pub fn observe_operation_completed(&self, operation: &'static str, elapsed: Duration) {
+        let attrs = [KeyValue::new("operation", operation)];
+
+        self.operation_total.add(1, &attrs);
+        self.operation_duration_seconds
+            .record(elapsed.as_secs_f64(), &attrs);
+    }
+
+    /// For streaming operations, that's the time it took to send the first record.
+    pub fn observe_operation_first_message(&self, operation: &'static str, elapsed: Duration) {
+        self.operation_first_message_seconds.record(
+            elapsed.as_secs_f64(),
+            &[KeyValue::new("operation", operation)],
+        );
+    }
+
     pub fn observe_client_error(&self) {
         self.client_errors_total.add(1, &[]);
     }
@@ -103,15 +153,27 @@ impl Metrics {
 
 static METRICS: OnceCell<Metrics> = OnceCell::const_new();
 
+// The default boundaries are meant for milliseconds.
+const LATENCY_BOUNDARIES: [f64; 14] = [
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
 pub fn get_metrics() -> Metrics {
     METRICS.get().unwrap().clone()
 }
 
-pub fn configure_metrics() {
-    METRICS.set(init_meter()).expect("not to be configured yet");
+pub fn configure_metrics(top_streams: usize) {
+    // Metrics outlive the engine, starting it again in the same process keeps the first ones.
+    if METRICS.initialized() {
+        return;
+    }
+
+    METRICS
+        .set(init_meter(top_streams))
+        .expect("not to be configured yet");
 }
 
-fn init_meter() -> Metrics {
+fn init_meter(top_streams: usize) -> Metrics {
     let meter = opentelemetry::global::meter("geth-engine");
 
     let refreshes = RefreshKind::nothing()
@@ -228,6 +290,54 @@ fn init_meter() -> Metrics {
             .with_unit("subscriptions")
             .build(),
 
+        stream_append_total: meter
+            .u64_counter("geth_stream_append_total")
+            .with_description("Total number of events appended, per stream")
+            .with_unit("events")
+            .build(),
+
+        stream_append_size_bytes: meter
+            .u64_counter("geth_stream_append_size_bytes")
+            .with_description("Total size of the events appended, per stream")
+            .with_unit("bytes")
+            .build(),
+
+        stream_read_total: meter
+            .u64_counter("geth_stream_read_total")
+            .with_description("Total number of events read, per stream")
+            .with_unit("events")
+            .build(),
+
+        stream_read_size_bytes: meter
+            .u64_counter("geth_stream_read_size_bytes")
+            .with_description("Total size of the events read, per stream")
+            .with_unit("bytes")
+            .build(),
+
+        operation_total: meter
+            .u64_counter("geth_operation_total")
+            .with_description("Total number of gRPC operations served, per operation")
+            .with_unit("operations")
+            .build(),
+
+        operation_duration_seconds: meter
+            .f64_histogram("geth_operation_duration_seconds")
+            .with_description("Distribution of the gRPC operations duration, per operation")
+            .with_unit("s")
+            .with_boundaries(LATENCY_BOUNDARIES.to_vec())
+            .build(),
+
+        operation_first_message_seconds: meter
+            .f64_histogram("geth_operation_first_message_seconds")
+            .with_description(
+                "Distribution of the time to send the first message of a gRPC response, per operation",
+            )
+            .with_unit("s")
+            .with_boundaries(LATENCY_BOUNDARIES.to_vec())
+            .build(),
+
+        hot_streams: Arc::new(Mutex::new(HotStreams::new(top_streams))),
+
         _total_memory: meter
             .f64_observable_gauge("geth_sys_memory_total")
             .with_description("Total system memory")
@@ -0,0 +1,121 @@
+use std::collections::{HashMap, HashSet};
+
+/// Series the streams that aren't among the busiest ones are reported under. User streams can't
+/// start with `$`, so it can't collide with one of them.
+pub const OTHER_STREAMS: &str = "$other";
+
+/// How many observations go by before the busiest streams are worked out again.
+const REFRESH_INTERVAL: u32 = 256;
+
+#[derive(Debug)]
+struct Slot {
+    count: u64,
+    /// How much of `count` may belong to the streams the slot was taken from.
+    error: u64,
+}
+
+/// Keeps track of the `top` busiest streams using the space-saving algorithm: a fixed number of
+/// counters is shared by all streams, a stream without one taking over the counter of the least
+/// busy stream. Memory and the number of metric series stay bounded no matter how many streams
+/// the database holds.
+#[derive(Debug)]
+pub struct HotStreams {
+    top: usize,
+    capacity: usize,
+    slots: HashMap<String, Slot>,
+    hot: HashSet<String>,
+    until_refresh: u32,
+}
+
+impl HotStreams {
+    pub fn new(top: usize) -> Self {
+        // Extra counters make the estimates of the busiest streams a lot more accurate.
+        let capacity = top.saturating_mul(4);
+
+        Self {
+            top,
+            capacity,
+            slots: HashMap::with_capacity(capacity),
+            hot: HashSet::with_capacity(top),
+            until_refresh: REFRESH_INTERVAL,
+        }
+    }
+
+    /// Accounts for `weight` operations on `stream` and returns the series they are reported
+    /// under, either the stream itself or [`OTHER_STREAMS`].
+    pub fn observe<'a>(&mut self, stream: &'a str, weight: u64) -> &'a str {
+        if self.capacity == 0 {
+            return OTHER_STREAMS;
+        }
+
+        if let Some(slot) = self.slots.get_mut(stream) {
+            slot.count += weight;
+        } else if self.slots.len() < self.capacity {
+            self.slots.insert(
+                stream.to_string(),
+                Slot {
+                    count: weight,
+                    error: 0,
+                },
+            );
+
+            // Until the top is full, a new stream makes it there right away.
+            if self.hot.len() < self.top {
+                self.hot.insert(stream.to_string());
+            }
+        } else {
+            self.replace_least_busy(stream, weight);
+        }
+
+        self.until_refresh -= 1;
+        if self.until_refresh == 0 {
+            self.refresh();
+        }
+
+        if self.hot.contains(stream) {
+            stream
+        } else {
+            OTHER_STREAMS
+        }
+    }
+
+    fn replace_least_busy(&mut self, stream: &str, weight: u64) {
+        let Some((evicted, min)) = self
+            .slots
+            .iter()
+            .min_by_key(|(_, slot)| slot.count)
+            .map(|(name, slot)| (name.clone(), slot.count))
+        else {
+            return;
+        };
+
+        self.slots.remove(&evicted);
+        self.slots.insert(
+            stream.to_string(),
+            Slot {
+                count: min + weight,
+                error: min,
+            },
+        );
+    }
+
+    /// Streams are ranked by the operations they are guaranteed to have, so a stream that just
+    /// took over a busy counter doesn't make it to the top right away.
+    fn refresh(&mut self) {
+        let mut ranked = self
+            .slots
+            .iter()
+            .map(|(name, slot)| (slot.count - slot.error, name))
+            .collect::<Vec<_>>();
+
+        ranked.sort_unstable_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+
+        self.hot = ranked
+            .into_iter()
+            .take(self.top)
+            .map(|(_, name)| name.clone())
+            .collect();
+
+        self.until_refresh = REFRESH_INTERVAL;
+    }
+}
@@ -3,10 +3,80 @@ pub mod streams {
     pub static ALL: &str = "$all";
     pub static GLOBALS: &str = "$globals";
     pub static SYSTEM: &str = "$system";
+
+    /// Stream holding the metadata of another stream, truncation markers for instance.
+    pub fn metadata_of(stream: &str) -> String {
+        format!("$${stream}")
+    }
+
+    pub fn is_metadata(stream: &str) -> bool {
+        stream.starts_with("$$")
+    }
+
+    /// Stream `stream` holds the metadata of, if it's a metadata stream.
+    pub fn metadata_target(stream: &str) -> Option<&str> {
+        stream.strip_prefix("$$")
+    }
+
+    /// Stream holding the checkpoints of a persistent subscription. Group names can't contain
+    /// `:` so two subscriptions never share one.
+    pub fn checkpoints_of(group: &str, stream: &str) -> String {
+        format!("$checkpoints:{group}:{stream}")
+    }
+}
+
+/// Streams of a namespace are stored under a reserved prefix, `$ns:<namespace>:<stream>`, so
+/// requests scoped to different namespaces never address the same streams.
+pub mod namespaces {
+    pub static PREFIX: &str = "$ns:";
+    /// First name past the ones starting with [`PREFIX`].
+    pub static PREFIX_END: &str = "$ns;";
+
+    pub fn scope(namespace: &str, stream: &str) -> String {
+        format!("{PREFIX}{namespace}:{stream}")
+    }
+
+    /// Namespace a stored stream, or the stream it holds the metadata of, belongs to. `None` is
+    /// the default namespace.
+    pub fn namespace_of(stream: &str) -> Option<&str> {
+        let stream = stream.strip_prefix("$$").unwrap_or(stream);
+
+        stream
+            .strip_prefix(PREFIX)
+            .and_then(|rest| rest.split_once(':'))
+            .map(|(namespace, _)| namespace)
+    }
+
+    /// Name of a stored stream as seen from within `namespace`, `None` if it belongs to another
+    /// namespace.
+    pub fn unscope(namespace: Option<&str>, stream: &str) -> Option<String> {
+        if namespace_of(stream) != namespace {
+            return None;
+        }
+
+        let Some(namespace) = namespace else {
+            return Some(stream.to_string());
+        };
+
+        let scoped = scope(namespace, "");
+        if let Some(meta) = stream.strip_prefix("$$") {
+            return meta.strip_prefix(&scoped).map(|s| format!("$${s}"));
+        }
+
+        stream.strip_prefix(&scoped).map(str::to_string)
+    }
 }
 
 pub mod types {
     pub static STREAM_DELETED: &str = "$stream-deleted";
+    pub static STREAM_TRUNCATED: &str = "$stream-truncated";
+    pub static STREAM_METADATA: &str = "$metadata";
+    pub static CHECKPOINT: &str = "$checkpoint";
     pub static EVENTS_WRITTEN: &str = "$events-written";
     pub static EVENTS_INDEXED: &str = "$events-indexed";
+
+    /// Classes only the server appends, through deletions, truncations and settings.
+    pub fn is_reserved(class: &str) -> bool {
+        class == STREAM_DELETED || class == STREAM_TRUNCATED || class == STREAM_METADATA
+    }
 }
@@ -1,4 +1,15 @@
 use clap::Parser;
+use geth_common::ProgramLimits;
+use geth_mikoshi::compression::Compression;
+use geth_mikoshi::hashing::HashAlgorithm;
+use geth_mikoshi::wal::chunks::DEFAULT_CHUNK_SIZE;
+
+use std::path::PathBuf;
+
+use crate::acl::{AclResolver, SharedAclResolver};
+use crate::clock::{Clock, SharedClock};
+use crate::mapping::{EventMapper, EventMappers};
+use crate::validation::{SchemaValidator, SharedSchemaValidator};
 
 #[derive(Parser, Debug, Clone, Default)]
 pub struct Telemetry {
@@ -38,6 +49,32 @@ pub struct Telemetry {
 
     #[arg(long = "telemetry-event-filters")]
     pub event_filters: Vec<String>,
+
+    /// Write logs to stdout as JSON, one object per line. Works alongside the OpenTelemetry
+    /// exporters.
+    #[arg(long = "telemetry-json-logs", env = "GETH_TELEMETRY_JSON_LOGS")]
+    pub json_logs: bool,
+
+    /// Serve the runtime's tasks to tokio-console, on `TOKIO_CONSOLE_BIND` or 127.0.0.1:6669.
+    /// Requires the `tokio-console` feature.
+    #[arg(long = "telemetry-tokio-console", env = "GETH_TELEMETRY_TOKIO_CONSOLE")]
+    pub tokio_console: bool,
+}
+
+/// TLS settings of the gRPC server. It serves plaintext unless a certificate is set.
+#[derive(Parser, Debug, Clone, Default)]
+pub struct Tls {
+    /// PEM encoded certificate chain the gRPC server presents.
+    #[arg(long = "tls-cert", env = "GETH_TLS_CERT", requires = "key")]
+    pub cert: Option<PathBuf>,
+
+    /// PEM encoded private key of the certificate.
+    #[arg(long = "tls-key", env = "GETH_TLS_KEY", requires = "cert")]
+    pub key: Option<PathBuf>,
+
+    /// PEM encoded CA certificate. When set, clients must present a certificate it signed.
+    #[arg(long = "tls-client-ca", env = "GETH_TLS_CLIENT_CA", requires = "cert")]
+    pub client_ca: Option<PathBuf>,
 }
 
 #[derive(Parser, Debug, Clone)]
@@ -60,18 +97,137 @@ pub struct Options {
     #[command(flatten)]
     pub telemetry: Telemetry,
 
+    #[command(flatten)]
+    pub tls: Tls,
+
+    /// Hash algorithm used to compute stream keys: sha512, xxh3 or fnv1a. A database keeps using
+    /// the algorithm it was created with.
+    #[arg(long, default_value = "sha512", env = "GETH_HASH_ALGORITHM")]
+    pub hash_algorithm: HashAlgorithm,
+
+    /// Rebuilds the index when the configured hash algorithm differs from the database one.
+    #[arg(long, env = "GETH_REINDEX")]
+    pub reindex: bool,
+
+    /// Checks the data of every completed chunk against the hash recorded in its footer on startup.
+    /// Startup fails if a chunk is corrupted.
+    #[arg(long, env = "GETH_VERIFY_CHUNKS")]
+    pub verify_chunks: bool,
+
+    /// Size in bytes of the chunks of a new database. An existing database keeps the chunk size it
+    /// was created with.
+    #[arg(long, default_value_t = DEFAULT_CHUNK_SIZE, env = "GETH_CHUNK_SIZE")]
+    pub chunk_size: usize,
+
+    /// Algorithm event payloads are compressed with in the log: none, lz4 or zstd. Records keep
+    /// the algorithm they were written with, changing it doesn't affect existing records.
+    #[arg(long, default_value = "none", env = "GETH_COMPRESSION")]
+    pub compression: Compression,
+
+    /// Payloads smaller than that many bytes are stored uncompressed.
+    #[arg(long, default_value = "4096", env = "GETH_COMPRESSION_THRESHOLD")]
+    pub compression_threshold: usize,
+
+    /// Maximum number of records a read produces before letting other reads make progress.
+    #[arg(long, default_value = "500", env = "GETH_READ_QUANTUM")]
+    pub read_quantum: usize,
+
+    /// Directory of JSON schemas, one `<class>.json` file per event class. When set, appended
+    /// events are validated against the schema of their class.
+    #[arg(long, env = "GETH_SCHEMA_DIR")]
+    pub schema_dir: Option<PathBuf>,
+
+    /// Validator appended events go through, takes precedence over `schema_dir`.
+    #[arg(skip)]
+    pub schema_validator: Option<SharedSchemaValidator>,
+
+    /// JSON file mapping stream name prefixes to the principals allowed to read, write and
+    /// delete them. Streams are open to everyone when unset.
+    #[arg(long, env = "GETH_ACL_FILE")]
+    pub acl_file: Option<PathBuf>,
+
+    /// Principals allowed to run and stop programs, change the log filters and maintain the index
+    /// of a server whose ACLs come from `acl_file`.
+    #[arg(long = "acl-admin", env = "GETH_ACL_ADMINS", value_delimiter = ',')]
+    pub acl_admins: Vec<String>,
+
+    /// Resolver of the stream ACLs the gRPC server enforces, takes precedence over `acl_file`.
+    #[arg(skip)]
+    pub acl: Option<SharedAclResolver>,
+
+    /// Mappers stream copies can transform events with, by name.
+    #[arg(skip)]
+    pub mappers: EventMappers,
+
+    /// Events a program can emit within a second before being stopped.
+    #[arg(long, default_value = "10000", env = "GETH_PROGRAM_MAX_EVENTS_PER_SEC")]
+    pub program_max_events_per_sec: u64,
+
+    /// Streams a program can be subscribed to at the same time before being stopped.
+    #[arg(long, default_value = "32", env = "GETH_PROGRAM_MAX_SUBSCRIPTIONS")]
+    pub program_max_subscriptions: usize,
+
+    /// Emitted events a program can have waiting to be pushed before being stopped.
+    #[arg(long, default_value = "10000", env = "GETH_PROGRAM_MAX_OUTPUT_BUFFER")]
+    pub program_max_output_buffer: usize,
+
+    /// Items a process can have waiting in its mailbox. Past that, the requests sent to the
+    /// process fail right away instead of piling up in memory, except the ones that can't be
+    /// lost, which wait for room. Also the messages a streamed response, e.g. a read, can have
+    /// waiting for its consumer.
+    #[arg(long, default_value = "10000", env = "GETH_MAILBOX_CAPACITY")]
+    pub mailbox_capacity: usize,
+
+    /// Milliseconds a process has to reply to a request before the request fails with a timeout.
+    /// The writer's requests to index and publish an append never time out, they are already in
+    /// the log.
+    #[arg(long, default_value = "30000", env = "GETH_REQUEST_TIMEOUT_IN_MS")]
+    pub request_timeout_in_ms: u64,
+
+    /// Streams getting their own per-stream metric series, the busiest ones. The others are
+    /// reported together under a `$other` series.
+    #[arg(long, default_value = "20", env = "GETH_METRICS_TOP_STREAMS")]
+    pub metrics_top_streams: usize,
+
     #[arg(skip)]
     pub disable_grpc: bool,
+
+    /// Clock used for the timestamps the server assigns.
+    #[arg(skip)]
+    pub clock: SharedClock,
 }
 
 impl Options {
     pub fn new(host: String, port: u16, db: String) -> Self {
+        let limits = ProgramLimits::default();
+
         Self {
             host,
             port,
             db,
             telemetry: Telemetry::default(),
+            tls: Tls::default(),
+            hash_algorithm: HashAlgorithm::default(),
+            reindex: false,
+            verify_chunks: false,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            compression: Compression::None,
+            compression_threshold: 4_096,
+            read_quantum: 500,
+            schema_dir: None,
+            schema_validator: None,
+            acl_file: None,
+            acl_admins: Vec::new(),
+            acl: None,
+            mappers: EventMappers::default(),
+            program_max_events_per_sec: limits.max_events_per_sec,
+            program_max_subscriptions: limits.max_subscriptions,
+            program_max_output_buffer: limits.max_output_buffer,
+            mailbox_capacity: 10_000,
+            request_timeout_in_ms: 30_000,
+            metrics_top_streams: 20,
             disable_grpc: false,
+            clock: SharedClock::default(),
         }
     }
 
@@ -99,6 +255,10 @@ impl Options {
         }
     }
 
+    pub fn with_tls(self, tls: Tls) -> Self {
+        Self { tls, ..self }
+    }
+
     pub fn disable_grpc(self) -> Self {
         Self {
             disable_grpc: true,
@@ -106,6 +266,62 @@ impl Options {
         }
     }
 
+    pub fn with_clock(self, clock: impl Clock + 'static) -> Self {
+        Self {
+            clock: SharedClock::new(clock),
+            ..self
+        }
+    }
+
+    pub fn with_schema_validator(self, validator: impl SchemaValidator + 'static) -> Self {
+        Self {
+            schema_validator: Some(SharedSchemaValidator::new(validator)),
+            ..self
+        }
+    }
+
+    pub fn with_acl(self, resolver: impl AclResolver + 'static) -> Self {
+        Self {
+            acl: Some(SharedAclResolver::new(resolver)),
+            ..self
+        }
+    }
+
+    pub fn with_mapper(
+        mut self,
+        name: impl Into<String>,
+        mapper: impl EventMapper + 'static,
+    ) -> Self {
+        self.mappers.register(name, mapper);
+        self
+    }
+
+    pub fn with_compression(self, compression: Compression, threshold: usize) -> Self {
+        Self {
+            compression,
+            compression_threshold: threshold,
+            ..self
+        }
+    }
+
+    pub fn with_program_limits(self, limits: ProgramLimits) -> Self {
+        Self {
+            program_max_events_per_sec: limits.max_events_per_sec,
+            program_max_subscriptions: limits.max_subscriptions,
+            program_max_output_buffer: limits.max_output_buffer,
+            ..self
+        }
+    }
+
+    /// Limits every program started on this server runs with.
+    pub fn program_limits(&self) -> ProgramLimits {
+        ProgramLimits {
+            max_events_per_sec: self.program_max_events_per_sec,
+            max_subscriptions: self.program_max_subscriptions,
+            max_output_buffer: self.program_max_output_buffer,
+        }
+    }
+
     pub fn in_mem() -> Self {
         Self {
             db: "in_mem".to_string(),
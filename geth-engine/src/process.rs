@@ -1,6 +1,9 @@
 use messages::Messages;
-use std::time::Instant;
-use tokio::sync::mpsc::UnboundedSender;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{
+    Sender,
+    error::{SendTimeoutError, TrySendError},
+};
 use uuid::Uuid;
 
 use crate::Options;
@@ -10,6 +13,7 @@ use crate::process::manager::{Catalog, start_process_manager_with_catalog};
 mod tests;
 
 pub mod consumer;
+pub mod copying;
 #[cfg(test)]
 mod echo;
 mod env;
@@ -19,6 +23,7 @@ pub mod manager;
 mod messages;
 #[cfg(test)]
 mod panic;
+pub mod persistent;
 pub mod query;
 pub mod reading;
 #[cfg(test)]
@@ -51,15 +56,62 @@ impl RequestContext {
 
 #[derive(Clone)]
 enum Mailbox {
-    Tokio(UnboundedSender<Item>),
-    Raw(std::sync::mpsc::Sender<Item>),
+    Tokio(Sender<Item>),
+    Raw(std::sync::mpsc::SyncSender<Item>),
+}
+
+/// Why an item couldn't be delivered to a process.
+#[allow(clippy::large_enum_variant)]
+enum Undelivered {
+    /// The process is lagging behind and its mailbox is at capacity.
+    Full(Item),
+    /// The process is terminated.
+    Closed,
 }
 
 impl Mailbox {
-    pub fn send(&self, item: Item) -> bool {
+    /// Never waits for room in the mailbox, so a slow process can't stall the process manager.
+    #[allow(clippy::result_large_err)]
+    pub fn send(&self, item: Item) -> Result<(), Undelivered> {
         match self {
-            Mailbox::Tokio(x) => x.send(item).is_ok(),
-            Mailbox::Raw(x) => x.send(item).is_ok(),
+            Mailbox::Tokio(x) => x.try_send(item).map_err(|e| match e {
+                TrySendError::Full(item) => Undelivered::Full(item),
+                TrySendError::Closed(_) => Undelivered::Closed,
+            }),
+
+            Mailbox::Raw(x) => x.try_send(item).map_err(|e| match e {
+                std::sync::mpsc::TrySendError::Full(item) => Undelivered::Full(item),
+                std::sync::mpsc::TrySendError::Disconnected(_) => Undelivered::Closed,
+            }),
+        }
+    }
+
+    /// Waits up to `timeout` for room in the mailbox.
+    pub async fn send_timeout(&self, item: Item, timeout: Duration) -> Result<(), Undelivered> {
+        match self {
+            Mailbox::Tokio(x) => x.send_timeout(item, timeout).await.map_err(|e| match e {
+                SendTimeoutError::Timeout(item) => Undelivered::Full(item),
+                SendTimeoutError::Closed(_) => Undelivered::Closed,
+            }),
+
+            // There is no async send on std channels, so it retries until the deadline.
+            Mailbox::Raw(_) => {
+                let deadline = tokio::time::Instant::now() + timeout;
+                let mut item = item;
+
+                loop {
+                    match self.send(item) {
+                        Err(Undelivered::Full(rejected))
+                            if tokio::time::Instant::now() < deadline =>
+                        {
+                            item = rejected;
+                            tokio::time::sleep(Duration::from_millis(1)).await;
+                        }
+
+                        outcome => return outcome,
+                    }
+                }
+            }
         }
     }
 }
@@ -104,7 +156,7 @@ pub struct Stream {
     context: RequestContext,
     correlation: Uuid,
     payload: Messages,
-    sender: UnboundedSender<Messages>,
+    sender: Sender<Messages>,
 }
 
 pub enum Item {
@@ -130,6 +182,8 @@ impl Item {
 pub enum SpawnError {
     LimitReached,
     Timeout,
+    /// The process is not part of the catalog the manager was started with.
+    NotRegistered,
 }
 
 pub enum SpawnResult {
@@ -151,6 +205,9 @@ impl SpawnResult {
             SpawnResult::Failure { proc, error } => match error {
                 SpawnError::LimitReached => eyre::bail!("process {:?} limit reached", proc),
                 SpawnError::Timeout => eyre::bail!("process {:?} did not start in time", proc),
+                SpawnError::NotRegistered => {
+                    eyre::bail!("process {:?} is not registered in the catalog", proc)
+                }
             },
         }
     }
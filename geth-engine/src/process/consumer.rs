@@ -1,14 +1,20 @@
 use std::{cmp::max, collections::VecDeque, fmt::Display};
 
 use geth_common::{
-    Direction, ReadStreamCompleted, Record, Revision, SubscriptionEvent, UnsubscribeReason,
+    Direction, ReadStreamCompleted, Record, Revision, SubscriptionBatching,
+    SubscriptionConfirmation, SubscriptionEvent, UnsubscribeReason,
+};
+use tokio::{
+    select,
+    sync::mpsc::{UnboundedReceiver, unbounded_channel},
+    task::JoinHandle,
+    time::Instant,
 };
-use geth_mikoshi::hashing::mikoshi_hash;
-use tokio::select;
 use tracing::instrument;
 
 use crate::{
-    IndexClient, ManagerClient, ReaderClient, RequestContext,
+    ManagerClient, ReaderClient, RequestContext,
+    names::streams,
     process::subscription::{self, SubscriptionClient},
     reading,
 };
@@ -37,10 +43,14 @@ pub struct Consumer {
     state: State,
     done: bool,
     stream_name: String,
-    end: u64,
+    /// Lowest revision, or log position when subscribed to `$all`, that can still be delivered.
+    next: u64,
     history: VecDeque<Record>,
+    /// First catch-up record, read ahead to confirm where the subscription starts.
+    peeked: Option<Record>,
+    /// Only records of those classes are delivered, every record when empty.
+    class_filter: Vec<String>,
     reader: ReaderClient,
-    index: IndexClient,
     sub: SubscriptionClient,
     start: Revision<u64>,
     reader_streaming: reading::Streaming,
@@ -59,29 +69,30 @@ pub async fn start_consumer(
     start: Revision<u64>,
     client: ManagerClient,
 ) -> eyre::Result<ConsumerResult> {
-    let index = client.new_index_client().await?;
     let reader = client.new_reader_client().await?;
     let sub = client.new_subscription_client().await?;
 
-    let result = index
-        .latest_revision(context, mikoshi_hash(&stream_name))
-        .await?;
+    if stream_name != streams::ALL {
+        let index = client.new_index_client().await?;
+        let result = index
+            .latest_revision(context, index.key(&stream_name))
+            .await?;
 
-    if result.is_deleted() {
-        return Ok(ConsumerResult::StreamDeleted);
+        if result.is_deleted() {
+            return Ok(ConsumerResult::StreamDeleted);
+        }
     }
 
-    let end = result.revision().unwrap_or_default();
-
     Ok(ConsumerResult::Success(Consumer {
         context,
         state: State::Init,
         done: false,
         history: VecDeque::new(),
+        peeked: None,
+        class_filter: Vec::new(),
         stream_name,
-        end,
+        next: 0,
         reader,
-        index,
         sub,
         start,
         reader_streaming: reading::Streaming::empty(),
@@ -90,6 +101,12 @@ pub async fn start_consumer(
 }
 
 impl Consumer {
+    /// Only delivers records of `classes`, every record when empty.
+    pub fn filter_classes(mut self, classes: Vec<String>) -> Self {
+        self.class_filter = classes;
+        self
+    }
+
     // CAUTION: a situation where an user is reading very far away from the head of the stream and while that stream is actively being writen on could lead
     // to uncheck memory usage as everything will be stored in the history buffer.
     //
@@ -103,20 +120,48 @@ impl Consumer {
         loop {
             match self.state {
                 State::Init => {
-                    let result = self
-                        .reader
-                        .read(
-                            self.context,
-                            &self.stream_name,
-                            self.start,
-                            Direction::Forward,
-                            usize::MAX,
-                        )
+                    // We subscribe before reading so every record committed after the read
+                    // started is delivered by the subscription. Records seen by both are
+                    // deduplicated when played.
+                    let mut sub_streaming = self
+                        .sub
+                        .subscribe_to_stream(self.context, &self.stream_name)
                         .await?;
 
+                    let Some(SubscriptionEvent::Confirmed(conf)) = sub_streaming.next().await?
+                    else {
+                        self.done = true;
+                        eyre::bail!("subscription was not confirmed");
+                    };
+
+                    let result = if self.stream_name == streams::ALL {
+                        ReadStreamCompleted::Success(
+                            self.reader
+                                .read_all(
+                                    self.context,
+                                    self.start,
+                                    Direction::Forward,
+                                    usize::MAX,
+                                    false,
+                                )
+                                .await?,
+                        )
+                    } else {
+                        self.reader
+                            .read(
+                                self.context,
+                                &self.stream_name,
+                                self.start,
+                                Direction::Forward,
+                                usize::MAX,
+                            )
+                            .await?
+                    };
+
                     match result {
                         ReadStreamCompleted::StreamDeleted => {
                             tracing::error!("stream got deleted while streaming");
+                            self.done = true;
                             return Ok(Some(SubscriptionEvent::Unsubscribed(
                                 UnsubscribeReason::Server,
                             )));
@@ -127,43 +172,45 @@ impl Consumer {
                         }
                     };
 
-                    let result = self
-                        .index
-                        .latest_revision(self.context, mikoshi_hash(&self.stream_name))
-                        .await?;
-
-                    if result.is_deleted() {
-                        tracing::error!("stream got deleted while streaming");
-                        return Ok(Some(SubscriptionEvent::Unsubscribed(
-                            UnsubscribeReason::Server,
-                        )));
+                    if let Revision::Revision(start) = self.start {
+                        self.next = start;
                     }
 
-                    self.end = result.revision().unwrap_or_default();
-
-                    let mut sub_streaming = self
-                        .sub
-                        .subscribe_to_stream(self.context, &self.stream_name)
-                        .await?;
+                    let conf = match conf {
+                        SubscriptionConfirmation::StreamName { stream_name, .. }
+                            if self.stream_name != streams::ALL =>
+                        {
+                            SubscriptionConfirmation::StreamName {
+                                stream_name,
+                                start_revision: Some(self.resolve_start_revision().await?),
+                            }
+                        }
 
-                    if let Some(SubscriptionEvent::Confirmed(conf)) = sub_streaming.next().await? {
-                        self.state = State::CatchingUp;
-                        self.sub_streaming = sub_streaming;
-                        return Ok(Some(SubscriptionEvent::Confirmed(conf)));
-                    }
+                        conf => conf,
+                    };
 
-                    self.done = true;
-                    eyre::bail!("subscription was not confirmed");
+                    self.state = State::CatchingUp;
+                    self.sub_streaming = sub_streaming;
+                    return Ok(Some(SubscriptionEvent::Confirmed(conf)));
                 }
 
                 State::CatchingUp => {
+                    if let Some(record) = self.peeked.take() {
+                        if self.accept(&record) {
+                            return Ok(Some(SubscriptionEvent::EventAppeared(record)));
+                        }
+
+                        continue;
+                    }
+
                     select! {
                         outcome = self.reader_streaming.next() => {
                             match outcome {
                                 Err(e) => return Err(e),
                                 Ok(outcome) => if let Some(event) = outcome {
-                                    self.end = max(self.end, event.revision);
-                                    return Ok(Some(SubscriptionEvent::EventAppeared(event)))
+                                    if self.accept(&event) {
+                                        return Ok(Some(SubscriptionEvent::EventAppeared(event)));
+                                    }
                                 } else {
                                     if self.history.is_empty() {
                                         self.state = State::Live;
@@ -182,11 +229,6 @@ impl Consumer {
                                 if let Some(event) = outcome {
                                     match event {
                                         SubscriptionEvent::EventAppeared(record) => {
-                                            if record.revision < self.end {
-                                                continue;
-                                            }
-
-                                            self.end = record.revision;
                                             self.history.push_back(record);
                                         }
 
@@ -197,7 +239,10 @@ impl Consumer {
 
                                         SubscriptionEvent::Notification(n) => return Ok(Some(SubscriptionEvent::Notification(n))),
 
-                                        SubscriptionEvent::CaughtUp | SubscriptionEvent::Confirmed(_) => unreachable!(),
+                                        SubscriptionEvent::CaughtUp
+                                        | SubscriptionEvent::EventsAppeared(_)
+                                        | SubscriptionEvent::Confirmed(_)
+                                        | SubscriptionEvent::ProgramLog { .. } => unreachable!(),
                                     }
                                 } else {
                                     self.done = true;
@@ -210,12 +255,11 @@ impl Consumer {
 
                 State::PlayHistory => {
                     if let Some(record) = self.history.pop_front() {
-                        if record.revision < self.end {
-                            continue;
+                        if self.accept(&record) {
+                            return Ok(Some(SubscriptionEvent::EventAppeared(record)));
                         }
 
-                        self.end = record.revision;
-                        return Ok(Some(SubscriptionEvent::EventAppeared(record)));
+                        continue;
                     }
 
                     self.state = State::Live;
@@ -223,8 +267,8 @@ impl Consumer {
 
                 State::Live => {
                     if let Some(event) = self.sub_streaming.next().await? {
-                        if let SubscriptionEvent::EventAppeared(temp) = &event
-                            && temp.revision < self.end
+                        if let SubscriptionEvent::EventAppeared(record) = &event
+                            && !self.accept(record)
                         {
                             continue;
                         }
@@ -238,4 +282,162 @@ impl Consumer {
             }
         }
     }
+
+    /// Records are delivered in order, the ones we already delivered are dropped.
+    /// Reads the first catch-up record ahead to find the revision the subscription actually
+    /// starts at. When there is nothing to catch up on, it starts right after the end of the
+    /// stream, or at the requested revision if that's further.
+    async fn resolve_start_revision(&mut self) -> eyre::Result<u64> {
+        self.peeked = self.reader_streaming.next().await?;
+
+        if let Some(record) = &self.peeked {
+            return Ok(record.revision);
+        }
+
+        let requested = if let Revision::Revision(start) = self.start {
+            start
+        } else {
+            0
+        };
+
+        Ok(self
+            .reader_streaming
+            .tail_revision()
+            .map_or(requested, |tail| max(requested, tail + 1)))
+    }
+
+    /// Records the class filter rules out aren't delivered but still move the subscription
+    /// forward.
+    fn accept(&mut self, record: &Record) -> bool {
+        let cursor = if self.stream_name == streams::ALL {
+            record.position
+        } else {
+            record.revision
+        };
+
+        if cursor < self.next {
+            return false;
+        }
+
+        self.next = cursor + 1;
+
+        self.class_filter.is_empty() || self.class_filter.contains(&record.class)
+    }
+}
+
+/// Delivers what a [`Consumer`] reads, one record at a time or in batches.
+#[allow(clippy::large_enum_variant)]
+pub enum Delivery {
+    Single(Consumer),
+    Batched(BatchedConsumer),
+}
+
+impl Delivery {
+    pub fn new(consumer: Consumer, batching: Option<SubscriptionBatching>) -> Self {
+        match batching {
+            None => Delivery::Single(consumer),
+            Some(batching) => Delivery::Batched(BatchedConsumer::new(consumer, batching)),
+        }
+    }
+
+    pub async fn next(&mut self) -> eyre::Result<Option<SubscriptionEvent>> {
+        match self {
+            Delivery::Single(consumer) => consumer.next().await,
+            Delivery::Batched(consumer) => consumer.next().await,
+        }
+    }
+}
+
+/// Groups the records of a consumer as described by [`SubscriptionBatching`]. The consumer runs
+/// in its own task because it can't be interrupted halfway through `next` when a batch is due.
+pub struct BatchedConsumer {
+    batching: SubscriptionBatching,
+    outcomes: UnboundedReceiver<eyre::Result<SubscriptionEvent>>,
+    pending: Vec<Record>,
+    /// When the pending records must be delivered.
+    deadline: Option<Instant>,
+    /// What came after the pending records, delivered once they are.
+    held: Option<eyre::Result<Option<SubscriptionEvent>>>,
+    task: JoinHandle<()>,
+}
+
+impl BatchedConsumer {
+    fn new(mut consumer: Consumer, batching: SubscriptionBatching) -> Self {
+        let (sender, outcomes) = unbounded_channel();
+
+        let task = tokio::spawn(async move {
+            while let Some(outcome) = consumer.next().await.transpose() {
+                let failed = outcome.is_err();
+
+                if sender.send(outcome).is_err() || failed {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            batching,
+            outcomes,
+            pending: Vec::new(),
+            deadline: None,
+            held: None,
+            task,
+        }
+    }
+
+    pub async fn next(&mut self) -> eyre::Result<Option<SubscriptionEvent>> {
+        if let Some(outcome) = self.held.take() {
+            return outcome;
+        }
+
+        loop {
+            let received = match self.deadline {
+                None => self.outcomes.recv().await,
+                Some(deadline) => {
+                    match tokio::time::timeout_at(deadline, self.outcomes.recv()).await {
+                        Ok(received) => received,
+                        Err(_) => return Ok(Some(self.flush())),
+                    }
+                }
+            };
+
+            let outcome = match received {
+                Some(Ok(SubscriptionEvent::EventAppeared(record))) => {
+                    if self.pending.is_empty() {
+                        self.deadline = Some(Instant::now() + self.batching.max_delay);
+                    }
+
+                    self.pending.push(record);
+
+                    if self.pending.len() >= self.batching.max_size {
+                        return Ok(Some(self.flush()));
+                    }
+
+                    continue;
+                }
+
+                Some(Ok(event)) => Ok(Some(event)),
+                Some(Err(e)) => Err(e),
+                None => Ok(None),
+            };
+
+            if self.pending.is_empty() {
+                return outcome;
+            }
+
+            self.held = Some(outcome);
+            return Ok(Some(self.flush()));
+        }
+    }
+
+    fn flush(&mut self) -> SubscriptionEvent {
+        self.deadline = None;
+        SubscriptionEvent::EventsAppeared(std::mem::take(&mut self.pending))
+    }
+}
+
+impl Drop for BatchedConsumer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
 }
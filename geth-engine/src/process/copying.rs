@@ -0,0 +1,193 @@
+use geth_common::{
+    AppendError, AppendStreamCompleted, CopyError, CopyStream, CopyStreamCompleted, DeleteError,
+    DeleteStreamCompleted, Direction, ExpectedRevision, MapperErrorPolicy, Propose,
+    ReadStreamCompleted, Revision, StreamCopied,
+};
+
+use crate::mapping::EventMappers;
+use crate::process::RequestContext;
+use crate::process::reading::ReaderClient;
+use crate::process::writing::WriterClient;
+
+const COPY_BATCH_SIZE: usize = 500;
+
+/// Reads the source stream and appends its events to the target stream, in batches. Each batch
+/// expects the revision the previous one left the target at, so a concurrent write to the target
+/// fails the copy instead of interleaving with it. A mapper splitting events can make a batch go
+/// past the batch size, it is still appended as a whole.
+pub async fn copy_stream(
+    context: RequestContext,
+    reader: &ReaderClient,
+    writer: &WriterClient,
+    mappers: &EventMappers,
+    params: CopyStream,
+) -> eyre::Result<CopyStreamCompleted> {
+    // The read would keep on picking up the events being copied.
+    if params.source_stream == params.target_stream {
+        eyre::bail!(
+            "stream '{}' can't be copied to itself",
+            params.source_stream
+        );
+    }
+
+    let mapper = match params.mapper.as_deref() {
+        None => None,
+        Some(name) => match mappers.get(name) {
+            Some(mapper) => Some(mapper),
+            None => {
+                return Ok(CopyStreamCompleted::Error(CopyError::UnknownMapper(
+                    name.to_string(),
+                )));
+            }
+        },
+    };
+
+    let mut source = match reader
+        .read(
+            context,
+            &params.source_stream,
+            Revision::Start,
+            Direction::Forward,
+            usize::MAX,
+        )
+        .await?
+    {
+        ReadStreamCompleted::StreamDeleted => {
+            return Ok(CopyStreamCompleted::Error(CopyError::SourceDeleted));
+        }
+
+        ReadStreamCompleted::Success(source) => source,
+    };
+
+    let mut expected = params.expected_revision;
+    let mut copied = 0u64;
+    let mut skipped = 0u64;
+    let mut next_expected_version = None;
+    let mut batch = Vec::with_capacity(COPY_BATCH_SIZE);
+
+    loop {
+        let record = source.next().await?;
+        let done = record.is_none();
+
+        if let Some(record) = record {
+            let events = match mapper {
+                None => vec![Propose {
+                    id: record.id,
+                    content_type: record.content_type,
+                    class: record.class,
+                    data: record.data,
+                    metadata: record.metadata,
+                }],
+
+                Some(mapper) => match mapper.map(&record) {
+                    Ok(events) => events,
+
+                    Err(e) if params.on_mapper_error == MapperErrorPolicy::Skip => {
+                        tracing::warn!(
+                            correlation = %context.correlation,
+                            stream_name = params.source_stream,
+                            revision = record.revision,
+                            reason = e.reason,
+                            "mapper failed, event left out of the copy"
+                        );
+
+                        skipped += 1;
+                        Vec::new()
+                    }
+
+                    Err(e) => {
+                        return Ok(CopyStreamCompleted::Error(CopyError::MapperFailed {
+                            revision: record.revision,
+                            reason: e.reason,
+                        }));
+                    }
+                },
+            };
+
+            for mut event in events {
+                if let Some(class) = params.class_renames.get(&event.class) {
+                    event.class = class.clone();
+                }
+
+                batch.push(event);
+            }
+        }
+
+        if batch.is_empty() || (!done && batch.len() < COPY_BATCH_SIZE) {
+            if done {
+                break;
+            }
+
+            continue;
+        }
+
+        let count = batch.len() as u64;
+        let events = std::mem::replace(&mut batch, Vec::with_capacity(COPY_BATCH_SIZE));
+
+        match writer
+            .append(context, params.target_stream.clone(), expected, events)
+            .await?
+        {
+            AppendStreamCompleted::Error(e) => {
+                return Ok(CopyStreamCompleted::Error(CopyError::Append(e)));
+            }
+
+            AppendStreamCompleted::Success(result) => {
+                copied += count;
+                expected =
+                    ExpectedRevision::Revision(result.next_expected_version.raw() as u64 - 1);
+                next_expected_version = Some(result.next_expected_version);
+            }
+        }
+
+        if done {
+            break;
+        }
+    }
+
+    if let Some(next_expected_version) = next_expected_version {
+        return Ok(CopyStreamCompleted::Success(StreamCopied {
+            copied,
+            skipped,
+            next_expected_version,
+        }));
+    }
+
+    // Nothing was copied, the target is still checked against the expected revision.
+    let next_expected_version = match writer
+        .delete(context, params.target_stream.clone(), expected, true)
+        .await?
+    {
+        DeleteStreamCompleted::DryRun(ExpectedRevision::Revision(r)) => {
+            ExpectedRevision::Revision(r + 1)
+        }
+
+        DeleteStreamCompleted::DryRun(current) => current,
+
+        DeleteStreamCompleted::Error(DeleteError::StreamDeleted) => {
+            return Ok(CopyStreamCompleted::Error(CopyError::Append(
+                AppendError::StreamDeleted,
+            )));
+        }
+
+        DeleteStreamCompleted::Error(DeleteError::WrongExpectedRevision(e)) => {
+            return Ok(CopyStreamCompleted::Error(CopyError::Append(
+                AppendError::WrongExpectedRevision(e),
+            )));
+        }
+
+        DeleteStreamCompleted::Error(DeleteError::NotLeaderException(_)) => {
+            eyre::bail!("not leader");
+        }
+
+        DeleteStreamCompleted::Success(_) => {
+            eyre::bail!("dry run deleted stream '{}'", params.target_stream);
+        }
+    };
+
+    Ok(CopyStreamCompleted::Success(StreamCopied {
+        copied,
+        skipped,
+        next_expected_version,
+    }))
+}
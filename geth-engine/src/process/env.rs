@@ -1,8 +1,14 @@
-use std::{future::Future, sync::Arc};
+use std::{
+    future::Future,
+    sync::Arc,
+    sync::mpsc::{RecvTimeoutError, TryRecvError},
+    task::Poll,
+    time::Duration,
+};
 
 use tokio::{
     runtime::Handle,
-    sync::{mpsc::UnboundedReceiver, oneshot},
+    sync::{mpsc::Receiver, oneshot},
     task::JoinHandle,
 };
 
@@ -12,7 +18,7 @@ use crate::{
 };
 
 pub struct Managed {
-    pub queue: UnboundedReceiver<Item>,
+    pub queue: Receiver<Item>,
 }
 
 pub struct Raw {
@@ -83,6 +89,35 @@ impl ProcessEnv<Raw> {
         None
     }
 
+    /// Non-blocking version of [`ProcessEnv::recv`]. Returns `Poll::Pending` if no item is
+    /// available yet.
+    pub fn try_recv(&mut self) -> Poll<Option<Item>> {
+        if let Some(ready) = self.ready.take() {
+            let _ = ready.send(());
+        }
+
+        match self.inner.queue.try_recv() {
+            Ok(item) if item.is_shutdown() => Poll::Ready(None),
+            Ok(item) => Poll::Ready(Some(item)),
+            Err(TryRecvError::Empty) => Poll::Pending,
+            Err(TryRecvError::Disconnected) => Poll::Ready(None),
+        }
+    }
+
+    /// Like [`ProcessEnv::try_recv`], but waits up to `timeout` for an item to come.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Poll<Option<Item>> {
+        if let Some(ready) = self.ready.take() {
+            let _ = ready.send(());
+        }
+
+        match self.inner.queue.recv_timeout(timeout) {
+            Ok(item) if item.is_shutdown() => Poll::Ready(None),
+            Ok(item) => Poll::Ready(Some(item)),
+            Err(RecvTimeoutError::Timeout) => Poll::Pending,
+            Err(RecvTimeoutError::Disconnected) => Poll::Ready(None),
+        }
+    }
+
     pub fn spawn_blocking<F, R>(&self, func: F) -> JoinHandle<R>
     where
         F: FnOnce() -> R + Send + 'static,
@@ -1,18 +1,32 @@
-use std::{pin::Pin, sync::Arc};
+use std::{
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Instant,
+};
 
+use eyre::WrapErr;
+use http_body::{Body, Frame, SizeHint};
 use tokio::sync::Notify;
-use tonic::{Code, Status, transport::Server};
+use tonic::{
+    Code, Status,
+    transport::{Certificate, Identity, Server, ServerTlsConfig},
+};
 
 use geth_grpc::generated::protocol::protocol_server::ProtocolServer;
 use tracing::instrument;
 
 use crate::{
-    Options,
+    Options, PrefixAclResolver, SharedAclResolver, Tls,
     metrics::{Metrics, get_metrics},
     process::{Managed, ProcessEnv, manager::ManagerClient},
 };
 
+mod namespace;
+mod principal;
 mod protocol;
+#[cfg(test)]
+mod tests;
 
 pub async fn start_server(
     client: ManagerClient,
@@ -23,15 +37,33 @@ pub async fn start_server(
         .parse()
         .unwrap();
 
-    let protocols = protocol::ProtocolImpl::connect(client).await?;
+    let acl = match (&options.acl, &options.acl_file) {
+        (Some(resolver), _) => Some(resolver.clone()),
+        (None, Some(path)) => Some(SharedAclResolver::new(
+            PrefixAclResolver::load(path)?.with_admins(options.acl_admins.clone()),
+        )),
+        (None, None) => None,
+    };
+    let protocols = protocol::ProtocolImpl::connect(client, options.mappers.clone(), acl).await?;
+
+    let mut builder = Server::builder();
 
-    tracing::info!(%addr, db = options.db, "GethDB is listening",);
+    if let Some(tls) = server_tls_config(&options.tls)? {
+        builder = builder.tls_config(tls)?;
+    }
+
+    tracing::info!(
+        %addr,
+        db = options.db,
+        tls = options.tls.cert.is_some(),
+        "GethDB is listening",
+    );
 
     let layer = tower::ServiceBuilder::new()
         .layer(MetricsLayer)
         .into_inner();
 
-    Server::builder()
+    builder
         .layer(layer)
         .add_service(ProtocolServer::new(protocols))
         .serve_with_shutdown(addr, notify.notified())
@@ -40,6 +72,25 @@ pub async fn start_server(
     Ok(())
 }
 
+/// `None` when no certificate is configured, the server then serves plaintext.
+fn server_tls_config(tls: &Tls) -> eyre::Result<Option<ServerTlsConfig>> {
+    let (Some(cert), Some(key)) = (&tls.cert, &tls.key) else {
+        return Ok(None);
+    };
+
+    let read = |path: &std::path::PathBuf| {
+        std::fs::read(path).wrap_err_with(|| format!("cannot read '{}'", path.display()))
+    };
+
+    let mut config = ServerTlsConfig::new().identity(Identity::from_pem(read(cert)?, read(key)?));
+
+    if let Some(client_ca) = &tls.client_ca {
+        config = config.client_ca_root(Certificate::from_pem(read(client_ca)?));
+    }
+
+    Ok(Some(config))
+}
+
 #[instrument(skip_all, fields(host = env.options.host, port = env.options.port, proc = ?env.proc))]
 pub async fn run(mut env: ProcessEnv<Managed>) -> eyre::Result<()> {
     let notify = Arc::new(Notify::new());
@@ -97,7 +148,7 @@ where
     S::Future: Send + 'static,
     ReqBody: Send + 'static,
 {
-    type Response = S::Response;
+    type Response = http::Response<ObservedBody<ResBody>>;
     type Error = S::Error;
     type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
 
@@ -113,6 +164,8 @@ where
         let clone = self.inner.clone();
         let metrics = self.metrics.clone();
         let mut inner = std::mem::replace(&mut self.inner, clone);
+        let operation = operation_name(req.uri().path());
+        let started = Instant::now();
 
         Box::pin(async move {
             let resp = inner.call(req).await?;
@@ -126,11 +179,119 @@ where
                 }
             }
 
-            Ok(resp)
+            Ok(resp.map(|inner| ObservedBody {
+                inner,
+                metrics,
+                operation,
+                started,
+                first_message_sent: false,
+                completed: false,
+            }))
         })
     }
 }
 
+/// Response body that records how long it took to send the first message and the whole
+/// response. Streaming responses only complete once their last record is sent.
+struct ObservedBody<B> {
+    inner: B,
+    metrics: Metrics,
+    operation: &'static str,
+    started: Instant,
+    first_message_sent: bool,
+    completed: bool,
+}
+
+impl<B> ObservedBody<B> {
+    fn complete(&mut self) {
+        if self.completed {
+            return;
+        }
+
+        self.completed = true;
+        self.metrics
+            .observe_operation_completed(self.operation, self.started.elapsed());
+    }
+}
+
+impl<B> Body for ObservedBody<B>
+where
+    B: Body + Unpin,
+{
+    type Data = B::Data;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        let polled = Pin::new(&mut this.inner).poll_frame(cx);
+
+        match &polled {
+            Poll::Pending => {}
+
+            Poll::Ready(Some(Ok(frame))) if frame.is_data() => {
+                if !this.first_message_sent {
+                    this.first_message_sent = true;
+                    this.metrics
+                        .observe_operation_first_message(this.operation, this.started.elapsed());
+                }
+            }
+
+            // Trailers, errors or the end of the body.
+            Poll::Ready(_) => this.complete(),
+        }
+
+        polled
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+// Covers responses without a body and clients going away in the middle of a stream.
+impl<B> Drop for ObservedBody<B> {
+    fn drop(&mut self) {
+        self.complete();
+    }
+}
+
+/// Known operations only, so a client can't make up metric series.
+fn operation_name(path: &str) -> &'static str {
+    let Some(method) = path.strip_prefix("/geth.Protocol/") else {
+        return "unknown";
+    };
+
+    match method {
+        "AppendStream" => "append_stream",
+        "ReadStream" => "read_stream",
+        "ReadAll" => "read_all",
+        "ReadProjection" => "read_projection",
+        "DeleteStream" => "delete_stream",
+        "TruncateStream" => "truncate_stream",
+        "SetStreamMetadata" => "set_stream_metadata",
+        "GetStreamMetadata" => "get_stream_metadata",
+        "GetStreamRevision" => "get_stream_revision",
+        "Subscribe" => "subscribe",
+        "Ack" => "ack",
+        "ListPrograms" => "list_programs",
+        "ProgramStats" => "program_stats",
+        "StopProgram" => "stop_program",
+        "SetLogFilters" => "set_log_filters",
+        "ListStreams" => "list_streams",
+        "AppendAndSubscribe" => "append_and_subscribe",
+        "CopyStream" => "copy_stream",
+        "MaintainIndex" => "maintain_index",
+        _ => "unknown",
+    }
+}
+
 fn is_client_error(code: Code) -> bool {
     matches!(
         code,
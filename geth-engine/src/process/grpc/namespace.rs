@@ -0,0 +1,162 @@
+use geth_common::{
+    ProjectedRecord, Record, STREAMS_FEED, StreamFeedEvent, SubscriptionConfirmation,
+    SubscriptionEvent,
+};
+use tonic::{Request, Status};
+
+use crate::names::{namespaces, streams};
+
+/// Namespace a gRPC request is scoped to, taken from its `namespace` metadata. Requests without
+/// one, or with an empty one, are scoped to the default namespace.
+///
+/// Stream names are scoped when entering the server and unscoped when leaving it, so the
+/// processes behind it only ever see stored names. A namespaced request can't address streams
+/// outside its namespace, and the default namespace is denied access to namespaced streams.
+#[derive(Clone, Debug, Default)]
+pub struct Namespace(Option<String>);
+
+impl Namespace {
+    #[allow(clippy::result_large_err)]
+    pub fn from_request<A>(req: &Request<A>) -> Result<Self, Status> {
+        let Some(value) = req.metadata().get("namespace") else {
+            return Ok(Self::default());
+        };
+
+        let value = value.to_str().map_err(|e| {
+            Status::invalid_argument(format!("invalid namespace metadata value: {e}"))
+        })?;
+
+        if value.is_empty() {
+            return Ok(Self::default());
+        }
+
+        if value.contains(':') {
+            return Err(Status::invalid_argument(format!(
+                "invalid namespace '{value}': ':' is not allowed"
+            )));
+        }
+
+        Ok(Self(Some(value.to_string())))
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.0.is_none()
+    }
+
+    /// Refuses operations affecting the whole node to namespaced requests.
+    #[allow(clippy::result_large_err)]
+    pub fn must_be_default(&self, operation: &str) -> Result<(), Status> {
+        match &self.0 {
+            None => Ok(()),
+            Some(namespace) => Err(Status::permission_denied(format!(
+                "{operation} is not available to namespace '{namespace}'"
+            ))),
+        }
+    }
+
+    /// Stored name of `stream`.
+    #[allow(clippy::result_large_err)]
+    pub fn scope(&self, stream: &str) -> Result<String, Status> {
+        let Some(namespace) = &self.0 else {
+            if namespaces::namespace_of(stream).is_some() {
+                return Err(Status::permission_denied(format!(
+                    "stream '{stream}' belongs to another namespace"
+                )));
+            }
+
+            return Ok(stream.to_string());
+        };
+
+        // The metadata stream of a namespaced stream is the one of its stored name.
+        if streams::is_metadata(stream) {
+            return Ok(streams::metadata_of(&namespaces::scope(
+                namespace,
+                &stream[2..],
+            )));
+        }
+
+        Ok(namespaces::scope(namespace, stream))
+    }
+
+    /// Same as [`Namespace::scope`], except for the streams spanning the whole database which
+    /// are left as is. Their records are meant to go through [`Namespace::event`].
+    #[allow(clippy::result_large_err)]
+    pub fn scope_subscription(&self, stream: &str) -> Result<String, Status> {
+        if stream == streams::ALL || stream == STREAMS_FEED {
+            return Ok(stream.to_string());
+        }
+
+        self.scope(stream)
+    }
+
+    pub fn unscope(&self, stream: &str) -> Option<String> {
+        namespaces::unscope(self.0.as_deref(), stream)
+    }
+
+    /// `record` as seen from the namespace, `None` if it belongs to another namespace.
+    pub fn record(&self, mut record: Record) -> Option<Record> {
+        if record.stream_name == STREAMS_FEED {
+            let event = match StreamFeedEvent::from_record(&record).ok()? {
+                StreamFeedEvent::StreamCreated { name } => StreamFeedEvent::StreamCreated {
+                    name: self.unscope(&name)?,
+                },
+
+                StreamFeedEvent::StreamDeleted { name } => StreamFeedEvent::StreamDeleted {
+                    name: self.unscope(&name)?,
+                },
+            };
+
+            record.data = event.data();
+            return Some(record);
+        }
+
+        record.stream_name = self.unscope(&record.stream_name)?;
+
+        if let Some(link) = record.link.as_mut() {
+            link.stream_name = self.unscope(&link.stream_name)?;
+        }
+
+        Some(record)
+    }
+
+    pub fn projected(&self, mut record: ProjectedRecord) -> Option<ProjectedRecord> {
+        record.stream_name = self.unscope(&record.stream_name)?;
+
+        Some(record)
+    }
+
+    /// Subscription `event` as seen from the namespace, `requested` being the stream name the
+    /// subscription was asked for. `None` if the event belongs to another namespace.
+    pub fn event(&self, requested: &str, event: SubscriptionEvent) -> Option<SubscriptionEvent> {
+        match event {
+            SubscriptionEvent::EventAppeared(record) => {
+                self.record(record).map(SubscriptionEvent::EventAppeared)
+            }
+
+            SubscriptionEvent::EventsAppeared(records) => {
+                let records = records
+                    .into_iter()
+                    .filter_map(|record| self.record(record))
+                    .collect::<Vec<_>>();
+
+                if records.is_empty() {
+                    None
+                } else {
+                    Some(SubscriptionEvent::EventsAppeared(records))
+                }
+            }
+
+            SubscriptionEvent::Confirmed(SubscriptionConfirmation::StreamName {
+                start_revision,
+                ..
+            }) => Some(SubscriptionEvent::Confirmed(
+                SubscriptionConfirmation::StreamName {
+                    stream_name: requested.to_string(),
+                    start_revision,
+                },
+            )),
+
+            event => Some(event),
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use tonic::{Request, Status};
+use webpki::EndEntityCert;
+
+/// Principal a gRPC request is made on behalf of, the first DNS name of the certificate the
+/// client authenticated with over mutual TLS. Requests made without a client certificate, or
+/// with one naming no DNS name, are anonymous.
+#[derive(Clone, Debug, Default)]
+pub struct Principal(Option<String>);
+
+impl Principal {
+    #[allow(clippy::result_large_err)]
+    pub fn from_request<A>(req: &Request<A>) -> Result<Self, Status> {
+        let Some(certs) = req.peer_certs() else {
+            return Ok(Self::default());
+        };
+
+        // The TLS handshake already verified the chain, the end-entity certificate comes first.
+        let Some(cert) = certs.first() else {
+            return Ok(Self::default());
+        };
+
+        let cert = EndEntityCert::try_from(cert)
+            .map_err(|e| Status::unauthenticated(format!("malformed client certificate: {e}")))?;
+
+        Ok(Self(cert.valid_dns_names().next().map(str::to_string)))
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.0.as_deref()
+    }
+}
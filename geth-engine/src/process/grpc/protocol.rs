@@ -1,39 +1,192 @@
+use std::time::Duration;
+
 use geth_grpc::protocol::protocol_server::Protocol;
 use geth_grpc::protocol::{self, SubscribeResponse};
-use tokio::sync::mpsc::unbounded_channel;
+use tokio::sync::mpsc::{UnboundedSender, unbounded_channel};
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
 use tonic::codegen::tokio_stream::wrappers::UnboundedReceiverStream;
 
 use geth_common::{
-    AppendStream, DeleteStream, GetProgramStats, KillProgram, ProgramKilled, ProgramListed,
-    ProgramObtained, ReadStream, ReadStreamCompleted, ReadStreamResponse, Subscribe,
-    SubscriptionEvent, UnsubscribeReason,
+    Ack, AppendStream, AppendStreamCompleted, CopyStream, DeleteStream, GetProgramStats,
+    KillProgram, ListStreams, MaintainIndex, ProgramKilled, ProgramListed, ProgramObtained,
+    ReadAll, ReadProjection, ReadProjectionResponse, ReadStream, ReadStreamCompleted,
+    ReadStreamResponse, SetLogFilters, SetStreamMetadata, StreamMetadata, Subscribe,
+    SubscriptionConfirmation, SubscriptionEvent, TruncateStream, UnsubscribeReason,
 };
 use tonic::{Request, Response, Status};
 use uuid::Uuid;
 
+use crate::acl::{self, SharedAclResolver, StreamAccess};
+use crate::mapping::EventMappers;
 use crate::metrics::get_metrics;
-use crate::process::consumer::{ConsumerResult, start_consumer};
-use crate::process::reading::ReaderClient;
+use crate::names::streams;
+use crate::process::consumer::{ConsumerResult, Delivery, start_consumer};
+use crate::process::copying::copy_stream;
+use crate::process::indexing::IndexClient;
+use crate::process::persistent::PersistentGroups;
+use crate::process::reading::{FieldSelector, ReaderClient};
 use crate::process::subscription::SubscriptionClient;
 use crate::process::writing::WriterClient;
-use crate::process::{ManagerClient, RequestContext};
+use crate::process::{ManagerClient, Proc, RequestContext};
+
+use super::namespace::Namespace;
+use super::principal::Principal;
 
+/// Processes left out of the catalog are `None`, the operations relying on them are answered
+/// with an `Unimplemented` status.
 #[derive(Clone)]
 pub struct ProtocolImpl {
-    writer: WriterClient,
-    reader: ReaderClient,
-    sub: SubscriptionClient,
+    writer: Option<WriterClient>,
+    reader: Option<ReaderClient>,
+    sub: Option<SubscriptionClient>,
+    index: Option<IndexClient>,
+    mappers: EventMappers,
+    groups: PersistentGroups,
+    /// Configured stream ACLs. When `None`, only the ACLs recorded in stream metadata apply.
+    acl: Option<SharedAclResolver>,
 }
 
 impl ProtocolImpl {
-    pub async fn connect(client: ManagerClient) -> eyre::Result<Self> {
+    pub async fn connect(
+        client: ManagerClient,
+        mappers: EventMappers,
+        acl: Option<SharedAclResolver>,
+    ) -> eyre::Result<Self> {
         Ok(Self {
-            writer: client.new_writer_client().await?,
-            reader: client.new_reader_client().await?,
-            sub: client.new_subscription_client().await?,
+            writer: if client.has(Proc::Writing).await? {
+                Some(client.new_writer_client().await?)
+            } else {
+                None
+            },
+            reader: if client.has(Proc::Reading).await? {
+                Some(client.new_reader_client().await?)
+            } else {
+                None
+            },
+            sub: if client.has(Proc::PubSub).await? {
+                Some(client.new_subscription_client().await?)
+            } else {
+                None
+            },
+            index: if client.has(Proc::Indexing).await? {
+                Some(client.new_index_client().await?)
+            } else {
+                None
+            },
+            mappers,
+            groups: PersistentGroups::default(),
+            acl,
         })
     }
 
+    #[allow(clippy::result_large_err)]
+    fn writer(&self) -> Result<&WriterClient, Status> {
+        self.writer
+            .as_ref()
+            .ok_or_else(|| Status::unimplemented("writes are not enabled on this node"))
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn reader(&self) -> Result<&ReaderClient, Status> {
+        self.reader
+            .as_ref()
+            .ok_or_else(|| Status::unimplemented("reads are not enabled on this node"))
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn sub(&self) -> Result<&SubscriptionClient, Status> {
+        self.sub
+            .as_ref()
+            .ok_or_else(|| Status::unimplemented("subscriptions are not enabled on this node"))
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn index(&self) -> Result<&IndexClient, Status> {
+        self.index
+            .as_ref()
+            .ok_or_else(|| Status::unimplemented("indexing is not enabled on this node"))
+    }
+
+    /// Refuses `access` to `stream_name`, a stored name, unless the ACLs grant it to `principal`.
+    /// Both the configured ACLs and the one recorded in the metadata of the stream apply, the
+    /// latter only on nodes serving reads.
+    async fn authorize(
+        &self,
+        ctx: RequestContext,
+        principal: &Principal,
+        stream_name: &str,
+        access: StreamAccess,
+    ) -> Result<(), Status> {
+        let configured = self
+            .acl
+            .as_ref()
+            .is_none_or(|acl| acl.allows(principal.name(), stream_name, access));
+
+        if configured
+            && self
+                .metadata_allows(ctx, principal, stream_name, access)
+                .await?
+        {
+            return Ok(());
+        }
+
+        Err(Status::permission_denied(format!(
+            "{} access to stream '{}' denied to {}",
+            access.as_str(),
+            stream_name,
+            principal
+                .name()
+                .map_or_else(|| "anonymous requests".to_string(), |p| format!("'{p}'")),
+        )))
+    }
+
+    /// Whether the ACL recorded in the metadata of the stream guarding `access` to `stream_name`
+    /// grants it to `principal`. Streams without one are open to everyone.
+    async fn metadata_allows(
+        &self,
+        ctx: RequestContext,
+        principal: &Principal,
+        stream_name: &str,
+        access: StreamAccess,
+    ) -> Result<bool, Status> {
+        let Some(reader) = &self.reader else {
+            return Ok(true);
+        };
+
+        let (stream_name, accesses) = acl::guarding(stream_name, access);
+        let outcome = reader
+            .metadata(ctx, stream_name)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        let ReadStreamCompleted::Success(StreamMetadata { acl: Some(acl), .. }) = outcome else {
+            return Ok(true);
+        };
+
+        Ok(acl::grants(&acl, principal.name(), accesses))
+    }
+
+    /// Refuses `operation`, which administers the node, unless the ACLs grant it to `principal`.
+    #[allow(clippy::result_large_err)]
+    fn authorize_admin(&self, principal: &Principal, operation: &str) -> Result<(), Status> {
+        let Some(acl) = &self.acl else {
+            return Ok(());
+        };
+
+        if acl.allows_admin(principal.name()) {
+            return Ok(());
+        }
+
+        Err(Status::permission_denied(format!(
+            "{} denied to {}",
+            operation,
+            principal
+                .name()
+                .map_or_else(|| "anonymous requests".to_string(), |p| format!("'{p}'")),
+        )))
+    }
+
     #[allow(clippy::result_large_err)]
     pub fn try_get_request_context_from<A>(
         &self,
@@ -63,23 +216,161 @@ impl Protocol for ProtocolImpl {
         request: Request<protocol::AppendStreamRequest>,
     ) -> Result<Response<protocol::AppendStreamResponse>, Status> {
         let ctx = self.try_get_request_context_from(&request)?;
+        let namespace = Namespace::from_request(&request)?;
+        let principal = Principal::from_request(&request)?;
         let params: AppendStream = request.into_inner().try_into()?;
+        let writer = self.writer()?;
+        let stream_name = namespace.scope(&params.stream_name)?;
+        self.authorize(ctx, &principal, &stream_name, StreamAccess::Write)
+            .await?;
+        let outcome = if params.notify_subscribers {
+            writer
+                .append(ctx, stream_name, params.expected_revision, params.events)
+                .await
+        } else {
+            writer
+                .append_without_notification(
+                    ctx,
+                    stream_name,
+                    params.expected_revision,
+                    params.events,
+                )
+                .await
+        };
 
-        match self
-            .writer
-            .append(
-                ctx,
-                params.stream_name,
-                params.expected_revision,
-                params.events,
-            )
-            .await
-        {
+        match outcome {
             Err(e) => Err(Status::internal(e.to_string())),
 
             Ok(result) => Ok(Response::new(result.into())),
         }
     }
+
+    type AppendAndSubscribeStream =
+        UnboundedReceiverStream<Result<protocol::AppendAndSubscribeResponse, Status>>;
+
+    async fn append_and_subscribe(
+        &self,
+        request: Request<protocol::AppendStreamRequest>,
+    ) -> Result<Response<Self::AppendAndSubscribeStream>, Status> {
+        let ctx = self.try_get_request_context_from(&request)?;
+        let namespace = Namespace::from_request(&request)?;
+        let principal = Principal::from_request(&request)?;
+        let params: AppendStream = request.into_inner().try_into()?;
+        let stream_name = namespace.scope(&params.stream_name)?;
+        self.authorize(ctx, &principal, &stream_name, StreamAccess::Write)
+            .await?;
+        self.authorize(ctx, &principal, &stream_name, StreamAccess::Read)
+            .await?;
+
+        // The subscription is live before the append is sent to the writer, which pushes the
+        // committed events to the subscriptions only after that.
+        let mut stream = self
+            .sub()?
+            .subscribe_to_stream(ctx, &stream_name)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        stream
+            .wait_until_confirmation()
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        let result = self
+            .writer()?
+            .append(ctx, stream_name, params.expected_revision, params.events)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        let (sender, recv) = unbounded_channel();
+        let next_revision = match &result {
+            AppendStreamCompleted::Success(r) => Some(r.next_expected_version.raw() as u64),
+            AppendStreamCompleted::Error(_) => None,
+        };
+
+        let _ = sender.send(Ok(protocol::AppendAndSubscribeResponse {
+            response: Some(protocol::append_and_subscribe_response::Response::Appended(
+                result.into(),
+            )),
+        }));
+
+        let Some(next_revision) = next_revision else {
+            return Ok(Response::new(UnboundedReceiverStream::new(recv)));
+        };
+
+        let send_event = move |event: SubscriptionEvent| {
+            sender
+                .send(Ok(protocol::AppendAndSubscribeResponse {
+                    response: Some(protocol::append_and_subscribe_response::Response::Event(
+                        event.into(),
+                    )),
+                }))
+                .is_ok()
+        };
+
+        tokio::spawn(async move {
+            let metrics = get_metrics();
+
+            if !send_event(SubscriptionEvent::Confirmed(
+                SubscriptionConfirmation::StreamName {
+                    stream_name: params.stream_name.clone(),
+                    start_revision: Some(next_revision),
+                },
+            )) {
+                return;
+            }
+
+            loop {
+                match stream.next().await {
+                    Err(e) => {
+                        metrics.observe_server_error();
+                        tracing::error!(
+                            stream = params.stream_name,
+                            "append and subscribe subscription failed: {e}"
+                        );
+
+                        break;
+                    }
+
+                    // Events committed before the append, by concurrent writes, are skipped.
+                    Ok(Some(SubscriptionEvent::EventAppeared(record)))
+                        if record.revision < next_revision => {}
+
+                    Ok(Some(event)) => {
+                        let Some(event) = namespace.event(&params.stream_name, event) else {
+                            continue;
+                        };
+
+                        let ended = matches!(event, SubscriptionEvent::Unsubscribed(_));
+
+                        if !send_event(event) {
+                            tracing::debug!(
+                                stream = params.stream_name,
+                                "user disconnected from append and subscribe subscription"
+                            );
+
+                            break;
+                        }
+
+                        if ended {
+                            break;
+                        }
+                    }
+
+                    // The processes behind the subscription are gone.
+                    Ok(None) => {
+                        let _ = send_event(SubscriptionEvent::Unsubscribed(
+                            UnsubscribeReason::Shutdown,
+                        ));
+
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(Response::new(UnboundedReceiverStream::new(recv)))
+    }
+
     type ReadStreamStream = UnboundedReceiverStream<Result<protocol::ReadStreamResponse, Status>>;
 
     async fn read_stream(
@@ -87,19 +378,14 @@ impl Protocol for ProtocolImpl {
         request: Request<protocol::ReadStreamRequest>,
     ) -> Result<Response<Self::ReadStreamStream>, Status> {
         let ctx = self.try_get_request_context_from(&request)?;
-        let params: ReadStream = request.into_inner().try_into()?;
+        let namespace = Namespace::from_request(&request)?;
+        let principal = Principal::from_request(&request)?;
+        let mut params: ReadStream = request.into_inner().try_into()?;
+        params.stream_name = namespace.scope(&params.stream_name)?;
+        self.authorize(ctx, &principal, &params.stream_name, StreamAccess::Read)
+            .await?;
 
-        match self
-            .reader
-            .read(
-                ctx,
-                &params.stream_name,
-                params.revision,
-                params.direction,
-                params.max_count as usize,
-            )
-            .await
-        {
+        match self.reader()?.read_stream(ctx, params).await {
             Err(e) => Err(Status::internal(e.to_string())),
 
             Ok(outcome) => match outcome {
@@ -108,20 +394,36 @@ impl Protocol for ProtocolImpl {
                 }
 
                 ReadStreamCompleted::Success(mut stream) => {
+                    if let Some(acl) = self.acl.clone() {
+                        stream.restrict_links(move |target| {
+                            acl.allows(principal.name(), target, StreamAccess::Read)
+                        });
+                    }
+
                     let (sender, recv) = unbounded_channel();
 
                     tokio::spawn(async move {
                         while let Some(event) = stream.next().await? {
+                            let Some(event) = namespace.record(event) else {
+                                continue;
+                            };
+
                             if sender
                                 .send(Ok(ReadStreamResponse::EventAppeared(event)
                                     .try_into()
                                     .unwrap()))
                                 .is_err()
                             {
-                                break;
+                                return Ok(());
                             }
                         }
 
+                        let _ = sender.send(Ok(ReadStreamResponse::EndOfStream {
+                            tail_revision: stream.tail_revision(),
+                        }
+                        .try_into()
+                        .unwrap()));
+
                         Ok::<_, eyre::Report>(())
                     });
 
@@ -131,16 +433,205 @@ impl Protocol for ProtocolImpl {
         }
     }
 
+    type ReadAllStream = UnboundedReceiverStream<Result<protocol::ReadStreamResponse, Status>>;
+
+    async fn read_all(
+        &self,
+        request: Request<protocol::ReadAllRequest>,
+    ) -> Result<Response<Self::ReadAllStream>, Status> {
+        let ctx = self.try_get_request_context_from(&request)?;
+        let namespace = Namespace::from_request(&request)?;
+        let principal = Principal::from_request(&request)?;
+        let params: ReadAll = request.into_inner().try_into()?;
+        self.authorize(ctx, &principal, streams::ALL, StreamAccess::Read)
+            .await?;
+        let mut stream = self
+            .reader()?
+            .read_all(
+                ctx,
+                params.position,
+                params.direction,
+                params
+                    .max_count
+                    .map_or(usize::MAX, |c| usize::try_from(c).unwrap_or(usize::MAX)),
+                true,
+            )
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        let (sender, recv) = unbounded_channel();
+
+        tokio::spawn(async move {
+            while let Some(event) = stream.next().await? {
+                let Some(event) = namespace.record(event) else {
+                    continue;
+                };
+
+                if sender
+                    .send(Ok(ReadStreamResponse::EventAppeared(event)
+                        .try_into()
+                        .unwrap()))
+                    .is_err()
+                {
+                    return Ok(());
+                }
+            }
+
+            let _ = sender.send(Ok(ReadStreamResponse::EndOfStream {
+                tail_revision: None,
+            }
+            .try_into()
+            .unwrap()));
+
+            Ok::<_, eyre::Report>(())
+        });
+
+        Ok(Response::new(UnboundedReceiverStream::new(recv)))
+    }
+
+    type ReadProjectionStream =
+        UnboundedReceiverStream<Result<protocol::ReadProjectionResponse, Status>>;
+
+    async fn read_projection(
+        &self,
+        request: Request<protocol::ReadProjectionRequest>,
+    ) -> Result<Response<Self::ReadProjectionStream>, Status> {
+        let ctx = self.try_get_request_context_from(&request)?;
+        let namespace = Namespace::from_request(&request)?;
+        let principal = Principal::from_request(&request)?;
+        let params: ReadProjection = request.into_inner().try_into()?;
+        let stream_name = namespace.scope(&params.stream_name)?;
+        self.authorize(ctx, &principal, &stream_name, StreamAccess::Read)
+            .await?;
+        let selectors = params
+            .fields
+            .iter()
+            .map(|f| FieldSelector::parse(f))
+            .collect::<eyre::Result<Vec<_>>>()
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        match self
+            .reader()?
+            .read_projection(
+                ctx,
+                &stream_name,
+                params.revision,
+                params.direction,
+                params.max_count as usize,
+                selectors,
+            )
+            .await
+        {
+            Err(e) => Err(Status::internal(e.to_string())),
+
+            Ok(outcome) => match outcome {
+                ReadStreamCompleted::StreamDeleted => {
+                    Err(Status::failed_precondition("stream-deleted"))
+                }
+
+                ReadStreamCompleted::Success(mut stream) => {
+                    let (sender, recv) = unbounded_channel();
+
+                    tokio::spawn(async move {
+                        loop {
+                            match stream.next().await {
+                                Err(e) => {
+                                    let _ = sender.send(Err(Status::internal(e.to_string())));
+                                    break;
+                                }
+
+                                Ok(None) => break,
+
+                                Ok(Some(record)) => {
+                                    let Some(record) = namespace.projected(record) else {
+                                        continue;
+                                    };
+
+                                    if sender
+                                        .send(Ok(
+                                            ReadProjectionResponse::EventAppeared(record).into()
+                                        ))
+                                        .is_err()
+                                    {
+                                        break;
+                                    }
+                                }
+                            }
+                        }
+                    });
+
+                    Ok(Response::new(UnboundedReceiverStream::new(recv)))
+                }
+            },
+        }
+    }
+
     async fn delete_stream(
         &self,
         request: Request<protocol::DeleteStreamRequest>,
     ) -> Result<Response<protocol::DeleteStreamResponse>, Status> {
         let ctx = self.try_get_request_context_from(&request)?;
+        let namespace = Namespace::from_request(&request)?;
+        let principal = Principal::from_request(&request)?;
         let params: DeleteStream = request.into_inner().try_into()?;
+        let stream_name = namespace.scope(&params.stream_name)?;
+        self.authorize(ctx, &principal, &stream_name, StreamAccess::Delete)
+            .await?;
+
+        match self
+            .writer()?
+            .delete(ctx, stream_name, params.expected_revision, params.dry_run)
+            .await
+        {
+            Err(e) => Err(Status::internal(e.to_string())),
+
+            Ok(result) => Ok(Response::new(result.into())),
+        }
+    }
+
+    async fn truncate_stream(
+        &self,
+        request: Request<protocol::TruncateStreamRequest>,
+    ) -> Result<Response<protocol::TruncateStreamResponse>, Status> {
+        let ctx = self.try_get_request_context_from(&request)?;
+        let namespace = Namespace::from_request(&request)?;
+        let principal = Principal::from_request(&request)?;
+        let params: TruncateStream = request.into_inner().into();
+        let stream_name = namespace.scope(&params.stream_name)?;
+        self.authorize(ctx, &principal, &stream_name, StreamAccess::Delete)
+            .await?;
+
+        match self
+            .writer()?
+            .truncate(ctx, stream_name, params.before)
+            .await
+        {
+            Err(e) => Err(Status::internal(e.to_string())),
+
+            Ok(result) => Ok(Response::new(result.into())),
+        }
+    }
+
+    async fn set_stream_metadata(
+        &self,
+        request: Request<protocol::SetStreamMetadataRequest>,
+    ) -> Result<Response<protocol::SetStreamMetadataResponse>, Status> {
+        let ctx = self.try_get_request_context_from(&request)?;
+        let namespace = Namespace::from_request(&request)?;
+        let principal = Principal::from_request(&request)?;
+        let params: SetStreamMetadata = request.into_inner().try_into()?;
+        let stream_name = namespace.scope(&params.stream_name)?;
+        self.authorize(
+            ctx,
+            &principal,
+            &streams::metadata_of(&stream_name),
+            StreamAccess::Write,
+        )
+        .await?;
 
         match self
-            .writer
-            .delete(ctx, params.stream_name, params.expected_revision)
+            .writer()?
+            .set_metadata(ctx, stream_name, params.metadata)
             .await
         {
             Err(e) => Err(Status::internal(e.to_string())),
@@ -149,6 +640,44 @@ impl Protocol for ProtocolImpl {
         }
     }
 
+    async fn get_stream_metadata(
+        &self,
+        request: Request<protocol::GetStreamMetadataRequest>,
+    ) -> Result<Response<protocol::GetStreamMetadataResponse>, Status> {
+        let ctx = self.try_get_request_context_from(&request)?;
+        let namespace = Namespace::from_request(&request)?;
+        let principal = Principal::from_request(&request)?;
+        let stream_name = namespace.scope(&request.into_inner().stream_name)?;
+        self.authorize(ctx, &principal, &stream_name, StreamAccess::Read)
+            .await?;
+
+        match self.reader()?.metadata(ctx, &stream_name).await {
+            Err(e) => Err(Status::internal(e.to_string())),
+
+            Ok(result) => Ok(Response::new(result.into())),
+        }
+    }
+
+    async fn get_stream_revision(
+        &self,
+        request: Request<protocol::GetStreamRevisionRequest>,
+    ) -> Result<Response<protocol::GetStreamRevisionResponse>, Status> {
+        let ctx = self.try_get_request_context_from(&request)?;
+        let namespace = Namespace::from_request(&request)?;
+        let principal = Principal::from_request(&request)?;
+        let stream_name = namespace.scope(&request.into_inner().stream_name)?;
+        self.authorize(ctx, &principal, &stream_name, StreamAccess::Read)
+            .await?;
+
+        match self.index()?.stream_revision(ctx, &stream_name).await {
+            Err(e) => Err(Status::internal(e.to_string())),
+
+            Ok(revision) => Ok(Response::new(protocol::GetStreamRevisionResponse {
+                revision,
+            })),
+        }
+    }
+
     type SubscribeStream = UnboundedReceiverStream<Result<protocol::SubscribeResponse, Status>>;
 
     async fn subscribe(
@@ -156,26 +685,37 @@ impl Protocol for ProtocolImpl {
         request: Request<protocol::SubscribeRequest>,
     ) -> Result<Response<Self::SubscribeStream>, Status> {
         let ctx = self.try_get_request_context_from(&request)?;
+        let namespace = Namespace::from_request(&request)?;
+        let principal = Principal::from_request(&request)?;
         let (sender, recv) = unbounded_channel::<Result<SubscribeResponse, Status>>();
 
         match request.into_inner().try_into()? {
             Subscribe::ToStream(params) => {
-                let mut consumer = match start_consumer(
-                    ctx,
-                    params.stream_name.clone(),
-                    params.start,
-                    self.reader.manager(),
-                )
-                .await
-                {
-                    Err(e) => return Err(Status::internal(e.to_string())),
-                    Ok(result) => match result {
-                        ConsumerResult::Success(c) => c,
-                        ConsumerResult::StreamDeleted => {
-                            return Err(Status::failed_precondition("stream-deleted"));
-                        }
-                    },
-                };
+                // The consumer relies on the subscription process to go live.
+                self.sub()?;
+
+                let stream_name = namespace.scope_subscription(&params.stream_name)?;
+                self.authorize(ctx, &principal, &stream_name, StreamAccess::Read)
+                    .await?;
+
+                let consumer =
+                    match start_consumer(ctx, stream_name, params.start, self.reader()?.manager())
+                        .await
+                    {
+                        Err(e) => return Err(Status::internal(e.to_string())),
+                        Ok(result) => match result {
+                            ConsumerResult::Success(c) => c,
+                            ConsumerResult::StreamDeleted => {
+                                return Err(Status::failed_precondition("stream-deleted"));
+                            }
+                        },
+                    };
+
+                let consumer = consumer.filter_classes(params.class_filter);
+                let mut consumer = Delivery::new(consumer, params.batching);
+                let heartbeat = params
+                    .heartbeat_interval
+                    .map(|interval| send_heartbeats(sender.clone(), interval));
 
                 tokio::spawn(async move {
                     let metrics = get_metrics();
@@ -190,6 +730,13 @@ impl Protocol for ProtocolImpl {
 
                             Ok(event) => {
                                 if let Some(event) = event {
+                                    let Some(event) = namespace.event(&params.stream_name, event)
+                                    else {
+                                        continue;
+                                    };
+
+                                    let ended = matches!(event, SubscriptionEvent::Unsubscribed(_));
+
                                     if sender.send(Ok(event.into())).is_err() {
                                         tracing::debug!(
                                             stream = params.stream_name,
@@ -198,6 +745,10 @@ impl Protocol for ProtocolImpl {
 
                                         break;
                                     }
+
+                                    if ended {
+                                        break;
+                                    }
                                 } else {
                                     tracing::debug!(
                                         stream = params.stream_name,
@@ -205,7 +756,7 @@ impl Protocol for ProtocolImpl {
                                     );
 
                                     let _ = sender.send(Ok(SubscriptionEvent::Unsubscribed(
-                                        UnsubscribeReason::Server,
+                                        UnsubscribeReason::Shutdown,
                                     )
                                     .into()));
 
@@ -214,13 +765,86 @@ impl Protocol for ProtocolImpl {
                             }
                         }
                     }
+
+                    if let Some(heartbeat) = heartbeat {
+                        heartbeat.abort();
+                    }
+                });
+            }
+
+            Subscribe::ToGroup(params) => {
+                // The consumer relies on the subscription process to go live.
+                self.sub()?;
+
+                // Records of other namespaces would never be acknowledged.
+                if params.stream_name == streams::ALL {
+                    namespace.must_be_default("persistent subscription to $all")?;
+                }
+
+                let stream_name = namespace.scope_subscription(&params.stream_name)?;
+                self.authorize(ctx, &principal, &stream_name, StreamAccess::Read)
+                    .await?;
+
+                let mut member = self.groups.join(
+                    namespace.scope(&checkpoints_of(&params.group, &params.stream_name)?)?,
+                    stream_name,
+                    self.reader()?.clone(),
+                    self.writer()?.clone(),
+                );
+
+                tokio::spawn(async move {
+                    loop {
+                        // Leaving right away hands the records the user didn't acknowledge to
+                        // the other members.
+                        let event = tokio::select! {
+                            event = member.next() => event,
+                            _ = sender.closed() => None,
+                        };
+
+                        let Some(event) = event else {
+                            break;
+                        };
+
+                        let Some(event) = namespace.event(&params.stream_name, event) else {
+                            continue;
+                        };
+
+                        if sender.send(Ok(event.into())).is_err() {
+                            break;
+                        }
+                    }
+
+                    if sender.is_closed() {
+                        tracing::debug!(
+                            group = params.group,
+                            stream = params.stream_name,
+                            "user disconnected from persistent subscription"
+                        );
+
+                        return;
+                    }
+
+                    let _ = sender.send(Ok(SubscriptionEvent::Unsubscribed(
+                        UnsubscribeReason::Server,
+                    )
+                    .into()));
                 });
             }
 
             Subscribe::ToProgram(params) => {
+                namespace.must_be_default("program subscription")?;
+                // Programs read whichever streams they like.
+                self.authorize_admin(&principal, "running programs")?;
+
                 match self
-                    .sub
-                    .subscribe_to_program(ctx, &params.name, &params.source)
+                    .sub()?
+                    .subscribe_to_program(
+                        ctx,
+                        &params.name,
+                        &params.source,
+                        params.logs,
+                        params.restart,
+                    )
                     .await
                 {
                     Err(e) => {
@@ -240,6 +864,9 @@ impl Protocol for ProtocolImpl {
 
                                     Ok(event) => {
                                         if let Some(event) = event {
+                                            let ended =
+                                                matches!(event, SubscriptionEvent::Unsubscribed(_));
+
                                             if sender.send(Ok(event.into())).is_err() {
                                                 tracing::debug!(
                                                     name = params.name,
@@ -248,6 +875,10 @@ impl Protocol for ProtocolImpl {
 
                                                 break;
                                             }
+
+                                            if ended {
+                                                break;
+                                            }
                                         } else {
                                             tracing::debug!(
                                                 name = params.name,
@@ -256,7 +887,7 @@ impl Protocol for ProtocolImpl {
 
                                             let _ =
                                                 sender.send(Ok(SubscriptionEvent::Unsubscribed(
-                                                    UnsubscribeReason::Server,
+                                                    UnsubscribeReason::Shutdown,
                                                 )
                                                 .into()));
 
@@ -274,12 +905,41 @@ impl Protocol for ProtocolImpl {
         Ok(Response::new(UnboundedReceiverStream::new(recv)))
     }
 
+    async fn ack(
+        &self,
+        request: Request<protocol::AckRequest>,
+    ) -> Result<Response<protocol::AckResponse>, Status> {
+        let ctx = self.try_get_request_context_from(&request)?;
+        let namespace = Namespace::from_request(&request)?;
+        let principal = Principal::from_request(&request)?;
+        let params: Ack = request.into_inner().into();
+        let checkpoints = namespace.scope(&checkpoints_of(&params.group, &params.stream_name)?)?;
+        self.authorize(
+            ctx,
+            &principal,
+            &namespace.scope_subscription(&params.stream_name)?,
+            StreamAccess::Read,
+        )
+        .await?;
+
+        if !self.groups.ack(&checkpoints, params.position).await {
+            return Err(Status::not_found(format!(
+                "no member of group '{}' is subscribed to '{}'",
+                params.group, params.stream_name
+            )));
+        }
+
+        Ok(Response::new(protocol::AckResponse {}))
+    }
+
     async fn list_programs(
         &self,
         request: Request<protocol::ListProgramsRequest>,
     ) -> Result<Response<protocol::ListProgramsResponse>, Status> {
         let ctx = self.try_get_request_context_from(&request)?;
-        match self.sub.list_programs(ctx).await {
+        Namespace::from_request(&request)?.must_be_default("listing programs")?;
+
+        match self.sub()?.list_programs(ctx).await {
             Err(e) => Err(Status::internal(e.to_string())),
 
             Ok(programs) => Ok(Response::new(ProgramListed { programs }.into())),
@@ -291,8 +951,9 @@ impl Protocol for ProtocolImpl {
         request: Request<protocol::ProgramStatsRequest>,
     ) -> Result<Response<protocol::ProgramStatsResponse>, Status> {
         let ctx = self.try_get_request_context_from(&request)?;
+        Namespace::from_request(&request)?.must_be_default("program stats")?;
         let params: GetProgramStats = request.into_inner().into();
-        match self.sub.program_stats(ctx, params.id).await {
+        match self.sub()?.program_stats(ctx, params.id).await {
             Err(e) => Err(Status::internal(e.to_string())),
 
             Ok(stats) => {
@@ -310,11 +971,141 @@ impl Protocol for ProtocolImpl {
         request: Request<protocol::StopProgramRequest>,
     ) -> Result<Response<protocol::StopProgramResponse>, Status> {
         let ctx = self.try_get_request_context_from(&request)?;
+        Namespace::from_request(&request)?.must_be_default("stopping programs")?;
+        self.authorize_admin(&Principal::from_request(&request)?, "stopping programs")?;
         let params: KillProgram = request.into_inner().into();
-        if let Err(e) = self.sub.program_stop(ctx, params.id).await {
+        if let Err(e) = self.sub()?.program_stop(ctx, params.id).await {
             return Err(Status::internal(e.to_string()));
         }
 
         Ok(Response::new(ProgramKilled::Success.into()))
     }
+
+    async fn set_log_filters(
+        &self,
+        request: Request<protocol::SetLogFiltersRequest>,
+    ) -> Result<Response<protocol::SetLogFiltersResponse>, Status> {
+        Namespace::from_request(&request)?.must_be_default("setting log filters")?;
+        self.authorize_admin(&Principal::from_request(&request)?, "setting log filters")?;
+        let params: SetLogFilters = request.into_inner().into();
+
+        match crate::set_log_filters(&params.directives) {
+            Err(e) => Err(Status::internal(e.to_string())),
+            Ok(result) => Ok(Response::new(result.into())),
+        }
+    }
+
+    async fn maintain_index(
+        &self,
+        request: Request<protocol::MaintainIndexRequest>,
+    ) -> Result<Response<protocol::MaintainIndexResponse>, Status> {
+        let ctx = self.try_get_request_context_from(&request)?;
+        Namespace::from_request(&request)?.must_be_default("index maintenance")?;
+        self.authorize_admin(&Principal::from_request(&request)?, "index maintenance")?;
+        let params: MaintainIndex = request.into_inner().into();
+
+        match self.index()?.maintain(ctx, params.compact).await {
+            Err(e) => Err(Status::internal(e.to_string())),
+            Ok(result) => Ok(Response::new(result.into())),
+        }
+    }
+
+    async fn list_streams(
+        &self,
+        request: Request<protocol::ListStreamsRequest>,
+    ) -> Result<Response<protocol::ListStreamsResponse>, Status> {
+        let ctx = self.try_get_request_context_from(&request)?;
+        let namespace = Namespace::from_request(&request)?;
+        let principal = Principal::from_request(&request)?;
+        let params: ListStreams = request.into_inner().into();
+        // Stream names are seen the same way as the events of `$all`.
+        self.authorize(ctx, &principal, streams::ALL, StreamAccess::Read)
+            .await?;
+        let prefix = match params.prefix {
+            Some(prefix) => Some(namespace.scope(&prefix)?),
+            None if namespace.is_default() => None,
+            None => Some(namespace.scope("")?),
+        };
+
+        match self
+            .index()?
+            .list_streams(ctx, prefix, params.offset, params.limit)
+            .await
+        {
+            Err(e) => Err(Status::internal(e.to_string())),
+            Ok(mut page) => {
+                page.streams = page
+                    .streams
+                    .into_iter()
+                    .filter_map(|s| namespace.unscope(&s))
+                    .collect();
+
+                Ok(Response::new(page.into()))
+            }
+        }
+    }
+
+    async fn copy_stream(
+        &self,
+        request: Request<protocol::CopyStreamRequest>,
+    ) -> Result<Response<protocol::CopyStreamResponse>, Status> {
+        let ctx = self.try_get_request_context_from(&request)?;
+        let namespace = Namespace::from_request(&request)?;
+        let principal = Principal::from_request(&request)?;
+        let mut params: CopyStream = request.into_inner().try_into()?;
+
+        if params.source_stream == params.target_stream {
+            return Err(Status::invalid_argument(format!(
+                "stream '{}' can't be copied to itself",
+                params.source_stream
+            )));
+        }
+
+        params.source_stream = namespace.scope(&params.source_stream)?;
+        params.target_stream = namespace.scope(&params.target_stream)?;
+        self.authorize(ctx, &principal, &params.source_stream, StreamAccess::Read)
+            .await?;
+        self.authorize(ctx, &principal, &params.target_stream, StreamAccess::Write)
+            .await?;
+
+        match copy_stream(ctx, self.reader()?, self.writer()?, &self.mappers, params).await {
+            Err(e) => Err(Status::internal(e.to_string())),
+            Ok(result) => Ok(Response::new(result.into())),
+        }
+    }
+}
+
+/// Sends a heartbeat every `interval` until the subscriber goes away, keeping idle subscriptions
+/// from being dropped by proxies and letting the subscriber notice a dead connection.
+fn send_heartbeats(
+    sender: UnboundedSender<Result<SubscribeResponse, Status>>,
+    interval: Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(interval);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        // The first tick completes right away.
+        ticks.tick().await;
+
+        loop {
+            ticks.tick().await;
+
+            if sender.send(Ok(SubscribeResponse::heartbeat())).is_err() {
+                break;
+            }
+        }
+    })
+}
+
+/// Stream holding the checkpoints of a persistent subscription, as seen from its namespace.
+#[allow(clippy::result_large_err)]
+fn checkpoints_of(group: &str, stream: &str) -> Result<String, Status> {
+    if group.is_empty() || group.contains(':') {
+        return Err(Status::invalid_argument(format!(
+            "invalid group '{group}': must be non-empty and ':' is not allowed"
+        )));
+    }
+
+    Ok(streams::checkpoints_of(group, stream))
 }
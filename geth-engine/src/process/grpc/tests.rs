@@ -0,0 +1,86 @@
+use std::net::TcpListener;
+
+use geth_common::{AppendStream, ExpectedRevision, Propose};
+use geth_grpc::generated::protocol::{self, protocol_client::ProtocolClient};
+use opentelemetry_sdk::metrics::{
+    InMemoryMetricExporter, PeriodicReader, SdkMeterProvider,
+    data::{AggregatedMetrics, MetricData},
+};
+use uuid::Uuid;
+
+use crate::Options;
+use crate::process::tests::Foo;
+
+fn operation_count(exporter: &InMemoryMetricExporter, name: &str, operation: &str) -> u64 {
+    let metrics = exporter.get_finished_metrics().unwrap();
+    let Some(resource) = metrics.last() else {
+        return 0;
+    };
+
+    resource
+        .scope_metrics()
+        .flat_map(|scope| scope.metrics())
+        .filter(|metric| metric.name() == name)
+        .filter_map(|metric| match metric.data() {
+            AggregatedMetrics::F64(MetricData::Histogram(histogram)) => Some(histogram),
+            _ => None,
+        })
+        .flat_map(|histogram| histogram.data_points())
+        .filter(|point| {
+            point
+                .attributes()
+                .any(|attr| attr.key.as_str() == "operation" && attr.value.as_str() == operation)
+        })
+        .map(|point| point.count())
+        .sum()
+}
+
+#[tokio::test]
+async fn test_append_latency_is_recorded() -> eyre::Result<()> {
+    let exporter = InMemoryMetricExporter::default();
+    let provider = SdkMeterProvider::builder()
+        .with_reader(PeriodicReader::builder(exporter.clone()).build())
+        .build();
+
+    // Must be set before the engine builds its instruments.
+    opentelemetry::global::set_meter_provider(provider.clone());
+
+    let port = TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+    let options = Options::new("127.0.0.1".to_string(), port, "in_mem".to_string());
+    let embedded = crate::run_embedded(&options).await?;
+    let mut client = ProtocolClient::connect(format!("http://127.0.0.1:{port}")).await?;
+    let stream_name = Uuid::new_v4().to_string();
+
+    for i in 0..3 {
+        client
+            .append_stream(protocol::AppendStreamRequest::from(AppendStream {
+                stream_name: stream_name.clone(),
+                events: vec![Propose::from_value(&Foo { baz: i })?],
+                expected_revision: ExpectedRevision::Any,
+                notify_subscribers: true,
+            }))
+            .await?;
+
+        provider.force_flush()?;
+
+        assert_eq!(
+            i as u64 + 1,
+            operation_count(
+                &exporter,
+                "geth_operation_duration_seconds",
+                "append_stream"
+            )
+        );
+    }
+
+    assert_eq!(
+        3,
+        operation_count(
+            &exporter,
+            "geth_operation_first_message_seconds",
+            "append_stream"
+        )
+    );
+
+    embedded.shutdown().await
+}
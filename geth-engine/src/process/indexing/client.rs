@@ -1,22 +1,36 @@
 use std::vec;
 
-use crate::domain::index::CurrentRevision;
+use crate::domain::catalog::CatalogChange;
+use crate::domain::index::{CurrentRevision, StreamState};
 use crate::process::messages::{IndexRequests, IndexResponses, Messages, Requests};
 use crate::process::{ManagerClient, ProcId, RequestContext};
-use geth_common::{Direction, ReadCompleted};
+use geth_common::{Direction, IndexMaintained, StreamPage};
 use geth_domain::index::BlockEntry;
-use tokio::sync::mpsc::UnboundedReceiver;
+use geth_mikoshi::hashing::{HashAlgorithm, mikoshi_hash};
+use geth_mikoshi::wal::LogCursor;
+use geth_mikoshi::wal::chunks::Scavenged;
+use tokio::sync::mpsc::Receiver;
 use tracing::instrument;
 
 #[derive(Debug, Clone)]
 pub struct IndexClient {
     target: ProcId,
     inner: ManagerClient,
+    hash_algorithm: HashAlgorithm,
 }
 
 impl IndexClient {
-    pub fn new(target: ProcId, inner: ManagerClient) -> Self {
-        Self { target, inner }
+    pub fn new(target: ProcId, inner: ManagerClient, hash_algorithm: HashAlgorithm) -> Self {
+        Self {
+            target,
+            inner,
+            hash_algorithm,
+        }
+    }
+
+    /// Index key of a stream, hashed with the algorithm of the database.
+    pub fn key(&self, stream_name: impl AsRef<[u8]>) -> u64 {
+        mikoshi_hash(self.hash_algorithm, stream_name)
     }
 
     #[instrument(skip(self, context), fields(origin = ?self.inner.origin(), correlation = %context.correlation))]
@@ -27,7 +41,7 @@ impl IndexClient {
         start: u64,
         count: usize,
         dir: Direction,
-    ) -> eyre::Result<ReadCompleted<Streaming>> {
+    ) -> eyre::Result<Streaming> {
         let mut inner = self
             .inner
             .request_stream(
@@ -54,13 +68,11 @@ impl IndexClient {
                     eyre::bail!("internal error when running a read request to the index process")
                 }
 
-                IndexResponses::StreamDeleted => return Ok(ReadCompleted::StreamDeleted),
-
                 IndexResponses::Entries(entries) => {
-                    return Ok(ReadCompleted::Success(Streaming {
+                    return Ok(Streaming {
                         inner,
                         batch: Some(entries.into_iter()),
-                    }));
+                    });
                 }
 
                 _ => {
@@ -79,13 +91,20 @@ impl IndexClient {
         &self,
         context: RequestContext,
         entries: Vec<BlockEntry>,
+        change: Option<CatalogChange>,
+        cursor: Option<LogCursor>,
     ) -> eyre::Result<()> {
+        // The entries are already in the log, they can't be dropped because the index is busy.
         let resp = self
             .inner
-            .request(
+            .request_with_backpressure(
                 context,
                 self.target,
-                Messages::Requests(Requests::Index(IndexRequests::Store { entries })),
+                Messages::Requests(Requests::Index(IndexRequests::Store {
+                    entries,
+                    change,
+                    cursor,
+                })),
             )
             .await?;
 
@@ -114,6 +133,31 @@ impl IndexClient {
         context: RequestContext,
         key: u64,
     ) -> eyre::Result<CurrentRevision> {
+        Ok(self.stream_state(context, key).await?.current)
+    }
+
+    /// Latest revision of a stream, `None` if it was never written to or is deleted.
+    #[instrument(skip(self, context), fields(origin = ?self.inner.origin(), correlation = %context.correlation))]
+    pub async fn stream_revision(
+        &self,
+        context: RequestContext,
+        stream_name: &str,
+    ) -> eyre::Result<Option<u64>> {
+        let current = self.latest_revision(context, self.key(stream_name)).await?;
+
+        if current.is_deleted() {
+            return Ok(None);
+        }
+
+        Ok(current.revision())
+    }
+
+    #[instrument(skip(self, context), fields(origin = ?self.inner.origin(), correlation = %context.correlation))]
+    pub async fn stream_state(
+        &self,
+        context: RequestContext,
+        key: u64,
+    ) -> eyre::Result<StreamState> {
         let resp = self
             .inner
             .request(
@@ -129,8 +173,8 @@ impl IndexClient {
                     eyre::bail!("error when fetching the latest revision from the index process");
                 }
 
-                IndexResponses::CurrentRevision(rev) => {
-                    return Ok(rev);
+                IndexResponses::StreamState(state) => {
+                    return Ok(state);
                 }
 
                 _ => {
@@ -143,11 +187,101 @@ impl IndexClient {
 
         eyre::bail!("unexpected message from the index process");
     }
+
+    #[instrument(skip(self, context), fields(origin = ?self.inner.origin(), correlation = %context.correlation))]
+    pub async fn list_streams(
+        &self,
+        context: RequestContext,
+        prefix: Option<String>,
+        offset: usize,
+        limit: usize,
+    ) -> eyre::Result<StreamPage> {
+        let resp = self
+            .inner
+            .request(
+                context,
+                self.target,
+                Messages::Requests(Requests::Index(IndexRequests::ListStreams {
+                    prefix,
+                    offset,
+                    limit,
+                })),
+            )
+            .await?;
+
+        if let Ok(resp) = resp.payload.try_into() {
+            match resp {
+                IndexResponses::Streams(page) => {
+                    return Ok(page);
+                }
+
+                _ => {
+                    eyre::bail!("unexpected response when listing streams from the index process");
+                }
+            }
+        }
+
+        eyre::bail!("unexpected message from the index process");
+    }
+
+    /// Flushes the index memtable and, with `compact`, merges every sstable into one. Returns
+    /// once done.
+    #[instrument(skip(self, context), fields(origin = ?self.inner.origin(), correlation = %context.correlation))]
+    pub async fn maintain(
+        &self,
+        context: RequestContext,
+        compact: bool,
+    ) -> eyre::Result<IndexMaintained> {
+        let mut inner = self
+            .inner
+            .request_stream(
+                context,
+                self.target,
+                Messages::Requests(Requests::Index(IndexRequests::Maintain { compact })),
+            )
+            .await?;
+
+        let Some(resp) = inner.recv().await else {
+            eyre::bail!("index process is no longer reachable");
+        };
+
+        match resp.try_into() {
+            Ok(IndexResponses::Maintained(maintained)) => Ok(maintained),
+            Ok(IndexResponses::Error) => eyre::bail!("error when maintaining the index"),
+            Ok(_) => eyre::bail!("unexpected response when maintaining the index"),
+            Err(_) => eyre::bail!("unexpected message from the index process"),
+        }
+    }
+
+    /// Drops the events of deleted streams from the completed chunks and rebuilds the index out
+    /// of what's left. Returns once done.
+    #[instrument(skip(self, context), fields(origin = ?self.inner.origin(), correlation = %context.correlation))]
+    pub async fn scavenge(&self, context: RequestContext) -> eyre::Result<Scavenged> {
+        let mut inner = self
+            .inner
+            .request_stream(
+                context,
+                self.target,
+                Messages::Requests(Requests::Index(IndexRequests::Scavenge)),
+            )
+            .await?;
+
+        let Some(resp) = inner.recv().await else {
+            eyre::bail!("index process is no longer reachable");
+        };
+
+        match resp.try_into() {
+            Ok(IndexResponses::Scavenged(scavenged)) => Ok(scavenged),
+            Ok(IndexResponses::Error) => eyre::bail!("error when scavenging the log"),
+            Ok(_) => eyre::bail!("unexpected response when scavenging the log"),
+            Err(_) => eyre::bail!("unexpected message from the index process"),
+        }
+    }
 }
 
 pub struct Streaming {
     batch: Option<vec::IntoIter<BlockEntry>>,
-    inner: UnboundedReceiver<Messages>,
+    inner: Receiver<Messages>,
 }
 
 impl Streaming {
@@ -1,5 +1,5 @@
 mod client;
 mod proc;
 
-pub use client::IndexClient;
+pub use client::{IndexClient, Streaming};
 pub use proc::run;
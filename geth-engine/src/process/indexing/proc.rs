@@ -1,41 +1,63 @@
-use crate::domain::index::CurrentRevision;
+use crate::domain::catalog::{CatalogChange, StreamCatalog};
+use crate::domain::index::{
+    CurrentRevision, StreamState, TOMBSTONE_FLOOR, is_tombstone, tombstone_epoch,
+    tombstone_revision,
+};
 use crate::metrics::get_metrics;
-use crate::names::types::STREAM_DELETED;
+use crate::names::streams;
+use crate::names::types::{STREAM_DELETED, STREAM_METADATA, STREAM_TRUNCATED};
 use crate::process::messages::{IndexRequests, IndexResponses, Messages};
 use crate::process::reading::record_try_from;
 use crate::process::{Item, ProcessEnv, Raw, RequestContext};
 use crate::{get_chunk_container, get_storage};
-use geth_common::{Direction, IteratorIO};
+use chrono::{DateTime, Utc};
+use geth_common::{Direction, IndexMaintained, IteratorIO, Record, StreamMetadata};
 use geth_domain::index::BlockEntry;
 use geth_domain::{Lsm, LsmSettings};
 use geth_mikoshi::hashing::mikoshi_hash;
-use geth_mikoshi::wal::LogReader;
-use geth_mikoshi::wal::chunks::ChunkContainer;
+use geth_mikoshi::storage::FileId;
+use geth_mikoshi::wal::chunks::{ChunkContainer, Scavenged, scavenge};
+use geth_mikoshi::wal::{LogCursor, LogReader};
 use std::cmp::min;
-use std::sync::{Arc, RwLock};
-use std::{io, mem};
-use tokio::sync::mpsc::UnboundedSender;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::sync::mpsc::Sender;
 use tracing::instrument;
 use uuid::Uuid;
 
-type RevisionCache = moka::sync::Cache<u64, u64>;
+type RevisionCache = moka::sync::Cache<u64, StreamState>;
 
 fn new_revision_cache() -> RevisionCache {
-    moka::sync::Cache::<u64, u64>::builder()
+    moka::sync::Cache::<u64, StreamState>::builder()
         .max_capacity(10_000)
         .name(&format!("revision-cache-{}", Uuid::new_v4()))
         .build()
 }
 
+/// Compaction runs in the background, so writes don't wait for sstables to be merged.
+fn lsm_settings() -> LsmSettings {
+    LsmSettings {
+        background_compaction: true,
+        ..Default::default()
+    }
+}
+
 #[instrument(skip(env), fields(origin = ?env.proc))]
 pub fn run(mut env: ProcessEnv<Raw>) -> eyre::Result<()> {
-    let mut lsm = Lsm::load(LsmSettings::default(), get_storage())?;
+    let mut lsm = Lsm::load(lsm_settings(), get_storage())?;
 
     tracing::info!("rebuilding index...");
-    let revision_cache = rebuild_index(&mut lsm, get_chunk_container().clone())?;
+    let mut catalog = rebuild_index(&mut lsm, get_chunk_container().clone())?;
     tracing::info!("index rebuilt successfully");
 
+    let revision_cache = new_revision_cache();
+    let log_reader = LogReader::new(get_chunk_container().clone());
+
     let lsm = Arc::new(RwLock::new(lsm));
+    let compacting = Arc::new(AtomicBool::new(false));
+    // Scavenges rewrite the chunks without the index lock, they run one after the other.
+    let scavenging = Arc::new(Mutex::new(()));
     let metrics = get_metrics();
 
     while let Some(item) = env.recv() {
@@ -43,7 +65,11 @@ pub fn run(mut env: ProcessEnv<Raw>) -> eyre::Result<()> {
             Item::Mail(mail) => {
                 if let Ok(req) = mail.payload.try_into() {
                     match req {
-                        IndexRequests::Store { entries } => {
+                        IndexRequests::Store {
+                            entries,
+                            change,
+                            cursor,
+                        } => {
                             if entries.is_empty() {
                                 tracing::warn!("empty entries vector received");
 
@@ -58,40 +84,59 @@ pub fn run(mut env: ProcessEnv<Raw>) -> eyre::Result<()> {
                             }
 
                             let last = entries.last().copied().unwrap();
-                            if let Err(e) = store_entries(&lsm, entries) {
-                                tracing::error!("error when storing index entries: {}", e);
-                                metrics.observe_index_write_error();
-                                let _ = env.client.reply(
-                                    mail.context,
-                                    mail.origin,
-                                    mail.correlation,
-                                    IndexResponses::Error.into(),
-                                );
-                            } else {
-                                revision_cache.insert(last.key, last.revision);
+                            let needs_compaction = match store_entries(&lsm, entries, cursor) {
+                                Ok(needs_compaction) => needs_compaction,
+                                Err(e) => {
+                                    tracing::error!("error when storing index entries: {}", e);
+                                    metrics.observe_index_write_error();
+                                    let _ = env.client.reply(
+                                        mail.context,
+                                        mail.origin,
+                                        mail.correlation,
+                                        IndexResponses::Error.into(),
+                                    );
+
+                                    continue;
+                                }
+                            };
+
+                            if needs_compaction && !compacting.swap(true, Ordering::AcqRel) {
+                                let compaction_lsm = lsm.clone();
+                                let compacting = compacting.clone();
+                                env.spawn_blocking(move || {
+                                    if let Err(error) = compact_index(&compaction_lsm) {
+                                        tracing::error!(%error, "error when compacting the index");
+                                    }
+
+                                    compacting.store(false, Ordering::Release);
+                                });
+                            }
 
-                                let _ = env.client.reply(
-                                    mail.context,
-                                    mail.origin,
-                                    mail.correlation,
-                                    IndexResponses::Committed.into(),
-                                );
+                            // A tombstone changes the epochs of the stream, worked out again on
+                            // the next lookup.
+                            if is_tombstone(last.revision) {
+                                revision_cache.invalidate(&last.key);
+                            } else if let Some(mut state) = revision_cache.get(&last.key) {
+                                state.current = CurrentRevision::Revision(last.revision);
+                                revision_cache.insert(last.key, state);
                             }
+
+                            if let Some(change) = change {
+                                catalog.apply(change);
+                            }
+
+                            let _ = env.client.reply(
+                                mail.context,
+                                mail.origin,
+                                mail.correlation,
+                                IndexResponses::Committed.into(),
+                            );
                         }
 
                         IndexRequests::LatestRevision { key } => {
-                            if let Some(current) = revision_cache.get(&key) {
+                            let state = if let Some(state) = revision_cache.get(&key) {
                                 metrics.observe_index_cache_hit();
-
-                                env.client.reply(
-                                    mail.context,
-                                    mail.origin,
-                                    mail.correlation,
-                                    IndexResponses::CurrentRevision(CurrentRevision::Revision(
-                                        current,
-                                    ))
-                                    .into(),
-                                )?;
+                                state
                             } else {
                                 metrics.observe_index_cache_miss();
 
@@ -99,25 +144,43 @@ pub fn run(mut env: ProcessEnv<Raw>) -> eyre::Result<()> {
                                     eyre::eyre!("poisoned lock when reading to the index: {}", e)
                                 })?;
 
-                                let revison = lsm_read.highest_revision(key)?;
-                                let mut value = CurrentRevision::NoStream;
+                                let state = stream_state(&lsm_read, &log_reader, key)?;
+                                revision_cache.insert(key, state.clone());
+                                state
+                            };
 
-                                if let Some(revision) = revison {
-                                    revision_cache.insert(key, revision);
-                                    value = CurrentRevision::Revision(revision);
-                                }
+                            env.client.reply(
+                                mail.context,
+                                mail.origin,
+                                mail.correlation,
+                                IndexResponses::StreamState(state).into(),
+                            )?;
+                        }
 
-                                env.client.reply(
-                                    mail.context,
-                                    mail.origin,
-                                    mail.correlation,
-                                    IndexResponses::CurrentRevision(value).into(),
-                                )?;
-                            }
+                        IndexRequests::ListStreams {
+                            prefix,
+                            offset,
+                            limit,
+                        } => {
+                            env.client.reply(
+                                mail.context,
+                                mail.origin,
+                                mail.correlation,
+                                IndexResponses::Streams(catalog.page(
+                                    prefix.as_deref(),
+                                    offset,
+                                    limit,
+                                ))
+                                .into(),
+                            )?;
                         }
 
-                        IndexRequests::Read { .. } => {
-                            tracing::error!("read from the index should be a streaming operation");
+                        IndexRequests::Read { .. }
+                        | IndexRequests::Maintain { .. }
+                        | IndexRequests::Scavenge => {
+                            tracing::error!(
+                                "reads, maintenance and scavenging of the index are streaming operations"
+                            );
 
                             env.client.reply(
                                 mail.context,
@@ -130,21 +193,18 @@ pub fn run(mut env: ProcessEnv<Raw>) -> eyre::Result<()> {
                 }
             }
 
-            Item::Stream(stream) => {
-                if let Ok(IndexRequests::Read {
+            Item::Stream(stream) => match stream.payload.try_into() {
+                Ok(IndexRequests::Read {
                     key,
                     start,
                     count,
                     dir,
-                }) = stream.payload.try_into()
-                {
-                    let stream_cache = revision_cache.clone();
+                }) => {
                     let stream_lsm = lsm.clone();
                     env.spawn_blocking(move || {
                         if let Err(error) = stream_indexed_read(IndexRead {
                             context: stream.context,
                             lsm: stream_lsm,
-                            cache: stream_cache,
                             key,
                             start,
                             count,
@@ -153,139 +213,458 @@ pub fn run(mut env: ProcessEnv<Raw>) -> eyre::Result<()> {
                         }) {
                             get_metrics().observe_index_read_error();
                             tracing::error!(%error, "error when reading the index");
-                            let _ = stream.sender.send(IndexResponses::Error.into());
+                            let _ = stream.sender.blocking_send(IndexResponses::Error.into());
                         }
                     });
                 }
-            }
+
+                Ok(IndexRequests::Maintain { compact }) => {
+                    let stream_lsm = lsm.clone();
+                    env.spawn_blocking(move || {
+                        let span = tracing::info_span!(
+                            "index_maintenance",
+                            correlation = %stream.context.correlation,
+                            compact
+                        );
+                        let resp = match span.in_scope(|| maintain_index(&stream_lsm, compact)) {
+                            Ok(maintained) => IndexResponses::Maintained(maintained),
+                            Err(error) => {
+                                get_metrics().observe_index_write_error();
+                                tracing::error!(%error, "error when maintaining the index");
+                                IndexResponses::Error
+                            }
+                        };
+
+                        let _ = stream.sender.blocking_send(resp.into());
+                    });
+                }
+
+                Ok(IndexRequests::Scavenge) => {
+                    let stream_lsm = lsm.clone();
+                    let revision_cache = revision_cache.clone();
+                    let scavenging = scavenging.clone();
+                    let clock = env.options.clock.clone();
+                    env.spawn_blocking(move || {
+                        let _scavenging = scavenging.lock().unwrap_or_else(|e| e.into_inner());
+                        let span = tracing::info_span!(
+                            "scavenge",
+                            correlation = %stream.context.correlation,
+                        );
+                        let resp = match span.in_scope(|| {
+                            scavenge_log(&stream_lsm, get_chunk_container().clone(), clock.now())
+                        }) {
+                            Ok(scavenged) => {
+                                revision_cache.invalidate_all();
+                                IndexResponses::Scavenged(scavenged)
+                            }
+                            Err(error) => {
+                                get_metrics().observe_index_write_error();
+                                tracing::error!(%error, "error when scavenging the log");
+                                IndexResponses::Error
+                            }
+                        };
+
+                        let _ = stream.sender.blocking_send(resp.into());
+                    });
+                }
+
+                _ => {}
+            },
         };
     }
 
     Ok(())
 }
 
-fn rebuild_index(lsm: &mut Lsm, container: ChunkContainer) -> eyre::Result<RevisionCache> {
+/// Only the entries past the saved [`LogCursor`] are put back in the index, the ones before it
+/// are already on disk. The catalog isn't persisted so it's rebuilt from the whole log.
+fn rebuild_index(lsm: &mut Lsm, container: ChunkContainer) -> eyre::Result<StreamCatalog> {
+    let hash_algorithm = container.hash_algorithm()?;
+    let cursor = LogCursor::load(lsm.storage(), FileId::index_chk())?;
     let reader = LogReader::new(container);
-    let writer_checkpoint = reader.get_writer_checkpoint()?;
-    let cache = new_revision_cache();
-    let mut entries = reader.entries(0, writer_checkpoint);
+    let mut catalog = StreamCatalog::default();
+    // Streams deleted so far, an event appended to one of them recreates it.
+    let mut deleted = HashSet::new();
+    let mut entries = reader.entries_from(LogCursor::default())?;
+
+    tracing::info!(position = cursor.position, "resuming index");
 
     while let Some(entry) = entries.next()? {
         if entry.r#type != 0 {
             continue;
         }
 
+        let indexed = entry.position < cursor.position;
+
         let record = record_try_from(entry)?;
-        let key = mikoshi_hash(&record.stream_name);
+        let key = mikoshi_hash(hash_algorithm, &record.stream_name);
 
-        let final_revision = if record.class == STREAM_DELETED {
-            u64::MAX
-        } else {
-            record.revision
-        };
+        if !indexed {
+            lsm.put_single(key, indexed_revision(&record), record.position)?;
+        }
 
-        lsm.put_single(key, final_revision, record.position)?;
-        cache.insert(key, record.revision);
+        if record.class == STREAM_DELETED {
+            deleted.insert(record.stream_name.clone());
+            catalog.apply(CatalogChange::Deleted(record.stream_name));
+        } else if (record.revision == 0 || deleted.remove(&record.stream_name))
+            && !streams::is_metadata(&record.stream_name)
+        {
+            catalog.apply(CatalogChange::Created(record.stream_name));
+        }
     }
 
-    Ok(cache)
+    Ok(catalog)
 }
 
-fn key_latest_revision(
-    lsm: &Lsm,
-    cache: RevisionCache,
-    stream_key: u64,
-) -> io::Result<CurrentRevision> {
-    let current_revision = if let Some(current) = cache.get(&stream_key) {
-        CurrentRevision::Revision(current)
-    } else {
-        let revision = lsm
-            .highest_revision(stream_key)?
-            .map_or_else(|| CurrentRevision::NoStream, CurrentRevision::Revision);
+/// Works the epochs of a stream out of its tombstones. The revision a tombstone was written at,
+/// only found in the log, is the one right before the next epoch starts.
+fn stream_state(lsm: &Lsm, reader: &LogReader, key: u64) -> eyre::Result<StreamState> {
+    let mut state = StreamState::default();
+    let mut tombstones = vec![];
+    let mut scan = lsm.scan_forward(key, TOMBSTONE_FLOOR, usize::MAX);
 
-        if let CurrentRevision::Revision(rev) = revision {
-            cache.insert(stream_key, rev);
-        }
+    while let Some(tombstone) = scan.next()? {
+        tombstones.push(tombstone);
+    }
+
+    // The first epoch has the highest tombstone revision.
+    tombstones.sort_unstable_by(|a, b| b.revision.cmp(&a.revision));
+
+    for tombstone in tombstones {
+        let record = record_try_from(reader.read_at(tombstone.position)?)?;
+        state.epoch_starts.push(record.revision + 1);
+    }
+
+    let highest = lsm
+        .scan_backward(key, TOMBSTONE_FLOOR - 1, 1)
+        .last()?
+        .map(|e| e.revision);
 
-        revision
+    state.current = match highest {
+        Some(revision) if revision >= state.epoch_start() => CurrentRevision::Revision(revision),
+        _ if state.epoch() > 0 => CurrentRevision::Revision(u64::MAX),
+        _ => CurrentRevision::NoStream,
     };
 
-    Ok(current_revision)
+    Ok(state)
 }
 
-fn store_entries(lsm: &Arc<RwLock<Lsm>>, entries: Vec<BlockEntry>) -> eyre::Result<()> {
+/// Returns whether the index is due for a compaction.
+fn store_entries(
+    lsm: &Arc<RwLock<Lsm>>,
+    entries: Vec<BlockEntry>,
+    cursor: Option<LogCursor>,
+) -> eyre::Result<bool> {
     let mut lsm = lsm
         .write()
         .map_err(|e| eyre::eyre!("poisoned lock when writing to the index: {}", e))?;
 
     lsm.put_values(entries.into_iter().map(|e| (e.key, e.revision, e.position)))?;
 
-    Ok(())
+    // Entries still in memory are lost on restart, so the cursor only moves once they are all
+    // flushed, and the ones after it are replayed from the log instead.
+    if let Some(cursor) = cursor
+        && !lsm.has_unflushed_entries()
+    {
+        cursor.save(lsm.storage(), FileId::index_chk())?;
+    }
+
+    Ok(lsm.needs_compaction())
+}
+
+/// Only takes the index read lock to snapshot the sstables to merge, and the write lock to swap
+/// them with the merged one. Reads and writes go on while the tables are merged.
+fn compact_index(lsm: &Arc<RwLock<Lsm>>) -> eyre::Result<()> {
+    loop {
+        let compaction = lsm
+            .read()
+            .map_err(|e| eyre::eyre!("poisoned lock when compacting the index: {}", e))?
+            .next_compaction();
+
+        let Some(compaction) = compaction else {
+            return Ok(());
+        };
+
+        let table = compaction.run()?;
+
+        lsm.write()
+            .map_err(|e| eyre::eyre!("poisoned lock when compacting the index: {}", e))?
+            .apply_compaction(compaction, table)?;
+    }
+}
+
+/// Holds the index write lock for the whole operation: reads in progress complete against the
+/// tables they started with and the next ones only see the new tables.
+fn maintain_index(lsm: &Arc<RwLock<Lsm>>, compact: bool) -> eyre::Result<IndexMaintained> {
+    let mut lsm = lsm
+        .write()
+        .map_err(|e| eyre::eyre!("poisoned lock when maintaining the index: {}", e))?;
+
+    let ss_tables_before = lsm.ss_table_count() as u64;
+    let flushed = lsm.flush()? as u64;
+
+    if compact {
+        lsm.compact()?;
+    }
+
+    Ok(IndexMaintained {
+        flushed,
+        ss_tables_before,
+        ss_tables_after: lsm.ss_table_count() as u64,
+    })
+}
+
+/// Drops from the completed chunks the events reads no longer return: the ones of deleted
+/// streams, the ones before the latest truncation of their stream and the ones past its
+/// `max_count` or `max_age_secs`. The log is scanned and the chunks rewritten without the index lock, a read going
+/// through a dropped entry in the meantime still finds it in the previous version of its chunk.
+/// The write lock is only taken to swap the index for one without the dropped entries.
+fn scavenge_log(
+    lsm: &Arc<RwLock<Lsm>>,
+    container: ChunkContainer,
+    now: DateTime<Utc>,
+) -> eyre::Result<Scavenged> {
+    let hash_algorithm = container.hash_algorithm()?;
+    let reader = LogReader::new(container.clone());
+    let mut retention = Retention::default();
+    let mut indexed = Vec::new();
+    let mut entries = reader.entries_from(LogCursor::default())?;
+
+    while let Some(entry) = entries.next()? {
+        if entry.r#type != 0 {
+            continue;
+        }
+
+        let record = record_try_from(entry)?;
+        let key = mikoshi_hash(hash_algorithm, &record.stream_name);
+
+        indexed.push((key, indexed_revision(&record), record.position));
+        retention.observe(&record);
+    }
+
+    let scanned = entries.cursor();
+    let mut dropped = HashSet::new();
+    let scavenged = scavenge(&container, |entry| {
+        if entry.r#type != 0 {
+            return true;
+        }
+
+        // Entries that don't parse are left for the reads to report.
+        let Ok(record) = record_try_from(entry.clone()) else {
+            return true;
+        };
+
+        let keep = retention.keeps(&record, now);
+        if !keep {
+            dropped.insert(record.position);
+        }
+
+        keep
+    })?;
+
+    if scavenged.chunks == 0 {
+        return Ok(scavenged);
+    }
+
+    let mut lsm = lsm
+        .write()
+        .map_err(|e| eyre::eyre!("poisoned lock when scavenging the log: {}", e))?;
+
+    let storage = lsm.storage().clone();
+    Lsm::clear(&storage)?;
+    *lsm = Lsm::load(lsm_settings(), storage)?;
+
+    lsm.put_values(
+        indexed
+            .into_iter()
+            .filter(|(_, _, position)| !dropped.contains(position)),
+    )?;
+
+    // Entries appended since the scan, none of them were scavenged.
+    let mut entries = reader.entries_from(scanned)?;
+    while let Some(entry) = entries.next()? {
+        if entry.r#type != 0 {
+            continue;
+        }
+
+        let record = record_try_from(entry)?;
+        let key = mikoshi_hash(hash_algorithm, &record.stream_name);
+
+        lsm.put_single(key, indexed_revision(&record), record.position)?;
+    }
+
+    Ok(scavenged)
+}
+
+/// Revision a record is indexed at, tombstones are put above every event revision.
+fn indexed_revision(record: &Record) -> u64 {
+    if record.class == STREAM_DELETED {
+        tombstone_revision(tombstone_epoch(&record.data))
+    } else {
+        record.revision
+    }
+}
+
+/// What a scavenge keeps of every stream, as found in the log. Mirrors what reads return: see
+/// `first_revision` in the reading process.
+#[derive(Default)]
+struct Retention {
+    /// Log position of the last tombstone of every stream deleted so far.
+    tombstones: HashMap<String, u64>,
+    /// Revision the latest truncation of every stream starts at.
+    truncations: HashMap<String, u64>,
+    /// Latest metadata of every stream.
+    metadata: HashMap<String, StreamMetadata>,
+    /// Latest revision of every stream, tombstones aside.
+    revisions: HashMap<String, u64>,
+}
+
+impl Retention {
+    fn observe(&mut self, record: &Record) {
+        if record.class == STREAM_DELETED {
+            self.tombstones
+                .insert(record.stream_name.clone(), record.position);
+            return;
+        }
+
+        self.revisions
+            .insert(record.stream_name.clone(), record.revision);
+
+        let Some(stream) = record.stream_name.strip_prefix("$$") else {
+            return;
+        };
+
+        // Malformed settings are left for the reads to report, nothing is dropped because of them.
+        if record.class == STREAM_TRUNCATED {
+            if let Ok(before) = record.data.as_ref().try_into() {
+                self.truncations
+                    .insert(stream.to_string(), u64::from_le_bytes(before));
+            }
+        } else if record.class == STREAM_METADATA
+            && let Ok(metadata) = serde_json::from_slice::<StreamMetadata>(&record.data)
+        {
+            self.metadata.insert(stream.to_string(), metadata);
+        }
+    }
+
+    /// Tombstones and metadata streams are always kept as the epochs and settings of a stream
+    /// are worked out of them.
+    fn keeps(&self, record: &Record, now: DateTime<Utc>) -> bool {
+        if record.class == STREAM_DELETED || streams::is_metadata(&record.stream_name) {
+            return true;
+        }
+
+        let stream = &record.stream_name;
+        if self
+            .tombstones
+            .get(stream)
+            .is_some_and(|position| *position > record.position)
+        {
+            return false;
+        }
+
+        let mut first_revision = self.truncations.get(stream).copied().unwrap_or_default();
+        let Some(metadata) = self.metadata.get(stream) else {
+            return record.revision >= first_revision;
+        };
+
+        if let (Some(max_count), Some(latest)) = (metadata.max_count, self.revisions.get(stream)) {
+            first_revision = first_revision.max((latest + 1).saturating_sub(max_count));
+        }
+
+        // Records written before commit times were stored have none and never expire.
+        let expired = metadata.expires_before(now).is_some_and(|expires_before| {
+            record.created.timestamp_millis() != 0 && record.created < expires_before
+        });
+
+        record.revision >= first_revision && !expired
+    }
 }
 
 struct IndexRead<'a> {
     context: RequestContext,
     lsm: Arc<RwLock<Lsm>>,
-    cache: RevisionCache,
     key: u64,
     start: u64,
     count: usize,
     dir: Direction,
-    stream: &'a UnboundedSender<Messages>,
+    stream: &'a Sender<Messages>,
 }
 
+/// The index is only locked while a batch is collected, a consumer slow to take the batches
+/// doesn't hold up writes to the index.
 #[instrument(skip(params), fields(correlation = %params.context.correlation, key = params.key, start = params.start, count = params.count, direction = ?params.dir))]
 fn stream_indexed_read(params: IndexRead<'_>) -> eyre::Result<()> {
-    let lsm = params
-        .lsm
-        .read()
-        .map_err(|e| eyre::eyre!("poisoned lock when reading the index: {}", e))?;
-
-    let current_revision = key_latest_revision(&lsm, params.cache, params.key)?;
+    let batch_size = min(params.count, 500);
+    let mut remaining = params.count;
+    let mut no_entries = true;
+    // Tombstones are never returned, they sit above every event revision.
+    let mut next = match params.dir {
+        Direction::Forward => params.start,
+        Direction::Backward => min(params.start, TOMBSTONE_FLOOR - 1),
+    };
 
-    if current_revision.is_deleted()
-        && params
-            .stream
-            .send(IndexResponses::StreamDeleted.into())
-            .is_err()
-    {
-        return Ok(());
-    }
+    while remaining > 0 {
+        let limit = min(remaining, batch_size);
+        let batch = index_batch(&params, next, limit)?;
 
-    let mut iter: Box<dyn IteratorIO<Item = BlockEntry>> = match params.dir {
-        Direction::Forward => Box::new(lsm.scan_forward(params.key, params.start, params.count)),
-        Direction::Backward => Box::new(lsm.scan_backward(params.key, params.start, params.count)),
-    };
+        let Some(last) = batch.last().map(|e| e.revision) else {
+            break;
+        };
 
-    let batch_size = min(params.count, 500);
-    let mut batch = Vec::with_capacity(batch_size);
-    let mut no_entries = true;
-    while let Some(item) = iter.next()? {
-        if batch.len() >= batch_size {
-            let entries = mem::replace(&mut batch, Vec::with_capacity(batch_size));
-            if params
-                .stream
-                .send(IndexResponses::Entries(entries).into())
-                .is_err()
-            {
-                return Ok(());
-            }
+        // A short batch means there is nothing left to read.
+        let mut complete = batch.len() < limit;
+        match params.dir {
+            Direction::Forward => next = last + 1,
+            Direction::Backward => match last.checked_sub(1) {
+                Some(previous) => next = previous,
+                None => complete = true,
+            },
         }
 
-        batch.push(item);
+        remaining -= batch.len();
         no_entries = false;
-    }
 
-    if !batch.is_empty() {
-        let _ = params.stream.send(IndexResponses::Entries(batch).into());
-        return Ok(());
+        if params
+            .stream
+            .blocking_send(IndexResponses::Entries(batch).into())
+            .is_err()
+        {
+            return Ok(());
+        }
+
+        if complete {
+            return Ok(());
+        }
     }
 
     if no_entries {
         let _ = params
             .stream
-            .send(IndexResponses::Entries(Vec::new()).into());
+            .blocking_send(IndexResponses::Entries(Vec::new()).into());
     }
 
     Ok(())
 }
+
+/// Up to `limit` entries of the read, starting at revision `start`.
+fn index_batch(params: &IndexRead<'_>, start: u64, limit: usize) -> eyre::Result<Vec<BlockEntry>> {
+    let lsm = params
+        .lsm
+        .read()
+        .map_err(|e| eyre::eyre!("poisoned lock when reading the index: {}", e))?;
+
+    let mut iter: Box<dyn IteratorIO<Item = BlockEntry>> = match params.dir {
+        Direction::Forward => Box::new(lsm.range(params.key, start, TOMBSTONE_FLOOR - 1)),
+        Direction::Backward => Box::new(lsm.scan_backward(params.key, start, limit)),
+    };
+
+    let mut batch = Vec::with_capacity(limit);
+    while batch.len() < limit
+        && let Some(entry) = iter.next()?
+    {
+        batch.push(entry);
+    }
+
+    Ok(batch)
+}
@@ -3,7 +3,7 @@ use std::{
     time::Instant,
 };
 
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use geth_common::ProgramSummary;
 use tokio::sync::oneshot;
 use uuid::Uuid;
@@ -96,6 +96,10 @@ struct Registry {
 }
 
 impl Registry {
+    fn contains(&self, proc: &Proc) -> bool {
+        self.inner.contains_key(proc)
+    }
+
     fn get_process_mut(&mut self, proc: &Proc) -> Option<&mut RegisteredProcess> {
         self.inner.get_mut(proc)
     }
@@ -162,14 +166,19 @@ pub struct Catalog {
 }
 
 impl Catalog {
-    pub fn lookup(&self, proc: &Proc) -> eyre::Result<Option<ProgramSummary>> {
+    /// Tells if the process was registered, processes left out of the catalog can't be spawned.
+    pub fn has(&self, proc: Proc) -> bool {
+        self.registry.contains(&proc)
+    }
+
+    pub fn lookup(&self, proc: &Proc, now: DateTime<Utc>) -> eyre::Result<Option<ProgramSummary>> {
         let registered = self.registry.process(proc)?;
         if let Some(prev) = registered.as_singleton() {
             if let Some(run) = prev.as_ref().and_then(|p| self.monitor.get(p)) {
                 Ok(Some(ProgramSummary {
                     id: run.id,
                     name: format!("{proc:?}"),
-                    started_at: Utc::now(), // TODO - no use for date times.
+                    started_at: now, // TODO - no use for date times.
                 }))
             } else {
                 Ok(None)
@@ -325,7 +334,7 @@ impl Catalog {
         self.registry.clear();
 
         for (_, running) in self.monitor.drain() {
-            running.mailbox.send(Item::Mail(Mail {
+            let _ = running.mailbox.send(Item::Mail(Mail {
                 origin: 0,
                 correlation: Uuid::nil(),
                 context: RequestContext::nil(),
@@ -1,22 +1,24 @@
 use std::time::{Duration, Instant};
 
 use geth_common::ProgramSummary;
+use geth_mikoshi::wal::chunks::Scavenged;
 use tokio::sync::{
-    mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel},
+    mpsc::{Receiver, UnboundedReceiver, UnboundedSender, channel, unbounded_channel},
     oneshot,
 };
 use tracing::instrument;
 use uuid::Uuid;
 
 use crate::{
-    IndexClient, Proc, ReaderClient, RequestContext, WriterClient,
+    IndexClient, Proc, ReaderClient, RequestContext, WriterClient, get_chunk_container,
     process::{
         Item, Mail, ProcId, RunningProc, SpawnResult, Stream,
         manager::{
-            FindParams, ManagerCommand, ProcReadyParams, ProcTerminatedParams, SendParams,
-            ShutdownNotification, ShutdownParams, TimeoutParams, TimeoutTarget, WaitForParams,
+            Delivery, DeliveryFailedParams, FindParams, HasParams, ManagerCommand, ProcReadyParams,
+            ProcTerminatedParams, ScavengeParams, SendParams, ShutdownNotification, ShutdownParams,
+            TimeoutParams, TimeoutTarget, WaitForParams,
         },
-        messages::Messages,
+        messages::{Messages, Responses},
         subscription::SubscriptionClient,
     },
 };
@@ -27,11 +29,14 @@ pub struct ManagerClient {
     origin_proc: Proc,
     inner: UnboundedSender<ManagerCommand>,
     shutdown_notif: ShutdownNotification,
+    /// Messages a streamed response can have waiting for its consumer.
+    stream_capacity: usize,
 }
 
 impl ManagerClient {
     pub(crate) fn new_root_client(
         shutdown_notif: ShutdownNotification,
+        stream_capacity: usize,
     ) -> (Self, UnboundedReceiver<ManagerCommand>) {
         let (sender, queue) = unbounded_channel();
         let client = ManagerClient {
@@ -39,6 +44,7 @@ impl ManagerClient {
             origin_proc: Proc::Root,
             inner: sender,
             shutdown_notif,
+            stream_capacity: stream_capacity.max(1),
         };
 
         (client, queue)
@@ -87,6 +93,18 @@ impl ManagerClient {
         }
     }
 
+    /// Tells if the process is part of the catalog, whether it is running or not.
+    pub async fn has(&self, proc: Proc) -> eyre::Result<bool> {
+        let (resp, receiver) = oneshot::channel();
+
+        self.send_internal(ManagerCommand::Has(HasParams { proc, resp }))?;
+
+        match receiver.await {
+            Ok(has) => Ok(has),
+            Err(_) => eyre::bail!("process manager has shutdown"),
+        }
+    }
+
     pub fn send(
         &self,
         context: RequestContext,
@@ -113,6 +131,7 @@ impl ManagerClient {
                 created: Instant::now(),
             }),
             resp: None,
+            delivery: Delivery::Shed,
         }))
     }
 
@@ -121,6 +140,17 @@ impl ManagerClient {
         context: RequestContext,
         dest: ProcId,
         payload: Messages,
+    ) -> eyre::Result<Option<Mail>> {
+        self.request_opt_with(context, dest, payload, Delivery::Shed)
+            .await
+    }
+
+    async fn request_opt_with(
+        &self,
+        context: RequestContext,
+        dest: ProcId,
+        payload: Messages,
+        delivery: Delivery,
     ) -> eyre::Result<Option<Mail>> {
         let (resp, receiver) = oneshot::channel();
         self.send_internal(ManagerCommand::Send(SendParams {
@@ -133,9 +163,20 @@ impl ManagerClient {
                 created: Instant::now(),
             }),
             resp: Some(resp),
+            delivery,
         }))?;
 
-        Ok(receiver.await.ok())
+        match receiver.await {
+            Ok(mail) if matches!(mail.payload, Messages::Responses(Responses::Timeout)) => {
+                eyre::bail!("request to process {} timed out", dest)
+            }
+
+            Ok(mail) if matches!(mail.payload, Messages::Responses(Responses::Overloaded)) => {
+                eyre::bail!("process {} is overloaded", dest)
+            }
+
+            outcome => Ok(outcome.ok()),
+        }
     }
 
     pub async fn request(
@@ -151,13 +192,31 @@ impl ManagerClient {
         }
     }
 
+    /// Like [`ManagerClient::request`], but waits for room when the mailbox of `dest` is full
+    /// instead of failing right away, see [`Delivery::Backpressure`].
+    pub(crate) async fn request_with_backpressure(
+        &self,
+        context: RequestContext,
+        dest: ProcId,
+        payload: Messages,
+    ) -> eyre::Result<Mail> {
+        if let Some(mail) = self
+            .request_opt_with(context, dest, payload, Delivery::Backpressure)
+            .await?
+        {
+            Ok(mail)
+        } else {
+            eyre::bail!("process {} doesn't exist or is down", dest)
+        }
+    }
+
     pub async fn request_stream(
         &self,
         context: RequestContext,
         dest: ProcId,
         payload: Messages,
-    ) -> eyre::Result<UnboundedReceiver<Messages>> {
-        let (sender, receiver) = unbounded_channel();
+    ) -> eyre::Result<Receiver<Messages>> {
+        let (sender, receiver) = channel(self.stream_capacity);
         self.send_internal(ManagerCommand::Send(SendParams {
             dest,
             item: Item::Stream(Stream {
@@ -167,6 +226,7 @@ impl ManagerClient {
                 sender,
             }),
             resp: None,
+            delivery: Delivery::Shed,
         }))?;
 
         Ok(receiver)
@@ -189,6 +249,7 @@ impl ManagerClient {
                 created: Instant::now(),
             }),
             resp: None,
+            delivery: Delivery::Shed,
         }))
     }
 
@@ -234,7 +295,9 @@ impl ManagerClient {
 
     pub async fn new_index_client(&self) -> eyre::Result<IndexClient> {
         let id = self.wait_for(Proc::Indexing).await?.must_succeed()?;
-        Ok(IndexClient::new(id, self.clone()))
+        let hash_algorithm = get_chunk_container().hash_algorithm()?;
+
+        Ok(IndexClient::new(id, self.clone(), hash_algorithm))
     }
 
     pub async fn new_reader_client(&self) -> eyre::Result<ReaderClient> {
@@ -259,6 +322,14 @@ impl ManagerClient {
         });
     }
 
+    pub(crate) fn report_delivery_failed(&self, dest: ProcId, correlation: Uuid, closed: bool) {
+        let _ = self.send_internal(ManagerCommand::DeliveryFailed(DeliveryFailedParams {
+            dest,
+            correlation,
+            closed,
+        }));
+    }
+
     pub fn send_process_ready(&self, correlation: Uuid, running: RunningProc) {
         let _ = self.send_internal(ManagerCommand::ProcReady(ProcReadyParams {
             correlation,
@@ -279,6 +350,19 @@ impl ManagerClient {
         Ok(())
     }
 
+    /// Drops the events of deleted streams from the completed chunks, see
+    /// [`geth_mikoshi::wal::chunks::scavenge`].
+    pub async fn scavenge(&self) -> eyre::Result<Scavenged> {
+        let (resp, receiver) = oneshot::channel();
+
+        self.send_internal(ManagerCommand::Scavenge(ScavengeParams { resp }))?;
+
+        match receiver.await {
+            Ok(outcome) => outcome,
+            Err(_) => eyre::bail!("process manager has shutdown"),
+        }
+    }
+
     pub async fn manager_exited(self) {
         self.shutdown_notif.wait_for_shutdown().await
     }
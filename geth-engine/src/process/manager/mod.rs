@@ -1,5 +1,5 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeSet, HashMap},
     sync::{
         Arc,
         atomic::{AtomicBool, Ordering},
@@ -8,13 +8,14 @@ use std::{
 };
 
 use geth_common::ProgramSummary;
+use geth_mikoshi::wal::chunks::Scavenged;
 use tokio::sync::{Notify, oneshot};
 use uuid::Uuid;
 
 use crate::{
     Options, Proc, RequestContext,
     process::{
-        Item, Mail, ProcId, RunningProc, SpawnError, SpawnResult,
+        Item, Mail, Mailbox, ProcId, RunningProc, SpawnError, SpawnResult, Undelivered,
         manager::{
             catalog::ProvisionResult,
             proc::process_manager,
@@ -93,10 +94,28 @@ pub(crate) struct FindParams {
     resp: oneshot::Sender<Option<ProgramSummary>>,
 }
 
+pub(crate) struct HasParams {
+    proc: Proc,
+    resp: oneshot::Sender<bool>,
+}
+
+/// What happens to a request sent to a process whose mailbox is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Delivery {
+    /// The request fails right away with [`Responses::Overloaded`].
+    Shed,
+    /// The request waits for room in the mailbox, up to the request timeout, and never times out
+    /// once delivered. Only for requests
+    /// that can't be lost, and never to a process that could be waiting on the sender itself,
+    /// otherwise both would wait for each other until the timeout.
+    Backpressure,
+}
+
 pub(crate) struct SendParams {
     dest: ProcId,
     item: Item,
     resp: Option<oneshot::Sender<Mail>>,
+    delivery: Delivery,
 }
 
 pub(crate) struct WaitForParams {
@@ -114,6 +133,10 @@ pub(crate) struct ShutdownParams {
     resp: oneshot::Sender<()>,
 }
 
+pub(crate) struct ScavengeParams {
+    resp: oneshot::Sender<eyre::Result<Scavenged>>,
+}
+
 pub(crate) enum TimeoutTarget {
     SpawnProcess(ProcId),
     Shutdown,
@@ -129,21 +152,43 @@ pub(crate) struct ProcReadyParams {
     correlation: Uuid,
 }
 
+pub(crate) struct DeliveryFailedParams {
+    dest: ProcId,
+    correlation: Uuid,
+    closed: bool,
+}
+
+#[allow(clippy::large_enum_variant)]
 pub(crate) enum ManagerCommand {
     Find(FindParams),
+    Has(HasParams),
     Send(SendParams),
     WaitFor(WaitForParams),
     ProcTerminated(ProcTerminatedParams),
     ProcReady(ProcReadyParams),
     Shutdown(ShutdownParams),
     Timeout(TimeoutParams),
+    Scavenge(ScavengeParams),
+    DeliveryFailed(DeliveryFailedParams),
+}
+
+/// A request waiting on its reply. Requests only time out here, on the server: clients talk to
+/// the server over gRPC, where each call is its own HTTP/2 stream failed by the transport when the
+/// connection is lost, so no client keeps a map of correlations that could leak. Between
+/// processes though, a reply can be dropped for good, e.g. by a process that went down.
+struct PendingRequest {
+    dest: ProcId,
+    /// `None` for requests that can't be given up on, see [`Delivery::Backpressure`].
+    deadline: Option<Instant>,
+    resp: oneshot::Sender<Mail>,
 }
 
 pub struct Manager {
     options: Arc<Options>,
     client: ManagerClient,
     catalog: Catalog,
-    requests: HashMap<Uuid, oneshot::Sender<Mail>>,
+    requests: HashMap<Uuid, PendingRequest>,
+    deadlines: BTreeSet<(Instant, Uuid)>,
     closing: bool,
     close_resp: Vec<oneshot::Sender<()>>,
     processes_shutting_down: HashMap<u64, Proc>,
@@ -151,15 +196,122 @@ pub struct Manager {
 }
 
 impl Manager {
+    fn track_request(
+        &mut self,
+        dest: ProcId,
+        correlation: Uuid,
+        resp: oneshot::Sender<Mail>,
+        delivery: Delivery,
+    ) {
+        // Failing a request that is already in the log, e.g. the indexing of an append, would
+        // leave the append out of the index while the process could be merely slow.
+        let deadline = match delivery {
+            Delivery::Shed => {
+                Some(Instant::now() + Duration::from_millis(self.options.request_timeout_in_ms))
+            }
+            Delivery::Backpressure => None,
+        };
+
+        if let Some(deadline) = deadline {
+            self.deadlines.insert((deadline, correlation));
+        }
+
+        self.requests.insert(
+            correlation,
+            PendingRequest {
+                dest,
+                deadline,
+                resp,
+            },
+        );
+    }
+
+    fn untrack_request(&mut self, correlation: &Uuid) -> Option<PendingRequest> {
+        let pending = self.requests.remove(correlation)?;
+
+        if let Some(deadline) = pending.deadline {
+            self.deadlines.remove(&(deadline, *correlation));
+        }
+
+        Some(pending)
+    }
+
+    /// When the oldest pending request expires, if any.
+    fn next_deadline(&self) -> Option<Instant> {
+        self.deadlines.first().map(|(deadline, _)| *deadline)
+    }
+
+    /// Fails every pending request whose deadline has passed, so callers are not left waiting on
+    /// a process that will never reply.
+    fn handle_expired_requests(&mut self) {
+        let now = Instant::now();
+
+        while let Some(&(deadline, correlation)) = self.deadlines.first() {
+            if deadline > now {
+                break;
+            }
+
+            self.deadlines.pop_first();
+
+            if let Some(pending) = self.requests.remove(&correlation) {
+                tracing::warn!(proc_id = pending.dest, %correlation, "request timed out");
+
+                let _ = pending.resp.send(Mail {
+                    context: RequestContext::new(),
+                    origin: pending.dest,
+                    correlation,
+                    payload: Messages::Responses(Responses::Timeout),
+                    created: Instant::now(),
+                });
+            }
+        }
+    }
+
     fn handle_find(&mut self, cmd: FindParams) -> eyre::Result<()> {
         if self.closing {
             return Ok(());
         }
 
-        let _ = cmd.resp.send(self.catalog.lookup(&cmd.proc)?);
+        if !self.catalog.has(cmd.proc) {
+            let _ = cmd.resp.send(None);
+            return Ok(());
+        }
+
+        let _ = cmd
+            .resp
+            .send(self.catalog.lookup(&cmd.proc, self.options.clock.now())?);
         Ok(())
     }
 
+    fn handle_has(&mut self, cmd: HasParams) {
+        let _ = cmd.resp.send(self.catalog.has(cmd.proc));
+    }
+
+    /// Scavenging is carried out by the index process, which is the only one knowing which
+    /// entries are still reachable. The manager keeps handling commands in the meantime.
+    fn handle_scavenge(&mut self, cmd: ScavengeParams) {
+        if self.closing {
+            let _ = cmd
+                .resp
+                .send(Err(eyre::eyre!("process manager is shutting down")));
+            return;
+        }
+
+        let client = self.client.clone();
+        tokio::spawn(async move {
+            let outcome = async {
+                client
+                    .new_index_client()
+                    .await?
+                    .scavenge(RequestContext::new())
+                    .await
+            }
+            .await;
+
+            let _ = cmd.resp.send(outcome);
+        });
+    }
+
     fn handle_send(&mut self, cmd: SendParams) -> eyre::Result<()> {
         if self.closing {
             return Ok(());
@@ -167,33 +319,84 @@ impl Manager {
 
         match cmd.item {
             Item::Mail(mail) => {
-                if let Some(resp) = self.requests.remove(&mail.correlation) {
-                    let _ = resp.send(mail);
+                if let Some(pending) = self.untrack_request(&mail.correlation) {
+                    let _ = pending.resp.send(mail);
                 } else if let Some(proc) = self.catalog.get_process_mut(cmd.dest) {
-                    if let Some(resp) = cmd.resp {
-                        self.requests.insert(mail.correlation, resp);
-                        proc.last_received_request = mail.correlation;
-                    } else {
-                        proc.last_received_request = Uuid::nil();
-                    }
-
-                    if !proc.mailbox.send(Item::Mail(mail)) {
-                        self.handle_terminate(ProcTerminatedParams {
-                            id: cmd.dest,
-                            error: None,
-                        });
+                    let context = mail.context;
+                    let correlation = mail.correlation;
+
+                    match proc.mailbox.send(Item::Mail(mail)) {
+                        Err(Undelivered::Full(item))
+                            if cmd.delivery == Delivery::Backpressure && cmd.resp.is_some() =>
+                        {
+                            proc.last_received_request = correlation;
+                            let mailbox = proc.mailbox.clone();
+
+                            if let Some(resp) = cmd.resp {
+                                self.track_request(cmd.dest, correlation, resp, cmd.delivery);
+                            }
+
+                            self.deliver_when_possible(cmd.dest, mailbox, item, correlation);
+                        }
+
+                        Err(Undelivered::Full(_)) => {
+                            tracing::warn!(proc_id = cmd.dest, proc = ?proc.proc, %correlation, "mailbox is full, mail is dropped");
+
+                            if let Some(resp) = cmd.resp {
+                                let _ = resp.send(Mail {
+                                    context,
+                                    origin: cmd.dest,
+                                    correlation,
+                                    payload: Messages::Responses(Responses::Overloaded),
+                                    created: Instant::now(),
+                                });
+                            }
+                        }
+
+                        outcome => {
+                            proc.last_received_request = if cmd.resp.is_some() {
+                                correlation
+                            } else {
+                                Uuid::nil()
+                            };
+
+                            if let Some(resp) = cmd.resp {
+                                self.track_request(cmd.dest, correlation, resp, cmd.delivery);
+                            }
+
+                            if outcome.is_err() {
+                                self.handle_terminate(ProcTerminatedParams {
+                                    id: cmd.dest,
+                                    error: None,
+                                });
+                            }
+                        }
                     }
                 }
             }
 
             Item::Stream(stream) => {
-                if let Some(proc) = self.catalog.get_process(cmd.dest)
-                    && !proc.mailbox.send(Item::Stream(stream))
-                {
-                    self.handle_terminate(ProcTerminatedParams {
-                        id: cmd.dest,
-                        error: None,
-                    });
+                if let Some(proc) = self.catalog.get_process(cmd.dest) {
+                    match proc.mailbox.send(Item::Stream(stream)) {
+                        Ok(()) => {}
+
+                        Err(Undelivered::Full(item)) => {
+                            tracing::warn!(proc_id = cmd.dest, proc = ?proc.proc, "mailbox is full, stream request is dropped");
+
+                            if let Item::Stream(stream) = item {
+                                let _ = stream
+                                    .sender
+                                    .try_send(Messages::Responses(Responses::Overloaded));
+                            }
+                        }
+
+                        Err(Undelivered::Closed) => {
+                            self.handle_terminate(ProcTerminatedParams {
+                                id: cmd.dest,
+                                error: None,
+                            });
+                        }
+                    }
                 }
             }
         }
@@ -201,11 +404,59 @@ impl Manager {
         Ok(())
     }
 
+    /// Waits for room in the mailbox of a lagging process without holding up the manager. The
+    /// request is tracked beforehand, so its reply is routed back even if it comes before this
+    /// task completes.
+    fn deliver_when_possible(&self, dest: ProcId, mailbox: Mailbox, item: Item, correlation: Uuid) {
+        let client = self.client.clone();
+        let timeout = Duration::from_millis(self.options.request_timeout_in_ms);
+
+        tokio::spawn(async move {
+            if let Err(undelivered) = mailbox.send_timeout(item, timeout).await {
+                client.report_delivery_failed(
+                    dest,
+                    correlation,
+                    matches!(undelivered, Undelivered::Closed),
+                );
+            }
+        });
+    }
+
+    fn handle_delivery_failed(&mut self, cmd: DeliveryFailedParams) {
+        let Some(pending) = self.untrack_request(&cmd.correlation) else {
+            return;
+        };
+
+        let payload = if cmd.closed {
+            Responses::FatalError
+        } else {
+            tracing::warn!(proc_id = cmd.dest, correlation = %cmd.correlation, "mailbox stayed full, mail is dropped");
+            Responses::Overloaded
+        };
+
+        let _ = pending.resp.send(Mail {
+            context: RequestContext::new(),
+            origin: cmd.dest,
+            correlation: cmd.correlation,
+            payload: Messages::Responses(payload),
+            created: Instant::now(),
+        });
+    }
+
     fn handle_wait_for(&mut self, cmd: WaitForParams) -> eyre::Result<()> {
         if self.closing {
             return Ok(());
         }
 
+        if !self.catalog.has(cmd.proc) {
+            let _ = cmd.resp.send(SpawnResult::Failure {
+                proc: cmd.proc,
+                error: SpawnError::NotRegistered,
+            });
+
+            return Ok(());
+        }
+
         let provision = match self.catalog.provision_process(cmd.origin, cmd.proc)? {
             ProvisionResult::AlreadyProvisioned(id) => {
                 let _ = cmd.resp.send(SpawnResult::Success(id));
@@ -255,7 +506,7 @@ impl Manager {
                 tracing::info!(id = cmd.id, proc = ?running.proc, closing = self.closing, "process terminated");
             }
 
-            if let Some(resp) = self.requests.remove(&running.last_received_request) {
+            if let Some(pending) = self.untrack_request(&running.last_received_request) {
                 tracing::warn!(
                     id = cmd.id,
                     proc = ?running.proc,
@@ -263,7 +514,7 @@ impl Manager {
                     "process terminated with pending request",
                 );
 
-                let _ = resp.send(Mail {
+                let _ = pending.resp.send(Mail {
                     context: RequestContext::new(),
                     origin: running.id,
                     correlation: running.last_received_request,
@@ -275,13 +526,16 @@ impl Manager {
             for dependent in running.dependents {
                 if let Some(running) = self.catalog.get_process(dependent)
                     && !self.closing
-                    && !running.mailbox.send(Item::Mail(Mail {
-                        context: RequestContext::new(),
-                        origin: 0,
-                        correlation: Uuid::nil(),
-                        payload: Notifications::ProcessTerminated(cmd.id).into(),
-                        created: Instant::now(),
-                    }))
+                    && running
+                        .mailbox
+                        .send(Item::Mail(Mail {
+                            context: RequestContext::new(),
+                            origin: 0,
+                            correlation: Uuid::nil(),
+                            payload: Notifications::ProcessTerminated(cmd.id).into(),
+                            created: Instant::now(),
+                        }))
+                        .is_err()
                 {
                     // I don't want to call `handle_terminate` here because it could end up blowing up the stack.
                     // I could rewrite `handle_terminate` to avoid recursion.
@@ -376,7 +630,8 @@ pub async fn start_process_manager_with_catalog(
     catalog: Catalog,
 ) -> eyre::Result<ManagerClient> {
     let reporter = ShutdownReporter::default();
-    let (client, queue) = ManagerClient::new_root_client(reporter.clone().into());
+    let (client, queue) =
+        ManagerClient::new_root_client(reporter.clone().into(), options.mailbox_capacity);
 
     process_manager(options, client.clone(), catalog, reporter, queue);
 
@@ -21,6 +21,7 @@ pub fn process_manager(
         client,
         catalog,
         requests: Default::default(),
+        deadlines: Default::default(),
         closing: false,
         close_resp: vec![],
         processes_shutting_down: Default::default(),
@@ -28,9 +29,30 @@ pub fn process_manager(
     };
 
     tokio::spawn(async move {
-        while let Some(cmd) = queue.recv().await {
+        loop {
+            let cmd = match manager.next_deadline() {
+                None => queue.recv().await,
+                Some(deadline) => tokio::select! {
+                    cmd = queue.recv() => cmd,
+                    _ = tokio::time::sleep_until(deadline.into()) => {
+                        manager.handle_expired_requests();
+                        continue;
+                    }
+                },
+            };
+
+            let Some(cmd) = cmd else {
+                break;
+            };
+
             let outcome = match cmd {
                 ManagerCommand::Find(cmd) => manager.handle_find(cmd),
+
+                ManagerCommand::Has(cmd) => {
+                    manager.handle_has(cmd);
+                    Ok(())
+                }
+
                 ManagerCommand::Send(cmd) => manager.handle_send(cmd),
                 ManagerCommand::WaitFor(cmd) => manager.handle_wait_for(cmd),
                 ManagerCommand::Shutdown(cmd) => manager.handle_shutdown(cmd),
@@ -49,6 +71,16 @@ pub fn process_manager(
                     manager.handle_proc_ready(cmd);
                     Ok(())
                 }
+
+                ManagerCommand::Scavenge(cmd) => {
+                    manager.handle_scavenge(cmd);
+                    Ok(())
+                }
+
+                ManagerCommand::DeliveryFailed(cmd) => {
+                    manager.handle_delivery_failed(cmd);
+                    Ok(())
+                }
             };
 
             if let Err(error) = outcome {
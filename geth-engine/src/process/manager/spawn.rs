@@ -1,6 +1,6 @@
 use std::{future::Future, sync::Arc, thread, time::Duration};
 
-use tokio::sync::{mpsc::unbounded_channel, oneshot};
+use tokio::sync::{mpsc::channel, oneshot};
 use uuid::Uuid;
 
 #[cfg(test)]
@@ -85,7 +85,8 @@ fn spawn_raw<F>(params: SpawnParams, sender_ready: oneshot::Sender<()>, run: F)
 where
     F: FnOnce(ProcessEnv<Raw>) -> eyre::Result<()> + Send + Sync + 'static,
 {
-    let (proc_sender, proc_queue) = std::sync::mpsc::channel();
+    let (proc_sender, proc_queue) =
+        std::sync::mpsc::sync_channel(params.options.mailbox_capacity.max(1));
     let env = ProcessEnv::new(
         params.process,
         params.client.clone(),
@@ -112,7 +113,7 @@ where
     F: FnOnce(ProcessEnv<Managed>) -> Fut + Send + Sync + 'static,
     Fut: Future<Output = eyre::Result<()>> + Send + 'static,
 {
-    let (proc_sender, proc_queue) = unbounded_channel();
+    let (proc_sender, proc_queue) = channel(params.options.mailbox_capacity.max(1));
     let env = ProcessEnv::new(
         params.process,
         params.client.clone(),
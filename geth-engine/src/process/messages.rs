@@ -1,10 +1,19 @@
 use chrono::{DateTime, Utc};
-use geth_common::{Direction, ExpectedRevision, ProgramStats, ProgramSummary, Propose, Record};
+use geth_common::{
+    Direction, Epochs, ExpectedRevision, IndexMaintained, InvalidEventError, ProgramLimit,
+    ProgramStats, ProgramSummary, Propose, Record, RestartPolicy, Revision, StreamMetadata,
+    StreamPage,
+};
 use geth_domain::index::BlockEntry;
-use geth_mikoshi::wal::LogEntry;
-use tokio::sync::mpsc::UnboundedSender;
+use geth_mikoshi::wal::chunks::Scavenged;
+use geth_mikoshi::wal::{LogCursor, LogEntry};
+use tokio::sync::mpsc::Sender;
+use uuid::Uuid;
 
-use crate::{domain::index::CurrentRevision, process::subscription::ProgramClient};
+use crate::{
+    domain::{catalog::CatalogChange, index::StreamState},
+    process::subscription::ProgramClient,
+};
 
 use super::ProcId;
 
@@ -284,11 +293,28 @@ pub enum IndexRequests {
 
     Store {
         entries: Vec<BlockEntry>,
+        change: Option<CatalogChange>,
+        /// Log position right after the entries, `None` if they weren't read from the log.
+        cursor: Option<LogCursor>,
     },
 
     LatestRevision {
         key: u64,
     },
+
+    ListStreams {
+        prefix: Option<String>,
+        offset: usize,
+        limit: usize,
+    },
+
+    /// Streaming operation as a compaction can outlast the request timeout.
+    Maintain {
+        compact: bool,
+    },
+
+    /// Streaming operation for the same reason as `Maintain`.
+    Scavenge,
 }
 
 #[derive(Debug)]
@@ -298,11 +324,26 @@ pub enum ReadRequests {
         start: u64,
         direction: Direction,
         count: usize,
+        epochs: Epochs,
+    },
+
+    /// Reads the log itself, in position order, across all streams.
+    ReadAll {
+        start: Revision<u64>,
+        direction: Direction,
+        count: usize,
+        /// Subscriptions keep the events of deleted streams, so catching up delivers what they
+        /// would have seen live.
+        skip_deleted: bool,
     },
 
     ReadAt {
         position: u64,
     },
+
+    Metadata {
+        ident: String,
+    },
 }
 
 #[derive(Debug)]
@@ -314,8 +355,15 @@ pub enum SubscribeRequests {
 
 #[derive(Debug)]
 pub enum SubscriptionType {
-    Stream { ident: String },
-    Program { name: String, code: String },
+    Stream {
+        ident: String,
+    },
+    Program {
+        name: String,
+        code: String,
+        logs: bool,
+        restart: RestartPolicy,
+    },
 }
 
 #[derive(Debug)]
@@ -324,11 +372,24 @@ pub enum WriteRequests {
         ident: String,
         expected: ExpectedRevision,
         events: Vec<Propose>,
+        /// Pushes the committed events to the subscriptions.
+        notify_subscribers: bool,
     },
 
     Delete {
         ident: String,
         expected: ExpectedRevision,
+        dry_run: bool,
+    },
+
+    Truncate {
+        ident: String,
+        before: u64,
+    },
+
+    SetMetadata {
+        ident: String,
+        metadata: StreamMetadata,
     },
 }
 
@@ -337,7 +398,9 @@ pub enum ProgramRequests {
     Start {
         name: String,
         code: String,
-        sender: UnboundedSender<Messages>,
+        sender: Sender<Messages>,
+        logs: bool,
+        restart: RestartPolicy,
     },
 
     Stats {
@@ -372,6 +435,8 @@ pub enum Responses {
     TestSink(TestSinkResponses),
     Error(RequestError),
     FatalError,
+    Timeout,
+    Overloaded,
 }
 
 #[derive(Debug)]
@@ -387,18 +452,26 @@ pub enum RequestError {
 #[derive(Debug)]
 pub enum IndexResponses {
     Error,
-    StreamDeleted,
     Entries(Vec<BlockEntry>),
-    CurrentRevision(CurrentRevision),
+    StreamState(StreamState),
     Committed,
+    Streams(StreamPage),
+    Maintained(IndexMaintained),
+    Scavenged(Scavenged),
 }
 
 #[derive(Debug)]
 pub enum ReadResponses {
     Error,
     StreamDeleted,
+    /// First message of a read of a stream that was recreated, carries the first revision of
+    /// each of its epochs.
+    Epochs(Vec<u64>),
     Entries(Vec<LogEntry>),
+    /// Last message of a read, carries the stream's last revision when the read started.
+    EndOfStream(Option<u64>),
     Entry(LogEntry),
+    Metadata(StreamMetadata),
 }
 
 #[derive(Debug)]
@@ -416,7 +489,7 @@ pub enum SubscribeResponses {
 pub struct ProgramProcess {
     pub client: ProgramClient,
     pub name: String,
-    pub sender: UnboundedSender<Messages>,
+    pub sender: Sender<Messages>,
     pub started_at: DateTime<Utc>,
 }
 
@@ -429,16 +502,30 @@ pub enum SubscribeInternal {
 pub enum WriteResponses {
     Error,
     StreamDeleted,
+    StreamNotFound,
 
     WrongExpectedRevision {
         expected: ExpectedRevision,
         current: ExpectedRevision,
     },
 
+    /// A dry run found the request would succeed, the stream is at the `current` revision.
+    DryRun {
+        current: ExpectedRevision,
+    },
+
+    /// The append was rejected because one of its events failed schema validation.
+    InvalidEvent(InvalidEventError),
+
+    /// Some of the events were already appended, but not as the whole last append of the stream.
+    DuplicateEvents,
+
     Committed {
         start_position: u64,
         next_position: u64,
         next_expected_version: ExpectedRevision,
+        event_ids: Vec<Uuid>,
+        created: DateTime<Utc>,
     },
 
     WritePosition(u64),
@@ -460,6 +547,10 @@ pub enum ProgramResponses {
     Error(eyre::Report),
     Started,
     Stopped,
+    /// A line the program printed, only sent when the subscriber asked for logs.
+    Log(String),
+    /// The program was stopped for going over one of its limits.
+    LimitExceeded(ProgramLimit),
 }
 
 #[derive(Debug)]
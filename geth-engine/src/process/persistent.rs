@@ -0,0 +1,415 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use geth_common::{
+    AppendStreamCompleted, ContentType, Direction, ExpectedRevision, Propose, ReadStreamCompleted,
+    Record, Revision, SubscriptionConfirmation, SubscriptionEvent, UnsubscribeReason,
+};
+use tokio::select;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender, unbounded_channel};
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+use crate::names::{streams, types};
+use crate::process::RequestContext;
+use crate::process::consumer::{ConsumerResult, start_consumer};
+use crate::process::reading::ReaderClient;
+use crate::process::writing::WriterClient;
+
+/// How many records a group hands out without them being acknowledged before it stops reading.
+const MAX_IN_FLIGHT: usize = 500;
+
+/// Persistent subscriptions with at least one member connected to this node, by the stream
+/// holding their checkpoints.
+#[derive(Clone, Default)]
+pub struct PersistentGroups {
+    inner: Arc<Mutex<HashMap<String, UnboundedSender<Command>>>>,
+}
+
+enum Command {
+    Join(Uuid, UnboundedSender<SubscriptionEvent>),
+    Leave(Uuid),
+    /// Replies once the checkpoint is stored.
+    Ack(u64, oneshot::Sender<()>),
+}
+
+impl PersistentGroups {
+    /// Joins the persistent subscription whose checkpoints are stored in `checkpoints`, starting
+    /// it when no member is connected yet.
+    pub fn join(
+        &self,
+        checkpoints: String,
+        stream_name: String,
+        reader: ReaderClient,
+        writer: WriterClient,
+    ) -> Member {
+        let (sender, events) = unbounded_channel();
+        let id = Uuid::new_v4();
+        let mut groups = self.inner.lock().unwrap();
+
+        // Joins are sent while holding the lock, so a group can't stop with a join pending.
+        let commands = match groups.get(&checkpoints) {
+            Some(commands) if !commands.is_closed() => commands.clone(),
+            _ => {
+                let (commands, receiver) = unbounded_channel();
+                let group = Group::new(checkpoints.clone(), stream_name, writer);
+
+                tokio::spawn(group.run(self.clone(), reader, receiver));
+                groups.insert(checkpoints, commands.clone());
+
+                commands
+            }
+        };
+
+        let _ = commands.send(Command::Join(id, sender));
+
+        Member {
+            id,
+            commands,
+            events,
+        }
+    }
+
+    /// Returns `false` if no member of the subscription is connected.
+    pub async fn ack(&self, checkpoints: &str, position: u64) -> bool {
+        let (sender, stored) = oneshot::channel();
+        let sent = self
+            .inner
+            .lock()
+            .unwrap()
+            .get(checkpoints)
+            .is_some_and(|commands| commands.send(Command::Ack(position, sender)).is_ok());
+
+        sent && stored.await.is_ok()
+    }
+}
+
+/// Member of a persistent subscription, leaves it when dropped.
+pub struct Member {
+    id: Uuid,
+    commands: UnboundedSender<Command>,
+    events: UnboundedReceiver<SubscriptionEvent>,
+}
+
+impl Member {
+    /// `None` when the subscription stopped.
+    pub async fn next(&mut self) -> Option<SubscriptionEvent> {
+        self.events.recv().await
+    }
+}
+
+impl Drop for Member {
+    fn drop(&mut self) {
+        let _ = self.commands.send(Command::Leave(self.id));
+    }
+}
+
+struct Group {
+    context: RequestContext,
+    checkpoints: String,
+    stream_name: String,
+    writer: WriterClient,
+    members: Vec<(Uuid, UnboundedSender<SubscriptionEvent>)>,
+    /// Member the next record goes to.
+    turn: usize,
+    /// Records handed to a member and not acknowledged yet, by revision, or log position when
+    /// subscribed to `$all`.
+    in_flight: BTreeMap<u64, (Uuid, Record)>,
+    /// Records of members that left before acknowledging them, handed out before reading more.
+    pending: BTreeMap<u64, Record>,
+    /// Position of the next record to read.
+    next: u64,
+    /// Stored position the subscription resumes from. Everything before it was acknowledged.
+    checkpoint: u64,
+}
+
+impl Group {
+    fn new(checkpoints: String, stream_name: String, writer: WriterClient) -> Self {
+        Self {
+            context: RequestContext::new(),
+            checkpoints,
+            stream_name,
+            writer,
+            members: Vec::new(),
+            turn: 0,
+            in_flight: BTreeMap::new(),
+            pending: BTreeMap::new(),
+            next: 0,
+            checkpoint: 0,
+        }
+    }
+
+    async fn run(
+        mut self,
+        groups: PersistentGroups,
+        reader: ReaderClient,
+        mut commands: UnboundedReceiver<Command>,
+    ) {
+        let Err(e) = self.serve(&groups, &reader, &mut commands).await else {
+            return;
+        };
+
+        tracing::error!(
+            checkpoints = self.checkpoints,
+            "persistent subscription stopped: {}",
+            e
+        );
+
+        commands.close();
+
+        {
+            let mut groups = groups.inner.lock().unwrap();
+            if groups
+                .get(&self.checkpoints)
+                .is_some_and(UnboundedSender::is_closed)
+            {
+                groups.remove(&self.checkpoints);
+            }
+        }
+
+        let unsubscribed = || SubscriptionEvent::Unsubscribed(UnsubscribeReason::Server);
+
+        for (_, member) in self.members.drain(..) {
+            let _ = member.send(unsubscribed());
+        }
+
+        while let Ok(command) = commands.try_recv() {
+            if let Command::Join(_, member) = command {
+                let _ = member.send(unsubscribed());
+            }
+        }
+    }
+
+    /// Returns once the last member left.
+    async fn serve(
+        &mut self,
+        groups: &PersistentGroups,
+        reader: &ReaderClient,
+        commands: &mut UnboundedReceiver<Command>,
+    ) -> eyre::Result<()> {
+        self.checkpoint = read_checkpoint(reader, self.context, &self.checkpoints).await?;
+        self.next = self.checkpoint;
+
+        let mut consumer = match start_consumer(
+            self.context,
+            self.stream_name.clone(),
+            Revision::Revision(self.checkpoint),
+            reader.manager(),
+        )
+        .await?
+        {
+            ConsumerResult::Success(consumer) => consumer,
+            ConsumerResult::StreamDeleted => {
+                eyre::bail!("stream '{}' is deleted", self.stream_name)
+            }
+        };
+
+        // The consumer can't be interrupted halfway through `next`, so it runs in its own task.
+        let (sender, mut outcomes) = mpsc::channel(MAX_IN_FLIGHT);
+        let reading = tokio::spawn(async move {
+            while let Some(outcome) = consumer.next().await.transpose() {
+                let failed = outcome.is_err();
+
+                if sender.send(outcome).await.is_err() || failed {
+                    break;
+                }
+            }
+        });
+
+        let result = loop {
+            while !self.members.is_empty()
+                && let Some((_, record)) = self.pending.pop_first()
+            {
+                self.dispatch(record);
+            }
+
+            if self.members.is_empty() && self.stop(groups, commands) {
+                break Ok(());
+            }
+
+            let can_read = !self.members.is_empty()
+                && self.pending.is_empty()
+                && self.in_flight.len() < MAX_IN_FLIGHT;
+
+            select! {
+                command = commands.recv() => match command {
+                    None => break Ok(()),
+
+                    Some(Command::Join(id, member)) => {
+                        let confirmation = SubscriptionConfirmation::StreamName {
+                            stream_name: self.stream_name.clone(),
+                            start_revision: None,
+                        };
+
+                        if member.send(SubscriptionEvent::Confirmed(confirmation)).is_ok() {
+                            self.members.push((id, member));
+                        }
+                    }
+
+                    Some(Command::Leave(id)) => self.leave(id),
+
+                    Some(Command::Ack(position, stored)) => {
+                        if self.in_flight.remove(&position).is_some()
+                            && let Err(e) = self.save_checkpoint().await
+                        {
+                            break Err(e);
+                        }
+
+                        let _ = stored.send(());
+                    }
+                },
+
+                outcome = outcomes.recv(), if can_read => match outcome {
+                    Some(Ok(SubscriptionEvent::EventAppeared(record))) => {
+                        self.next = self.position_of(&record) + 1;
+                        self.dispatch(record);
+                    }
+
+                    Some(Ok(SubscriptionEvent::Unsubscribed(_))) | None => {
+                        break Err(eyre::eyre!("subscription to '{}' ended", self.stream_name));
+                    }
+
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => break Err(e),
+                },
+            }
+        };
+
+        reading.abort();
+        result
+    }
+
+    /// Stops the subscription unless a command is waiting, a member joining for instance.
+    fn stop(&self, groups: &PersistentGroups, commands: &mut UnboundedReceiver<Command>) -> bool {
+        let mut groups = groups.inner.lock().unwrap();
+
+        if !commands.is_empty() {
+            return false;
+        }
+
+        commands.close();
+        groups.remove(&self.checkpoints);
+
+        true
+    }
+
+    fn position_of(&self, record: &Record) -> u64 {
+        if self.stream_name == streams::ALL {
+            record.position
+        } else {
+            record.revision
+        }
+    }
+
+    /// Hands `record` to the next member in line. Kept for later if every member is gone.
+    fn dispatch(&mut self, record: Record) {
+        let position = self.position_of(&record);
+
+        while !self.members.is_empty() {
+            let (id, member) = &self.members[self.turn % self.members.len()];
+            let id = *id;
+
+            self.turn = self.turn.wrapping_add(1);
+
+            if member
+                .send(SubscriptionEvent::EventAppeared(record.clone()))
+                .is_ok()
+            {
+                self.in_flight.insert(position, (id, record));
+                return;
+            }
+
+            self.leave(id);
+        }
+
+        self.pending.insert(position, record);
+    }
+
+    /// The records `id` didn't acknowledge go to the remaining members.
+    fn leave(&mut self, id: Uuid) {
+        self.members.retain(|(member, _)| *member != id);
+
+        let abandoned = self
+            .in_flight
+            .iter()
+            .filter(|(_, (member, _))| *member == id)
+            .map(|(position, _)| *position)
+            .collect::<Vec<_>>();
+
+        for position in abandoned {
+            if let Some((_, record)) = self.in_flight.remove(&position) {
+                self.pending.insert(position, record);
+            }
+        }
+    }
+
+    /// Moves the checkpoint to the first record not acknowledged yet.
+    async fn save_checkpoint(&mut self) -> eyre::Result<()> {
+        let checkpoint = self
+            .in_flight
+            .keys()
+            .next()
+            .into_iter()
+            .chain(self.pending.keys().next())
+            .min()
+            .copied()
+            .unwrap_or(self.next);
+
+        if checkpoint <= self.checkpoint {
+            return Ok(());
+        }
+
+        let completed = self
+            .writer
+            .append_without_notification(
+                self.context,
+                self.checkpoints.clone(),
+                ExpectedRevision::Any,
+                vec![Propose {
+                    id: Uuid::new_v4(),
+                    content_type: ContentType::Binary,
+                    class: types::CHECKPOINT.to_string(),
+                    data: Bytes::copy_from_slice(&checkpoint.to_le_bytes()),
+                    metadata: Bytes::new(),
+                }],
+            )
+            .await?;
+
+        if let AppendStreamCompleted::Error(e) = completed {
+            eyre::bail!("checkpoint not stored in '{}': {}", self.checkpoints, e);
+        }
+
+        self.checkpoint = checkpoint;
+
+        Ok(())
+    }
+}
+
+/// Latest checkpoint of a persistent subscription, 0 when it never stored one.
+async fn read_checkpoint(
+    reader: &ReaderClient,
+    context: RequestContext,
+    checkpoints: &str,
+) -> eyre::Result<u64> {
+    let ReadStreamCompleted::Success(mut stream) = reader
+        .read(context, checkpoints, Revision::End, Direction::Backward, 1)
+        .await?
+    else {
+        return Ok(0);
+    };
+
+    let Some(record) = stream.next().await? else {
+        return Ok(0);
+    };
+
+    let checkpoint = record.data.as_ref().try_into().map_err(|_| {
+        eyre::eyre!(
+            "malformed checkpoint in '{}' at revision {}",
+            checkpoints,
+            record.revision
+        )
+    })?;
+
+    Ok(u64::from_le_bytes(checkpoint))
+}
@@ -1,10 +1,5 @@
-use std::collections::{HashMap, HashSet};
-
 use geth_common::{Direction, Revision};
-use geth_eventql::{
-    ContextFrame, Expr, ExprVisitor, Literal, NodeAttributes, Operation, Query, QueryVisitor,
-    Subject, Value,
-};
+use geth_eventql::{ReadPlan, ReadTarget, SourceRead};
 
 use crate::{
     RequestContext,
@@ -23,32 +18,39 @@ pub async fn run(mut env: ProcessEnv<Managed>) -> eyre::Result<()> {
             let infered = match geth_eventql::parse_rename_and_infer(&query) {
                 Ok(q) => q,
                 Err(e) => {
-                    let _ = stream.sender.send(QueryResponses::Error(e.into()).into());
+                    let _ = stream
+                        .sender
+                        .send(QueryResponses::Error(e.into()).into())
+                        .await;
                     continue;
                 }
             };
 
-            let reqs = collect_requirements(infered.query());
+            let plan = geth_eventql::plan(infered.query());
             let reader = env.client.new_reader_client().await?;
-            // let mut sources = HashMap::with_capacity(reqs.subjects.len());
             let ctx = RequestContext::new();
 
-            for (_, subjects) in reqs.subjects.iter() {
-                for subject in subjects.iter() {
-                    // TODO - need to support true subject instead of relaying on stream name.
-                    let stream_name = subject.to_string();
-                    let _stream = reader
-                        .read(
-                            ctx,
-                            &stream_name,
-                            Revision::Start,
-                            Direction::Forward,
-                            usize::MAX,
-                        )
-                        .await?;
-
-                    // TODO need to load them stream readers in a better way for all the sources.
-                }
+            for source in subject_reads(&plan) {
+                let Some(subject) = source.target.as_subject() else {
+                    continue;
+                };
+
+                let (start, count) = match source.range {
+                    None => (Revision::Start, usize::MAX),
+                    Some(range) => (
+                        Revision::Revision(range.start),
+                        range.max_count().map_or(usize::MAX, |c| c as usize),
+                    ),
+                };
+
+                // TODO - need to support true subject instead of relaying on stream name.
+                let stream_name = subject.to_string();
+                let _stream = reader
+                    .read(ctx, &stream_name, start, Direction::Forward, count)
+                    .await?;
+
+                // TODO need to load them stream readers in a better way for all the sources.
+                // TODO - filter on the pushed down class once the reader supports it.
             }
         }
     }
@@ -56,96 +58,20 @@ pub async fn run(mut env: ProcessEnv<Managed>) -> eyre::Result<()> {
     Ok(())
 }
 
-struct Requirements {
-    subjects: HashMap<Binding, HashSet<Subject>>,
-}
-
-fn collect_requirements(query: &Query) -> Requirements {
-    let mut collect_reqs = CollectRequirements::default();
-    query.dfs_post_order(&mut collect_reqs);
-
-    Requirements {
-        subjects: collect_reqs.subjects,
-    }
-}
-
-#[derive(Hash, PartialEq, Eq)]
-struct Binding {
-    scope: u64,
-    ident: String,
-}
-
-#[derive(Default)]
-struct CollectRequirements {
-    context: ContextFrame,
-    subjects: HashMap<Binding, HashSet<Subject>>,
-}
-
-impl QueryVisitor for CollectRequirements {
-    type Inner<'a> = CollectRequirementsFromExpr<'a>;
-
-    fn enter_where_clause(&mut self, _attrs: &NodeAttributes, _expr: &Expr) {
-        self.context = ContextFrame::Where;
-    }
-
-    fn exit_where_clause(&mut self, _attrs: &NodeAttributes, _expr: &Expr) {
-        self.context = ContextFrame::Unspecified;
-    }
-
-    fn on_source_subject(&mut self, attrs: &NodeAttributes, ident: &str, subject: &Subject) {
-        let binding = Binding {
-            scope: attrs.scope,
-            ident: ident.to_string(),
-        };
-
-        self.subjects
-            .entry(binding)
-            .or_default()
-            .insert(subject.clone());
-    }
-
-    fn expr_visitor<'a>(&'a mut self) -> Self::Inner<'a> {
-        CollectRequirementsFromExpr { inner: self }
-    }
-}
-
-struct CollectRequirementsFromExpr<'a> {
-    inner: &'a mut CollectRequirements,
-}
-
-impl ExprVisitor for CollectRequirementsFromExpr<'_> {
-    fn exit_binary_op(&mut self, attrs: &NodeAttributes, op: &Operation, lhs: &Expr, rhs: &Expr) {
-        if self.inner.context != ContextFrame::Where {
-            return;
-        }
-
-        match (&lhs.value, &rhs.value) {
-            (Value::Var(_), Value::Var(_)) => {
-                // TODO - a possible optimization could be to check if two variables are looking at the same subject.
-                // if they do then we can merge the request into a singular read operation. Current thought of the matter: the way we deal
-                // with binding to subjects needs to be extended to either we have a direct binding
-                //  (a.k.a an explict x.subject = "/foo/bar" or FROM x IN "/foo/bar") and an indirect where
-                // we have something like x.subject ==  y.subject. By doing that we can simplify a convuloted requests like
-                // those to a much direct one.
+/// Sources of the query and its subqueries that read a single subject.
+fn subject_reads<'a>(plan: &'a ReadPlan<'a>) -> Vec<&'a SourceRead<'a>> {
+    let mut reads = Vec::new();
+    let mut plans = vec![plan];
+
+    while let Some(plan) = plans.pop() {
+        for source in &plan.sources {
+            match &source.target {
+                ReadTarget::Subject(_) => reads.push(source),
+                ReadTarget::Subquery(plan) => plans.push(plan),
+                ReadTarget::Scan => {}
             }
-
-            (Value::Var(x), Value::Literal(Literal::Subject(sub)))
-            | (Value::Literal(Literal::Subject(sub)), Value::Var(x)) => {
-                if x.path.as_slice() == ["subject"] && op == &Operation::Equal {
-                    let binding = Binding {
-                        scope: attrs.scope,
-                        ident: x.name.clone(),
-                    };
-
-                    self.inner
-                        .subjects
-                        .entry(binding)
-                        .or_default()
-                        .insert(sub.clone());
-                }
-            }
-
-            _ => {}
         }
     }
+
+    reads
 }
@@ -1,29 +1,65 @@
+use crate::domain::index::epoch_of;
+use crate::names::namespaces;
 use crate::process::messages::{Messages, ReadRequests, ReadResponses};
-use crate::process::reading::record_try_from;
+use crate::process::reading::{FieldSelector, ProjectedStreaming, record_try_from};
 use crate::process::{Managed, ManagerClient, Proc, ProcId, ProcessEnv, RequestContext};
-use geth_common::{Direction, ReadStreamCompleted, Record, Revision};
+use geth_common::{
+    Direction, Epochs, LinkTo, ReadStream, ReadStreamCompleted, Record, RecordLink, Revision,
+    StreamMetadata,
+};
 use geth_mikoshi::wal::LogEntry;
+use std::sync::Arc;
 use std::vec;
-use tokio::sync::mpsc::{self, UnboundedReceiver};
+use tokio::sync::mpsc::{self, Receiver};
 use tracing::instrument;
 
 pub struct Streaming {
-    inner: UnboundedReceiver<Messages>,
+    inner: Receiver<Messages>,
     batch: Option<vec::IntoIter<LogEntry>>,
+    tail_revision: Option<u64>,
+    links: Option<LinkResolver>,
+    /// First revision of each epoch of the stream, empty if it was never recreated.
+    epoch_starts: Vec<u64>,
 }
 
 impl Streaming {
     pub fn empty() -> Self {
         Self {
-            inner: mpsc::unbounded_channel().1,
+            inner: mpsc::channel(1).1,
             batch: None,
+            tail_revision: None,
+            links: None,
+            epoch_starts: Vec::new(),
         }
     }
 
+    /// Leaves the links pointing to a stream `allowed` refuses unresolved, as if the events they
+    /// point to were no longer readable. Only matters to reads resolving links.
+    pub fn restrict_links(&mut self, allowed: impl Fn(&str) -> bool + Send + Sync + 'static) {
+        if let Some(links) = &mut self.links {
+            links.allowed = Some(Arc::new(allowed));
+        }
+    }
+
+    /// Last revision of the stream when the read started, `None` if the stream was empty. Only
+    /// known once [`Streaming::next`] returned `None`.
+    pub fn tail_revision(&self) -> Option<u64> {
+        self.tail_revision
+    }
+
     pub async fn next(&mut self) -> eyre::Result<Option<Record>> {
         loop {
             if let Some(entry) = self.batch.as_mut().and_then(Iterator::next) {
-                return Ok(Some(record_try_from(entry)?));
+                let mut record = record_try_from(entry)?;
+                record.epoch = epoch_of(&self.epoch_starts, record.revision);
+
+                if let Some(links) = &self.links
+                    && record.class == LinkTo::CLASS
+                {
+                    return Ok(Some(links.resolve(record).await?));
+                }
+
+                return Ok(Some(record));
             }
 
             self.batch = None;
@@ -38,6 +74,11 @@ impl Streaming {
                         continue;
                     }
 
+                    ReadResponses::EndOfStream(tail_revision) => {
+                        self.tail_revision = tail_revision;
+                        return Ok(None);
+                    }
+
                     _ => {
                         eyre::bail!("unexpected message when streaming from the reader process");
                     }
@@ -49,6 +90,76 @@ impl Streaming {
     }
 }
 
+type LinkFilter = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// Follows the link events met by a [`Streaming`].
+struct LinkResolver {
+    client: ReaderClient,
+    context: RequestContext,
+    /// Streams links may be followed to, any when `None`.
+    allowed: Option<LinkFilter>,
+}
+
+impl LinkResolver {
+    /// Returns the event a link points to, or the link itself when that event is no longer
+    /// readable.
+    async fn resolve(&self, link: Record) -> eyre::Result<Record> {
+        let Ok(target) = link.as_value::<LinkTo>() else {
+            tracing::warn!(
+                stream = link.stream_name,
+                revision = link.revision,
+                "malformed link event"
+            );
+
+            return Ok(link);
+        };
+
+        // Link targets are named as seen from the namespace of the link.
+        let target_stream = match namespaces::namespace_of(&link.stream_name) {
+            Some(namespace) => namespaces::scope(namespace, &target.target_stream),
+            None if namespaces::namespace_of(&target.target_stream).is_some() => return Ok(link),
+            None => target.target_stream,
+        };
+
+        if let Some(allowed) = &self.allowed
+            && !allowed(&target_stream)
+        {
+            return Ok(link);
+        }
+
+        let mut streaming = match self
+            .client
+            .read(
+                self.context,
+                &target_stream,
+                Revision::Revision(target.target_revision),
+                Direction::Forward,
+                1,
+            )
+            .await?
+        {
+            ReadStreamCompleted::StreamDeleted => return Ok(link),
+            ReadStreamCompleted::Success(streaming) => streaming,
+        };
+
+        // That read doesn't resolve links, so this doesn't recurse any further.
+        match Box::pin(streaming.next()).await? {
+            // A truncated target makes the read start past the requested revision.
+            Some(mut record) if record.revision == target.target_revision => {
+                record.link = Some(RecordLink {
+                    stream_name: link.stream_name,
+                    revision: link.revision,
+                    position: link.position,
+                });
+
+                Ok(record)
+            }
+
+            _ => Ok(link),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct ReaderClient {
     target: ProcId,
@@ -72,7 +183,6 @@ impl ReaderClient {
         self.inner.clone()
     }
 
-    #[instrument(skip(self, context), fields(correlation = %context.correlation))]
     pub async fn read(
         &self,
         context: RequestContext,
@@ -81,38 +191,158 @@ impl ReaderClient {
         direction: Direction,
         count: usize,
     ) -> eyre::Result<ReadStreamCompleted<Streaming>> {
-        let mut mailbox = self
+        self.read_stream(
+            context,
+            ReadStream {
+                stream_name: stream_name.to_string(),
+                direction,
+                revision: start,
+                max_count: Some(count as u64),
+                resolve_links: false,
+                epochs: Epochs::Current,
+            },
+        )
+        .await
+    }
+
+    /// Same as [`ReaderClient::read`] but link events are replaced by the events they point to,
+    /// see [`LinkTo`].
+    pub async fn read_resolving_links(
+        &self,
+        context: RequestContext,
+        stream_name: &str,
+        start: Revision<u64>,
+        direction: Direction,
+        count: usize,
+    ) -> eyre::Result<ReadStreamCompleted<Streaming>> {
+        self.read_stream(
+            context,
+            ReadStream {
+                stream_name: stream_name.to_string(),
+                direction,
+                revision: start,
+                max_count: Some(count as u64),
+                resolve_links: true,
+                epochs: Epochs::Current,
+            },
+        )
+        .await
+    }
+
+    /// Reads a stream with every setting a client can ask for.
+    #[instrument(skip(self, context), fields(correlation = %context.correlation))]
+    pub async fn read_stream(
+        &self,
+        context: RequestContext,
+        params: ReadStream,
+    ) -> eyre::Result<ReadStreamCompleted<Streaming>> {
+        let mailbox = self
             .inner
             .request_stream(
                 context,
                 self.target,
                 ReadRequests::Read {
-                    ident: stream_name.to_string(),
-                    start: start.raw(),
+                    ident: params.stream_name,
+                    start: params.revision.raw(),
+                    direction: params.direction,
+                    count: params
+                        .max_count
+                        .map_or(usize::MAX, |c| usize::try_from(c).unwrap_or(usize::MAX)),
+                    epochs: params.epochs,
+                }
+                .into(),
+            )
+            .await?;
+
+        let mut outcome = open_streaming(mailbox).await?;
+
+        if params.resolve_links
+            && let ReadStreamCompleted::Success(streaming) = &mut outcome
+        {
+            streaming.links = Some(LinkResolver {
+                client: self.clone(),
+                context,
+                allowed: None,
+            });
+        }
+
+        Ok(outcome)
+    }
+
+    /// Reads the log in position order, across all streams, starting at log position `start`,
+    /// which doesn't have to be the position of an event.
+    #[instrument(skip(self, context), fields(correlation = %context.correlation))]
+    pub async fn read_all(
+        &self,
+        context: RequestContext,
+        start: Revision<u64>,
+        direction: Direction,
+        count: usize,
+        skip_deleted: bool,
+    ) -> eyre::Result<Streaming> {
+        let mailbox = self
+            .inner
+            .request_stream(
+                context,
+                self.target,
+                ReadRequests::ReadAll {
+                    start,
                     direction,
                     count,
+                    skip_deleted,
                 }
                 .into(),
             )
             .await?;
 
-        if let Some(resp) = mailbox.recv().await
-            && let Ok(resp) = resp.try_into()
+        match open_streaming(mailbox).await? {
+            ReadStreamCompleted::Success(streaming) => Ok(streaming),
+            ReadStreamCompleted::StreamDeleted => {
+                eyre::bail!("protocol error when communicating with the reader process")
+            }
+        }
+    }
+
+    #[instrument(skip(self, context, selectors), fields(correlation = %context.correlation))]
+    pub async fn read_projection(
+        &self,
+        context: RequestContext,
+        stream_name: &str,
+        start: Revision<u64>,
+        direction: Direction,
+        count: usize,
+        selectors: Vec<FieldSelector>,
+    ) -> eyre::Result<ReadStreamCompleted<ProjectedStreaming>> {
+        match self
+            .read(context, stream_name, start, direction, count)
+            .await?
         {
+            ReadStreamCompleted::StreamDeleted => Ok(ReadStreamCompleted::StreamDeleted),
+            ReadStreamCompleted::Success(streaming) => Ok(ReadStreamCompleted::Success(
+                ProjectedStreaming::new(streaming, selectors),
+            )),
+        }
+    }
+
+    #[instrument(skip(self, context), fields(correlation = %context.correlation))]
+    pub async fn read_at(&self, context: RequestContext, position: u64) -> eyre::Result<LogEntry> {
+        let resp = self
+            .inner
+            .request(
+                context,
+                self.target,
+                ReadRequests::ReadAt { position }.into(),
+            )
+            .await?;
+
+        if let Ok(resp) = resp.payload.try_into() {
             match resp {
                 ReadResponses::Error => {
-                    eyre::bail!("internal error when running a read request to the reader process");
-                }
-
-                ReadResponses::StreamDeleted => {
-                    return Ok(ReadStreamCompleted::StreamDeleted);
+                    eyre::bail!("unexpected error when reading from the reader process");
                 }
 
-                ReadResponses::Entries(entries) => {
-                    return Ok(ReadStreamCompleted::Success(Streaming {
-                        inner: mailbox,
-                        batch: Some(entries.into_iter()),
-                    }));
+                ReadResponses::Entry(entry) => {
+                    return Ok(entry);
                 }
 
                 _ => {
@@ -121,28 +351,39 @@ impl ReaderClient {
             }
         }
 
-        eyre::bail!("reader process is no longer running")
+        eyre::bail!("unexpected response from the reader process")
     }
 
     #[instrument(skip(self, context), fields(correlation = %context.correlation))]
-    pub async fn read_at(&self, context: RequestContext, position: u64) -> eyre::Result<LogEntry> {
+    pub async fn metadata(
+        &self,
+        context: RequestContext,
+        stream_name: &str,
+    ) -> eyre::Result<ReadStreamCompleted<StreamMetadata>> {
         let resp = self
             .inner
             .request(
                 context,
                 self.target,
-                ReadRequests::ReadAt { position }.into(),
+                ReadRequests::Metadata {
+                    ident: stream_name.to_string(),
+                }
+                .into(),
             )
             .await?;
 
         if let Ok(resp) = resp.payload.try_into() {
             match resp {
                 ReadResponses::Error => {
-                    eyre::bail!("unexpected error when reading from the reader process");
+                    eyre::bail!("unexpected error when reading stream metadata");
                 }
 
-                ReadResponses::Entry(entry) => {
-                    return Ok(entry);
+                ReadResponses::StreamDeleted => {
+                    return Ok(ReadStreamCompleted::StreamDeleted);
+                }
+
+                ReadResponses::Metadata(metadata) => {
+                    return Ok(ReadStreamCompleted::Success(metadata));
                 }
 
                 _ => {
@@ -154,3 +395,54 @@ impl ReaderClient {
         eyre::bail!("unexpected response from the reader process")
     }
 }
+
+async fn open_streaming(
+    mut mailbox: Receiver<Messages>,
+) -> eyre::Result<ReadStreamCompleted<Streaming>> {
+    let mut epoch_starts = Vec::new();
+
+    while let Some(resp) = mailbox.recv().await
+        && let Ok(resp) = resp.try_into()
+    {
+        match resp {
+            ReadResponses::Epochs(starts) => {
+                epoch_starts = starts;
+                continue;
+            }
+
+            ReadResponses::Error => {
+                eyre::bail!("internal error when running a read request to the reader process");
+            }
+
+            ReadResponses::StreamDeleted => {
+                return Ok(ReadStreamCompleted::StreamDeleted);
+            }
+
+            ReadResponses::Entries(entries) => {
+                return Ok(ReadStreamCompleted::Success(Streaming {
+                    inner: mailbox,
+                    batch: Some(entries.into_iter()),
+                    tail_revision: None,
+                    links: None,
+                    epoch_starts,
+                }));
+            }
+
+            ReadResponses::EndOfStream(tail_revision) => {
+                return Ok(ReadStreamCompleted::Success(Streaming {
+                    inner: mailbox,
+                    batch: None,
+                    tail_revision,
+                    links: None,
+                    epoch_starts,
+                }));
+            }
+
+            _ => {
+                eyre::bail!("protocol error when communicating with the reader process");
+            }
+        }
+    }
+
+    eyre::bail!("reader process is no longer running")
+}
@@ -1,26 +1,150 @@
 mod client;
 mod proc;
+mod projection;
 
-use bytes::Buf;
+use bytes::{Buf, Bytes};
+use chrono::{DateTime, Utc};
 pub use client::{ReaderClient, Streaming};
+use eyre::WrapErr;
 use geth_common::{ContentType, Record};
+use geth_mikoshi::compression::Compression;
 use geth_mikoshi::wal::LogEntry;
 pub use proc::run;
+pub use projection::{FieldSelector, ProjectedStreaming};
 use uuid::Uuid;
 
-pub fn record_try_from(mut entry: LogEntry) -> eyre::Result<Record> {
-    let revision = entry.payload.get_u64_le();
-    let stream_name_len = entry.payload.get_u16_le() as usize;
-    let stream_name = unsafe {
-        String::from_utf8_unchecked(entry.payload.copy_to_bytes(stream_name_len).to_vec())
+pub fn record_try_from(entry: LogEntry) -> eyre::Result<Record> {
+    let position = entry.position;
+
+    parse_record(entry).wrap_err_with(|| format!("malformed record at log position {position}"))
+}
+
+/// Stream name and revision of the record stored in a log entry, without decoding the rest.
+pub fn record_header(entry: &LogEntry) -> eyre::Result<(String, u64)> {
+    let mut payload = entry.payload.clone();
+
+    ensure_remaining(
+        &payload,
+        size_of::<u64>() + size_of::<u16>(),
+        "record header",
+    )
+    .wrap_err_with(|| format!("malformed record at log position {}", entry.position))?;
+    let revision = payload.get_u64_le();
+    let stream_name_len = payload.get_u16_le() as usize;
+    let stream_name = get_string(&mut payload, stream_name_len, "stream name")
+        .wrap_err_with(|| format!("malformed record at log position {}", entry.position))?;
+
+    Ok((stream_name, revision))
+}
+
+/// Commit time of the record stored in a log entry, without decoding its payload. `None` for
+/// records written before commit times were stored.
+pub fn record_created(entry: &LogEntry) -> eyre::Result<Option<DateTime<Utc>>> {
+    let mut payload = entry.payload.clone();
+
+    skip_to_commit_time(&mut payload)
+        .wrap_err_with(|| format!("malformed record at log position {}", entry.position))?;
+
+    if payload.remaining() < size_of::<i64>() {
+        return Ok(None);
+    }
+
+    let created = payload.get_i64_le();
+    if created == 0 {
+        return Ok(None);
+    }
+
+    DateTime::from_timestamp_millis(created)
+        .map(Some)
+        .ok_or_else(|| eyre::eyre!("invalid commit time {}", created))
+}
+
+fn skip_to_commit_time(payload: &mut Bytes) -> eyre::Result<()> {
+    ensure_remaining(
+        payload,
+        size_of::<u64>() + size_of::<u16>(),
+        "record header",
+    )?;
+    payload.advance(size_of::<u64>());
+    let stream_name_len = payload.get_u16_le() as usize;
+    ensure_remaining(payload, stream_name_len, "stream name")?;
+    payload.advance(stream_name_len);
+
+    ensure_remaining(
+        payload,
+        size_of::<u128>() + size_of::<u32>() + size_of::<u16>(),
+        "event header",
+    )?;
+    payload.advance(size_of::<u128>() + size_of::<u32>());
+    let class_len = payload.get_u16_le() as usize;
+    ensure_remaining(payload, class_len, "class")?;
+    payload.advance(class_len);
+
+    ensure_remaining(payload, size_of::<u32>(), "payload size")?;
+    let payload_len = payload.get_u32_le() as usize;
+    ensure_remaining(payload, payload_len, "payload")?;
+    payload.advance(payload_len);
+
+    Ok(())
+}
+
+fn parse_record(mut entry: LogEntry) -> eyre::Result<Record> {
+    let payload = &mut entry.payload;
+
+    ensure_remaining(
+        payload,
+        size_of::<u64>() + size_of::<u16>(),
+        "record header",
+    )?;
+    let revision = payload.get_u64_le();
+    let stream_name_len = payload.get_u16_le() as usize;
+    let stream_name = get_string(payload, stream_name_len, "stream name")?;
+
+    ensure_remaining(
+        payload,
+        size_of::<u128>() + size_of::<u32>() + size_of::<u16>(),
+        "event header",
+    )?;
+    let id = Uuid::from_u128_le(payload.get_u128_le());
+    let content_type = payload.get_u32_le() as i32;
+    let class_len = payload.get_u16_le() as usize;
+    let class = get_string(payload, class_len, "class")?;
+
+    ensure_remaining(payload, size_of::<u32>(), "payload size")?;
+    let payload_len = payload.get_u32_le() as usize;
+    ensure_remaining(payload, payload_len, "payload")?;
+    let data = payload.split_to(payload_len);
+
+    // Records written before commit times were stored don't have one.
+    let created = if payload.remaining() >= size_of::<i64>() {
+        payload.get_i64_le()
+    } else {
+        0
+    };
+    let created = DateTime::from_timestamp_millis(created)
+        .ok_or_else(|| eyre::eyre!("invalid commit time {}", created))?;
+
+    // Neither do records written before metadata was stored.
+    let metadata = if payload.remaining() >= size_of::<u32>() {
+        let metadata_len = payload.get_u32_le() as usize;
+        ensure_remaining(payload, metadata_len, "metadata")?;
+        payload.split_to(metadata_len)
+    } else {
+        Bytes::new()
     };
 
-    let id = Uuid::from_u128_le(entry.payload.get_u128_le());
-    let content_type = entry.payload.get_u32_le() as i32;
-    let class_len = entry.payload.get_u16_le() as usize;
-    let class =
-        unsafe { String::from_utf8_unchecked(entry.payload.copy_to_bytes(class_len).to_vec()) };
-    entry.payload.advance(size_of::<u32>()); // skip the payload size
+    // Nor do records written before payloads could be compressed.
+    let data = if payload.remaining() >= size_of::<u8>() {
+        let flag = payload.get_u8();
+        let compression = Compression::from_u8(flag)
+            .ok_or_else(|| eyre::eyre!("unknown payload compression {}", flag))?;
+
+        compression
+            .decompress(data)
+            .map_err(|e| eyre::eyre!("cannot decompress the {} payload: {}", compression, e))?
+    } else {
+        data
+    };
 
     Ok(Record {
         id,
@@ -29,6 +153,30 @@ pub fn record_try_from(mut entry: LogEntry) -> eyre::Result<Record> {
         class,
         position: entry.position,
         revision,
-        data: entry.payload,
+        data,
+        metadata,
+        created,
+        link: None,
+        epoch: 0,
     })
 }
+
+fn ensure_remaining(buf: &Bytes, len: usize, what: &str) -> eyre::Result<()> {
+    if buf.remaining() < len {
+        eyre::bail!(
+            "expected {} bytes for the {} but only {} remain",
+            len,
+            what,
+            buf.remaining()
+        );
+    }
+
+    Ok(())
+}
+
+fn get_string(buf: &mut Bytes, len: usize, what: &str) -> eyre::Result<String> {
+    ensure_remaining(buf, len, what)?;
+
+    String::from_utf8(buf.split_to(len).to_vec())
+        .map_err(|e| eyre::eyre!("invalid UTF-8 in the {}: {}", what, e))
+}
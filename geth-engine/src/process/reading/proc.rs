@@ -1,129 +1,679 @@
-use std::cmp::min;
+use std::cmp::{max, min};
+use std::collections::VecDeque;
 use std::mem;
+use std::task::Poll;
+use std::time::Duration;
 
+use crate::domain::index::{StreamState, is_tombstone};
 use crate::get_chunk_container;
-use crate::metrics::get_metrics;
-use crate::process::messages::{ReadRequests, ReadResponses};
-use crate::process::{Item, ProcessEnv, Raw};
-use geth_common::ReadCompleted;
-use geth_mikoshi::hashing::mikoshi_hash;
-use geth_mikoshi::wal::LogReader;
+use crate::metrics::{Metrics, get_metrics};
+use crate::names::streams;
+use crate::names::types::{STREAM_METADATA, STREAM_TRUNCATED};
+use crate::process::indexing::{IndexClient, Streaming as IndexStreaming};
+use crate::process::messages::{Messages, ReadRequests, ReadResponses};
+use crate::process::reading::{record_created, record_header, record_try_from};
+use crate::process::{Item, ProcessEnv, Raw, RequestContext};
+use chrono::{DateTime, Utc};
+use geth_common::{Direction, Epochs, Revision, StreamMetadata};
+use geth_mikoshi::wal::{BackwardEntries, LogEntry, LogReader};
+use tokio::sync::mpsc::{Sender, error::TrySendError};
+use uuid::Uuid;
+
+/// How long the reader waits for new requests when every read in progress is waiting on its
+/// consumer, instead of spinning until one of them catches up.
+const STALLED_READS_WAIT: Duration = Duration::from_millis(1);
+
+/// Streams a read across all streams remembers the state of, when it skips deleted ones. The
+/// state of the others is asked to the index again.
+const READ_ALL_STREAM_STATES: u64 = 1_000;
+
+/// Settings of a stream with the revision of its metadata stream they were collected at.
+type SettingsCache = moka::sync::Cache<String, (u64, StreamSettings)>;
+
+fn new_settings_cache() -> SettingsCache {
+    moka::sync::Cache::builder()
+        .max_capacity(10_000)
+        .name(&format!("settings-cache-{}", Uuid::new_v4()))
+        .build()
+}
 
 pub fn run(mut env: ProcessEnv<Raw>) -> eyre::Result<()> {
     let reader = LogReader::new(get_chunk_container());
     let index_client = env.new_index_client()?;
+    let settings_cache = new_settings_cache();
     let metrics = get_metrics();
+    let quantum = env.options.read_quantum.max(1);
+    let mut reads = VecDeque::<ActiveRead>::new();
+    // Reads in a row whose consumer had no room for more records.
+    let mut stalled = 0;
+
+    loop {
+        // Only block when there is no read in progress, otherwise we pick up whatever is already
+        // queued and give the next read in line its quantum.
+        let item = if reads.is_empty() {
+            match env.recv() {
+                Some(item) => Some(item),
+                None => break,
+            }
+        } else if stalled >= reads.len() {
+            stalled = 0;
+
+            match env.recv_timeout(STALLED_READS_WAIT) {
+                Poll::Ready(Some(item)) => Some(item),
+                Poll::Ready(None) => break,
+                Poll::Pending => None,
+            }
+        } else {
+            match env.try_recv() {
+                Poll::Ready(Some(item)) => Some(item),
+                Poll::Ready(None) => break,
+                Poll::Pending => None,
+            }
+        };
 
-    while let Some(item) = env.recv() {
         match item {
-            Item::Stream(stream) => {
-                if let Ok(ReadRequests::Read {
+            Some(Item::Stream(stream)) => match stream.payload.try_into() {
+                Ok(ReadRequests::Read {
                     ident,
                     start,
                     direction,
                     count,
-                }) = stream.payload.try_into()
-                {
+                    epochs,
+                }) => {
+                    let key = index_client.key(&ident);
+                    let state = env.block_on(index_client.stream_state(stream.context, key))?;
+
+                    if epochs == Epochs::Current && state.current.is_deleted() {
+                        let _ = stream.sender.try_send(ReadResponses::StreamDeleted.into());
+                        continue;
+                    }
+
+                    let settings = stream_settings(
+                        &env,
+                        &index_client,
+                        &reader,
+                        &settings_cache,
+                        stream.context,
+                        &ident,
+                    )?;
+                    let mut first_revision =
+                        first_revision(&env, &index_client, stream.context, &ident, &settings)?;
+
+                    if epochs == Epochs::Current {
+                        first_revision = max(first_revision, state.epoch_start());
+                    }
+
+                    let start = match direction {
+                        Direction::Forward => max(start, first_revision),
+                        Direction::Backward => start,
+                    };
+
+                    let tail_revision = state.current.revision().filter(|r| !is_tombstone(*r));
                     let index_stream = env.block_on(index_client.read(
                         stream.context,
-                        mikoshi_hash(ident),
+                        key,
                         start,
                         count,
                         direction,
                     ))?;
 
-                    let mut index_stream = match index_stream {
-                        ReadCompleted::Success(r) => r,
-                        ReadCompleted::StreamDeleted => {
-                            let _ = stream.sender.send(ReadResponses::StreamDeleted.into());
+                    if state.epoch() > 0
+                        && stream
+                            .sender
+                            .try_send(ReadResponses::Epochs(state.epoch_starts).into())
+                            .is_err()
+                    {
+                        continue;
+                    }
 
-                            continue;
+                    reads.push_back(ActiveRead::new(
+                        stream.context,
+                        stream.correlation,
+                        stream.sender,
+                        ident,
+                        ReadSource::Index {
+                            stream: index_stream,
+                            direction,
+                            first_revision,
+                            expires_before: settings
+                                .metadata
+                                .expires_before(env.options.clock.now()),
+                        },
+                        tail_revision,
+                        count,
+                    ));
+                }
+
+                Ok(ReadRequests::ReadAll {
+                    start,
+                    direction,
+                    count,
+                    skip_deleted,
+                }) => {
+                    let limit = reader.get_writer_checkpoint()?;
+                    let entries = match direction {
+                        Direction::Forward => LogEntries::Forward {
+                            next: match start {
+                                Revision::Start => 0,
+                                Revision::End => limit,
+                                Revision::Revision(p) => reader.align(min(p, limit))?,
+                            },
+                            limit,
+                        },
+
+                        Direction::Backward => {
+                            let end = match start {
+                                Revision::Start => 0,
+                                Revision::End => limit,
+                                Revision::Revision(p) => min(p.saturating_add(1), limit),
+                            };
+
+                            LogEntries::Backward(BackwardEntries::new(reader.clone(), end))
                         }
                     };
 
-                    let batch_size = min(count, 500);
-                    let mut batch = Vec::with_capacity(batch_size);
-                    let span =
-                        tracing::info_span!("read_from_log", correlation = %stream.correlation);
+                    reads.push_back(ActiveRead::new(
+                        stream.context,
+                        stream.correlation,
+                        stream.sender,
+                        streams::ALL.to_string(),
+                        ReadSource::Log {
+                            entries,
+                            remaining: count,
+                            deleted: skip_deleted
+                                .then(|| moka::sync::Cache::new(READ_ALL_STREAM_STATES)),
+                        },
+                        None,
+                        count,
+                    ));
+                }
 
-                    let result: eyre::Result<()> = span.in_scope(|| {
-                        let mut no_entries = true;
-                        while let Some(entry) = env.block_on(index_stream.next())? {
-                            let entry = reader.read_at(entry.position)?;
+                _ => {
+                    tracing::warn!(
+                        "malformed reader request from stream request {}",
+                        stream.correlation
+                    );
+                }
+            },
 
-                            metrics.observe_read_log_entry(&entry);
+            Some(Item::Mail(mail)) => match mail.payload.try_into() {
+                Ok(ReadRequests::ReadAt { position }) => {
+                    let entry = reader.read_at(position)?;
 
-                            batch.push(entry);
-                            no_entries = false;
+                    metrics.observe_read_log_entry(&entry);
 
-                            if batch.len() < batch_size {
-                                continue;
-                            }
+                    env.client.reply(
+                        mail.context,
+                        mail.origin,
+                        mail.correlation,
+                        ReadResponses::Entry(entry).into(),
+                    )?;
+                }
 
-                            let entries = mem::replace(&mut batch, Vec::with_capacity(batch_size));
-                            if stream
-                                .sender
-                                .send(ReadResponses::Entries(entries).into())
-                                .is_err()
-                            {
-                                break;
-                            }
-                        }
+                Ok(ReadRequests::Metadata { ident }) => {
+                    let current_revision = env.block_on(
+                        index_client.latest_revision(mail.context, index_client.key(&ident)),
+                    )?;
 
-                        if !batch.is_empty() {
-                            let _ = stream.sender.send(ReadResponses::Entries(batch).into());
-                            return Ok(());
-                        }
+                    let resp = if current_revision.is_deleted() {
+                        ReadResponses::StreamDeleted
+                    } else {
+                        let settings = stream_settings(
+                            &env,
+                            &index_client,
+                            &reader,
+                            &settings_cache,
+                            mail.context,
+                            &ident,
+                        )?;
 
-                        if no_entries {
-                            let _ = stream
-                                .sender
-                                .send(ReadResponses::Entries(Vec::new()).into());
-                        }
+                        ReadResponses::Metadata(settings.metadata)
+                    };
+
+                    env.client
+                        .reply(mail.context, mail.origin, mail.correlation, resp.into())?;
+                }
 
-                        Ok(())
-                    });
+                _ => {
+                    tracing::warn!("mail {} ignored", mail.correlation);
+                }
+            },
+
+            None => {}
+        }
 
-                    if let Err(err) = result {
-                        tracing::error!(
-                            correlation = %stream.context.correlation,
-                            "error reading from log: {}",
-                            err
-                        );
+        let Some(mut read) = reads.pop_front() else {
+            continue;
+        };
 
-                        let _ = stream.sender.send(ReadResponses::Error.into());
-                        metrics.observe_read_error();
+        let span = tracing::info_span!("read_from_log", correlation = %read.correlation);
+        let turn =
+            match span.in_scope(|| read.resume(&env, &reader, &index_client, &metrics, quantum)) {
+                Ok(turn) => turn,
+                Err(err) => {
+                    tracing::error!(
+                        correlation = %read.context.correlation,
+                        "error reading from log: {}",
+                        err
+                    );
+
+                    metrics.observe_read_error();
+                    read.finish(ReadResponses::Error)
+                }
+            };
+
+        match turn {
+            Turn::Done => {}
+
+            Turn::Progressed => {
+                stalled = 0;
+                reads.push_back(read);
+            }
+
+            Turn::Stalled => {
+                stalled += 1;
+                reads.push_back(read);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Where a read gets its log entries from.
+enum ReadSource {
+    /// Entries of a single stream, looked up in the index.
+    Index {
+        stream: IndexStreaming,
+        direction: Direction,
+        first_revision: u64,
+        /// Events committed before are past the `max_age_secs` of the stream.
+        expires_before: Option<DateTime<Utc>>,
+    },
+
+    /// Entries of every stream, straight from the log.
+    Log {
+        entries: LogEntries,
+        remaining: usize,
+        /// When events of deleted streams are skipped, the state of the streams met so far.
+        deleted: Option<moka::sync::Cache<String, StreamState>>,
+    },
+}
+
+enum LogEntries {
+    Forward { next: u64, limit: u64 },
+    Backward(BackwardEntries),
+}
+
+impl LogEntries {
+    fn next(&mut self, reader: &LogReader) -> eyre::Result<Option<LogEntry>> {
+        match self {
+            LogEntries::Forward { next, limit } => {
+                let entry = reader.entries(*next, *limit).next()?;
+
+                if let Some(entry) = &entry {
+                    *next = entry.position + entry.size() as u64;
+                }
+
+                Ok(entry)
+            }
+
+            LogEntries::Backward(entries) => entries.next(),
+        }
+    }
+}
+
+/// Outcome of the turn of a read.
+enum Turn {
+    /// The read is over, either complete or cancelled by its consumer.
+    Done,
+    Progressed,
+    /// The consumer has no room for more records, the read waits for it to catch up.
+    Stalled,
+}
+
+/// A read request in progress. Reads are served in turns, each one producing at most a quantum
+/// of records before letting the next one in line make progress. A read never produces more
+/// than its consumer has room for, the records of a slow consumer aren't piling up in memory.
+struct ActiveRead {
+    context: RequestContext,
+    correlation: Uuid,
+    sender: Sender<Messages>,
+    /// Messages the consumer had no room for yet.
+    outbox: VecDeque<Messages>,
+    /// Nothing is left to produce once the outbox is delivered.
+    finished: bool,
+    /// Stream the read is accounted to in the metrics.
+    stream_name: String,
+    source: ReadSource,
+    tail_revision: Option<u64>,
+    batch_size: usize,
+    batch: Vec<LogEntry>,
+}
+
+impl ActiveRead {
+    fn new(
+        context: RequestContext,
+        correlation: Uuid,
+        sender: Sender<Messages>,
+        stream_name: String,
+        source: ReadSource,
+        tail_revision: Option<u64>,
+        count: usize,
+    ) -> Self {
+        let batch_size = min(count, 500);
+
+        Self {
+            context,
+            correlation,
+            sender,
+            outbox: VecDeque::new(),
+            finished: false,
+            stream_name,
+            source,
+            tail_revision,
+            batch_size,
+            batch: Vec::with_capacity(batch_size),
+        }
+    }
+
+    /// Produces up to `quantum` records.
+    fn resume(
+        &mut self,
+        env: &ProcessEnv<Raw>,
+        reader: &LogReader,
+        index_client: &IndexClient,
+        metrics: &Metrics,
+        quantum: usize,
+    ) -> eyre::Result<Turn> {
+        if !self.send_outbox() {
+            return Ok(Turn::Done);
+        }
+
+        if !self.outbox.is_empty() {
+            return Ok(Turn::Stalled);
+        }
+
+        if self.finished {
+            return Ok(Turn::Done);
+        }
+
+        for _ in 0..quantum {
+            // The consumer went away, no need to keep reading on its behalf.
+            if self.sender.is_closed() {
+                tracing::debug!("read request cancelled by the consumer");
+                return Ok(Turn::Done);
+            }
+
+            let Some(entry) = self.next_entry(env, reader, index_client)? else {
+                return Ok(self.finish(ReadResponses::EndOfStream(self.tail_revision)));
+            };
+
+            metrics.observe_read_log_entry(&entry);
+            metrics.observe_stream_read(&self.stream_name, entry.payload_size());
+
+            self.batch.push(entry);
+
+            if self.batch.len() >= self.batch_size {
+                self.flush();
+
+                if !self.send_outbox() {
+                    return Ok(Turn::Done);
+                }
+
+                if !self.outbox.is_empty() {
+                    return Ok(Turn::Progressed);
+                }
+            }
+        }
+
+        // Sends what we have so far so the consumer isn't kept waiting while other reads run.
+        self.flush();
+
+        if self.send_outbox() {
+            Ok(Turn::Progressed)
+        } else {
+            Ok(Turn::Done)
+        }
+    }
+
+    fn next_entry(
+        &mut self,
+        env: &ProcessEnv<Raw>,
+        reader: &LogReader,
+        index_client: &IndexClient,
+    ) -> eyre::Result<Option<LogEntry>> {
+        match &mut self.source {
+            ReadSource::Index {
+                stream,
+                direction,
+                first_revision,
+                expires_before,
+            } => loop {
+                let Some(entry) = env.block_on(stream.next())? else {
+                    return Ok(None);
+                };
+
+                // Only reachable when reading backward, past the first readable revision.
+                if entry.revision < *first_revision {
+                    return Ok(None);
+                }
+
+                let entry = reader.read_at(entry.position)?;
+
+                // Commit times follow revisions: reading forward the expired events come first,
+                // reading backward they come last.
+                if let Some(expires_before) = expires_before
+                    && record_created(&entry)?.is_some_and(|created| created < *expires_before)
+                {
+                    match direction {
+                        Direction::Forward => continue,
+                        Direction::Backward => return Ok(None),
+                    }
+                }
+
+                return Ok(Some(entry));
+            },
+
+            ReadSource::Log {
+                entries,
+                remaining,
+                deleted,
+            } => {
+                while *remaining > 0 {
+                    let Some(entry) = entries.next(reader)? else {
+                        break;
+                    };
+
+                    if entry.r#type != 0 {
+                        continue;
                     }
 
-                    continue;
+                    if let Some(streams) = deleted {
+                        let (stream_name, revision) = record_header(&entry)?;
+                        let state = match streams.get(&stream_name) {
+                            Some(state) => state,
+                            None => {
+                                let state = env.block_on(
+                                    index_client
+                                        .stream_state(self.context, index_client.key(&stream_name)),
+                                )?;
+
+                                streams.insert(stream_name, state.clone());
+                                state
+                            }
+                        };
+
+                        if is_tombstone(revision)
+                            || state.current.is_deleted()
+                            || revision < state.epoch_start()
+                        {
+                            continue;
+                        }
+                    }
+
+                    *remaining -= 1;
+                    return Ok(Some(entry));
                 }
 
-                tracing::warn!(
-                    "malformed reader request from stream request {}",
-                    stream.correlation
-                );
+                Ok(None)
             }
+        }
+    }
 
-            Item::Mail(mail) => {
-                if let Ok(ReadRequests::ReadAt { position }) = mail.payload.try_into() {
-                    let entry = reader.read_at(position)?;
+    /// Moves the pending batch, if any, to the outbox.
+    fn flush(&mut self) {
+        if self.batch.is_empty() {
+            return;
+        }
 
-                    metrics.observe_read_log_entry(&entry);
+        let entries = mem::replace(&mut self.batch, Vec::with_capacity(self.batch_size));
+        self.outbox
+            .push_back(ReadResponses::Entries(entries).into());
+    }
 
-                    env.client.reply(
-                        mail.context,
-                        mail.origin,
-                        mail.correlation,
-                        ReadResponses::Entry(entry).into(),
-                    )?;
+    /// Sends as much of the outbox as the consumer has room for. Returns `false` if the consumer
+    /// went away.
+    fn send_outbox(&mut self) -> bool {
+        while let Some(msg) = self.outbox.pop_front() {
+            match self.sender.try_send(msg) {
+                Ok(()) => {}
 
-                    continue;
+                Err(TrySendError::Full(msg)) => {
+                    self.outbox.push_front(msg);
+                    break;
                 }
 
-                tracing::warn!("mail {} ignored", mail.correlation);
+                Err(TrySendError::Closed(_)) => {
+                    tracing::debug!("read request cancelled by the consumer");
+                    return false;
+                }
             }
         }
+
+        true
+    }
+
+    /// Ends the read with `last`, once the consumer got the records produced so far.
+    fn finish(&mut self, last: ReadResponses) -> Turn {
+        self.flush();
+        self.outbox.push_back(last.into());
+        self.finished = true;
+
+        if self.send_outbox() && !self.outbox.is_empty() {
+            Turn::Stalled
+        } else {
+            Turn::Done
+        }
     }
+}
 
-    Ok(())
+#[derive(Clone, Default)]
+struct StreamSettings {
+    truncate_before: u64,
+    metadata: StreamMetadata,
+}
+
+/// Collects the latest truncation and metadata out of the metadata stream of a stream. Settings
+/// are cached, only the markers appended to the metadata stream since they were collected are
+/// read.
+fn stream_settings(
+    env: &ProcessEnv<Raw>,
+    index_client: &IndexClient,
+    reader: &LogReader,
+    cache: &SettingsCache,
+    context: RequestContext,
+    ident: &str,
+) -> eyre::Result<StreamSettings> {
+    let key = index_client.key(streams::metadata_of(ident));
+    let Some(latest) = env
+        .block_on(index_client.latest_revision(context, key))?
+        .revision()
+    else {
+        return Ok(StreamSettings::default());
+    };
+
+    let cached = cache.get(ident);
+    if let Some((revision, settings)) = &cached
+        && *revision == latest
+    {
+        return Ok(settings.clone());
+    }
+
+    let (collected_at, mut settings) = match cached {
+        Some((revision, settings)) if revision < latest => (Some(revision), settings),
+        _ => (None, StreamSettings::default()),
+    };
+
+    let mut entries =
+        env.block_on(index_client.read(context, key, latest, usize::MAX, Direction::Backward))?;
+
+    let mut truncation_found = false;
+    let mut metadata_found = false;
+
+    while !(truncation_found && metadata_found) {
+        let Some(entry) = env.block_on(entries.next())? else {
+            break;
+        };
+
+        if collected_at.is_some_and(|revision| entry.revision <= revision) {
+            break;
+        }
+
+        let record = record_try_from(reader.read_at(entry.position)?)?;
+
+        if record.class == STREAM_TRUNCATED && !truncation_found {
+            let before = record.data.as_ref().try_into().map_err(|_| {
+                eyre::eyre!(
+                    "malformed truncation marker for stream '{}' at log position {}",
+                    ident,
+                    record.position
+                )
+            })?;
+
+            settings.truncate_before = u64::from_le_bytes(before);
+            truncation_found = true;
+        } else if record.class == STREAM_METADATA && !metadata_found {
+            settings.metadata = serde_json::from_slice(&record.data).map_err(|e| {
+                eyre::eyre!(
+                    "malformed metadata for stream '{}' at log position {}: {}",
+                    ident,
+                    record.position,
+                    e
+                )
+            })?;
+
+            metadata_found = true;
+        }
+    }
+
+    cache.insert(ident.to_string(), (latest, settings.clone()));
+
+    Ok(settings)
+}
+
+/// Returns the first revision of a stream that is still readable, considering its latest
+/// truncation and its `max_count` setting.
+fn first_revision(
+    env: &ProcessEnv<Raw>,
+    index_client: &IndexClient,
+    context: RequestContext,
+    ident: &str,
+    settings: &StreamSettings,
+) -> eyre::Result<u64> {
+    let mut first_revision = settings.truncate_before;
+
+    if let Some(max_count) = settings.metadata.max_count {
+        let current_revision =
+            env.block_on(index_client.latest_revision(context, index_client.key(ident)))?;
+
+        if !current_revision.is_deleted() {
+            first_revision = max(
+                first_revision,
+                current_revision.next_revision().saturating_sub(max_count),
+            );
+        }
+    }
+
+    Ok(first_revision)
 }
@@ -0,0 +1,152 @@
+use geth_common::{ContentType, ProjectedRecord, Record};
+use serde_json::Value;
+
+use crate::process::reading::Streaming;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// A JSONPath-like field selector, e.g. `$.user.addresses[0].city`. The leading `$` is
+/// optional.
+#[derive(Debug, Clone)]
+pub struct FieldSelector {
+    segments: Vec<Segment>,
+}
+
+impl FieldSelector {
+    pub fn parse(path: &str) -> eyre::Result<Self> {
+        let mut segments = Vec::new();
+        let path = path.strip_prefix('$').unwrap_or(path);
+        let mut chars = path.chars().peekable();
+        let mut key = String::new();
+        let mut after_index = false;
+
+        while let Some(c) = chars.next() {
+            match c {
+                '.' => {
+                    if chars.peek().is_none() {
+                        eyre::bail!("invalid field selector '{path}': empty field name");
+                    }
+
+                    if !key.is_empty() {
+                        segments.push(Segment::Key(std::mem::take(&mut key)));
+                    } else if !(segments.is_empty() || after_index) {
+                        eyre::bail!("invalid field selector '{path}': empty field name");
+                    }
+
+                    after_index = false;
+                }
+
+                '[' => {
+                    if !key.is_empty() {
+                        segments.push(Segment::Key(std::mem::take(&mut key)));
+                    }
+
+                    let mut index = String::new();
+                    loop {
+                        match chars.next() {
+                            Some(']') => break,
+                            Some(c) => index.push(c),
+                            None => eyre::bail!("invalid field selector '{path}': missing ']'"),
+                        }
+                    }
+
+                    let index = index.trim().parse::<usize>().map_err(|_| {
+                        eyre::eyre!("invalid field selector '{path}': invalid index '{index}'")
+                    })?;
+
+                    segments.push(Segment::Index(index));
+                    after_index = true;
+                }
+
+                c => {
+                    key.push(c);
+                    after_index = false;
+                }
+            }
+        }
+
+        if !key.is_empty() {
+            segments.push(Segment::Key(key));
+        }
+
+        if segments.is_empty() {
+            eyre::bail!("invalid field selector '{path}': no field selected");
+        }
+
+        Ok(Self { segments })
+    }
+
+    pub fn select<'a>(&self, value: &'a Value) -> Option<&'a Value> {
+        let mut current = value;
+
+        for segment in &self.segments {
+            current = match segment {
+                Segment::Key(key) => current.get(key.as_str())?,
+                Segment::Index(idx) => current.get(*idx)?,
+            };
+        }
+
+        Some(current)
+    }
+}
+
+/// Extracts the selected fields out of a record. Records that are not JSON encoded, or whose
+/// JSON payload is malformed, are skipped and `None` is returned. Fields missing from the payload
+/// are set to `null`.
+pub fn project(selectors: &[FieldSelector], record: Record) -> Option<ProjectedRecord> {
+    if record.content_type != ContentType::Json {
+        return None;
+    }
+
+    let payload = match serde_json::from_slice::<Value>(&record.data) {
+        Ok(payload) => payload,
+        Err(e) => {
+            tracing::warn!(
+                stream = record.stream_name,
+                revision = record.revision,
+                error = %e,
+                "malformed JSON payload skipped by projection"
+            );
+
+            return None;
+        }
+    };
+
+    let values = selectors
+        .iter()
+        .map(|s| s.select(&payload).cloned().unwrap_or(Value::Null))
+        .collect();
+
+    Some(ProjectedRecord {
+        id: record.id,
+        stream_name: record.stream_name,
+        position: record.position,
+        revision: record.revision,
+        values,
+    })
+}
+
+pub struct ProjectedStreaming {
+    inner: Streaming,
+    selectors: Vec<FieldSelector>,
+}
+
+impl ProjectedStreaming {
+    pub fn new(inner: Streaming, selectors: Vec<FieldSelector>) -> Self {
+        Self { inner, selectors }
+    }
+
+    pub async fn next(&mut self) -> eyre::Result<Option<ProjectedRecord>> {
+        while let Some(record) = self.inner.next().await? {
+            if let Some(projected) = project(&self.selectors, record) {
+                return Ok(Some(projected));
+            }
+        }
+
+        Ok(None)
+    }
+}
@@ -1,5 +1,5 @@
 use crate::process::{Item, Managed, ManagerClient, Proc, ProcId, ProcessEnv, RequestContext};
-use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::mpsc::Receiver;
 
 use super::messages::{Messages, TestSinkRequests, TestSinkResponses};
 
@@ -11,6 +11,7 @@ pub async fn run(mut env: ProcessEnv<Managed>) -> eyre::Result<()> {
                     if stream
                         .sender
                         .send(TestSinkResponses::Stream(num).into())
+                        .await
                         .is_err()
                     {
                         break;
@@ -51,7 +52,7 @@ impl SinkClient {
 }
 
 pub struct Streaming {
-    inner: UnboundedReceiver<Messages>,
+    inner: Receiver<Messages>,
 }
 
 impl Streaming {
@@ -4,10 +4,10 @@ use crate::process::messages::{
 };
 use crate::process::{ManagerClient, ProcId, RequestContext};
 use geth_common::{
-    ProgramStats, ProgramSummary, Record, SubscriptionConfirmation, SubscriptionEvent,
-    SubscriptionNotification, UnsubscribeReason,
+    ProgramStats, ProgramSummary, Record, RestartPolicy, SubscriptionConfirmation,
+    SubscriptionEvent, SubscriptionNotification, UnsubscribeReason,
 };
-use tokio::sync::mpsc::{UnboundedReceiver, unbounded_channel};
+use tokio::sync::mpsc::{Receiver, channel};
 use tracing::instrument;
 
 #[derive(Debug)]
@@ -15,7 +15,7 @@ pub struct Streaming {
     context: RequestContext,
     stream_name: String,
     id: Option<ProcId>,
-    inner: UnboundedReceiver<Messages>,
+    inner: Receiver<Messages>,
 }
 
 impl Streaming {
@@ -24,15 +24,11 @@ impl Streaming {
             context: RequestContext::nil(),
             stream_name: String::new(),
             id: None,
-            inner: unbounded_channel().1,
+            inner: channel(1).1,
         }
     }
 
-    pub fn from(
-        context: RequestContext,
-        stream_name: String,
-        inner: UnboundedReceiver<Messages>,
-    ) -> Self {
+    pub fn from(context: RequestContext, stream_name: String, inner: Receiver<Messages>) -> Self {
         Self {
             context,
             stream_name,
@@ -76,7 +72,10 @@ impl Streaming {
                     } else {
                         self.id = Some(0);
                         tracing::debug!(stream_name = self.stream_name, correlation = %self.context.correlation, "subscription confirmed");
-                        SubscriptionConfirmation::StreamName(std::mem::take(&mut self.stream_name))
+                        SubscriptionConfirmation::StreamName {
+                            stream_name: std::mem::take(&mut self.stream_name),
+                            start_revision: None,
+                        }
                     };
 
                     return Ok(Some(SubscriptionEvent::Confirmed(conf)));
@@ -106,6 +105,18 @@ impl Streaming {
                         )));
                     }
 
+                    ProgramResponses::Log(line) => {
+                        return Ok(Some(SubscriptionEvent::ProgramLog { line }));
+                    }
+
+                    ProgramResponses::LimitExceeded(limit) => {
+                        self.inner.close();
+
+                        return Ok(Some(SubscriptionEvent::Unsubscribed(
+                            UnsubscribeReason::LimitExceeded(limit),
+                        )));
+                    }
+
                     x => {
                         tracing::error!(msg = ?x, correlation = %self.context.correlation, "unexpected message");
                         eyre::bail!("unexpected message when streaming from process");
@@ -161,6 +172,8 @@ impl SubscriptionClient {
         context: RequestContext,
         name: &str,
         code: &str,
+        logs: bool,
+        restart: RestartPolicy,
     ) -> eyre::Result<Streaming> {
         let mailbox = self
             .inner
@@ -170,6 +183,8 @@ impl SubscriptionClient {
                 SubscribeRequests::Subscribe(SubscriptionType::Program {
                     name: name.to_string(),
                     code: code.to_string(),
+                    logs,
+                    restart,
                 })
                 .into(),
             )
@@ -281,9 +296,10 @@ impl SubscriptionClient {
 
     #[instrument(skip(self, events, context), fields(origin = ?self.inner.origin(), target = self.target, correlation = %context.correlation))]
     pub async fn push(&self, context: RequestContext, events: Vec<Record>) -> eyre::Result<()> {
+        // The events are already committed, subscribers would miss them if they were dropped.
         let resp = self
             .inner
-            .request(
+            .request_with_backpressure(
                 context,
                 self.target,
                 SubscribeRequests::Push { events }.into(),
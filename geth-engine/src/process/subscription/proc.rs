@@ -1,3 +1,4 @@
+use crate::SharedClock;
 use crate::metrics::{Metrics, get_metrics};
 use crate::names::types::STREAM_DELETED;
 use crate::process::messages::{
@@ -7,22 +8,21 @@ use crate::process::messages::{
 use crate::process::subscription::program::{ProgramClient, ProgramStartResult};
 use crate::process::{Item, Managed, ProcId, ProcessEnv};
 use crate::{ManagerClient, Proc, RequestContext};
-use chrono::Utc;
-use geth_common::{ProgramSummary, Record};
+use geth_common::{ProgramSummary, Record, RestartPolicy, STREAMS_FEED};
 use std::collections::HashMap;
 use std::time::Duration;
-use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::mpsc::{Sender, error::TrySendError};
 use uuid::Uuid;
 
 const ALL_IDENT: &str = "$all";
 
 #[derive(Default)]
 struct Register {
-    inner: HashMap<String, Vec<UnboundedSender<Messages>>>,
+    inner: HashMap<String, Vec<Sender<Messages>>>,
 }
 
 impl Register {
-    fn register(&mut self, key: String, sender: UnboundedSender<Messages>) {
+    fn register(&mut self, key: String, sender: Sender<Messages>) {
         self.inner.entry(key).or_default().push(sender);
     }
 
@@ -30,21 +30,22 @@ impl Register {
         if let Some(senders) = self.inner.get_mut(&record.stream_name) {
             let before = senders.len();
             senders.retain(|sender| {
-                sender
-                    .send(SubscribeResponses::Record(record.clone()).into())
-                    .is_ok()
+                deliver(sender, SubscribeResponses::Record(record.clone()).into())
                     && record.class != STREAM_DELETED
             });
             let after = senders.len();
             metrics.observe_subscription_terminated(before - after);
         }
 
+        // The `$streams` feed is not part of the log.
+        if record.stream_name == STREAMS_FEED {
+            return;
+        }
+
         if let Some(senders) = self.inner.get_mut(ALL_IDENT) {
             let before = senders.len();
             senders.retain(|sender| {
-                sender
-                    .send(SubscribeResponses::Record(record.clone()).into())
-                    .is_ok()
+                deliver(sender, SubscribeResponses::Record(record.clone()).into())
             });
             let after = senders.len();
             metrics.observe_subscription_terminated(before - after);
@@ -52,6 +53,19 @@ impl Register {
     }
 }
 
+/// A subscriber so far behind that its buffer is full is dropped rather than holding up every
+/// other subscription. Its stream ends, so it can subscribe again from where it left off.
+fn deliver(sender: &Sender<Messages>, msg: Messages) -> bool {
+    match sender.try_send(msg) {
+        Ok(()) => true,
+        Err(TrySendError::Full(_)) => {
+            tracing::warn!("subscriber is lagging behind, dropping its subscription");
+            false
+        }
+        Err(TrySendError::Closed(_)) => false,
+    }
+}
+
 fn unit() -> eyre::Result<()> {
     Ok(())
 }
@@ -59,9 +73,12 @@ fn unit() -> eyre::Result<()> {
 struct StartPyroWorker {
     context: RequestContext,
     client: ManagerClient,
-    sender: UnboundedSender<Messages>,
+    sender: Sender<Messages>,
     name: String,
     code: String,
+    logs: bool,
+    restart: RestartPolicy,
+    clock: SharedClock,
 }
 
 fn start_pyro_worker(args: StartPyroWorker) {
@@ -71,7 +88,7 @@ fn start_pyro_worker(args: StartPyroWorker) {
             Err(e) => {
                 tracing::error!(error = %e, correlation = %args.context.correlation, "error when spawning a pyro worker");
 
-                let _ = args.sender.send(SubscribeResponses::Error(e).into());
+                let _ = args.sender.send(SubscribeResponses::Error(e).into()).await;
                 return unit();
             }
 
@@ -93,6 +110,8 @@ fn start_pyro_worker(args: StartPyroWorker) {
                 args.name.clone(),
                 args.code,
                 args.sender.clone(),
+                args.logs,
+                args.restart,
             )
             .await?
         {
@@ -111,7 +130,7 @@ fn start_pyro_worker(args: StartPyroWorker) {
                             client,
                             name: args.name,
                             sender: args.sender,
-                            started_at: Utc::now(),
+                            started_at: args.clock.now(),
                         },
                     ))
                     .into(),
@@ -120,7 +139,7 @@ fn start_pyro_worker(args: StartPyroWorker) {
 
             ProgramStartResult::Failed(e) => {
                 tracing::error!(id = %id, name = args.name, error = %e, "error when starting program");
-                let _ = args.sender.send(SubscribeResponses::Error(e).into());
+                let _ = args.sender.send(SubscribeResponses::Error(e).into()).await;
             }
         };
 
@@ -206,7 +225,7 @@ pub async fn run(mut env: ProcessEnv<Managed>) -> eyre::Result<()> {
                             SubscriptionType::Stream { ident } => {
                                 if stream
                                     .sender
-                                    .send(SubscribeResponses::Confirmed(None).into())
+                                    .try_send(SubscribeResponses::Confirmed(None).into())
                                     .is_ok()
                                 {
                                     reg.register(ident, stream.sender);
@@ -217,13 +236,21 @@ pub async fn run(mut env: ProcessEnv<Managed>) -> eyre::Result<()> {
                                 tracing::warn!(stream = ident, correlation = %stream.context.correlation, "subscription wasn't registered because nothing is listening to it");
                             }
 
-                            SubscriptionType::Program { name, code } => {
+                            SubscriptionType::Program {
+                                name,
+                                code,
+                                logs,
+                                restart,
+                            } => {
                                 start_pyro_worker(StartPyroWorker {
                                     context: stream.context,
                                     client: env.client.clone(),
                                     sender: stream.sender,
                                     name,
                                     code,
+                                    logs,
+                                    restart,
+                                    clock: env.options.clock.clone(),
                                 });
                             }
                         },
@@ -251,7 +278,9 @@ pub async fn run(mut env: ProcessEnv<Managed>) -> eyre::Result<()> {
                 {
                     if let Some(prog) = programs.remove(&proc_id) {
                         tracing::info!(id = proc_id, name = prog.name, "program terminated");
-                        let _ = prog.sender.send(SubscribeResponses::Unsubscribed.into());
+                        let _ = prog
+                            .sender
+                            .try_send(SubscribeResponses::Unsubscribed.into());
                         metrics.observe_program_terminated();
                     }
 
@@ -269,7 +298,9 @@ pub async fn run(mut env: ProcessEnv<Managed>) -> eyre::Result<()> {
 
                             if args
                                 .sender
-                                .send(SubscribeResponses::Confirmed(Some(args.client.id())).into())
+                                .try_send(
+                                    SubscribeResponses::Confirmed(Some(args.client.id())).into(),
+                                )
                                 .is_ok()
                             {
                                 tracing::debug!(name = args.name, correlation = %mail.context.correlation, "program was registered successfully");
@@ -1,5 +1,5 @@
-use geth_common::ProgramStats;
-use tokio::sync::mpsc::UnboundedSender;
+use geth_common::{ProgramStats, RestartPolicy};
+use tokio::sync::mpsc::Sender;
 use tracing::instrument;
 
 use crate::{
@@ -36,7 +36,9 @@ impl ProgramClient {
         context: RequestContext,
         name: String,
         code: String,
-        output: UnboundedSender<Messages>,
+        output: Sender<Messages>,
+        logs: bool,
+        restart: RestartPolicy,
     ) -> eyre::Result<ProgramStartResult> {
         let mailbox = self
             .inner
@@ -47,6 +49,8 @@ impl ProgramClient {
                     name,
                     code,
                     sender: output,
+                    logs,
+                    restart,
                 }
                 .into(),
             )
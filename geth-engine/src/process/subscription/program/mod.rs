@@ -1,4 +1,5 @@
-use tokio::sync::mpsc::UnboundedSender;
+use geth_common::RestartPolicy;
+use tokio::sync::mpsc::Sender;
 
 use crate::process::messages::Messages;
 
@@ -10,5 +11,7 @@ pub use client::{ProgramClient, ProgramStartResult};
 pub struct ProgramArgs {
     pub name: String,
     pub code: String,
-    pub output: UnboundedSender<Messages>,
+    pub output: Sender<Messages>,
+    pub logs: bool,
+    pub restart: RestartPolicy,
 }
@@ -1,8 +1,16 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
 
 use base64::Engine as _;
 use chrono::{DateTime, Utc};
-use geth_common::{ContentType, Record, Revision, SubscriptionConfirmation, SubscriptionEvent};
+use geth_common::{
+    ContentType, Record, RestartPolicy, Revision, SubscriptionConfirmation, SubscriptionEvent,
+};
 use pyro_core::{NominalTyping, ast::Prop, sym::Literal};
 use pyro_runtime::{
     Channel, Engine, Env, PyroProcess, PyroType, PyroValue, RuntimeValue,
@@ -62,6 +70,7 @@ impl PyroValue for EventRecord {
         let payload = if record.data.is_empty() {
             serde_json::Value::Array(vec![])
         } else if record.content_type != ContentType::Json {
+            // Binary and protobuf payloads alike, the runtime can't decode protobuf yet.
             let encoded = base64::engine::general_purpose::STANDARD.encode(&record.data);
             serde_json::Value::String(encoded)
         } else {
@@ -248,12 +257,15 @@ pub enum PyroRuntimeNotification {
 pub enum PyroEvent {
     Value(RuntimeValue),
     Notification(PyroRuntimeNotification),
+    Log(String),
 }
 
 pub struct PyroRuntime {
     engine: Engine<NominalTyping>,
     output: UnboundedReceiver<RuntimeValue>,
     notifications: UnboundedReceiver<PyroRuntimeNotification>,
+    logs: UnboundedReceiver<String>,
+    restarts: Arc<AtomicUsize>,
     started: DateTime<Utc>,
 }
 
@@ -266,37 +278,77 @@ impl PyroRuntime {
         select! {
             Some(value) = self.output.recv() => Some(PyroEvent::Value(value)),
             Some(notif) = self.notifications.recv() => Some(PyroEvent::Notification(notif)),
+            Some(line) = self.logs.recv() => Some(PyroEvent::Log(line)),
             else => None
         }
     }
 
+    /// Values the program emitted that weren't received yet.
+    pub fn buffered(&self) -> usize {
+        self.output.len()
+    }
+
+    /// How many times the program subscriptions were restarted after an error.
+    pub fn restarts(&self) -> usize {
+        self.restarts.load(Ordering::Relaxed)
+    }
+
     pub fn started(&self) -> DateTime<Utc> {
         self.started
     }
 }
 
+/// Waits for the backoff and returns true when the restart policy allows another attempt.
+async fn should_restart(policy: RestartPolicy, attempts: &mut u32, restarts: &AtomicUsize) -> bool {
+    match policy {
+        RestartPolicy::Never => false,
+        RestartPolicy::OnError { max, backoff } => {
+            if *attempts >= max {
+                return false;
+            }
+
+            *attempts += 1;
+            restarts.fetch_add(1, Ordering::Relaxed);
+            tokio::time::sleep(backoff).await;
+
+            true
+        }
+    }
+}
+
 pub fn create_pyro_runtime(
     context: RequestContext,
     client: ManagerClient,
     proc_id: ProcId,
     name: &str,
+    started: DateTime<Utc>,
+    logs: bool,
+    restart: RestartPolicy,
 ) -> eyre::Result<PyroRuntime> {
     let (stdout_handle, mut stdout_recv) = unbounded_channel();
     let env = Env { stdout_handle };
     let name_stdout = name.to_string();
+    let (send_log, recv_log) = unbounded_channel();
+    // When logs are not requested, the sender is dropped right away so the runtime never
+    // waits on that channel.
+    let send_log = logs.then_some(send_log);
     tokio::spawn(async move {
         while let Some(value) = stdout_recv.recv().await {
-            tracing::info!(
-                kind = "pyro",
-                process = name_stdout,
-                message = value.to_string(),
-            );
+            let message = value.to_string();
+
+            if let Some(send_log) = &send_log {
+                let _ = send_log.send(message.clone());
+            }
+
+            tracing::info!(kind = "pyro", process = name_stdout, message);
         }
     });
 
     let (send_output, recv_output) = unbounded_channel();
     let (send_notification, recv_notification) = unbounded_channel();
     let name_subscribe = name.to_string();
+    let restarts = Arc::new(AtomicUsize::new(0));
+    let runtime_restarts = restarts.clone();
     let engine = Engine::with_nominal_typing()
         .stdlib(env)
         .register_type::<EventEntry>("Entry")
@@ -314,114 +366,146 @@ pub fn create_pyro_runtime(
             let name_subscribe_local = name_subscribe.clone();
             let manager_client = client.clone();
             let local_send_notification = send_notification.clone();
+            let local_restarts = restarts.clone();
             tokio::spawn(async move {
-                let mut consumer =
-                    match start_consumer(context, stream_name.clone(), Revision::Start, manager_client)
-                        .await
-                    {
-                        Err(error) => {
-                            tracing::error!(%error, stream_name, "unexpected error when starting a new consumer");
-                            return Ok(());
-                        }
+                // Where the subscription picks up after a restart, right after the last event
+                // handed to the program.
+                let mut start = Revision::Start;
+                let mut attempts = 0;
+
+                'subscription: loop {
+                    let mut consumer =
+                        match start_consumer(context, stream_name.clone(), start, manager_client.clone())
+                            .await
+                        {
+                            Err(error) => {
+                                tracing::error!(%error, stream_name, "unexpected error when starting a new consumer");
+
+                                if should_restart(restart, &mut attempts, &local_restarts).await {
+                                    continue;
+                                }
 
-                        Ok(result) => match result {
-                            ConsumerResult::Success(c) => c,
-                            ConsumerResult::StreamDeleted => {
-                                tracing::error!(reason = "stream deleted", stream_name, "cannot start a new consumer");
                                 return Ok(());
                             }
-                        }
-                    };
 
-                loop {
-                    match consumer.next().await {
-                        Err(error) => {
-                            tracing::error!(
-                                name = name_subscribe_local,
-                                target = "subscription",
-                                proc_id,
-                                kind = "pyro",
-                                stream_name,
-                                reason = "error",
-                                %error,
-                                "unexpected subscription error"
-                            );
-
-                            let _ = local_send_notification.send(PyroRuntimeNotification::UnsubscribedToStream(name_subscribe_local.clone()));
-
-                            break;
-                        }
+                            Ok(result) => match result {
+                                ConsumerResult::Success(c) => c,
+                                ConsumerResult::StreamDeleted => {
+                                    tracing::error!(reason = "stream deleted", stream_name, "cannot start a new consumer");
+                                    return Ok(());
+                                }
+                            }
+                        };
+
+                    loop {
+                        match consumer.next().await {
+                            Err(error) => {
+                                tracing::error!(
+                                    name = name_subscribe_local,
+                                    target = "subscription",
+                                    proc_id,
+                                    kind = "pyro",
+                                    stream_name,
+                                    reason = "error",
+                                    %error,
+                                    "unexpected subscription error"
+                                );
+
+                                if should_restart(restart, &mut attempts, &local_restarts).await {
+                                    tracing::info!(
+                                        name = name_subscribe_local,
+                                        target = "subscription",
+                                        proc_id,
+                                        kind = "pyro",
+                                        stream_name,
+                                        attempts,
+                                        "restarting subscription"
+                                    );
+
+                                    continue 'subscription;
+                                }
+
+                                let _ = local_send_notification.send(PyroRuntimeNotification::UnsubscribedToStream(name_subscribe_local.clone()));
+
+                                break 'subscription;
+                            }
+
+                            Ok(outcome) => {
+                                if let Some(event) = outcome {
+                                    match event {
+                                        SubscriptionEvent::CaughtUp => {}
+
+                                        SubscriptionEvent::Confirmed(conf) => {
+                                            if let SubscriptionConfirmation::StreamName { stream_name, .. } =
+                                                conf
+                                            {
+                                                tracing::debug!(
+                                                    name = name_subscribe_local,
+                                                    proc_id = proc_id,
+                                                    target = "subscription",
+                                                    kind = "pyro",
+                                                    stream_name = stream_name,
+                                                    "subscription is confirmed"
+                                                );
 
-                        Ok(outcome) => {
-                            if let Some(event) = outcome {
-                                match event {
-                                    SubscriptionEvent::CaughtUp => {}
+                                                let _ = local_send_notification.send(PyroRuntimeNotification::SubscribedToStream(stream_name));
+                                            }
+                                        }
 
-                                    SubscriptionEvent::Confirmed(conf) => {
-                                        if let SubscriptionConfirmation::StreamName(stream_name) =
-                                            conf
-                                        {
-                                            tracing::debug!(
+                                        SubscriptionEvent::Unsubscribed(reason) => {
+                                            tracing::warn!(
                                                 name = name_subscribe_local,
-                                                proc_id = proc_id,
                                                 target = "subscription",
+                                                proc_id = proc_id,
                                                 kind = "pyro",
                                                 stream_name = stream_name,
-                                                "subscription is confirmed"
+                                                reason = ?reason,
+                                                "subscription was dropped"
                                             );
 
-                                            let _ = local_send_notification.send(PyroRuntimeNotification::SubscribedToStream(stream_name));
-                                        }
-                                    }
+                                            let _ = local_send_notification.send(PyroRuntimeNotification::UnsubscribedToStream(name_subscribe_local.clone()));
 
-                                    SubscriptionEvent::Unsubscribed(reason) => {
-                                        tracing::warn!(
-                                            name = name_subscribe_local,
-                                            target = "subscription",
-                                            proc_id = proc_id,
-                                            kind = "pyro",
-                                            stream_name = stream_name,
-                                            reason = ?reason,
-                                            "subscription was dropped"
-                                        );
-
-                                        let _ = local_send_notification.send(PyroRuntimeNotification::UnsubscribedToStream(name_subscribe_local.clone()));
-
-                                        break;
-                                    }
+                                            break 'subscription;
+                                        }
 
-                                    SubscriptionEvent::EventAppeared(record) => {
-                                        let serialized = EventRecord(record)
-                                            .serialize()
-                                            .inspect_err(|error| {
-                                                tracing::error!(
-                                                    %error,
+                                        SubscriptionEvent::EventAppeared(record) => {
+                                            let next = Revision::Revision(record.revision + 1);
+                                            let serialized = EventRecord(record)
+                                                .serialize()
+                                                .inspect_err(|error| {
+                                                    tracing::error!(
+                                                        %error,
+                                                        proc_id,
+                                                        stream_name,
+                                                        name = name_subscribe_local,
+                                                        "serialization error"
+                                                    );
+                                                })?;
+
+                                            if input.send(serialized).is_err() {
+                                                tracing::debug!(
+                                                    name = name_subscribe_local,
+                                                    target = "subscription",
                                                     proc_id,
+                                                    kind = "pyro",
                                                     stream_name,
-                                                    name = name_subscribe_local,
-                                                    "serialization error"
+                                                    reason = "User",
+                                                    "subscription was dropped"
                                                 );
-                                            })?;
 
-                                        if input.send(serialized).is_err() {
-                                            tracing::debug!(
-                                                name = name_subscribe_local,
-                                                target = "subscription",
-                                                proc_id,
-                                                kind = "pyro",
-                                                stream_name,
-                                                reason = "User",
-                                                "subscription was dropped"
-                                            );
+                                                break 'subscription;
+                                            }
 
-                                            break;
+                                            start = next;
                                         }
-                                    }
 
-                                    SubscriptionEvent::Notification(_) => {}
+                                        SubscriptionEvent::EventsAppeared(_)
+                                        | SubscriptionEvent::Notification(_)
+                                        | SubscriptionEvent::ProgramLog { .. } => {}
+                                    }
+                                } else {
+                                    break 'subscription;
                                 }
-                            } else {
-                                break;
                             }
                         }
                     }
@@ -449,6 +533,8 @@ pub fn create_pyro_runtime(
         engine,
         output: recv_output,
         notifications: recv_notification,
-        started: Utc::now(),
+        logs: recv_log,
+        restarts: runtime_restarts,
+        started,
     })
 }
@@ -1,7 +1,10 @@
-use std::collections::HashSet;
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
 
 use bytes::Bytes;
-use geth_common::{ContentType, ProgramStats, Record};
+use geth_common::{ContentType, ProgramLimit, ProgramLimits, ProgramStats, Record};
 use uuid::Uuid;
 
 use crate::{
@@ -19,6 +22,53 @@ use crate::{
     },
 };
 
+struct RateWindow {
+    started: Instant,
+    count: u64,
+}
+
+impl RateWindow {
+    fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            count: 0,
+        }
+    }
+
+    /// Records an emitted event and returns how many were emitted during the current second.
+    fn record(&mut self) -> u64 {
+        if self.started.elapsed() >= Duration::from_secs(1) {
+            self.started = Instant::now();
+            self.count = 0;
+        }
+
+        self.count += 1;
+        self.count
+    }
+}
+
+/// Returns the limit the program went over, if any.
+fn check_limits(
+    limits: &ProgramLimits,
+    events_this_second: u64,
+    subscriptions: usize,
+    buffered: usize,
+) -> Option<ProgramLimit> {
+    if events_this_second > limits.max_events_per_sec {
+        return Some(ProgramLimit::EventsPerSecond(limits.max_events_per_sec));
+    }
+
+    if subscriptions > limits.max_subscriptions {
+        return Some(ProgramLimit::Subscriptions(limits.max_subscriptions));
+    }
+
+    if buffered > limits.max_output_buffer {
+        return Some(ProgramLimit::OutputBuffer(limits.max_output_buffer));
+    }
+
+    None
+}
+
 struct WorkerArgs {
     context: RequestContext,
     program: ProgramArgs,
@@ -33,7 +83,13 @@ pub async fn run(mut env: ProcessEnv<Managed>) -> eyre::Result<()> {
     tracing::debug!("computation unit allocated, waiting for program instructions");
     while let Some(item) = env.recv().await {
         if let Item::Mail(message) = item
-            && let Ok(ProgramRequests::Start { name, code, sender }) = message.payload.try_into()
+            && let Ok(ProgramRequests::Start {
+                name,
+                code,
+                sender,
+                logs,
+                restart,
+            }) = message.payload.try_into()
         {
             args = Some(WorkerArgs {
                 context: message.context,
@@ -41,6 +97,8 @@ pub async fn run(mut env: ProcessEnv<Managed>) -> eyre::Result<()> {
                     name,
                     code,
                     output: sender,
+                    logs,
+                    restart,
                 },
                 origin: message.origin,
                 correlation: message.correlation,
@@ -68,6 +126,9 @@ pub async fn run(mut env: ProcessEnv<Managed>) -> eyre::Result<()> {
         env.client.clone(),
         env.client.id(),
         &args.program.name,
+        env.options.clock.now(),
+        args.program.logs,
+        args.program.restart,
     ) {
         Ok(runtime) => runtime,
         Err(e) => {
@@ -75,7 +136,7 @@ pub async fn run(mut env: ProcessEnv<Managed>) -> eyre::Result<()> {
             let _ = args
                 .program
                 .output
-                .send(SubscribeResponses::Error(e).into());
+                .try_send(SubscribeResponses::Error(e).into());
             return Ok(());
         }
     };
@@ -91,7 +152,7 @@ pub async fn run(mut env: ProcessEnv<Managed>) -> eyre::Result<()> {
             let _ = args
                 .program
                 .output
-                .send(SubscribeResponses::Error(e).into());
+                .try_send(SubscribeResponses::Error(e).into());
             return Ok(());
         }
     };
@@ -108,13 +169,16 @@ pub async fn run(mut env: ProcessEnv<Managed>) -> eyre::Result<()> {
     let mut execution = Box::pin(process.run());
     let mut revision = 0;
     let mut subs = HashSet::new();
+    let clock = env.options.clock.clone();
+    let limits = env.options.program_limits();
+    let mut rate = RateWindow::new();
 
     loop {
         tokio::select! {
             outcome = &mut execution => {
                 if let Err(e) = outcome {
                     tracing::error!(name = args.program.name, error = %e, correlation = %args.context.correlation, "error when running pyro program");
-                    let _ = args.program.output.send(SubscribeResponses::Error(eyre::eyre!("program panicked")).into());
+                    let _ = args.program.output.send(SubscribeResponses::Error(eyre::eyre!("program panicked")).into()).await;
                 } else {
                     tracing::info!(name = args.program.name, correlation = %args.context.correlation, "program completed successfully");
                 }
@@ -140,6 +204,8 @@ pub async fn run(mut env: ProcessEnv<Managed>) -> eyre::Result<()> {
                                     subscriptions: subs.iter().cloned().collect(),
                                     pushed_events: revision as usize,
                                     started: runtime.started(),
+                                    limits,
+                                    restarts: runtime.restarts(),
                                 }).into());
                             }
 
@@ -152,6 +218,33 @@ pub async fn run(mut env: ProcessEnv<Managed>) -> eyre::Result<()> {
             }
 
             Some(output) = runtime.recv() => {
+                let events_this_second = if let PyroEvent::Value(_) = &output {
+                    rate.record()
+                } else {
+                    0
+                };
+
+                let subscriptions = if let PyroEvent::Notification(PyroRuntimeNotification::SubscribedToStream(s)) = &output {
+                    subs.len() + usize::from(!subs.contains(s))
+                } else {
+                    subs.len()
+                };
+
+                if let Some(limit) = check_limits(&limits, events_this_second, subscriptions, runtime.buffered()) {
+                    tracing::warn!(
+                        name = args.program.name,
+                        id = env.client.id(),
+                        %limit,
+                        correlation = %args.context.correlation,
+                        "stopping program because it went over its limits",
+                    );
+
+                    let _ = args.program.output.send(
+                        SubscribeResponses::Programs(ProgramResponses::LimitExceeded(limit)).into()).await;
+
+                    break;
+                }
+
                 match output {
                     PyroEvent::Value(output) => {
                         match from_runtime_value_to_json(output) {
@@ -163,12 +256,16 @@ pub async fn run(mut env: ProcessEnv<Managed>) -> eyre::Result<()> {
                                     stream_name: args.program.name.clone(),
                                     revision,
                                     data: Bytes::from(serde_json::to_vec(&json)?),
+                                    metadata: Default::default(),
                                     position: u64::MAX,
+                                    created: clock.now(),
+                                    link: None,
+                                    epoch: 0,
                                 });
 
                                 revision += 1;
 
-                                if args.program.output.send(resp.into()).is_err() {
+                                if args.program.output.send(resp.into()).await.is_err() {
                                     tracing::warn!(
                                         correlation = %args.context.correlation,
                                         "exiting program because nothing is listening",
@@ -193,26 +290,37 @@ pub async fn run(mut env: ProcessEnv<Managed>) -> eyre::Result<()> {
                                     "error when converting runtime value to JSON",
                                 );
 
-                                let _ = args.program.output.send(SubscribeResponses::Error(e).into());
+                                let _ = args.program.output.send(SubscribeResponses::Error(e).into()).await;
                                 break;
                             }
                         }
                     }
 
+                    PyroEvent::Log(line) => {
+                        if args.program.output.send(SubscribeResponses::Programs(ProgramResponses::Log(line)).into()).await.is_err() {
+                            tracing::warn!(
+                                correlation = %args.context.correlation,
+                                "exiting program because nothing is listening",
+                            );
+
+                            break;
+                        }
+                    }
+
                     PyroEvent::Notification(notif) => {
                         match notif {
                             PyroRuntimeNotification::SubscribedToStream(s) => {
                                 subs.insert(s.clone());
 
                                 let _ = args.program.output.send(
-                                    SubscribeResponses::Programs(ProgramResponses::Subscribed(s)).into());
+                                    SubscribeResponses::Programs(ProgramResponses::Subscribed(s)).into()).await;
                             }
 
                             PyroRuntimeNotification::UnsubscribedToStream(s) => {
                                 subs.remove(&s);
 
                                 let _ = args.program.output.send(
-                                    SubscribeResponses::Programs(ProgramResponses::Unsubscribed(s)).into());
+                                    SubscribeResponses::Programs(ProgramResponses::Unsubscribed(s)).into()).await;
                             }
                         }
                     }
@@ -1,11 +1,15 @@
 use std::usize;
 
-use geth_common::Direction;
+use bytes::BytesMut;
+use chrono::{TimeDelta, TimeZone, Utc};
+use geth_common::{
+    Direction, Epochs, ExpectedRevision, Propose, ReadStream, Revision, StreamMetadata, StreamPage,
+};
 use geth_domain::index::BlockEntry;
-use geth_mikoshi::hashing::mikoshi_hash;
+use geth_mikoshi::wal::LogReader;
 use uuid::Uuid;
 
-use crate::{Options, RequestContext};
+use crate::{MockClock, Options, RequestContext, process::tests::Foo};
 
 #[tokio::test]
 async fn test_store_read() -> eyre::Result<()> {
@@ -22,11 +26,10 @@ async fn test_store_read() -> eyre::Result<()> {
         });
     }
 
-    client.store(ctx, expected.clone()).await?;
+    client.store(ctx, expected.clone(), None, None).await?;
     let entries = client
         .read(ctx, 2, 0, usize::MAX, Direction::Forward)
         .await?
-        .ok()?
         .collect()
         .await?;
 
@@ -50,7 +53,7 @@ async fn test_last_revision_when_exists() -> eyre::Result<()> {
         });
     }
 
-    client.store(ctx, expected.clone()).await?;
+    client.store(ctx, expected.clone(), None, None).await?;
     let revision = client.latest_revision(ctx, 2).await?.revision();
 
     assert!(revision.is_some());
@@ -82,15 +85,291 @@ async fn test_empty_index_does_not_hang() -> eyre::Result<()> {
     let mut streaming = client
         .read(
             ctx,
-            mikoshi_hash(&stream_name),
+            client.key(&stream_name),
             0,
             usize::MAX,
             Direction::Forward,
         )
-        .await?
-        .ok()?;
+        .await?;
 
     while let Some(_) = streaming.next().await? {}
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_list_streams() -> eyre::Result<()> {
+    let embedded = crate::run_embedded(&Options::in_mem_no_grpc()).await?;
+    let client = embedded.manager().new_index_client().await?;
+    let writer = embedded.manager().new_writer_client().await?;
+    let ctx = RequestContext::new();
+
+    // Streams of a namespace are stored under a reserved prefix.
+    for stream in [
+        "orders-2",
+        "orders-1",
+        "users-1",
+        "orders-3",
+        "$ns:tenant-a:orders-1",
+        "$ns:tenant-a:orders-2",
+    ] {
+        writer
+            .append(
+                ctx,
+                stream.to_string(),
+                ExpectedRevision::Any,
+                vec![Propose::from_value(&Foo { baz: 1 })?],
+            )
+            .await?
+            .success()?;
+    }
+
+    writer
+        .delete(ctx, "orders-3".to_string(), ExpectedRevision::Any, false)
+        .await?
+        .success()?;
+
+    // Truncations go to the metadata stream of their target, which is not listed.
+    writer
+        .truncate(ctx, "users-1".to_string(), 0)
+        .await?
+        .success()?;
+
+    let page = client.list_streams(ctx, None, 0, 10).await?;
+    assert_eq!(
+        page,
+        StreamPage {
+            streams: vec![
+                "orders-1".to_string(),
+                "orders-2".to_string(),
+                "users-1".to_string(),
+            ],
+            total: 3,
+        }
+    );
+
+    // Namespaced streams take no room in the pages of the default namespace.
+    let page = client.list_streams(ctx, None, 1, 2).await?;
+    assert_eq!(
+        page,
+        StreamPage {
+            streams: vec!["orders-2".to_string(), "users-1".to_string()],
+            total: 3,
+        }
+    );
+
+    let page = client
+        .list_streams(ctx, Some("$ns:tenant-a:".to_string()), 1, 10)
+        .await?;
+    assert_eq!(
+        page,
+        StreamPage {
+            streams: vec!["$ns:tenant-a:orders-2".to_string()],
+            total: 2,
+        }
+    );
+
+    let page = client
+        .list_streams(ctx, Some("orders-".to_string()), 1, 10)
+        .await?;
+    assert_eq!(
+        page,
+        StreamPage {
+            streams: vec!["orders-2".to_string()],
+            total: 2,
+        }
+    );
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn test_scavenge_drops_deleted_stream_events() -> eyre::Result<()> {
+    let db = std::env::temp_dir().join(Uuid::new_v4().to_string());
+    let options = Options::new(
+        "127.0.0.1".to_string(),
+        2_113,
+        db.to_string_lossy().to_string(),
+    )
+    .disable_grpc();
+    let embedded = crate::run_embedded(&options).await?;
+    let writer = embedded.manager().new_writer_client().await?;
+    let reader = embedded.manager().new_reader_client().await?;
+    let ctx = RequestContext::new();
+
+    for (stream, count) in [("deleted", 3), ("kept", 2)] {
+        writer
+            .append(
+                ctx,
+                stream.to_string(),
+                ExpectedRevision::Any,
+                (0..count)
+                    .map(|baz| Propose::from_value(&Foo { baz }))
+                    .collect::<Result<Vec<_>, _>>()?,
+            )
+            .await?
+            .success()?;
+    }
+
+    writer
+        .delete(ctx, "deleted".to_string(), ExpectedRevision::Any, false)
+        .await?
+        .success()?;
+
+    writer
+        .append(
+            ctx,
+            "deleted".to_string(),
+            ExpectedRevision::NoStream,
+            vec![Propose::from_value(&Foo { baz: 3 })?],
+        )
+        .await?
+        .success()?;
+
+    // Only completed chunks are scavenged.
+    let container = crate::get_chunk_container();
+    let position = LogReader::new(container.clone()).get_writer_checkpoint()?;
+    container.new_chunk(&mut BytesMut::new(), position)?;
+
+    let scavenged = embedded.manager().scavenge().await?;
+
+    assert_eq!(1, scavenged.chunks);
+    assert_eq!(3, scavenged.dropped_entries);
+    assert!(scavenged.reclaimed > 0);
+
+    let read = |stream_name: &str| {
+        let params = ReadStream {
+            stream_name: stream_name.to_string(),
+            direction: Direction::Forward,
+            revision: Revision::Start,
+            max_count: None,
+            resolve_links: false,
+            epochs: Epochs::All,
+        };
+
+        let reader = reader.clone();
+        async move {
+            let mut streaming = reader.read_stream(ctx, params).await?.success()?;
+            let mut revisions = vec![];
+
+            while let Some(record) = streaming.next().await? {
+                revisions.push((record.epoch, record.revision));
+            }
+
+            eyre::Ok(revisions)
+        }
+    };
+
+    // The tombstone took revision 3.
+    assert_eq!(vec![(1, 4)], read("deleted").await?);
+    assert_eq!(vec![(0, 0), (0, 1)], read("kept").await?);
+
+    embedded.shutdown().await?;
+    std::fs::remove_dir_all(db)?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_scavenge_drops_truncated_capped_and_expired_events() -> eyre::Result<()> {
+    let db = std::env::temp_dir().join(Uuid::new_v4().to_string());
+    let clock = MockClock::new(Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap());
+    let options = Options::new(
+        "127.0.0.1".to_string(),
+        2_113,
+        db.to_string_lossy().to_string(),
+    )
+    .disable_grpc()
+    .with_clock(clock.clone());
+    let embedded = crate::run_embedded(&options).await?;
+    let writer = embedded.manager().new_writer_client().await?;
+    let reader = embedded.manager().new_reader_client().await?;
+    let ctx = RequestContext::new();
+
+    for stream in ["truncated", "capped", "expired", "kept"] {
+        writer
+            .append(
+                ctx,
+                stream.to_string(),
+                ExpectedRevision::Any,
+                (0..5)
+                    .map(|baz| Propose::from_value(&Foo { baz }))
+                    .collect::<Result<Vec<_>, _>>()?,
+            )
+            .await?
+            .success()?;
+    }
+
+    writer
+        .truncate(ctx, "truncated".to_string(), 3)
+        .await?
+        .success()?;
+
+    writer
+        .set_metadata(
+            ctx,
+            "capped".to_string(),
+            StreamMetadata {
+                max_count: Some(2),
+                ..Default::default()
+            },
+        )
+        .await?
+        .success()?;
+
+    clock.advance(TimeDelta::hours(1));
+
+    writer
+        .set_metadata(
+            ctx,
+            "expired".to_string(),
+            StreamMetadata {
+                max_age_secs: Some(60),
+                ..Default::default()
+            },
+        )
+        .await?
+        .success()?;
+
+    let container = crate::get_chunk_container();
+    let position = LogReader::new(container.clone()).get_writer_checkpoint()?;
+    container.new_chunk(&mut BytesMut::new(), position)?;
+
+    let scavenged = embedded.manager().scavenge().await?;
+
+    assert_eq!(1, scavenged.chunks);
+    assert_eq!(11, scavenged.dropped_entries);
+
+    let read = |stream_name: &str| {
+        let params = ReadStream {
+            stream_name: stream_name.to_string(),
+            direction: Direction::Forward,
+            revision: Revision::Start,
+            max_count: None,
+            resolve_links: false,
+            epochs: Epochs::All,
+        };
+
+        let reader = reader.clone();
+        async move {
+            let mut streaming = reader.read_stream(ctx, params).await?.success()?;
+            let mut revisions = vec![];
+
+            while let Some(record) = streaming.next().await? {
+                revisions.push(record.revision);
+            }
+
+            eyre::Ok(revisions)
+        }
+    };
+
+    assert_eq!(vec![3, 4], read("truncated").await?);
+    assert_eq!(vec![3, 4], read("capped").await?);
+    assert!(read("expired").await?.is_empty());
+    assert_eq!(vec![0, 1, 2, 3, 4], read("kept").await?);
+
+    embedded.shutdown().await?;
+    std::fs::remove_dir_all(db)?;
+
+    Ok(())
+}
@@ -1,11 +1,15 @@
+use std::time::{Duration, Instant};
+
 use crate::{
     Options, RequestContext,
     process::{
-        Catalog, Mail, Proc, messages::TestSinkResponses, sink::SinkClient,
-        start_process_manager_with_catalog,
+        Catalog, Item, Mail, Mailbox, Proc, SpawnError, SpawnResult, Undelivered,
+        messages::TestSinkResponses, sink::SinkClient, start_process_manager_with_catalog,
     },
 };
 use bytes::{BufMut, BytesMut};
+use tokio::task::JoinSet;
+use uuid::Uuid;
 
 fn test_catalog() -> Catalog {
     Catalog::builder()
@@ -79,6 +83,29 @@ async fn test_find_proc() -> eyre::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_unregistered_proc_is_reported() -> eyre::Result<()> {
+    let manager =
+        start_process_manager_with_catalog(Options::in_mem_no_grpc(), test_catalog()).await?;
+
+    assert!(manager.has(Proc::Echo).await?);
+    assert!(!manager.has(Proc::PubSub).await?);
+    assert!(manager.find(Proc::PubSub).await?.is_none());
+
+    match manager.wait_for(Proc::PubSub).await? {
+        SpawnResult::Failure {
+            error: SpawnError::NotRegistered,
+            ..
+        } => {}
+        _ => eyre::bail!("expected the process to be reported as not registered"),
+    }
+
+    // The manager keeps serving the registered processes.
+    manager.wait_for(Proc::Echo).await?.must_succeed()?;
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_shutdown_reported_properly() -> eyre::Result<()> {
     let manager =
@@ -141,3 +168,132 @@ async fn test_stream_returns_when_proc_panicked() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_request_times_out_when_proc_never_replies() -> eyre::Result<()> {
+    let mut options = Options::in_mem_no_grpc();
+    options.request_timeout_in_ms = 100;
+
+    let manager = start_process_manager_with_catalog(options, test_catalog()).await?;
+    // The sink process only serves streams, mails are left unanswered.
+    let proc_id = manager.wait_for(Proc::Sink).await?.must_succeed()?;
+
+    let outcome = manager
+        .request(
+            RequestContext::new(),
+            proc_id,
+            TestSinkResponses::Stream(42).into(),
+        )
+        .await;
+
+    let Err(error) = outcome else {
+        panic!("request should have timed out");
+    };
+
+    assert!(error.to_string().contains("timed out"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_backpressured_request_does_not_time_out() -> eyre::Result<()> {
+    let mut options = Options::in_mem_no_grpc();
+    options.request_timeout_in_ms = 100;
+
+    let manager = start_process_manager_with_catalog(options, test_catalog()).await?;
+    // The sink process only serves streams, mails are left unanswered.
+    let proc_id = manager.wait_for(Proc::Sink).await?.must_succeed()?;
+
+    let outcome = tokio::time::timeout(
+        Duration::from_millis(500),
+        manager.request_with_backpressure(
+            RequestContext::new(),
+            proc_id,
+            TestSinkResponses::Stream(42).into(),
+        ),
+    )
+    .await;
+
+    assert!(
+        outcome.is_err(),
+        "request should still be waiting on its reply"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_processes_sending_to_each_other_under_load_dont_deadlock() -> eyre::Result<()> {
+    let mut options = Options::in_mem_no_grpc();
+    options.mailbox_capacity = 2;
+
+    let catalog = Catalog::builder().register_multiple(2, Proc::Echo).build();
+    let manager = start_process_manager_with_catalog(options, catalog).await?;
+    let ping = manager.wait_for(Proc::Echo).await?.must_succeed()?;
+    let pong = manager.wait_for(Proc::Echo).await?.must_succeed()?;
+
+    // Each echo process replies to the other, so those mails bounce between them for good and
+    // keep both mailboxes full.
+    let ping_client = manager.new_with_overrides(ping, Proc::Echo);
+    for num in 0..100 {
+        ping_client.send(
+            RequestContext::new(),
+            pong,
+            TestSinkResponses::Stream(num).into(),
+        )?;
+    }
+
+    let mut requests = JoinSet::new();
+    for num in 0..100u64 {
+        let manager = manager.clone();
+        let dest = if num % 2 == 0 { ping } else { pong };
+
+        requests.spawn(async move {
+            let resp = manager
+                .request_with_backpressure(
+                    RequestContext::new(),
+                    dest,
+                    TestSinkResponses::Stream(num).into(),
+                )
+                .await?;
+
+            eyre::Ok((num, sink_response(resp)))
+        });
+    }
+
+    let replies = tokio::time::timeout(Duration::from_secs(10), requests.join_all())
+        .await
+        .expect("processes to keep replying under load");
+
+    for reply in replies {
+        let (sent, received) = reply?;
+        assert_eq!(sent, received);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_mailbox_sheds_when_full() {
+    let mail = || {
+        Item::Mail(Mail {
+            origin: 0,
+            correlation: Uuid::new_v4(),
+            context: RequestContext::new(),
+            payload: TestSinkResponses::Stream(42).into(),
+            created: Instant::now(),
+        })
+    };
+
+    let (sender, mut queue) = tokio::sync::mpsc::channel(1);
+    let mailbox = Mailbox::Tokio(sender);
+
+    assert!(mailbox.send(mail()).is_ok());
+    assert!(matches!(mailbox.send(mail()), Err(Undelivered::Full(_))));
+
+    assert!(queue.try_recv().is_ok());
+    assert!(mailbox.send(mail()).is_ok());
+
+    drop(queue);
+    assert!(matches!(mailbox.send(mail()), Err(Undelivered::Closed)));
+}
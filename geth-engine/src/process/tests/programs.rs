@@ -2,8 +2,8 @@ use std::any::type_name;
 
 use bytes::Bytes;
 use geth_common::{
-    ContentType, ExpectedRevision, Propose, SubscriptionConfirmation, SubscriptionEvent,
-    SubscriptionNotification,
+    ContentType, ExpectedRevision, ProgramLimit, ProgramLimits, Propose, RestartPolicy,
+    SubscriptionConfirmation, SubscriptionEvent, SubscriptionNotification, UnsubscribeReason,
 };
 use uuid::Uuid;
 
@@ -24,7 +24,13 @@ pub async fn test_program_created() -> eyre::Result<()> {
 
     let stream_name = "foobar";
     let mut streaming = client
-        .subscribe_to_program(ctx, "echo", include_str!("./resources/programs/echo.pyro"))
+        .subscribe_to_program(
+            ctx,
+            "echo",
+            include_str!("./resources/programs/echo.pyro"),
+            false,
+            RestartPolicy::Never,
+        )
         .await?;
 
     while let Some(e) = streaming.next().await? {
@@ -90,7 +96,13 @@ pub async fn test_program_list() -> eyre::Result<()> {
     let ctx = RequestContext::new();
 
     let mut ignored = client
-        .subscribe_to_program(ctx, "echo", include_str!("./resources/programs/echo.pyro"))
+        .subscribe_to_program(
+            ctx,
+            "echo",
+            include_str!("./resources/programs/echo.pyro"),
+            false,
+            RestartPolicy::Never,
+        )
         .await?;
 
     ignored.wait_until_confirmation().await?;
@@ -110,7 +122,13 @@ pub async fn test_program_stats() -> eyre::Result<()> {
     let ctx = RequestContext::new();
 
     let mut program_out = client
-        .subscribe_to_program(ctx, "echo", include_str!("./resources/programs/echo.pyro"))
+        .subscribe_to_program(
+            ctx,
+            "echo",
+            include_str!("./resources/programs/echo.pyro"),
+            false,
+            RestartPolicy::Never,
+        )
         .await?;
 
     program_out.wait_until_confirmation().await?;
@@ -132,6 +150,7 @@ pub async fn test_program_stats() -> eyre::Result<()> {
                             content_type: ContentType::Binary,
                             class: "created".to_string(),
                             data: Bytes::default(),
+                            metadata: Default::default(),
                         }],
                     )
                     .await?
@@ -160,6 +179,8 @@ pub async fn test_program_stats() -> eyre::Result<()> {
         include_str!("./resources/programs/echo.pyro")
     );
     assert_eq!(program.subscriptions, vec!["foobar".to_string()]);
+    assert_eq!(program.limits, ProgramLimits::default());
+    assert_eq!(program.restarts, 0);
 
     embedded.shutdown().await
 }
@@ -179,7 +200,13 @@ pub async fn test_program_stop() -> eyre::Result<()> {
 
     let stream_name = "foobar";
     let mut streaming = client
-        .subscribe_to_program(ctx, "echo", include_str!("./resources/programs/echo.pyro"))
+        .subscribe_to_program(
+            ctx,
+            "echo",
+            include_str!("./resources/programs/echo.pyro"),
+            false,
+            RestartPolicy::Never,
+        )
         .await?;
 
     streaming.wait_until_confirmation().await?;
@@ -232,3 +259,64 @@ pub async fn test_program_stop() -> eyre::Result<()> {
 
     embedded.shutdown().await
 }
+
+#[tokio::test]
+pub async fn test_program_over_event_rate_is_stopped() -> eyre::Result<()> {
+    let limits = ProgramLimits {
+        max_events_per_sec: 10,
+        ..ProgramLimits::default()
+    };
+    let embedded =
+        crate::run_embedded(&Options::in_mem_no_grpc().with_program_limits(limits)).await?;
+    let client = embedded.manager().new_subscription_client().await?;
+    let writer = embedded.manager().new_writer_client().await?;
+    let ctx = RequestContext::new();
+
+    let mut events = vec![];
+
+    for i in 0..100 {
+        events.push(Propose::from_value(&Foo { baz: i })?);
+    }
+
+    // The program catches up on the whole stream as fast as it can.
+    writer
+        .append(ctx, "foobar".to_string(), ExpectedRevision::Any, events)
+        .await?
+        .success()?;
+
+    let mut streaming = client
+        .subscribe_to_program(
+            ctx,
+            "echo",
+            include_str!("./resources/programs/echo.pyro"),
+            false,
+            RestartPolicy::Never,
+        )
+        .await?;
+
+    let mut count = 0usize;
+    let mut reason = None;
+
+    while let Some(event) = streaming.next().await? {
+        match event {
+            SubscriptionEvent::EventAppeared(_) => count += 1,
+
+            SubscriptionEvent::Unsubscribed(r) => {
+                reason = Some(r);
+                break;
+            }
+
+            _ => {}
+        }
+    }
+
+    assert!(count <= 10);
+    assert!(matches!(
+        reason,
+        Some(UnsubscribeReason::LimitExceeded(
+            ProgramLimit::EventsPerSecond(10)
+        ))
+    ));
+
+    embedded.shutdown().await
+}
@@ -1,8 +1,19 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::usize;
 
 use crate::Options;
 use crate::RequestContext;
-use geth_common::{Direction, ExpectedRevision, Propose, Revision};
+use crate::reading::{FieldSelector, record_try_from};
+use bytes::{BufMut, Bytes, BytesMut};
+use geth_common::{
+    AppendError, AppendStreamCompleted, ContentType, Direction, Epochs, ExpectedRevision, LinkTo,
+    Propose, ReadStream, Record, RecordLink, Revision,
+};
+use geth_mikoshi::compression::Compression;
+use geth_mikoshi::storage::{FaultInjector, FileId, IoOp, Storage};
+use geth_mikoshi::wal::LogEntry;
+use proptest::prelude::*;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -61,6 +72,116 @@ async fn test_reader_proc_simple() -> eyre::Result<()> {
     embedded.shutdown().await
 }
 
+#[tokio::test]
+async fn test_read_resolves_links() -> eyre::Result<()> {
+    let embedded = crate::run_embedded(&Options::in_mem_no_grpc()).await?;
+    let writer_client = embedded.manager().new_writer_client().await?;
+    let reader_client = embedded.manager().new_reader_client().await?;
+    let ctx = RequestContext::new();
+    let target_stream = Uuid::new_v4().to_string();
+    let index_stream = Uuid::new_v4().to_string();
+
+    writer_client
+        .append(
+            ctx,
+            target_stream.clone(),
+            ExpectedRevision::Any,
+            vec![
+                Propose::from_value(&Foo { baz: 1 })?,
+                Propose::from_value(&Foo { baz: 2 })?,
+            ],
+        )
+        .await?
+        .success()?;
+
+    writer_client
+        .append(
+            ctx,
+            index_stream.clone(),
+            ExpectedRevision::Any,
+            vec![
+                LinkTo {
+                    target_stream: target_stream.clone(),
+                    target_revision: 1,
+                }
+                .propose(),
+            ],
+        )
+        .await?
+        .success()?;
+
+    let mut stream = reader_client
+        .read_resolving_links(
+            ctx,
+            &index_stream,
+            Revision::Start,
+            Direction::Forward,
+            usize::MAX,
+        )
+        .await?
+        .success()?;
+
+    let record = stream.next().await?.unwrap();
+    assert_eq!(target_stream, record.stream_name);
+    assert_eq!(1, record.revision);
+    assert_eq!(2, record.as_value::<Foo>()?.baz);
+    assert_eq!(
+        Some(RecordLink {
+            stream_name: index_stream.clone(),
+            revision: 0,
+            position: record.link.as_ref().unwrap().position,
+        }),
+        record.link
+    );
+    assert!(stream.next().await?.is_none());
+
+    let mut stream = reader_client
+        .read(
+            ctx,
+            &index_stream,
+            Revision::Start,
+            Direction::Forward,
+            usize::MAX,
+        )
+        .await?
+        .success()?;
+
+    let record = stream.next().await?.unwrap();
+    assert_eq!(LinkTo::CLASS, record.class);
+    assert!(record.link.is_none());
+
+    // A dangling link is returned as-is.
+    writer_client
+        .delete(ctx, target_stream.clone(), ExpectedRevision::Any, false)
+        .await?
+        .success()?;
+
+    let mut stream = reader_client
+        .read_resolving_links(
+            ctx,
+            &index_stream,
+            Revision::Start,
+            Direction::Forward,
+            usize::MAX,
+        )
+        .await?
+        .success()?;
+
+    let record = stream.next().await?.unwrap();
+    assert_eq!(LinkTo::CLASS, record.class);
+    assert_eq!(index_stream, record.stream_name);
+    assert!(record.link.is_none());
+    assert_eq!(
+        LinkTo {
+            target_stream,
+            target_revision: 1,
+        },
+        record.as_value::<LinkTo>()?
+    );
+
+    embedded.shutdown().await
+}
+
 #[tokio::test]
 async fn test_empty_read_does_not_hang() -> eyre::Result<()> {
     let embedded = crate::run_embedded(&Options::in_mem_no_grpc()).await?;
@@ -80,6 +201,609 @@ async fn test_empty_read_does_not_hang() -> eyre::Result<()> {
         .success()?;
 
     while let Some(_) = streaming.next().await? {}
+    assert!(streaming.tail_revision().is_none());
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn test_read_reports_tail_revision() -> eyre::Result<()> {
+    let embedded = crate::run_embedded(&Options::in_mem_no_grpc()).await?;
+    let writer_client = embedded.manager().new_writer_client().await?;
+    let reader_client = embedded.manager().new_reader_client().await?;
+    let ctx = RequestContext::new();
+    let stream_name = Uuid::new_v4().to_string();
+    let mut proposes = vec![];
+
+    for i in 0..5 {
+        proposes.push(Propose::from_value(&Foo { baz: i })?);
+    }
+
+    writer_client
+        .append(ctx, stream_name.clone(), ExpectedRevision::Any, proposes)
+        .await?
+        .success()?;
+
+    let mut streaming = reader_client
+        .read(ctx, &stream_name, Revision::Start, Direction::Forward, 2)
+        .await?
+        .success()?;
+
+    let mut last = None;
+    while let Some(record) = streaming.next().await? {
+        last = Some(record.revision);
+    }
+
+    // More to read, the tail is ahead of the last record.
+    assert_eq!(Some(1), last);
+    assert_eq!(Some(4), streaming.tail_revision());
+
+    let mut streaming = reader_client
+        .read(
+            ctx,
+            &stream_name,
+            Revision::Revision(2),
+            Direction::Forward,
+            usize::MAX,
+        )
+        .await?
+        .success()?;
+
+    while let Some(record) = streaming.next().await? {
+        last = Some(record.revision);
+    }
+
+    assert_eq!(Some(4), last);
+    assert_eq!(last, streaming.tail_revision());
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn test_read_recreated_stream_epochs() -> eyre::Result<()> {
+    let embedded = crate::run_embedded(&Options::in_mem_no_grpc()).await?;
+    let writer_client = embedded.manager().new_writer_client().await?;
+    let reader_client = embedded.manager().new_reader_client().await?;
+    let ctx = RequestContext::new();
+    let stream_name = Uuid::new_v4().to_string();
+    let read = |direction, epochs| ReadStream {
+        stream_name: stream_name.clone(),
+        direction,
+        revision: match direction {
+            Direction::Forward => Revision::Start,
+            Direction::Backward => Revision::End,
+        },
+        max_count: None,
+        resolve_links: false,
+        epochs,
+    };
+
+    writer_client
+        .append(
+            ctx,
+            stream_name.clone(),
+            ExpectedRevision::Any,
+            vec![
+                Propose::from_value(&Foo { baz: 0 })?,
+                Propose::from_value(&Foo { baz: 1 })?,
+            ],
+        )
+        .await?
+        .success()?;
+
+    writer_client
+        .delete(ctx, stream_name.clone(), ExpectedRevision::Any, false)
+        .await?
+        .success()?;
+
+    // Only an append expecting no stream recreates it.
+    let outcome = writer_client
+        .append(
+            ctx,
+            stream_name.clone(),
+            ExpectedRevision::Any,
+            vec![Propose::from_value(&Foo { baz: 2 })?],
+        )
+        .await?;
+    assert!(matches!(
+        outcome,
+        AppendStreamCompleted::Error(AppendError::StreamDeleted)
+    ));
+
+    writer_client
+        .append(
+            ctx,
+            stream_name.clone(),
+            ExpectedRevision::NoStream,
+            vec![
+                Propose::from_value(&Foo { baz: 2 })?,
+                Propose::from_value(&Foo { baz: 3 })?,
+            ],
+        )
+        .await?
+        .success()?;
+
+    let collect = |records: Vec<Record>| {
+        records
+            .into_iter()
+            .map(|r| (r.epoch, r.revision))
+            .collect::<Vec<_>>()
+    };
+
+    let mut records = vec![];
+    let mut streaming = reader_client
+        .read_stream(ctx, read(Direction::Forward, Epochs::Current))
+        .await?
+        .success()?;
+    while let Some(record) = streaming.next().await? {
+        records.push(record);
+    }
+
+    // The tombstone took revision 2.
+    assert_eq!(vec![(1, 3), (1, 4)], collect(records));
+
+    let mut records = vec![];
+    let mut streaming = reader_client
+        .read_stream(ctx, read(Direction::Backward, Epochs::Current))
+        .await?
+        .success()?;
+    while let Some(record) = streaming.next().await? {
+        records.push(record);
+    }
+
+    assert_eq!(vec![(1, 4), (1, 3)], collect(records));
+
+    let mut records = vec![];
+    let mut streaming = reader_client
+        .read_stream(ctx, read(Direction::Forward, Epochs::All))
+        .await?
+        .success()?;
+    while let Some(record) = streaming.next().await? {
+        records.push(record);
+    }
+
+    assert_eq!(vec![(0, 0), (0, 1), (1, 3), (1, 4)], collect(records));
+
+    writer_client
+        .delete(ctx, stream_name.clone(), ExpectedRevision::Any, false)
+        .await?
+        .success()?;
+
+    let outcome = reader_client
+        .read_stream(ctx, read(Direction::Forward, Epochs::Current))
+        .await?;
+    assert!(outcome.is_stream_deleted());
+
+    let mut records = vec![];
+    let mut streaming = reader_client
+        .read_stream(ctx, read(Direction::Backward, Epochs::All))
+        .await?
+        .success()?;
+    while let Some(record) = streaming.next().await? {
+        records.push(record);
+    }
+
+    assert_eq!(vec![(1, 4), (1, 3), (0, 1), (0, 0)], collect(records));
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn test_reader_projection() -> eyre::Result<()> {
+    let embedded = crate::run_embedded(&Options::in_mem_no_grpc()).await?;
+    let writer_client = embedded.manager().new_writer_client().await?;
+    let reader_client = embedded.manager().new_reader_client().await?;
+    let ctx = RequestContext::new();
+    let stream_name = Uuid::new_v4().to_string();
+
+    let mut proposes = vec![];
+    for i in 0..10 {
+        proposes.push(Propose::from_value(&Foo { baz: i })?);
+    }
+
+    proposes.push(Propose {
+        id: Uuid::new_v4(),
+        content_type: ContentType::Binary,
+        class: "binary".to_string(),
+        data: vec![1, 2, 3].into(),
+        metadata: Default::default(),
+    });
+
+    proposes.push(Propose {
+        id: Uuid::new_v4(),
+        content_type: ContentType::Json,
+        class: "malformed".to_string(),
+        data: Bytes::from_static(b"{ not json"),
+        metadata: Default::default(),
+    });
+
+    proposes.push(Propose::from_value(&Foo { baz: 12 })?);
+
+    writer_client
+        .append(ctx, stream_name.clone(), ExpectedRevision::Any, proposes)
+        .await?
+        .success()?;
+
+    let selectors = vec![
+        FieldSelector::parse("$.baz")?,
+        FieldSelector::parse("$.missing")?,
+    ];
+
+    let mut stream = reader_client
+        .read_projection(
+            ctx,
+            &stream_name,
+            Revision::Start,
+            Direction::Forward,
+            usize::MAX,
+            selectors,
+        )
+        .await?
+        .success()?;
+
+    let mut revisions = vec![];
+    while let Some(record) = stream.next().await? {
+        let expected = if record.revision < 10 {
+            record.revision
+        } else {
+            12
+        };
+
+        assert_eq!(
+            vec![serde_json::json!(expected), serde_json::Value::Null],
+            record.values
+        );
+
+        revisions.push(record.revision);
+    }
+
+    // Neither the binary record nor the malformed JSON one ends the read.
+    assert_eq!((0..10).chain([12]).collect::<Vec<_>>(), revisions);
+
+    embedded.shutdown().await
+}
+
+#[test]
+fn test_field_selector_parsing() {
+    let value = serde_json::json!({ "foo": { "bar": [{ "baz": 42 }] } });
+
+    let selector = FieldSelector::parse("$.foo.bar[0].baz").unwrap();
+    assert_eq!(Some(&serde_json::json!(42)), selector.select(&value));
+
+    let selector = FieldSelector::parse("foo.bar[1]").unwrap();
+    assert_eq!(None, selector.select(&value));
+
+    assert!(FieldSelector::parse("$").is_err());
+    assert!(FieldSelector::parse("foo..bar").is_err());
+    assert!(FieldSelector::parse("foo.").is_err());
+    assert!(FieldSelector::parse("foo[0].").is_err());
+    assert!(FieldSelector::parse("foo[x]").is_err());
+}
+
+#[tokio::test]
+async fn test_dropped_read_stops_reader() -> eyre::Result<()> {
+    let embedded = crate::run_embedded(&Options::in_mem_no_grpc()).await?;
+    let writer_client = embedded.manager().new_writer_client().await?;
+    let reader_client = embedded.manager().new_reader_client().await?;
+    let ctx = RequestContext::new();
+    let stream_name = Uuid::new_v4().to_string();
+
+    for batch in 0..10 {
+        let mut proposes = vec![];
+        for i in 0..1_000 {
+            proposes.push(Propose::from_value(&Foo {
+                baz: batch * 1_000 + i,
+            })?);
+        }
+
+        writer_client
+            .append(ctx, stream_name.clone(), ExpectedRevision::Any, proposes)
+            .await?
+            .success()?;
+    }
+
+    // Counts the reads of the log, nothing fails.
+    let chunk_reads = Arc::new(AtomicU32::new(0));
+    let Storage::InMemory(storage) = crate::get_storage() else {
+        eyre::bail!("expected an in-memory storage");
+    };
+
+    let counter = chunk_reads.clone();
+    storage.inject_faults(FaultInjector::default().fail_when(move |op, id| {
+        if op == IoOp::Read && matches!(id, FileId::Chunk { .. }) {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+
+        false
+    }));
+
+    let mut stream = reader_client
+        .read(
+            ctx,
+            &stream_name,
+            Revision::Start,
+            Direction::Forward,
+            usize::MAX,
+        )
+        .await?
+        .success()?;
+
+    for _ in 0..3 {
+        assert!(stream.next().await?.is_some());
+    }
+
+    drop(stream);
+
+    // Gives the reader time to notice, then it must not read anything more.
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    let reads_after_drop = chunk_reads.load(Ordering::Relaxed);
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+    assert_eq!(reads_after_drop, chunk_reads.load(Ordering::Relaxed));
+    // Every entry takes several reads, going through the whole stream takes way more.
+    assert!(reads_after_drop < 10_000, "{reads_after_drop} reads");
+    storage.clear_faults();
+
+    // The abandoned read must not prevent new reads from being served.
+    let mut stream = tokio::time::timeout(
+        std::time::Duration::from_secs(5),
+        reader_client.read(ctx, &stream_name, Revision::Start, Direction::Forward, 1),
+    )
+    .await??
+    .success()?;
+
+    let record = stream.next().await?.unwrap();
+    assert_eq!(0, record.revision);
+    assert!(stream.next().await?.is_none());
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn test_huge_read_does_not_starve_small_reads() -> eyre::Result<()> {
+    let options = Options {
+        read_quantum: 10,
+        ..Options::in_mem_no_grpc()
+    };
+
+    let embedded = crate::run_embedded(&options).await?;
+    let writer_client = embedded.manager().new_writer_client().await?;
+    let reader_client = embedded.manager().new_reader_client().await?;
+    let ctx = RequestContext::new();
+    let huge_stream = Uuid::new_v4().to_string();
+    let small_stream = Uuid::new_v4().to_string();
+    let total = 10_000u32;
+
+    for batch in 0..10 {
+        let mut proposes = vec![];
+        for i in 0..total / 10 {
+            proposes.push(Propose::from_value(&Foo {
+                baz: batch * total / 10 + i,
+            })?);
+        }
+
+        writer_client
+            .append(ctx, huge_stream.clone(), ExpectedRevision::Any, proposes)
+            .await?
+            .success()?;
+    }
+
+    writer_client
+        .append(
+            ctx,
+            small_stream.clone(),
+            ExpectedRevision::Any,
+            vec![Propose::from_value(&Foo { baz: 42 })?],
+        )
+        .await?
+        .success()?;
+
+    let mut huge = reader_client
+        .read(
+            ctx,
+            &huge_stream,
+            Revision::Start,
+            Direction::Forward,
+            usize::MAX,
+        )
+        .await?
+        .success()?;
+
+    let consumed = Arc::new(AtomicU32::new(0));
+    let huge_consumer = tokio::spawn({
+        let consumed = consumed.clone();
+        async move {
+            while huge.next().await?.is_some() {
+                consumed.fetch_add(1, Ordering::SeqCst);
+            }
+
+            eyre::Ok(())
+        }
+    });
+
+    let mut small = reader_client
+        .read(
+            ctx,
+            &small_stream,
+            Revision::Start,
+            Direction::Forward,
+            usize::MAX,
+        )
+        .await?
+        .success()?;
+
+    assert_eq!(42, small.next().await?.unwrap().as_value::<Foo>()?.baz);
+    assert!(small.next().await?.is_none());
+
+    // The small read completed while the huge one was still in progress.
+    assert!(consumed.load(Ordering::SeqCst) < total);
+
+    huge_consumer.await??;
+    assert_eq!(total, consumed.load(Ordering::SeqCst));
+
+    embedded.shutdown().await
+}
+
+fn serialized_record(stream_name: &str, class: &str, data: &[u8]) -> BytesMut {
+    let mut buffer = BytesMut::new();
+
+    buffer.put_u64_le(42);
+    buffer.put_u16_le(stream_name.len() as u16);
+    buffer.extend_from_slice(stream_name.as_bytes());
+    buffer.put_u128_le(Uuid::new_v4().to_u128_le());
+    buffer.put_u32_le(ContentType::Binary as u32);
+    buffer.put_u16_le(class.len() as u16);
+    buffer.extend_from_slice(class.as_bytes());
+    buffer.put_u32_le(data.len() as u32);
+    buffer.extend_from_slice(data);
+
+    buffer
+}
+
+proptest! {
+    #[test]
+    fn test_record_parsing_never_panics_on_random_bytes(
+        position in any::<u64>(),
+        payload in proptest::collection::vec(any::<u8>(), 0..512),
+    ) {
+        let _ = record_try_from(LogEntry {
+            position,
+            r#type: 0,
+            payload: payload.into(),
+            checksum: None,
+        });
+    }
+
+    #[test]
+    fn test_record_parsing_rejects_truncated_records(
+        stream_name in "[a-z]{1,32}",
+        class in "[a-z]{1,32}",
+        data in proptest::collection::vec(any::<u8>(), 0..128),
+        cut in any::<prop::sample::Index>(),
+    ) {
+        let buffer = serialized_record(&stream_name, &class, &data).freeze();
+        let record = record_try_from(LogEntry {
+            position: 0,
+            r#type: 0,
+            payload: buffer.clone(),
+            checksum: None,
+        })
+        .unwrap();
+
+        prop_assert_eq!(&stream_name, &record.stream_name);
+        prop_assert_eq!(&class, &record.class);
+        prop_assert_eq!(data.as_slice(), record.data.as_ref());
+
+        let truncated = buffer.slice(0..cut.index(buffer.len()));
+        let result = record_try_from(LogEntry {
+            position: 0,
+            r#type: 0,
+            payload: truncated,
+            checksum: None,
+        });
+
+        prop_assert!(result.is_err());
+    }
+}
+
+#[test]
+fn test_record_parsing_rejects_invalid_utf8() {
+    let mut buffer = serialized_record("foo", "bar", b"baz");
+    // corrupts the first byte of the stream name.
+    buffer[10] = 0xFF;
+
+    let err = record_try_from(LogEntry {
+        position: 1_234,
+        r#type: 0,
+        payload: buffer.freeze(),
+        checksum: None,
+    })
+    .unwrap_err();
+
+    assert!(err.to_string().contains("1234"));
+    assert!(format!("{err:?}").contains("invalid UTF-8 in the stream name"));
+
+    // class names are checked too.
+    let mut buffer = serialized_record("foo", "bar", b"baz");
+    buffer[35] = 0xC3;
+
+    let err = record_try_from(LogEntry {
+        position: 0,
+        r#type: 0,
+        payload: buffer.freeze(),
+        checksum: None,
+    })
+    .unwrap_err();
+
+    assert!(format!("{err:?}").contains("invalid UTF-8 in the class"));
+}
+
+#[tokio::test]
+async fn test_lz4_payloads_read_back_identical() -> eyre::Result<()> {
+    compressed_payloads_read_back_identical(Compression::Lz4).await
+}
+
+#[tokio::test]
+async fn test_zstd_payloads_read_back_identical() -> eyre::Result<()> {
+    compressed_payloads_read_back_identical(Compression::Zstd).await
+}
+
+// The engine can only run once per process, each compression gets its own test.
+async fn compressed_payloads_read_back_identical(compression: Compression) -> eyre::Result<()> {
+    let options = Options::in_mem_no_grpc().with_compression(compression, 1_024);
+    let embedded = crate::run_embedded(&options).await?;
+    let writer_client = embedded.manager().new_writer_client().await?;
+    let reader_client = embedded.manager().new_reader_client().await?;
+    let ctx = RequestContext::new();
+    let stream_name = Uuid::new_v4().to_string();
+    let large = Propose {
+        id: Uuid::new_v4(),
+        content_type: ContentType::Json,
+        class: "large".to_string(),
+        data: Bytes::from(r#"{"key":"value","count":42}"#.repeat(1_000)),
+        metadata: Bytes::new(),
+    };
+    let small = Propose::from_value(&Foo { baz: 42 })?;
+
+    // Appended one at a time so the log span of an append is the size of its record.
+    let mut spans = vec![];
+    for propose in [large.clone(), small.clone()] {
+        let result = writer_client
+            .append(
+                ctx,
+                stream_name.clone(),
+                ExpectedRevision::Any,
+                vec![propose],
+            )
+            .await?
+            .success()?;
+
+        spans.push(result.next_logical_position - result.position);
+    }
+
+    assert!(spans[0] < large.data.len() as u64 / 2, "{compression}");
+    assert!(spans[1] > small.data.len() as u64, "{compression}");
+
+    let mut stream = reader_client
+        .read(
+            ctx,
+            &stream_name,
+            Revision::Start,
+            Direction::Forward,
+            usize::MAX,
+        )
+        .await?
+        .success()?;
+
+    for expected in [large, small] {
+        let record = stream.next().await?.unwrap();
+
+        assert_eq!(expected.id, record.id);
+        assert_eq!(expected.content_type, record.content_type);
+        assert_eq!(expected.data, record.data, "{compression}");
+    }
+
+    assert!(stream.next().await?.is_none());
 
     embedded.shutdown().await
 }
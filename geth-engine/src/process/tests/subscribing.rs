@@ -1,6 +1,14 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
 use crate::Options;
 use crate::RequestContext;
-use geth_common::{ExpectedRevision, Propose, SubscriptionEvent};
+use crate::names::streams;
+use crate::process::consumer::{ConsumerResult, start_consumer};
+use geth_common::{
+    ExpectedRevision, Propose, Revision, STREAMS_FEED, StreamFeedEvent, SubscriptionConfirmation,
+    SubscriptionEvent,
+};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -58,3 +66,370 @@ async fn test_pubsub_proc_simple() -> eyre::Result<()> {
 
     embedded.shutdown().await
 }
+
+#[tokio::test]
+async fn test_subscribe_to_all_catch_up_handoff() -> eyre::Result<()> {
+    let embedded = crate::run_embedded(&Options::in_mem_no_grpc()).await?;
+    let writer_client = embedded.manager().new_writer_client().await?;
+    let ctx = RequestContext::new();
+    let stream_names = (0..3)
+        .map(|_| Uuid::new_v4().to_string())
+        .collect::<Vec<_>>();
+    let batches = 150usize;
+    let batch_size = 2usize;
+
+    let writes = tokio::spawn({
+        let writer_client = writer_client.clone();
+        let stream_names = stream_names.clone();
+        async move {
+            for i in 0..batches {
+                let events = (0..batch_size)
+                    .map(|j| {
+                        Propose::from_value(&Foo {
+                            baz: (i * batch_size + j) as u32,
+                        })
+                    })
+                    .collect::<eyre::Result<Vec<_>>>()?;
+
+                writer_client
+                    .append(
+                        ctx,
+                        stream_names[i % stream_names.len()].clone(),
+                        ExpectedRevision::Any,
+                        events,
+                    )
+                    .await?
+                    .success()?;
+
+                // Leaves the consumer a chance to start while we are still writing.
+                if i == batches / 3 {
+                    tokio::task::yield_now().await;
+                }
+            }
+
+            eyre::Ok(())
+        }
+    });
+
+    let mut consumer = match start_consumer(
+        ctx,
+        streams::ALL.to_string(),
+        Revision::Start,
+        embedded.manager().clone(),
+    )
+    .await?
+    {
+        ConsumerResult::Success(c) => c,
+        ConsumerResult::StreamDeleted => eyre::bail!("$all can't be deleted"),
+    };
+
+    let mut positions = Vec::new();
+    let mut revisions = HashMap::<String, Vec<u64>>::new();
+    let mut caught_up = false;
+    let expected = batches * batch_size;
+
+    tokio::time::timeout(Duration::from_secs(30), async {
+        while positions.len() < expected {
+            match consumer.next().await? {
+                Some(SubscriptionEvent::EventAppeared(record)) => {
+                    if !stream_names.contains(&record.stream_name) {
+                        continue;
+                    }
+
+                    positions.push(record.position);
+                    revisions
+                        .entry(record.stream_name)
+                        .or_default()
+                        .push(record.revision);
+                }
+
+                Some(SubscriptionEvent::CaughtUp) => caught_up = true,
+                Some(_) => {}
+                None => eyre::bail!("subscription ended early"),
+            }
+        }
+
+        eyre::Ok(())
+    })
+    .await??;
+
+    writes.await??;
+
+    assert!(caught_up);
+    assert!(positions.windows(2).all(|w| w[0] < w[1]));
+
+    for name in &stream_names {
+        let expected = (0..(batches / stream_names.len() * batch_size) as u64).collect::<Vec<_>>();
+        assert_eq!(Some(&expected), revisions.get(name));
+    }
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn test_subscribe_to_stream_catch_up_handoff() -> eyre::Result<()> {
+    let embedded = crate::run_embedded(&Options::in_mem_no_grpc()).await?;
+    let writer_client = embedded.manager().new_writer_client().await?;
+    let ctx = RequestContext::new();
+    let stream_name = Uuid::new_v4().to_string();
+    let expected = 300u64;
+
+    let writes = tokio::spawn({
+        let writer_client = writer_client.clone();
+        let stream_name = stream_name.clone();
+        async move {
+            for i in 0..expected {
+                writer_client
+                    .append(
+                        ctx,
+                        stream_name.clone(),
+                        ExpectedRevision::Any,
+                        vec![Propose::from_value(&Foo { baz: i as u32 })?],
+                    )
+                    .await?
+                    .success()?;
+
+                if i == expected / 3 {
+                    tokio::task::yield_now().await;
+                }
+            }
+
+            eyre::Ok(())
+        }
+    });
+
+    let mut consumer = match start_consumer(
+        ctx,
+        stream_name.clone(),
+        Revision::Start,
+        embedded.manager().clone(),
+    )
+    .await?
+    {
+        ConsumerResult::Success(c) => c,
+        ConsumerResult::StreamDeleted => eyre::bail!("stream should not be deleted"),
+    };
+
+    let mut revisions = Vec::new();
+
+    tokio::time::timeout(Duration::from_secs(30), async {
+        while (revisions.len() as u64) < expected {
+            match consumer.next().await? {
+                Some(SubscriptionEvent::EventAppeared(record)) => revisions.push(record.revision),
+                Some(_) => {}
+                None => eyre::bail!("subscription ended early"),
+            }
+        }
+
+        eyre::Ok(())
+    })
+    .await??;
+
+    writes.await??;
+
+    assert_eq!((0..expected).collect::<Vec<_>>(), revisions);
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn test_caught_up_is_sent_once_between_history_and_live() -> eyre::Result<()> {
+    let embedded = crate::run_embedded(&Options::in_mem_no_grpc()).await?;
+    let writer_client = embedded.manager().new_writer_client().await?;
+    let ctx = RequestContext::new();
+    let stream_name = Uuid::new_v4().to_string();
+    let history = 25u32;
+
+    writer_client
+        .append(
+            ctx,
+            stream_name.clone(),
+            ExpectedRevision::Any,
+            (0..history)
+                .map(|baz| Propose::from_value(&Foo { baz }))
+                .collect::<eyre::Result<Vec<_>>>()?,
+        )
+        .await?
+        .success()?;
+
+    let mut consumer = match start_consumer(
+        ctx,
+        stream_name.clone(),
+        Revision::Start,
+        embedded.manager().clone(),
+    )
+    .await?
+    {
+        ConsumerResult::Success(c) => c,
+        ConsumerResult::StreamDeleted => eyre::bail!("stream should not be deleted"),
+    };
+
+    let mut revisions = Vec::new();
+
+    tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            match consumer.next().await? {
+                Some(SubscriptionEvent::EventAppeared(record)) => revisions.push(record.revision),
+                Some(SubscriptionEvent::CaughtUp) => break,
+                Some(_) => {}
+                None => eyre::bail!("subscription ended early"),
+            }
+        }
+
+        eyre::Ok(())
+    })
+    .await??;
+
+    assert_eq!((0..history as u64).collect::<Vec<_>>(), revisions);
+
+    writer_client
+        .append(
+            ctx,
+            stream_name.clone(),
+            ExpectedRevision::Any,
+            vec![Propose::from_value(&Foo { baz: history })?],
+        )
+        .await?
+        .success()?;
+
+    let live = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            match consumer.next().await? {
+                Some(SubscriptionEvent::EventAppeared(record)) => return eyre::Ok(record),
+                Some(SubscriptionEvent::CaughtUp) => eyre::bail!("caught up twice"),
+                Some(_) => {}
+                None => eyre::bail!("subscription ended early"),
+            }
+        }
+    })
+    .await??;
+
+    assert_eq!(history as u64, live.revision);
+    assert_eq!(history, live.as_value::<Foo>()?.baz);
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn test_subscribe_to_streams_feed() -> eyre::Result<()> {
+    let embedded = crate::run_embedded(&Options::in_mem_no_grpc()).await?;
+    let writer_client = embedded.manager().new_writer_client().await?;
+    let ctx = RequestContext::new();
+    let stream_name = Uuid::new_v4().to_string();
+
+    let mut consumer = match start_consumer(
+        ctx,
+        STREAMS_FEED.to_string(),
+        Revision::Start,
+        embedded.manager().clone(),
+    )
+    .await?
+    {
+        ConsumerResult::Success(c) => c,
+        ConsumerResult::StreamDeleted => eyre::bail!("$streams can't be deleted"),
+    };
+
+    // The feed isn't persisted, the consumer must be subscribed before the writes.
+    let Some(SubscriptionEvent::Confirmed(_)) = consumer.next().await? else {
+        eyre::bail!("subscription to $streams was not confirmed");
+    };
+
+    // Only the first write creates the stream.
+    for i in 0..2 {
+        writer_client
+            .append(
+                ctx,
+                stream_name.clone(),
+                ExpectedRevision::Any,
+                vec![Propose::from_value(&Foo { baz: i })?],
+            )
+            .await?
+            .success()?;
+    }
+
+    writer_client
+        .delete(ctx, stream_name.clone(), ExpectedRevision::Any, false)
+        .await?
+        .success()?;
+
+    let mut events = vec![];
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while events.len() < 2 {
+            match consumer.next().await? {
+                Some(SubscriptionEvent::EventAppeared(record)) => {
+                    assert_eq!(events.len() as u64, record.revision);
+                    events.push(StreamFeedEvent::from_record(&record)?);
+                }
+
+                Some(_) => {}
+                None => eyre::bail!("subscription ended early"),
+            }
+        }
+
+        eyre::Ok(())
+    })
+    .await??;
+
+    assert_eq!(
+        events,
+        vec![
+            StreamFeedEvent::StreamCreated {
+                name: stream_name.clone()
+            },
+            StreamFeedEvent::StreamDeleted { name: stream_name },
+        ]
+    );
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn test_confirmation_reports_resolved_start_revision() -> eyre::Result<()> {
+    let embedded = crate::run_embedded(&Options::in_mem_no_grpc()).await?;
+    let writer_client = embedded.manager().new_writer_client().await?;
+    let ctx = RequestContext::new();
+    let stream_name = Uuid::new_v4().to_string();
+
+    let mut proposes = vec![];
+    for i in 0..5 {
+        proposes.push(Propose::from_value(&Foo { baz: i })?);
+    }
+
+    writer_client
+        .append(ctx, stream_name.clone(), ExpectedRevision::Any, proposes)
+        .await?
+        .success()?;
+
+    writer_client
+        .truncate(ctx, stream_name.clone(), 2)
+        .await?
+        .success()?;
+
+    let start_revision_from = |start: Revision<u64>| {
+        let manager = embedded.manager().clone();
+        let stream_name = stream_name.clone();
+
+        async move {
+            let mut consumer = match start_consumer(ctx, stream_name, start, manager).await? {
+                ConsumerResult::Success(c) => c,
+                ConsumerResult::StreamDeleted => eyre::bail!("stream is not deleted"),
+            };
+
+            match consumer.next().await? {
+                Some(SubscriptionEvent::Confirmed(SubscriptionConfirmation::StreamName {
+                    start_revision,
+                    ..
+                })) => Ok(start_revision),
+
+                _ => eyre::bail!("expected a stream confirmation"),
+            }
+        }
+    };
+
+    assert_eq!(Some(2), start_revision_from(Revision::Start).await?);
+    assert_eq!(Some(3), start_revision_from(Revision::Revision(3)).await?);
+    assert_eq!(Some(5), start_revision_from(Revision::End).await?);
+    assert_eq!(Some(8), start_revision_from(Revision::Revision(8)).await?);
+
+    embedded.shutdown().await
+}
@@ -1,8 +1,14 @@
-use crate::Options;
+use crate::names::{streams, types};
 use crate::process::tests::Foo;
+use crate::{MockClock, Options};
 use crate::{RequestContext, process::reading::record_try_from};
-use geth_common::{AppendStreamCompleted, Direction, ExpectedRevision, Propose, Record};
-use geth_mikoshi::hashing::mikoshi_hash;
+use bytes::Bytes;
+use chrono::{SubsecRound, TimeDelta, TimeZone, Utc};
+use geth_common::{
+    AppendError, AppendStreamCompleted, DeleteError, DeleteStreamCompleted, Direction,
+    ExpectedRevision, Propose, Record, Revision, StreamMetadata, TruncateError,
+    TruncateStreamCompleted,
+};
 use uuid::Uuid;
 
 #[tokio::test]
@@ -37,13 +43,12 @@ async fn test_writer_proc_simple() -> eyre::Result<()> {
     let mut stream = index_client
         .read(
             ctx,
-            mikoshi_hash(&stream_name),
+            index_client.key(&stream_name),
             0,
             usize::MAX,
             Direction::Forward,
         )
-        .await?
-        .ok()?;
+        .await?;
 
     while let Some(entry) = stream.next().await? {
         assert_eq!(index as u64, entry.revision);
@@ -63,3 +68,830 @@ async fn test_writer_proc_simple() -> eyre::Result<()> {
 
     embedded.shutdown().await
 }
+
+#[tokio::test]
+async fn test_writer_assigns_ids_to_nil_proposes() -> eyre::Result<()> {
+    let embedded = crate::run_embedded(&Options::in_mem_no_grpc()).await?;
+    let writer_client = embedded.manager().new_writer_client().await?;
+    let reader_client = embedded.manager().new_reader_client().await?;
+    let ctx = RequestContext::new();
+    let stream_name = Uuid::new_v4().to_string();
+    let explicit_id = Uuid::new_v4();
+
+    let mut first = Propose::from_value(&Foo { baz: 1 })?;
+    first.id = Uuid::nil();
+    let mut second = Propose::from_value(&Foo { baz: 2 })?;
+    second.id = explicit_id;
+
+    let result = writer_client
+        .append(
+            ctx,
+            stream_name.clone(),
+            ExpectedRevision::Any,
+            vec![first, second],
+        )
+        .await?
+        .success()?;
+
+    assert_eq!(2, result.event_ids.len());
+    assert!(!result.event_ids[0].is_nil());
+    assert_eq!(
+        Some(uuid::Version::SortRand),
+        result.event_ids[0].get_version()
+    );
+    assert_eq!(explicit_id, result.event_ids[1]);
+
+    let mut stream = reader_client
+        .read(
+            ctx,
+            &stream_name,
+            Revision::Start,
+            Direction::Forward,
+            usize::MAX,
+        )
+        .await?
+        .success()?;
+
+    let mut ids = vec![];
+    while let Some(record) = stream.next().await? {
+        ids.push(record.id);
+    }
+
+    assert_eq!(result.event_ids, ids);
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn test_writer_returns_commit_time() -> eyre::Result<()> {
+    let embedded = crate::run_embedded(&Options::in_mem_no_grpc()).await?;
+    let writer_client = embedded.manager().new_writer_client().await?;
+    let reader_client = embedded.manager().new_reader_client().await?;
+    let ctx = RequestContext::new();
+    let stream_name = Uuid::new_v4().to_string();
+    let before = Utc::now().trunc_subsecs(3);
+
+    let result = writer_client
+        .append(
+            ctx,
+            stream_name.clone(),
+            ExpectedRevision::Any,
+            vec![
+                Propose::from_value(&Foo { baz: 1 })?,
+                Propose::from_value(&Foo { baz: 2 })?,
+            ],
+        )
+        .await?
+        .success()?;
+
+    assert!(before <= result.created && result.created <= Utc::now());
+
+    let mut stream = reader_client
+        .read(
+            ctx,
+            &stream_name,
+            Revision::Start,
+            Direction::Forward,
+            usize::MAX,
+        )
+        .await?
+        .success()?;
+
+    let mut count = 0;
+    while let Some(record) = stream.next().await? {
+        assert_eq!(result.created, record.created);
+        count += 1;
+    }
+
+    assert_eq!(2, count);
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn test_writer_stores_event_metadata() -> eyre::Result<()> {
+    let embedded = crate::run_embedded(&Options::in_mem_no_grpc()).await?;
+    let writer_client = embedded.manager().new_writer_client().await?;
+    let reader_client = embedded.manager().new_reader_client().await?;
+    let ctx = RequestContext::new();
+    let stream_name = Uuid::new_v4().to_string();
+
+    let mut with_metadata = Propose::from_value(&Foo { baz: 1 })?;
+    with_metadata.metadata = Bytes::from_static(b"{\"correlation\":\"abc\"}");
+
+    writer_client
+        .append(
+            ctx,
+            stream_name.clone(),
+            ExpectedRevision::Any,
+            vec![with_metadata.clone(), Propose::from_value(&Foo { baz: 2 })?],
+        )
+        .await?
+        .success()?;
+
+    let mut stream = reader_client
+        .read(
+            ctx,
+            &stream_name,
+            Revision::Start,
+            Direction::Forward,
+            usize::MAX,
+        )
+        .await?
+        .success()?;
+
+    let first = stream.next().await?.unwrap();
+    assert_eq!(with_metadata.metadata, first.metadata);
+    assert_eq!(with_metadata.data, first.data);
+
+    let second = stream.next().await?.unwrap();
+    assert!(second.metadata.is_empty());
+    assert_eq!(2, second.as_value::<Foo>()?.baz);
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn test_writer_uses_configured_clock() -> eyre::Result<()> {
+    let now = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+    let clock = MockClock::new(now);
+    let options = Options::in_mem_no_grpc().with_clock(clock.clone());
+    let embedded = crate::run_embedded(&options).await?;
+    let writer_client = embedded.manager().new_writer_client().await?;
+    let ctx = RequestContext::new();
+    let stream_name = Uuid::new_v4().to_string();
+
+    let first = writer_client
+        .append(
+            ctx,
+            stream_name.clone(),
+            ExpectedRevision::Any,
+            vec![Propose::from_value(&Foo { baz: 1 })?],
+        )
+        .await?
+        .success()?;
+
+    clock.advance(TimeDelta::seconds(5));
+
+    let second = writer_client
+        .append(
+            ctx,
+            stream_name.clone(),
+            ExpectedRevision::Any,
+            vec![Propose::from_value(&Foo { baz: 2 })?],
+        )
+        .await?
+        .success()?;
+
+    assert_eq!(now, first.created);
+    assert_eq!(now + TimeDelta::seconds(5), second.created);
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn test_writer_truncate_stream() -> eyre::Result<()> {
+    let embedded = crate::run_embedded(&Options::in_mem_no_grpc()).await?;
+    let writer_client = embedded.manager().new_writer_client().await?;
+    let reader_client = embedded.manager().new_reader_client().await?;
+    let ctx = RequestContext::new();
+    let stream_name = Uuid::new_v4().to_string();
+
+    let read_revisions = |direction: Direction, start: Revision<u64>| {
+        let reader_client = reader_client.clone();
+        let stream_name = stream_name.clone();
+
+        async move {
+            let mut stream = reader_client
+                .read(ctx, &stream_name, start, direction, usize::MAX)
+                .await?
+                .success()?;
+
+            let mut revisions = vec![];
+            while let Some(record) = stream.next().await? {
+                revisions.push(record.revision);
+            }
+
+            eyre::Ok(revisions)
+        }
+    };
+
+    // Nothing to truncate, no marker is left behind.
+    let result = writer_client.truncate(ctx, stream_name.clone(), 4).await?;
+
+    assert!(matches!(
+        result,
+        TruncateStreamCompleted::Error(TruncateError::StreamNotFound)
+    ));
+
+    let mut markers = reader_client
+        .read(
+            ctx,
+            &streams::metadata_of(&stream_name),
+            Revision::Start,
+            Direction::Forward,
+            usize::MAX,
+        )
+        .await?
+        .success()?;
+
+    assert!(markers.next().await?.is_none());
+
+    let mut proposes = vec![];
+    for i in 0..10 {
+        proposes.push(Propose::from_value(&Foo { baz: i })?);
+    }
+
+    writer_client
+        .append(ctx, stream_name.clone(), ExpectedRevision::Any, proposes)
+        .await?
+        .success()?;
+
+    let result = writer_client
+        .truncate(ctx, stream_name.clone(), 4)
+        .await?
+        .into_result()?;
+
+    assert_eq!(ExpectedRevision::Revision(9), result.next_expected_version);
+    assert_eq!(
+        (4..10).collect::<Vec<_>>(),
+        read_revisions(Direction::Forward, Revision::Start).await?
+    );
+    assert_eq!(
+        (4..10).rev().collect::<Vec<_>>(),
+        read_revisions(Direction::Backward, Revision::End).await?
+    );
+
+    writer_client
+        .append(
+            ctx,
+            stream_name.clone(),
+            ExpectedRevision::Revision(9),
+            vec![Propose::from_value(&Foo { baz: 10 })?],
+        )
+        .await?
+        .success()?;
+
+    assert_eq!(
+        (4..11).collect::<Vec<_>>(),
+        read_revisions(Direction::Forward, Revision::Start).await?
+    );
+
+    // Truncating past the end empties the stream without deleting it.
+    writer_client
+        .truncate(ctx, stream_name.clone(), 1_000)
+        .await?
+        .success()?;
+
+    assert!(
+        read_revisions(Direction::Forward, Revision::Start)
+            .await?
+            .is_empty()
+    );
+
+    writer_client
+        .append(
+            ctx,
+            stream_name.clone(),
+            ExpectedRevision::Revision(10),
+            vec![Propose::from_value(&Foo { baz: 11 })?],
+        )
+        .await?
+        .success()?;
+
+    assert_eq!(
+        vec![11],
+        read_revisions(Direction::Forward, Revision::Start).await?
+    );
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn test_writer_truncate_never_moves_back() -> eyre::Result<()> {
+    let embedded = crate::run_embedded(&Options::in_mem_no_grpc()).await?;
+    let writer_client = embedded.manager().new_writer_client().await?;
+    let reader_client = embedded.manager().new_reader_client().await?;
+    let ctx = RequestContext::new();
+    let stream_name = Uuid::new_v4().to_string();
+
+    let mut proposes = vec![];
+    for i in 0..10 {
+        proposes.push(Propose::from_value(&Foo { baz: i })?);
+    }
+
+    writer_client
+        .append(ctx, stream_name.clone(), ExpectedRevision::Any, proposes)
+        .await?
+        .success()?;
+
+    writer_client
+        .truncate(ctx, stream_name.clone(), 6)
+        .await?
+        .success()?;
+
+    // The revisions before 6 may already be scavenged, they can't be exposed again.
+    writer_client
+        .truncate(ctx, stream_name.clone(), 2)
+        .await?
+        .success()?;
+
+    let mut stream = reader_client
+        .read(
+            ctx,
+            &stream_name,
+            Revision::Start,
+            Direction::Forward,
+            usize::MAX,
+        )
+        .await?
+        .success()?;
+
+    let mut revisions = vec![];
+    while let Some(record) = stream.next().await? {
+        revisions.push(record.revision);
+    }
+
+    assert_eq!((6..10).collect::<Vec<_>>(), revisions);
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn test_writer_stream_metadata() -> eyre::Result<()> {
+    let embedded = crate::run_embedded(&Options::in_mem_no_grpc()).await?;
+    let writer_client = embedded.manager().new_writer_client().await?;
+    let reader_client = embedded.manager().new_reader_client().await?;
+    let ctx = RequestContext::new();
+    let stream_name = Uuid::new_v4().to_string();
+
+    assert_eq!(
+        StreamMetadata::default(),
+        reader_client.metadata(ctx, &stream_name).await?.success()?
+    );
+
+    let mut proposes = vec![];
+    for i in 0..10 {
+        proposes.push(Propose::from_value(&Foo { baz: i })?);
+    }
+
+    writer_client
+        .append(ctx, stream_name.clone(), ExpectedRevision::Any, proposes)
+        .await?
+        .success()?;
+
+    let mut metadata = StreamMetadata {
+        max_count: Some(3),
+        ..Default::default()
+    };
+    metadata
+        .custom
+        .insert("owner".to_string(), "billing".to_string());
+
+    writer_client
+        .set_metadata(ctx, stream_name.clone(), metadata.clone())
+        .await?
+        .success()?;
+
+    // A truncation lands in the same metadata stream but must not shadow the settings.
+    writer_client
+        .truncate(ctx, stream_name.clone(), 2)
+        .await?
+        .success()?;
+
+    assert_eq!(
+        metadata,
+        reader_client.metadata(ctx, &stream_name).await?.success()?
+    );
+
+    let mut stream = reader_client
+        .read(
+            ctx,
+            &stream_name,
+            Revision::Start,
+            Direction::Forward,
+            usize::MAX,
+        )
+        .await?
+        .success()?;
+
+    let mut revisions = vec![];
+    while let Some(record) = stream.next().await? {
+        revisions.push(record.revision);
+    }
+
+    assert_eq!(vec![7, 8, 9], revisions);
+
+    writer_client
+        .delete(ctx, stream_name.clone(), ExpectedRevision::Any, false)
+        .await?
+        .success()?;
+
+    assert!(
+        writer_client
+            .set_metadata(ctx, stream_name.clone(), metadata)
+            .await?
+            .is_error()
+    );
+    assert!(
+        reader_client
+            .metadata(ctx, &stream_name)
+            .await?
+            .is_stream_deleted()
+    );
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn test_reader_hides_events_past_max_age() -> eyre::Result<()> {
+    let now = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+    let clock = MockClock::new(now);
+    let options = Options::in_mem_no_grpc().with_clock(clock.clone());
+    let embedded = crate::run_embedded(&options).await?;
+    let writer_client = embedded.manager().new_writer_client().await?;
+    let reader_client = embedded.manager().new_reader_client().await?;
+    let ctx = RequestContext::new();
+    let stream_name = Uuid::new_v4().to_string();
+
+    let read_revisions = |direction: Direction, start: Revision<u64>| {
+        let reader_client = reader_client.clone();
+        let stream_name = stream_name.clone();
+        async move {
+            let mut stream = reader_client
+                .read(ctx, &stream_name, start, direction, usize::MAX)
+                .await?
+                .success()?;
+
+            let mut revisions = vec![];
+            while let Some(record) = stream.next().await? {
+                revisions.push(record.revision);
+            }
+
+            eyre::Ok(revisions)
+        }
+    };
+
+    for baz in 0..5 {
+        writer_client
+            .append(
+                ctx,
+                stream_name.clone(),
+                ExpectedRevision::Any,
+                vec![Propose::from_value(&Foo { baz })?],
+            )
+            .await?
+            .success()?;
+
+        clock.advance(TimeDelta::minutes(10));
+    }
+
+    writer_client
+        .set_metadata(
+            ctx,
+            stream_name.clone(),
+            StreamMetadata {
+                max_age_secs: Some(25 * 60),
+                ..Default::default()
+            },
+        )
+        .await?
+        .success()?;
+
+    assert_eq!(
+        vec![3, 4],
+        read_revisions(Direction::Forward, Revision::Start).await?
+    );
+    assert_eq!(
+        vec![4, 3],
+        read_revisions(Direction::Backward, Revision::End).await?
+    );
+
+    clock.advance(TimeDelta::hours(1));
+
+    assert!(
+        read_revisions(Direction::Forward, Revision::Start)
+            .await?
+            .is_empty()
+    );
+
+    // Settings are cached, newer ones replace them.
+    writer_client
+        .set_metadata(ctx, stream_name.clone(), StreamMetadata::default())
+        .await?
+        .success()?;
+
+    assert_eq!(
+        vec![0, 1, 2, 3, 4],
+        read_revisions(Direction::Forward, Revision::Start).await?
+    );
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn test_writer_rejects_events_failing_their_schema() -> eyre::Result<()> {
+    let schema_dir = std::env::temp_dir().join(Uuid::new_v4().to_string());
+    std::fs::create_dir_all(&schema_dir)?;
+    std::fs::write(
+        schema_dir.join("foo.json"),
+        r#"{"type": "object", "properties": {"baz": {"type": "integer", "maximum": 10}}, "required": ["baz"]}"#,
+    )?;
+
+    let mut options = Options::in_mem_no_grpc();
+    options.schema_dir = Some(schema_dir.clone());
+
+    let embedded = crate::run_embedded(&options).await?;
+    let writer_client = embedded.manager().new_writer_client().await?;
+    let ctx = RequestContext::new();
+    let stream_name = Uuid::new_v4().to_string();
+
+    let mut valid = Propose::from_value(&Foo { baz: 1 })?;
+    valid.class = "foo".to_string();
+    let mut invalid = Propose::from_value(&Foo { baz: 42 })?;
+    invalid.class = "foo".to_string();
+    let unchecked = Propose::from_value(&Foo { baz: 42 })?;
+
+    let error = writer_client
+        .append(
+            ctx,
+            stream_name.clone(),
+            ExpectedRevision::Any,
+            vec![valid.clone(), invalid],
+        )
+        .await?
+        .err()?;
+
+    let AppendError::InvalidEvent(error) = error else {
+        eyre::bail!("expected an invalid event error, got {error:?}");
+    };
+
+    assert_eq!(1, error.index);
+    assert_eq!("foo", error.class);
+
+    // Nothing from the rejected append made it to the stream.
+    writer_client
+        .append(
+            ctx,
+            stream_name.clone(),
+            ExpectedRevision::NoStream,
+            vec![valid, unchecked],
+        )
+        .await?
+        .success()?;
+
+    std::fs::remove_dir_all(schema_dir)?;
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn test_writer_rejects_reserved_classes() -> eyre::Result<()> {
+    let embedded = crate::run_embedded(&Options::in_mem_no_grpc()).await?;
+    let writer_client = embedded.manager().new_writer_client().await?;
+    let reader_client = embedded.manager().new_reader_client().await?;
+    let ctx = RequestContext::new();
+    let stream_name = Uuid::new_v4().to_string();
+
+    writer_client
+        .append(
+            ctx,
+            stream_name.clone(),
+            ExpectedRevision::Any,
+            vec![Propose::from_value(&Foo { baz: 1 })?],
+        )
+        .await?
+        .success()?;
+
+    let mut hiding = Propose::from_value(&StreamMetadata {
+        max_count: Some(0),
+        ..Default::default()
+    })?;
+    hiding.class = types::STREAM_METADATA.to_string();
+
+    let error = writer_client
+        .append(
+            ctx,
+            streams::metadata_of(&stream_name),
+            ExpectedRevision::Any,
+            vec![Propose::from_value(&Foo { baz: 2 })?, hiding],
+        )
+        .await?
+        .err()?;
+
+    let AppendError::InvalidEvent(error) = error else {
+        eyre::bail!("expected an invalid event error, got {error:?}");
+    };
+
+    assert_eq!(1, error.index);
+    assert_eq!(types::STREAM_METADATA, error.class);
+
+    let metadata = reader_client.metadata(ctx, &stream_name).await?.success()?;
+
+    assert_eq!(None, metadata.max_count);
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn test_writer_delete_dry_run() -> eyre::Result<()> {
+    let embedded = crate::run_embedded(&Options::in_mem_no_grpc()).await?;
+    let writer_client = embedded.manager().new_writer_client().await?;
+    let reader_client = embedded.manager().new_reader_client().await?;
+    let ctx = RequestContext::new();
+    let stream_name = Uuid::new_v4().to_string();
+
+    writer_client
+        .append(
+            ctx,
+            stream_name.clone(),
+            ExpectedRevision::Any,
+            vec![
+                Propose::from_value(&Foo { baz: 1 })?,
+                Propose::from_value(&Foo { baz: 2 })?,
+            ],
+        )
+        .await?
+        .success()?;
+
+    let current = writer_client
+        .delete(
+            ctx,
+            stream_name.clone(),
+            ExpectedRevision::Revision(1),
+            true,
+        )
+        .await?
+        .dry_run()?;
+
+    assert_eq!(ExpectedRevision::Revision(1), current);
+
+    let result = writer_client
+        .delete(ctx, stream_name.clone(), ExpectedRevision::NoStream, true)
+        .await?;
+
+    let DeleteStreamCompleted::Error(DeleteError::WrongExpectedRevision(e)) = result else {
+        eyre::bail!("expected a wrong expected revision error, got {result:?}");
+    };
+
+    assert_eq!(ExpectedRevision::Revision(1), e.current);
+
+    // The stream survived both dry runs.
+    let mut stream = reader_client
+        .read(
+            ctx,
+            &stream_name,
+            Revision::Start,
+            Direction::Forward,
+            usize::MAX,
+        )
+        .await?
+        .success()?;
+
+    let mut count = 0;
+    while stream.next().await?.is_some() {
+        count += 1;
+    }
+
+    assert_eq!(2, count);
+
+    writer_client
+        .delete(
+            ctx,
+            stream_name.clone(),
+            ExpectedRevision::Revision(1),
+            false,
+        )
+        .await?
+        .success()?;
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn test_append_is_readable_once_acknowledged() -> eyre::Result<()> {
+    let embedded = crate::run_embedded(&Options::in_mem_no_grpc()).await?;
+    let writer_client = embedded.manager().new_writer_client().await?;
+    let reader_client = embedded.manager().new_reader_client().await?;
+    let ctx = RequestContext::new();
+    let stream_name = Uuid::new_v4().to_string();
+
+    // The writer stores the index entries before acknowledging an append, so the written
+    // events can be read by revision right away.
+    for i in 0..200u64 {
+        writer_client
+            .append(
+                ctx,
+                stream_name.clone(),
+                ExpectedRevision::Any,
+                vec![Propose::from_value(&Foo { baz: i as u32 })?],
+            )
+            .await?
+            .success()?;
+
+        let record = reader_client
+            .read(
+                ctx,
+                &stream_name,
+                Revision::Revision(i),
+                Direction::Forward,
+                1,
+            )
+            .await?
+            .success()?
+            .next()
+            .await?
+            .expect("appended event to be readable");
+
+        assert_eq!(i, record.revision);
+        assert_eq!(i as u32, record.as_value::<Foo>()?.baz);
+    }
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn test_writer_resending_an_append_is_idempotent() -> eyre::Result<()> {
+    let embedded = crate::run_embedded(&Options::in_mem_no_grpc()).await?;
+    let writer_client = embedded.manager().new_writer_client().await?;
+    let index_client = embedded.manager().new_index_client().await?;
+    let ctx = RequestContext::new();
+    let stream_name = Uuid::new_v4().to_string();
+    let mut events = vec![];
+
+    for i in 0..3 {
+        events.push(Propose::from_value(&Foo { baz: i })?);
+    }
+
+    let first = writer_client
+        .append(
+            ctx,
+            stream_name.clone(),
+            ExpectedRevision::NoStream,
+            events.clone(),
+        )
+        .await?
+        .success()?;
+
+    // Same expected revision as the first attempt, like a client retrying after a timeout.
+    let retry = writer_client
+        .append(
+            ctx,
+            stream_name.clone(),
+            ExpectedRevision::NoStream,
+            events.clone(),
+        )
+        .await?
+        .success()?;
+
+    assert_eq!(first, retry);
+
+    let revision = index_client
+        .latest_revision(ctx, index_client.key(&stream_name))
+        .await?;
+
+    assert_eq!(Some(2), revision.revision());
+
+    embedded.shutdown().await
+}
+
+#[tokio::test]
+async fn test_writer_rejects_partially_resent_append() -> eyre::Result<()> {
+    let embedded = crate::run_embedded(&Options::in_mem_no_grpc()).await?;
+    let writer_client = embedded.manager().new_writer_client().await?;
+    let index_client = embedded.manager().new_index_client().await?;
+    let ctx = RequestContext::new();
+    let stream_name = Uuid::new_v4().to_string();
+    let first = Propose::from_value(&Foo { baz: 1 })?;
+    let second = Propose::from_value(&Foo { baz: 2 })?;
+
+    writer_client
+        .append(
+            ctx,
+            stream_name.clone(),
+            ExpectedRevision::Any,
+            vec![first.clone(), second],
+        )
+        .await?
+        .success()?;
+
+    let error = writer_client
+        .append(
+            ctx,
+            stream_name.clone(),
+            ExpectedRevision::Any,
+            vec![first, Propose::from_value(&Foo { baz: 3 })?],
+        )
+        .await?
+        .err()?;
+
+    assert_eq!(AppendError::DuplicateEvents, error);
+
+    let revision = index_client
+        .latest_revision(ctx, index_client.key(&stream_name))
+        .await?;
+
+    assert_eq!(Some(1), revision.revision());
+
+    embedded.shutdown().await
+}
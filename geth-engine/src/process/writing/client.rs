@@ -4,7 +4,8 @@ use crate::process::{
 };
 use geth_common::{
     AppendError, AppendStreamCompleted, DeleteError, DeleteStreamCompleted, ExpectedRevision,
-    Propose, WriteResult, WrongExpectedRevisionError,
+    Propose, SetStreamMetadataCompleted, SetStreamMetadataError, StreamMetadata, TruncateError,
+    TruncateStreamCompleted, WriteResult, WrongExpectedRevisionError,
 };
 use tracing::instrument;
 
@@ -19,13 +20,36 @@ impl WriterClient {
         Self { target, inner }
     }
 
-    #[instrument(skip(self, events, context), fields(origin = ?self.inner.origin(), correlation = %context.correlation))]
     pub async fn append(
         &self,
         context: RequestContext,
         stream: String,
         expected: ExpectedRevision,
         events: Vec<Propose>,
+    ) -> eyre::Result<AppendStreamCompleted> {
+        self.write(context, stream, expected, events, true).await
+    }
+
+    /// Same as [`WriterClient::append`] but the events are not pushed to the subscriptions, the
+    /// streams feed included. Subscriptions only see them by reading the stream again.
+    pub async fn append_without_notification(
+        &self,
+        context: RequestContext,
+        stream: String,
+        expected: ExpectedRevision,
+        events: Vec<Propose>,
+    ) -> eyre::Result<AppendStreamCompleted> {
+        self.write(context, stream, expected, events, false).await
+    }
+
+    #[instrument(skip(self, events, context), fields(origin = ?self.inner.origin(), correlation = %context.correlation))]
+    async fn write(
+        &self,
+        context: RequestContext,
+        stream: String,
+        expected: ExpectedRevision,
+        events: Vec<Propose>,
+        notify_subscribers: bool,
     ) -> eyre::Result<AppendStreamCompleted> {
         let resp = self
             .inner
@@ -36,6 +60,7 @@ impl WriterClient {
                     ident: stream.clone(),
                     expected,
                     events,
+                    notify_subscribers,
                 }
                 .into(),
             )
@@ -51,6 +76,14 @@ impl WriterClient {
                     Ok(AppendStreamCompleted::Error(AppendError::StreamDeleted))
                 }
 
+                WriteResponses::InvalidEvent(e) => {
+                    Ok(AppendStreamCompleted::Error(AppendError::InvalidEvent(e)))
+                }
+
+                WriteResponses::DuplicateEvents => {
+                    Ok(AppendStreamCompleted::Error(AppendError::DuplicateEvents))
+                }
+
                 WriteResponses::WrongExpectedRevision { expected, current } => Ok(
                     AppendStreamCompleted::Error(AppendError::WrongExpectedRevision(
                         WrongExpectedRevisionError { expected, current },
@@ -61,6 +94,8 @@ impl WriterClient {
                     start_position: start,
                     next_position: next,
                     next_expected_version,
+                    event_ids,
+                    created,
                 } => {
                     tracing::debug!(correlation = %context.correlation, "completed successfully");
 
@@ -68,6 +103,8 @@ impl WriterClient {
                         next_expected_version,
                         position: start,
                         next_logical_position: next,
+                        event_ids,
+                        created,
                     }))
                 }
 
@@ -84,6 +121,7 @@ impl WriterClient {
         context: RequestContext,
         stream: String,
         expected: ExpectedRevision,
+        dry_run: bool,
     ) -> eyre::Result<DeleteStreamCompleted> {
         let resp = self
             .inner
@@ -93,6 +131,7 @@ impl WriterClient {
                 WriteRequests::Delete {
                     ident: stream.clone(),
                     expected,
+                    dry_run,
                 }
                 .into(),
             )
@@ -108,6 +147,8 @@ impl WriterClient {
                     Ok(DeleteStreamCompleted::Error(DeleteError::StreamDeleted))
                 }
 
+                WriteResponses::DryRun { current } => Ok(DeleteStreamCompleted::DryRun(current)),
+
                 WriteResponses::WrongExpectedRevision { expected, current } => Ok(
                     DeleteStreamCompleted::Error(DeleteError::WrongExpectedRevision(
                         WrongExpectedRevisionError { expected, current },
@@ -118,10 +159,14 @@ impl WriterClient {
                     start_position: start,
                     next_position: next,
                     next_expected_version,
+                    event_ids,
+                    created,
                 } => Ok(DeleteStreamCompleted::Success(WriteResult {
                     next_expected_version,
                     position: start,
                     next_logical_position: next,
+                    event_ids,
+                    created,
                 })),
 
                 _ => eyre::bail!("unexpected response when appending to stream: '{}'", stream),
@@ -130,4 +175,116 @@ impl WriterClient {
             eyre::bail!("internal protocol error when appending to the writer process");
         }
     }
+
+    #[instrument(skip(self, context), fields(origin = ?self.inner.origin(), correlation = %context.correlation))]
+    pub async fn truncate(
+        &self,
+        context: RequestContext,
+        stream: String,
+        before: u64,
+    ) -> eyre::Result<TruncateStreamCompleted> {
+        let resp = self
+            .inner
+            .request(
+                context,
+                self.target,
+                WriteRequests::Truncate {
+                    ident: stream.clone(),
+                    before,
+                }
+                .into(),
+            )
+            .await?;
+
+        if let Ok(resp) = resp.payload.try_into() {
+            match resp {
+                WriteResponses::Error => {
+                    eyre::bail!("internal error when truncating stream: '{}'", stream);
+                }
+
+                WriteResponses::StreamDeleted => {
+                    Ok(TruncateStreamCompleted::Error(TruncateError::StreamDeleted))
+                }
+
+                WriteResponses::StreamNotFound => Ok(TruncateStreamCompleted::Error(
+                    TruncateError::StreamNotFound,
+                )),
+
+                WriteResponses::Committed {
+                    start_position: start,
+                    next_position: next,
+                    next_expected_version,
+                    event_ids,
+                    created,
+                } => Ok(TruncateStreamCompleted::Success(WriteResult {
+                    next_expected_version,
+                    position: start,
+                    next_logical_position: next,
+                    event_ids,
+                    created,
+                })),
+
+                _ => eyre::bail!("unexpected response when truncating stream: '{}'", stream),
+            }
+        } else {
+            eyre::bail!("internal protocol error when truncating with the writer process");
+        }
+    }
+
+    #[instrument(skip(self, context, metadata), fields(origin = ?self.inner.origin(), correlation = %context.correlation))]
+    pub async fn set_metadata(
+        &self,
+        context: RequestContext,
+        stream: String,
+        metadata: StreamMetadata,
+    ) -> eyre::Result<SetStreamMetadataCompleted> {
+        let resp = self
+            .inner
+            .request(
+                context,
+                self.target,
+                WriteRequests::SetMetadata {
+                    ident: stream.clone(),
+                    metadata,
+                }
+                .into(),
+            )
+            .await?;
+
+        if let Ok(resp) = resp.payload.try_into() {
+            match resp {
+                WriteResponses::Error => {
+                    eyre::bail!(
+                        "internal error when setting metadata of stream: '{}'",
+                        stream
+                    );
+                }
+
+                WriteResponses::StreamDeleted => Ok(SetStreamMetadataCompleted::Error(
+                    SetStreamMetadataError::StreamDeleted,
+                )),
+
+                WriteResponses::Committed {
+                    start_position: start,
+                    next_position: next,
+                    next_expected_version,
+                    event_ids,
+                    created,
+                } => Ok(SetStreamMetadataCompleted::Success(WriteResult {
+                    next_expected_version,
+                    position: start,
+                    next_logical_position: next,
+                    event_ids,
+                    created,
+                })),
+
+                _ => eyre::bail!(
+                    "unexpected response when setting metadata of stream: '{}'",
+                    stream
+                ),
+            }
+        } else {
+            eyre::bail!("internal protocol error when setting metadata with the writer process");
+        }
+    }
 }
@@ -1,13 +1,15 @@
 use std::vec;
 
-use bytes::{BufMut, BytesMut};
+use bytes::{BufMut, Bytes, BytesMut};
+use chrono::{DateTime, SubsecRound, Utc};
 use geth_common::{Propose, Record};
 use geth_domain::index::BlockEntry;
 use geth_mikoshi::{
-    hashing::mikoshi_hash,
+    compression::Compression,
     wal::{LogEntries, LogEntry},
 };
 
+use crate::domain::index::tombstone_revision;
 use crate::{metrics::Metrics, names::types::STREAM_DELETED};
 
 pub(crate) struct ProposeEntries {
@@ -16,14 +18,23 @@ pub(crate) struct ProposeEntries {
     pub committed: Vec<Record>,
     events: vec::IntoIter<Propose>,
     current: Option<Propose>,
+    /// Payload of the current event as written to the log, and how it was compressed.
+    stored: (Compression, Bytes),
+    compression: Compression,
+    /// Payloads smaller than that many bytes are stored as is.
+    compression_threshold: usize,
     ident: String,
     key: u64,
     pub revision: u64,
+    epoch: u64,
+    /// Commit time shared by all the events of the append.
+    pub created: DateTime<Utc>,
 }
 
 impl LogEntries for ProposeEntries {
     fn move_next(&mut self) -> bool {
         if let Some(event) = self.events.next() {
+            self.stored = self.store(&event.data);
             self.current = Some(event);
             return true;
         }
@@ -36,13 +47,17 @@ impl LogEntries for ProposeEntries {
         size_of::<u64>() // revision
             + size_of::<u16>() // stream name length
             + self.ident.len() // stream name
-            + propose_estimate_size(self.current.as_ref().unwrap())
+            + propose_estimate_size(self.current.as_ref().unwrap(), &self.stored.1)
+            + size_of::<i64>() // created
+            + size_of::<u32>() // metadata size
+            + self.current.as_ref().unwrap().metadata.len()
+            + size_of::<u8>() // compression
     }
 
     fn write_current_entry(&mut self, buffer: &mut BytesMut, position: u64) {
         let event = self.current.as_ref().unwrap();
         let final_revision = if event.class == STREAM_DELETED {
-            u64::MAX
+            tombstone_revision(self.epoch)
         } else {
             self.revision
         };
@@ -56,8 +71,14 @@ impl LogEntries for ProposeEntries {
         buffer.put_u64_le(self.revision);
         buffer.put_u16_le(self.ident.len() as u16);
         buffer.extend_from_slice(self.ident.as_bytes());
-        propose_serialize(event, buffer);
+        propose_serialize(event, &self.stored.1, buffer);
+        buffer.put_i64_le(self.created.timestamp_millis());
+        buffer.put_u32_le(event.metadata.len() as u32);
+        buffer.extend_from_slice(&event.metadata);
+        buffer.put_u8(self.stored.0 as u8);
         self.metrics.observe_written_propose_event(self);
+        self.metrics
+            .observe_stream_append(&self.ident, self.current_entry_size());
     }
 
     fn expected_count(&self) -> usize {
@@ -74,6 +95,10 @@ impl LogEntries for ProposeEntries {
             position: entry.position,
             revision: self.revision,
             data: propose.data,
+            metadata: propose.metadata,
+            created: self.created,
+            link: None,
+            epoch: self.epoch,
         });
 
         self.revision += 1;
@@ -81,9 +106,15 @@ impl LogEntries for ProposeEntries {
 }
 
 impl ProposeEntries {
-    pub fn new(metrics: Metrics, ident: String, start_revision: u64, events: Vec<Propose>) -> Self {
-        let key = mikoshi_hash(&ident);
-
+    pub fn new(
+        metrics: Metrics,
+        ident: String,
+        key: u64,
+        start_revision: u64,
+        epoch: u64,
+        events: Vec<Propose>,
+        created: DateTime<Utc>,
+    ) -> Self {
         Self {
             metrics,
             indexes: vec![],
@@ -92,25 +123,66 @@ impl ProposeEntries {
             ident,
             key,
             current: None,
+            stored: (Compression::None, Bytes::new()),
+            compression: Compression::None,
+            compression_threshold: usize::MAX,
             revision: start_revision,
+            epoch,
+            // Stored with millisecond precision, we truncate now so live and read records agree.
+            created: created.trunc_subsecs(3),
+        }
+    }
+
+    /// Compresses the payloads of at least `threshold` bytes with `compression`.
+    pub fn with_compression(self, compression: Compression, threshold: usize) -> Self {
+        Self {
+            compression,
+            compression_threshold: threshold,
+            ..self
+        }
+    }
+
+    /// Payloads are kept as is when compressing them doesn't save space.
+    fn store(&self, data: &Bytes) -> (Compression, Bytes) {
+        if self.compression == Compression::None || data.len() < self.compression_threshold {
+            return (Compression::None, data.clone());
+        }
+
+        match self.compression.compress(data) {
+            Ok(compressed) if compressed.len() < data.len() => {
+                (self.compression, Bytes::from(compressed))
+            }
+
+            Ok(_) => (Compression::None, data.clone()),
+
+            Err(e) => {
+                tracing::warn!(
+                    stream = self.ident,
+                    compression = %self.compression,
+                    "payload stored uncompressed: {}",
+                    e
+                );
+
+                (Compression::None, data.clone())
+            }
         }
     }
 }
 
-fn propose_estimate_size(propose: &Propose) -> usize {
+fn propose_estimate_size(propose: &Propose, payload: &[u8]) -> usize {
     size_of::<u128>() // id
         + size_of::<u32>() // content type
         + size_of::<u16>() // class length
         + propose.class.len()
         + size_of::<u32>() // payload size
-        + propose.data.len()
+        + payload.len()
 }
 
-fn propose_serialize(propose: &Propose, buffer: &mut BytesMut) {
+fn propose_serialize(propose: &Propose, payload: &[u8], buffer: &mut BytesMut) {
     buffer.put_u128_le(propose.id.to_u128_le());
     buffer.put_u32_le((propose.content_type as i32) as u32);
     buffer.put_u16_le(propose.class.len() as u16);
     buffer.extend_from_slice(propose.class.as_bytes());
-    buffer.put_u32_le(propose.data.len() as u32);
-    buffer.extend_from_slice(&propose.data);
+    buffer.put_u32_le(payload.len() as u32);
+    buffer.extend_from_slice(payload);
 }
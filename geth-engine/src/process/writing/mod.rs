@@ -1,6 +1,7 @@
 mod client;
 mod entries;
 mod proc;
+mod recent;
 
 pub use client::WriterClient;
 pub use proc::run;
@@ -1,22 +1,45 @@
+use crate::domain::catalog::CatalogChange;
 use crate::domain::index::CurrentRevision;
 use crate::get_chunk_container;
 use crate::metrics::get_metrics;
-use crate::names::types::STREAM_DELETED;
+use crate::names::streams;
+use crate::names::types::{self, STREAM_DELETED, STREAM_METADATA, STREAM_TRUNCATED};
+use crate::process::indexing::IndexClient;
 use crate::process::messages::{WriteRequests, WriteResponses};
-use crate::process::{Item, ProcessEnv, Raw};
+use crate::process::reading::record_try_from;
+use crate::process::{Item, Proc, ProcId, ProcessEnv, Raw, RequestContext};
+use crate::validation::{JsonSchemaValidator, SharedSchemaValidator};
 use bytes::{Bytes, BytesMut};
-use geth_common::{ContentType, ExpectedRevision, Propose, WrongExpectedRevisionError};
-use geth_mikoshi::hashing::mikoshi_hash;
-use geth_mikoshi::wal::LogWriter;
+use geth_common::{
+    ContentType, Direction, ExpectedRevision, InvalidEventError, Propose,
+    WrongExpectedRevisionError,
+};
+use geth_mikoshi::wal::{LogCursor, LogReader, LogWriter};
+use std::cmp::{max, min};
 use uuid::Uuid;
 
 use super::entries::ProposeEntries;
+use super::recent::{RecentAppend, RecentAppends, Retry};
 
 pub fn run(mut env: ProcessEnv<Raw>) -> eyre::Result<()> {
     let mut log_writer = LogWriter::load(get_chunk_container(), BytesMut::with_capacity(4_096))?;
+    let log_reader = LogReader::new(get_chunk_container());
     let index_client = env.new_index_client()?;
-    let sub_client = env.new_subscription_client()?;
+    // Write-only deployments can leave subscriptions out of the catalog.
+    let sub_client = if env.block_on(env.client.has(Proc::PubSub))? {
+        Some(env.new_subscription_client()?)
+    } else {
+        None
+    };
+    // Revision of the next `$streams` feed event, the feed is not persisted.
+    let mut feed_revision = 0u64;
+    let recent_appends = RecentAppends::new(10_000);
     let metrics = get_metrics();
+    let validator = match (&env.options.schema_validator, &env.options.schema_dir) {
+        (Some(validator), _) => Some(validator.clone()),
+        (None, Some(dir)) => Some(SharedSchemaValidator::new(JsonSchemaValidator::load(dir)?)),
+        (None, None) => None,
+    };
 
     while let Some(item) = env.recv() {
         match item {
@@ -26,19 +49,56 @@ pub fn run(mut env: ProcessEnv<Raw>) -> eyre::Result<()> {
 
             Item::Mail(mail) => {
                 if let Ok(req) = mail.payload.try_into() {
-                    let (ident, expected, events) = match req {
+                    // Truncations and settings go to the metadata stream of the stream they
+                    // target. The reported revision is the one of the target, left unchanged.
+                    let mut target_revision = None;
+                    let mut dry_run = false;
+                    let mut deleting = false;
+                    let mut notify_subscribers = true;
+                    let mut retriable = false;
+                    let (ident, expected, mut events) = match req {
                         WriteRequests::Write {
                             ident,
                             expected,
                             events,
-                        } => (ident, expected, events),
+                            notify_subscribers: notify,
+                        } => {
+                            let checked = check_reserved_classes(&events).and_then(|_| {
+                                validator.as_ref().map_or(Ok(()), |v| v.check(&events))
+                            });
 
-                        WriteRequests::Delete { ident, expected } => {
+                            if let Err(e) = checked {
+                                tracing::debug!(stream = ident, error = %e, "append rejected");
+
+                                env.client.reply(
+                                    mail.context,
+                                    mail.origin,
+                                    mail.correlation,
+                                    WriteResponses::InvalidEvent(e).into(),
+                                )?;
+
+                                continue;
+                            }
+
+                            notify_subscribers = notify;
+                            retriable = true;
+                            (ident, expected, events)
+                        }
+
+                        WriteRequests::Delete {
+                            ident,
+                            expected,
+                            dry_run: is_dry_run,
+                        } => {
                             tracing::debug!(
+                                dry_run = is_dry_run,
                                 "received stream deletion request for stream {}",
                                 ident
                             );
 
+                            dry_run = is_dry_run;
+                            deleting = true;
+
                             (
                                 ident,
                                 expected,
@@ -47,16 +107,114 @@ pub fn run(mut env: ProcessEnv<Raw>) -> eyre::Result<()> {
                                     content_type: ContentType::Binary,
                                     class: STREAM_DELETED.to_string(),
                                     data: Bytes::default(),
+                                    metadata: Default::default(),
+                                }],
+                            )
+                        }
+
+                        WriteRequests::Truncate { ident, before } => {
+                            tracing::debug!(
+                                "received stream truncation request for stream {} before {}",
+                                ident,
+                                before
+                            );
+
+                            let Some(current_revision) = metadata_target_revision(
+                                &env,
+                                &index_client,
+                                mail.context,
+                                mail.origin,
+                                mail.correlation,
+                                &ident,
+                            )?
+                            else {
+                                continue;
+                            };
+
+                            if matches!(current_revision, CurrentRevision::NoStream) {
+                                env.client.reply(
+                                    mail.context,
+                                    mail.origin,
+                                    mail.correlation,
+                                    WriteResponses::StreamNotFound.into(),
+                                )?;
+
+                                continue;
+                            }
+
+                            // Truncating past the end empties the stream but keeps it alive, so
+                            // events appended later on remain visible. A stream is never truncated
+                            // back, the revisions before the previous truncation may be scavenged.
+                            let truncated_before = truncated_before(
+                                &env,
+                                &index_client,
+                                &log_reader,
+                                mail.context,
+                                &ident,
+                            )?;
+                            let before = max(
+                                truncated_before,
+                                min(before, current_revision.next_revision()),
+                            );
+                            target_revision = Some(current_revision);
+
+                            (
+                                streams::metadata_of(&ident),
+                                ExpectedRevision::Any,
+                                vec![Propose {
+                                    id: Uuid::now_v7(),
+                                    content_type: ContentType::Binary,
+                                    class: STREAM_TRUNCATED.to_string(),
+                                    data: Bytes::copy_from_slice(&before.to_le_bytes()),
+                                    metadata: Default::default(),
+                                }],
+                            )
+                        }
+
+                        WriteRequests::SetMetadata { ident, metadata } => {
+                            tracing::debug!("received stream metadata for stream {}", ident);
+
+                            let Some(current_revision) = metadata_target_revision(
+                                &env,
+                                &index_client,
+                                mail.context,
+                                mail.origin,
+                                mail.correlation,
+                                &ident,
+                            )?
+                            else {
+                                continue;
+                            };
+
+                            target_revision = Some(current_revision);
+
+                            (
+                                streams::metadata_of(&ident),
+                                ExpectedRevision::Any,
+                                vec![Propose {
+                                    id: Uuid::now_v7(),
+                                    content_type: ContentType::Json,
+                                    class: STREAM_METADATA.to_string(),
+                                    data: serde_json::to_vec(&metadata)?.into(),
+                                    metadata: Default::default(),
                                 }],
                             )
                         }
                     };
 
-                    let key = mikoshi_hash(&ident);
-                    let current_revision =
-                        env.block_on(index_client.latest_revision(mail.context, key))?;
+                    let key = index_client.key(&ident);
+                    let state = env.block_on(index_client.stream_state(mail.context, key))?;
+                    let mut current_revision = state.current;
+
+                    // A deleted stream appended to as if it didn't exist starts a new epoch.
+                    let recreating = current_revision.is_deleted()
+                        && !deleting
+                        && expected == ExpectedRevision::NoStream
+                        && !events.is_empty();
 
-                    if current_revision.is_deleted() {
+                    if recreating {
+                        current_revision = CurrentRevision::NoStream;
+                    } else if current_revision.is_deleted() {
                         env.client.reply(
                             mail.context,
                             mail.origin,
@@ -67,6 +225,47 @@ pub fn run(mut env: ProcessEnv<Raw>) -> eyre::Result<()> {
                         continue;
                     }
 
+                    // Checked before the expected revision, a retry comes with the same one as
+                    // the append it retries.
+                    if retriable {
+                        match recent_appends.check(key, current_revision, &events) {
+                            Retry::No => {}
+
+                            Retry::Committed(append) => {
+                                tracing::debug!(stream = ident, "append already committed");
+
+                                env.client.reply(
+                                    mail.context,
+                                    mail.origin,
+                                    mail.correlation,
+                                    WriteResponses::Committed {
+                                        start_position: append.start_position,
+                                        next_position: append.next_position,
+                                        next_expected_version: ExpectedRevision::Revision(
+                                            append.revision,
+                                        ),
+                                        event_ids: append.event_ids,
+                                        created: append.created,
+                                    }
+                                    .into(),
+                                )?;
+
+                                continue;
+                            }
+
+                            Retry::Overlapping => {
+                                env.client.reply(
+                                    mail.context,
+                                    mail.origin,
+                                    mail.correlation,
+                                    WriteResponses::DuplicateEvents.into(),
+                                )?;
+
+                                continue;
+                            }
+                        }
+                    }
+
                     if let Some(e) = optimistic_concurrency_check(expected, current_revision) {
                         env.client.reply(
                             mail.context,
@@ -82,8 +281,61 @@ pub fn run(mut env: ProcessEnv<Raw>) -> eyre::Result<()> {
                         continue;
                     }
 
-                    let revision = current_revision.next_revision();
-                    let mut entries = ProposeEntries::new(metrics.clone(), ident, revision, events);
+                    if dry_run {
+                        env.client.reply(
+                            mail.context,
+                            mail.origin,
+                            mail.correlation,
+                            WriteResponses::DryRun {
+                                current: current_revision.as_expected(),
+                            }
+                            .into(),
+                        )?;
+
+                        continue;
+                    }
+
+                    // Nil ids are not meant for idempotency, the server assigns a time-ordered id
+                    // to those events instead.
+                    for event in events.iter_mut() {
+                        if event.id.is_nil() {
+                            event.id = Uuid::now_v7();
+                        }
+                    }
+
+                    let change = if deleting {
+                        Some(CatalogChange::Deleted(ident.clone()))
+                    } else if matches!(current_revision, CurrentRevision::NoStream)
+                        && !streams::is_metadata(&ident)
+                    {
+                        Some(CatalogChange::Created(ident.clone()))
+                    } else {
+                        None
+                    };
+
+                    // Tells an index rebuild which epoch the tombstone closes.
+                    if deleting {
+                        for event in events.iter_mut() {
+                            event.data = Bytes::copy_from_slice(&state.epoch().to_le_bytes());
+                        }
+                    }
+
+                    let event_ids: Vec<Uuid> = events.iter().map(|e| e.id).collect();
+                    let revision = if recreating {
+                        state.epoch_start()
+                    } else {
+                        current_revision.next_revision()
+                    };
+                    let mut entries = ProposeEntries::new(
+                        metrics.clone(),
+                        ident,
+                        key,
+                        revision,
+                        state.epoch(),
+                        events,
+                        env.options.clock.now(),
+                    )
+                    .with_compression(env.options.compression, env.options.compression_threshold);
                     let span = tracing::info_span!("append_entries_to_log", correlation = %mail.context.correlation);
 
                     match span.in_scope(|| log_writer.append(&mut entries)) {
@@ -100,7 +352,41 @@ pub fn run(mut env: ProcessEnv<Raw>) -> eyre::Result<()> {
                         }
 
                         Ok(receipt) => {
-                            env.block_on(index_client.store(mail.context, entries.indexes))?;
+                            let mut committed = entries.committed;
+                            if let Some(change) = &change
+                                && notify_subscribers
+                            {
+                                committed.push(change.feed_record(
+                                    feed_revision,
+                                    receipt.start_position,
+                                    entries.created,
+                                ));
+                                feed_revision += 1;
+                            }
+
+                            env.block_on(index_client.store(
+                                mail.context,
+                                entries.indexes,
+                                change,
+                                Some(LogCursor {
+                                    position: receipt.next_position,
+                                }),
+                            ))?;
+
+                            if deleting {
+                                recent_appends.forget(key);
+                            } else if retriable {
+                                recent_appends.record(
+                                    key,
+                                    RecentAppend {
+                                        event_ids: event_ids.clone(),
+                                        revision: entries.revision,
+                                        start_position: receipt.start_position,
+                                        next_position: receipt.next_position,
+                                        created: entries.created,
+                                    },
+                                );
+                            }
 
                             env.client.reply(
                                 mail.context,
@@ -109,14 +395,27 @@ pub fn run(mut env: ProcessEnv<Raw>) -> eyre::Result<()> {
                                 WriteResponses::Committed {
                                     start_position: receipt.start_position,
                                     next_position: receipt.next_position,
-                                    next_expected_version: ExpectedRevision::Revision(
-                                        entries.revision,
+                                    next_expected_version: target_revision.map_or(
+                                        ExpectedRevision::Revision(entries.revision),
+                                        |r| r.as_expected(),
                                     ),
+                                    event_ids,
+                                    created: entries.created,
                                 }
                                 .into(),
                             )?;
 
-                            env.block_on(sub_client.push(mail.context, entries.committed))?;
+                            if let Some(sub_client) = &sub_client
+                                && notify_subscribers
+                            {
+                                // The append is committed either way, live subscribers would
+                                // only miss it.
+                                if let Err(e) =
+                                    env.block_on(sub_client.push(mail.context, committed))
+                                {
+                                    tracing::error!(correlation = %mail.context.correlation, "error when pushing to subscribers: {}", e);
+                                }
+                            }
                         }
                     }
 
@@ -131,6 +430,92 @@ pub fn run(mut env: ProcessEnv<Raw>) -> eyre::Result<()> {
     Ok(())
 }
 
+/// Rejects the first event of an append with a class only the server appends. Deletions,
+/// truncations and settings each go through their own request.
+fn check_reserved_classes(events: &[Propose]) -> Result<(), InvalidEventError> {
+    let Some((index, event)) = events
+        .iter()
+        .enumerate()
+        .find(|(_, e)| types::is_reserved(&e.class))
+    else {
+        return Ok(());
+    };
+
+    Err(InvalidEventError {
+        index,
+        class: event.class.clone(),
+        reason: "class is reserved to the server".to_string(),
+    })
+}
+
+/// Returns the current revision of the stream a metadata write targets. When that stream is
+/// deleted, replies to the request right away and returns `None`.
+fn metadata_target_revision(
+    env: &ProcessEnv<Raw>,
+    index_client: &IndexClient,
+    context: RequestContext,
+    origin: ProcId,
+    correlation: Uuid,
+    ident: &str,
+) -> eyre::Result<Option<CurrentRevision>> {
+    let current_revision =
+        env.block_on(index_client.latest_revision(context, index_client.key(ident)))?;
+
+    if current_revision.is_deleted() {
+        env.client.reply(
+            context,
+            origin,
+            correlation,
+            WriteResponses::StreamDeleted.into(),
+        )?;
+
+        return Ok(None);
+    }
+
+    Ok(Some(current_revision))
+}
+
+/// Revision a stream was last truncated before, found in the latest truncation marker of its
+/// metadata stream.
+fn truncated_before(
+    env: &ProcessEnv<Raw>,
+    index_client: &IndexClient,
+    reader: &LogReader,
+    context: RequestContext,
+    ident: &str,
+) -> eyre::Result<u64> {
+    let key = index_client.key(streams::metadata_of(ident));
+    let Some(latest) = env
+        .block_on(index_client.latest_revision(context, key))?
+        .revision()
+    else {
+        return Ok(0);
+    };
+
+    let mut entries =
+        env.block_on(index_client.read(context, key, latest, usize::MAX, Direction::Backward))?;
+
+    while let Some(entry) = env.block_on(entries.next())? {
+        let record = record_try_from(reader.read_at(entry.position)?)?;
+
+        if record.class != STREAM_TRUNCATED {
+            continue;
+        }
+
+        let before = record.data.as_ref().try_into().map_err(|_| {
+            eyre::eyre!(
+                "malformed truncation marker for stream '{}' at log position {}",
+                ident,
+                record.position
+            )
+        })?;
+
+        return Ok(u64::from_le_bytes(before));
+    }
+
+    Ok(0)
+}
+
 fn optimistic_concurrency_check(
     expected: ExpectedRevision,
     current: CurrentRevision,
@@ -0,0 +1,72 @@
+use chrono::{DateTime, Utc};
+use geth_common::Propose;
+use uuid::Uuid;
+
+use crate::domain::index::CurrentRevision;
+
+/// Last append of each of the streams written to recently, so a client retrying an append it
+/// never got the outcome of is given the original result instead of writing the events twice.
+/// Kept in memory only, a restart forgets about every append.
+pub struct RecentAppends {
+    inner: moka::sync::Cache<u64, RecentAppend>,
+}
+
+#[derive(Clone)]
+pub struct RecentAppend {
+    pub event_ids: Vec<Uuid>,
+    /// Revision following the last event of the append, as reported to the client.
+    pub revision: u64,
+    pub start_position: u64,
+    pub next_position: u64,
+    pub created: DateTime<Utc>,
+}
+
+pub enum Retry {
+    /// None of the events were appended before.
+    No,
+    /// The same events, in the same order, are the last ones appended to the stream.
+    Committed(RecentAppend),
+    /// Some of the events were appended before, but not as the whole last append of the stream.
+    Overlapping,
+}
+
+impl RecentAppends {
+    pub fn new(capacity: u64) -> Self {
+        Self {
+            inner: moka::sync::Cache::builder()
+                .max_capacity(capacity)
+                .name(&format!("recent-appends-{}", Uuid::new_v4()))
+                .build(),
+        }
+    }
+
+    pub fn check(&self, key: u64, current: CurrentRevision, events: &[Propose]) -> Retry {
+        let Some(recent) = self.inner.get(&key) else {
+            return Retry::No;
+        };
+
+        // Nil ids are replaced by the server, they never match.
+        if !events.iter().any(|e| recent.event_ids.contains(&e.id)) {
+            return Retry::No;
+        }
+
+        let same_events = events
+            .iter()
+            .map(|e| e.id)
+            .eq(recent.event_ids.iter().copied());
+
+        if same_events && current.next_revision() == recent.revision {
+            return Retry::Committed(recent);
+        }
+
+        Retry::Overlapping
+    }
+
+    pub fn record(&self, key: u64, append: RecentAppend) {
+        self.inner.insert(key, append);
+    }
+
+    pub fn forget(&self, key: u64) {
+        self.inner.invalidate(&key);
+    }
+}
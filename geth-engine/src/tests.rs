@@ -0,0 +1,72 @@
+use geth_mikoshi::InMemoryStorage;
+use geth_mikoshi::hashing::HashAlgorithm;
+use geth_mikoshi::manifest::Manifest;
+use geth_mikoshi::wal::chunks::ChunkContainer;
+use opentelemetry_sdk::metrics::{InMemoryMetricExporter, PeriodicReader, SdkMeterProvider};
+
+use crate::Options;
+
+#[tokio::test]
+async fn test_shutdown_signal_flushes_telemetry() -> eyre::Result<()> {
+    let mut client = crate::run_embedded(&Options::in_mem_no_grpc()).await?;
+    let provider = SdkMeterProvider::builder()
+        .with_reader(PeriodicReader::builder(InMemoryMetricExporter::default()).build())
+        .build();
+
+    client.handles.metrics = Some(provider.clone());
+
+    let manager = client.manager().clone();
+    client.run_until(async {}).await?;
+
+    // The provider can only be shut down once.
+    assert!(provider.shutdown().is_err());
+
+    tokio::time::timeout(std::time::Duration::from_secs(5), manager.manager_exited()).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_manager_exit_flushes_telemetry() -> eyre::Result<()> {
+    let mut client = crate::run_embedded(&Options::in_mem_no_grpc()).await?;
+    let provider = SdkMeterProvider::builder()
+        .with_reader(PeriodicReader::builder(InMemoryMetricExporter::default()).build())
+        .build();
+
+    client.handles.metrics = Some(provider.clone());
+    client.manager().shutdown().await?;
+    client.run_until(std::future::pending()).await?;
+
+    assert!(provider.shutdown().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_manifest_decides_the_hash_algorithm() -> eyre::Result<()> {
+    let container = ChunkContainer::load(InMemoryStorage::new_storage())?;
+    Manifest::new(HashAlgorithm::Xxh3, container.chunk_size()?).save(container.storage())?;
+
+    let mut options = Options::in_mem_no_grpc();
+    options.hash_algorithm = HashAlgorithm::Xxh3;
+
+    let mut manifest = crate::check_manifest(&container)?;
+    crate::check_hash_algorithm(&options, &container, &mut manifest)?;
+
+    // Chunk headers are brought back in line with the manifest.
+    assert_eq!(HashAlgorithm::Xxh3, container.hash_algorithm()?);
+
+    options.hash_algorithm = HashAlgorithm::Fnv1a;
+    assert!(crate::check_hash_algorithm(&options, &container, &mut manifest).is_err());
+
+    options.reindex = true;
+    crate::check_hash_algorithm(&options, &container, &mut manifest)?;
+
+    assert_eq!(HashAlgorithm::Fnv1a, container.hash_algorithm()?);
+    assert_eq!(
+        Some(HashAlgorithm::Fnv1a),
+        Manifest::load(container.storage())?.map(|m| m.hash_algorithm)
+    );
+
+    Ok(())
+}
@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::path::Path;
+use std::sync::Arc;
+
+use geth_common::{ContentType, InvalidEventError, Propose};
+use serde_json::Value;
+
+/// Reason an event was rejected by a [`SchemaValidator`].
+#[derive(Debug, Clone)]
+pub struct ValidationError {
+    pub reason: String,
+}
+
+impl ValidationError {
+    pub fn new(reason: impl Into<String>) -> Self {
+        Self {
+            reason: reason.into(),
+        }
+    }
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.reason)
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Checks events before they are written. A single invalid event rejects the whole append.
+pub trait SchemaValidator: Send + Sync {
+    fn validate(
+        &self,
+        class: &str,
+        content_type: ContentType,
+        payload: &[u8],
+    ) -> Result<(), ValidationError>;
+}
+
+/// Validates JSON events against the JSON schema of their class. Schemas are loaded from a
+/// directory where each `<class>.json` file holds the schema of that class. Events of a class
+/// without a schema are accepted as is.
+pub struct JsonSchemaValidator {
+    schemas: HashMap<String, jsonschema::Validator>,
+}
+
+impl JsonSchemaValidator {
+    pub fn load(dir: impl AsRef<Path>) -> eyre::Result<Self> {
+        let dir = dir.as_ref();
+        let mut schemas = HashMap::new();
+
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+
+            let Some(class) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+
+            let schema = serde_json::from_slice::<Value>(&std::fs::read(&path)?)
+                .map_err(|e| eyre::eyre!("malformed JSON schema in '{}': {}", path.display(), e))?;
+
+            let validator = jsonschema::validator_for(&schema)
+                .map_err(|e| eyre::eyre!("invalid JSON schema in '{}': {}", path.display(), e))?;
+
+            schemas.insert(class.to_string(), validator);
+        }
+
+        tracing::info!(count = schemas.len(), dir = %dir.display(), "loaded event schemas");
+
+        Ok(Self { schemas })
+    }
+}
+
+impl SchemaValidator for JsonSchemaValidator {
+    fn validate(
+        &self,
+        class: &str,
+        content_type: ContentType,
+        payload: &[u8],
+    ) -> Result<(), ValidationError> {
+        let Some(schema) = self.schemas.get(class) else {
+            return Ok(());
+        };
+
+        if content_type != ContentType::Json {
+            return Err(ValidationError::new("expected a JSON payload"));
+        }
+
+        let value = serde_json::from_slice::<Value>(payload)
+            .map_err(|e| ValidationError::new(format!("malformed JSON payload: {e}")))?;
+
+        schema
+            .validate(&value)
+            .map_err(|e| ValidationError::new(e.to_string()))
+    }
+}
+
+/// The schema validator the writer uses, configured through [`crate::Options`].
+#[derive(Clone)]
+pub struct SharedSchemaValidator(Arc<dyn SchemaValidator>);
+
+impl SharedSchemaValidator {
+    pub fn new(validator: impl SchemaValidator + 'static) -> Self {
+        Self(Arc::new(validator))
+    }
+
+    /// Returns the first invalid event of an append, if any.
+    pub(crate) fn check(&self, events: &[Propose]) -> Result<(), InvalidEventError> {
+        for (index, event) in events.iter().enumerate() {
+            if let Err(e) = self
+                .0
+                .validate(&event.class, event.content_type, &event.data)
+            {
+                return Err(InvalidEventError {
+                    index,
+                    class: event.class.clone(),
+                    reason: e.reason,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for SharedSchemaValidator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SharedSchemaValidator")
+    }
+}
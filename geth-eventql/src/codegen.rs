@@ -1,4 +1,6 @@
-use crate::{Expr, ExprVisitor, Literal, NodeAttributes, Operation, Query, QueryVisitor, Var};
+use crate::{
+    Aggregate, Expr, ExprVisitor, Literal, NodeAttributes, Operation, Query, QueryVisitor, Var,
+};
 
 pub enum Instr {
     Push(Literal),
@@ -7,24 +9,84 @@ pub enum Instr {
     Array(usize),
     Rec(usize),
     Call(String),
+    /// Folds the current row into an aggregate slot. Pops the aggregate parameters, if any.
+    Accumulate {
+        slot: usize,
+        arity: usize,
+    },
+    /// Pushes the value an aggregate slot computed over the rows of a group.
+    LoadAggregate(usize),
 }
 
-pub fn codegen(query: &Query) -> Vec<Instr> {
+pub struct Program {
+    /// Evaluated for every row.
+    pub row: Vec<Instr>,
+    /// Evaluated once per group, after its rows were accumulated. Empty when the query doesn't
+    /// group its rows.
+    pub group: Vec<Instr>,
+    /// Aggregates computed over the rows of a group, indexed by slot.
+    pub aggregates: Vec<Aggregate>,
+}
+
+pub fn codegen(query: &Query) -> Program {
     let mut state = Codegen::default();
 
     query.dfs_post_order(&mut state);
 
-    state.instrs
+    Program {
+        row: state.row,
+        group: state.group,
+        aggregates: state.aggregates,
+    }
 }
 
 #[derive(Default)]
 pub struct Codegen {
-    instrs: Vec<Instr>,
+    row: Vec<Instr>,
+    group: Vec<Instr>,
+    aggregates: Vec<Aggregate>,
+    grouped: bool,
+    in_group: bool,
+    accumulating: usize,
+}
+
+impl Codegen {
+    fn emit(&mut self, instr: Instr) {
+        if self.in_group && self.accumulating == 0 {
+            self.group.push(instr);
+        } else {
+            self.row.push(instr);
+        }
+    }
 }
 
 impl QueryVisitor for Codegen {
     type Inner<'a> = ExprCodegen<'a>;
 
+    fn exit_query(&mut self) {
+        self.grouped = false;
+    }
+
+    fn enter_group_by(&mut self, _expr: &Expr) {
+        self.grouped = true;
+    }
+
+    fn enter_having(&mut self, _expr: &Expr) {
+        self.in_group = self.grouped;
+    }
+
+    fn leave_having(&mut self, _expr: &Expr) {
+        self.in_group = false;
+    }
+
+    fn enter_projection(&mut self, _expr: &Expr) {
+        self.in_group = self.grouped;
+    }
+
+    fn leave_projection(&mut self, _expr: &Expr) {
+        self.in_group = false;
+    }
+
     fn expr_visitor<'a>(&'a mut self) -> Self::Inner<'a> {
         ExprCodegen { inner: self }
     }
@@ -34,25 +96,60 @@ pub struct ExprCodegen<'a> {
     inner: &'a mut Codegen,
 }
 
+impl ExprCodegen<'_> {
+    fn aggregate(&self, name: &str) -> Option<Aggregate> {
+        if !self.inner.in_group {
+            return None;
+        }
+
+        Aggregate::from_name(name)
+    }
+}
+
 impl ExprVisitor for ExprCodegen<'_> {
     fn on_literal(&mut self, _attrs: &NodeAttributes, lit: &Literal) {
-        self.inner.instrs.push(Instr::Push(lit.clone()));
+        self.inner.emit(Instr::Push(lit.clone()));
     }
 
     fn on_var(&mut self, _attrs: &NodeAttributes, var: &Var) {
-        self.inner.instrs.push(Instr::LoadVar(var.clone()));
+        self.inner.emit(Instr::LoadVar(var.clone()));
+    }
+
+    fn enter_field(&mut self, _attrs: &NodeAttributes, label: &str, _value: &Expr) {
+        self.inner
+            .emit(Instr::Push(Literal::String(label.to_string())));
     }
 
     fn exit_record(&mut self, _attrs: &NodeAttributes, record: &[Expr]) {
-        self.inner.instrs.push(Instr::Rec(record.len()));
+        self.inner.emit(Instr::Rec(record.len()));
     }
 
     fn exit_array(&mut self, _attrs: &NodeAttributes, values: &[Expr]) {
-        self.inner.instrs.push(Instr::Array(values.len()));
+        self.inner.emit(Instr::Array(values.len()));
+    }
+
+    fn enter_app(&mut self, _attrs: &NodeAttributes, name: &str, _params: &[Expr]) {
+        if self.aggregate(name).is_some() {
+            self.inner.accumulating += 1;
+        }
     }
 
-    fn exit_app(&mut self, _attrs: &NodeAttributes, name: &str, _params: &[Expr]) {
-        self.inner.instrs.push(Instr::Call(name.to_string()));
+    fn exit_app(&mut self, _attrs: &NodeAttributes, name: &str, params: &[Expr]) {
+        let Some(aggregate) = self.aggregate(name) else {
+            self.inner.emit(Instr::Call(name.to_ascii_lowercase()));
+            return;
+        };
+
+        let slot = self.inner.aggregates.len();
+
+        self.inner.aggregates.push(aggregate);
+        self.inner.row.push(Instr::Accumulate {
+            slot,
+            arity: params.len(),
+        });
+
+        self.inner.accumulating -= 1;
+        self.inner.emit(Instr::LoadAggregate(slot));
     }
 
     fn exit_binary_op(
@@ -62,10 +159,10 @@ impl ExprVisitor for ExprCodegen<'_> {
         _lhs: &Expr,
         _rhs: &Expr,
     ) {
-        self.inner.instrs.push(Instr::Operation(*op));
+        self.inner.emit(Instr::Operation(*op));
     }
 
     fn exit_unary_op(&mut self, _attrs: &NodeAttributes, op: &Operation, _expr: &Expr) {
-        self.inner.instrs.push(Instr::Operation(*op));
+        self.inner.emit(Instr::Operation(*op));
     }
 }
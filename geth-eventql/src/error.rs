@@ -3,7 +3,7 @@ use std::{
     num::{ParseFloatError, ParseIntError},
 };
 
-use crate::{Operation, Pos, Type, Var, sym::Sym};
+use crate::{Aggregate, Operation, Pos, Type, Var, sym::Sym};
 
 #[derive(Debug)]
 pub struct Error {
@@ -122,6 +122,10 @@ pub enum ParserError {
     ExpectedExpr(Sym),
     SubjectDoesNotStartWithSlash,
     SubjectInvalidFormat,
+    HavingWithoutGroupBy,
+    RevisionRangeOnSubquery,
+    RevisionRangeAlreadyDefined,
+    RevisionRangeEndsBeforeStart(u64, u64),
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -136,6 +140,14 @@ pub enum InferError {
     TypeMismatch(Type, Type),
     VarTypeMismatch(Var, Type, Type),
     UnsupportedBinaryOperation(Operation),
+    NotGroupKeyOrAggregate(Var),
+    NotComparable(Type),
+    AggregateWithoutGroupBy(Aggregate),
+    NestedAggregate(Aggregate),
+    AggregateArity(Aggregate, usize),
+    NotNumeric(Aggregate, Type),
+    FunctionArity(String, usize, usize),
+    InvalidSubjectPattern(String),
 }
 
 impl Display for LexerError {
@@ -194,6 +206,23 @@ impl Display for ParserError {
             ParserError::SubjectInvalidFormat => {
                 write!(f, "invalid subject format")
             }
+
+            ParserError::HavingWithoutGroupBy => {
+                write!(f, "'HAVING' can only be used along with 'GROUP BY'")
+            }
+
+            ParserError::RevisionRangeOnSubquery => {
+                write!(f, "a subquery can't be bounded by a revision range")
+            }
+
+            ParserError::RevisionRangeAlreadyDefined => {
+                write!(f, "the source already has a revision range")
+            }
+
+            ParserError::RevisionRangeEndsBeforeStart(start, end) => write!(
+                f,
+                "revision range ends at {end} before it starts at {start}"
+            ),
         }
     }
 }
@@ -232,6 +261,41 @@ impl Display for InferError {
             InferError::UnsupportedBinaryOperation(op) => {
                 write!(f, "'{op}' is not supported for binary operations")
             }
+
+            InferError::NotGroupKeyOrAggregate(var) => {
+                write!(f, "'{var}' is neither a group key nor used in an aggregate")
+            }
+
+            InferError::NotComparable(tpe) => {
+                write!(f, "values of type '{tpe}' can't be compared")
+            }
+
+            InferError::AggregateWithoutGroupBy(agg) => write!(
+                f,
+                "'{agg}' can only be used in the HAVING clause or the projection of a query with a GROUP BY"
+            ),
+
+            InferError::NestedAggregate(agg) => {
+                write!(f, "'{agg}' can't be used within another aggregate")
+            }
+
+            InferError::AggregateArity(agg, count) => {
+                write!(f, "'{agg}' can't be called with {count} parameter(s)")
+            }
+
+            InferError::NotNumeric(agg, tpe) => {
+                write!(f, "'{agg}' expects numbers but got '{tpe}' instead")
+            }
+
+            InferError::FunctionArity(fun, expected, got) => write!(
+                f,
+                "'{fun}' expects {expected} parameter(s) but got {got} instead"
+            ),
+
+            InferError::InvalidSubjectPattern(pattern) => write!(
+                f,
+                "'{pattern}' is not a valid subject pattern, segments can't be empty and '>' can only be last"
+            ),
         }
     }
 }
@@ -1,10 +1,12 @@
 use std::collections::HashMap;
 
-use crate::{Instr, Literal, Operation, Var};
+use crate::{Aggregate, Instr, Literal, Operation, Subject, Var};
 
+#[derive(Debug)]
 pub enum EvalError {
     UnexpectedRuntimeError,
     UnexpectedVarNotFoundError(Var),
+    EmptyGroup(Aggregate),
 }
 
 pub struct Dictionary {
@@ -12,8 +14,123 @@ pub struct Dictionary {
 }
 
 impl Dictionary {
-    fn lookup(&self, _var: &Var) -> Result<Literal> {
-        todo!()
+    fn lookup(&self, var: &Var) -> Result<Literal> {
+        if let Some(lit) = self.inner.get(&var.to_string()) {
+            return Ok(lit.clone());
+        }
+
+        Err(EvalError::UnexpectedVarNotFoundError(var.clone()))
+    }
+}
+
+/// State of the aggregates of a group, fed one row at a time.
+#[derive(Clone)]
+pub struct Accumulators {
+    inner: Vec<Accumulator>,
+}
+
+impl Accumulators {
+    pub fn new(aggregates: &[Aggregate]) -> Self {
+        Self {
+            inner: aggregates
+                .iter()
+                .map(|fun| Accumulator {
+                    fun: *fun,
+                    count: 0,
+                    value: None,
+                })
+                .collect(),
+        }
+    }
+
+    fn get(&self, slot: usize) -> Result<&Accumulator> {
+        self.inner
+            .get(slot)
+            .ok_or(EvalError::UnexpectedRuntimeError)
+    }
+
+    fn get_mut(&mut self, slot: usize) -> Result<&mut Accumulator> {
+        self.inner
+            .get_mut(slot)
+            .ok_or(EvalError::UnexpectedRuntimeError)
+    }
+}
+
+#[derive(Clone)]
+struct Accumulator {
+    fun: Aggregate,
+    count: i64,
+    value: Option<Literal>,
+}
+
+impl Accumulator {
+    fn fold(&mut self, param: Option<Literal>) -> Result<()> {
+        self.count += 1;
+
+        let Some(param) = param else {
+            return Ok(());
+        };
+
+        self.value = match (self.fun, self.value.take()) {
+            (Aggregate::Count, _) => None,
+
+            (_, None) => match param {
+                Literal::Integral(_) | Literal::Float(_) => Some(param),
+                Literal::String(_) | Literal::Subject(_)
+                    if matches!(self.fun, Aggregate::Min | Aggregate::Max) =>
+                {
+                    Some(param)
+                }
+                _ => return Err(EvalError::UnexpectedRuntimeError),
+            },
+
+            (Aggregate::Sum | Aggregate::Avg, Some(acc)) => Some(add(acc, param)?),
+
+            (Aggregate::Min, Some(acc)) => Some(if less_than(&param, &acc)? { param } else { acc }),
+
+            (Aggregate::Max, Some(acc)) => Some(if less_than(&acc, &param)? { param } else { acc }),
+        };
+
+        Ok(())
+    }
+
+    fn finish(&self) -> Result<Literal> {
+        match (self.fun, &self.value) {
+            (Aggregate::Count, _) => Ok(Literal::Integral(self.count)),
+            (Aggregate::Sum, None) => Ok(Literal::Integral(0)),
+            (Aggregate::Avg, Some(Literal::Integral(i))) => {
+                Ok(Literal::Float(*i as f64 / self.count as f64))
+            }
+            (Aggregate::Avg, Some(Literal::Float(f))) => Ok(Literal::Float(f / self.count as f64)),
+            (Aggregate::Avg, Some(_)) => Err(EvalError::UnexpectedRuntimeError),
+            (_, Some(value)) => Ok(value.clone()),
+            (fun, None) => Err(EvalError::EmptyGroup(fun)),
+        }
+    }
+}
+
+fn add(lhs: Literal, rhs: Literal) -> Result<Literal> {
+    match (lhs, rhs) {
+        (Literal::Integral(x), Literal::Integral(y)) => x
+            .checked_add(y)
+            .map(Literal::Integral)
+            .ok_or(EvalError::UnexpectedRuntimeError),
+        (Literal::Integral(x), Literal::Float(y)) => Ok(Literal::Float(x as f64 + y)),
+        (Literal::Float(x), Literal::Integral(y)) => Ok(Literal::Float(x + y as f64)),
+        (Literal::Float(x), Literal::Float(y)) => Ok(Literal::Float(x + y)),
+        _ => Err(EvalError::UnexpectedRuntimeError),
+    }
+}
+
+fn less_than(lhs: &Literal, rhs: &Literal) -> Result<bool> {
+    match (lhs, rhs) {
+        (Literal::Integral(x), Literal::Integral(y)) => Ok(x < y),
+        (Literal::Integral(x), Literal::Float(y)) => Ok((*x as f64) < *y),
+        (Literal::Float(x), Literal::Integral(y)) => Ok(*x < *y as f64),
+        (Literal::Float(x), Literal::Float(y)) => Ok(x < y),
+        (Literal::String(x), Literal::String(y)) => Ok(x < y),
+        (Literal::Subject(x), Literal::Subject(y)) => Ok(x < y),
+        _ => Err(EvalError::UnexpectedRuntimeError),
     }
 }
 
@@ -103,14 +220,49 @@ impl Stack {
 }
 
 pub fn eval(dict: &Dictionary, instrs: Vec<Instr>) -> Result<Option<Entry>> {
+    run(dict, &instrs, &mut Accumulators::new(&[]))
+}
+
+/// Feeds a row to the aggregates of its group.
+pub fn accumulate(dict: &Dictionary, instrs: &[Instr], accs: &mut Accumulators) -> Result<()> {
+    run(dict, instrs, accs)?;
+
+    Ok(())
+}
+
+/// Evaluates the part of a query that runs once per group, after all its rows were accumulated.
+pub fn eval_group(
+    dict: &Dictionary,
+    instrs: &[Instr],
+    accs: &Accumulators,
+) -> Result<Option<Entry>> {
+    run(dict, instrs, &mut accs.clone())
+}
+
+fn run(dict: &Dictionary, instrs: &[Instr], accs: &mut Accumulators) -> Result<Option<Entry>> {
     let mut stack = Stack::default();
 
     for instr in instrs {
         match instr {
-            Instr::Push(lit) => stack.push_literal(lit),
+            Instr::Push(lit) => stack.push_literal(lit.clone()),
 
             Instr::LoadVar(var) => {
-                let lit = dict.lookup(&var)?;
+                let lit = dict.lookup(var)?;
+                stack.push_literal(lit);
+            }
+
+            Instr::Accumulate { slot, arity } => {
+                let param = match arity {
+                    0 => None,
+                    1 => Some(stack.pop_as_literal_or_bail()?),
+                    _ => return Err(EvalError::UnexpectedRuntimeError),
+                };
+
+                accs.get_mut(*slot)?.fold(param)?;
+            }
+
+            Instr::LoadAggregate(slot) => {
+                let lit = accs.get(*slot)?.finish()?;
                 stack.push_literal(lit);
             }
 
@@ -461,9 +613,9 @@ pub fn eval(dict: &Dictionary, instrs: Vec<Instr>) -> Result<Option<Entry>> {
             },
 
             Instr::Array(siz) => {
-                let mut array = Vec::with_capacity(siz);
+                let mut array = Vec::with_capacity(*siz);
 
-                for _ in 0..siz {
+                for _ in 0..*siz {
                     array.push(stack.pop_or_bail()?);
                 }
 
@@ -471,9 +623,9 @@ pub fn eval(dict: &Dictionary, instrs: Vec<Instr>) -> Result<Option<Entry>> {
             }
 
             Instr::Rec(siz) => {
-                let mut fields = HashMap::with_capacity(siz);
+                let mut fields = HashMap::with_capacity(*siz);
 
-                for _ in 0..siz {
+                for _ in 0..*siz {
                     let value = stack.pop_or_bail()?;
                     let key = stack.pop_as_string_or_bail()?;
 
@@ -514,6 +666,47 @@ pub fn eval(dict: &Dictionary, instrs: Vec<Instr>) -> Result<Option<Entry>> {
                     Either::Right(f) => stack.push_literal(Literal::Float(f.tan())),
                 },
 
+                "starts_with" => {
+                    let prefix = stack.pop_as_string_or_bail()?;
+                    let value = stack.pop_as_string_or_bail()?;
+
+                    stack.push_literal(Literal::Bool(value.starts_with(&prefix)));
+                }
+
+                "contains" => {
+                    let needle = stack.pop_as_string_or_bail()?;
+                    let value = stack.pop_as_string_or_bail()?;
+
+                    stack.push_literal(Literal::Bool(value.contains(&needle)));
+                }
+
+                "lower" => {
+                    let value = stack.pop_as_string_or_bail()?;
+                    stack.push_literal(Literal::String(value.to_lowercase()));
+                }
+
+                "upper" => {
+                    let value = stack.pop_as_string_or_bail()?;
+                    stack.push_literal(Literal::String(value.to_uppercase()));
+                }
+
+                "subject_match" => {
+                    let pattern = stack.pop_as_string_or_bail()?;
+                    let Some(pattern) = Subject::pattern(&pattern) else {
+                        return Err(EvalError::UnexpectedRuntimeError);
+                    };
+
+                    let matched = match stack.pop_as_literal_or_bail()? {
+                        Literal::Subject(subject) => pattern.matches(subject.path()),
+                        Literal::String(name) => {
+                            Subject::split(&name).is_some_and(|segments| pattern.matches(&segments))
+                        }
+                        _ => return Err(EvalError::UnexpectedRuntimeError),
+                    };
+
+                    stack.push_literal(Literal::Bool(matched));
+                }
+
                 _ => return Err(EvalError::UnexpectedRuntimeError),
             },
         }
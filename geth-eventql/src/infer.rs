@@ -1,9 +1,9 @@
 use std::{collections::HashMap, fmt::Display};
 
 use crate::{
-    Expr, Literal, Operation, Pos, Query, Scopes, Var,
+    Aggregate, Expr, Literal, Operation, Pos, Query, Scopes, Subject, Var,
     error::InferError,
-    parser::{ExprVisitorMut, NodeAttributes, QueryVisitorMut},
+    parser::{ExprVisitor, ExprVisitorMut, NodeAttributes, QueryVisitorMut},
 };
 
 pub struct InferedQuery {
@@ -135,6 +135,10 @@ pub fn infer(scopes: Scopes, mut query: Query) -> crate::Result<InferedQuery> {
     let mut type_check = Typecheck {
         assumptions: inner,
         scopes,
+        grouped: false,
+        group_keys: Vec::new(),
+        aggregates_allowed: false,
+        aggregating: 0,
     };
 
     query.dfs_post_order_mut(&mut type_check)?;
@@ -151,6 +155,13 @@ pub fn infer(scopes: Scopes, mut query: Query) -> crate::Result<InferedQuery> {
 struct Typecheck {
     assumptions: HashMap<String, Type>,
     scopes: Scopes,
+    grouped: bool,
+    /// Variables the query being checked groups by.
+    group_keys: Vec<Var>,
+    /// Aggregates can only be used where a grouped query looks at whole groups: in its `having`
+    /// clause and its projection.
+    aggregates_allowed: bool,
+    aggregating: usize,
 }
 
 fn urn(scope: u64, name: &String, path: &Vec<String>) -> String {
@@ -194,6 +205,60 @@ impl QueryVisitorMut for Typecheck {
         Ok(())
     }
 
+    fn exit_query_mut(&mut self) -> crate::Result<()> {
+        self.grouped = false;
+        self.group_keys.clear();
+
+        Ok(())
+    }
+
+    fn enter_group_by_mut(&mut self, expr: &mut Expr) -> crate::Result<()> {
+        let mut keys = GroupKeys::default();
+        expr.dfs_post_order(&mut keys);
+        self.grouped = true;
+        self.group_keys = keys.vars;
+
+        Ok(())
+    }
+
+    fn enter_having_mut(&mut self, expr: &mut Expr) -> crate::Result<()> {
+        expr.attrs.tpe = Type::Bool;
+
+        let mut check = HavingCheck {
+            group_keys: &self.group_keys,
+            aggregates: 0,
+            error: None,
+        };
+
+        expr.dfs_post_order(&mut check);
+
+        if let Some((pos, var)) = check.error {
+            bail!(pos, InferError::NotGroupKeyOrAggregate(var));
+        }
+
+        self.aggregates_allowed = true;
+
+        Ok(())
+    }
+
+    fn leave_having_mut(&mut self, _expr: &mut Expr) -> crate::Result<()> {
+        self.aggregates_allowed = false;
+
+        Ok(())
+    }
+
+    fn enter_projection_mut(&mut self, _expr: &mut Expr) -> crate::Result<()> {
+        self.aggregates_allowed = self.grouped;
+
+        Ok(())
+    }
+
+    fn leave_projection_mut(&mut self, _expr: &mut Expr) -> crate::Result<()> {
+        self.aggregates_allowed = false;
+
+        Ok(())
+    }
+
     fn expr_visitor_mut<'a>(&'a mut self) -> Self::Inner<'a> {
         TypecheckExpr { inner: self }
     }
@@ -263,12 +328,126 @@ impl ExprVisitorMut for TypecheckExpr<'_> {
         Ok(())
     }
 
+    fn enter_app(
+        &mut self,
+        attrs: &mut NodeAttributes,
+        name: &str,
+        params: &mut Vec<Expr>,
+    ) -> crate::Result<()> {
+        let Some(aggregate) = Aggregate::from_name(name) else {
+            return Ok(());
+        };
+
+        if !self.inner.aggregates_allowed {
+            bail!(attrs.pos, InferError::AggregateWithoutGroupBy(aggregate));
+        }
+
+        if self.inner.aggregating > 0 {
+            bail!(attrs.pos, InferError::NestedAggregate(aggregate));
+        }
+
+        let arity_ok = match aggregate {
+            Aggregate::Count => params.len() <= 1,
+            _ => params.len() == 1,
+        };
+
+        if !arity_ok {
+            bail!(
+                attrs.pos,
+                InferError::AggregateArity(aggregate, params.len())
+            );
+        }
+
+        self.inner.aggregating += 1;
+
+        Ok(())
+    }
+
     fn exit_app(
         &mut self,
-        _attrs: &mut NodeAttributes,
-        _name: &str,
-        _params: &mut Vec<Expr>,
+        attrs: &mut NodeAttributes,
+        name: &str,
+        params: &mut Vec<Expr>,
     ) -> crate::Result<()> {
+        if let Some(aggregate) = Aggregate::from_name(name) {
+            self.inner.aggregating -= 1;
+
+            let param = params.first().map(|p| (p.attrs.pos, p.attrs.tpe));
+            let result_type = match (aggregate, param) {
+                (Aggregate::Count, _) => Type::Integer,
+
+                (Aggregate::Sum | Aggregate::Avg, Some((pos, tpe))) => {
+                    if !matches!(tpe, Type::Unspecified | Type::Integer | Type::Float) {
+                        bail!(pos, InferError::NotNumeric(aggregate, tpe));
+                    }
+
+                    if aggregate == Aggregate::Avg {
+                        Type::Float
+                    } else {
+                        tpe
+                    }
+                }
+
+                (Aggregate::Min | Aggregate::Max, Some((pos, tpe))) => {
+                    if matches!(tpe, Type::Array | Type::Record) {
+                        bail!(pos, InferError::NotComparable(tpe));
+                    }
+
+                    tpe
+                }
+
+                (_, None) => Type::Unspecified,
+            };
+
+            if result_type != Type::Unspecified {
+                if attrs.tpe != Type::Unspecified && attrs.tpe != result_type {
+                    bail!(attrs.pos, InferError::TypeMismatch(attrs.tpe, result_type));
+                }
+
+                attrs.tpe = result_type;
+            }
+        }
+
+        if let Some((param_types, result_type)) = builtin_signature(name) {
+            if params.len() != param_types.len() {
+                bail!(
+                    attrs.pos,
+                    InferError::FunctionArity(name.to_string(), param_types.len(), params.len())
+                );
+            }
+
+            for (param, expected) in params.iter_mut().zip(param_types) {
+                if param.attrs.tpe == Type::Unspecified {
+                    param.attrs.tpe = *expected;
+
+                    if let Some(var) = param.as_var() {
+                        self.inner.set_type_info(param.attrs.scope, var, *expected);
+                    }
+                } else if !accepts(*expected, param.attrs.tpe) {
+                    bail!(
+                        param.attrs.pos,
+                        InferError::TypeMismatch(*expected, param.attrs.tpe)
+                    );
+                }
+            }
+
+            if name.eq_ignore_ascii_case("subject_match")
+                && let Some(pattern) = params[1].as_string_literal()
+                && Subject::pattern(pattern).is_none()
+            {
+                bail!(
+                    params[1].attrs.pos,
+                    InferError::InvalidSubjectPattern(pattern.to_string())
+                );
+            }
+
+            if attrs.tpe != Type::Unspecified && attrs.tpe != result_type {
+                bail!(attrs.pos, InferError::TypeMismatch(attrs.tpe, result_type));
+            }
+
+            attrs.tpe = result_type;
+        }
+
         // TODO - we can make a lot of assumptions when it comes to the return type of the
         // function call.
         //
@@ -361,6 +540,10 @@ impl ExprVisitorMut for TypecheckExpr<'_> {
             );
         }
 
+        if operation_orders(op) && matches!(lhs.attrs.tpe, Type::Array | Type::Record) {
+            bail!(attrs.pos, InferError::NotComparable(lhs.attrs.tpe));
+        }
+
         if op == &Operation::Contains && lhs.attrs.tpe != Type::Array {
             bail!(
                 attrs.pos,
@@ -418,6 +601,70 @@ impl ExprVisitorMut for TypecheckExpr<'_> {
     }
 }
 
+#[derive(Default)]
+struct GroupKeys {
+    vars: Vec<Var>,
+}
+
+impl ExprVisitor for GroupKeys {
+    fn on_var(&mut self, _attrs: &NodeAttributes, var: &Var) {
+        self.vars.push(var.clone());
+    }
+}
+
+/// Groups no longer have rows, so a `having` clause can only look at what the rows of a group
+/// share, its keys, or at what is computed over all of them, aggregates.
+struct HavingCheck<'a> {
+    group_keys: &'a [Var],
+    aggregates: usize,
+    error: Option<(Pos, Var)>,
+}
+
+impl ExprVisitor for HavingCheck<'_> {
+    fn on_var(&mut self, attrs: &NodeAttributes, var: &Var) {
+        if self.aggregates == 0 && self.error.is_none() && !self.group_keys.contains(var) {
+            self.error = Some((attrs.pos, var.clone()));
+        }
+    }
+
+    fn enter_app(&mut self, _attrs: &NodeAttributes, name: &str, _params: &[Expr]) {
+        if Aggregate::from_name(name).is_some() {
+            self.aggregates += 1;
+        }
+    }
+
+    fn exit_app(&mut self, _attrs: &NodeAttributes, name: &str, _params: &[Expr]) {
+        if Aggregate::from_name(name).is_some() {
+            self.aggregates -= 1;
+        }
+    }
+}
+
+/// Parameter and return types of the builtin functions.
+fn builtin_signature(name: &str) -> Option<(&'static [Type], Type)> {
+    match name.to_ascii_lowercase().as_str() {
+        "starts_with" | "contains" => Some((&[Type::String, Type::String], Type::Bool)),
+        "lower" | "upper" => Some((&[Type::String], Type::String)),
+        "subject_match" => Some((&[Type::Subject, Type::String], Type::Bool)),
+        _ => None,
+    }
+}
+
+/// Stream names are subjects too, even when written as plain strings.
+fn accepts(expected: Type, actual: Type) -> bool {
+    expected == actual || (expected == Type::Subject && actual == Type::String)
+}
+
 fn operation_requires_same_type(op: &Operation) -> bool {
     !matches!(op, Operation::Contains)
 }
+
+fn operation_orders(op: &Operation) -> bool {
+    matches!(
+        op,
+        Operation::LessThan
+            | Operation::GreaterThan
+            | Operation::LessThanOrEqual
+            | Operation::GreaterThanOrEqual
+    )
+}
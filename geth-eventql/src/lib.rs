@@ -8,6 +8,7 @@ mod error;
 mod eval;
 mod infer;
 mod parser;
+mod plan;
 mod rename;
 mod sym;
 mod tokenizer;
@@ -21,10 +22,10 @@ pub mod private {
 
 pub use parser::{
     ContextFrame, Expr, ExprVisitor, ExprVisitorMut, FromSource, Limit, LimitKind, NodeAttributes,
-    Order, Query, QueryVisitor, QueryVisitorMut, Sort, Source, SourceType, Subject, Value, Var,
-    Where,
+    Order, Query, QueryVisitor, QueryVisitorMut, RevisionRange, Sort, Source, SourceType, Subject,
+    Value, Var, Where,
 };
-pub use sym::{Literal, Operation};
+pub use sym::{Aggregate, Literal, Operation};
 pub use tokenizer::Pos;
 
 pub type Result<A> = std::result::Result<A, crate::error::Error>;
@@ -41,9 +42,10 @@ pub fn parse_rename_and_infer(query: &str) -> crate::Result<InferedQuery> {
     infer(scopes, query)
 }
 
-pub use codegen::{Instr, codegen};
-pub use eval::{Dictionary, Entry, EvalError, eval};
+pub use codegen::{Instr, Program, codegen};
+pub use eval::{Accumulators, Dictionary, Entry, EvalError, accumulate, eval, eval_group};
 pub use infer::infer;
 pub use infer::{Infer, InferedQuery, Type};
+pub use plan::{Predicate, ReadPlan, ReadTarget, SourceRead, plan};
 pub use rename::rename;
 pub use rename::{Properties, Scope, Scopes};
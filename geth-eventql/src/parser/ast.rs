@@ -27,6 +27,8 @@ pub struct Query {
     pub from_stmts: Vec<FromSource>,
     pub predicate: Option<Where>,
     pub group_by: Option<Expr>,
+    /// Filters the groups, only ever set along with `group_by`.
+    pub having: Option<Expr>,
     pub order_by: Option<Sort>,
     pub limit: Option<Limit>,
     pub projection: Expr,
@@ -76,6 +78,10 @@ fn query_dfs_post_order_mut<V: QueryVisitorMut>(
                     }
                 }
 
+                if let Some(range) = from_stmt.source.range.as_mut() {
+                    visitor.on_source_range_mut(&mut from_stmt.attrs, &from_stmt.ident, range)?;
+                }
+
                 visitor.exit_source_mut(&mut from_stmt.source.attrs)?;
                 visitor.exit_from_mut(&mut from_stmt.attrs, &from_stmt.ident)?;
             }
@@ -95,6 +101,12 @@ fn query_dfs_post_order_mut<V: QueryVisitorMut>(
             visitor.leave_group_by_mut(expr)?;
         }
 
+        if let Some(expr) = query.having.as_mut() {
+            visitor.enter_having_mut(expr)?;
+            on_expr_mut(visitor, expr)?;
+            visitor.leave_having_mut(expr)?;
+        }
+
         if let Some(sort) = query.order_by.as_mut() {
             visitor.enter_order_by_mut(&mut sort.order, &mut sort.expr)?;
             on_expr_mut(visitor, &mut sort.expr)?;
@@ -143,6 +155,10 @@ fn query_dfs_post_order<V: QueryVisitor>(mut stack: Vec<Item<Query>>, visitor: &
                     }
                 }
 
+                if let Some(range) = from_stmt.source.range.as_ref() {
+                    visitor.on_source_range(&from_stmt.attrs, &from_stmt.ident, range);
+                }
+
                 visitor.exit_source(&from_stmt.source.attrs);
                 visitor.exit_from(&from_stmt.attrs, &from_stmt.ident);
             }
@@ -162,6 +178,12 @@ fn query_dfs_post_order<V: QueryVisitor>(mut stack: Vec<Item<Query>>, visitor: &
             visitor.leave_group_by(expr);
         }
 
+        if let Some(expr) = query.having.as_ref() {
+            visitor.enter_having(expr);
+            on_expr(visitor, expr);
+            visitor.leave_having(expr);
+        }
+
         if let Some(sort) = query.order_by.as_ref() {
             visitor.enter_order_by(&sort.order, &sort.expr);
             on_expr(visitor, &sort.expr);
@@ -205,6 +227,54 @@ impl Subject {
     pub fn path(&self) -> &[String] {
         self.inner.as_slice()
     }
+
+    /// Splits a subject into its segments, the leading `/` being optional. Returns `None` if a
+    /// segment is empty.
+    pub(crate) fn split(subject: &str) -> Option<Vec<&str>> {
+        let subject = subject.strip_prefix('/').unwrap_or(subject);
+        let mut segments = Vec::new();
+
+        for segment in subject.split_terminator('/') {
+            if segment.trim_ascii().is_empty() {
+                return None;
+            }
+
+            segments.push(segment);
+        }
+
+        Some(segments)
+    }
+
+    /// Parses a subject-style pattern, where `*` matches a single segment and `>` matches all the
+    /// remaining ones. `>` can only be the last segment.
+    pub fn pattern(pattern: &str) -> Option<Self> {
+        let segments = Self::split(pattern)?;
+
+        if segments.iter().rev().skip(1).any(|segment| *segment == ">") {
+            return None;
+        }
+
+        Some(Self {
+            inner: segments.into_iter().map(String::from).collect(),
+        })
+    }
+
+    /// Tells if the segments match this subject used as a pattern. `>` requires at least one
+    /// remaining segment.
+    pub fn matches<S: AsRef<str>>(&self, segments: &[S]) -> bool {
+        for (idx, pattern) in self.inner.iter().enumerate() {
+            if pattern == ">" {
+                return segments.len() > idx;
+            }
+
+            match segments.get(idx) {
+                Some(segment) if pattern == "*" || pattern == segment.as_ref() => {}
+                _ => return false,
+            }
+        }
+
+        segments.len() == self.inner.len()
+    }
 }
 
 pub enum SourceType {
@@ -234,6 +304,22 @@ impl SourceType {
 pub struct Source {
     pub attrs: NodeAttributes,
     pub inner: SourceType,
+    /// Only the events within the range are read from the source.
+    pub range: Option<RevisionRange>,
+}
+
+/// Revisions a source is read between, both ends included. No end means up to the last event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RevisionRange {
+    pub start: u64,
+    pub end: Option<u64>,
+}
+
+impl RevisionRange {
+    /// How many events the range spans at most.
+    pub fn max_count(&self) -> Option<u64> {
+        self.end.map(|end| end - self.start + 1)
+    }
 }
 
 impl Source {
@@ -257,11 +343,54 @@ pub struct Where {
     pub expr: Expr,
 }
 
+#[derive(Clone)]
 pub struct Expr {
     pub attrs: NodeAttributes,
     pub value: Value,
 }
 
+impl Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.value {
+            Value::Literal(lit) => write!(f, "{lit}"),
+            Value::Var(var) => write!(f, "{var}"),
+            Value::Field { label, value } => write!(f, "{label}: {value}"),
+            Value::Record(fields) => {
+                write!(f, "{{ ")?;
+                write_separated(f, fields)?;
+                write!(f, " }}")
+            }
+
+            Value::Array(values) => {
+                write!(f, "[")?;
+                write_separated(f, values)?;
+                write!(f, "]")
+            }
+
+            Value::App { fun, params } => {
+                write!(f, "{fun}(")?;
+                write_separated(f, params)?;
+                write!(f, ")")
+            }
+
+            Value::Binary { lhs, op, rhs } => write!(f, "({lhs} {op} {rhs})"),
+            Value::Unary { op, expr } => write!(f, "{op} {expr}"),
+        }
+    }
+}
+
+fn write_separated(f: &mut std::fmt::Formatter<'_>, exprs: &[Expr]) -> std::fmt::Result {
+    for (idx, expr) in exprs.iter().enumerate() {
+        if idx > 0 {
+            write!(f, ", ")?;
+        }
+
+        write!(f, "{expr}")?;
+    }
+
+    Ok(())
+}
+
 impl AsMut<Value> for Expr {
     fn as_mut(&mut self) -> &mut Value {
         &mut self.value
@@ -610,6 +739,7 @@ impl Display for Var {
     }
 }
 
+#[derive(Clone)]
 pub enum Value {
     Literal(Literal),
 
@@ -670,6 +800,7 @@ pub enum ContextFrame {
     Unspecified,
     Where,
     GroupBy,
+    Having,
     OrderBy,
     Projection,
 }
@@ -721,6 +852,15 @@ pub trait QueryVisitorMut {
         Ok(true)
     }
 
+    fn on_source_range_mut(
+        &mut self,
+        attrs: &mut NodeAttributes,
+        ident: &str,
+        range: &mut RevisionRange,
+    ) -> crate::Result<()> {
+        Ok(())
+    }
+
     fn exit_source_mut(&mut self, _attrs: &mut NodeAttributes) -> crate::Result<()> {
         Ok(())
     }
@@ -749,6 +889,14 @@ pub trait QueryVisitorMut {
         Ok(())
     }
 
+    fn enter_having_mut(&mut self, expr: &mut Expr) -> crate::Result<()> {
+        Ok(())
+    }
+
+    fn leave_having_mut(&mut self, expr: &mut Expr) -> crate::Result<()> {
+        Ok(())
+    }
+
     fn enter_order_by_mut(&mut self, order: &mut Order, _expr: &mut Expr) -> crate::Result<()> {
         Ok(())
     }
@@ -900,11 +1048,14 @@ pub trait QueryVisitor {
     fn on_source_subquery(&mut self, attrs: &NodeAttributes, ident: &str) -> bool {
         true
     }
+    fn on_source_range(&mut self, attrs: &NodeAttributes, ident: &str, range: &RevisionRange) {}
     fn exit_source(&mut self, attrs: &NodeAttributes) {}
     fn enter_where_clause(&mut self, attrs: &NodeAttributes, expr: &Expr) {}
     fn exit_where_clause(&mut self, attrs: &NodeAttributes, expr: &Expr) {}
     fn enter_group_by(&mut self, expr: &Expr) {}
     fn leave_group_by(&mut self, expr: &Expr) {}
+    fn enter_having(&mut self, expr: &Expr) {}
+    fn leave_having(&mut self, expr: &Expr) {}
     fn enter_order_by(&mut self, order: &Order, expr: &Expr) {}
     fn leave_order_by(&mut self, order: &Order, expr: &Expr) {}
     fn enter_projection(&mut self, expr: &Expr) {}
@@ -1,6 +1,6 @@
 use crate::{
     error::ParserError,
-    sym::{Keyword, Literal, Sym},
+    sym::{Keyword, Literal, Operation, Sym},
     tokenizer::{Lexer, Pos},
 };
 
@@ -20,11 +20,26 @@ fn parse_query(state: &mut ParserState<'_>) -> crate::Result<Query> {
     let pos = state.pos();
     let mut from_stmts = Vec::new();
 
-    from_stmts.push(parse_from_statement(state)?);
+    state.skip_whitespace()?;
+    state.expect(Sym::Keyword(Keyword::From))?;
+    from_stmts.push(parse_from_statement(state, pos)?);
     state.skip_whitespace()?;
 
+    // `FROM` either starts another source or bounds the previous one, `FROM REVISION 1 TO 2`.
     while let Some(Sym::Keyword(Keyword::From)) = state.look_ahead()? {
-        from_stmts.push(parse_from_statement(state)?);
+        let pos = state.pos();
+        state.shift()?;
+        state.skip_whitespace()?;
+
+        if let Some(Sym::Id(id)) = state.look_ahead()?
+            && id.eq_ignore_ascii_case("revision")
+        {
+            let from = from_stmts.last_mut().expect("to be always defined");
+            parse_revision_range(state, pos, &mut from.source)?;
+        } else {
+            from_stmts.push(parse_from_statement(state, pos)?);
+        }
+
         state.skip_whitespace()?;
     }
 
@@ -33,6 +48,8 @@ fn parse_query(state: &mut ParserState<'_>) -> crate::Result<Query> {
     state.skip_whitespace()?;
     let group_by = parse_group_by(state)?;
     state.skip_whitespace()?;
+    let having = parse_having(state, group_by.is_some())?;
+    state.skip_whitespace()?;
     let order_by = parse_order_by(state)?;
     state.skip_whitespace()?;
     let limit = parse_limit(state)?;
@@ -52,6 +69,7 @@ fn parse_query(state: &mut ParserState<'_>) -> crate::Result<Query> {
         from_stmts,
         predicate,
         group_by,
+        having,
         projection,
         order_by,
         limit,
@@ -68,11 +86,8 @@ fn check_projection(proj: &Expr) -> crate::Result<()> {
     }
 }
 
-fn parse_from_statement(state: &mut ParserState<'_>) -> crate::Result<FromSource> {
-    state.skip_whitespace()?;
-
-    let pos = state.pos();
-    state.expect(Sym::Keyword(Keyword::From))?;
+/// Parses what follows the `FROM` keyword found at `pos`.
+fn parse_from_statement(state: &mut ParserState<'_>, pos: Pos) -> crate::Result<FromSource> {
     state.skip_whitespace()?;
     let ident = parse_ident(state)?;
     state.skip_whitespace()?;
@@ -88,6 +103,58 @@ fn parse_from_statement(state: &mut ParserState<'_>) -> crate::Result<FromSource
     })
 }
 
+/// Parses what follows `FROM` in `FROM REVISION <start> [TO <end>]`.
+fn parse_revision_range(
+    state: &mut ParserState<'_>,
+    pos: Pos,
+    source: &mut Source,
+) -> crate::Result<()> {
+    if source.as_subquery().is_some() {
+        bail!(pos, ParserError::RevisionRangeOnSubquery);
+    }
+
+    if source.range.is_some() {
+        bail!(pos, ParserError::RevisionRangeAlreadyDefined);
+    }
+
+    state.shift()?;
+    state.skip_whitespace()?;
+    let start = parse_revision(state)?;
+    state.skip_whitespace()?;
+
+    let mut end = None;
+    if let Some(Sym::Id(id)) = state.look_ahead()?
+        && id.eq_ignore_ascii_case("to")
+    {
+        state.shift()?;
+        state.skip_whitespace()?;
+
+        let end_pos = state.pos();
+        let revision = parse_revision(state)?;
+
+        if revision < start {
+            bail!(
+                end_pos,
+                ParserError::RevisionRangeEndsBeforeStart(start, revision)
+            );
+        }
+
+        end = Some(revision);
+    }
+
+    source.range = Some(RevisionRange { start, end });
+
+    Ok(())
+}
+
+fn parse_revision(state: &mut ParserState<'_>) -> crate::Result<u64> {
+    let pos = state.pos();
+    match state.shift_or_bail()? {
+        Sym::Literal(Literal::Integral(n)) if n >= 0 => Ok(n as u64),
+        x => bail!(pos, ParserError::ExpectedGreaterOrEqualToZero(x)),
+    }
+}
+
 fn parse_group_by(state: &mut ParserState<'_>) -> crate::Result<Option<Expr>> {
     state.skip_whitespace()?;
     if let Some(sym) = state.look_ahead()?
@@ -104,6 +171,25 @@ fn parse_group_by(state: &mut ParserState<'_>) -> crate::Result<Option<Expr>> {
     Ok(Some(parse_expr_single(state)?))
 }
 
+fn parse_having(state: &mut ParserState<'_>, grouped: bool) -> crate::Result<Option<Expr>> {
+    state.skip_whitespace()?;
+    if let Some(sym) = state.look_ahead()?
+        && sym != &Sym::Keyword(Keyword::Having)
+    {
+        return Ok(None);
+    }
+
+    let pos = state.pos();
+    if !grouped {
+        bail!(pos, ParserError::HavingWithoutGroupBy);
+    }
+
+    state.shift()?;
+    state.skip_whitespace()?;
+
+    Ok(Some(parse_expr(state)?))
+}
+
 fn parse_order_by(state: &mut ParserState<'_>) -> crate::Result<Option<Sort>> {
     state.skip_whitespace()?;
     if let Some(sym) = state.look_ahead()?
@@ -182,11 +268,13 @@ fn parse_source(state: &mut ParserState<'_>) -> crate::Result<Source> {
         Sym::Id(id) if id.to_lowercase() == "events" => Ok(Source {
             attrs: NodeAttributes::new(pos),
             inner: SourceType::Events,
+            range: None,
         }),
 
         Sym::Literal(Literal::String(sub)) => Ok(Source {
             attrs: NodeAttributes::new(pos),
             inner: SourceType::Subject(parse_subject(pos, &sub)?),
+            range: None,
         }),
 
         Sym::LParens => {
@@ -198,6 +286,7 @@ fn parse_source(state: &mut ParserState<'_>) -> crate::Result<Source> {
             Ok(Source {
                 attrs: NodeAttributes::new(pos),
                 inner: SourceType::Subquery(Box::new(query)),
+                range: None,
             })
         }
 
@@ -210,16 +299,13 @@ pub(crate) fn parse_subject(pos: Pos, subject: &str) -> crate::Result<Subject> {
         bail!(pos, ParserError::SubjectDoesNotStartWithSlash);
     }
 
-    let mut inner = Vec::new();
-    for segment in subject.split_terminator('/').skip(1) {
-        if segment.trim_ascii().is_empty() {
-            bail!(pos, ParserError::SubjectInvalidFormat);
-        }
-
-        inner.push(segment.to_string());
-    }
+    let Some(segments) = Subject::split(subject) else {
+        bail!(pos, ParserError::SubjectInvalidFormat);
+    };
 
-    Ok(Subject { inner })
+    Ok(Subject {
+        inner: segments.into_iter().map(String::from).collect(),
+    })
 }
 
 fn parse_where_clause(state: &mut ParserState<'_>) -> crate::Result<Option<Where>> {
@@ -247,7 +333,7 @@ fn parse_where_clause(state: &mut ParserState<'_>) -> crate::Result<Option<Where
 fn parse_expr(state: &mut ParserState<'_>) -> crate::Result<Expr> {
     state.skip_whitespace()?;
 
-    let expr = parse_expr_single(state)?;
+    let expr = parse_operand(state)?;
     state.skip_whitespace()?;
 
     // binary operation
@@ -264,7 +350,7 @@ fn parse_expr(state: &mut ParserState<'_>) -> crate::Result<Expr> {
             value: Value::Binary {
                 lhs: Box::new(expr),
                 op,
-                rhs: Box::new(parse_expr_single(state)?),
+                rhs: Box::new(parse_operand(state)?),
             },
         });
 
@@ -274,7 +360,7 @@ fn parse_expr(state: &mut ParserState<'_>) -> crate::Result<Expr> {
             op_stack.push(*op);
             state.shift()?;
             state.skip_whitespace()?;
-            expr_stack.push(parse_expr_single(state)?);
+            expr_stack.push(parse_operand(state)?);
             state.skip_whitespace()?;
         }
 
@@ -298,6 +384,49 @@ fn parse_expr(state: &mut ParserState<'_>) -> crate::Result<Expr> {
     Ok(expr)
 }
 
+/// Parses an operand of a binary operation, which can be a range predicate:
+/// `x BETWEEN low AND high` desugars into `(x >= low) AND (x <= high)`, both bounds included.
+/// Each comparison gets the position of its bound, so a type error points at the faulty bound.
+fn parse_operand(state: &mut ParserState<'_>) -> crate::Result<Expr> {
+    let expr = parse_expr_single(state)?;
+    state.skip_whitespace()?;
+
+    if let Some(Sym::Keyword(Keyword::Between)) = state.look_ahead()? {
+        state.shift()?;
+        state.skip_whitespace()?;
+        let low = parse_expr_single(state)?;
+        state.skip_whitespace()?;
+        state.expect(Sym::Operation(Operation::And))?;
+        state.skip_whitespace()?;
+        let high = parse_expr_single(state)?;
+
+        return Ok(Expr {
+            attrs: expr.attrs,
+            value: Value::Binary {
+                lhs: Box::new(Expr {
+                    attrs: low.attrs,
+                    value: Value::Binary {
+                        lhs: Box::new(expr.clone()),
+                        op: Operation::GreaterThanOrEqual,
+                        rhs: Box::new(low),
+                    },
+                }),
+                op: Operation::And,
+                rhs: Box::new(Expr {
+                    attrs: high.attrs,
+                    value: Value::Binary {
+                        lhs: Box::new(expr),
+                        op: Operation::LessThanOrEqual,
+                        rhs: Box::new(high),
+                    },
+                }),
+            },
+        });
+    }
+
+    Ok(expr)
+}
+
 // TODO - move the parsing from the stack to the heap so we could never have stack overflow
 // errors.
 fn parse_expr_single(state: &mut ParserState<'_>) -> crate::Result<Expr> {
@@ -325,31 +454,12 @@ fn parse_expr_single(state: &mut ParserState<'_>) -> crate::Result<Expr> {
 
         Sym::Id(id) => {
             if let Some(Sym::LParens) = state.look_ahead()? {
-                state.shift()?;
-                state.skip_whitespace()?;
-
-                let mut params = Vec::new();
-
-                if let Some(sym) = state.look_ahead()?
-                    && sym != &Sym::RParens
-                {
-                    params.push(parse_expr_single(state)?);
-                    state.skip_whitespace()?;
-
-                    while let Some(Sym::Comma) = state.look_ahead()? {
-                        state.shift()?;
-                        state.skip_whitespace()?;
-                        params.push(parse_expr_single(state)?);
-                        state.skip_whitespace()?;
-                    }
-                }
-
-                state.skip_whitespace()?;
-                state.expect(Sym::RParens)?;
-
                 return Ok(Expr {
                     attrs: NodeAttributes::new(pos),
-                    value: Value::App { fun: id, params },
+                    value: Value::App {
+                        fun: id,
+                        params: parse_app_params(state)?,
+                    },
                 });
             }
 
@@ -440,6 +550,17 @@ fn parse_expr_single(state: &mut ParserState<'_>) -> crate::Result<Expr> {
             })
         }
 
+        // `contains` is also the name of a string function.
+        Sym::Operation(Operation::Contains) if state.look_ahead()? == Some(&Sym::LParens) => {
+            Ok(Expr {
+                attrs: NodeAttributes::new(pos),
+                value: Value::App {
+                    fun: "contains".to_string(),
+                    params: parse_app_params(state)?,
+                },
+            })
+        }
+
         Sym::Operation(op) => {
             state.skip_whitespace()?;
             let expr = parse_expr_single(state)?;
@@ -456,3 +577,29 @@ fn parse_expr_single(state: &mut ParserState<'_>) -> crate::Result<Expr> {
         x => bail!(state.pos(), ParserError::ExpectedExpr(x)),
     }
 }
+
+fn parse_app_params(state: &mut ParserState<'_>) -> crate::Result<Vec<Expr>> {
+    state.expect(Sym::LParens)?;
+    state.skip_whitespace()?;
+
+    let mut params = Vec::new();
+
+    if let Some(sym) = state.look_ahead()?
+        && sym != &Sym::RParens
+    {
+        params.push(parse_expr_single(state)?);
+        state.skip_whitespace()?;
+
+        while let Some(Sym::Comma) = state.look_ahead()? {
+            state.shift()?;
+            state.skip_whitespace()?;
+            params.push(parse_expr_single(state)?);
+            state.skip_whitespace()?;
+        }
+    }
+
+    state.skip_whitespace()?;
+    state.expect(Sym::RParens)?;
+
+    Ok(params)
+}
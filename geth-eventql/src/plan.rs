@@ -0,0 +1,213 @@
+use std::fmt::Display;
+
+use crate::{Expr, Literal, Operation, Query, RevisionRange, SourceType, Subject, Value, Var};
+
+/// How the sources of a query are read, along with what the `WHERE` clause leaves to evaluate on
+/// every row.
+pub struct ReadPlan<'a> {
+    pub sources: Vec<SourceRead<'a>>,
+    pub predicates: Vec<Predicate<'a>>,
+}
+
+impl ReadPlan<'_> {
+    /// Predicates that didn't make it into a source read and are evaluated on every row.
+    pub fn residual(&self) -> impl Iterator<Item = &Expr> {
+        self.predicates.iter().filter(|p| !p.pushed).map(|p| p.expr)
+    }
+}
+
+pub struct SourceRead<'a> {
+    pub ident: &'a str,
+    pub target: ReadTarget<'a>,
+    pub range: Option<RevisionRange>,
+    /// Only events of that class are read.
+    pub class: Option<&'a str>,
+}
+
+pub enum ReadTarget<'a> {
+    /// Every event of the database is read.
+    Scan,
+    Subject(&'a Subject),
+    Subquery(Box<ReadPlan<'a>>),
+}
+
+impl ReadTarget<'_> {
+    pub fn as_subject(&self) -> Option<&Subject> {
+        if let Self::Subject(sub) = self {
+            return Some(sub);
+        }
+
+        None
+    }
+}
+
+/// A conjunct of the `WHERE` clause.
+pub struct Predicate<'a> {
+    pub expr: &'a Expr,
+    /// The source read only returns events the predicate holds for.
+    pub pushed: bool,
+}
+
+/// Works out how to read the sources of a renamed query. Conjuncts of the `WHERE` clause comparing
+/// the subject or the type of a source to a literal are pushed into its read, so a query like
+/// `FROM e IN events WHERE e.subject == "/foo"` only reads the `/foo` subject instead of every
+/// event.
+///
+/// Pushed predicates are still part of the query, evaluating them again on every row is harmless.
+pub fn plan(query: &Query) -> ReadPlan<'_> {
+    let mut sources = query
+        .from_stmts
+        .iter()
+        .map(|from| SourceRead {
+            ident: from.ident.as_str(),
+            target: match &from.source.inner {
+                SourceType::Events => ReadTarget::Scan,
+                SourceType::Subject(sub) => ReadTarget::Subject(sub),
+                SourceType::Subquery(query) => ReadTarget::Subquery(Box::new(plan(query))),
+            },
+            range: from.source.range,
+            class: None,
+        })
+        .collect::<Vec<_>>();
+
+    let mut conjuncts = Vec::new();
+    if let Some(predicate) = query.predicate.as_ref() {
+        collect_conjuncts(&predicate.expr, &mut conjuncts);
+    }
+
+    let predicates = conjuncts
+        .into_iter()
+        .map(|expr| Predicate {
+            expr,
+            pushed: push(&mut sources, expr),
+        })
+        .collect();
+
+    ReadPlan {
+        sources,
+        predicates,
+    }
+}
+
+fn collect_conjuncts<'a>(expr: &'a Expr, conjuncts: &mut Vec<&'a Expr>) {
+    let mut stack = vec![expr];
+
+    while let Some(expr) = stack.pop() {
+        if let Value::Binary {
+            lhs,
+            op: Operation::And,
+            rhs,
+        } = &expr.value
+        {
+            stack.push(rhs);
+            stack.push(lhs);
+            continue;
+        }
+
+        conjuncts.push(expr);
+    }
+}
+
+fn push<'a>(sources: &mut [SourceRead<'a>], expr: &'a Expr) -> bool {
+    let Some((var, lit)) = var_equals_literal(expr) else {
+        return false;
+    };
+
+    let Some(source) = sources.iter_mut().find(|s| s.ident == var.name) else {
+        return false;
+    };
+
+    // Only the first equality is pushed, the others are left to the per-row evaluation.
+    match (var.path.as_slice(), lit) {
+        ([prop], Literal::Subject(sub)) if prop == "subject" => {
+            if let ReadTarget::Scan = source.target {
+                source.target = ReadTarget::Subject(sub);
+                return true;
+            }
+
+            false
+        }
+
+        ([prop], Literal::String(class)) if prop == "type" => {
+            if source.class.is_some() || matches!(source.target, ReadTarget::Subquery(_)) {
+                return false;
+            }
+
+            source.class = Some(class.as_str());
+            true
+        }
+
+        _ => false,
+    }
+}
+
+fn var_equals_literal(expr: &Expr) -> Option<(&Var, &Literal)> {
+    let Value::Binary {
+        lhs,
+        op: Operation::Equal,
+        rhs,
+    } = &expr.value
+    else {
+        return None;
+    };
+
+    match (&lhs.value, &rhs.value) {
+        (Value::Var(var), Value::Literal(lit)) | (Value::Literal(lit), Value::Var(var)) => {
+            Some((var, lit))
+        }
+
+        _ => None,
+    }
+}
+
+impl Display for ReadPlan<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.explain(f, 0)
+    }
+}
+
+impl ReadPlan<'_> {
+    fn explain(&self, f: &mut std::fmt::Formatter<'_>, depth: usize) -> std::fmt::Result {
+        let indent = "  ".repeat(depth);
+
+        for source in &self.sources {
+            write!(f, "{indent}FROM {} ", source.ident)?;
+
+            match &source.target {
+                ReadTarget::Scan => write!(f, "SCAN events")?,
+                ReadTarget::Subject(sub) => write!(f, "READ {sub}")?,
+                ReadTarget::Subquery(_) => write!(f, "SUBQUERY")?,
+            }
+
+            if let Some(range) = source.range {
+                write!(f, " FROM REVISION {}", range.start)?;
+
+                if let Some(end) = range.end {
+                    write!(f, " TO {end}")?;
+                }
+            }
+
+            if let Some(class) = source.class {
+                write!(f, " CLASS \"{class}\"")?;
+            }
+
+            writeln!(f)?;
+
+            if let ReadTarget::Subquery(plan) = &source.target {
+                plan.explain(f, depth + 1)?;
+            }
+        }
+
+        for predicate in &self.predicates {
+            let how = if predicate.pushed {
+                "pushed"
+            } else {
+                "per row"
+            };
+
+            writeln!(f, "{indent}WHERE {} ({how})", predicate.expr)?;
+        }
+
+        Ok(())
+    }
+}
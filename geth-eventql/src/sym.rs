@@ -20,6 +20,7 @@ pub enum Keyword {
     Having,
     As,
     If,
+    Between,
 }
 
 impl Display for Keyword {
@@ -41,6 +42,7 @@ impl Display for Keyword {
             Keyword::Having => write!(f, "HAVING"),
             Keyword::As => write!(f, "AS"),
             Keyword::If => write!(f, "IF"),
+            Keyword::Between => write!(f, "BETWEEN"),
         }
     }
 }
@@ -78,6 +80,41 @@ impl Display for Operation {
     }
 }
 
+/// Functions computed over all the rows of a group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregate {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl Aggregate {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "count" => Some(Self::Count),
+            "sum" => Some(Self::Sum),
+            "avg" => Some(Self::Avg),
+            "min" => Some(Self::Min),
+            "max" => Some(Self::Max),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Aggregate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Count => write!(f, "count"),
+            Self::Sum => write!(f, "sum"),
+            Self::Avg => write!(f, "avg"),
+            Self::Min => write!(f, "min"),
+            Self::Max => write!(f, "max"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum Literal {
     String(String),
@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+
+use crate::{Accumulators, Aggregate, Dictionary, Entry, EvalError, Instr, Literal, Program};
+
+fn program() -> crate::Result<Program> {
+    let query = include_str!("./resources/infer_aggregates.eql");
+    let infered = crate::parse_rename_and_infer(query)?;
+
+    Ok(crate::codegen(infered.query()))
+}
+
+fn row(tpe: &str, price: Literal) -> Dictionary {
+    let mut inner = HashMap::new();
+
+    inner.insert("e.type".to_string(), Literal::String(tpe.to_string()));
+    inner.insert("e.data.price".to_string(), price);
+
+    Dictionary { inner }
+}
+
+fn group(program: &Program, rows: &[Dictionary]) -> HashMap<String, Entry> {
+    let mut accs = Accumulators::new(&program.aggregates);
+
+    for row in rows {
+        crate::accumulate(row, &program.row, &mut accs).expect("to accumulate");
+    }
+
+    let dict = row("book-added", Literal::Integral(0));
+    match crate::eval_group(&dict, &program.group, &accs) {
+        Ok(Some(Entry::Record(rec))) => rec.fields,
+        _ => panic!("expected a record"),
+    }
+}
+
+fn as_integral(entry: &Entry) -> i64 {
+    match entry {
+        Entry::Literal(Literal::Integral(n)) => *n,
+        _ => panic!("expected an integer"),
+    }
+}
+
+fn as_float(entry: &Entry) -> f64 {
+    match entry {
+        Entry::Literal(Literal::Float(f)) => *f,
+        _ => panic!("expected a float"),
+    }
+}
+
+#[test]
+fn test_codegen_accumulates_aggregates() -> crate::Result<()> {
+    let program = program()?;
+
+    assert_eq!(
+        vec![
+            Aggregate::Count,
+            Aggregate::Sum,
+            Aggregate::Avg,
+            Aggregate::Min,
+            Aggregate::Max
+        ],
+        program.aggregates
+    );
+
+    assert!(!program.row.is_empty());
+    assert!(!program.group.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_eval_aggregates_over_group() -> crate::Result<()> {
+    let program = program()?;
+    let rows = [
+        row("book-added", Literal::Integral(10)),
+        row("book-added", Literal::Integral(4)),
+        row("book-added", Literal::Integral(7)),
+    ];
+
+    let fields = group(&program, &rows);
+
+    assert_eq!(3, as_integral(&fields["count"]));
+    assert_eq!(21, as_integral(&fields["total"]));
+    assert_eq!(7.0, as_float(&fields["average"]));
+    assert_eq!(4, as_integral(&fields["lowest"]));
+    assert_eq!(10, as_integral(&fields["highest"]));
+
+    Ok(())
+}
+
+#[test]
+fn test_eval_sum_mixing_integers_and_floats() -> crate::Result<()> {
+    let program = program()?;
+    let rows = [
+        row("book-added", Literal::Integral(1)),
+        row("book-added", Literal::Float(0.5)),
+    ];
+
+    let fields = group(&program, &rows);
+
+    assert_eq!(1.5, as_float(&fields["total"]));
+    assert_eq!(0.75, as_float(&fields["average"]));
+    assert_eq!(0.5, as_float(&fields["lowest"]));
+    assert_eq!(1, as_integral(&fields["highest"]));
+
+    Ok(())
+}
+
+#[test]
+fn test_eval_aggregates_over_empty_group() {
+    let dict = row("book-added", Literal::Integral(0));
+    let load = [Instr::LoadAggregate(0)];
+
+    for aggregate in [Aggregate::Count, Aggregate::Sum] {
+        let accs = Accumulators::new(&[aggregate]);
+        let value = crate::eval_group(&dict, &load, &accs).expect("to evaluate");
+
+        assert_eq!(0, as_integral(&value.expect("a value")));
+    }
+
+    for aggregate in [Aggregate::Avg, Aggregate::Min, Aggregate::Max] {
+        let accs = Accumulators::new(&[aggregate]);
+        let e = crate::eval_group(&dict, &load, &accs)
+            .err()
+            .expect("to return an error");
+
+        assert!(matches!(e, EvalError::EmptyGroup(x) if x == aggregate));
+    }
+}
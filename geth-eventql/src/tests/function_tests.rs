@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+
+use crate::{Dictionary, Entry, Literal, Pos, Subject, Type, error::InferError};
+
+fn payload() -> Dictionary {
+    let mut inner = HashMap::new();
+
+    inner.insert(
+        "e.type".to_string(),
+        Literal::String("io.example.book-added".to_string()),
+    );
+    inner.insert(
+        "e.data.title".to_string(),
+        Literal::String("GethDB Internals".to_string()),
+    );
+
+    Dictionary { inner }
+}
+
+#[test]
+fn test_codegen_string_functions() -> crate::Result<()> {
+    let query = include_str!("./resources/codegen_string_functions.eql");
+    let infered = crate::parse_rename_and_infer(query)?;
+    let program = crate::codegen(infered.query());
+
+    let Ok(Some(Entry::Record(rec))) = crate::eval(&payload(), program.row) else {
+        panic!("expected a record");
+    };
+
+    let field = |label: &str| match &rec.fields[label] {
+        Entry::Literal(lit) => lit.clone(),
+        _ => panic!("expected a literal"),
+    };
+
+    assert_eq!(Literal::Bool(true), field("starts"));
+    assert_eq!(Literal::Bool(false), field("missing"));
+    assert_eq!(
+        Literal::String("gethdb internals".to_string()),
+        field("lowered")
+    );
+    assert_eq!(
+        Literal::String("IO.EXAMPLE.BOOK-ADDED".to_string()),
+        field("uppered")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_infer_string_functions() -> crate::Result<()> {
+    let query = include_str!("./resources/codegen_string_functions.eql");
+    let infered = crate::parse_rename_and_infer(query)?;
+    let projection = infered.query().projection.as_record().expect("a record");
+
+    assert_eq!(Type::Bool, projection.get("starts").unwrap().attrs.tpe);
+    assert_eq!(Type::Bool, projection.get("missing").unwrap().attrs.tpe);
+    assert_eq!(Type::String, projection.get("lowered").unwrap().attrs.tpe);
+
+    let app = projection.get("starts").unwrap().as_apply_fun().unwrap();
+    assert_eq!(Type::String, app.params[0].attrs.tpe);
+
+    Ok(())
+}
+
+#[test]
+fn test_infer_lower_not_string() -> crate::Result<()> {
+    let query = include_str!("./resources/infer_lower_not_string.eql");
+    let mut query = crate::parse(query)?;
+    let scopes = crate::rename(&mut query)?;
+
+    let e = crate::infer(scopes, query)
+        .err()
+        .expect("to return an error");
+
+    assert_eq!(
+        e.kind,
+        InferError::TypeMismatch(Type::String, Type::Integer)
+    );
+    assert_eq!(Pos::new(2, 28), e.pos);
+
+    Ok(())
+}
+
+#[test]
+fn test_subject_match_single_segment_wildcard() {
+    let pattern = Subject::pattern("orders/*/created").unwrap();
+
+    assert!(pattern.matches(&["orders", "42", "created"]));
+    assert!(pattern.matches(&["orders", "43", "created"]));
+    assert!(!pattern.matches(&["orders", "42", "shipped"]));
+    assert!(!pattern.matches(&["orders", "created"]));
+    assert!(!pattern.matches(&["orders", "42", "created", "v2"]));
+}
+
+#[test]
+fn test_subject_match_tail_wildcard() {
+    let pattern = Subject::pattern("/orders/>").unwrap();
+
+    assert!(pattern.matches(&["orders", "42"]));
+    assert!(pattern.matches(&["orders", "42", "created"]));
+    assert!(!pattern.matches(&["orders"]));
+    assert!(!pattern.matches(&["invoices", "42"]));
+
+    assert!(Subject::pattern("orders/>/created").is_none());
+    assert!(Subject::pattern("orders//created").is_none());
+}
+
+#[test]
+fn test_codegen_subject_match() -> crate::Result<()> {
+    let query = include_str!("./resources/codegen_subject_match.eql");
+    let infered = crate::parse_rename_and_infer(query)?;
+    let program = crate::codegen(infered.query());
+
+    let mut inner = HashMap::new();
+    inner.insert(
+        "e.subject".to_string(),
+        Literal::Subject(crate::parser::parse_subject(
+            Pos::new(0, 0),
+            "/orders/42/created",
+        )?),
+    );
+
+    let Ok(Some(Entry::Record(rec))) = crate::eval(&Dictionary { inner }, program.row) else {
+        panic!("expected a record");
+    };
+
+    let field = |label: &str| match &rec.fields[label] {
+        Entry::Literal(lit) => lit.clone(),
+        _ => panic!("expected a literal"),
+    };
+
+    assert_eq!(Literal::Bool(true), field("single"));
+    assert_eq!(Literal::Bool(true), field("tail"));
+    assert_eq!(Literal::Bool(false), field("other"));
+
+    Ok(())
+}
+
+#[test]
+fn test_infer_subject_match_invalid_pattern() -> crate::Result<()> {
+    let query = include_str!("./resources/infer_subject_match_invalid_pattern.eql");
+    let mut query = crate::parse(query)?;
+    let scopes = crate::rename(&mut query)?;
+
+    let e = crate::infer(scopes, query)
+        .err()
+        .expect("to return an error");
+
+    assert_eq!(
+        e.kind,
+        InferError::InvalidSubjectPattern("orders/>/created".to_string())
+    );
+    assert_eq!(Pos::new(2, 32), e.pos);
+
+    Ok(())
+}
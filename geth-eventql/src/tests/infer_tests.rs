@@ -1,4 +1,4 @@
-use crate::{Type, error::InferError};
+use crate::{Aggregate, Pos, Type, Var, error::InferError};
 
 #[test]
 fn test_infer_wrong_where_clause_1() -> crate::Result<()> {
@@ -32,3 +32,173 @@ fn test_infer_wrong_where_clause_2() -> crate::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_infer_having_on_group_keys_and_aggregates() -> crate::Result<()> {
+    let query = include_str!("./resources/parser_group_by_having.eql");
+    let query = crate::parse_rename_and_infer(query)?;
+    let having = query.query().having.as_ref().expect("a having clause");
+
+    assert_eq!(Type::Bool, having.attrs.tpe);
+
+    let bin_op = having.as_binary_op().expect("a binary op");
+    assert_eq!(Type::Integer, bin_op.lhs.attrs.tpe);
+
+    Ok(())
+}
+
+#[test]
+fn test_infer_having_on_row_field() -> crate::Result<()> {
+    let query = include_str!("./resources/infer_having_row_field.eql");
+    let mut query = crate::parse(query)?;
+    let scopes = crate::rename(&mut query)?;
+
+    let e = crate::infer(scopes, query)
+        .err()
+        .expect("to return an error");
+
+    assert_eq!(
+        e.kind,
+        InferError::NotGroupKeyOrAggregate(Var {
+            name: "e".to_string(),
+            path: vec!["source".to_string()],
+        })
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_infer_between() -> crate::Result<()> {
+    let query = "FROM e IN events WHERE e.data.price BETWEEN 10 AND 20 PROJECT INTO e";
+    let query = crate::parse_rename_and_infer(query)?;
+    let pred = query.query().predicate.as_ref().expect("a predicate");
+
+    assert_eq!(Type::Bool, pred.expr.attrs.tpe);
+
+    let between = pred.expr.as_binary_op().expect("a binary op");
+    for bound in [between.lhs, between.rhs] {
+        assert_eq!(Type::Bool, bound.attrs.tpe);
+
+        let bound = bound.as_binary_op().expect("a binary op");
+        assert_eq!(Type::Integer, bound.lhs.attrs.tpe);
+        assert_eq!(Type::Integer, bound.rhs.attrs.tpe);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_infer_between_wrong_bound() -> crate::Result<()> {
+    let query = include_str!("./resources/infer_between_wrong_bound.eql");
+    let mut query = crate::parse(query)?;
+    let scopes = crate::rename(&mut query)?;
+
+    let e = crate::infer(scopes, query)
+        .err()
+        .expect("to return an error");
+
+    assert_eq!(
+        e.kind,
+        InferError::TypeMismatch(Type::Integer, Type::String)
+    );
+    assert_eq!(Pos::new(2, 35), e.pos);
+
+    Ok(())
+}
+
+#[test]
+fn test_infer_between_not_comparable() -> crate::Result<()> {
+    let query = include_str!("./resources/infer_between_not_comparable.eql");
+    let mut query = crate::parse(query)?;
+    let scopes = crate::rename(&mut query)?;
+
+    let e = crate::infer(scopes, query)
+        .err()
+        .expect("to return an error");
+
+    assert_eq!(e.kind, InferError::NotComparable(Type::Array));
+    assert_eq!(Pos::new(2, 27), e.pos);
+
+    Ok(())
+}
+
+#[test]
+fn test_infer_aggregates() -> crate::Result<()> {
+    let query = include_str!("./resources/infer_aggregates.eql");
+    let query = crate::parse_rename_and_infer(query)?;
+    let projection = query.query().projection.as_record().expect("a record");
+
+    assert_eq!(Type::Integer, projection.get("count").unwrap().attrs.tpe);
+    assert_eq!(Type::Float, projection.get("average").unwrap().attrs.tpe);
+
+    Ok(())
+}
+
+#[test]
+fn test_infer_aggregate_without_group_by() -> crate::Result<()> {
+    let query = include_str!("./resources/from_events_where_subject_project_record_with_count.eql");
+    let mut query = crate::parse(query)?;
+    let scopes = crate::rename(&mut query)?;
+
+    let e = crate::infer(scopes, query)
+        .err()
+        .expect("to return an error");
+
+    assert_eq!(
+        e.kind,
+        InferError::AggregateWithoutGroupBy(Aggregate::Count)
+    );
+    assert_eq!(Pos::new(3, 23), e.pos);
+
+    Ok(())
+}
+
+#[test]
+fn test_infer_aggregate_in_where_clause() -> crate::Result<()> {
+    let query = include_str!("./resources/infer_aggregate_in_where.eql");
+    let mut query = crate::parse(query)?;
+    let scopes = crate::rename(&mut query)?;
+
+    let e = crate::infer(scopes, query)
+        .err()
+        .expect("to return an error");
+
+    assert_eq!(
+        e.kind,
+        InferError::AggregateWithoutGroupBy(Aggregate::Count)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_infer_sum_not_numeric() -> crate::Result<()> {
+    let query = include_str!("./resources/infer_sum_not_numeric.eql");
+    let mut query = crate::parse(query)?;
+    let scopes = crate::rename(&mut query)?;
+
+    let e = crate::infer(scopes, query)
+        .err()
+        .expect("to return an error");
+
+    assert_eq!(e.kind, InferError::NotNumeric(Aggregate::Sum, Type::String));
+    assert_eq!(Pos::new(3, 27), e.pos);
+
+    Ok(())
+}
+
+#[test]
+fn test_infer_nested_aggregate() -> crate::Result<()> {
+    let query = include_str!("./resources/infer_nested_aggregate.eql");
+    let mut query = crate::parse(query)?;
+    let scopes = crate::rename(&mut query)?;
+
+    let e = crate::infer(scopes, query)
+        .err()
+        .expect("to return an error");
+
+    assert_eq!(e.kind, InferError::NestedAggregate(Aggregate::Count));
+
+    Ok(())
+}
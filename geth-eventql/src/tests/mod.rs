@@ -1,3 +1,6 @@
+mod aggregate_tests;
+mod function_tests;
 mod infer_tests;
 mod parser_tests;
+mod plan_tests;
 mod rename_tests;
@@ -1,4 +1,8 @@
-use crate::{Limit, LimitKind, Order, sym::Operation};
+use crate::{
+    Limit, LimitKind, Order, Pos, RevisionRange,
+    error::ParserError,
+    sym::{Operation, Sym},
+};
 
 #[test]
 fn test_parsing_from_events_with_top_identity_projection() -> crate::Result<()> {
@@ -287,3 +291,130 @@ fn test_parser_inhinged_unary_op() -> crate::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_parser_group_by_having() -> crate::Result<()> {
+    let query = include_str!("./resources/parser_group_by_having.eql");
+
+    let query = crate::parse(query)?;
+
+    let group_by = query.group_by.as_ref().and_then(|x| x.as_var());
+    assert_eq!(Some("e.type".to_string()), group_by.map(|x| x.to_string()));
+
+    let having = query
+        .having
+        .as_ref()
+        .and_then(|x| x.as_binary_op())
+        .expect("a binary op");
+
+    assert_eq!(Operation::GreaterThan, having.op);
+    assert_eq!(
+        "COUNT",
+        having.lhs.as_apply_fun().expect("a function call").name
+    );
+    assert_eq!(100, having.rhs.as_i64_literal().expect("an integer"));
+
+    Ok(())
+}
+
+#[test]
+fn test_parser_having_without_group_by() {
+    let query = include_str!("./resources/parser_having_without_group_by.eql");
+
+    let e = crate::parse(query).err().expect("to return an error");
+
+    assert_eq!(e.kind, ParserError::HavingWithoutGroupBy);
+}
+
+#[test]
+fn test_parser_source_revision_range() -> crate::Result<()> {
+    let query = include_str!("./resources/parser_source_revision_range.eql");
+
+    let query = crate::parse(query)?;
+
+    assert_eq!(2, query.from_stmts.len());
+    assert_eq!(
+        Some(RevisionRange {
+            start: 1_000,
+            end: Some(2_000),
+        }),
+        query.from_stmts[0].source.range
+    );
+    assert_eq!(
+        Some(RevisionRange {
+            start: 5,
+            end: None,
+        }),
+        query.from_stmts[1].source.range
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_parser_source_revision_range_ends_before_start() {
+    let query = include_str!("./resources/parser_source_revision_range_ends_before_start.eql");
+
+    let e = crate::parse(query).err().expect("to return an error");
+
+    assert_eq!(e.kind, ParserError::RevisionRangeEndsBeforeStart(20, 10));
+}
+
+#[test]
+fn test_parser_source_revision_range_on_subquery() {
+    let query = include_str!("./resources/parser_source_revision_range_on_subquery.eql");
+
+    let e = crate::parse(query).err().expect("to return an error");
+
+    assert_eq!(e.kind, ParserError::RevisionRangeOnSubquery);
+}
+
+#[test]
+fn test_parser_between() -> crate::Result<()> {
+    let query = include_str!("./resources/parser_between.eql");
+
+    let query = crate::parse(query)?;
+    let pred = query.predicate.as_ref().expect("a predicate");
+    let bin_op = pred.expr.as_binary_op().expect("a binary op");
+
+    assert_eq!(Operation::And, bin_op.op);
+    assert_eq!(
+        Operation::Equal,
+        bin_op.lhs.as_binary_op().expect("a binary op").op
+    );
+
+    let between = bin_op.rhs.as_binary_op().expect("a binary op");
+    assert_eq!(Operation::And, between.op);
+
+    let low = between.lhs.as_binary_op().expect("a binary op");
+    assert_eq!(Operation::GreaterThanOrEqual, low.op);
+    assert_eq!("e.data.price", low.lhs.as_var().expect("a var").to_string());
+    assert_eq!(10, low.rhs.as_i64_literal().expect("an integer"));
+    assert_eq!(Pos::new(2, 55), between.lhs.attrs.pos);
+
+    let high = between.rhs.as_binary_op().expect("a binary op");
+    assert_eq!(Operation::LessThanOrEqual, high.op);
+    assert_eq!(
+        "e.data.price",
+        high.lhs.as_var().expect("a var").to_string()
+    );
+    assert_eq!(20, high.rhs.as_i64_literal().expect("an integer"));
+    assert_eq!(Pos::new(2, 62), between.rhs.attrs.pos);
+
+    Ok(())
+}
+
+#[test]
+fn test_parser_between_without_and() {
+    let query = "FROM e IN events WHERE e.data.price BETWEEN 10 OR 20 PROJECT INTO e";
+
+    let e = crate::parse(query).err().expect("to return an error");
+
+    assert_eq!(
+        e.kind,
+        ParserError::UnexpectedSymbol(
+            Sym::Operation(Operation::And),
+            Sym::Operation(Operation::Or)
+        )
+    );
+}
@@ -0,0 +1,44 @@
+use crate::Subject;
+
+#[test]
+fn test_plan_subject_equality_becomes_targeted_read() -> crate::Result<()> {
+    let query = include_str!("./resources/plan_subject_equality.eql");
+    let infered = crate::parse_rename_and_infer(query)?;
+    let plan = crate::plan(infered.query());
+
+    assert_eq!(1, plan.sources.len());
+
+    let source = &plan.sources[0];
+    let expected = Subject {
+        inner: vec!["books".to_string(), "42".to_string()],
+    };
+
+    assert_eq!(Some(&expected), source.target.as_subject());
+    assert_eq!(Some("io.example.book-added"), source.class);
+
+    let pushed = plan.predicates.iter().map(|p| p.pushed).collect::<Vec<_>>();
+
+    assert_eq!(vec![true, true, false], pushed);
+
+    let residual = plan.residual().map(|e| e.to_string()).collect::<Vec<_>>();
+    assert_eq!(vec!["(e.data.price > 10)"], residual);
+
+    let explain = plan.to_string();
+    assert!(explain.contains("FROM e READ /books/42/"));
+    assert!(explain.contains("WHERE (e.data.price > 10) (per row)"));
+
+    Ok(())
+}
+
+#[test]
+fn test_plan_disjunction_is_not_pushed() -> crate::Result<()> {
+    let query = include_str!("./resources/plan_disjunction.eql");
+    let infered = crate::parse_rename_and_infer(query)?;
+    let plan = crate::plan(infered.query());
+
+    assert!(plan.sources[0].target.as_subject().is_none());
+    assert!(plan.predicates.iter().all(|p| !p.pushed));
+    assert!(plan.to_string().contains("FROM e SCAN events"));
+
+    Ok(())
+}
@@ -140,6 +140,7 @@ impl<'a> Lexer<'a> {
                         "having" => Ok(Some(Sym::Keyword(Keyword::Having))),
                         "as" => Ok(Some(Sym::Keyword(Keyword::As))),
                         "if" => Ok(Some(Sym::Keyword(Keyword::If))),
+                        "between" => Ok(Some(Sym::Keyword(Keyword::Between))),
                         "contains" => Ok(Some(Sym::Operation(Operation::Contains))),
                         "and" => Ok(Some(Sym::Operation(Operation::And))),
                         "or" => Ok(Some(Sym::Operation(Operation::Or))),
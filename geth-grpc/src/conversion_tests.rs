@@ -0,0 +1,310 @@
+use chrono::Utc;
+use geth_common::{
+    AppendStream, CopyStream, DeleteStream, Direction, Epochs, ExpectedRevision, MapperErrorPolicy,
+    Propose, ReadAll, ReadStream, Revision, SetStreamMetadata, SetStreamMetadataCompleted,
+    Subscribe, SubscribeToStream, SubscriptionEvent, WriteResult,
+};
+use std::time::Duration;
+use tonic::Code;
+
+use crate::protocol;
+
+fn propose() -> protocol::append_stream_request::Propose {
+    protocol::append_stream_request::Propose {
+        id: Some(uuid::Uuid::new_v4().into()),
+        content_type: protocol::ContentType::Json as i32,
+        class: "foo".to_string(),
+        payload: Default::default(),
+        metadata: Default::default(),
+    }
+}
+
+#[test]
+fn test_append_without_expected_revision_is_rejected() {
+    let request = protocol::AppendStreamRequest {
+        stream_name: "foo".to_string(),
+        events: vec![propose()],
+        expected_revision: None,
+        notify_subscribers: None,
+    };
+
+    let status = AppendStream::try_from(request).unwrap_err();
+
+    assert_eq!(Code::InvalidArgument, status.code());
+}
+
+#[test]
+fn test_propose_without_id_is_rejected() {
+    let request = protocol::append_stream_request::Propose {
+        id: None,
+        ..propose()
+    };
+
+    let status = Propose::try_from(request).unwrap_err();
+
+    assert_eq!(Code::InvalidArgument, status.code());
+}
+
+#[test]
+fn test_propose_with_unknown_content_type_is_accepted() {
+    let request = protocol::append_stream_request::Propose {
+        content_type: 42,
+        ..propose()
+    };
+
+    assert!(Propose::try_from(request).is_ok());
+}
+
+#[test]
+fn test_delete_without_expected_revision_is_rejected() {
+    let request = protocol::DeleteStreamRequest {
+        stream_name: "foo".to_string(),
+        expected_revision: None,
+        dry_run: false,
+    };
+
+    let status = DeleteStream::try_from(request).unwrap_err();
+
+    assert_eq!(Code::InvalidArgument, status.code());
+}
+
+#[test]
+fn test_copy_without_expected_revision_is_rejected() {
+    let request = protocol::CopyStreamRequest {
+        source_stream: "foo".to_string(),
+        target_stream: "bar".to_string(),
+        expected_revision: None,
+        class_renames: Default::default(),
+        mapper: None,
+        skip_mapper_errors: false,
+    };
+
+    let status = CopyStream::try_from(request).unwrap_err();
+
+    assert_eq!(Code::InvalidArgument, status.code());
+}
+
+#[test]
+fn test_copy_mapper_settings_roundtrip() {
+    let params = CopyStream {
+        source_stream: "foo".to_string(),
+        target_stream: "bar".to_string(),
+        expected_revision: ExpectedRevision::Any,
+        class_renames: Default::default(),
+        mapper: Some("upcast".to_string()),
+        on_mapper_error: MapperErrorPolicy::Skip,
+    };
+
+    let actual = CopyStream::try_from(protocol::CopyStreamRequest::from(params)).unwrap();
+
+    assert_eq!(Some("upcast".to_string()), actual.mapper);
+    assert_eq!(MapperErrorPolicy::Skip, actual.on_mapper_error);
+}
+
+#[test]
+fn test_read_epochs_roundtrip() {
+    for epochs in [Epochs::Current, Epochs::All] {
+        let params = ReadStream {
+            stream_name: "foo".to_string(),
+            direction: Direction::Forward,
+            revision: Revision::Start,
+            max_count: Some(10),
+            resolve_links: false,
+            epochs,
+        };
+
+        let actual = ReadStream::try_from(protocol::ReadStreamRequest::from(params)).unwrap();
+
+        assert_eq!(epochs, actual.epochs);
+    }
+}
+
+#[test]
+fn test_unbounded_read_roundtrip() {
+    for max_count in [None, Some(0), Some(10)] {
+        let params = ReadStream {
+            stream_name: "foo".to_string(),
+            direction: Direction::Forward,
+            revision: Revision::Start,
+            max_count,
+            resolve_links: false,
+            epochs: Epochs::Current,
+        };
+
+        let message = protocol::ReadStreamRequest::from(params);
+        let actual = ReadStream::try_from(message).unwrap();
+
+        assert_eq!(max_count, actual.max_count);
+    }
+
+    // Older clients ask for everything with the largest count.
+    let request = protocol::ReadStreamRequest {
+        stream_name: "foo".to_string(),
+        max_count: u64::MAX,
+        direction: Some(protocol::read_stream_request::Direction::Forwards(())),
+        start: Some(protocol::read_stream_request::Start::Beginning(())),
+        resolve_links: None,
+        all_epochs: false,
+    };
+
+    assert_eq!(None, ReadStream::try_from(request).unwrap().max_count);
+}
+
+#[test]
+fn test_read_all_roundtrip() {
+    for (position, max_count) in [
+        (Revision::Start, None),
+        (Revision::End, Some(10)),
+        (Revision::Revision(42), Some(0)),
+    ] {
+        let params = ReadAll {
+            position,
+            direction: Direction::Backward,
+            max_count,
+        };
+
+        let message = protocol::ReadAllRequest::from(params);
+        let actual = ReadAll::try_from(message).unwrap();
+
+        assert_eq!(position, actual.position);
+        assert_eq!(Direction::Backward, actual.direction);
+        assert_eq!(max_count, actual.max_count);
+    }
+}
+
+#[test]
+fn test_read_without_direction_or_start_is_rejected() {
+    let request = protocol::ReadStreamRequest {
+        stream_name: "foo".to_string(),
+        max_count: 10,
+        direction: None,
+        start: Some(protocol::read_stream_request::Start::Beginning(())),
+        resolve_links: None,
+        all_epochs: false,
+    };
+
+    assert_eq!(
+        Code::InvalidArgument,
+        ReadStream::try_from(request).unwrap_err().code()
+    );
+
+    let request = protocol::ReadStreamRequest {
+        stream_name: "foo".to_string(),
+        max_count: 10,
+        direction: Some(protocol::read_stream_request::Direction::Forwards(())),
+        start: None,
+        resolve_links: None,
+        all_epochs: false,
+    };
+
+    assert_eq!(
+        Code::InvalidArgument,
+        ReadStream::try_from(request).unwrap_err().code()
+    );
+}
+
+#[test]
+fn test_subscribe_without_target_or_start_is_rejected() {
+    let request = protocol::SubscribeRequest { to: None };
+
+    assert_eq!(
+        Code::InvalidArgument,
+        Subscribe::try_from(request).unwrap_err().code()
+    );
+
+    let request = protocol::SubscribeRequest {
+        to: Some(protocol::subscribe_request::To::Stream(
+            protocol::subscribe_request::Stream {
+                stream_name: "foo".to_string(),
+                start: None,
+                batching: None,
+                class_filter: vec![],
+                heartbeat_interval_ms: None,
+            },
+        )),
+    };
+
+    assert_eq!(
+        Code::InvalidArgument,
+        Subscribe::try_from(request).unwrap_err().code()
+    );
+}
+
+#[test]
+fn test_subscribe_heartbeat_interval_round_trip() {
+    let params = SubscribeToStream {
+        stream_name: "foo".to_string(),
+        start: Revision::Start,
+        batching: None,
+        class_filter: vec![],
+        heartbeat_interval: Some(Duration::from_millis(250)),
+    };
+
+    let mut message = protocol::subscribe_request::Stream::from(params);
+    assert_eq!(Some(250), message.heartbeat_interval_ms);
+    assert_eq!(
+        Some(Duration::from_millis(250)),
+        SubscribeToStream::try_from(message.clone())
+            .unwrap()
+            .heartbeat_interval
+    );
+
+    message.heartbeat_interval_ms = Some(0);
+    assert_eq!(
+        Code::InvalidArgument,
+        SubscribeToStream::try_from(message).unwrap_err().code()
+    );
+}
+
+#[test]
+fn test_heartbeat_is_not_a_subscription_event() {
+    let heartbeat = protocol::SubscribeResponse::heartbeat();
+
+    assert!(heartbeat.is_heartbeat());
+    assert!(SubscriptionEvent::try_from(heartbeat).is_err());
+}
+
+#[test]
+fn test_set_metadata_without_metadata_is_rejected() {
+    let request = protocol::SetStreamMetadataRequest {
+        stream_name: "foo".to_string(),
+        metadata: None,
+    };
+
+    let status = SetStreamMetadata::try_from(request).unwrap_err();
+
+    assert_eq!(Code::InvalidArgument, status.code());
+}
+
+#[test]
+fn test_no_stream_write_result_is_not_sent_as_a_revision() {
+    let result = WriteResult {
+        next_expected_version: ExpectedRevision::NoStream,
+        position: 42,
+        next_logical_position: 64,
+        event_ids: vec![],
+        created: Utc::now(),
+    };
+
+    let message = protocol::set_stream_metadata_response::WriteResult::from(result);
+
+    assert_eq!(
+        Some(
+            protocol::set_stream_metadata_response::write_result::NextExpectedVersion::NoStream(())
+        ),
+        message.next_expected_version
+    );
+
+    let response = protocol::SetStreamMetadataResponse {
+        result: Some(protocol::set_stream_metadata_response::Result::WriteResult(
+            message,
+        )),
+    };
+
+    let result = SetStreamMetadataCompleted::try_from(response)
+        .unwrap()
+        .success()
+        .unwrap();
+
+    assert_eq!(ExpectedRevision::NoStream, result.next_expected_version);
+}
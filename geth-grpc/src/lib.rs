@@ -1,22 +1,37 @@
 pub use crate::generated::protocol;
-use chrono::{TimeZone, Utc};
+use chrono::{DateTime, TimeZone, Utc};
 use geth_common::{
-    AppendError, AppendStream, AppendStreamCompleted, ContentType, DeleteError, DeleteStream,
-    DeleteStreamCompleted, Direction, EndPoint, ExpectedRevision, GetProgramError, GetProgramStats,
-    KillProgram, ListPrograms, ProgramKillError, ProgramKilled, ProgramListed, ProgramObtained,
-    ProgramStats, ProgramSummary, Propose, ReadError, ReadStream, ReadStreamResponse, Record,
-    Revision, Subscribe, SubscribeToProgram, SubscribeToStream, SubscriptionConfirmation,
-    SubscriptionEvent, SubscriptionNotification, UnsubscribeReason, WriteResult,
+    Ack, AppendError, AppendStream, AppendStreamCompleted, ContentType, CopyError, CopyStream,
+    CopyStreamCompleted, DeleteError, DeleteStream, DeleteStreamCompleted, Direction, EndPoint,
+    Epochs, ExpectedRevision, GetProgramError, GetProgramStats, IndexMaintained, InvalidEventError,
+    KillProgram, ListPrograms, ListStreams, LogFiltersSet, MaintainIndex, MapperErrorPolicy,
+    ProgramKillError, ProgramKilled, ProgramLimit, ProgramLimits, ProgramListed, ProgramObtained,
+    ProgramStats, ProgramSummary, ProjectedRecord, Propose, ReadAll, ReadError, ReadProjection,
+    ReadProjectionResponse, ReadStream, ReadStreamCompleted, ReadStreamResponse, Record,
+    RecordLink, RestartPolicy, Revision, SetLogFilters, SetStreamMetadata,
+    SetStreamMetadataCompleted, SetStreamMetadataError, StreamAcl, StreamCopied, StreamMetadata,
+    StreamPage, Subscribe, SubscribeToGroup, SubscribeToProgram, SubscribeToStream,
+    SubscriptionBatching, SubscriptionConfirmation, SubscriptionEvent, SubscriptionNotification,
+    TruncateError, TruncateStream, TruncateStreamCompleted, UnsubscribeReason, WriteResult,
     WrongExpectedRevisionError,
 };
+use std::time::Duration;
 use uuid::Uuid;
 
+#[cfg(test)]
+mod conversion_tests;
+#[cfg(test)]
+mod roundtrip_tests;
+
 pub mod generated {
     pub mod protocol {
         include!(concat!(env!("OUT_DIR"), "/geth.rs"));
     }
 }
 
+/// `max_count` sent for reads that go until the end of the stream.
+const UNBOUNDED_MAX_COUNT: u64 = u64::MAX;
+
 impl From<Direction> for protocol::read_stream_request::Direction {
     fn from(value: Direction) -> Self {
         match value {
@@ -35,6 +50,24 @@ impl From<protocol::read_stream_request::Direction> for Direction {
     }
 }
 
+impl From<Direction> for protocol::read_all_request::Direction {
+    fn from(value: Direction) -> Self {
+        match value {
+            Direction::Forward => protocol::read_all_request::Direction::Forwards(()),
+            Direction::Backward => protocol::read_all_request::Direction::Backwards(()),
+        }
+    }
+}
+
+impl From<protocol::read_all_request::Direction> for Direction {
+    fn from(value: protocol::read_all_request::Direction) -> Self {
+        match value {
+            protocol::read_all_request::Direction::Forwards(_) => Direction::Forward,
+            protocol::read_all_request::Direction::Backwards(_) => Direction::Backward,
+        }
+    }
+}
+
 impl From<Uuid> for protocol::Ident {
     fn from(value: Uuid) -> Self {
         let (most, least) = value.as_u64_pair();
@@ -54,6 +87,7 @@ impl From<AppendStream> for protocol::AppendStreamRequest {
             stream_name: value.stream_name,
             events: value.events.into_iter().map(|p| p.into()).collect(),
             expected_revision: Some(value.expected_revision.into()),
+            notify_subscribers: Some(value.notify_subscribers),
         }
     }
 }
@@ -79,6 +113,7 @@ impl TryFrom<protocol::AppendStreamRequest> for AppendStream {
             stream_name: value.stream_name,
             events,
             expected_revision,
+            notify_subscribers: value.notify_subscribers.unwrap_or(true),
         })
     }
 }
@@ -88,6 +123,7 @@ impl From<DeleteStream> for protocol::DeleteStreamRequest {
         Self {
             stream_name: value.stream_name,
             expected_revision: Some(value.expected_revision.into()),
+            dry_run: value.dry_run,
         }
     }
 }
@@ -104,6 +140,117 @@ impl TryFrom<protocol::DeleteStreamRequest> for DeleteStream {
         Ok(Self {
             stream_name: value.stream_name,
             expected_revision,
+            dry_run: value.dry_run,
+        })
+    }
+}
+
+impl From<TruncateStream> for protocol::TruncateStreamRequest {
+    fn from(value: TruncateStream) -> Self {
+        Self {
+            stream_name: value.stream_name,
+            before: value.before,
+        }
+    }
+}
+
+impl From<protocol::TruncateStreamRequest> for TruncateStream {
+    fn from(value: protocol::TruncateStreamRequest) -> Self {
+        Self {
+            stream_name: value.stream_name,
+            before: value.before,
+        }
+    }
+}
+
+impl From<CopyStream> for protocol::CopyStreamRequest {
+    fn from(value: CopyStream) -> Self {
+        Self {
+            source_stream: value.source_stream,
+            target_stream: value.target_stream,
+            expected_revision: Some(value.expected_revision.into()),
+            class_renames: value.class_renames,
+            mapper: value.mapper,
+            skip_mapper_errors: value.on_mapper_error == MapperErrorPolicy::Skip,
+        }
+    }
+}
+
+impl TryFrom<protocol::CopyStreamRequest> for CopyStream {
+    type Error = tonic::Status;
+
+    fn try_from(value: protocol::CopyStreamRequest) -> Result<Self, Self::Error> {
+        let expected_revision = value
+            .expected_revision
+            .map(Into::into)
+            .ok_or_else(|| tonic::Status::invalid_argument("expected_revision is missing"))?;
+
+        Ok(Self {
+            source_stream: value.source_stream,
+            target_stream: value.target_stream,
+            expected_revision,
+            class_renames: value.class_renames,
+            mapper: value.mapper,
+            on_mapper_error: if value.skip_mapper_errors {
+                MapperErrorPolicy::Skip
+            } else {
+                MapperErrorPolicy::Stop
+            },
+        })
+    }
+}
+
+impl From<StreamMetadata> for protocol::StreamMetadata {
+    fn from(value: StreamMetadata) -> Self {
+        Self {
+            max_count: value.max_count,
+            max_age_secs: value.max_age_secs,
+            acl: value.acl.map(|acl| protocol::stream_metadata::StreamAcl {
+                read: acl.read,
+                write: acl.write,
+                delete: acl.delete,
+            }),
+            custom: value.custom,
+        }
+    }
+}
+
+impl From<protocol::StreamMetadata> for StreamMetadata {
+    fn from(value: protocol::StreamMetadata) -> Self {
+        Self {
+            max_count: value.max_count,
+            max_age_secs: value.max_age_secs,
+            acl: value.acl.map(|acl| StreamAcl {
+                read: acl.read,
+                write: acl.write,
+                delete: acl.delete,
+            }),
+            custom: value.custom,
+        }
+    }
+}
+
+impl From<SetStreamMetadata> for protocol::SetStreamMetadataRequest {
+    fn from(value: SetStreamMetadata) -> Self {
+        Self {
+            stream_name: value.stream_name,
+            metadata: Some(value.metadata.into()),
+        }
+    }
+}
+
+impl TryFrom<protocol::SetStreamMetadataRequest> for SetStreamMetadata {
+    type Error = tonic::Status;
+
+    fn try_from(value: protocol::SetStreamMetadataRequest) -> Result<Self, Self::Error> {
+        let metadata = value
+            .metadata
+            .map(Into::into)
+            .ok_or_else(|| tonic::Status::invalid_argument("metadata is missing"))?;
+
+        Ok(Self {
+            stream_name: value.stream_name,
+            metadata,
         })
     }
 }
@@ -112,9 +259,11 @@ impl From<ReadStream> for protocol::ReadStreamRequest {
     fn from(value: ReadStream) -> Self {
         Self {
             stream_name: value.stream_name,
-            max_count: value.max_count,
+            max_count: value.max_count.unwrap_or(UNBOUNDED_MAX_COUNT),
             direction: Some(value.direction.into()),
             start: Some(value.revision.into()),
+            resolve_links: Some(value.resolve_links),
+            all_epochs: value.epochs == Epochs::All,
         }
     }
 }
@@ -139,7 +288,89 @@ impl TryFrom<protocol::ReadStreamRequest> for ReadStream {
             stream_name: value.stream_name,
             direction,
             revision,
-            max_count: value.max_count,
+            max_count: (value.max_count != UNBOUNDED_MAX_COUNT).then_some(value.max_count),
+            resolve_links: value.resolve_links.unwrap_or(true),
+            epochs: if value.all_epochs {
+                Epochs::All
+            } else {
+                Epochs::Current
+            },
+        })
+    }
+}
+
+impl From<ReadAll> for protocol::ReadAllRequest {
+    fn from(value: ReadAll) -> Self {
+        Self {
+            direction: Some(value.direction.into()),
+            start: Some(value.position.into()),
+            max_count: value.max_count.unwrap_or(UNBOUNDED_MAX_COUNT),
+        }
+    }
+}
+
+impl TryFrom<protocol::ReadAllRequest> for ReadAll {
+    type Error = tonic::Status;
+
+    fn try_from(value: protocol::ReadAllRequest) -> Result<Self, Self::Error> {
+        let direction = if let Some(d) = value.direction.map(Into::into) {
+            d
+        } else {
+            return Err(tonic::Status::invalid_argument("direction is missing"));
+        };
+
+        let position = if let Some(s) = value.start.map(Into::into) {
+            s
+        } else {
+            return Err(tonic::Status::invalid_argument("start is missing"));
+        };
+
+        Ok(Self {
+            position,
+            direction,
+            max_count: (value.max_count != UNBOUNDED_MAX_COUNT).then_some(value.max_count),
+        })
+    }
+}
+
+impl From<ReadProjection> for protocol::ReadProjectionRequest {
+    fn from(value: ReadProjection) -> Self {
+        Self {
+            read: Some(
+                ReadStream {
+                    stream_name: value.stream_name,
+                    direction: value.direction,
+                    revision: value.revision,
+                    max_count: Some(value.max_count),
+                    resolve_links: false,
+                    epochs: Epochs::Current,
+                }
+                .into(),
+            ),
+            fields: value.fields,
+        }
+    }
+}
+
+impl TryFrom<protocol::ReadProjectionRequest> for ReadProjection {
+    type Error = tonic::Status;
+
+    fn try_from(value: protocol::ReadProjectionRequest) -> Result<Self, Self::Error> {
+        let read: ReadStream = value
+            .read
+            .ok_or_else(|| tonic::Status::invalid_argument("read is missing"))?
+            .try_into()?;
+
+        if value.fields.is_empty() {
+            return Err(tonic::Status::invalid_argument("fields are missing"));
+        }
+
+        Ok(Self {
+            stream_name: read.stream_name,
+            direction: read.direction,
+            revision: read.revision,
+            max_count: read.max_count.unwrap_or(UNBOUNDED_MAX_COUNT),
+            fields: value.fields,
         })
     }
 }
@@ -154,6 +385,10 @@ impl From<Subscribe> for protocol::SubscribeRequest {
             Subscribe::ToStream(v) => protocol::SubscribeRequest {
                 to: Some(protocol::subscribe_request::To::Stream(v.into())),
             },
+
+            Subscribe::ToGroup(v) => protocol::SubscribeRequest {
+                to: Some(protocol::subscribe_request::To::Group(v.into())),
+            },
         }
     }
 }
@@ -169,6 +404,7 @@ impl TryFrom<protocol::SubscribeRequest> for Subscribe {
         match value {
             protocol::subscribe_request::To::Program(v) => Ok(Subscribe::ToProgram(v.into())),
             protocol::subscribe_request::To::Stream(v) => Ok(Subscribe::ToStream(v.try_into()?)),
+            protocol::subscribe_request::To::Group(v) => Ok(Subscribe::ToGroup(v.into())),
         }
     }
 }
@@ -193,6 +429,26 @@ impl From<protocol::read_stream_request::Start> for Revision<u64> {
     }
 }
 
+impl From<Revision<u64>> for protocol::read_all_request::Start {
+    fn from(value: Revision<u64>) -> Self {
+        match value {
+            Revision::Start => protocol::read_all_request::Start::Beginning(()),
+            Revision::End => protocol::read_all_request::Start::End(()),
+            Revision::Revision(p) => protocol::read_all_request::Start::Position(p),
+        }
+    }
+}
+
+impl From<protocol::read_all_request::Start> for Revision<u64> {
+    fn from(value: protocol::read_all_request::Start) -> Self {
+        match value {
+            protocol::read_all_request::Start::Beginning(_) => Revision::Start,
+            protocol::read_all_request::Start::End(_) => Revision::End,
+            protocol::read_all_request::Start::Position(p) => Revision::Revision(p),
+        }
+    }
+}
+
 impl From<protocol::subscribe_request::stream::Start> for Revision<u64> {
     fn from(value: protocol::subscribe_request::stream::Start) -> Self {
         match value {
@@ -253,6 +509,7 @@ impl From<protocol::ContentType> for ContentType {
             protocol::ContentType::Unknown => Self::Unknown,
             protocol::ContentType::Json => Self::Json,
             protocol::ContentType::Binary => Self::Binary,
+            protocol::ContentType::Protobuf => Self::Protobuf,
         }
     }
 }
@@ -264,7 +521,7 @@ impl From<Propose> for protocol::append_stream_request::Propose {
             content_type: value.content_type as i32,
             class: value.class,
             payload: value.data,
-            metadata: Default::default(),
+            metadata: value.metadata,
         }
     }
 }
@@ -285,6 +542,7 @@ impl TryFrom<protocol::append_stream_request::Propose> for Propose {
                 .unwrap_or(ContentType::Unknown),
             class: value.class,
             data: value.payload,
+            metadata: value.metadata,
         })
     }
 }
@@ -308,6 +566,14 @@ impl TryFrom<protocol::RecordedEvent> for Record {
             position: value.position,
             revision: value.revision,
             data: value.payload,
+            metadata: value.metadata,
+            created: created_from_millis(value.created)?,
+            link: value.link.map(|l| RecordLink {
+                stream_name: l.stream_name,
+                revision: l.revision,
+                position: l.position,
+            }),
+            epoch: value.epoch,
         })
     }
 }
@@ -322,7 +588,14 @@ impl From<Record> for protocol::RecordedEvent {
             position: value.position,
             revision: value.revision,
             payload: value.data,
-            metadata: Default::default(),
+            metadata: value.metadata,
+            created: value.created.timestamp_millis(),
+            link: value.link.map(|l| protocol::recorded_event::Link {
+                stream_name: l.stream_name,
+                revision: l.revision,
+                position: l.position,
+            }),
+            epoch: value.epoch,
         }
     }
 }
@@ -360,6 +633,33 @@ impl From<ExpectedRevision>
     }
 }
 
+impl From<protocol::delete_stream_response::dry_run_result::CurrentRevision> for ExpectedRevision {
+    fn from(value: protocol::delete_stream_response::dry_run_result::CurrentRevision) -> Self {
+        match value {
+            protocol::delete_stream_response::dry_run_result::CurrentRevision::NotExists(_) => {
+                ExpectedRevision::NoStream
+            }
+            protocol::delete_stream_response::dry_run_result::CurrentRevision::Revision(v) => {
+                ExpectedRevision::Revision(v)
+            }
+        }
+    }
+}
+
+impl From<ExpectedRevision> for protocol::delete_stream_response::dry_run_result::CurrentRevision {
+    fn from(value: ExpectedRevision) -> Self {
+        match value {
+            ExpectedRevision::Revision(v) => {
+                protocol::delete_stream_response::dry_run_result::CurrentRevision::Revision(v)
+            }
+            ExpectedRevision::NoStream => {
+                protocol::delete_stream_response::dry_run_result::CurrentRevision::NotExists(())
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
 impl From<ExpectedRevision>
     for protocol::delete_stream_response::error::wrong_expected_revision::CurrentRevision
 {
@@ -452,6 +752,40 @@ impl From<protocol::delete_stream_request::ExpectedRevision> for ExpectedRevisio
     }
 }
 
+impl From<protocol::copy_stream_request::ExpectedRevision> for ExpectedRevision {
+    fn from(value: protocol::copy_stream_request::ExpectedRevision) -> Self {
+        match value {
+            protocol::copy_stream_request::ExpectedRevision::Revision(r) => {
+                ExpectedRevision::Revision(r)
+            }
+            protocol::copy_stream_request::ExpectedRevision::NoStream(_) => {
+                ExpectedRevision::NoStream
+            }
+            protocol::copy_stream_request::ExpectedRevision::Any(_) => ExpectedRevision::Any,
+            protocol::copy_stream_request::ExpectedRevision::StreamExists(_) => {
+                ExpectedRevision::StreamExists
+            }
+        }
+    }
+}
+
+impl From<ExpectedRevision> for protocol::copy_stream_request::ExpectedRevision {
+    fn from(value: ExpectedRevision) -> Self {
+        match value {
+            ExpectedRevision::Revision(r) => {
+                protocol::copy_stream_request::ExpectedRevision::Revision(r)
+            }
+            ExpectedRevision::NoStream => {
+                protocol::copy_stream_request::ExpectedRevision::NoStream(())
+            }
+            ExpectedRevision::Any => protocol::copy_stream_request::ExpectedRevision::Any(()),
+            ExpectedRevision::StreamExists => {
+                protocol::copy_stream_request::ExpectedRevision::StreamExists(())
+            }
+        }
+    }
+}
+
 impl From<ExpectedRevision> for protocol::delete_stream_request::ExpectedRevision {
     fn from(value: ExpectedRevision) -> Self {
         match value {
@@ -542,6 +876,14 @@ impl From<SubscribeToStream> for protocol::subscribe_request::Stream {
         Self {
             stream_name: value.stream_name,
             start: Some(value.start.into()),
+            batching: value
+                .batching
+                .map(|b| protocol::subscribe_request::Batching {
+                    max_size: b.max_size as u32,
+                    max_delay_ms: b.max_delay.as_millis() as u64,
+                }),
+            class_filter: value.class_filter,
+            heartbeat_interval_ms: value.heartbeat_interval.map(|i| i.as_millis() as u64),
         }
     }
 }
@@ -555,9 +897,34 @@ impl TryFrom<protocol::subscribe_request::Stream> for SubscribeToStream {
             .map(Into::into)
             .ok_or_else(|| tonic::Status::invalid_argument("start is missing"))?;
 
+        let batching = match value.batching {
+            None => None,
+            Some(b) if b.max_size == 0 => {
+                return Err(tonic::Status::invalid_argument(
+                    "batching max_size must be positive",
+                ));
+            }
+            Some(b) => Some(SubscriptionBatching {
+                max_size: b.max_size as usize,
+                max_delay: Duration::from_millis(b.max_delay_ms),
+            }),
+        };
+
+        let heartbeat_interval = match value.heartbeat_interval_ms {
+            Some(0) => {
+                return Err(tonic::Status::invalid_argument(
+                    "heartbeat interval must be positive",
+                ));
+            }
+            interval => interval.map(Duration::from_millis),
+        };
+
         Ok(Self {
             stream_name: value.stream_name,
             start,
+            batching,
+            class_filter: value.class_filter,
+            heartbeat_interval,
         })
     }
 }
@@ -567,6 +934,8 @@ impl From<SubscribeToProgram> for protocol::subscribe_request::Program {
         Self {
             name: value.name,
             source: value.source,
+            logs: value.logs,
+            restart: Some(value.restart.into()),
         }
     }
 }
@@ -576,6 +945,81 @@ impl From<protocol::subscribe_request::Program> for SubscribeToProgram {
         Self {
             name: value.name,
             source: value.source,
+            logs: value.logs,
+            restart: value.restart.map(RestartPolicy::from).unwrap_or_default(),
+        }
+    }
+}
+
+impl From<SubscribeToGroup> for protocol::subscribe_request::Group {
+    fn from(value: SubscribeToGroup) -> Self {
+        Self {
+            group: value.group,
+            stream_name: value.stream_name,
+        }
+    }
+}
+
+impl From<protocol::subscribe_request::Group> for SubscribeToGroup {
+    fn from(value: protocol::subscribe_request::Group) -> Self {
+        Self {
+            group: value.group,
+            stream_name: value.stream_name,
+        }
+    }
+}
+
+impl From<Ack> for protocol::AckRequest {
+    fn from(value: Ack) -> Self {
+        Self {
+            group: value.group,
+            stream_name: value.stream_name,
+            position: value.position,
+        }
+    }
+}
+
+impl From<protocol::AckRequest> for Ack {
+    fn from(value: protocol::AckRequest) -> Self {
+        Self {
+            group: value.group,
+            stream_name: value.stream_name,
+            position: value.position,
+        }
+    }
+}
+
+impl From<RestartPolicy> for protocol::subscribe_request::RestartPolicy {
+    fn from(value: RestartPolicy) -> Self {
+        let kind = match value {
+            RestartPolicy::Never => protocol::subscribe_request::restart_policy::Kind::Never(()),
+            RestartPolicy::OnError { max, backoff } => {
+                protocol::subscribe_request::restart_policy::Kind::OnError(
+                    protocol::subscribe_request::restart_policy::OnError {
+                        max,
+                        backoff_ms: backoff.as_millis() as u64,
+                    },
+                )
+            }
+        };
+
+        Self { kind: Some(kind) }
+    }
+}
+
+impl From<protocol::subscribe_request::RestartPolicy> for RestartPolicy {
+    fn from(value: protocol::subscribe_request::RestartPolicy) -> Self {
+        match value.kind {
+            None | Some(protocol::subscribe_request::restart_policy::Kind::Never(_)) => {
+                RestartPolicy::Never
+            }
+
+            Some(protocol::subscribe_request::restart_policy::Kind::OnError(e)) => {
+                RestartPolicy::OnError {
+                    max: e.max,
+                    backoff: Duration::from_millis(e.backoff_ms),
+                }
+            }
         }
     }
 }
@@ -591,37 +1035,16 @@ impl TryFrom<protocol::AppendStreamResponse> for AppendStreamCompleted {
         match append_result {
             protocol::append_stream_response::AppendResult::WriteResult(r) => {
                 Ok(AppendStreamCompleted::Success(WriteResult {
-                    next_expected_version: ExpectedRevision::Revision(r.next_revision),
+                    next_expected_version: next_expected_version(r.next_expected_version),
                     position: r.position,
-                    next_logical_position: 0,
+                    next_logical_position: r.next_logical_position,
+                    event_ids: r.event_ids.into_iter().map(Into::into).collect(),
+                    created: created_from_millis(r.created)?,
                 }))
             }
 
             protocol::append_stream_response::AppendResult::Error(e) => {
-                let error = e
-                    .error
-                    .ok_or_else(|| tonic::Status::invalid_argument("error is missing"))?;
-
-                match error {
-                    protocol::append_stream_response::error::Error::WrongRevision(e) => {
-                        let expected = e.expected_revision.map(Into::into).ok_or_else(|| {
-                            tonic::Status::invalid_argument("expected_revision is missing")
-                        })?;
-                        let current = e.current_revision.map(Into::into).ok_or_else(|| {
-                            tonic::Status::invalid_argument("current_revision is missing")
-                        })?;
-
-                        Ok(AppendStreamCompleted::Error(
-                            AppendError::WrongExpectedRevision(WrongExpectedRevisionError {
-                                expected,
-                                current,
-                            }),
-                        ))
-                    }
-                    protocol::append_stream_response::error::Error::StreamDeleted(_) => {
-                        Ok(AppendStreamCompleted::Error(AppendError::StreamDeleted))
-                    }
-                }
+                Ok(AppendStreamCompleted::Error(e.try_into()?))
             }
         }
     }
@@ -638,42 +1061,233 @@ impl From<AppendStreamCompleted> for protocol::AppendStreamResponse {
 
             AppendStreamCompleted::Error(e) => protocol::AppendStreamResponse {
                 append_result: Some(protocol::append_stream_response::AppendResult::Error(
-                    protocol::append_stream_response::Error {
-                        error: Some(match e {
-                            AppendError::WrongExpectedRevision(e) => {
-                                protocol::append_stream_response::error::Error::WrongRevision(
-                                    e.into(),
-                                )
-                            }
-                            AppendError::StreamDeleted => {
-                                protocol::append_stream_response::error::Error::StreamDeleted(())
-                            }
-                        }),
-                    },
+                    e.into(),
                 )),
             },
         }
     }
 }
 
-impl From<WriteResult> for protocol::append_stream_response::WriteResult {
-    fn from(value: WriteResult) -> Self {
-        Self {
-            next_revision: value.next_expected_version.raw() as u64,
-            position: value.position,
-        }
+impl TryFrom<protocol::append_stream_response::Error> for AppendError {
+    type Error = tonic::Status;
+
+    fn try_from(value: protocol::append_stream_response::Error) -> Result<Self, tonic::Status> {
+        let error = value
+            .error
+            .ok_or_else(|| tonic::Status::invalid_argument("error is missing"))?;
+
+        match error {
+            protocol::append_stream_response::error::Error::WrongRevision(e) => {
+                let expected = e.expected_revision.map(Into::into).ok_or_else(|| {
+                    tonic::Status::invalid_argument("expected_revision is missing")
+                })?;
+                let current = e.current_revision.map(Into::into).ok_or_else(|| {
+                    tonic::Status::invalid_argument("current_revision is missing")
+                })?;
+
+                Ok(AppendError::WrongExpectedRevision(
+                    WrongExpectedRevisionError { expected, current },
+                ))
+            }
+            protocol::append_stream_response::error::Error::StreamDeleted(_) => {
+                Ok(AppendError::StreamDeleted)
+            }
+            protocol::append_stream_response::error::Error::InvalidEvent(e) => {
+                Ok(AppendError::InvalidEvent(InvalidEventError {
+                    index: e.index as usize,
+                    class: e.class,
+                    reason: e.reason,
+                }))
+            }
+            protocol::append_stream_response::error::Error::DuplicateEvents(_) => {
+                Ok(AppendError::DuplicateEvents)
+            }
+        }
+    }
+}
+
+impl From<AppendError> for protocol::append_stream_response::Error {
+    fn from(value: AppendError) -> Self {
+        Self {
+            error: Some(match value {
+                AppendError::WrongExpectedRevision(e) => {
+                    protocol::append_stream_response::error::Error::WrongRevision(e.into())
+                }
+                AppendError::StreamDeleted => {
+                    protocol::append_stream_response::error::Error::StreamDeleted(())
+                }
+                AppendError::InvalidEvent(e) => {
+                    protocol::append_stream_response::error::Error::InvalidEvent(
+                        protocol::append_stream_response::error::InvalidEvent {
+                            index: e.index as u64,
+                            class: e.class,
+                            reason: e.reason,
+                        },
+                    )
+                }
+                AppendError::DuplicateEvents => {
+                    protocol::append_stream_response::error::Error::DuplicateEvents(())
+                }
+            }),
+        }
+    }
+}
+
+impl From<WriteResult> for protocol::append_stream_response::WriteResult {
+    fn from(value: WriteResult) -> Self {
+        Self {
+            position: value.position,
+            next_expected_version: Some(value.next_expected_version.into()),
+            event_ids: value.event_ids.into_iter().map(Into::into).collect(),
+            created: value.created.timestamp_millis(),
+            next_logical_position: value.next_logical_position,
+        }
     }
 }
 
 impl From<WriteResult> for protocol::delete_stream_response::DeleteResult {
     fn from(value: WriteResult) -> Self {
         Self {
-            next_revision: value.next_expected_version.raw() as u64,
             position: value.position,
+            next_expected_version: Some(value.next_expected_version.into()),
+            created: value.created.timestamp_millis(),
+            next_logical_position: value.next_logical_position,
+        }
+    }
+}
+
+impl From<WriteResult> for protocol::truncate_stream_response::TruncateResult {
+    fn from(value: WriteResult) -> Self {
+        Self {
+            position: value.position,
+            next_expected_version: Some(value.next_expected_version.into()),
+            created: value.created.timestamp_millis(),
+            next_logical_position: value.next_logical_position,
+        }
+    }
+}
+
+impl From<WriteResult> for protocol::set_stream_metadata_response::WriteResult {
+    fn from(value: WriteResult) -> Self {
+        Self {
+            position: value.position,
+            next_expected_version: Some(value.next_expected_version.into()),
+            created: value.created.timestamp_millis(),
+            next_logical_position: value.next_logical_position,
+        }
+    }
+}
+
+impl From<ExpectedRevision>
+    for protocol::append_stream_response::write_result::NextExpectedVersion
+{
+    fn from(value: ExpectedRevision) -> Self {
+        match value {
+            ExpectedRevision::Revision(v) => Self::NextRevision(v),
+            ExpectedRevision::NoStream => Self::NoStream(()),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl From<protocol::append_stream_response::write_result::NextExpectedVersion>
+    for ExpectedRevision
+{
+    fn from(value: protocol::append_stream_response::write_result::NextExpectedVersion) -> Self {
+        match value {
+            protocol::append_stream_response::write_result::NextExpectedVersion::NextRevision(
+                v,
+            ) => ExpectedRevision::Revision(v),
+            protocol::append_stream_response::write_result::NextExpectedVersion::NoStream(_) => {
+                ExpectedRevision::NoStream
+            }
+        }
+    }
+}
+
+impl From<ExpectedRevision>
+    for protocol::delete_stream_response::delete_result::NextExpectedVersion
+{
+    fn from(value: ExpectedRevision) -> Self {
+        match value {
+            ExpectedRevision::Revision(v) => Self::NextRevision(v),
+            ExpectedRevision::NoStream => Self::NoStream(()),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl From<protocol::delete_stream_response::delete_result::NextExpectedVersion>
+    for ExpectedRevision
+{
+    fn from(value: protocol::delete_stream_response::delete_result::NextExpectedVersion) -> Self {
+        match value {
+            protocol::delete_stream_response::delete_result::NextExpectedVersion::NextRevision(
+                v,
+            ) => ExpectedRevision::Revision(v),
+            protocol::delete_stream_response::delete_result::NextExpectedVersion::NoStream(_) => {
+                ExpectedRevision::NoStream
+            }
+        }
+    }
+}
+
+impl From<ExpectedRevision>
+    for protocol::truncate_stream_response::truncate_result::NextExpectedVersion
+{
+    fn from(value: ExpectedRevision) -> Self {
+        match value {
+            ExpectedRevision::Revision(v) => Self::NextRevision(v),
+            ExpectedRevision::NoStream => Self::NoStream(()),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl From<protocol::truncate_stream_response::truncate_result::NextExpectedVersion>
+    for ExpectedRevision
+{
+    fn from(
+        value: protocol::truncate_stream_response::truncate_result::NextExpectedVersion,
+    ) -> Self {
+        match value {
+            protocol::truncate_stream_response::truncate_result::NextExpectedVersion::NextRevision(v) => ExpectedRevision::Revision(v),
+            protocol::truncate_stream_response::truncate_result::NextExpectedVersion::NoStream(_) => ExpectedRevision::NoStream,
+        }
+    }
+}
+
+impl From<ExpectedRevision>
+    for protocol::set_stream_metadata_response::write_result::NextExpectedVersion
+{
+    fn from(value: ExpectedRevision) -> Self {
+        match value {
+            ExpectedRevision::Revision(v) => Self::NextRevision(v),
+            ExpectedRevision::NoStream => Self::NoStream(()),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl From<protocol::set_stream_metadata_response::write_result::NextExpectedVersion>
+    for ExpectedRevision
+{
+    fn from(
+        value: protocol::set_stream_metadata_response::write_result::NextExpectedVersion,
+    ) -> Self {
+        match value {
+            protocol::set_stream_metadata_response::write_result::NextExpectedVersion::NextRevision(v) => ExpectedRevision::Revision(v),
+            protocol::set_stream_metadata_response::write_result::NextExpectedVersion::NoStream(_) => ExpectedRevision::NoStream,
         }
     }
 }
 
+/// Servers predating the `no_stream` case send the next revision as a plain field, which is left
+/// out of the message when it is 0.
+fn next_expected_version<A: Into<ExpectedRevision>>(value: Option<A>) -> ExpectedRevision {
+    value.map_or(ExpectedRevision::Revision(0), Into::into)
+}
+
 impl TryFrom<protocol::ReadStreamResponse> for ReadStreamResponse {
     type Error = tonic::Status;
 
@@ -683,8 +1297,10 @@ impl TryFrom<protocol::ReadStreamResponse> for ReadStreamResponse {
             .ok_or_else(|| tonic::Status::invalid_argument("read_result is missing"))?;
 
         match read_result {
-            protocol::read_stream_response::ReadResult::EndOfStream(_) => {
-                Ok(ReadStreamResponse::EndOfStream)
+            protocol::read_stream_response::ReadResult::EndOfStream(e) => {
+                Ok(ReadStreamResponse::EndOfStream {
+                    tail_revision: e.tail_revision,
+                })
             }
             protocol::read_stream_response::ReadResult::EventAppeared(e) => {
                 Ok(ReadStreamResponse::EventAppeared(e.try_into()?))
@@ -698,8 +1314,10 @@ impl TryFrom<ReadStreamResponse> for protocol::ReadStreamResponse {
 
     fn try_from(value: ReadStreamResponse) -> Result<Self, Self::Error> {
         match value {
-            ReadStreamResponse::EndOfStream => Ok(protocol::ReadStreamResponse {
-                read_result: Some(protocol::read_stream_response::ReadResult::EndOfStream(())),
+            ReadStreamResponse::EndOfStream { tail_revision } => Ok(protocol::ReadStreamResponse {
+                read_result: Some(protocol::read_stream_response::ReadResult::EndOfStream(
+                    protocol::read_stream_response::EndOfStream { tail_revision },
+                )),
             }),
 
             ReadStreamResponse::EventAppeared(e) => Ok(protocol::ReadStreamResponse {
@@ -713,6 +1331,87 @@ impl TryFrom<ReadStreamResponse> for protocol::ReadStreamResponse {
     }
 }
 
+impl TryFrom<protocol::read_projection_response::ProjectedEvent> for ProjectedRecord {
+    type Error = tonic::Status;
+
+    fn try_from(
+        value: protocol::read_projection_response::ProjectedEvent,
+    ) -> Result<Self, Self::Error> {
+        let id = value
+            .id
+            .map(Into::into)
+            .ok_or_else(|| tonic::Status::invalid_argument("id is missing"))?;
+
+        let mut values = Vec::with_capacity(value.values.len());
+        for bytes in value.values {
+            values.push(serde_json::from_slice(&bytes).map_err(|e| {
+                tonic::Status::invalid_argument(format!("invalid projected value: {e}"))
+            })?);
+        }
+
+        Ok(Self {
+            id,
+            stream_name: value.stream_name,
+            position: value.position,
+            revision: value.revision,
+            values,
+        })
+    }
+}
+
+impl From<ProjectedRecord> for protocol::read_projection_response::ProjectedEvent {
+    fn from(value: ProjectedRecord) -> Self {
+        Self {
+            id: Some(value.id.into()),
+            stream_name: value.stream_name,
+            revision: value.revision,
+            position: value.position,
+            values: value
+                .values
+                .iter()
+                .map(|v| serde_json::to_vec(v).expect("json value to be serializable"))
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<protocol::ReadProjectionResponse> for ReadProjectionResponse {
+    type Error = tonic::Status;
+
+    fn try_from(value: protocol::ReadProjectionResponse) -> Result<Self, Self::Error> {
+        let read_result = value
+            .read_result
+            .ok_or_else(|| tonic::Status::invalid_argument("read_result is missing"))?;
+
+        match read_result {
+            protocol::read_projection_response::ReadResult::EndOfStream(_) => {
+                Ok(ReadProjectionResponse::EndOfStream)
+            }
+            protocol::read_projection_response::ReadResult::EventAppeared(e) => {
+                Ok(ReadProjectionResponse::EventAppeared(e.try_into()?))
+            }
+        }
+    }
+}
+
+impl From<ReadProjectionResponse> for protocol::ReadProjectionResponse {
+    fn from(value: ReadProjectionResponse) -> Self {
+        match value {
+            ReadProjectionResponse::EndOfStream => protocol::ReadProjectionResponse {
+                read_result: Some(protocol::read_projection_response::ReadResult::EndOfStream(
+                    (),
+                )),
+            },
+
+            ReadProjectionResponse::EventAppeared(e) => protocol::ReadProjectionResponse {
+                read_result: Some(
+                    protocol::read_projection_response::ReadResult::EventAppeared(e.into()),
+                ),
+            },
+        }
+    }
+}
+
 impl TryFrom<protocol::DeleteStreamResponse> for DeleteStreamCompleted {
     type Error = tonic::Status;
 
@@ -724,12 +1423,22 @@ impl TryFrom<protocol::DeleteStreamResponse> for DeleteStreamCompleted {
         match result {
             protocol::delete_stream_response::Result::WriteResult(r) => {
                 Ok(DeleteStreamCompleted::Success(WriteResult {
-                    next_expected_version: ExpectedRevision::Revision(r.next_revision),
+                    next_expected_version: next_expected_version(r.next_expected_version),
                     position: r.position,
-                    next_logical_position: 0,
+                    next_logical_position: r.next_logical_position,
+                    event_ids: vec![],
+                    created: created_from_millis(r.created)?,
                 }))
             }
 
+            protocol::delete_stream_response::Result::DryRun(r) => {
+                let current = r.current_revision.map(Into::into).ok_or_else(|| {
+                    tonic::Status::invalid_argument("current_revision is missing")
+                })?;
+
+                Ok(DeleteStreamCompleted::DryRun(current))
+            }
+
             protocol::delete_stream_response::Result::Error(e) => {
                 let error = e
                     .error
@@ -776,6 +1485,14 @@ impl From<DeleteStreamCompleted> for protocol::DeleteStreamResponse {
                 )),
             },
 
+            DeleteStreamCompleted::DryRun(r) => protocol::DeleteStreamResponse {
+                result: Some(protocol::delete_stream_response::Result::DryRun(
+                    protocol::delete_stream_response::DryRunResult {
+                        current_revision: Some(r.into()),
+                    },
+                )),
+            },
+
             DeleteStreamCompleted::Error(e) => protocol::DeleteStreamResponse {
                 result: Some(protocol::delete_stream_response::Result::Error(
                     protocol::delete_stream_response::Error {
@@ -806,6 +1523,170 @@ impl From<DeleteStreamCompleted> for protocol::DeleteStreamResponse {
     }
 }
 
+impl TryFrom<protocol::TruncateStreamResponse> for TruncateStreamCompleted {
+    type Error = tonic::Status;
+
+    fn try_from(value: protocol::TruncateStreamResponse) -> Result<Self, tonic::Status> {
+        let result = value
+            .result
+            .ok_or_else(|| tonic::Status::invalid_argument("result is missing"))?;
+
+        match result {
+            protocol::truncate_stream_response::Result::WriteResult(r) => {
+                Ok(TruncateStreamCompleted::Success(WriteResult {
+                    next_expected_version: next_expected_version(r.next_expected_version),
+                    position: r.position,
+                    next_logical_position: r.next_logical_position,
+                    event_ids: vec![],
+                    created: created_from_millis(r.created)?,
+                }))
+            }
+
+            protocol::truncate_stream_response::Result::Error(e) => {
+                let error = e
+                    .error
+                    .ok_or_else(|| tonic::Status::invalid_argument("error is missing"))?;
+
+                match error {
+                    protocol::truncate_stream_response::error::Error::StreamDeleted(_) => {
+                        Ok(TruncateStreamCompleted::Error(TruncateError::StreamDeleted))
+                    }
+
+                    protocol::truncate_stream_response::error::Error::StreamNotFound(_) => Ok(
+                        TruncateStreamCompleted::Error(TruncateError::StreamNotFound),
+                    ),
+                }
+            }
+        }
+    }
+}
+
+impl From<TruncateStreamCompleted> for protocol::TruncateStreamResponse {
+    fn from(value: TruncateStreamCompleted) -> Self {
+        match value {
+            TruncateStreamCompleted::Success(w) => protocol::TruncateStreamResponse {
+                result: Some(protocol::truncate_stream_response::Result::WriteResult(
+                    w.into(),
+                )),
+            },
+
+            TruncateStreamCompleted::Error(e) => protocol::TruncateStreamResponse {
+                result: Some(protocol::truncate_stream_response::Result::Error(
+                    protocol::truncate_stream_response::Error {
+                        error: Some(match e {
+                            TruncateError::StreamDeleted => {
+                                protocol::truncate_stream_response::error::Error::StreamDeleted(())
+                            }
+
+                            TruncateError::StreamNotFound => {
+                                protocol::truncate_stream_response::error::Error::StreamNotFound(())
+                            }
+                        }),
+                    },
+                )),
+            },
+        }
+    }
+}
+
+impl TryFrom<protocol::SetStreamMetadataResponse> for SetStreamMetadataCompleted {
+    type Error = tonic::Status;
+
+    fn try_from(value: protocol::SetStreamMetadataResponse) -> Result<Self, tonic::Status> {
+        let result = value
+            .result
+            .ok_or_else(|| tonic::Status::invalid_argument("result is missing"))?;
+
+        match result {
+            protocol::set_stream_metadata_response::Result::WriteResult(r) => {
+                Ok(SetStreamMetadataCompleted::Success(WriteResult {
+                    next_expected_version: next_expected_version(r.next_expected_version),
+                    position: r.position,
+                    next_logical_position: r.next_logical_position,
+                    event_ids: vec![],
+                    created: created_from_millis(r.created)?,
+                }))
+            }
+
+            protocol::set_stream_metadata_response::Result::Error(e) => {
+                let error = e
+                    .error
+                    .ok_or_else(|| tonic::Status::invalid_argument("error is missing"))?;
+
+                match error {
+                    protocol::set_stream_metadata_response::error::Error::StreamDeleted(_) => Ok(
+                        SetStreamMetadataCompleted::Error(SetStreamMetadataError::StreamDeleted),
+                    ),
+                }
+            }
+        }
+    }
+}
+
+impl From<SetStreamMetadataCompleted> for protocol::SetStreamMetadataResponse {
+    fn from(value: SetStreamMetadataCompleted) -> Self {
+        match value {
+            SetStreamMetadataCompleted::Success(w) => protocol::SetStreamMetadataResponse {
+                result: Some(protocol::set_stream_metadata_response::Result::WriteResult(
+                    w.into(),
+                )),
+            },
+
+            SetStreamMetadataCompleted::Error(e) => protocol::SetStreamMetadataResponse {
+                result: Some(protocol::set_stream_metadata_response::Result::Error(
+                    protocol::set_stream_metadata_response::Error {
+                        error: Some(match e {
+                            SetStreamMetadataError::StreamDeleted => {
+                                protocol::set_stream_metadata_response::error::Error::StreamDeleted(
+                                    (),
+                                )
+                            }
+                        }),
+                    },
+                )),
+            },
+        }
+    }
+}
+
+impl TryFrom<protocol::GetStreamMetadataResponse> for ReadStreamCompleted<StreamMetadata> {
+    type Error = tonic::Status;
+
+    fn try_from(value: protocol::GetStreamMetadataResponse) -> Result<Self, tonic::Status> {
+        let result = value
+            .result
+            .ok_or_else(|| tonic::Status::invalid_argument("result is missing"))?;
+
+        match result {
+            protocol::get_stream_metadata_response::Result::Metadata(m) => {
+                Ok(ReadStreamCompleted::Success(m.into()))
+            }
+
+            protocol::get_stream_metadata_response::Result::StreamDeleted(_) => {
+                Ok(ReadStreamCompleted::StreamDeleted)
+            }
+        }
+    }
+}
+
+impl From<ReadStreamCompleted<StreamMetadata>> for protocol::GetStreamMetadataResponse {
+    fn from(value: ReadStreamCompleted<StreamMetadata>) -> Self {
+        let result = match value {
+            ReadStreamCompleted::Success(m) => {
+                protocol::get_stream_metadata_response::Result::Metadata(m.into())
+            }
+
+            ReadStreamCompleted::StreamDeleted => {
+                protocol::get_stream_metadata_response::Result::StreamDeleted(())
+            }
+        };
+
+        Self {
+            result: Some(result),
+        }
+    }
+}
+
 impl TryFrom<protocol::subscribe_response::Notification> for SubscriptionNotification {
     type Error = tonic::Status;
 
@@ -856,7 +1737,10 @@ impl TryFrom<protocol::SubscribeResponse> for SubscriptionEvent {
 
                 match kind {
                     protocol::subscribe_response::confirmation::Kind::StreamName(s) => Ok(
-                        SubscriptionEvent::Confirmed(SubscriptionConfirmation::StreamName(s)),
+                        SubscriptionEvent::Confirmed(SubscriptionConfirmation::StreamName {
+                            stream_name: s,
+                            start_revision: c.start_revision,
+                        }),
                     ),
                     protocol::subscribe_response::confirmation::Kind::ProcessId(p) => Ok(
                         SubscriptionEvent::Confirmed(SubscriptionConfirmation::ProcessId(p)),
@@ -869,27 +1753,72 @@ impl TryFrom<protocol::SubscribeResponse> for SubscriptionEvent {
                     .ok_or_else(|| tonic::Status::invalid_argument("event is missing"))?;
                 Ok(SubscriptionEvent::EventAppeared(event.try_into()?))
             }
+            protocol::subscribe_response::Event::EventsAppeared(e) => {
+                let mut records = Vec::with_capacity(e.events.len());
+
+                for event in e.events {
+                    records.push(event.try_into()?);
+                }
+
+                Ok(SubscriptionEvent::EventsAppeared(records))
+            }
             protocol::subscribe_response::Event::CaughtUp(_) => Ok(SubscriptionEvent::CaughtUp),
-            protocol::subscribe_response::Event::Error(_) => {
-                Ok(SubscriptionEvent::Unsubscribed(UnsubscribeReason::Server))
+            protocol::subscribe_response::Event::Error(e) => {
+                let reason = match e.limit_exceeded.and_then(|l| l.limit) {
+                    Some(limit) => UnsubscribeReason::LimitExceeded(limit.into()),
+                    None if e.user => UnsubscribeReason::User,
+                    None if e.shutdown => UnsubscribeReason::Shutdown,
+                    None => UnsubscribeReason::Server,
+                };
+
+                Ok(SubscriptionEvent::Unsubscribed(reason))
             }
             protocol::subscribe_response::Event::Notification(n) => {
                 Ok(SubscriptionEvent::Notification(n.try_into()?))
             }
+            protocol::subscribe_response::Event::ProgramLog(l) => {
+                Ok(SubscriptionEvent::ProgramLog { line: l.line })
+            }
+            protocol::subscribe_response::Event::Heartbeat(_) => Err(
+                tonic::Status::invalid_argument("a heartbeat is not a subscription event"),
+            ),
+        }
+    }
+}
+
+impl protocol::SubscribeResponse {
+    pub fn heartbeat() -> Self {
+        Self {
+            event: Some(protocol::subscribe_response::Event::Heartbeat(
+                protocol::subscribe_response::Heartbeat {},
+            )),
         }
     }
+
+    pub fn is_heartbeat(&self) -> bool {
+        matches!(
+            self.event,
+            Some(protocol::subscribe_response::Event::Heartbeat(_))
+        )
+    }
 }
 
 impl From<SubscriptionEvent> for protocol::SubscribeResponse {
     fn from(value: SubscriptionEvent) -> Self {
         match value {
             SubscriptionEvent::Confirmed(c) => match c {
-                SubscriptionConfirmation::StreamName(s) => protocol::SubscribeResponse {
+                SubscriptionConfirmation::StreamName {
+                    stream_name,
+                    start_revision,
+                } => protocol::SubscribeResponse {
                     event: Some(protocol::subscribe_response::Event::Confirmation(
                         protocol::subscribe_response::Confirmation {
                             kind: Some(
-                                protocol::subscribe_response::confirmation::Kind::StreamName(s),
+                                protocol::subscribe_response::confirmation::Kind::StreamName(
+                                    stream_name,
+                                ),
                             ),
+                            start_revision,
                         },
                     )),
                 },
@@ -899,6 +1828,7 @@ impl From<SubscriptionEvent> for protocol::SubscribeResponse {
                             kind: Some(
                                 protocol::subscribe_response::confirmation::Kind::ProcessId(p),
                             ),
+                            start_revision: None,
                         },
                     )),
                 },
@@ -910,20 +1840,73 @@ impl From<SubscriptionEvent> for protocol::SubscribeResponse {
                     },
                 )),
             },
+            SubscriptionEvent::EventsAppeared(records) => protocol::SubscribeResponse {
+                event: Some(protocol::subscribe_response::Event::EventsAppeared(
+                    protocol::subscribe_response::EventsAppeared {
+                        events: records.into_iter().map(Into::into).collect(),
+                    },
+                )),
+            },
             SubscriptionEvent::CaughtUp => protocol::SubscribeResponse {
                 event: Some(protocol::subscribe_response::Event::CaughtUp(
                     protocol::subscribe_response::CaughtUp {},
                 )),
             },
-            SubscriptionEvent::Unsubscribed(_) => protocol::SubscribeResponse {
-                event: Some(protocol::subscribe_response::Event::Error(
-                    protocol::subscribe_response::Error {},
-                )),
-            },
+            SubscriptionEvent::Unsubscribed(reason) => {
+                let limit_exceeded = if let UnsubscribeReason::LimitExceeded(limit) = reason {
+                    Some(protocol::subscribe_response::LimitExceeded {
+                        limit: Some(limit.into()),
+                    })
+                } else {
+                    None
+                };
+
+                protocol::SubscribeResponse {
+                    event: Some(protocol::subscribe_response::Event::Error(
+                        protocol::subscribe_response::Error {
+                            user: reason == UnsubscribeReason::User,
+                            shutdown: reason == UnsubscribeReason::Shutdown,
+                            limit_exceeded,
+                        },
+                    )),
+                }
+            }
 
             SubscriptionEvent::Notification(n) => protocol::SubscribeResponse {
                 event: Some(protocol::subscribe_response::Event::Notification(n.into())),
             },
+
+            SubscriptionEvent::ProgramLog { line } => protocol::SubscribeResponse {
+                event: Some(protocol::subscribe_response::Event::ProgramLog(
+                    protocol::subscribe_response::ProgramLog { line },
+                )),
+            },
+        }
+    }
+}
+
+impl From<protocol::subscribe_response::limit_exceeded::Limit> for ProgramLimit {
+    fn from(value: protocol::subscribe_response::limit_exceeded::Limit) -> Self {
+        match value {
+            protocol::subscribe_response::limit_exceeded::Limit::EventsPerSecond(n) => {
+                ProgramLimit::EventsPerSecond(n)
+            }
+            protocol::subscribe_response::limit_exceeded::Limit::Subscriptions(n) => {
+                ProgramLimit::Subscriptions(n as usize)
+            }
+            protocol::subscribe_response::limit_exceeded::Limit::OutputBuffer(n) => {
+                ProgramLimit::OutputBuffer(n as usize)
+            }
+        }
+    }
+}
+
+impl From<ProgramLimit> for protocol::subscribe_response::limit_exceeded::Limit {
+    fn from(value: ProgramLimit) -> Self {
+        match value {
+            ProgramLimit::EventsPerSecond(n) => Self::EventsPerSecond(n),
+            ProgramLimit::Subscriptions(n) => Self::Subscriptions(n as u64),
+            ProgramLimit::OutputBuffer(n) => Self::OutputBuffer(n as u64),
         }
     }
 }
@@ -958,6 +1941,7 @@ impl From<ProgramSummary> for protocol::list_programs_response::ProgramSummary {
 impl TryFrom<protocol::ListProgramsResponse> for ProgramListed {
     type Error = tonic::Status;
 
+    #[allow(clippy::result_large_err)]
     fn try_from(value: protocol::ListProgramsResponse) -> Result<Self, Self::Error> {
         let programs: Result<Vec<ProgramSummary>, tonic::Status> =
             value.programs.into_iter().map(|p| p.try_into()).collect();
@@ -1046,6 +2030,90 @@ impl From<ProgramKilled> for protocol::StopProgramResponse {
     }
 }
 
+impl From<SetLogFilters> for protocol::SetLogFiltersRequest {
+    fn from(value: SetLogFilters) -> Self {
+        Self {
+            directives: value.directives,
+        }
+    }
+}
+
+impl From<protocol::SetLogFiltersRequest> for SetLogFilters {
+    fn from(value: protocol::SetLogFiltersRequest) -> Self {
+        Self {
+            directives: value.directives,
+        }
+    }
+}
+
+impl TryFrom<protocol::SetLogFiltersResponse> for LogFiltersSet {
+    type Error = tonic::Status;
+
+    fn try_from(value: protocol::SetLogFiltersResponse) -> Result<Self, tonic::Status> {
+        let result = value
+            .result
+            .ok_or_else(|| tonic::Status::invalid_argument("result is missing"))?;
+
+        match result {
+            protocol::set_log_filters_response::Result::Success(_) => Ok(LogFiltersSet::Success),
+            protocol::set_log_filters_response::Result::InvalidDirective(e) => {
+                Ok(LogFiltersSet::InvalidDirective(e))
+            }
+        }
+    }
+}
+
+impl From<LogFiltersSet> for protocol::SetLogFiltersResponse {
+    fn from(value: LogFiltersSet) -> Self {
+        let result = match value {
+            LogFiltersSet::Success => protocol::set_log_filters_response::Result::Success(()),
+            LogFiltersSet::InvalidDirective(e) => {
+                protocol::set_log_filters_response::Result::InvalidDirective(e)
+            }
+        };
+
+        Self {
+            result: Some(result),
+        }
+    }
+}
+
+impl From<MaintainIndex> for protocol::MaintainIndexRequest {
+    fn from(value: MaintainIndex) -> Self {
+        Self {
+            compact: value.compact,
+        }
+    }
+}
+
+impl From<protocol::MaintainIndexRequest> for MaintainIndex {
+    fn from(value: protocol::MaintainIndexRequest) -> Self {
+        Self {
+            compact: value.compact,
+        }
+    }
+}
+
+impl From<IndexMaintained> for protocol::MaintainIndexResponse {
+    fn from(value: IndexMaintained) -> Self {
+        Self {
+            flushed: value.flushed,
+            ss_tables_before: value.ss_tables_before,
+            ss_tables_after: value.ss_tables_after,
+        }
+    }
+}
+
+impl From<protocol::MaintainIndexResponse> for IndexMaintained {
+    fn from(value: protocol::MaintainIndexResponse) -> Self {
+        Self {
+            flushed: value.flushed,
+            ss_tables_before: value.ss_tables_before,
+            ss_tables_after: value.ss_tables_after,
+        }
+    }
+}
+
 impl TryFrom<protocol::ProgramStatsResponse> for ProgramObtained {
     type Error = tonic::Status;
 
@@ -1114,10 +2182,35 @@ impl TryFrom<protocol::program_stats_response::ProgramStats> for ProgramStats {
                 .timestamp_opt(value.started_at, 0)
                 .single()
                 .ok_or_else(|| tonic::Status::invalid_argument("started_at is out of range"))?,
+            limits: value
+                .limits
+                .ok_or_else(|| tonic::Status::invalid_argument("limits is missing"))?
+                .into(),
+            restarts: value.restarts as usize,
         })
     }
 }
 
+impl From<protocol::program_stats_response::ProgramLimits> for ProgramLimits {
+    fn from(value: protocol::program_stats_response::ProgramLimits) -> Self {
+        Self {
+            max_events_per_sec: value.max_events_per_sec,
+            max_subscriptions: value.max_subscriptions as usize,
+            max_output_buffer: value.max_output_buffer as usize,
+        }
+    }
+}
+
+impl From<ProgramLimits> for protocol::program_stats_response::ProgramLimits {
+    fn from(value: ProgramLimits) -> Self {
+        Self {
+            max_events_per_sec: value.max_events_per_sec,
+            max_subscriptions: value.max_subscriptions as u64,
+            max_output_buffer: value.max_output_buffer as u64,
+        }
+    }
+}
+
 impl From<ProgramStats> for protocol::program_stats_response::ProgramStats {
     fn from(value: ProgramStats) -> Self {
         Self {
@@ -1127,6 +2220,8 @@ impl From<ProgramStats> for protocol::program_stats_response::ProgramStats {
             subscriptions: value.subscriptions,
             pushed_events: value.pushed_events as u64,
             started_at: value.started.timestamp(),
+            limits: Some(value.limits.into()),
+            restarts: value.restarts as u64,
         }
     }
 }
@@ -1142,3 +2237,143 @@ impl From<protocol::ListProgramsRequest> for ListPrograms {
         Self {}
     }
 }
+
+#[allow(clippy::result_large_err)]
+fn created_from_millis(millis: i64) -> Result<DateTime<Utc>, tonic::Status> {
+    Utc.timestamp_millis_opt(millis)
+        .single()
+        .ok_or_else(|| tonic::Status::invalid_argument("created is out of range"))
+}
+
+impl From<ListStreams> for protocol::ListStreamsRequest {
+    fn from(value: ListStreams) -> Self {
+        Self {
+            prefix: value.prefix,
+            offset: value.offset as u64,
+            limit: value.limit as u64,
+        }
+    }
+}
+
+impl From<protocol::ListStreamsRequest> for ListStreams {
+    fn from(value: protocol::ListStreamsRequest) -> Self {
+        Self {
+            prefix: value.prefix,
+            offset: value.offset as usize,
+            limit: value.limit as usize,
+        }
+    }
+}
+
+impl From<StreamPage> for protocol::ListStreamsResponse {
+    fn from(value: StreamPage) -> Self {
+        Self {
+            streams: value.streams,
+            total: value.total as u64,
+        }
+    }
+}
+
+impl From<protocol::ListStreamsResponse> for StreamPage {
+    fn from(value: protocol::ListStreamsResponse) -> Self {
+        Self {
+            streams: value.streams,
+            total: value.total as usize,
+        }
+    }
+}
+
+impl TryFrom<protocol::CopyStreamResponse> for CopyStreamCompleted {
+    type Error = tonic::Status;
+
+    fn try_from(value: protocol::CopyStreamResponse) -> Result<Self, tonic::Status> {
+        let result = value
+            .result
+            .ok_or_else(|| tonic::Status::invalid_argument("result is missing"))?;
+
+        match result {
+            protocol::copy_stream_response::Result::Copied(c) => {
+                let next_expected_version = match c.next_expected_version.ok_or_else(|| {
+                    tonic::Status::invalid_argument("next_expected_version is missing")
+                })? {
+                    protocol::copy_stream_response::copied::NextExpectedVersion::NoStream(_) => {
+                        ExpectedRevision::NoStream
+                    }
+
+                    protocol::copy_stream_response::copied::NextExpectedVersion::NextRevision(
+                        r,
+                    ) => ExpectedRevision::Revision(r),
+                };
+
+                Ok(CopyStreamCompleted::Success(StreamCopied {
+                    copied: c.count,
+                    skipped: c.skipped,
+                    next_expected_version,
+                }))
+            }
+
+            protocol::copy_stream_response::Result::SourceDeleted(_) => {
+                Ok(CopyStreamCompleted::Error(CopyError::SourceDeleted))
+            }
+
+            protocol::copy_stream_response::Result::AppendError(e) => {
+                Ok(CopyStreamCompleted::Error(CopyError::Append(e.try_into()?)))
+            }
+
+            protocol::copy_stream_response::Result::UnknownMapper(name) => {
+                Ok(CopyStreamCompleted::Error(CopyError::UnknownMapper(name)))
+            }
+
+            protocol::copy_stream_response::Result::MapperFailed(e) => {
+                Ok(CopyStreamCompleted::Error(CopyError::MapperFailed {
+                    revision: e.revision,
+                    reason: e.reason,
+                }))
+            }
+        }
+    }
+}
+
+impl From<CopyStreamCompleted> for protocol::CopyStreamResponse {
+    fn from(value: CopyStreamCompleted) -> Self {
+        let result = match value {
+            CopyStreamCompleted::Success(c) => {
+                protocol::copy_stream_response::Result::Copied(protocol::copy_stream_response::Copied {
+                    count: c.copied,
+                    next_expected_version: Some(match c.next_expected_version {
+                        ExpectedRevision::NoStream => {
+                            protocol::copy_stream_response::copied::NextExpectedVersion::NoStream(())
+                        }
+
+                        other => protocol::copy_stream_response::copied::NextExpectedVersion::NextRevision(
+                            other.raw() as u64,
+                        ),
+                    }),
+                    skipped: c.skipped,
+                })
+            }
+
+            CopyStreamCompleted::Error(CopyError::SourceDeleted) => {
+                protocol::copy_stream_response::Result::SourceDeleted(())
+            }
+
+            CopyStreamCompleted::Error(CopyError::Append(e)) => {
+                protocol::copy_stream_response::Result::AppendError(e.into())
+            }
+
+            CopyStreamCompleted::Error(CopyError::UnknownMapper(name)) => {
+                protocol::copy_stream_response::Result::UnknownMapper(name)
+            }
+
+            CopyStreamCompleted::Error(CopyError::MapperFailed { revision, reason }) => {
+                protocol::copy_stream_response::Result::MapperFailed(
+                    protocol::copy_stream_response::MapperFailed { revision, reason },
+                )
+            }
+        };
+
+        Self {
+            result: Some(result),
+        }
+    }
+}
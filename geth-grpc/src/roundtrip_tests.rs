@@ -0,0 +1,309 @@
+use bytes::Bytes;
+use chrono::{DateTime, TimeZone, Utc};
+use geth_common::{
+    AppendError, AppendStream, AppendStreamCompleted, ContentType, DeleteError, DeleteStream,
+    DeleteStreamCompleted, EndPoint, ExpectedRevision, InvalidEventError, ProgramLimit, Propose,
+    Record, RecordLink, SubscriptionConfirmation, SubscriptionEvent, SubscriptionNotification,
+    UnsubscribeReason, WriteResult, WrongExpectedRevisionError,
+};
+use proptest::prelude::*;
+use prost::Message;
+use uuid::Uuid;
+
+use crate::protocol;
+
+/// Goes through the wire format too, so fields left out of an encoded message are caught.
+fn wire<M: Message + Default>(message: M) -> M {
+    M::decode(message.encode_to_vec().as_slice()).unwrap()
+}
+
+fn uuid() -> impl Strategy<Value = Uuid> {
+    any::<u128>().prop_map(Uuid::from_u128)
+}
+
+fn bytes() -> impl Strategy<Value = Bytes> {
+    prop::collection::vec(any::<u8>(), 0..64).prop_map(Bytes::from)
+}
+
+fn content_type() -> impl Strategy<Value = ContentType> {
+    prop_oneof![
+        Just(ContentType::Unknown),
+        Just(ContentType::Json),
+        Just(ContentType::Binary),
+        Just(ContentType::Protobuf),
+    ]
+}
+
+/// Timestamps are sent with millisecond precision.
+fn created() -> impl Strategy<Value = DateTime<Utc>> {
+    (-8_000_000_000_000i64..8_000_000_000_000)
+        .prop_map(|millis| Utc.timestamp_millis_opt(millis).unwrap())
+}
+
+fn expected_revision() -> impl Strategy<Value = ExpectedRevision> {
+    prop_oneof![
+        any::<u64>().prop_map(ExpectedRevision::Revision),
+        Just(ExpectedRevision::NoStream),
+        Just(ExpectedRevision::Any),
+        Just(ExpectedRevision::StreamExists),
+    ]
+}
+
+/// Where a stream is at, which is never `Any` or `StreamExists`.
+fn current_revision() -> impl Strategy<Value = ExpectedRevision> {
+    prop_oneof![
+        any::<u64>().prop_map(ExpectedRevision::Revision),
+        Just(ExpectedRevision::NoStream),
+    ]
+}
+
+fn propose() -> impl Strategy<Value = Propose> {
+    (uuid(), content_type(), ".*", bytes(), bytes()).prop_map(
+        |(id, content_type, class, data, metadata)| Propose {
+            id,
+            content_type,
+            class,
+            data,
+            metadata,
+        },
+    )
+}
+
+fn record() -> impl Strategy<Value = Record> {
+    let link = (".*", any::<u64>(), any::<u64>()).prop_map(|(stream_name, revision, position)| {
+        RecordLink {
+            stream_name,
+            revision,
+            position,
+        }
+    });
+
+    (
+        (uuid(), content_type(), ".*", ".*"),
+        (any::<u64>(), any::<u64>(), any::<u64>()),
+        (bytes(), bytes(), created(), prop::option::of(link)),
+    )
+        .prop_map(
+            |(
+                (id, content_type, stream_name, class),
+                (position, revision, epoch),
+                (data, metadata, created, link),
+            )| Record {
+                id,
+                content_type,
+                stream_name,
+                class,
+                position,
+                revision,
+                data,
+                metadata,
+                created,
+                link,
+                epoch,
+            },
+        )
+}
+
+/// Only appends report the ids of the events they wrote.
+fn write_result(event_ids: bool) -> impl Strategy<Value = WriteResult> {
+    let ids = if event_ids { 0..8usize } else { 0..1 };
+
+    (
+        current_revision(),
+        any::<u64>(),
+        any::<u64>(),
+        prop::collection::vec(uuid(), ids),
+        created(),
+    )
+        .prop_map(
+            |(next_expected_version, position, next_logical_position, event_ids, created)| {
+                WriteResult {
+                    next_expected_version,
+                    position,
+                    next_logical_position,
+                    event_ids,
+                    created,
+                }
+            },
+        )
+}
+
+fn wrong_expected_revision() -> impl Strategy<Value = WrongExpectedRevisionError> {
+    (expected_revision(), current_revision())
+        .prop_map(|(expected, current)| WrongExpectedRevisionError { expected, current })
+}
+
+fn append_completed() -> impl Strategy<Value = AppendStreamCompleted> {
+    let invalid_event = (any::<u32>(), ".*", ".*").prop_map(|(index, class, reason)| {
+        AppendError::InvalidEvent(InvalidEventError {
+            index: index as usize,
+            class,
+            reason,
+        })
+    });
+
+    prop_oneof![
+        write_result(true).prop_map(AppendStreamCompleted::Success),
+        wrong_expected_revision()
+            .prop_map(|e| AppendStreamCompleted::Error(AppendError::WrongExpectedRevision(e))),
+        Just(AppendStreamCompleted::Error(AppendError::StreamDeleted)),
+        Just(AppendStreamCompleted::Error(AppendError::DuplicateEvents)),
+        invalid_event.prop_map(AppendStreamCompleted::Error),
+    ]
+}
+
+fn delete_completed() -> impl Strategy<Value = DeleteStreamCompleted> {
+    let not_leader = (".*", any::<u16>())
+        .prop_map(|(host, port)| DeleteError::NotLeaderException(EndPoint { host, port }));
+
+    prop_oneof![
+        write_result(false).prop_map(DeleteStreamCompleted::Success),
+        current_revision().prop_map(DeleteStreamCompleted::DryRun),
+        wrong_expected_revision()
+            .prop_map(|e| DeleteStreamCompleted::Error(DeleteError::WrongExpectedRevision(e))),
+        not_leader.prop_map(DeleteStreamCompleted::Error),
+        Just(DeleteStreamCompleted::Error(DeleteError::StreamDeleted)),
+    ]
+}
+
+fn subscription_event() -> impl Strategy<Value = SubscriptionEvent> {
+    let confirmation = prop_oneof![
+        (".*", prop::option::of(any::<u64>())).prop_map(|(stream_name, start_revision)| {
+            SubscriptionConfirmation::StreamName {
+                stream_name,
+                start_revision,
+            }
+        }),
+        any::<u64>().prop_map(SubscriptionConfirmation::ProcessId),
+    ];
+
+    let limit = prop_oneof![
+        any::<u64>().prop_map(ProgramLimit::EventsPerSecond),
+        any::<u32>().prop_map(|n| ProgramLimit::Subscriptions(n as usize)),
+        any::<u32>().prop_map(|n| ProgramLimit::OutputBuffer(n as usize)),
+    ];
+
+    // `Disconnected` is never sent over the wire.
+    let reason = prop_oneof![
+        Just(UnsubscribeReason::User),
+        Just(UnsubscribeReason::Server),
+        Just(UnsubscribeReason::Shutdown),
+        limit.prop_map(UnsubscribeReason::LimitExceeded),
+    ];
+
+    let notification = prop_oneof![
+        ".*".prop_map(SubscriptionNotification::Subscribed),
+        ".*".prop_map(SubscriptionNotification::Unsubscribed),
+    ];
+
+    prop_oneof![
+        record().prop_map(SubscriptionEvent::EventAppeared),
+        prop::collection::vec(record(), 1..4).prop_map(SubscriptionEvent::EventsAppeared),
+        confirmation.prop_map(SubscriptionEvent::Confirmed),
+        Just(SubscriptionEvent::CaughtUp),
+        reason.prop_map(SubscriptionEvent::Unsubscribed),
+        notification.prop_map(SubscriptionEvent::Notification),
+        ".*".prop_map(|line| SubscriptionEvent::ProgramLog { line }),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn test_propose_roundtrip(propose in propose()) {
+        let message = wire(protocol::append_stream_request::Propose::from(propose.clone()));
+
+        prop_assert_eq!(propose, Propose::try_from(message).unwrap());
+    }
+
+    #[test]
+    fn test_record_roundtrip(record in record()) {
+        let message = wire(protocol::RecordedEvent::from(record.clone()));
+
+        prop_assert_eq!(record, Record::try_from(message).unwrap());
+    }
+
+    #[test]
+    fn test_append_request_roundtrip(
+        stream_name in ".*",
+        events in prop::collection::vec(propose(), 0..4),
+        expected_revision in expected_revision(),
+        notify_subscribers in any::<bool>(),
+    ) {
+        let params = AppendStream {
+            stream_name: stream_name.clone(),
+            events: events.clone(),
+            expected_revision,
+            notify_subscribers,
+        };
+
+        let actual = AppendStream::try_from(wire(protocol::AppendStreamRequest::from(params))).unwrap();
+
+        prop_assert_eq!(stream_name, actual.stream_name);
+        prop_assert_eq!(events, actual.events);
+        prop_assert_eq!(expected_revision, actual.expected_revision);
+        prop_assert_eq!(notify_subscribers, actual.notify_subscribers);
+    }
+
+    #[test]
+    fn test_delete_request_roundtrip(
+        stream_name in ".*",
+        expected_revision in expected_revision(),
+        dry_run in any::<bool>(),
+    ) {
+        let params = DeleteStream {
+            stream_name: stream_name.clone(),
+            expected_revision,
+            dry_run,
+        };
+
+        let actual = DeleteStream::try_from(wire(protocol::DeleteStreamRequest::from(params))).unwrap();
+
+        prop_assert_eq!(stream_name, actual.stream_name);
+        prop_assert_eq!(expected_revision, actual.expected_revision);
+        prop_assert_eq!(dry_run, actual.dry_run);
+    }
+
+    #[test]
+    fn test_append_completed_roundtrip(completed in append_completed()) {
+        let message = wire(protocol::AppendStreamResponse::from(completed.clone()));
+
+        prop_assert_eq!(completed, AppendStreamCompleted::try_from(message).unwrap());
+    }
+
+    #[test]
+    fn test_delete_completed_roundtrip(completed in delete_completed()) {
+        let message = wire(protocol::DeleteStreamResponse::from(completed.clone()));
+
+        prop_assert_eq!(completed, DeleteStreamCompleted::try_from(message).unwrap());
+    }
+
+    #[test]
+    fn test_subscription_event_roundtrip(event in subscription_event()) {
+        let message = wire(protocol::SubscribeResponse::from(event.clone()));
+
+        prop_assert_eq!(event, SubscriptionEvent::try_from(message).unwrap());
+    }
+}
+
+#[test]
+fn test_zero_next_revision_from_older_servers() {
+    // Older servers sent the next revision as a plain field, left out of the message when 0.
+    let message = protocol::AppendStreamResponse {
+        append_result: Some(protocol::append_stream_response::AppendResult::WriteResult(
+            protocol::append_stream_response::WriteResult {
+                position: 42,
+                next_expected_version: None,
+                event_ids: vec![],
+                created: 0,
+                next_logical_position: 0,
+            },
+        )),
+    };
+
+    let result = AppendStreamCompleted::try_from(message)
+        .unwrap()
+        .success()
+        .unwrap();
+
+    assert_eq!(ExpectedRevision::Revision(0), result.next_expected_version);
+}
@@ -0,0 +1,73 @@
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+use bytes::Bytes;
+
+/// Algorithm an event payload is compressed with in the log. Each record says how its payload is
+/// stored, so a database can hold payloads compressed with different algorithms, or not at all.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Compression {
+    #[default]
+    None = 0,
+    /// Fastest, with a lower compression ratio.
+    Lz4 = 1,
+    Zstd = 2,
+}
+
+impl Compression {
+    pub fn compress(self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Compression::None => Ok(data.to_vec()),
+            Compression::Lz4 => Ok(lz4_flex::compress_prepend_size(data)),
+            Compression::Zstd => zstd::bulk::compress(data, zstd::DEFAULT_COMPRESSION_LEVEL),
+        }
+    }
+
+    pub fn decompress(self, data: Bytes) -> io::Result<Bytes> {
+        match self {
+            Compression::None => Ok(data),
+
+            Compression::Lz4 => lz4_flex::decompress_size_prepended(&data)
+                .map(Bytes::from)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+
+            Compression::Zstd => zstd::decode_all(data.as_ref()).map(Bytes::from),
+        }
+    }
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Compression::None),
+            1 => Some(Compression::Lz4),
+            2 => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Compression::None => write!(f, "none"),
+            Compression::Lz4 => write!(f, "lz4"),
+            Compression::Zstd => write!(f, "zstd"),
+        }
+    }
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(Compression::None),
+            "lz4" => Ok(Compression::Lz4),
+            "zstd" => Ok(Compression::Zstd),
+            _ => Err(format!(
+                "unknown compression '{s}', expected none, lz4 or zstd"
+            )),
+        }
+    }
+}
@@ -1,6 +1,10 @@
 pub const CHUNK_HEADER_SIZE: usize = 128;
 pub const CHUNK_FOOTER_SIZE: usize = 128;
+/// Size of the chunks of a new database, unless configured otherwise.
 pub const CHUNK_SIZE: usize = 256 * 1024 * 1024;
+pub const MIN_CHUNK_SIZE: usize = 64 * 1024;
+/// Chunk headers record the chunk size on 4 bytes.
+pub const MAX_CHUNK_SIZE: usize = u32::MAX as usize;
 pub const _CHUNK_FILE_SIZE: usize =
     _aligned_size(CHUNK_SIZE + CHUNK_HEADER_SIZE + CHUNK_FOOTER_SIZE);
 
@@ -1,10 +1,120 @@
+use std::fmt;
+use std::hash::Hasher;
+use std::str::FromStr;
+
 use bytes::Buf;
 use digest::Digest;
 use sha2::{digest, Sha512};
 
-pub fn mikoshi_hash(value: impl AsRef<[u8]>) -> u64 {
-    let mut hasher = Sha512::new();
-    hasher.update(value);
+/// Algorithm used to compute stream keys. A database must always use the algorithm it was created
+/// with, the one in use is recorded in the chunk headers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(u8)]
+pub enum HashAlgorithm {
+    /// Slowest but the most collision resistant.
+    #[default]
+    Sha512 = 0,
+    Xxh3 = 1,
+    Fnv1a = 2,
+}
+
+impl HashAlgorithm {
+    pub fn hash(self, value: impl AsRef<[u8]>) -> u64 {
+        match self {
+            HashAlgorithm::Sha512 => {
+                let mut hasher = Sha512::new();
+                hasher.update(value);
+
+                hasher.finalize().as_slice().get_u64_le()
+            }
+
+            HashAlgorithm::Xxh3 => xxhash_rust::xxh3::xxh3_64(value.as_ref()),
+
+            HashAlgorithm::Fnv1a => {
+                let mut hasher = fnv::FnvHasher::default();
+                hasher.write(value.as_ref());
+
+                hasher.finish()
+            }
+        }
+    }
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(HashAlgorithm::Sha512),
+            1 => Some(HashAlgorithm::Xxh3),
+            2 => Some(HashAlgorithm::Fnv1a),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HashAlgorithm::Sha512 => write!(f, "sha512"),
+            HashAlgorithm::Xxh3 => write!(f, "xxh3"),
+            HashAlgorithm::Fnv1a => write!(f, "fnv1a"),
+        }
+    }
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "sha512" => Ok(HashAlgorithm::Sha512),
+            "xxh3" => Ok(HashAlgorithm::Xxh3),
+            "fnv1a" => Ok(HashAlgorithm::Fnv1a),
+            _ => Err(format!(
+                "unknown hash algorithm '{s}', expected sha512, xxh3 or fnv1a"
+            )),
+        }
+    }
+}
+
+/// Key of a stream in the index, `algorithm` being the one of the database, see
+/// [`crate::wal::chunks::ChunkContainer::hash_algorithm`].
+pub fn mikoshi_hash(algorithm: HashAlgorithm, value: impl AsRef<[u8]>) -> u64 {
+    algorithm.hash(value)
+}
+
+const CRC32C_TABLE: [u32; 256] = crc32c_table();
+
+const fn crc32c_table() -> [u32; 256] {
+    // Reflected Castagnoli polynomial.
+    const POLY: u32 = 0x82F6_3B78;
+
+    let mut table = [0u32; 256];
+    let mut i = 0;
+
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ POLY
+            } else {
+                crc >> 1
+            };
+
+            bit += 1;
+        }
+
+        table[i] = crc;
+        i += 1;
+    }
+
+    table
+}
+
+/// CRC32C (Castagnoli) checksum of `value`, used to detect corrupted log entries.
+pub fn crc32c(value: impl AsRef<[u8]>) -> u32 {
+    let crc = value.as_ref().iter().fold(!0u32, |crc, byte| {
+        CRC32C_TABLE[((crc ^ *byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+    });
 
-    hasher.finalize().as_slice().get_u64_le()
+    !crc
 }
@@ -5,24 +5,48 @@ use geth_common::Record;
 pub use crate::storage::fs::FileSystemStorage;
 pub use crate::storage::in_mem::InMemoryStorage;
 
+pub mod compression;
 mod constants;
 pub mod hashing;
+pub mod manifest;
 pub mod storage;
+#[cfg(test)]
+mod tests;
 pub mod wal;
 
 pub struct MikoshiStream {
-    inner: mpsc::UnboundedReceiver<Record>,
+    inner: Receiver,
+}
+
+enum Receiver {
+    Unbounded(mpsc::UnboundedReceiver<Record>),
+    Bounded(mpsc::Receiver<Record>),
 }
 
 impl MikoshiStream {
     pub fn empty() -> Self {
         let (_, inner) = mpsc::unbounded_channel();
 
-        Self { inner }
+        Self::new(inner)
     }
 
     pub fn new(inner: mpsc::UnboundedReceiver<Record>) -> Self {
-        Self { inner }
+        Self {
+            inner: Receiver::Unbounded(inner),
+        }
+    }
+
+    /// Stream holding at most `capacity` records the consumer didn't read yet. The producer
+    /// waits for the consumer to catch up when it's full.
+    pub fn bounded(capacity: usize) -> (MikoshiStreamSender, Self) {
+        let (sender, inner) = mpsc::channel(capacity);
+
+        (
+            MikoshiStreamSender { inner: sender },
+            Self {
+                inner: Receiver::Bounded(inner),
+            },
+        )
     }
 
     pub fn from_vec(entries: Vec<Record>) -> Self {
@@ -32,14 +56,54 @@ impl MikoshiStream {
             let _ = sender.send(entry);
         }
 
-        Self { inner }
+        Self::new(inner)
+    }
+
+    /// Number of records produced but not read yet.
+    pub fn len(&self) -> usize {
+        match &self.inner {
+            Receiver::Unbounded(inner) => inner.len(),
+            Receiver::Bounded(inner) => inner.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
 
     pub async fn next(&mut self) -> eyre::Result<Option<Record>> {
-        if let Some(record) = self.inner.recv().await {
-            return Ok(Some(record));
+        let record = match &mut self.inner {
+            Receiver::Unbounded(inner) => inner.recv().await,
+            Receiver::Bounded(inner) => inner.recv().await,
+        };
+
+        Ok(record)
+    }
+}
+
+/// Producing half of a [`MikoshiStream::bounded`] stream.
+#[derive(Clone)]
+pub struct MikoshiStreamSender {
+    inner: mpsc::Sender<Record>,
+}
+
+impl MikoshiStreamSender {
+    /// Waits until the stream has room for the record. Fails if the stream was dropped.
+    pub async fn send(&self, record: Record) -> eyre::Result<()> {
+        if self.inner.send(record).await.is_err() {
+            eyre::bail!("stream was dropped");
+        }
+
+        Ok(())
+    }
+
+    /// Same as [`MikoshiStreamSender::send`] but blocks the current thread, for producers that
+    /// don't run on the async runtime.
+    pub fn blocking_send(&self, record: Record) -> eyre::Result<()> {
+        if self.inner.blocking_send(record).is_err() {
+            eyre::bail!("stream was dropped");
         }
 
-        Ok(None)
+        Ok(())
     }
 }
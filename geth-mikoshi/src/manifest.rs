@@ -0,0 +1,136 @@
+use std::io;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::constants::{MAX_CHUNK_SIZE, MIN_CHUNK_SIZE};
+use crate::hashing::HashAlgorithm;
+use crate::storage::{FileCategory, FileId, Storage, MANIFEST_FILENAME};
+
+#[cfg(test)]
+mod tests;
+
+/// Version of the manifest format written by this build.
+pub const MANIFEST_VERSION: u16 = 1;
+
+const MANIFEST_MAGIC: &[u8; 4] = b"GETH";
+// magic, version, min_reader_version, hash algorithm, chunk size and creation time.
+const MANIFEST_BASE_SIZE: usize = 4 + 2 + 2 + 1 + 8 + 8;
+
+#[derive(Copy, Clone, Debug)]
+pub struct Manifests;
+impl FileCategory for Manifests {
+    type Item = FileId;
+
+    fn parse(&self, name: &str) -> Option<Self::Item> {
+        (name == MANIFEST_FILENAME).then_some(FileId::Manifest)
+    }
+}
+
+/// Database-wide metadata, written when the database is created and rewritten atomically each
+/// time one of its settings changes.
+///
+/// Forward-compat policy: new fields are only ever appended at the end. `min_reader_version` is
+/// the lowest manifest version able to make sense of the file, and is only bumped when a change
+/// can't be safely ignored. A build can open any manifest whose `min_reader_version` isn't
+/// greater than [`MANIFEST_VERSION`], and keeps the fields it doesn't know about untouched when
+/// saving it back.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Manifest {
+    pub version: u16,
+    pub min_reader_version: u16,
+    pub hash_algorithm: HashAlgorithm,
+    pub chunk_size: u64,
+    /// Creation time, in seconds since Unix epoch.
+    pub created: u64,
+    extra: Bytes,
+}
+
+impl Manifest {
+    pub fn new(hash_algorithm: HashAlgorithm, chunk_size: usize) -> Self {
+        let created = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        Self {
+            version: MANIFEST_VERSION,
+            min_reader_version: 1,
+            hash_algorithm,
+            chunk_size: chunk_size as u64,
+            created,
+            extra: Bytes::new(),
+        }
+    }
+
+    pub fn load(storage: &Storage) -> io::Result<Option<Self>> {
+        if !storage.exists(FileId::Manifest)? {
+            return Ok(None);
+        }
+
+        Self::decode(storage.read_all(FileId::Manifest)?).map(Some)
+    }
+
+    pub fn save(&self, storage: &Storage) -> io::Result<()> {
+        storage.write_atomic(FileId::Manifest, self.encode())
+    }
+
+    /// Checks the manifest against the settings this build supports.
+    pub fn validate(&self) -> io::Result<()> {
+        if !(MIN_CHUNK_SIZE as u64..=MAX_CHUNK_SIZE as u64).contains(&self.chunk_size) {
+            return Err(invalid_data(format!(
+                "database uses {} bytes chunks but this build only supports chunks between {} and {} bytes",
+                self.chunk_size, MIN_CHUNK_SIZE, MAX_CHUNK_SIZE
+            )));
+        }
+
+        Ok(())
+    }
+
+    pub fn encode(&self) -> Bytes {
+        let mut buffer = BytesMut::with_capacity(MANIFEST_BASE_SIZE + self.extra.len());
+
+        buffer.put_slice(MANIFEST_MAGIC);
+        buffer.put_u16_le(self.version.max(MANIFEST_VERSION));
+        buffer.put_u16_le(self.min_reader_version);
+        buffer.put_u8(self.hash_algorithm as u8);
+        buffer.put_u64_le(self.chunk_size);
+        buffer.put_u64_le(self.created);
+        buffer.put_slice(&self.extra);
+
+        buffer.freeze()
+    }
+
+    pub fn decode(mut bytes: Bytes) -> io::Result<Self> {
+        if bytes.len() < MANIFEST_BASE_SIZE || &bytes[..4] != MANIFEST_MAGIC {
+            return Err(invalid_data("manifest file is corrupted".to_string()));
+        }
+
+        bytes.advance(4);
+        let version = bytes.get_u16_le();
+        let min_reader_version = bytes.get_u16_le();
+
+        if min_reader_version > MANIFEST_VERSION {
+            return Err(invalid_data(format!(
+                "database requires manifest version {min_reader_version} or higher but this build only supports version {MANIFEST_VERSION}"
+            )));
+        }
+
+        let alg = bytes.get_u8();
+        let hash_algorithm = HashAlgorithm::from_u8(alg)
+            .ok_or_else(|| invalid_data(format!("unknown hash algorithm {alg} in manifest")))?;
+
+        Ok(Self {
+            version,
+            min_reader_version,
+            hash_algorithm,
+            chunk_size: bytes.get_u64_le(),
+            created: bytes.get_u64_le(),
+            extra: bytes,
+        })
+    }
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
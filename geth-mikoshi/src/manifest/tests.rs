@@ -0,0 +1,74 @@
+use std::io;
+
+use bytes::{BufMut, Bytes, BytesMut};
+
+use crate::constants::CHUNK_SIZE;
+use crate::hashing::HashAlgorithm;
+use crate::manifest::{Manifest, MANIFEST_VERSION};
+use crate::storage::{FaultInjector, FileId, Storage};
+use crate::InMemoryStorage;
+
+#[test]
+fn test_manifest_round_trip() -> io::Result<()> {
+    let storage = InMemoryStorage::new_storage();
+
+    assert!(Manifest::load(&storage)?.is_none());
+
+    let manifest = Manifest::new(HashAlgorithm::Xxh3, CHUNK_SIZE);
+    manifest.save(&storage)?;
+
+    let actual = Manifest::load(&storage)?.unwrap();
+    assert_eq!(manifest, actual);
+    assert_eq!(MANIFEST_VERSION, actual.version);
+    actual.validate()?;
+
+    Ok(())
+}
+
+#[test]
+fn test_manifest_keeps_unknown_fields() -> io::Result<()> {
+    let mut buffer = BytesMut::from(
+        Manifest::new(HashAlgorithm::Fnv1a, CHUNK_SIZE)
+            .encode()
+            .as_ref(),
+    );
+    buffer[4..6].copy_from_slice(&(MANIFEST_VERSION + 1).to_le_bytes());
+    buffer.put_u32_le(42);
+
+    let manifest = Manifest::decode(buffer.clone().freeze())?;
+    assert_eq!(MANIFEST_VERSION + 1, manifest.version);
+    assert_eq!(HashAlgorithm::Fnv1a, manifest.hash_algorithm);
+    assert_eq!(buffer.freeze(), manifest.encode());
+
+    Ok(())
+}
+
+#[test]
+fn test_manifest_rejects_incompatible_version() {
+    let mut buffer = BytesMut::from(
+        Manifest::new(HashAlgorithm::Sha512, CHUNK_SIZE)
+            .encode()
+            .as_ref(),
+    );
+    buffer[6..8].copy_from_slice(&(MANIFEST_VERSION + 1).to_le_bytes());
+
+    assert!(Manifest::decode(buffer.freeze()).is_err());
+    assert!(Manifest::decode(Bytes::from_static(b"not a manifest, not at all")).is_err());
+}
+
+#[test]
+fn test_manifest_failed_save_keeps_previous() -> io::Result<()> {
+    let storage = InMemoryStorage::default();
+    let previous = Manifest::new(HashAlgorithm::Sha512, CHUNK_SIZE);
+
+    previous.save(&Storage::InMemory(storage.clone()))?;
+    storage.inject_faults(FaultInjector::default().fail_writes_of(FileId::Manifest));
+
+    let storage = Storage::InMemory(storage);
+    assert!(Manifest::new(HashAlgorithm::Xxh3, CHUNK_SIZE)
+        .save(&storage)
+        .is_err());
+    assert_eq!(previous, Manifest::load(&storage)?.unwrap());
+
+    Ok(())
+}
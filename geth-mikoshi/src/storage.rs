@@ -3,18 +3,26 @@ use std::{fmt, io};
 use bytes::{BufMut, Bytes, BytesMut};
 use uuid::Uuid;
 
+#[cfg(any(test, feature = "test-utils"))]
+pub use faults::{FaultInjector, IoOp};
 pub use fs::FileSystemStorage;
 pub use in_mem::InMemoryStorage;
 
+#[cfg(any(test, feature = "test-utils"))]
+mod faults;
 pub(crate) mod fs;
 pub(crate) mod in_mem;
 
+#[cfg(test)]
+mod tests;
+
 #[derive(Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub enum FileId {
     SSTable(Uuid),
     IndexMap,
     Chunk { num: usize, version: usize },
     Checkpoint(Checkpoint),
+    Manifest,
 }
 
 impl FileId {
@@ -37,6 +45,17 @@ impl FileId {
     pub const fn index_global_chk() -> Self {
         Self::Checkpoint(Checkpoint::IndexGlobal)
     }
+
+    /// Name of the file on the file system.
+    pub fn file_name(&self) -> String {
+        match self {
+            FileId::SSTable(id) => id.to_string(),
+            FileId::IndexMap => "indexmap".to_string(),
+            FileId::Chunk { num, version } => format!("chunk-{num:06}.{version:06}"),
+            FileId::Checkpoint(c) => c.as_str().to_string(),
+            FileId::Manifest => MANIFEST_FILENAME.to_string(),
+        }
+    }
 }
 
 impl fmt::Debug for FileId {
@@ -46,6 +65,7 @@ impl fmt::Debug for FileId {
             FileId::IndexMap => write!(f, "indexmap"),
             FileId::Chunk { num, version } => write!(f, "chunk-{num:06}.{version:06}"),
             FileId::Checkpoint(checkpoint) => write!(f, "{}", checkpoint.as_str()),
+            FileId::Manifest => write!(f, "{MANIFEST_FILENAME}"),
         }
     }
 }
@@ -71,6 +91,8 @@ impl Checkpoint {
     }
 }
 
+pub const MANIFEST_FILENAME: &str = "manifest";
+
 pub trait FileCategory {
     type Item;
 
@@ -98,6 +120,15 @@ impl Storage {
         }
     }
 
+    /// Replaces the whole content of a file. Readers either see the previous content or the new
+    /// one, never a partially written file.
+    pub fn write_atomic(&self, id: FileId, bytes: Bytes) -> io::Result<()> {
+        match self {
+            Storage::FileSystem(s) => s.write_atomic(id, bytes),
+            Storage::InMemory(s) => s.write_atomic(id, bytes),
+        }
+    }
+
     pub fn offset(&self, id: FileId) -> io::Result<u64> {
         match self {
             Storage::FileSystem(s) => s.offset(id),
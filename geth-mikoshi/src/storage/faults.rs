@@ -0,0 +1,88 @@
+use std::fmt;
+use std::io;
+
+use crate::storage::FileId;
+
+type Predicate = Box<dyn Fn(IoOp, FileId) -> bool + Send>;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IoOp {
+    Read,
+    Write,
+}
+
+/// Makes [`InMemoryStorage`](crate::InMemoryStorage) fail on purpose so tests can exercise IO
+/// error handling. Testing only, never use it in production.
+///
+/// Writes cover `write_to` and `append`, reads cover `read_from` and `read_all`. Write numbers
+/// start at 1 and are counted from the moment the injector is installed.
+#[derive(Default)]
+pub struct FaultInjector {
+    writes: usize,
+    nth_writes: Vec<usize>,
+    reads_of: Vec<FileId>,
+    writes_of: Vec<FileId>,
+    predicates: Vec<Predicate>,
+}
+
+impl FaultInjector {
+    pub fn fail_nth_write(mut self, n: usize) -> Self {
+        self.nth_writes.push(n);
+        self
+    }
+
+    pub fn fail_reads_of(mut self, id: FileId) -> Self {
+        self.reads_of.push(id);
+        self
+    }
+
+    pub fn fail_writes_of(mut self, id: FileId) -> Self {
+        self.writes_of.push(id);
+        self
+    }
+
+    pub fn fail_when<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(IoOp, FileId) -> bool + Send + 'static,
+    {
+        self.predicates.push(Box::new(predicate));
+        self
+    }
+
+    pub(crate) fn on_write(&mut self, id: FileId) -> io::Result<()> {
+        self.writes += 1;
+
+        if self.nth_writes.contains(&self.writes)
+            || self.writes_of.contains(&id)
+            || self.predicates.iter().any(|p| p(IoOp::Write, id))
+        {
+            return Err(injected(IoOp::Write, id));
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn on_read(&self, id: FileId) -> io::Result<()> {
+        if self.reads_of.contains(&id) || self.predicates.iter().any(|p| p(IoOp::Read, id)) {
+            return Err(injected(IoOp::Read, id));
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for FaultInjector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FaultInjector")
+            .field("writes", &self.writes)
+            .field("nth_writes", &self.nth_writes)
+            .field("reads_of", &self.reads_of)
+            .field("writes_of", &self.writes_of)
+            .field("predicates", &self.predicates.len())
+            .finish()
+    }
+}
+
+fn injected(op: IoOp, id: FileId) -> io::Error {
+    io::Error::other(format!("injected {op:?} failure on {id:?}"))
+}
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 use std::fs::{read_dir, File, OpenOptions};
-use std::io::{self, ErrorKind, Seek};
+use std::io::{self, ErrorKind, Seek, Write};
 #[cfg(target_family = "unix")]
 use std::os::unix::fs::FileExt;
 #[cfg(target_os = "windows")]
@@ -10,7 +10,6 @@ use std::sync::{Arc, Mutex};
 
 use bytes::{Bytes, BytesMut};
 
-use crate::constants::CHUNK_SIZE;
 use crate::storage::{FileCategory, FileId, Storage};
 
 #[derive(Clone, Debug)]
@@ -38,11 +37,6 @@ impl FileSystemStorage {
         } else {
             let path = self.file_path(id);
             let file = self.open_file(path)?;
-
-            if let FileId::Chunk { .. } = id {
-                file.set_len(CHUNK_SIZE as u64)?;
-            }
-
             let file = Arc::new(file);
             inner.insert(id, file.clone());
 
@@ -64,12 +58,7 @@ impl FileSystemStorage {
     }
 
     fn file_path(&self, id: FileId) -> PathBuf {
-        match id {
-            FileId::SSTable(id) => self.root.join(id.to_string()),
-            FileId::IndexMap => self.root.join("indexmap"),
-            FileId::Chunk { num, version } => self.root.join(chunk_filename_from(num, version)),
-            FileId::Checkpoint(c) => self.root.join(c.as_str()),
-        }
+        self.root.join(id.file_name())
     }
 
     pub fn root(&self) -> &Path {
@@ -104,6 +93,26 @@ impl FileSystemStorage {
         Ok(())
     }
 
+    pub fn write_atomic(&self, id: FileId, bytes: Bytes) -> io::Result<()> {
+        let path = self.file_path(id);
+        let mut tmp = path.clone().into_os_string();
+        tmp.push(".tmp");
+
+        let mut file = File::create(&tmp)?;
+        file.write_all(&bytes)?;
+        file.sync_all()?;
+
+        // The cached handle, if any, would still point to the replaced file.
+        let mut inner = self.inner.lock().unwrap();
+        std::fs::rename(&tmp, &path)?;
+        inner.remove(&id);
+
+        #[cfg(target_family = "unix")]
+        File::open(self.root.as_path())?.sync_all()?;
+
+        Ok(())
+    }
+
     pub fn offset(&self, id: FileId) -> io::Result<u64> {
         let mut file = self.load_or_create(id)?;
         file.seek(io::SeekFrom::End(0))
@@ -170,10 +179,6 @@ impl FileSystemStorage {
     }
 }
 
-fn chunk_filename_from(seq_number: usize, version: usize) -> String {
-    format!("chunk-{seq_number:06}.{version:06}")
-}
-
 #[cfg(target_os = "windows")]
 fn win_write_all(file: &File, bytes: &Bytes, mut offset: u64) -> io::Result<()> {
     let mut buffer = bytes.as_ref();
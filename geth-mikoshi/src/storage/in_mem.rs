@@ -4,13 +4,36 @@ use std::sync::{Arc, Mutex};
 
 use bytes::{BufMut, Bytes, BytesMut};
 
-use crate::constants::CHUNK_SIZE;
+#[cfg(any(test, feature = "test-utils"))]
+use crate::storage::FaultInjector;
 use crate::storage::{FileCategory, FileId, Storage};
 
 #[derive(Debug)]
 struct Internal {
     buffer: BytesMut,
     map: HashMap<FileId, BytesMut>,
+    #[cfg(any(test, feature = "test-utils"))]
+    faults: Option<FaultInjector>,
+}
+
+impl Internal {
+    fn check_write(&mut self, _id: FileId) -> io::Result<()> {
+        #[cfg(any(test, feature = "test-utils"))]
+        if let Some(faults) = self.faults.as_mut() {
+            faults.on_write(_id)?;
+        }
+
+        Ok(())
+    }
+
+    fn check_read(&self, _id: FileId) -> io::Result<()> {
+        #[cfg(any(test, feature = "test-utils"))]
+        if let Some(faults) = self.faults.as_ref() {
+            faults.on_read(_id)?;
+        }
+
+        Ok(())
+    }
 }
 
 impl Default for Internal {
@@ -18,6 +41,8 @@ impl Default for Internal {
         Self {
             buffer: BytesMut::new(),
             map: Default::default(),
+            #[cfg(any(test, feature = "test-utils"))]
+            faults: None,
         }
     }
 }
@@ -31,6 +56,26 @@ impl InMemoryStorage {
     pub fn new_storage() -> Storage {
         Storage::InMemory(InMemoryStorage::default())
     }
+
+    /// Testing only. See [`FaultInjector`].
+    #[cfg(any(test, feature = "test-utils"))]
+    pub fn new_storage_with_faults(faults: FaultInjector) -> Storage {
+        let storage = InMemoryStorage::default();
+        storage.inject_faults(faults);
+
+        Storage::InMemory(storage)
+    }
+
+    /// Testing only. Replaces the current fault injector, if any.
+    #[cfg(any(test, feature = "test-utils"))]
+    pub fn inject_faults(&self, faults: FaultInjector) {
+        self.inner.lock().unwrap().faults = Some(faults);
+    }
+
+    #[cfg(any(test, feature = "test-utils"))]
+    pub fn clear_faults(&self) {
+        self.inner.lock().unwrap().faults = None;
+    }
 }
 
 impl Default for InMemoryStorage {
@@ -44,6 +89,7 @@ impl Default for InMemoryStorage {
 impl InMemoryStorage {
     pub fn write_to(&self, id: FileId, offset: u64, bytes: Bytes) -> io::Result<()> {
         let mut inner = self.inner.lock().unwrap();
+        inner.check_write(id)?;
         let offset = offset as usize;
 
         if let Some(buffer) = inner.map.get_mut(&id) {
@@ -65,6 +111,12 @@ impl InMemoryStorage {
                     }
                 }
 
+                // Like on a file system, writing past the end of a chunk leaves a zeroed gap.
+                std::cmp::Ordering::Less if matches!(id, FileId::Chunk { .. }) => {
+                    buffer.resize(offset, 0);
+                    buffer.extend_from_slice(&bytes);
+                }
+
                 std::cmp::Ordering::Less => {
                     return Err(io::Error::new(
                         io::ErrorKind::InvalidInput,
@@ -74,8 +126,8 @@ impl InMemoryStorage {
             }
         } else {
             if let FileId::Chunk { .. } = id {
-                inner.buffer.resize(CHUNK_SIZE, 0);
-                inner.buffer[offset..offset + bytes.len()].copy_from_slice(&bytes);
+                inner.buffer.resize(offset, 0);
+                inner.buffer.extend_from_slice(&bytes);
             } else {
                 if offset != 0 {
                     return Err(io::Error::new(
@@ -104,6 +156,7 @@ impl InMemoryStorage {
         }
 
         let mut inner = self.inner.lock().unwrap();
+        inner.check_write(id)?;
 
         if let Some(buffer) = inner.map.get_mut(&id) {
             buffer.extend_from_slice(&bytes);
@@ -117,6 +170,14 @@ impl InMemoryStorage {
         Ok(())
     }
 
+    pub fn write_atomic(&self, id: FileId, bytes: Bytes) -> io::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.check_write(id)?;
+        inner.map.insert(id, BytesMut::from(bytes.as_ref()));
+
+        Ok(())
+    }
+
     pub fn offset(&self, id: FileId) -> io::Result<u64> {
         if let FileId::Chunk { .. } = id {
             return Err(io::Error::new(
@@ -139,6 +200,7 @@ impl InMemoryStorage {
 
     pub fn read_from(&self, id: FileId, offset: u64, len: usize) -> io::Result<Bytes> {
         let inner = self.inner.lock().unwrap();
+        inner.check_read(id)?;
 
         if let Some(buffer) = inner.map.get(&id) {
             let offset = offset as usize;
@@ -161,6 +223,7 @@ impl InMemoryStorage {
 
     pub fn read_all(&self, id: FileId) -> io::Result<Bytes> {
         let inner = self.inner.lock().unwrap();
+        inner.check_read(id)?;
 
         if let Some(buffer) = inner.map.get(&id) {
             return Ok(Bytes::copy_from_slice(&buffer[..]));
@@ -197,10 +260,17 @@ impl InMemoryStorage {
         ))
     }
 
-    pub fn list<C>(&self, _category: C) -> io::Result<Vec<C::Item>>
+    /// Files are named as they would be on the file system.
+    pub fn list<C>(&self, category: C) -> io::Result<Vec<C::Item>>
     where
         C: FileCategory,
     {
-        Ok(Vec::new())
+        let inner = self.inner.lock().unwrap();
+
+        Ok(inner
+            .map
+            .keys()
+            .filter_map(|id| category.parse(&id.file_name()))
+            .collect())
     }
 }
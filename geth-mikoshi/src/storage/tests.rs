@@ -0,0 +1,86 @@
+use std::io;
+
+use bytes::Bytes;
+
+use crate::storage::{FaultInjector, FileId, IoOp};
+use crate::InMemoryStorage;
+
+#[test]
+fn test_in_mem_fail_nth_write() -> io::Result<()> {
+    let storage =
+        InMemoryStorage::new_storage_with_faults(FaultInjector::default().fail_nth_write(2));
+
+    storage.write_to(FileId::IndexMap, 0, Bytes::from_static(b"foo"))?;
+    assert!(storage
+        .write_to(FileId::IndexMap, 3, Bytes::from_static(b"bar"))
+        .is_err());
+    storage.write_to(FileId::IndexMap, 3, Bytes::from_static(b"baz"))?;
+
+    assert_eq!(
+        Bytes::from_static(b"foobaz"),
+        storage.read_all(FileId::IndexMap)?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_in_mem_fail_reads_of() -> io::Result<()> {
+    let storage = InMemoryStorage::default();
+
+    storage.write_to(FileId::IndexMap, 0, Bytes::from_static(b"foo"))?;
+    storage.write_to(FileId::writer_chk(), 0, Bytes::from_static(b"bar"))?;
+
+    storage.inject_faults(FaultInjector::default().fail_reads_of(FileId::IndexMap));
+
+    assert!(storage.read_all(FileId::IndexMap).is_err());
+    assert!(storage.read_from(FileId::IndexMap, 0, 1).is_err());
+    assert_eq!(
+        Bytes::from_static(b"bar"),
+        storage.read_all(FileId::writer_chk())?
+    );
+
+    storage.clear_faults();
+    assert_eq!(
+        Bytes::from_static(b"foo"),
+        storage.read_all(FileId::IndexMap)?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_in_mem_fail_when() -> io::Result<()> {
+    let storage = InMemoryStorage::default();
+    storage.inject_faults(
+        FaultInjector::default()
+            .fail_when(|op, id| op == IoOp::Write && matches!(id, FileId::SSTable(_))),
+    );
+
+    storage.append(FileId::IndexMap, Bytes::from_static(b"foo"))?;
+    assert!(storage
+        .append(
+            FileId::ss_table(uuid::Uuid::new_v4()),
+            Bytes::from_static(b"bar")
+        )
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_fs_write_atomic_replaces_content() -> io::Result<()> {
+    let root = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+    let storage = crate::FileSystemStorage::new_storage(root.clone())?;
+
+    storage.write_to(FileId::Manifest, 0, Bytes::from_static(b"previous content"))?;
+    storage.write_atomic(FileId::Manifest, Bytes::from_static(b"new"))?;
+
+    assert_eq!(
+        Bytes::from_static(b"new"),
+        storage.read_all(FileId::Manifest)?
+    );
+    assert!(!root.join("manifest.tmp").exists());
+
+    std::fs::remove_dir_all(root)
+}
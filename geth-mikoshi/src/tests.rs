@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use bytes::Bytes;
+use geth_common::{ContentType, Record};
+use uuid::Uuid;
+
+use crate::compression::Compression;
+use crate::MikoshiStream;
+
+fn record(revision: u64) -> Record {
+    Record {
+        id: Uuid::new_v4(),
+        content_type: ContentType::Binary,
+        class: "foo".to_string(),
+        stream_name: "bar".to_string(),
+        position: revision,
+        revision,
+        data: Bytes::new(),
+        metadata: Bytes::new(),
+        created: Default::default(),
+        link: None,
+        epoch: 0,
+    }
+}
+
+#[tokio::test]
+async fn test_bounded_stream_caps_pending_records() -> eyre::Result<()> {
+    let (sender, mut stream) = MikoshiStream::bounded(4);
+
+    let producer = tokio::spawn(async move {
+        for revision in 0..100 {
+            sender.send(record(revision)).await?;
+        }
+
+        eyre::Ok(())
+    });
+
+    let mut expected = 0;
+    while let Some(record) = stream.next().await? {
+        // Gives the producer all the time it needs to fill the stream.
+        tokio::time::sleep(Duration::from_millis(1)).await;
+
+        assert!(stream.len() <= 4);
+        assert_eq!(expected, record.revision);
+        expected += 1;
+    }
+
+    assert_eq!(100, expected);
+    producer.await?
+}
+
+#[tokio::test]
+async fn test_bounded_stream_fails_producer_when_dropped() {
+    let (sender, stream) = MikoshiStream::bounded(1);
+
+    drop(stream);
+
+    assert!(sender.send(record(0)).await.is_err());
+}
+
+#[test]
+fn test_compression_round_trip() -> eyre::Result<()> {
+    let data = "{\"key\":\"value\"}".repeat(1_000).into_bytes();
+
+    for compression in [Compression::None, Compression::Lz4, Compression::Zstd] {
+        let compressed = compression.compress(&data)?;
+
+        if compression != Compression::None {
+            assert!(compressed.len() < data.len());
+        }
+
+        let decompressed = compression.decompress(Bytes::from(compressed))?;
+
+        assert_eq!(data, decompressed.as_ref());
+        assert_eq!(Some(compression), Compression::from_u8(compression as u8));
+        assert_eq!(compression, compression.to_string().parse().unwrap());
+    }
+
+    Ok(())
+}
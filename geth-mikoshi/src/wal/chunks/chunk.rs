@@ -1,11 +1,14 @@
 use std::cmp::Ordering;
+use std::sync::{Arc, Mutex};
 
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 use nom::bytes::complete::{tag, take_till1};
 use nom::IResult;
 use uuid::Uuid;
 
-use crate::constants::{CHUNK_HEADER_SIZE, CHUNK_SIZE};
-use crate::storage::FileId;
+use crate::constants::{CHUNK_FOOTER_SIZE, CHUNK_HEADER_SIZE};
+use crate::hashing::HashAlgorithm;
+use crate::storage::{FileId, Storage};
 use crate::wal::chunks::footer::ChunkFooter;
 use crate::wal::chunks::header::ChunkHeader;
 
@@ -83,15 +86,96 @@ impl ChunkInfo {
         Ok((input, info))
     }
 }
+/// Where the entries kept by a scavenge ended up in the chunk file. Scavenging doesn't change the
+/// log position of the entries it keeps, but they are packed together at the start of the chunk.
+#[derive(Clone, Debug, Default)]
+pub struct PositionMap {
+    /// Log position and offset from the start of the chunk data of every entry, in log order.
+    entries: Arc<Vec<(u64, u32)>>,
+}
+
+impl PositionMap {
+    pub const ENTRY_SIZE: usize = size_of::<u64>() + size_of::<u32>();
+
+    pub fn new(entries: Vec<(u64, u32)>) -> Self {
+        Self {
+            entries: Arc::new(entries),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn size(&self) -> usize {
+        self.len() * Self::ENTRY_SIZE
+    }
+
+    /// Offset from the start of the chunk data of the entry at `log_position`, `None` if it was
+    /// dropped.
+    pub fn offset_of(&self, log_position: u64) -> Option<u32> {
+        self.entries
+            .binary_search_by_key(&log_position, |(pos, _)| *pos)
+            .ok()
+            .map(|idx| self.entries[idx].1)
+    }
+
+    /// Log position of the first entry kept at or after `log_position`.
+    pub fn next_position_from(&self, log_position: u64) -> Option<u64> {
+        let idx = self.entries.partition_point(|(pos, _)| *pos < log_position);
+        self.entries.get(idx).map(|(pos, _)| *pos)
+    }
+
+    pub fn put(&self, buf: &mut BytesMut) {
+        for (position, offset) in self.entries.iter() {
+            buf.put_u64_le(*position);
+            buf.put_u32_le(*offset);
+        }
+    }
+
+    pub fn get(mut buf: Bytes) -> Self {
+        let mut entries = Vec::with_capacity(buf.remaining() / Self::ENTRY_SIZE);
+
+        while buf.remaining() >= Self::ENTRY_SIZE {
+            entries.push((buf.get_u64_le(), buf.get_u32_le()));
+        }
+
+        Self::new(entries)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Chunk {
     pub info: ChunkInfo,
     pub header: ChunkHeader,
     pub footer: Option<ChunkFooter>,
+    /// Only set on scavenged chunks.
+    pub position_map: Option<PositionMap>,
+    pub(crate) file: Arc<ChunkFile>,
+}
+
+/// Shared by every copy of a [`Chunk`]. A chunk file replaced by a scavenge is only removed once
+/// the last copy is dropped, readers going through the previous version keep reading it.
+#[derive(Debug, Default)]
+pub(crate) struct ChunkFile {
+    retired: Mutex<Option<(Storage, FileId)>>,
+}
+
+impl Drop for ChunkFile {
+    fn drop(&mut self) {
+        if let Some((storage, file_id)) = self.retired.get_mut().ok().and_then(Option::take) {
+            // A file left behind is removed the next time the container is loaded.
+            let _ = storage.remove(file_id);
+        }
+    }
 }
 
 impl Chunk {
-    pub fn new(num: usize) -> Self {
+    pub fn new(num: usize, chunk_size: usize, hash_algorithm: HashAlgorithm) -> Self {
         Self {
             info: ChunkInfo {
                 seq_num: num,
@@ -99,17 +183,35 @@ impl Chunk {
             },
             header: ChunkHeader {
                 version: 0,
-                chunk_size: CHUNK_SIZE,
+                chunk_size,
                 chunk_start_number: num,
                 chunk_end_number: num,
                 chunk_id: Uuid::new_v4(),
+                hash_algorithm: hash_algorithm as u8,
             },
             footer: None,
+            position_map: None,
+            file: Default::default(),
         }
     }
 
+    /// Removes the file of this chunk once no copy of it is left.
+    pub(crate) fn retire(&self, storage: &Storage) {
+        if let Ok(mut retired) = self.file.retired.lock() {
+            *retired = Some((storage.clone(), self.file_id()));
+        }
+    }
+
+    /// Chunks of a database all have the size and the hash algorithm of the first one.
     pub fn next_chunk(&self) -> Self {
-        Self::new(self.info.seq_num + 1)
+        let mut chunk = Self::new(
+            self.info.seq_num + 1,
+            self.header.chunk_size,
+            HashAlgorithm::default(),
+        );
+
+        chunk.header.hash_algorithm = self.header.hash_algorithm;
+        chunk
     }
 
     pub fn file_id(&self) -> FileId {
@@ -124,12 +226,55 @@ impl Chunk {
         CHUNK_HEADER_SIZE as u64 + self.local_physical_position(log_position)
     }
 
+    /// Where the entry at `log_position` is in the chunk file, `None` if a scavenge dropped it.
+    pub fn entry_raw_position(&self, log_position: u64) -> Option<u64> {
+        match &self.position_map {
+            None => Some(self.raw_position(log_position)),
+            Some(map) => map
+                .offset_of(log_position)
+                .map(|offset| CHUNK_HEADER_SIZE as u64 + offset as u64),
+        }
+    }
+
+    /// Log position of the first entry of this chunk at or after `log_position`. Entries dropped by
+    /// a scavenge are skipped.
+    pub fn next_entry_position(&self, log_position: u64) -> u64 {
+        match &self.position_map {
+            None => log_position,
+            Some(map) => map
+                .next_position_from(log_position)
+                .unwrap_or_else(|| self.end_position()),
+        }
+    }
+
     pub fn start_position(&self) -> u64 {
-        self.header.chunk_start_number as u64 * CHUNK_SIZE as u64
+        self.header.chunk_start_number as u64 * self.header.chunk_size as u64
     }
 
     pub fn end_position(&self) -> u64 {
-        (self.header.chunk_end_number as u64 + 1) * CHUNK_SIZE as u64
+        (self.header.chunk_end_number as u64 + 1) * self.header.chunk_size as u64
+    }
+
+    /// Log position past which no entry can be written in this chunk. The end of the chunk file is
+    /// reserved for the footer.
+    pub fn max_data_position(&self) -> u64 {
+        self.start_position()
+            + (self.header.chunk_size - CHUNK_HEADER_SIZE - CHUNK_FOOTER_SIZE) as u64
+    }
+
+    /// Where the footer is in the chunk file.
+    pub fn footer_offset(&self) -> u64 {
+        (self.header.chunk_size - CHUNK_FOOTER_SIZE) as u64
+    }
+
+    /// Log position right after the last entry of this chunk. Anything between that position and
+    /// the end of the chunk is unused space left when the chunk was closed.
+    pub fn data_end_position(&self) -> u64 {
+        if let Some(footer) = &self.footer {
+            return self.start_position() + footer.logical_data_size as u64;
+        }
+
+        self.max_data_position()
     }
 
     pub fn contains_log_position(&self, log_position: u64) -> bool {
@@ -1,6 +1,16 @@
-use crate::constants::CHUNK_FOOTER_SIZE;
+use crate::constants::{CHUNK_FOOTER_SIZE, CHUNK_HEADER_SIZE};
 use bitflags::bitflags;
 use bytes::{Buf, BufMut, Bytes, BytesMut};
+use sha2::{Digest, Sha512};
+
+use crate::storage::{FileId, Storage};
+use crate::wal::chunks::chunk::PositionMap;
+
+/// Size of the hash stored at the end of the footer.
+pub const CHUNK_HASH_SIZE: usize = 16;
+
+/// Bytes of chunk data hashed at a time, so a completed chunk is never loaded in memory at once.
+const HASH_READ_SIZE: usize = 1_024 * 1_024;
 
 bitflags! {
     pub struct FooterFlags: u8 {
@@ -14,6 +24,8 @@ pub struct ChunkFooter {
     pub flags: FooterFlags,
     pub physical_data_size: usize,
     pub logical_data_size: usize,
+    /// Entries of the position map following the data of a scavenged chunk.
+    pub map_count: usize,
     pub hash: Bytes,
 }
 
@@ -28,13 +40,15 @@ impl ChunkFooter {
 
         let physical_data_size = buf.get_u32_le() as usize;
         let logical_data_size = buf.get_u64_le() as usize;
+        let map_count = buf.get_u32_le() as usize;
 
-        buf.advance(buf.remaining() - 16);
+        buf.advance(buf.remaining() - CHUNK_HASH_SIZE);
 
         Some(ChunkFooter {
             flags,
             physical_data_size,
             logical_data_size,
+            map_count,
             hash: buf,
         })
     }
@@ -44,12 +58,42 @@ impl ChunkFooter {
         buf.put_u8(self.flags.bits);
         buf.put_u32_le(self.physical_data_size as u32);
         buf.put_u64_le(self.logical_data_size as u64);
+        buf.put_u32_le(self.map_count as u32);
 
         let written = buf.len() - len;
-        let free_space_size = CHUNK_FOOTER_SIZE - written - 16;
+        let free_space_size = CHUNK_FOOTER_SIZE - written - CHUNK_HASH_SIZE;
 
         // Unused space.
         buf.put_bytes(0, free_space_size);
         buf.put(self.hash.clone());
     }
+
+    /// Chunks completed before footers recorded a hash have an all-zero one.
+    pub fn has_hash(&self) -> bool {
+        self.hash.iter().any(|b| *b != 0)
+    }
+}
+
+/// Hash of the `physical_data_size` bytes of data following the header of a chunk file and of
+/// the `map_count` entries of the position map right after, the first [`CHUNK_HASH_SIZE`] bytes of
+/// their SHA-512.
+pub fn chunk_data_hash(
+    storage: &Storage,
+    id: FileId,
+    physical_data_size: usize,
+    map_count: usize,
+) -> std::io::Result<Bytes> {
+    let mut hasher = Sha512::new();
+    let mut offset = CHUNK_HEADER_SIZE;
+    let end = CHUNK_HEADER_SIZE + physical_data_size + map_count * PositionMap::ENTRY_SIZE;
+
+    while offset < end {
+        let len = HASH_READ_SIZE.min(end - offset);
+        hasher.update(storage.read_from(id, offset as u64, len)?);
+        offset += len;
+    }
+
+    Ok(Bytes::copy_from_slice(
+        &hasher.finalize()[..CHUNK_HASH_SIZE],
+    ))
 }
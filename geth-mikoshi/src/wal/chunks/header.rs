@@ -9,6 +9,8 @@ pub struct ChunkHeader {
     pub chunk_start_number: usize,
     pub chunk_end_number: usize,
     pub chunk_id: Uuid,
+    /// Raw [`crate::hashing::HashAlgorithm`], zeroed headers default to SHA-512.
+    pub hash_algorithm: u8,
 }
 
 impl ChunkHeader {
@@ -18,6 +20,7 @@ impl ChunkHeader {
         buf.put_u32_le(self.chunk_start_number as u32);
         buf.put_u32_le(self.chunk_end_number as u32);
         buf.put_u128_le(self.chunk_id.as_u128());
+        buf.put_u8(self.hash_algorithm);
         buf.put_bytes(0, CHUNK_HEADER_SIZE - buf.len());
     }
 
@@ -27,6 +30,7 @@ impl ChunkHeader {
         let chunk_start_number = buf.get_u32_le() as usize;
         let chunk_end_number = buf.get_u32_le() as usize;
         let chunk_id = Uuid::from_u128(buf.get_u128_le());
+        let hash_algorithm = buf.get_u8();
 
         Self {
             version,
@@ -34,6 +38,7 @@ impl ChunkHeader {
             chunk_start_number,
             chunk_end_number,
             chunk_id,
+            hash_algorithm,
         }
     }
 }
@@ -1,22 +1,26 @@
-use bytes::BytesMut;
+use bytes::{BufMut, BytesMut};
 use std::collections::BTreeMap;
 use std::sync::{Arc, RwLock};
-use std::{io, mem};
+use std::{io, iter, mem};
 
-use crate::constants::{CHUNK_FOOTER_SIZE, CHUNK_HEADER_SIZE, CHUNK_SIZE};
+use crate::constants::{CHUNK_FOOTER_SIZE, CHUNK_HEADER_SIZE, MAX_CHUNK_SIZE, MIN_CHUNK_SIZE};
+use crate::hashing::HashAlgorithm;
 use crate::storage::{FileCategory, Storage};
 use crate::wal::chunks::chunk::ChunkInfo;
-use crate::wal::chunks::footer::{ChunkFooter, FooterFlags};
+use crate::wal::chunks::footer::{chunk_data_hash, ChunkFooter, FooterFlags};
 use crate::wal::chunks::header::ChunkHeader;
 
 mod chunk;
 mod footer;
 mod header;
+mod scavenge;
 
 #[cfg(test)]
 mod tests;
 
-pub use chunk::Chunk;
+pub use crate::constants::CHUNK_SIZE as DEFAULT_CHUNK_SIZE;
+pub use chunk::{Chunk, PositionMap};
+pub use scavenge::{scavenge, Scavenged};
 
 #[derive(Copy, Clone, Debug)]
 pub struct Chunks;
@@ -28,6 +32,29 @@ impl FileCategory for Chunks {
     }
 }
 
+#[derive(Clone, Copy, Debug)]
+pub struct ChunkOptions {
+    /// Size of the chunks when the database is created. An existing database keeps the size it
+    /// was created with, recorded in the chunk headers.
+    pub chunk_size: usize,
+    /// Checks the data of every completed chunk against the hash recorded in its footer. Chunks
+    /// completed before footers recorded a hash are trusted.
+    pub verify: bool,
+    /// Algorithm computing the stream keys of a new database. An existing database keeps the
+    /// algorithm recorded in its chunk headers.
+    pub hash_algorithm: HashAlgorithm,
+}
+
+impl Default for ChunkOptions {
+    fn default() -> Self {
+        Self {
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            verify: false,
+            hash_algorithm: HashAlgorithm::default(),
+        }
+    }
+}
+
 #[derive(Debug)]
 struct ContainerInner {
     closed: Vec<Chunk>,
@@ -42,44 +69,110 @@ pub struct ChunkContainer {
 
 impl ChunkContainer {
     pub fn load(storage: Storage) -> io::Result<ChunkContainer> {
+        Self::load_with(storage, ChunkOptions::default())
+    }
+
+    pub fn load_with(storage: Storage, options: ChunkOptions) -> io::Result<ChunkContainer> {
+        check_chunk_size(options.chunk_size)?;
+
         let mut buffer = BytesMut::new();
         let mut sorted_chunks = BTreeMap::<usize, ChunkInfo>::new();
+        let mut replaced = Vec::new();
 
         for info in storage.list(Chunks)? {
             if let Some(chunk) = sorted_chunks.get_mut(&info.seq_num) {
-                if chunk.version < info.version {
-                    *chunk = info;
+                let (older, newer) = if chunk.version < info.version {
+                    (*chunk, info)
+                } else {
+                    (info, *chunk)
+                };
+
+                // A scavenge stopped before the footer of the new version was written leaves it
+                // torn, the version it was replacing is still whole.
+                if is_completed(&storage, newer)? {
+                    *chunk = newer;
+                    replaced.push(older);
+                } else {
+                    *chunk = older;
+                    replaced.push(newer);
                 }
             } else {
                 sorted_chunks.insert(info.seq_num, info);
             }
         }
 
+        // Versions a scavenge replaced while they were still read, or didn't get to complete.
+        for info in replaced {
+            storage.remove(info.file_id())?;
+        }
+
         let mut chunks = Vec::new();
         for info in sorted_chunks.into_values() {
             let header = storage.read_from(info.file_id(), 0, CHUNK_HEADER_SIZE)?;
             let header = ChunkHeader::get(header);
+
+            check_chunk_size(header.chunk_size).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("chunk {} header is corrupted: {e}", info.seq_num),
+                )
+            })?;
+
             let footer = storage.read_from(
                 info.file_id(),
-                (CHUNK_SIZE - CHUNK_FOOTER_SIZE) as u64,
+                (header.chunk_size - CHUNK_FOOTER_SIZE) as u64,
                 CHUNK_FOOTER_SIZE,
             )?;
             let footer = ChunkFooter::get(footer);
+
+            if let Some(footer) = footer.as_ref().filter(|f| options.verify && f.has_hash()) {
+                let actual = chunk_data_hash(
+                    &storage,
+                    info.file_id(),
+                    footer.physical_data_size,
+                    footer.map_count,
+                )?;
+
+                if actual != footer.hash {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "chunk {} is corrupted: its data doesn't match the hash recorded in its footer",
+                            info.seq_num
+                        ),
+                    ));
+                }
+            }
+
+            let position_map = match footer.as_ref() {
+                Some(footer) if footer.flags.contains(FooterFlags::IS_MAP_12_BYTES) => {
+                    let map = storage.read_from(
+                        info.file_id(),
+                        (CHUNK_HEADER_SIZE + footer.physical_data_size) as u64,
+                        footer.map_count * PositionMap::ENTRY_SIZE,
+                    )?;
+
+                    Some(PositionMap::get(map))
+                }
+
+                _ => None,
+            };
+
             let chunk = Chunk {
                 info,
                 header,
                 footer,
+                position_map,
+                file: Default::default(),
             };
 
             chunks.push(chunk);
         }
 
         if chunks.is_empty() {
-            let chunk = Chunk::new(0);
-
-            chunk.header.put(&mut buffer);
-            storage.write_to(chunk.file_id(), 0, buffer.freeze())?;
+            let chunk = Chunk::new(0, options.chunk_size, options.hash_algorithm);
 
+            create_chunk_file(&storage, &mut buffer, &chunk)?;
             chunks.push(chunk);
         }
 
@@ -122,18 +215,35 @@ impl ChunkContainer {
         Ok(None)
     }
 
+    /// The data of the ongoing chunk is hashed before the container is locked, so reads of the
+    /// log aren't held up in the meantime. Nothing is written past `position` while it's
+    /// hashed: only the writer completes chunks.
     pub fn new_chunk(&self, buffer: &mut BytesMut, position: u64) -> eyre::Result<Chunk> {
+        let ongoing = self
+            .inner
+            .read()
+            .map_err(|_e| eyre::eyre!("failed to obtained a read-lock on the chunk container"))?
+            .ongoing
+            .clone();
+
+        let physical_data_size = ongoing.raw_position(position) as usize - CHUNK_HEADER_SIZE;
+        let hash = chunk_data_hash(&self.storage, ongoing.file_id(), physical_data_size, 0)?;
+
         let mut inner = self
             .inner
             .write()
             .map_err(|_e| eyre::eyre!("failed to obtained a write-lock on the chunk container"))?;
 
-        let physical_data_size = inner.ongoing.raw_position(position) as usize - CHUNK_HEADER_SIZE;
+        if inner.ongoing.info != ongoing.info {
+            eyre::bail!("chunk {} was completed concurrently", ongoing.info.seq_num);
+        }
+
         let footer = ChunkFooter {
             flags: FooterFlags::IS_COMPLETED,
             physical_data_size,
             logical_data_size: physical_data_size,
-            hash: Default::default(),
+            map_count: 0,
+            hash,
         };
 
         footer.put(buffer);
@@ -141,11 +251,13 @@ impl ChunkContainer {
 
         self.storage.write_to(
             inner.ongoing.file_id(),
-            (CHUNK_SIZE - CHUNK_FOOTER_SIZE) as u64,
+            inner.ongoing.footer_offset(),
             buffer.split().freeze(),
         )?;
 
         let new_chunk = inner.ongoing.next_chunk();
+        create_chunk_file(&self.storage, buffer, &new_chunk)?;
+
         let old_chunk = mem::replace(&mut inner.ongoing, new_chunk.clone());
 
         inner.closed.push(old_chunk);
@@ -156,4 +268,100 @@ impl ChunkContainer {
     pub fn storage(&self) -> &Storage {
         &self.storage
     }
+
+    /// Size of the chunks of the database, as recorded in the ongoing chunk.
+    pub fn chunk_size(&self) -> eyre::Result<usize> {
+        let inner = self
+            .inner
+            .read()
+            .map_err(|_e| eyre::eyre!("failed to obtained a read-lock on the chunk container"))?;
+
+        Ok(inner.ongoing.header.chunk_size)
+    }
+
+    /// Hash algorithm the database uses, as recorded in the ongoing chunk.
+    pub fn hash_algorithm(&self) -> eyre::Result<HashAlgorithm> {
+        let inner = self
+            .inner
+            .read()
+            .map_err(|_e| eyre::eyre!("failed to obtained a read-lock on the chunk container"))?;
+
+        let raw = inner.ongoing.header.hash_algorithm;
+        HashAlgorithm::from_u8(raw)
+            .ok_or_else(|| eyre::eyre!("unknown hash algorithm {raw} recorded in chunk header"))
+    }
+
+    /// Records a new hash algorithm in every chunk header. The index must be rebuilt afterward.
+    pub fn record_hash_algorithm(&self, algorithm: HashAlgorithm) -> eyre::Result<()> {
+        let mut inner = self
+            .inner
+            .write()
+            .map_err(|_e| eyre::eyre!("failed to obtained a write-lock on the chunk container"))?;
+
+        let inner = &mut *inner;
+        let mut buffer = BytesMut::new();
+
+        for chunk in inner
+            .closed
+            .iter_mut()
+            .chain(iter::once(&mut inner.ongoing))
+        {
+            chunk.header.hash_algorithm = algorithm as u8;
+            chunk.header.put(&mut buffer);
+            self.storage
+                .write_to(chunk.file_id(), 0, buffer.split().freeze())?;
+        }
+
+        Ok(())
+    }
+}
+
+fn check_chunk_size(chunk_size: usize) -> io::Result<()> {
+    if !(MIN_CHUNK_SIZE..=MAX_CHUNK_SIZE).contains(&chunk_size) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "chunk size of {chunk_size} bytes is not between {MIN_CHUNK_SIZE} and {MAX_CHUNK_SIZE} bytes"
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Whether a chunk file was written up to a completed footer.
+fn is_completed(storage: &Storage, info: ChunkInfo) -> io::Result<bool> {
+    let len = storage.len(info.file_id())?;
+
+    if len < CHUNK_HEADER_SIZE {
+        return Ok(false);
+    }
+
+    let header = ChunkHeader::get(storage.read_from(info.file_id(), 0, CHUNK_HEADER_SIZE)?);
+
+    if check_chunk_size(header.chunk_size).is_err() || len < header.chunk_size {
+        return Ok(false);
+    }
+
+    let footer = storage.read_from(
+        info.file_id(),
+        (header.chunk_size - CHUNK_FOOTER_SIZE) as u64,
+        CHUNK_FOOTER_SIZE,
+    )?;
+
+    Ok(ChunkFooter::get(footer).is_some())
+}
+
+/// Writes the header of a new chunk and zeroes its footer, so the chunk file has its full size
+/// right away.
+fn create_chunk_file(storage: &Storage, buffer: &mut BytesMut, chunk: &Chunk) -> io::Result<()> {
+    chunk.header.put(buffer);
+    storage.write_to(chunk.file_id(), 0, buffer.split().freeze())?;
+
+    buffer.put_bytes(0, CHUNK_FOOTER_SIZE);
+    storage.write_to(
+        chunk.file_id(),
+        chunk.footer_offset(),
+        buffer.split().freeze(),
+    )
 }
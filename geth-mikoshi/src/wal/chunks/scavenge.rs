@@ -0,0 +1,142 @@
+use bytes::BytesMut;
+
+use crate::constants::CHUNK_HEADER_SIZE;
+use crate::wal::chunks::chunk::{ChunkInfo, PositionMap};
+use crate::wal::chunks::footer::{chunk_data_hash, ChunkFooter, FooterFlags};
+use crate::wal::chunks::{Chunk, ChunkContainer};
+use crate::wal::{LogEntry, LogReader};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Scavenged {
+    /// Chunks a new version was written for.
+    pub chunks: usize,
+    pub dropped_entries: usize,
+    /// Bytes of chunk data that are no longer used.
+    pub reclaimed: u64,
+}
+
+/// Rewrites every completed chunk without the entries `keep` returns `false` for. Each rewritten
+/// chunk gets a new version replacing the previous one. Kept entries don't change log position, a
+/// position map appended to the chunk data tells where they are in the new version. The ongoing
+/// chunk is left untouched.
+///
+/// Reads already going through the previous version of a rewritten chunk carry on with it, its
+/// file is only removed once they are done. The next ones go through the new version and no
+/// longer find the dropped entries.
+pub fn scavenge<F>(container: &ChunkContainer, mut keep: F) -> eyre::Result<Scavenged>
+where
+    F: FnMut(&LogEntry) -> bool,
+{
+    let closed = {
+        let inner = container
+            .inner
+            .read()
+            .map_err(|_e| eyre::eyre!("failed to obtained a read-lock on the chunk container"))?;
+
+        inner.closed.clone()
+    };
+
+    let reader = LogReader::new(container.clone());
+    let storage = container.storage();
+    let mut buffer = BytesMut::new();
+    let mut scavenged = Scavenged::default();
+
+    for chunk in closed {
+        let Some(footer) = chunk.footer.as_ref() else {
+            continue;
+        };
+
+        let mut entries = reader.entries(chunk.start_position(), chunk.data_end_position());
+        let mut kept = Vec::new();
+        let mut dropped = 0usize;
+
+        while let Some(entry) = entries.next()? {
+            if keep(&entry) {
+                kept.push(entry);
+            } else {
+                dropped += 1;
+            }
+        }
+
+        if dropped == 0 {
+            continue;
+        }
+
+        let info = ChunkInfo {
+            seq_num: chunk.info.seq_num,
+            version: chunk.info.version + 1,
+        };
+
+        chunk.header.put(&mut buffer);
+        storage.write_to(info.file_id(), 0, buffer.split().freeze())?;
+
+        let mut map = Vec::with_capacity(kept.len());
+        let mut offset = 0usize;
+        for entry in kept {
+            let Some(raw_position) = chunk.entry_raw_position(entry.position) else {
+                eyre::bail!("log position {} was scavenged", entry.position);
+            };
+
+            let record = storage.read_from(chunk.file_id(), raw_position, entry.size())?;
+            storage.write_to(info.file_id(), (CHUNK_HEADER_SIZE + offset) as u64, record)?;
+
+            map.push((entry.position, offset as u32));
+            offset += entry.size();
+        }
+
+        let position_map = PositionMap::new(map);
+        position_map.put(&mut buffer);
+        storage.write_to(
+            info.file_id(),
+            (CHUNK_HEADER_SIZE + offset) as u64,
+            buffer.split().freeze(),
+        )?;
+
+        let new_footer = ChunkFooter {
+            flags: FooterFlags::IS_COMPLETED | FooterFlags::IS_MAP_12_BYTES,
+            physical_data_size: offset,
+            logical_data_size: footer.logical_data_size,
+            map_count: position_map.len(),
+            hash: chunk_data_hash(storage, info.file_id(), offset, position_map.len())?,
+        };
+
+        new_footer.put(&mut buffer);
+        storage.write_to(
+            info.file_id(),
+            chunk.footer_offset(),
+            buffer.split().freeze(),
+        )?;
+
+        let new_chunk = Chunk {
+            info,
+            header: chunk.header,
+            footer: Some(new_footer),
+            position_map: Some(position_map.clone()),
+            file: Default::default(),
+        };
+
+        {
+            let mut inner = container.inner.write().map_err(|_e| {
+                eyre::eyre!("failed to obtained a write-lock on the chunk container")
+            })?;
+
+            if let Some(current) = inner
+                .closed
+                .iter_mut()
+                .find(|c| c.info.seq_num == info.seq_num)
+            {
+                *current = new_chunk;
+            }
+        }
+
+        chunk.retire(storage);
+
+        scavenged.chunks += 1;
+        scavenged.dropped_entries += dropped;
+        scavenged.reclaimed += footer
+            .physical_data_size
+            .saturating_sub(offset + position_map.size()) as u64;
+    }
+
+    Ok(scavenged)
+}
@@ -1,10 +1,18 @@
-use std::vec;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::{io, vec};
 
-use crate::storage::InMemoryStorage;
-use crate::wal::chunks::ChunkContainer;
-use crate::wal::{LogEntries, LogReader, LogWriter};
-use bytes::{Bytes, BytesMut};
+use crate::constants::{CHUNK_HEADER_SIZE, MIN_CHUNK_SIZE};
+use crate::hashing::{crc32c, HashAlgorithm};
+use crate::storage::{FaultInjector, FileId, FileSystemStorage, InMemoryStorage, IoOp, Storage};
+use crate::wal::chunks::header::ChunkHeader;
+use crate::wal::chunks::{scavenge, ChunkContainer, ChunkOptions};
+use crate::wal::{
+    BackwardEntries, LogCursor, LogEntries, LogEntry, LogReader, LogWriter, LOG_ENTRY_HEADER_SIZE,
+};
+use bytes::{BufMut, Bytes, BytesMut};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 struct RawEntries {
     entries: vec::IntoIter<Bytes>,
@@ -50,6 +58,13 @@ struct Foobar {
     value: u32,
 }
 
+fn verified() -> ChunkOptions {
+    ChunkOptions {
+        verify: true,
+        ..ChunkOptions::default()
+    }
+}
+
 fn generate_bytes() -> Bytes {
     let mut bytes = Vec::new();
 
@@ -71,6 +86,8 @@ fn test_wal_chunk_iso() -> eyre::Result<()> {
 
     writer.append(&mut entries)?;
 
+    assert_eq!(writer.writer_position(), reader.get_writer_checkpoint()?);
+
     let entry = reader.read_at(0)?;
 
     assert_eq!(0, entry.position);
@@ -79,3 +96,623 @@ fn test_wal_chunk_iso() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_chunk_header_records_hash_algorithm() -> eyre::Result<()> {
+    let storage = InMemoryStorage::new_storage();
+    let container = ChunkContainer::load(storage.clone())?;
+
+    assert_eq!(HashAlgorithm::Sha512, container.hash_algorithm()?);
+
+    container.record_hash_algorithm(HashAlgorithm::Xxh3)?;
+    assert_eq!(HashAlgorithm::Xxh3, container.hash_algorithm()?);
+
+    let header = storage.read_from(FileId::chunk(0, 0), 0, CHUNK_HEADER_SIZE)?;
+    assert_eq!(
+        HashAlgorithm::Xxh3 as u8,
+        ChunkHeader::get(header).hash_algorithm
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_databases_keep_their_own_hash_algorithm() -> eyre::Result<()> {
+    let mut containers = vec![];
+
+    for hash_algorithm in [HashAlgorithm::Xxh3, HashAlgorithm::Fnv1a] {
+        let options = ChunkOptions {
+            chunk_size: MIN_CHUNK_SIZE,
+            hash_algorithm,
+            ..ChunkOptions::default()
+        };
+
+        containers.push((
+            hash_algorithm,
+            ChunkContainer::load_with(InMemoryStorage::new_storage(), options)?,
+        ));
+    }
+
+    for (hash_algorithm, container) in &containers {
+        let mut writer = LogWriter::load(container.clone(), BytesMut::new())?;
+
+        writer.append(&mut RawEntries::new(vec![generate_bytes(); 1_000]))?;
+
+        assert!(container.ongoing()?.info.seq_num > 0);
+        assert_eq!(*hash_algorithm, container.hash_algorithm()?);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_hash_algorithm_round_trip() {
+    for algorithm in [
+        HashAlgorithm::Sha512,
+        HashAlgorithm::Xxh3,
+        HashAlgorithm::Fnv1a,
+    ] {
+        assert_eq!(Some(algorithm), HashAlgorithm::from_u8(algorithm as u8));
+        assert_eq!(Ok(algorithm), algorithm.to_string().parse());
+        assert_eq!(algorithm.hash("foobar"), algorithm.hash("foobar"));
+    }
+
+    assert_ne!(
+        HashAlgorithm::Sha512.hash("foobar"),
+        HashAlgorithm::Xxh3.hash("foobar")
+    );
+}
+
+#[test]
+fn test_wal_entries_are_read_in_order_across_chunks() -> eyre::Result<()> {
+    let storage = InMemoryStorage::new_storage();
+    let container = ChunkContainer::load(storage.clone())?;
+    let reader = LogReader::new(container.clone());
+    let mut writer = LogWriter::load(container.clone(), BytesMut::new())?;
+    let payload_size = 5 * 1_024 * 1_024;
+    let mut expected = Vec::new();
+
+    // Enough entries for the writer to leave unused space at the end of the first chunk.
+    for i in 0..60u32 {
+        let stream = i % 3;
+        let mut payload = BytesMut::zeroed(payload_size);
+        payload[..4].copy_from_slice(&stream.to_le_bytes());
+        payload[4..8].copy_from_slice(&i.to_le_bytes());
+
+        let receipt = writer.append(&mut RawEntries::new(vec![payload.freeze()]))?;
+        expected.push((receipt.start_position, stream, i));
+    }
+
+    assert!(storage.exists(FileId::chunk(1, 0))?);
+
+    let mut entries = reader.entries(0, writer.writer_position());
+    let mut actual = Vec::new();
+
+    while let Some(entry) = entries.next()? {
+        let stream = u32::from_le_bytes(entry.payload[..4].try_into()?);
+        let i = u32::from_le_bytes(entry.payload[4..8].try_into()?);
+        actual.push((entry.position, stream, i));
+    }
+
+    assert!(actual.windows(2).all(|w| w[0].0 < w[1].0));
+    assert_eq!(expected, actual);
+
+    Ok(())
+}
+
+#[test]
+fn test_wal_entries_resume_from_saved_cursor() -> eyre::Result<()> {
+    let storage = InMemoryStorage::new_storage();
+    let container = ChunkContainer::load(storage.clone())?;
+    let mut writer = LogWriter::load(container.clone(), BytesMut::new())?;
+
+    for i in 0..20u32 {
+        writer.append(&mut RawEntries::new(vec![Bytes::copy_from_slice(
+            &i.to_le_bytes(),
+        )]))?;
+    }
+
+    let mut processed = Vec::new();
+
+    {
+        let reader = LogReader::new(container);
+        let cursor = LogCursor::load(&storage, FileId::index_chk())?;
+        let mut entries = reader.entries_from(cursor)?;
+
+        for _ in 0..10 {
+            let entry = entries.next()?.unwrap();
+            processed.push(u32::from_le_bytes(entry.payload[..].try_into()?));
+        }
+
+        entries.cursor().save(&storage, FileId::index_chk())?;
+        // Crashes before processing anything else.
+        entries.next()?;
+    }
+
+    let reader = LogReader::new(ChunkContainer::load(storage.clone())?);
+    let cursor = LogCursor::load(&storage, FileId::index_chk())?;
+    let mut entries = reader.entries_from(cursor)?;
+
+    while let Some(entry) = entries.next()? {
+        processed.push(u32::from_le_bytes(entry.payload[..].try_into()?));
+    }
+
+    assert_eq!((0..20).collect::<Vec<_>>(), processed);
+    assert_eq!(writer.writer_position(), entries.cursor().position);
+
+    Ok(())
+}
+
+#[test]
+fn test_wal_entries_are_read_backward_across_chunks() -> eyre::Result<()> {
+    let storage = InMemoryStorage::new_storage();
+    let options = ChunkOptions {
+        chunk_size: MIN_CHUNK_SIZE,
+        ..ChunkOptions::default()
+    };
+    let container = ChunkContainer::load_with(storage, options)?;
+    let reader = LogReader::new(container.clone());
+    let mut writer = LogWriter::load(container.clone(), BytesMut::new())?;
+    let mut positions = Vec::new();
+
+    for i in 0..5_000u32 {
+        let receipt = writer.append(&mut RawEntries::new(vec![Bytes::copy_from_slice(
+            &i.to_le_bytes(),
+        )]))?;
+
+        positions.push(receipt.start_position);
+    }
+
+    assert!(
+        container
+            .find(writer.writer_position())?
+            .unwrap()
+            .info
+            .seq_num
+            >= 1
+    );
+
+    // Starting in the middle of an entry returns the ones positioned before it.
+    let end = positions[2_500] + 1;
+    let mut entries = BackwardEntries::new(reader.clone(), end);
+    let mut actual = Vec::new();
+
+    while let Some(entry) = entries.next()? {
+        actual.push(u32::from_le_bytes(entry.payload[..].try_into()?));
+    }
+
+    assert_eq!((0..=2_500).rev().collect::<Vec<_>>(), actual);
+
+    let mut entries = BackwardEntries::new(reader, writer.writer_position());
+    let mut count = 0;
+
+    while entries.next()?.is_some() {
+        count += 1;
+    }
+
+    assert_eq!(5_000, count);
+
+    Ok(())
+}
+
+#[test]
+fn test_wal_align_finds_next_entry() -> eyre::Result<()> {
+    let storage = InMemoryStorage::new_storage();
+    let container = ChunkContainer::load(storage)?;
+    let reader = LogReader::new(container.clone());
+    let mut writer = LogWriter::load(container, BytesMut::new())?;
+    let mut positions = Vec::new();
+
+    for i in 0..10u32 {
+        let receipt = writer.append(&mut RawEntries::new(vec![Bytes::copy_from_slice(
+            &i.to_le_bytes(),
+        )]))?;
+
+        positions.push(receipt.start_position);
+    }
+
+    assert_eq!(0, reader.align(0)?);
+    assert_eq!(positions[3], reader.align(positions[3])?);
+    assert_eq!(positions[4], reader.align(positions[3] + 1)?);
+    assert_eq!(writer.writer_position(), reader.align(positions[9] + 1)?);
+
+    Ok(())
+}
+
+#[test]
+fn test_wal_align_from_remembered_entries() -> eyre::Result<()> {
+    fn append(writer: &mut LogWriter, positions: &mut Vec<u64>, count: usize) -> eyre::Result<()> {
+        for _ in 0..count {
+            let receipt =
+                writer.append(&mut RawEntries::new(vec![Bytes::from(vec![1u8; 1_000])]))?;
+
+            positions.push(receipt.start_position);
+        }
+
+        Ok(())
+    }
+
+    let storage = InMemoryStorage::new_storage();
+    let container = ChunkContainer::load(storage)?;
+    let reader = LogReader::new(container.clone());
+    let mut writer = LogWriter::load(container, BytesMut::new())?;
+    let mut positions = Vec::new();
+
+    append(&mut writer, &mut positions, 500)?;
+
+    // Aligns far in the chunk first, then before and after what was walked so far, in no
+    // particular order.
+    let expected = |positions: &[u64], end: u64, position: u64| {
+        positions
+            .iter()
+            .copied()
+            .find(|p| *p >= position)
+            .unwrap_or(end)
+    };
+
+    for position in (0..writer.writer_position()).rev().step_by(7_919) {
+        assert_eq!(
+            expected(&positions, writer.writer_position(), position),
+            reader.align(position)?
+        );
+    }
+
+    append(&mut writer, &mut positions, 100)?;
+
+    for position in (0..writer.writer_position()).step_by(4_999) {
+        assert_eq!(
+            expected(&positions, writer.writer_position(), position),
+            reader.align(position)?
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_crc32c_check_value() {
+    assert_eq!(0xE306_9283, crc32c(b"123456789"));
+}
+
+#[test]
+fn test_wal_detects_corrupted_entry() -> eyre::Result<()> {
+    let storage = InMemoryStorage::new_storage();
+    let container = ChunkContainer::load(storage.clone())?;
+    let data = generate_bytes();
+    let reader = LogReader::new(container.clone());
+    let mut writer = LogWriter::load(container.clone(), BytesMut::new())?;
+
+    writer.append(&mut RawEntries::new(vec![data.clone()]))?;
+
+    let entry = reader.read_at(0)?;
+    assert_eq!(Some(crc32c(&data)), entry.checksum);
+    assert_eq!(writer.writer_position(), entry.size() as u64);
+
+    let chunk = container.find(0)?.unwrap();
+    let offset = chunk.raw_position(0) + (size_of::<u32>() + LOG_ENTRY_HEADER_SIZE + 42) as u64;
+    let byte = storage.read_from(chunk.file_id(), offset, 1)?[0];
+    storage.write_to(chunk.file_id(), offset, Bytes::copy_from_slice(&[!byte]))?;
+
+    let err = reader.read_at(0).unwrap_err();
+    let err = err.downcast_ref::<io::Error>().expect("an io error");
+
+    assert_eq!(io::ErrorKind::InvalidData, err.kind());
+    assert!(err.to_string().contains("checksum mismatch"));
+
+    Ok(())
+}
+
+#[test]
+fn test_wal_reads_entries_without_checksum() -> eyre::Result<()> {
+    let data = generate_bytes();
+    let mut bytes = BytesMut::new();
+
+    bytes.put_u64_le(1_234);
+    bytes.put_u8(0);
+    bytes.extend_from_slice(&data);
+
+    let entry = LogEntry::try_from(bytes.freeze())?;
+
+    assert_eq!(1_234, entry.position);
+    assert_eq!(0, entry.r#type);
+    assert_eq!(data, entry.payload);
+    assert_eq!(None, entry.checksum);
+    assert_eq!(
+        size_of::<u32>() + LOG_ENTRY_HEADER_SIZE + data.len() + size_of::<u32>(),
+        entry.size()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_load_verified_detects_corrupted_chunk() -> eyre::Result<()> {
+    let root = std::env::temp_dir().join(Uuid::new_v4().to_string());
+    let storage = FileSystemStorage::new_storage(root.clone())?;
+    let container = ChunkContainer::load(storage.clone())?;
+    let mut writer = LogWriter::load(container.clone(), BytesMut::new())?;
+
+    writer.append(&mut RawEntries::new(vec![
+        generate_bytes(),
+        generate_bytes(),
+    ]))?;
+    container.new_chunk(&mut BytesMut::new(), writer.writer_position())?;
+
+    let chunk = container.find(0)?.unwrap();
+    assert!(chunk.footer.as_ref().unwrap().has_hash());
+    ChunkContainer::load_with(storage.clone(), verified())?;
+
+    let offset = chunk.raw_position(42);
+    let byte = storage.read_from(chunk.file_id(), offset, 1)?[0];
+    storage.write_to(chunk.file_id(), offset, Bytes::copy_from_slice(&[!byte]))?;
+
+    // Corruption is only detected when asked for.
+    ChunkContainer::load(storage.clone())?;
+
+    let err = ChunkContainer::load_with(storage, verified()).unwrap_err();
+
+    assert_eq!(io::ErrorKind::InvalidData, err.kind());
+    assert!(err.to_string().contains("chunk 0 is corrupted"));
+
+    std::fs::remove_dir_all(root)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_load_verified_detects_corrupted_position_map() -> eyre::Result<()> {
+    let root = std::env::temp_dir().join(Uuid::new_v4().to_string());
+    let storage = FileSystemStorage::new_storage(root.clone())?;
+    let container = ChunkContainer::load(storage.clone())?;
+    let mut writer = LogWriter::load(container.clone(), BytesMut::new())?;
+
+    for i in 0..10u8 {
+        writer.append(&mut RawEntries::new(vec![Bytes::from(vec![i; 128])]))?;
+    }
+
+    container.new_chunk(&mut BytesMut::new(), writer.writer_position())?;
+    scavenge(&container, |entry| entry.payload[0] % 2 == 0)?;
+    ChunkContainer::load_with(storage.clone(), verified())?;
+
+    let chunk = container.find(0)?.unwrap();
+    let footer = chunk.footer.as_ref().unwrap();
+    let offset = (CHUNK_HEADER_SIZE + footer.physical_data_size) as u64;
+    let byte = storage.read_from(chunk.file_id(), offset, 1)?[0];
+    storage.write_to(chunk.file_id(), offset, Bytes::copy_from_slice(&[!byte]))?;
+
+    let err = ChunkContainer::load_with(storage, verified()).unwrap_err();
+
+    assert_eq!(io::ErrorKind::InvalidData, err.kind());
+    assert!(err.to_string().contains("chunk 0 is corrupted"));
+
+    std::fs::remove_dir_all(root)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_scavenge_drops_entries_and_keeps_positions() -> eyre::Result<()> {
+    let root = std::env::temp_dir().join(Uuid::new_v4().to_string());
+    let storage = FileSystemStorage::new_storage(root.clone())?;
+    let container = ChunkContainer::load(storage.clone())?;
+    let mut writer = LogWriter::load(container.clone(), BytesMut::new())?;
+    let mut expected = Vec::new();
+    let mut dropped = Vec::new();
+
+    // Entries of the stream 1 are dropped.
+    for i in 0..20u8 {
+        let stream = i % 2;
+        let mut payload = BytesMut::zeroed(1_024);
+        payload[0] = stream;
+        payload[1] = i;
+
+        let receipt = writer.append(&mut RawEntries::new(vec![payload.freeze()]))?;
+
+        if stream == 0 {
+            expected.push((receipt.start_position, i));
+        } else {
+            dropped.push(receipt.start_position);
+        }
+    }
+
+    container.new_chunk(&mut BytesMut::new(), writer.writer_position())?;
+    let old_size = container
+        .find(0)?
+        .unwrap()
+        .footer
+        .unwrap()
+        .physical_data_size;
+
+    let scavenged = scavenge(&container, |entry| entry.payload[0] == 0)?;
+
+    assert_eq!(1, scavenged.chunks);
+    assert_eq!(10, scavenged.dropped_entries);
+    assert!(scavenged.reclaimed > 0);
+
+    let chunk = container.find(0)?.unwrap();
+    assert_eq!(1, chunk.info.version);
+    assert_eq!(
+        old_size,
+        chunk.footer.as_ref().unwrap().physical_data_size
+            + chunk.position_map.as_ref().unwrap().size()
+            + scavenged.reclaimed as usize
+    );
+    assert!(!storage.exists(FileId::chunk(0, 0))?);
+
+    let reader = LogReader::new(container.clone());
+    let mut entries = reader.entries(0, writer.writer_position());
+    let mut actual = Vec::new();
+
+    while let Some(entry) = entries.next()? {
+        actual.push((entry.position, entry.payload[1]));
+    }
+
+    assert_eq!(expected, actual);
+    assert!(reader.read_at(dropped[0]).is_err());
+
+    // The new version is picked up on load.
+    let reader = LogReader::new(ChunkContainer::load_with(storage, verified())?);
+    let mut entries = reader.entries(0, writer.writer_position());
+    let mut actual = Vec::new();
+
+    while let Some(entry) = entries.next()? {
+        actual.push((entry.position, entry.payload[1]));
+    }
+
+    assert_eq!(expected, actual);
+
+    std::fs::remove_dir_all(root)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_load_discards_the_version_of_an_interrupted_scavenge() -> eyre::Result<()> {
+    let mem = InMemoryStorage::default();
+    let storage = Storage::InMemory(mem.clone());
+    let options = ChunkOptions {
+        chunk_size: MIN_CHUNK_SIZE,
+        ..ChunkOptions::default()
+    };
+    let container = ChunkContainer::load_with(storage.clone(), options)?;
+    let mut writer = LogWriter::load(container.clone(), BytesMut::new())?;
+
+    for i in 0..10u8 {
+        writer.append(&mut RawEntries::new(vec![Bytes::from(vec![i; 128])]))?;
+    }
+
+    container.new_chunk(&mut BytesMut::new(), writer.writer_position())?;
+
+    // The header and the 5 kept entries of the new version make it to storage, the process
+    // stops before its position map and footer do.
+    let writes = Arc::new(AtomicUsize::new(0));
+    mem.inject_faults(FaultInjector::default().fail_when({
+        let writes = writes.clone();
+        move |op, id| {
+            op == IoOp::Write
+                && id == FileId::chunk(0, 1)
+                && writes.fetch_add(1, Ordering::SeqCst) >= 6
+        }
+    }));
+
+    assert!(scavenge(&container, |entry| entry.payload[0] % 2 == 0).is_err());
+    assert!(storage.exists(FileId::chunk(0, 1))?);
+
+    let end = writer.writer_position();
+    mem.clear_faults();
+    drop(writer);
+    drop(container);
+
+    let reader = LogReader::new(ChunkContainer::load_with(storage.clone(), options)?);
+    let mut entries = reader.entries(0, end);
+    let mut actual = Vec::new();
+
+    while let Some(entry) = entries.next()? {
+        actual.push(entry.payload[0]);
+    }
+
+    assert_eq!((0..10u8).collect::<Vec<_>>(), actual);
+    assert!(storage.exists(FileId::chunk(0, 0))?);
+    assert!(!storage.exists(FileId::chunk(0, 1))?);
+
+    Ok(())
+}
+
+#[test]
+fn test_scavenge_keeps_the_previous_version_while_it_is_read() -> eyre::Result<()> {
+    let storage = InMemoryStorage::new_storage();
+    let container = ChunkContainer::load(storage.clone())?;
+    let mut writer = LogWriter::load(container.clone(), BytesMut::new())?;
+    let mut positions = Vec::new();
+
+    for i in 0..10u8 {
+        let receipt = writer.append(&mut RawEntries::new(vec![Bytes::from(vec![i; 1_024])]))?;
+        positions.push(receipt.start_position);
+    }
+
+    container.new_chunk(&mut BytesMut::new(), writer.writer_position())?;
+
+    let reader = LogReader::new(container.clone());
+    let mut entries = reader.entries(0, writer.writer_position());
+    assert_eq!(0, entries.next()?.unwrap().payload[0]);
+
+    scavenge(&container, |entry| entry.payload[0] % 2 == 0)?;
+
+    // The read started before the scavenge goes on with the previous version.
+    assert!(storage.exists(FileId::chunk(0, 0))?);
+    for i in 1..10u8 {
+        assert_eq!(i, entries.next()?.unwrap().payload[0]);
+    }
+
+    drop(entries);
+    assert!(!storage.exists(FileId::chunk(0, 0))?);
+    assert!(reader.read_at(positions[1]).is_err());
+    assert_eq!(2, reader.read_at(positions[2])?.payload[0]);
+
+    Ok(())
+}
+
+#[test]
+fn test_chunk_size_is_kept_across_reloads() -> eyre::Result<()> {
+    let root = std::env::temp_dir().join(Uuid::new_v4().to_string());
+    let storage = FileSystemStorage::new_storage(root.clone())?;
+    let options = ChunkOptions {
+        chunk_size: MIN_CHUNK_SIZE,
+        ..ChunkOptions::default()
+    };
+
+    let container = ChunkContainer::load_with(storage.clone(), options)?;
+    let mut writer = LogWriter::load(container.clone(), BytesMut::new())?;
+
+    writer.append(&mut RawEntries::new(vec![generate_bytes(); 1_000]))?;
+    assert!(
+        container
+            .find(writer.writer_position())?
+            .unwrap()
+            .info
+            .seq_num
+            >= 2
+    );
+
+    // Opening with the default options doesn't change the size of the existing database.
+    let container = ChunkContainer::load(storage.clone())?;
+    assert_eq!(MIN_CHUNK_SIZE, container.chunk_size()?);
+
+    let reader = LogReader::new(container.clone());
+    let mut entries = reader.entries(0, writer.writer_position());
+    let mut count = 0;
+
+    while let Some(entry) = entries.next()? {
+        assert_eq!(generate_bytes(), entry.payload);
+        count += 1;
+    }
+
+    assert_eq!(1_000, count);
+
+    let mut writer = LogWriter::load(container.clone(), BytesMut::new())?;
+    writer.append(&mut RawEntries::new(vec![generate_bytes(); 1_000]))?;
+
+    let chunk = container.find(writer.writer_position())?.unwrap();
+    assert!(chunk.info.seq_num >= 4);
+    assert_eq!(MIN_CHUNK_SIZE, chunk.header.chunk_size);
+    assert_eq!(MIN_CHUNK_SIZE, storage.len(chunk.file_id())?);
+
+    std::fs::remove_dir_all(root)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_load_rejects_invalid_chunk_size() {
+    let storage = InMemoryStorage::new_storage();
+    let err = ChunkContainer::load_with(
+        storage,
+        ChunkOptions {
+            chunk_size: MIN_CHUNK_SIZE - 1,
+            ..ChunkOptions::default()
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(io::ErrorKind::InvalidInput, err.kind());
+}
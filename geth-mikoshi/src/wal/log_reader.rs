@@ -1,20 +1,73 @@
-use std::mem;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::{io, mem};
 
-use crate::storage::FileId;
+use crate::storage::{FileId, Storage};
 use crate::wal::chunks::ChunkContainer;
-use crate::wal::LogEntry;
-use bytes::Buf;
+use crate::wal::{LogEntry, LOG_ENTRY_HEADER_SIZE};
+use bytes::{Buf, Bytes};
 
 use super::chunks::Chunk;
 
+/// Position of the next entry a consumer of the log has to process. Saved by the consumer so it
+/// resumes from where it left off after a restart.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LogCursor {
+    pub position: u64,
+}
+
+impl LogCursor {
+    /// Cursor saved in `file`, the start of the log if it was never saved.
+    pub fn load(storage: &Storage, file: FileId) -> io::Result<Self> {
+        if !storage.exists(file)? {
+            return Ok(Self::default());
+        }
+
+        let mut bytes = storage.read_from(file, 0, mem::size_of::<u64>())?;
+
+        Ok(Self {
+            position: bytes.get_u64_le(),
+        })
+    }
+
+    pub fn save(&self, storage: &Storage, file: FileId) -> io::Result<()> {
+        storage.write_to(
+            file,
+            0,
+            Bytes::copy_from_slice(self.position.to_le_bytes().as_slice()),
+        )
+    }
+}
+
+/// Minimum distance between two entries of a chunk [`LogReader::align`] remembers.
+const SEEK_SPACING: u64 = 64 * 1_024;
+
+/// Chunks [`LogReader::align`] remembers entries of, the least recently aligned in is forgotten
+/// first.
+const SEEK_CHUNKS: usize = 16;
+
+/// Entries of a chunk `align` went through, to start the next alignments from the closest one.
+struct ChunkSeeks {
+    /// A new version of a scavenged chunk has its own entries.
+    file_id: FileId,
+    /// Positions of entries at least [`SEEK_SPACING`] apart, in log order.
+    positions: Vec<u64>,
+    /// Every entry of the chunk before this position was gone through.
+    walked: u64,
+}
+
 #[derive(Clone)]
 pub struct LogReader {
     container: ChunkContainer,
+    seeks: Arc<Mutex<VecDeque<ChunkSeeks>>>,
 }
 
 impl LogReader {
     pub fn new(container: ChunkContainer) -> Self {
-        Self { container }
+        Self {
+            container,
+            seeks: Default::default(),
+        }
     }
 
     pub fn read_at(&self, position: u64) -> eyre::Result<LogEntry> {
@@ -38,14 +91,85 @@ impl LogReader {
         Entries::new(self, start, limit)
     }
 
+    /// Entries from `cursor` up to the last one written so far.
+    pub fn entries_from(&self, cursor: LogCursor) -> eyre::Result<Entries<'_>> {
+        Ok(self.entries(cursor.position, self.get_writer_checkpoint()?))
+    }
+
+    /// Position of the first entry at or after `position`, which doesn't have to be the position
+    /// of an entry. `position` must not be past the writer checkpoint.
+    ///
+    /// Entries can only be walked forward from a known one. The reader remembers entries of the
+    /// chunks it aligned in lately, so it only walks from the closest one before `position`
+    /// instead of from the start of the chunk.
+    pub fn align(&self, position: u64) -> eyre::Result<u64> {
+        let Some(chunk) = self.container.find(position)? else {
+            return Ok(position);
+        };
+
+        let mut seeks = self
+            .seeks
+            .lock()
+            .map_err(|_e| eyre::eyre!("failed to obtain a lock on the log reader seeks"))?;
+
+        let mut seek = match seeks.iter().position(|s| s.file_id == chunk.file_id()) {
+            Some(index) => seeks.remove(index).unwrap(),
+            None => ChunkSeeks {
+                file_id: chunk.file_id(),
+                positions: Vec::new(),
+                walked: chunk.start_position(),
+            },
+        };
+
+        let start = if position < seek.walked {
+            let closest = seek.positions.partition_point(|p| *p <= position);
+            closest
+                .checked_sub(1)
+                .map_or(chunk.start_position(), |index| seek.positions[index])
+        } else {
+            seek.walked
+        };
+
+        let mut entries = self.entries(start, position);
+        while let Some(entry) = entries.next()? {
+            if entry.position >= seek.walked
+                && seek
+                    .positions
+                    .last()
+                    .is_none_or(|last| entry.position >= last + SEEK_SPACING)
+            {
+                seek.positions.push(entry.position);
+            }
+        }
+
+        let aligned = entries.cursor().position;
+        seek.walked = seek.walked.max(aligned);
+
+        seeks.push_front(seek);
+        seeks.truncate(SEEK_CHUNKS);
+
+        Ok(aligned)
+    }
+
     fn chunk_read_at(&self, chunk: &Chunk, position: u64) -> eyre::Result<LogEntry> {
         let storage = self.container.storage();
 
-        let local_offset = chunk.raw_position(position);
+        let Some(local_offset) = chunk.entry_raw_position(position) else {
+            eyre::bail!("log position {} was scavenged", position);
+        };
+
         let record_size = storage
             .read_from(chunk.file_id(), local_offset, mem::size_of::<u32>())?
             .get_u32_le() as usize;
 
+        if !(LOG_ENTRY_HEADER_SIZE..=chunk.header.chunk_size).contains(&record_size) {
+            eyre::bail!(
+                "invalid record size {} at log position {}",
+                record_size,
+                position
+            );
+        }
+
         let record_offset = local_offset + mem::size_of::<u32>() as u64;
         let record_bytes = storage.read_from(chunk.file_id(), record_offset, record_size)?;
 
@@ -87,28 +211,103 @@ impl<'a> Entries<'a> {
         }
     }
 
+    /// Returns entries in strictly increasing log position order, each exactly once. When an
+    /// entry didn't fit at the end of a chunk, the writer moved it to the next chunk, so the unused
+    /// tail of the chunk is skipped.
     pub fn next(&mut self) -> eyre::Result<Option<LogEntry>> {
         loop {
             if self.current >= self.limit {
                 return Ok(None);
             }
 
-            if let Some(chunk) = self.chunk.take() {
-                if !chunk.contains_log_position(self.current) {
-                    continue;
+            let chunk = match self.chunk.take() {
+                // The ongoing chunk might have been closed since we fetched it, so we only keep
+                // chunks that are known to be completed.
+                Some(chunk)
+                    if chunk.footer.is_some() && chunk.contains_log_position(self.current) =>
+                {
+                    chunk
                 }
 
-                let entry = self.inner.chunk_read_at(&chunk, self.current)?;
-                self.chunk = Some(chunk);
-                self.current += (entry.size() + 2 * mem::size_of::<u32>()) as u64;
+                _ => {
+                    if let Some(chunk) = self.inner.container.find(self.current)? {
+                        chunk
+                    } else {
+                        eyre::bail!("log position {} not found", self.current);
+                    }
+                }
+            };
+
+            if self.current >= chunk.data_end_position() {
+                self.current = chunk.end_position();
+                continue;
+            }
 
-                return Ok(Some(entry));
-            } else if let Some(chunk) = self.inner.container.find(self.current)? {
+            // Scavenged chunks only have the entries that were kept.
+            let next = chunk.next_entry_position(self.current);
+            if next != self.current {
+                self.current = next;
                 self.chunk = Some(chunk);
                 continue;
             }
 
-            eyre::bail!("log position {} not found", self.current);
+            let entry = self.inner.chunk_read_at(&chunk, self.current)?;
+            self.chunk = Some(chunk);
+            self.current += entry.size() as u64;
+
+            return Ok(Some(entry));
+        }
+    }
+
+    /// Where to resume from to get the entries this iterator didn't return yet.
+    pub fn cursor(&self) -> LogCursor {
+        LogCursor {
+            position: self.current,
+        }
+    }
+}
+
+/// Entries positioned before a given log position, latest first. Entries can only be walked
+/// forward, so the positions of a chunk are collected before returning its entries in reverse.
+pub struct BackwardEntries {
+    inner: LogReader,
+    /// Chunks from this position on were already collected.
+    end: u64,
+    /// Positions left to return, in log order.
+    pending: Vec<u64>,
+}
+
+impl BackwardEntries {
+    /// `end` must not be past the writer checkpoint.
+    pub fn new(inner: LogReader, end: u64) -> Self {
+        Self {
+            inner,
+            end,
+            pending: Vec::new(),
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> eyre::Result<Option<LogEntry>> {
+        loop {
+            if let Some(position) = self.pending.pop() {
+                return self.inner.read_at(position).map(Some);
+            }
+
+            if self.end == 0 {
+                return Ok(None);
+            }
+
+            let Some(chunk) = self.inner.container.find(self.end - 1)? else {
+                eyre::bail!("log position {} not found", self.end - 1);
+            };
+
+            let mut entries = self.inner.entries(chunk.start_position(), self.end);
+            while let Some(entry) = entries.next()? {
+                self.pending.push(entry.position);
+            }
+
+            self.end = chunk.start_position();
         }
     }
 }
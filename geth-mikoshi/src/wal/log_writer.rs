@@ -1,6 +1,7 @@
+use crate::hashing::crc32c;
 use crate::storage::{FileId, Storage};
 use crate::wal::chunks::ChunkContainer;
-use crate::wal::LogReceipt;
+use crate::wal::{LogReceipt, LOG_ENTRY_CHECKSUM_FLAG};
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 use std::io;
 
@@ -12,7 +13,9 @@ const ENTRY_PREFIX_SIZE: usize = size_of::<u32>() // pre-entry size
 const ENTRY_HEADER_SIZE: usize = size_of::<u64>() // log position
     + size_of::<u8>(); // log type
 
-const ENTRY_META_SIZE: usize = ENTRY_PREFIX_SIZE + size_of::<u32>(); // post-entry size;
+const ENTRY_META_SIZE: usize = ENTRY_PREFIX_SIZE
+    + size_of::<u32>() // payload checksum
+    + size_of::<u32>(); // post-entry size
 
 pub struct LogWriter {
     container: ChunkContainer,
@@ -45,7 +48,7 @@ impl LogWriter {
         E: LogEntries,
     {
         let mut position = self.writer;
-        let starting_position = position;
+        let mut starting_position = position;
         let storage = self.container.storage();
         let mut chunk = self.container.ongoing()?;
         let expected_count = entries.expected_count();
@@ -58,17 +61,22 @@ impl LogWriter {
 
             // Chunk is full, and we need to flush previous data we accumulated. We also create a new
             // chunk for next writes.
-            if !chunk.contains_log_position(projected_next_logical_position) {
+            if projected_next_logical_position > chunk.max_data_position() {
                 let remaining_space = chunk.remaining_space_from(position);
                 chunk = self.container.new_chunk(&mut self.buffer, position)?;
                 position += remaining_space;
             }
 
-            let reported_size = (entry_size + ENTRY_HEADER_SIZE) as u32;
+            // The first entry might have been moved to a new chunk.
+            if count == 0 {
+                starting_position = position;
+            }
+
+            let reported_size = (entry_size + ENTRY_HEADER_SIZE + size_of::<u32>()) as u32;
             self.buffer.reserve(actual_size);
             self.buffer.put_u32_le(reported_size);
             self.buffer.put_u64_le(position);
-            self.buffer.put_u8(0);
+            self.buffer.put_u8(LOG_ENTRY_CHECKSUM_FLAG);
             let mut payload_buffer = self.buffer.split_off(ENTRY_PREFIX_SIZE);
 
             entries.write_current_entry(&mut payload_buffer, position);
@@ -81,15 +89,18 @@ impl LogWriter {
                 );
             }
 
+            let checksum = crc32c(&payload_buffer);
+            payload_buffer.put_u32_le(checksum);
             payload_buffer.put_u32_le(reported_size);
             self.buffer.unsplit(payload_buffer);
             let record = self.buffer.split().freeze();
-            let payload = record.slice(ENTRY_PREFIX_SIZE..record.len() - size_of::<u32>());
+            let payload = record.slice(ENTRY_PREFIX_SIZE..ENTRY_PREFIX_SIZE + entry_size);
             let local_offset = chunk.raw_position(position);
             let entry = LogEntry {
                 position,
                 r#type: 0,
                 payload,
+                checksum: Some(checksum),
             };
 
             count += 1;
@@ -106,7 +117,7 @@ impl LogWriter {
             );
         }
 
-        flush_writer_chk(storage, self.writer)?;
+        flush_writer_chk(storage, position)?;
         self.writer = position;
 
         Ok(LogReceipt {
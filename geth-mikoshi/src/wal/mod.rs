@@ -1,14 +1,22 @@
+use std::io;
+
 use bytes::{Buf, Bytes, BytesMut};
 
+use crate::hashing::crc32c;
+
 pub mod chunks;
 mod log_reader;
 mod log_writer;
 
-pub use log_reader::LogReader;
+pub use log_reader::{BackwardEntries, LogCursor, LogReader};
 pub use log_writer::LogWriter;
 
 pub const LOG_ENTRY_HEADER_SIZE: usize = size_of::<u64>() + size_of::<u8>(); // position and type
 
+/// Set on the type byte of entries followed by a CRC32C checksum of their payload. Entries written
+/// before checksums were introduced don't have it.
+pub const LOG_ENTRY_CHECKSUM_FLAG: u8 = 0x80;
+
 pub trait LogEntries {
     fn move_next(&mut self) -> bool;
     fn current_entry_size(&self) -> usize;
@@ -22,6 +30,8 @@ pub struct LogEntry {
     pub position: u64,
     pub r#type: u8,
     pub payload: Bytes,
+    /// CRC32C of the payload, `None` for entries written without one.
+    pub checksum: Option<u32>,
 }
 
 impl LogEntry {
@@ -29,6 +39,7 @@ impl LogEntry {
         size_of::<u32>() // entry size
             + size_of::<u8>() // entry type
             + self.payload_size()
+            + self.checksum.map_or(0, |_| size_of::<u32>())
             + size_of::<u32>() // entry size
     }
 
@@ -36,20 +47,6 @@ impl LogEntry {
         size_of::<u64>() // position
             + self.payload.len()
     }
-
-    /// Parsing is not symmetrical with serialisation because parsing the size of the record
-    /// is done directly when communicating with the storage abstraction directly.
-    pub fn get(mut src: Bytes) -> Self {
-        let position = src.get_u64_le();
-        let r#type = src.get_u8();
-        let payload = src;
-
-        Self {
-            position,
-            r#type,
-            payload,
-        }
-    }
 }
 
 impl TryFrom<Bytes> for LogEntry {
@@ -60,14 +57,43 @@ impl TryFrom<Bytes> for LogEntry {
             eyre::bail!("bytes buffer is too short to contain a valid log entry");
         }
 
+        // Parsing is not symmetrical with serialisation because parsing the size of the record
+        // is done directly when communicating with the storage abstraction directly.
         let position = src.get_u64_le();
         let r#type = src.get_u8();
-        let payload = src;
+
+        if r#type & LOG_ENTRY_CHECKSUM_FLAG == 0 {
+            return Ok(Self {
+                position,
+                r#type,
+                payload: src,
+                checksum: None,
+            });
+        }
+
+        if src.remaining() < size_of::<u32>() {
+            eyre::bail!("bytes buffer is too short to contain a valid log entry");
+        }
+
+        let mut checksum_bytes = src.split_off(src.len() - size_of::<u32>());
+        let checksum = checksum_bytes.get_u32_le();
+        let actual = crc32c(&src);
+
+        if checksum != actual {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "checksum mismatch for log entry at position {position}: expected {checksum:#010x}, got {actual:#010x}"
+                ),
+            )
+            .into());
+        }
 
         Ok(Self {
             position,
-            r#type,
-            payload,
+            r#type: r#type & !LOG_ENTRY_CHECKSUM_FLAG,
+            payload: src,
+            checksum: Some(checksum),
         })
     }
 }
@@ -30,16 +30,17 @@ async fn main() -> eyre::Result<()> {
                 content_type: ContentType::Json,
                 class: "foobar".to_string(),
                 data: serde_json::to_vec(&Foobar { value: 10 * i })?.into(),
+                metadata: Default::default(),
             });
         }
 
         client
             .append_stream("baz", ExpectedRevision::Any, proposes)
             .await?
-            .success()?;
+            .into_result()?;
 
         let mut stream = client
-            .read_stream("baz", Direction::Forward, Revision::Start, u64::MAX)
+            .read_stream("baz", Direction::Forward, Revision::Start, None, true)
             .await?
             .success()?;
 
@@ -0,0 +1,148 @@
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use geth_client::Client;
+use geth_common::{
+    ContentType, Direction, ExpectedRevision, Propose, ReadStreamCompleted, Revision,
+};
+use uuid::Uuid;
+
+use crate::cli::Bench;
+
+const BENCH_EVENT_CLASS: &str = "bench-event";
+
+struct Measure {
+    total: Duration,
+    latencies: Vec<Duration>,
+}
+
+impl Measure {
+    fn new(capacity: usize) -> Self {
+        Self {
+            total: Duration::ZERO,
+            latencies: Vec::with_capacity(capacity),
+        }
+    }
+
+    fn report(&mut self, name: &str, total_bytes: u64) {
+        self.latencies.sort();
+
+        let secs = self.total.as_secs_f64();
+        let count = self.latencies.len();
+
+        println!(
+            "{name}: {count} event(s) in {:.3}s, {:.0} events/s, {:.2} MiB/s, p50 {:?}, p99 {:?}",
+            secs,
+            count as f64 / secs,
+            total_bytes as f64 / (1_024.0 * 1_024.0) / secs,
+            percentile(&self.latencies, 50),
+            percentile(&self.latencies, 99),
+        );
+    }
+}
+
+/// Appends synthetic events to a scratch stream one by one, reads them back and reports the
+/// throughput of both. The scratch stream is deleted afterward.
+pub async fn bench<C>(client: &C, opts: &Bench)
+where
+    C: Client,
+{
+    let stream_name = format!("bench-{}", Uuid::new_v4());
+
+    if let Err(e) = run(client, &stream_name, opts).await {
+        println!("ERR: error when benchmarking: {e}");
+    }
+
+    if let Err(e) = client
+        .delete_stream(&stream_name, ExpectedRevision::Any, false)
+        .await
+        .and_then(|r| r.success())
+    {
+        println!("ERR: error when deleting scratch stream '{stream_name}': {e}");
+    }
+}
+
+async fn run<C>(client: &C, stream_name: &str, opts: &Bench) -> eyre::Result<()>
+where
+    C: Client,
+{
+    let payload = Bytes::from(vec![b'x'; opts.size]);
+    let total_bytes = opts.count as u64 * opts.size as u64;
+
+    println!(
+        "benchmarking {} event(s) of {} byte(s) on scratch stream '{}'",
+        opts.count, opts.size, stream_name
+    );
+
+    let mut appends = Measure::new(opts.count);
+    for _ in 0..opts.count {
+        let propose = Propose {
+            id: Uuid::new_v4(),
+            content_type: ContentType::Binary,
+            class: BENCH_EVENT_CLASS.to_string(),
+            data: payload.clone(),
+            metadata: Default::default(),
+        };
+
+        let started = Instant::now();
+        client
+            .append_stream(stream_name, ExpectedRevision::Any, vec![propose])
+            .await?
+            .success()?;
+
+        let elapsed = started.elapsed();
+        appends.total += elapsed;
+        appends.latencies.push(elapsed);
+    }
+
+    appends.report("append", total_bytes);
+
+    let mut reads = Measure::new(opts.count);
+    let started = Instant::now();
+    let mut stream = match client
+        .read_stream(
+            stream_name,
+            Direction::Forward,
+            Revision::Start,
+            Some(opts.count as u64),
+            false,
+        )
+        .await?
+    {
+        ReadStreamCompleted::StreamDeleted => eyre::bail!("scratch stream was deleted"),
+        ReadStreamCompleted::Success(stream) => stream,
+    };
+
+    let mut last = Instant::now();
+    let mut read_bytes = 0u64;
+    while let Some(record) = stream.next().await? {
+        let now = Instant::now();
+        reads.latencies.push(now - last);
+        read_bytes += record.data.len() as u64;
+        last = now;
+    }
+
+    reads.total = started.elapsed();
+
+    if reads.latencies.len() != opts.count {
+        eyre::bail!(
+            "read {} event(s) back but {} were appended",
+            reads.latencies.len(),
+            opts.count
+        );
+    }
+
+    reads.report("read", read_bytes);
+    println!("total: {total_bytes} byte(s) written, {read_bytes} byte(s) read");
+
+    Ok(())
+}
+
+fn percentile(sorted: &[Duration], p: usize) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+
+    let index = (sorted.len() * p).div_ceil(100).saturating_sub(1);
+    sorted[index.min(sorted.len() - 1)]
+}
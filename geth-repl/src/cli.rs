@@ -1,5 +1,26 @@
 use clap::{Args, Parser, Subcommand};
+use geth_common::RestartPolicy;
 use std::path::PathBuf;
+use std::time::Duration;
+
+/// Command line arguments. Without any, the REPL starts in interactive mode.
+#[derive(Parser, Debug)]
+#[command(name = "geth-repl", author, version, about, long_about = None)]
+pub struct Args {
+    /// Run a command non-interactively, can be repeated. Results are written as JSON.
+    #[arg(long)]
+    pub exec: Vec<String>,
+
+    /// Run the commands of a file non-interactively, one per line. Results are written as JSON.
+    #[arg(long)]
+    pub script: Option<PathBuf>,
+}
+
+impl Args {
+    pub fn is_interactive(&self) -> bool {
+        self.exec.is_empty() && self.script.is_none()
+    }
+}
 
 pub enum Cli {
     Offline(Offline),
@@ -36,6 +57,15 @@ pub enum OfflineCommands {
     /// Use a Mikoshi directory
     Mikoshi { directory: PathBuf },
 
+    #[command(arg_required_else_help = true)]
+    /// Start an embedded GethDB node on a directory and connect to it
+    Serve {
+        directory: PathBuf,
+
+        #[arg(long)]
+        port: Option<u16>,
+    },
+
     /// Exit shell.
     Exit,
 }
@@ -57,12 +87,29 @@ pub enum OnlineCommands {
     /// Subscription commands
     Subscribe(Subscribe),
 
+    #[command(arg_required_else_help = true)]
+    /// Tail a stream live
+    Watch(WatchStream),
+
     /// Disconnect from the current GethDB node
     Disconnect,
 
     /// Process commands
     Process(Process),
 
+    /// Measure write and read throughput
+    Bench(Bench),
+
+    /// List streams
+    Streams(ListStreams),
+
+    #[command(arg_required_else_help = true)]
+    /// Change the server log filters, e.g. `geth_engine=trace`
+    LogFilters(LogFilters),
+
+    /// Flush the index to disk, and merge its files with `--compact`
+    Index(IndexMaintenance),
+
     /// Exit shell.
     Exit,
 }
@@ -88,6 +135,12 @@ pub struct SubscribeToStream {
     pub stream: String,
 }
 
+#[derive(Args, Debug)]
+pub struct WatchStream {
+    // Stream's name
+    pub stream: String,
+}
+
 #[derive(Args, Debug)]
 pub struct SubscribeToProgram {
     #[arg(long)]
@@ -96,6 +149,30 @@ pub struct SubscribeToProgram {
 
     /// Path to programmable subscription script.
     pub path: PathBuf,
+
+    #[arg(long)]
+    /// Also display what the program prints.
+    pub logs: bool,
+
+    #[arg(long)]
+    /// Restarts the program subscriptions up to that many times when they fail.
+    pub restart_max: Option<u32>,
+
+    #[arg(long, default_value = "1000")]
+    /// Delay before restarting a failed subscription, in milliseconds.
+    pub restart_backoff_ms: u64,
+}
+
+impl SubscribeToProgram {
+    pub fn restart_policy(&self) -> RestartPolicy {
+        match self.restart_max {
+            None => RestartPolicy::Never,
+            Some(max) => RestartPolicy::OnError {
+                max,
+                backoff: Duration::from_millis(self.restart_backoff_ms),
+            },
+        }
+    }
 }
 
 #[derive(Args, Debug)]
@@ -104,16 +181,64 @@ pub struct AppendStream {
     #[arg(long)]
     pub stream: String,
 
+    /// Don't push the events to subscriptions, for bulk imports no one is tailing. Offline mode
+    /// has no subscriptions to notify.
+    #[arg(long)]
+    pub no_notify: bool,
+
     /// Path to json file.
     pub json: PathBuf,
 }
 
 #[derive(Args, Debug)]
 pub struct DeleteStream {
+    /// Only check whether the stream could be deleted.
+    #[arg(long)]
+    pub dry_run: bool,
+
     // Stream's name
     pub stream: String,
 }
 
+#[derive(Args, Debug)]
+pub struct ListStreams {
+    /// Only list streams starting with that prefix.
+    #[arg(long)]
+    pub prefix: Option<String>,
+
+    /// Number of streams to skip.
+    #[arg(long, default_value = "0")]
+    pub offset: usize,
+
+    /// Maximum number of streams to list.
+    #[arg(long, default_value = "100")]
+    pub limit: usize,
+}
+
+#[derive(Args, Debug)]
+pub struct LogFilters {
+    /// Filter directives, replacing the ones currently in use.
+    pub directives: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct IndexMaintenance {
+    /// Also merge the index files into one, which can take a while on a large index.
+    #[arg(long)]
+    pub compact: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct Bench {
+    /// Number of events to append.
+    #[arg(long, default_value = "1000")]
+    pub count: usize,
+
+    /// Size of each event payload, in bytes.
+    #[arg(long, default_value = "256")]
+    pub size: usize,
+}
+
 #[derive(Args, Debug)]
 pub struct ReadStream {
     #[arg(long)]
@@ -150,6 +275,12 @@ pub enum MikoshiCommands {
     /// Append a stream
     Append(AppendStream),
 
+    /// Measure write and read throughput
+    Bench(Bench),
+
+    /// List streams
+    Streams(ListStreams),
+
     /// Leave Mikoshi directory
     Leave,
 }
@@ -1,15 +1,25 @@
-use geth_client::{Client, ReadStreaming, SubscriptionStreaming};
+use std::collections::HashMap;
+
+use geth_client::{Client, ProjectionStreaming, ReadStreaming, SubscriptionStreaming};
 use geth_common::{
-    AppendStreamCompleted, DeleteStreamCompleted, Direction, ExpectedRevision, ProgramStats,
-    ProgramSummary, Propose, ReadStreamCompleted, Revision,
+    AppendAndSubscribeCompleted, AppendStreamCompleted, CopyStream, CopyStreamCompleted,
+    DeleteStreamCompleted, Direction, Epochs, ExpectedRevision, IndexMaintained, LogFiltersSet,
+    MapperErrorPolicy, ProgramStats, ProgramSummary, Propose, ReadStream, ReadStreamCompleted,
+    RestartPolicy, Revision, SetStreamMetadataCompleted, StreamMetadata, StreamPage,
+    SubscriptionBatching, TruncateStreamCompleted,
+};
+use geth_engine::reading::FieldSelector;
+use geth_engine::{
+    EmbeddedClient, EventMappers, IndexClient, Options, ReaderClient, RequestContext, WriterClient,
 };
-use geth_engine::{EmbeddedClient, Options, ReaderClient, RequestContext, WriterClient};
 
 #[derive(Clone)]
 pub struct LocalClient {
     client: EmbeddedClient,
     writer: WriterClient,
     reader: ReaderClient,
+    index: IndexClient,
+    mappers: EventMappers,
 }
 
 impl LocalClient {
@@ -19,6 +29,8 @@ impl LocalClient {
         Ok(Self {
             writer: client.manager().new_writer_client().await?,
             reader: client.manager().new_reader_client().await?,
+            index: client.manager().new_index_client().await?,
+            mappers: options.mappers,
             client,
         })
     }
@@ -26,6 +38,17 @@ impl LocalClient {
     pub async fn shutdown(self) -> eyre::Result<()> {
         self.client.shutdown().await
     }
+
+    async fn copy(&self, params: CopyStream) -> eyre::Result<CopyStreamCompleted> {
+        geth_engine::copy_stream(
+            RequestContext::new(),
+            &self.reader,
+            &self.writer,
+            &self.mappers,
+            params,
+        )
+        .await
+    }
 }
 
 #[async_trait::async_trait]
@@ -46,29 +69,114 @@ impl Client for LocalClient {
             .await
     }
 
+    async fn append_and_subscribe(
+        &self,
+        _stream_id: &str,
+        _expected_revision: ExpectedRevision,
+        _proposes: Vec<Propose>,
+    ) -> eyre::Result<AppendAndSubscribeCompleted<SubscriptionStreaming>> {
+        eyre::bail!("subscriptions are not supported in local mode");
+    }
+
     async fn read_stream(
         &self,
         stream_id: &str,
         direction: Direction,
         revision: Revision<u64>,
-        max_count: u64,
+        max_count: Option<u64>,
+        resolve_links: bool,
+    ) -> eyre::Result<ReadStreamCompleted<ReadStreaming>> {
+        self.read_stream_with_epochs(
+            stream_id,
+            direction,
+            revision,
+            max_count,
+            resolve_links,
+            Epochs::Current,
+        )
+        .await
+    }
+
+    async fn read_stream_with_epochs(
+        &self,
+        stream_id: &str,
+        direction: Direction,
+        revision: Revision<u64>,
+        max_count: Option<u64>,
+        resolve_links: bool,
+        epochs: Epochs,
     ) -> eyre::Result<ReadStreamCompleted<ReadStreaming>> {
+        let params = ReadStream {
+            stream_name: stream_id.to_string(),
+            direction,
+            revision,
+            max_count,
+            resolve_links,
+            epochs,
+        };
+
+        match self
+            .reader
+            .read_stream(RequestContext::new(), params)
+            .await?
+        {
+            ReadStreamCompleted::StreamDeleted => Ok(ReadStreamCompleted::StreamDeleted),
+            ReadStreamCompleted::Success(reading) => {
+                Ok(ReadStreamCompleted::Success(ReadStreaming::Local(reading)))
+            }
+        }
+    }
+
+    async fn read_all(
+        &self,
+        from: Revision<u64>,
+        direction: Direction,
+        max_count: Option<u64>,
+    ) -> eyre::Result<ReadStreaming> {
+        let reading = self
+            .reader
+            .read_all(
+                RequestContext::new(),
+                from,
+                direction,
+                max_count.map_or(usize::MAX, |c| usize::try_from(c).unwrap_or(usize::MAX)),
+                true,
+            )
+            .await?;
+
+        Ok(ReadStreaming::Local(reading))
+    }
+
+    async fn read_projection(
+        &self,
+        stream_id: &str,
+        direction: Direction,
+        revision: Revision<u64>,
+        max_count: u64,
+        fields: Vec<String>,
+    ) -> eyre::Result<ReadStreamCompleted<ProjectionStreaming>> {
+        let selectors = fields
+            .iter()
+            .map(|f| FieldSelector::parse(f))
+            .collect::<eyre::Result<Vec<_>>>()?;
+
         let outcome = self
             .reader
-            .read(
+            .read_projection(
                 RequestContext::new(),
                 stream_id,
                 revision,
                 direction,
                 max_count as usize,
+                selectors,
             )
             .await?;
 
         match outcome {
             ReadStreamCompleted::StreamDeleted => Ok(ReadStreamCompleted::StreamDeleted),
-            ReadStreamCompleted::Success(reading) => {
-                Ok(ReadStreamCompleted::Success(ReadStreaming::Local(reading)))
-            }
+            ReadStreamCompleted::Success(streaming) => Ok(ReadStreamCompleted::Success(
+                ProjectionStreaming::Local(streaming),
+            )),
         }
     }
 
@@ -80,20 +188,93 @@ impl Client for LocalClient {
         eyre::bail!("subscriptions are not supported in local mode");
     }
 
+    async fn subscribe_to_stream_in_batches(
+        &self,
+        _stream_id: &str,
+        _start: Revision<u64>,
+        _batching: SubscriptionBatching,
+    ) -> eyre::Result<SubscriptionStreaming> {
+        eyre::bail!("subscriptions are not supported in local mode");
+    }
+
+    async fn subscribe_to_stream_with_classes(
+        &self,
+        _stream_id: &str,
+        _start: Revision<u64>,
+        _classes: Vec<String>,
+    ) -> eyre::Result<SubscriptionStreaming> {
+        eyre::bail!("subscriptions are not supported in local mode");
+    }
+
     async fn subscribe_to_process(
         &self,
         _name: &str,
         _source_code: &str,
+        _logs: bool,
+        _restart: RestartPolicy,
     ) -> eyre::Result<SubscriptionStreaming> {
         eyre::bail!("subscriptions are not supported in local mode");
     }
 
-    async fn delete_stream(
+    async fn subscribe_persistent(
         &self,
+        _group: &str,
         _stream_id: &str,
-        _expected_revision: ExpectedRevision,
+    ) -> eyre::Result<SubscriptionStreaming> {
+        eyre::bail!("subscriptions are not supported in local mode");
+    }
+
+    async fn ack(&self, _group: &str, _stream_id: &str, _position: u64) -> eyre::Result<()> {
+        eyre::bail!("subscriptions are not supported in local mode");
+    }
+
+    async fn delete_stream(
+        &self,
+        stream_id: &str,
+        expected_revision: ExpectedRevision,
+        dry_run: bool,
     ) -> eyre::Result<DeleteStreamCompleted> {
-        eyre::bail!("not implemented")
+        self.writer
+            .delete(
+                RequestContext::new(),
+                stream_id.to_string(),
+                expected_revision,
+                dry_run,
+            )
+            .await
+    }
+
+    async fn truncate_stream(
+        &self,
+        stream_id: &str,
+        before: u64,
+    ) -> eyre::Result<TruncateStreamCompleted> {
+        self.writer
+            .truncate(RequestContext::new(), stream_id.to_string(), before)
+            .await
+    }
+
+    async fn set_stream_metadata(
+        &self,
+        stream_id: &str,
+        metadata: StreamMetadata,
+    ) -> eyre::Result<SetStreamMetadataCompleted> {
+        self.writer
+            .set_metadata(RequestContext::new(), stream_id.to_string(), metadata)
+            .await
+    }
+
+    async fn get_stream_metadata(
+        &self,
+        stream_id: &str,
+    ) -> eyre::Result<ReadStreamCompleted<StreamMetadata>> {
+        self.reader.metadata(RequestContext::new(), stream_id).await
+    }
+
+    async fn latest_revision(&self, stream_id: &str) -> eyre::Result<Option<u64>> {
+        self.index
+            .stream_revision(RequestContext::new(), stream_id)
+            .await
     }
 
     async fn list_programs(&self) -> eyre::Result<Vec<ProgramSummary>> {
@@ -107,4 +288,60 @@ impl Client for LocalClient {
     async fn stop_program(&self, _id: u64) -> eyre::Result<()> {
         eyre::bail!("not implemented")
     }
+
+    async fn set_log_filters(&self, directives: Vec<String>) -> eyre::Result<LogFiltersSet> {
+        geth_engine::set_log_filters(&directives)
+    }
+
+    async fn maintain_index(&self, compact: bool) -> eyre::Result<IndexMaintained> {
+        self.index.maintain(RequestContext::new(), compact).await
+    }
+
+    async fn list_streams(
+        &self,
+        prefix: Option<String>,
+        offset: usize,
+        limit: usize,
+    ) -> eyre::Result<StreamPage> {
+        self.index
+            .list_streams(RequestContext::new(), prefix, offset, limit)
+            .await
+    }
+
+    async fn copy_stream(
+        &self,
+        source: &str,
+        target: &str,
+        expected_revision: ExpectedRevision,
+        class_renames: HashMap<String, String>,
+    ) -> eyre::Result<CopyStreamCompleted> {
+        self.copy(CopyStream {
+            source_stream: source.to_string(),
+            target_stream: target.to_string(),
+            expected_revision,
+            class_renames,
+            mapper: None,
+            on_mapper_error: MapperErrorPolicy::default(),
+        })
+        .await
+    }
+
+    async fn copy_stream_with_mapper(
+        &self,
+        source: &str,
+        target: &str,
+        expected_revision: ExpectedRevision,
+        mapper: &str,
+        on_mapper_error: MapperErrorPolicy,
+    ) -> eyre::Result<CopyStreamCompleted> {
+        self.copy(CopyStream {
+            source_stream: source.to_string(),
+            target_stream: target.to_string(),
+            expected_revision,
+            class_renames: HashMap::new(),
+            mapper: Some(mapper.to_string()),
+            on_mapper_error,
+        })
+        .await
+    }
 }
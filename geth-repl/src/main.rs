@@ -1,32 +1,47 @@
 use std::path::Path;
 use std::{fs, fs::File, io, path::PathBuf};
 
+use clap::Parser;
 use cli::AppendStream;
 use directories::UserDirs;
-use geth_engine::Options;
+use geth_engine::{EmbeddedClient, Options};
 use glyph::{FileBackedInputs, Input, PromptOptions};
 use local::LocalClient;
 use serde::Deserialize;
 use uuid::Uuid;
 
-use geth_client::{Client, GrpcClient, ReadStreaming};
+use geth_client::{Client, GrpcClient, ReadStreaming, SubscriptionStreaming};
 use geth_common::{
     AppendError, AppendStreamCompleted, DeleteError, DeleteStreamCompleted, Direction, EndPoint,
-    ExpectedRevision, Propose, ReadStreamCompleted, Revision,
+    ExpectedRevision, LogFiltersSet, Propose, ReadStreamCompleted, Record, Revision,
+    SubscriptionEvent,
 };
 
 use crate::cli::{
-    Cli, Mikoshi, MikoshiCommands, Offline, OfflineCommands, Online, OnlineCommands,
-    ProcessCommands, ReadStream, SubscribeCommands,
+    Args, Cli, ListStreams, Mikoshi, MikoshiCommands, Offline, OfflineCommands, Online,
+    OnlineCommands, ProcessCommands, ReadStream, SubscribeCommands,
 };
 use crate::utils::expand_path;
 
+mod bench;
 mod cli;
 mod local;
+mod script;
 mod utils;
+mod watch;
 
 #[tokio::main]
 async fn main() -> eyre::Result<()> {
+    let args = Args::parse();
+
+    if !args.is_interactive() {
+        if !script::run(args).await? {
+            std::process::exit(1);
+        }
+
+        return Ok(());
+    }
+
     let user_dirs = UserDirs::new().expect("to be defined");
     let history_path = PathBuf::from(user_dirs.home_dir()).join(".geth-repl");
     let options = glyph::Options::default()
@@ -55,21 +70,22 @@ async fn main() -> eyre::Result<()> {
                             host: host.clone(),
                             port,
                             client: GrpcClient::connect(EndPoint::new(host.clone(), port)).await?,
+                            server: None,
                         });
                     }
 
-                    OfflineCommands::Mikoshi { directory } => {
+                    OfflineCommands::Serve { directory, port } => {
                         let directory = expand_path(directory);
-                        let options = Options::new(
-                            "<repl>".to_string(),
-                            0,
-                            directory.clone().to_string_lossy().to_string(),
-                        )
-                        .disable_telemetry()
-                        .disable_grpc();
-
-                        let client = LocalClient::new(options).await?;
-                        repl_state = ReplState::Mikoshi(MikoshiState { directory, client });
+                        let port = port.unwrap_or(2_113);
+
+                        match serve(directory, port).await {
+                            Err(e) => println!("ERR: error when starting the server: {e}"),
+                            Ok(state) => repl_state = ReplState::Online(state),
+                        }
+                    }
+
+                    OfflineCommands::Mikoshi { directory } => {
+                        repl_state = ReplState::Mikoshi(open_mikoshi(directory).await?);
                     }
 
                     _ => unreachable!(),
@@ -106,16 +122,35 @@ async fn main() -> eyre::Result<()> {
 
                                 state
                                     .client
-                                    .subscribe_to_process(&opts.name, &source_code)
+                                    .subscribe_to_process(
+                                        &opts.name,
+                                        &source_code,
+                                        opts.logs,
+                                        opts.restart_policy(),
+                                    )
                                     .await
                             }
                         }?;
 
-                        display_stream(ReadStreaming::Subscription(stream)).await;
+                        display_subscription(stream).await;
+                    }
+
+                    OnlineCommands::Bench(opts) => {
+                        let state = repl_state.online();
+                        bench::bench(&state.client, &opts).await;
+                    }
+
+                    OnlineCommands::Watch(opts) => {
+                        let state = repl_state.online();
+                        watch::watch(&state.client, &opts.stream).await;
                     }
 
                     OnlineCommands::Disconnect => {
-                        repl_state = ReplState::Offline;
+                        if let ReplState::Online(state) =
+                            std::mem::replace(&mut repl_state, ReplState::Offline)
+                        {
+                            state.shutdown().await?;
+                        }
                     }
 
                     OnlineCommands::Process(cmd) => {
@@ -135,9 +170,40 @@ async fn main() -> eyre::Result<()> {
                         }
                     }
 
+                    OnlineCommands::Streams(opts) => {
+                        let state = repl_state.online();
+                        list_streams(&state.client, opts).await;
+                    }
+
+                    OnlineCommands::LogFilters(opts) => {
+                        let state = repl_state.online();
+                        match state.client.set_log_filters(opts.directives).await {
+                            Err(e) => println!("ERR: error when setting log filters: {e}"),
+                            Ok(LogFiltersSet::InvalidDirective(e)) => println!("ERR: {e}"),
+                            Ok(LogFiltersSet::Success) => println!("log filters updated"),
+                        }
+                    }
+
+                    OnlineCommands::Index(opts) => {
+                        let state = repl_state.online();
+                        match state.client.maintain_index(opts.compact).await {
+                            Err(e) => println!("ERR: error when maintaining the index: {e}"),
+                            Ok(result) => println!(
+                                "{} entries flushed, index files: {} -> {}",
+                                result.flushed, result.ss_tables_before, result.ss_tables_after
+                            ),
+                        }
+                    }
+
                     OnlineCommands::Append(opts) => {
                         let state = repl_state.online();
-                        append_stream(&state.client, &opts).await;
+
+                        if opts.no_notify {
+                            let client = state.client.clone().without_subscriber_notifications();
+                            append_stream(&client, &opts).await;
+                        } else {
+                            append_stream(&state.client, &opts).await;
+                        }
                     }
 
                     OnlineCommands::Delete(opts) => {
@@ -145,7 +211,11 @@ async fn main() -> eyre::Result<()> {
 
                         match state
                             .client
-                            .delete_stream(opts.stream.as_str(), ExpectedRevision::Any)
+                            .delete_stream(
+                                opts.stream.as_str(),
+                                ExpectedRevision::Any,
+                                opts.dry_run,
+                            )
                             .await
                         {
                             Err(e) => {
@@ -175,6 +245,13 @@ async fn main() -> eyre::Result<()> {
                                         }
                                     },
 
+                                    DeleteStreamCompleted::DryRun(r) => {
+                                        println!(
+                                            "stream '{}' can be deleted, current revision {}",
+                                            opts.stream, r,
+                                        );
+                                    }
+
                                     DeleteStreamCompleted::Success(p) => {
                                         println!(
                                             "stream '{}' deletion successful, position {}",
@@ -200,6 +277,16 @@ async fn main() -> eyre::Result<()> {
                         append_stream(&state.client, &args).await
                     }
 
+                    MikoshiCommands::Bench(opts) => {
+                        let state = repl_state.mikoshi();
+                        bench::bench(&state.client, &opts).await;
+                    }
+
+                    MikoshiCommands::Streams(opts) => {
+                        let state = repl_state.mikoshi();
+                        list_streams(&state.client, opts).await;
+                    }
+
                     MikoshiCommands::Leave => {
                         if let ReplState::Mikoshi(state) =
                             std::mem::replace(&mut repl_state, ReplState::Offline)
@@ -212,9 +299,63 @@ async fn main() -> eyre::Result<()> {
         }
     }
 
+    match repl_state {
+        ReplState::Online(state) => state.shutdown().await?,
+        ReplState::Mikoshi(state) => state.client.shutdown().await?,
+        ReplState::Offline => {}
+    }
+
     Ok(())
 }
 
+async fn open_mikoshi(directory: PathBuf) -> eyre::Result<MikoshiState> {
+    let directory = expand_path(directory);
+    let options = Options::new(
+        "<repl>".to_string(),
+        0,
+        directory.clone().to_string_lossy().to_string(),
+    )
+    .disable_telemetry()
+    .disable_grpc();
+
+    let client = LocalClient::new(options).await?;
+
+    Ok(MikoshiState { directory, client })
+}
+
+/// Starts an embedded node listening on `127.0.0.1:port` and connects to it.
+async fn serve(directory: PathBuf, port: u16) -> eyre::Result<OnlineState> {
+    let host = "127.0.0.1".to_string();
+
+    // The gRPC server binds in the background, check the port up front so a conflict is
+    // reported here rather than after the node started.
+    if let Err(e) = std::net::TcpListener::bind((host.as_str(), port)) {
+        eyre::bail!("cannot listen on {host}:{port}: {e}");
+    }
+
+    let options = Options::new(host.clone(), port, directory.to_string_lossy().to_string())
+        .disable_telemetry();
+
+    let server = geth_engine::run_embedded(&options).await?;
+
+    let client = match GrpcClient::connect(EndPoint::new(host.clone(), port)).await {
+        Ok(client) => client,
+        Err(e) => {
+            server.shutdown().await?;
+            return Err(e);
+        }
+    };
+
+    println!("serving {directory:?} on {host}:{port}");
+
+    Ok(OnlineState {
+        host,
+        port,
+        client,
+        server: Some(server),
+    })
+}
+
 enum ReplState {
     Offline,
     Online(OnlineState),
@@ -283,6 +424,18 @@ struct OnlineState {
     host: String,
     port: u16,
     client: GrpcClient,
+    /// Node started with the `serve` command, stopped on disconnect.
+    server: Option<EmbeddedClient>,
+}
+
+impl OnlineState {
+    async fn shutdown(self) -> eyre::Result<()> {
+        if let Some(server) = self.server {
+            server.shutdown().await?;
+        }
+
+        Ok(())
+    }
 }
 
 struct MikoshiState {
@@ -307,6 +460,7 @@ fn load_events_from_file(path: impl AsRef<Path>) -> eyre::Result<Vec<Propose>> {
             content_type: geth_common::ContentType::Json,
             class: event.r#type,
             data: serde_json::to_vec(&event.payload)?.into(),
+            metadata: Default::default(),
         });
     }
 
@@ -344,7 +498,9 @@ where
                 AppendError::StreamDeleted => {
                     println!("ERR: stream '{}' has been deleted", opts.stream);
                 }
-                AppendError::WrongExpectedRevision(_) => {
+                AppendError::WrongExpectedRevision(_)
+                | AppendError::InvalidEvent(_)
+                | AppendError::DuplicateEvents => {
                     println!("ERR: {e}");
                 }
             },
@@ -363,6 +519,25 @@ where
     }
 }
 
+async fn list_streams<C>(client: &C, opts: ListStreams)
+where
+    C: Client,
+{
+    match client
+        .list_streams(opts.prefix, opts.offset, opts.limit)
+        .await
+    {
+        Err(e) => println!("ERR: error when listing streams: {e}"),
+        Ok(page) => {
+            for stream in &page.streams {
+                println!("{stream}");
+            }
+
+            println!("{} of {} stream(s) listed", page.streams.len(), page.total);
+        }
+    }
+}
+
 async fn read_stream<C>(client: &C, opts: &ReadStream)
 where
     C: Client + 'static,
@@ -372,7 +547,8 @@ where
             opts.stream.as_str(),
             Direction::Forward,
             Revision::Start,
-            50,
+            Some(50),
+            true,
         )
         .await;
 
@@ -399,24 +575,42 @@ async fn display_stream(mut stream: ReadStreaming) {
                 break;
             }
 
-            Ok(Some(record)) => {
-                let data = serde_json::from_slice::<serde_json::Value>(&record.data).unwrap();
-                let record = serde_json::json!({
-                    "stream_name": record.stream_name,
-                    "id": record.id,
-                    "revision": record.revision,
-                    "position": record.position,
-                    "data": data,
-                });
-
-                println!("{}", serde_json::to_string_pretty(&record).unwrap());
-            }
+            Ok(Some(record)) => print_record(record),
 
             _ => break,
         }
     }
 }
 
+async fn display_subscription(mut stream: SubscriptionStreaming) {
+    loop {
+        match stream.next().await {
+            Err(e) => {
+                println!("ERR: error when reading subscription: {e}");
+                break;
+            }
+
+            Ok(Some(SubscriptionEvent::EventAppeared(record))) => print_record(record),
+            Ok(Some(SubscriptionEvent::ProgramLog { line })) => println!("log: {line}"),
+            Ok(Some(SubscriptionEvent::Unsubscribed(_))) | Ok(None) => break,
+            Ok(Some(_)) => continue,
+        }
+    }
+}
+
+fn print_record(record: Record) {
+    let data = serde_json::from_slice::<serde_json::Value>(&record.data).unwrap();
+    let record = serde_json::json!({
+        "stream_name": record.stream_name,
+        "id": record.id,
+        "revision": record.revision,
+        "position": record.position,
+        "data": data,
+    });
+
+    println!("{}", serde_json::to_string_pretty(&record).unwrap());
+}
+
 async fn list_programmable_subscriptions(state: &mut OnlineState) {
     let summaries = match state.client.list_programs().await {
         Err(e) => {
@@ -491,6 +685,12 @@ async fn get_programmable_subscription_stats(state: &mut OnlineState, id: String
         "started": stats.started,
         "subscriptions": stats.subscriptions,
         "pushed_events": stats.pushed_events,
+        "restarts": stats.restarts,
+        "limits": {
+            "max_events_per_sec": stats.limits.max_events_per_sec,
+            "max_subscriptions": stats.limits.max_subscriptions,
+            "max_output_buffer": stats.limits.max_output_buffer,
+        },
     });
 
     println!("{}", serde_json::to_string_pretty(&js).unwrap());
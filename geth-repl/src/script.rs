@@ -0,0 +1,393 @@
+use std::fs;
+
+use clap::Parser;
+use geth_client::{Client, GrpcClient};
+use geth_common::{
+    AppendStreamCompleted, DeleteStreamCompleted, Direction, EndPoint, ExpectedRevision,
+    LogFiltersSet, ReadStreamCompleted, Record, Revision,
+};
+use serde_json::{json, Value};
+
+use crate::cli::{
+    AppendStream, Args, DeleteStream, ListStreams, Mikoshi, MikoshiCommands, Offline,
+    OfflineCommands, Online, OnlineCommands, ProcessCommands, ReadStream,
+};
+use crate::utils::expand_path;
+use crate::{load_events_from_file, open_mikoshi, serve, OnlineState, ReplState};
+
+/// Runs commands non-interactively, as passed with `--exec` or read from a `--script` file.
+///
+/// Each command outputs a single line JSON envelope: `{"status":"ok","command":...,"data":...}`
+/// on stdout when it succeeds, `{"status":"error","command":...,"error":...}` on stderr when it
+/// fails. Execution stops at the first failure and the process exits with a non-zero code.
+pub async fn run(args: Args) -> eyre::Result<bool> {
+    let mut lines = args.exec;
+
+    if let Some(path) = args.script {
+        let script = fs::read_to_string(&path)
+            .map_err(|e| eyre::eyre!("error when reading script {:?}: {}", path, e))?;
+
+        lines.extend(script.lines().map(str::to_string));
+    }
+
+    let mut state = ReplState::Offline;
+    let mut success = true;
+
+    for line in lines {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        match execute(&mut state, line).await {
+            Ok(Step::Exit) => break,
+
+            Ok(Step::Continue(data)) => {
+                println!(
+                    "{}",
+                    json!({ "status": "ok", "command": line, "data": data })
+                );
+            }
+
+            Err(e) => {
+                eprintln!(
+                    "{}",
+                    json!({ "status": "error", "command": line, "error": e.to_string() })
+                );
+
+                success = false;
+                break;
+            }
+        }
+    }
+
+    match state {
+        ReplState::Online(state) => state.shutdown().await?,
+        ReplState::Mikoshi(state) => state.client.shutdown().await?,
+        ReplState::Offline => {}
+    }
+
+    Ok(success)
+}
+
+enum Step {
+    Continue(Value),
+    Exit,
+}
+
+async fn execute(state: &mut ReplState, line: &str) -> eyre::Result<Step> {
+    // clap expects the binary name first.
+    let args = std::iter::once("geth-repl".to_string()).chain(split_args(line)?);
+
+    match state {
+        ReplState::Offline => {
+            let cmd = Offline::try_parse_from(args)?;
+
+            match cmd.command {
+                OfflineCommands::Connect { host, port } => {
+                    let host = host.unwrap_or_else(|| "localhost".to_string());
+                    let port = port.unwrap_or(2_113);
+                    let client = GrpcClient::connect(EndPoint::new(host.clone(), port)).await?;
+
+                    *state = ReplState::Online(OnlineState {
+                        host: host.clone(),
+                        port,
+                        client,
+                        server: None,
+                    });
+
+                    Ok(Step::Continue(json!({ "host": host, "port": port })))
+                }
+
+                OfflineCommands::Serve { directory, port } => {
+                    let directory = expand_path(directory);
+                    let online = serve(directory.clone(), port.unwrap_or(2_113)).await?;
+                    let data = json!({
+                        "directory": directory,
+                        "host": online.host,
+                        "port": online.port,
+                    });
+
+                    *state = ReplState::Online(online);
+
+                    Ok(Step::Continue(data))
+                }
+
+                OfflineCommands::Mikoshi { directory } => {
+                    let mikoshi = open_mikoshi(directory).await?;
+                    let data = json!({ "directory": mikoshi.directory });
+
+                    *state = ReplState::Mikoshi(mikoshi);
+
+                    Ok(Step::Continue(data))
+                }
+
+                OfflineCommands::Exit => Ok(Step::Exit),
+            }
+        }
+
+        ReplState::Online(online) => {
+            let cmd = Online::try_parse_from(args)?;
+
+            match cmd.command {
+                OnlineCommands::Read(opts) => read_stream(&online.client, &opts).await,
+                OnlineCommands::Append(opts) if opts.no_notify => {
+                    let client = online.client.clone().without_subscriber_notifications();
+                    append_stream(&client, &opts).await
+                }
+
+                OnlineCommands::Append(opts) => append_stream(&online.client, &opts).await,
+                OnlineCommands::Delete(opts) => delete_stream(&online.client, &opts).await,
+
+                OnlineCommands::Process(cmd) => match cmd.commands {
+                    ProcessCommands::List => {
+                        let programs = online
+                            .client
+                            .list_programs()
+                            .await?
+                            .into_iter()
+                            .map(|p| {
+                                json!({ "id": p.id, "name": p.name, "started_at": p.started_at })
+                            })
+                            .collect::<Vec<_>>();
+
+                        Ok(Step::Continue(Value::Array(programs)))
+                    }
+
+                    ProcessCommands::Stats { id } => {
+                        let id = parse_program_id(&id)?;
+                        let Some(stats) = online.client.get_program(id).await? else {
+                            eyre::bail!("programmable subscription with id {id} does not exist");
+                        };
+
+                        Ok(Step::Continue(json!({
+                            "id": stats.id,
+                            "name": stats.name,
+                            "started": stats.started,
+                            "subscriptions": stats.subscriptions,
+                            "pushed_events": stats.pushed_events,
+                            "restarts": stats.restarts,
+                            "limits": {
+                                "max_events_per_sec": stats.limits.max_events_per_sec,
+                                "max_subscriptions": stats.limits.max_subscriptions,
+                                "max_output_buffer": stats.limits.max_output_buffer,
+                            },
+                            "source_code": stats.source_code,
+                        })))
+                    }
+
+                    ProcessCommands::Kill { id } => {
+                        online.client.stop_program(parse_program_id(&id)?).await?;
+
+                        Ok(Step::Continue(Value::Null))
+                    }
+                },
+
+                OnlineCommands::Streams(opts) => list_streams(&online.client, opts).await,
+
+                OnlineCommands::LogFilters(opts) => {
+                    match online.client.set_log_filters(opts.directives).await? {
+                        LogFiltersSet::Success => Ok(Step::Continue(Value::Null)),
+                        LogFiltersSet::InvalidDirective(e) => eyre::bail!(e),
+                    }
+                }
+
+                OnlineCommands::Index(opts) => {
+                    let result = online.client.maintain_index(opts.compact).await?;
+
+                    Ok(Step::Continue(json!({
+                        "flushed": result.flushed,
+                        "ss_tables_before": result.ss_tables_before,
+                        "ss_tables_after": result.ss_tables_after,
+                    })))
+                }
+
+                OnlineCommands::Subscribe(_)
+                | OnlineCommands::Watch(_)
+                | OnlineCommands::Bench(_) => {
+                    eyre::bail!("command is only available in interactive mode")
+                }
+
+                OnlineCommands::Disconnect => {
+                    if let ReplState::Online(online) = std::mem::replace(state, ReplState::Offline)
+                    {
+                        online.shutdown().await?;
+                    }
+
+                    Ok(Step::Continue(Value::Null))
+                }
+
+                OnlineCommands::Exit => Ok(Step::Exit),
+            }
+        }
+
+        ReplState::Mikoshi(mikoshi) => {
+            let cmd = Mikoshi::try_parse_from(args)?;
+
+            match cmd.commands {
+                MikoshiCommands::Read(opts) => read_stream(&mikoshi.client, &opts).await,
+                MikoshiCommands::Append(opts) => append_stream(&mikoshi.client, &opts).await,
+
+                MikoshiCommands::Streams(opts) => list_streams(&mikoshi.client, opts).await,
+
+                MikoshiCommands::Bench(_) => {
+                    eyre::bail!("command is only available in interactive mode")
+                }
+
+                MikoshiCommands::Leave => {
+                    if let ReplState::Mikoshi(mikoshi) =
+                        std::mem::replace(state, ReplState::Offline)
+                    {
+                        mikoshi.client.shutdown().await?;
+                    }
+
+                    Ok(Step::Continue(Value::Null))
+                }
+            }
+        }
+    }
+}
+
+async fn read_stream<C>(client: &C, opts: &ReadStream) -> eyre::Result<Step>
+where
+    C: Client,
+{
+    let mut stream = match client
+        .read_stream(
+            &opts.stream,
+            Direction::Forward,
+            Revision::Start,
+            None,
+            true,
+        )
+        .await?
+    {
+        ReadStreamCompleted::StreamDeleted => eyre::bail!("stream '{}' is deleted", opts.stream),
+        ReadStreamCompleted::Success(stream) => stream,
+    };
+
+    let mut records = vec![];
+    while let Some(record) = stream.next().await? {
+        records.push(record_to_json(record));
+    }
+
+    Ok(Step::Continue(json!({
+        "events": records,
+        "tail_revision": stream.tail_revision(),
+    })))
+}
+
+async fn list_streams<C>(client: &C, opts: ListStreams) -> eyre::Result<Step>
+where
+    C: Client,
+{
+    let page = client
+        .list_streams(opts.prefix, opts.offset, opts.limit)
+        .await?;
+
+    Ok(Step::Continue(json!({
+        "streams": page.streams,
+        "total": page.total,
+    })))
+}
+
+async fn append_stream<C>(client: &C, opts: &AppendStream) -> eyre::Result<Step>
+where
+    C: Client,
+{
+    let proposes = load_events_from_file(&opts.json)
+        .map_err(|e| eyre::eyre!("error when loading events from file {:?}: {}", opts.json, e))?;
+
+    match client
+        .append_stream(&opts.stream, ExpectedRevision::Any, proposes)
+        .await?
+    {
+        AppendStreamCompleted::Error(e) => eyre::bail!(e),
+        AppendStreamCompleted::Success(result) => Ok(Step::Continue(json!({
+            "position": result.position,
+            "next_expected_version": result.next_expected_version.raw(),
+            "next_logical_position": result.next_logical_position,
+            "created": result.created,
+        }))),
+    }
+}
+
+async fn delete_stream<C>(client: &C, opts: &DeleteStream) -> eyre::Result<Step>
+where
+    C: Client,
+{
+    match client
+        .delete_stream(&opts.stream, ExpectedRevision::Any, opts.dry_run)
+        .await?
+    {
+        DeleteStreamCompleted::Error(e) => eyre::bail!(e),
+        DeleteStreamCompleted::DryRun(current) => Ok(Step::Continue(json!({
+            "dry_run": true,
+            "current_revision": current.raw(),
+        }))),
+        DeleteStreamCompleted::Success(result) => Ok(Step::Continue(json!({
+            "position": result.position,
+            "created": result.created,
+        }))),
+    }
+}
+
+fn record_to_json(record: Record) -> Value {
+    let data = serde_json::from_slice::<Value>(&record.data).unwrap_or(Value::Null);
+
+    json!({
+        "stream_name": record.stream_name,
+        "id": record.id,
+        "class": record.class,
+        "revision": record.revision,
+        "position": record.position,
+        "created": record.created,
+        "data": data,
+    })
+}
+
+fn parse_program_id(id: &str) -> eyre::Result<u64> {
+    id.parse::<u64>().map_err(|e| {
+        eyre::eyre!("provided programmable subscription id is not a valid unsigned integer: {e}")
+    })
+}
+
+/// Splits a command line into arguments, honoring single and double quotes.
+fn split_args(line: &str) -> eyre::Result<Vec<String>> {
+    let mut args = vec![];
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut quote = None;
+
+    for c in line.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => current.push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                in_arg = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            (None, c) => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+
+    if quote.is_some() {
+        eyre::bail!("unterminated quote in '{line}'");
+    }
+
+    if in_arg {
+        args.push(current);
+    }
+
+    Ok(args)
+}
@@ -0,0 +1,257 @@
+use std::io::{self, BufRead, Write};
+use std::time::{Duration, Instant};
+
+use geth_client::{Client, SubscriptionStreaming};
+use geth_common::{Record, Revision, SubscriptionEvent};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+
+const COMPACT_PAYLOAD_LEN: usize = 80;
+
+enum Key {
+    ToggleFull,
+    Quit,
+}
+
+struct WatchView {
+    stream_name: String,
+    full: bool,
+    count: u64,
+    count_at_last_tick: u64,
+    rate: u64,
+    last_class: Option<String>,
+    last_revision: Option<u64>,
+    disconnected: bool,
+}
+
+impl WatchView {
+    fn new(stream_name: &str) -> Self {
+        Self {
+            stream_name: stream_name.to_string(),
+            full: false,
+            count: 0,
+            count_at_last_tick: 0,
+            rate: 0,
+            last_class: None,
+            last_revision: None,
+            disconnected: false,
+        }
+    }
+
+    /// Where to subscribe from so no event is displayed twice after a reconnection.
+    fn resume_from(&self) -> Revision<u64> {
+        self.last_revision
+            .map_or(Revision::Start, |r| Revision::Revision(r + 1))
+    }
+
+    fn tick(&mut self) {
+        self.rate = self.count - self.count_at_last_tick;
+        self.count_at_last_tick = self.count;
+    }
+
+    fn event_appeared(&mut self, record: &Record) {
+        self.count += 1;
+        self.last_class = Some(record.class.clone());
+        self.last_revision = Some(record.revision);
+
+        clear_line();
+
+        let data = serde_json::from_slice::<serde_json::Value>(&record.data);
+
+        if self.full {
+            let record = serde_json::json!({
+                "stream_name": record.stream_name,
+                "id": record.id,
+                "class": record.class,
+                "revision": record.revision,
+                "position": record.position,
+                "created": record.created,
+                "data": data.unwrap_or(serde_json::Value::Null),
+            });
+
+            println!("{}", serde_json::to_string_pretty(&record).unwrap());
+        } else {
+            let mut payload = match data {
+                Ok(value) => value.to_string(),
+                Err(_) => format!("<{} bytes>", record.data.len()),
+            };
+
+            if payload.len() > COMPACT_PAYLOAD_LEN {
+                let mut end = COMPACT_PAYLOAD_LEN;
+                while !payload.is_char_boundary(end) {
+                    end -= 1;
+                }
+
+                payload.truncate(end);
+                payload.push('…');
+            }
+
+            println!("#{} {} {}", record.revision, record.class, payload);
+        }
+    }
+
+    fn render_status(&self) {
+        clear_line();
+
+        print!(
+            "[{}] {} event(s), {}/s, last class: {}, last revision: {}{}",
+            self.stream_name,
+            self.count,
+            self.rate,
+            self.last_class.as_deref().unwrap_or("-"),
+            self.last_revision
+                .map_or_else(|| "-".to_string(), |r| r.to_string()),
+            if self.disconnected {
+                " (reconnecting...)"
+            } else {
+                ""
+            },
+        );
+
+        let _ = io::stdout().flush();
+    }
+}
+
+/// Tails a stream live, until `q` is entered. Resubscribes after the last displayed event when
+/// the subscription drops.
+pub async fn watch<C>(client: &C, stream_name: &str)
+where
+    C: Client,
+{
+    let (keys_sender, mut keys) = unbounded_channel();
+    // The thread exits once it reads `q`, which is also the only way out of the loop below so
+    // no stray line read is left competing with the REPL input.
+    std::thread::spawn(move || read_keys(keys_sender));
+
+    println!(
+        "watching '{stream_name}', enter 'f' to toggle full payloads and 'q' to stop watching"
+    );
+
+    let mut view = WatchView::new(stream_name);
+    let mut ticker = tokio::time::interval(Duration::from_secs(1));
+    let mut subscription = None;
+    let mut last_attempt = None::<Instant>;
+
+    loop {
+        if subscription.is_none() && last_attempt.is_none_or(|t| t.elapsed() >= ticker.period()) {
+            last_attempt = Some(Instant::now());
+
+            match subscribe(client, stream_name, view.resume_from()).await {
+                Ok(stream) => {
+                    subscription = Some(stream);
+                    view.disconnected = false;
+                }
+
+                Err(e) => {
+                    if !view.disconnected {
+                        clear_line();
+                        println!("ERR: error when subscribing to '{stream_name}': {e}");
+                        view.disconnected = true;
+                    }
+                }
+            }
+
+            view.render_status();
+        }
+
+        tokio::select! {
+            key = keys.recv() => match key {
+                Some(Key::ToggleFull) => {
+                    view.full = !view.full;
+                    view.render_status();
+                }
+
+                Some(Key::Quit) | None => break,
+            },
+
+            _ = ticker.tick() => {
+                view.tick();
+                view.render_status();
+            }
+
+            event = next_event(&mut subscription) => {
+                match event {
+                    Ok(Some(SubscriptionEvent::EventAppeared(record))) => {
+                        view.event_appeared(&record);
+                    }
+
+                    Ok(Some(SubscriptionEvent::Unsubscribed(reason))) => {
+                        clear_line();
+                        println!("unsubscribed: {reason:?}");
+                        subscription = None;
+                        view.disconnected = true;
+                    }
+
+                    Ok(Some(_)) => {}
+
+                    Ok(None) => {
+                        subscription = None;
+                        view.disconnected = true;
+                    }
+
+                    Err(e) => {
+                        clear_line();
+                        println!("ERR: subscription to '{stream_name}' dropped: {e}");
+                        subscription = None;
+                        view.disconnected = true;
+                    }
+                }
+
+                view.render_status();
+            }
+        }
+    }
+
+    println!();
+}
+
+async fn subscribe<C>(
+    client: &C,
+    stream_name: &str,
+    start: Revision<u64>,
+) -> eyre::Result<SubscriptionStreaming>
+where
+    C: Client,
+{
+    let mut stream = client.subscribe_to_stream(stream_name, start).await?;
+    stream.wait_until_confirmed().await?;
+
+    Ok(stream)
+}
+
+async fn next_event(
+    subscription: &mut Option<SubscriptionStreaming>,
+) -> eyre::Result<Option<SubscriptionEvent>> {
+    match subscription {
+        Some(stream) => stream.next().await,
+        None => std::future::pending().await,
+    }
+}
+
+fn read_keys(sender: UnboundedSender<Key>) {
+    let stdin = io::stdin();
+
+    for line in stdin.lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+
+        match line.trim() {
+            "f" => {
+                if sender.send(Key::ToggleFull).is_err() {
+                    break;
+                }
+            }
+
+            "q" => {
+                let _ = sender.send(Key::Quit);
+                break;
+            }
+
+            _ => {}
+        }
+    }
+}
+
+fn clear_line() {
+    print!("\r\x1b[2K");
+}